@@ -5,12 +5,11 @@ use ledger_api::v2::{
     state_service_client::StateServiceClient, CreatedEvent, EventFormat,
     GetActiveContractsRequest,
 };
-use std::collections::HashMap;
 use std::pin::Pin;
 use tonic::metadata::MetadataValue;
 use tracing::{debug, info};
 
-use crate::utils::build_filters_by_party;
+use crate::utils::event_format_for_parties;
 
 /// Represents an active contract from the ACS snapshot.
 #[derive(Debug, Clone)]
@@ -33,10 +32,28 @@ pub async fn stream_active_contracts(
     active_at_offset: i64,
     parties: Vec<String>,
     url: String,
+) -> Result<Pin<Box<dyn Stream<Item = Result<ActiveContract>> + Send>>> {
+    stream_active_contracts_with_event_format(
+        access_token,
+        active_at_offset,
+        event_format_for_parties(&parties, &[]),
+        url,
+    )
+    .await
+}
+
+/// Like `stream_active_contracts`, with the event format selecting the created events
+pub async fn stream_active_contracts_with_event_format(
+    access_token: Option<&str>,
+    active_at_offset: i64,
+    event_format: EventFormat,
+    url: String,
 ) -> Result<Pin<Box<dyn Stream<Item = Result<ActiveContract>> + Send>>> {
     info!(
         "Starting stream_active_contracts: url={}, parties={:?}, active_at_offset={}",
-        url, parties, active_at_offset
+        url,
+        event_format.filters_by_party.keys().collect::<Vec<_>>(),
+        active_at_offset
     );
 
     debug!("Connecting to state service at {}", url);
@@ -45,14 +62,7 @@ pub async fn stream_active_contracts(
         .with_context(|| format!("Failed to connect to state service at {}", url))?
         .max_decoding_message_size(64 * 1024 * 1024);
 
-    let filters_by_party: HashMap<String, ledger_api::v2::Filters> = build_filters_by_party(&parties);
-    debug!("Built filters_by_party: {:?}", filters_by_party);
-
-    let event_format = EventFormat {
-        filters_by_party,
-        filters_for_any_party: None,
-        verbose: true,
-    };
+    debug!("Event format: {:?}", event_format);
 
     let request = GetActiveContractsRequest {
         filter: None,
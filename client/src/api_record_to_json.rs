@@ -12,7 +12,7 @@ pub fn api_record_to_json(record: &Record) -> serde_json::Value {
     serde_json::Value::Object(fields_json)
 }
 
-fn api_value_to_json(value: &Value) -> serde_json::Value {
+pub fn api_value_to_json(value: &Value) -> serde_json::Value {
     match &value.sum {
         Some(ledger_api::com::daml::ledger::api::v2::value::Sum::Text(s)) => json!(s),
         Some(ledger_api::com::daml::ledger::api::v2::value::Sum::Int64(i)) => json!(i),
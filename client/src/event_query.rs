@@ -0,0 +1,164 @@
+use anyhow::Result;
+use ledger_api::v2::{
+    ArchivedEvent, CreatedEvent, EventFormat, ExercisedEvent, GetEventsByContractIdRequest,
+    GetUpdateByOffsetRequest, Transaction, TransactionFormat, TransactionShape, UpdateFormat,
+    event, event_query_service_client::EventQueryServiceClient,
+    get_update_response, update_service_client::UpdateServiceClient,
+};
+use tonic::Request;
+use tonic::metadata::MetadataValue;
+use tracing::debug;
+
+use crate::utils::build_filters_by_party;
+
+/// The history of a contract as seen by the requesting parties
+#[derive(Debug, Clone)]
+pub struct ContractEvents {
+    pub created: Option<CreatedEvent>,
+    pub archived: Option<ArchivedEvent>,
+    /// The consuming exercise which archived the contract, if it is archived and visible
+    pub archiving_exercise: Option<ExercisedEvent>,
+    pub synchronizer_id: String,
+}
+
+impl ContractEvents {
+    pub fn is_active(&self) -> bool {
+        self.created.is_some() && self.archived.is_none()
+    }
+}
+
+fn event_format(parties: &[String]) -> EventFormat {
+    EventFormat {
+        filters_by_party: build_filters_by_party(parties),
+        filters_for_any_party: None,
+        verbose: true,
+    }
+}
+
+/// Looks up the create and archive events of a contract, and the exercise which archived it.
+///
+/// # Arguments
+/// * `url` - The gRPC endpoint URL of the ledger API
+/// * `access_token` - Optional bearer token for authentication
+/// * `contract_id` - The contract to look up
+/// * `parties` - The requesting parties
+///
+/// # Returns
+/// The events of the contract; fails with NOT_FOUND if none of the parties can see it
+pub async fn get_events_by_contract_id(
+    url: String,
+    access_token: Option<&str>,
+    contract_id: String,
+    parties: Vec<String>,
+) -> Result<ContractEvents> {
+    let mut client = EventQueryServiceClient::connect(url.clone()).await?;
+    let request = GetEventsByContractIdRequest {
+        contract_id: contract_id.clone(),
+        requesting_parties: vec![],
+        event_format: Some(event_format(&parties)),
+    };
+    let mut req = Request::new(request);
+    if let Some(token) = access_token {
+        let meta = MetadataValue::try_from(format!("Bearer {}", token))?;
+        req.metadata_mut().insert("authorization", meta);
+    }
+    let response = client.get_events_by_contract_id(req).await?.into_inner();
+
+    let (created, created_synchronizer_id) = match response.created {
+        Some(created) => (created.created_event, created.synchronizer_id),
+        None => (None, String::new()),
+    };
+    let (archived, synchronizer_id) = match response.archived {
+        Some(archived) => (archived.archived_event, archived.synchronizer_id),
+        None => (None, created_synchronizer_id),
+    };
+
+    let archiving_exercise = match &archived {
+        Some(archived) => {
+            debug!("Contract {} archived at offset {}, fetching the transaction", contract_id, archived.offset);
+            get_archiving_exercise(url, access_token, archived, parties).await?
+        }
+        None => None,
+    };
+
+    Ok(ContractEvents {
+        created,
+        archived,
+        archiving_exercise,
+        synchronizer_id,
+    })
+}
+
+/// Fetches the transaction of an archive event and returns the consuming exercise on the contract.
+pub async fn get_archiving_exercise(
+    url: String,
+    access_token: Option<&str>,
+    archived: &ArchivedEvent,
+    parties: Vec<String>,
+) -> Result<Option<ExercisedEvent>> {
+    let mut client = UpdateServiceClient::connect(url).await?;
+    let request = GetUpdateByOffsetRequest {
+        offset: archived.offset,
+        update_format: Some(UpdateFormat {
+            include_transactions: Some(TransactionFormat {
+                event_format: Some(event_format(&parties)),
+                transaction_shape: TransactionShape::LedgerEffects as i32,
+            }),
+            include_reassignments: None,
+            include_topology_events: None,
+        }),
+    };
+    let mut req = Request::new(request);
+    if let Some(token) = access_token {
+        let meta = MetadataValue::try_from(format!("Bearer {}", token))?;
+        req.metadata_mut().insert("authorization", meta);
+    }
+    let response = client.get_update_by_offset(req).await?.into_inner();
+
+    match response.update {
+        Some(get_update_response::Update::Transaction(tx)) => {
+            Ok(find_consuming_exercise(&tx, &archived.contract_id, archived.node_id))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Returns the consuming exercise on `contract_id` at `node_id` in a transaction.
+pub fn find_consuming_exercise(tx: &Transaction, contract_id: &str, node_id: i32) -> Option<ExercisedEvent> {
+    tx.events.iter().find_map(|e| match &e.event {
+        Some(event::Event::Exercised(exercised))
+            if exercised.consuming && exercised.contract_id == contract_id && exercised.node_id == node_id =>
+        {
+            Some(exercised.clone())
+        }
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ledger_api::v2::Event;
+
+    #[test]
+    fn test_find_consuming_exercise() {
+        let exercise = |node_id: i32, consuming: bool, choice: &str| Event {
+            event: Some(event::Event::Exercised(ExercisedEvent {
+                node_id,
+                contract_id: "cid-1".to_string(),
+                consuming,
+                choice: choice.to_string(),
+                ..Default::default()
+            })),
+        };
+        let tx = Transaction {
+            events: vec![exercise(0, false, "Inspect"), exercise(1, true, "Give")],
+            ..Default::default()
+        };
+
+        let found = find_consuming_exercise(&tx, "cid-1", 1).expect("Exercise should be found");
+        assert_eq!(found.choice, "Give");
+        assert!(find_consuming_exercise(&tx, "cid-1", 0).is_none());
+        assert!(find_consuming_exercise(&tx, "cid-2", 1).is_none());
+    }
+}
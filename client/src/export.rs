@@ -0,0 +1,252 @@
+use anyhow::{Context, Result};
+use futures_util::StreamExt;
+use ledger_api::v2::{Identifier, Transaction, event};
+use ledger_api::v2::get_updates_response::Update;
+use std::io::Write;
+use std::path::Path;
+use tracing::{debug, info};
+
+use crate::api_record_to_json::{api_record_to_json, api_value_to_json};
+use crate::stream_updates::stream_updates;
+
+/// Column headers of the exported CSV, in the order of the `ExportRow` fields
+pub const EXPORT_HEADERS: [&str; 11] = [
+    "offset",
+    "update_id",
+    "effective_at",
+    "node_id",
+    "event_type",
+    "contract_id",
+    "template_id",
+    "choice",
+    "consuming",
+    "parties",
+    "payload",
+];
+
+/// One flattened created or exercised event of a transaction
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExportRow {
+    pub offset: i64,
+    pub update_id: String,
+    pub effective_at: String,
+    pub node_id: i32,
+    /// `created` or `exercised`
+    pub event_type: &'static str,
+    pub contract_id: String,
+    /// `<package id>:<module>:<entity>`
+    pub template_id: String,
+    /// Empty for created events
+    pub choice: String,
+    pub consuming: bool,
+    /// Signatories of created events, acting parties of exercised events, separated by `;`
+    pub parties: String,
+    /// Create arguments of created events, choice argument of exercised events, as JSON
+    pub payload: String,
+}
+
+impl ExportRow {
+    fn cells(&self) -> Vec<String> {
+        vec![
+            self.offset.to_string(),
+            self.update_id.clone(),
+            self.effective_at.clone(),
+            self.node_id.to_string(),
+            self.event_type.to_string(),
+            self.contract_id.clone(),
+            self.template_id.clone(),
+            self.choice.clone(),
+            self.consuming.to_string(),
+            self.parties.clone(),
+            self.payload.clone(),
+        ]
+    }
+}
+
+fn identifier_to_string(id: &Option<Identifier>) -> String {
+    id.as_ref()
+        .map(|id| format!("{}:{}:{}", id.package_id, id.module_name, id.entity_name))
+        .unwrap_or_default()
+}
+
+/// Flattens the created and exercised events of a transaction into export rows.
+pub fn transaction_to_rows(tx: &Transaction) -> Vec<ExportRow> {
+    let effective_at = tx
+        .effective_at
+        .as_ref()
+        .and_then(|ts| chrono::DateTime::from_timestamp(ts.seconds, ts.nanos as u32))
+        .map(|dt| dt.to_rfc3339())
+        .unwrap_or_default();
+
+    tx.events
+        .iter()
+        .filter_map(|e| e.event.as_ref())
+        .filter_map(|e| match e {
+            event::Event::Created(created) => Some(ExportRow {
+                offset: tx.offset,
+                update_id: tx.update_id.clone(),
+                effective_at: effective_at.clone(),
+                node_id: created.node_id,
+                event_type: "created",
+                contract_id: created.contract_id.clone(),
+                template_id: identifier_to_string(&created.template_id),
+                choice: String::new(),
+                consuming: false,
+                parties: created.signatories.join(";"),
+                payload: created
+                    .create_arguments
+                    .as_ref()
+                    .map(|args| api_record_to_json(args).to_string())
+                    .unwrap_or_default(),
+            }),
+            event::Event::Exercised(exercised) => Some(ExportRow {
+                offset: tx.offset,
+                update_id: tx.update_id.clone(),
+                effective_at: effective_at.clone(),
+                node_id: exercised.node_id,
+                event_type: "exercised",
+                contract_id: exercised.contract_id.clone(),
+                template_id: identifier_to_string(&exercised.template_id),
+                choice: exercised.choice.clone(),
+                consuming: exercised.consuming,
+                parties: exercised.acting_parties.join(";"),
+                payload: exercised
+                    .choice_argument
+                    .as_ref()
+                    .map(|arg| api_value_to_json(arg).to_string())
+                    .unwrap_or_default(),
+            }),
+            event::Event::Archived(_) => None,
+        })
+        .collect()
+}
+
+/// Quotes a CSV cell if it contains a separator, quote or line break (RFC 4180).
+fn csv_escape(cell: &str) -> String {
+    if cell.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", cell.replace('"', "\"\""))
+    } else {
+        cell.to_string()
+    }
+}
+
+/// Writes one CSV line.
+pub fn write_csv_line<W: Write>(writer: &mut W, cells: &[String]) -> Result<()> {
+    let line = cells.iter().map(|c| csv_escape(c)).collect::<Vec<String>>().join(",");
+    writeln!(writer, "{}", line)?;
+    Ok(())
+}
+
+/// Streams the transactions of a party over an offset range and writes their created and
+/// exercised events to a CSV file, one row per event.
+///
+/// # Arguments
+/// * `url` - The gRPC endpoint URL of the ledger API
+/// * `access_token` - Optional bearer token for authentication
+/// * `party` - The party whose transactions to export
+/// * `begin_exclusive` - The offset after which to start exporting
+/// * `end_inclusive` - The last offset to export
+/// * `output` - The path of the CSV file to write
+///
+/// # Returns
+/// The number of rows written
+pub async fn export_transactions_to_csv(
+    url: String,
+    access_token: Option<&str>,
+    party: String,
+    begin_exclusive: i64,
+    end_inclusive: i64,
+    output: &Path,
+) -> Result<usize> {
+    let file = std::fs::File::create(output)
+        .with_context(|| format!("Failed to create {}", output.display()))?;
+    let mut writer = std::io::BufWriter::new(file);
+    write_csv_line(&mut writer, &EXPORT_HEADERS.map(String::from))?;
+
+    let mut stream = stream_updates(
+        access_token,
+        begin_exclusive,
+        Some(end_inclusive),
+        vec![party],
+        url,
+    )
+    .await?;
+
+    let mut row_count = 0;
+    while let Some(response) = stream.next().await {
+        if let Some(Update::Transaction(tx)) = response?.update {
+            for row in transaction_to_rows(&tx) {
+                write_csv_line(&mut writer, &row.cells())?;
+                row_count += 1;
+            }
+            debug!("Exported transaction at offset {}, {} rows so far", tx.offset, row_count);
+        }
+    }
+    writer.flush()?;
+
+    info!("Exported {} rows to {}", row_count, output.display());
+    Ok(row_count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ledger_api::v2::{CreatedEvent, Event, ExercisedEvent, Record, RecordField, Value, value::Sum};
+
+    #[test]
+    fn test_transaction_to_rows_and_csv() {
+        let template_id = Some(Identifier {
+            package_id: "pkg".to_string(),
+            module_name: "Main".to_string(),
+            entity_name: "Asset".to_string(),
+        });
+        let tx = Transaction {
+            update_id: "update-1".to_string(),
+            offset: 7,
+            events: vec![
+                Event {
+                    event: Some(event::Event::Created(CreatedEvent {
+                        node_id: 0,
+                        contract_id: "cid-1".to_string(),
+                        template_id: template_id.clone(),
+                        signatories: vec!["Alice::1220".to_string()],
+                        create_arguments: Some(Record {
+                            record_id: None,
+                            fields: vec![RecordField {
+                                label: "name".to_string(),
+                                value: Some(Value { sum: Some(Sum::Text("TV, large".to_string())) }),
+                            }],
+                        }),
+                        ..Default::default()
+                    })),
+                },
+                Event {
+                    event: Some(event::Event::Exercised(ExercisedEvent {
+                        node_id: 1,
+                        contract_id: "cid-1".to_string(),
+                        template_id,
+                        choice: "Give".to_string(),
+                        consuming: true,
+                        acting_parties: vec!["Alice::1220".to_string(), "Bob::1220".to_string()],
+                        ..Default::default()
+                    })),
+                },
+            ],
+            ..Default::default()
+        };
+
+        let rows = transaction_to_rows(&tx);
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].event_type, "created");
+        assert_eq!(rows[0].template_id, "pkg:Main:Asset");
+        assert_eq!(rows[1].choice, "Give");
+        assert_eq!(rows[1].parties, "Alice::1220;Bob::1220");
+
+        let mut out = Vec::new();
+        write_csv_line(&mut out, &rows[0].cells()).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "7,update-1,,0,created,cid-1,pkg:Main:Asset,,false,Alice::1220,\"{\"\"name\"\":\"\"TV, large\"\"}\"\n"
+        );
+    }
+}
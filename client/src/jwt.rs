@@ -5,6 +5,7 @@ use anyhow::{Result, Context};
 use serde::Deserialize;
 use tracing::{debug, info, warn};
 use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
 use tokio::sync::RwLock;
 use std::time::{Duration as StdDuration, Instant};
 
@@ -87,6 +88,133 @@ pub fn fake_jwt_for_user(
     format!("{}.{}", header_enc, payload_enc)
 }
 
+/// Key material used to sign a JWT
+#[derive(Clone)]
+pub enum JwtSigningKey {
+    /// Unsigned token (alg "none"), accepted by Sandbox without JWT validation
+    Unsigned,
+    /// HMAC SHA-256 with a shared secret
+    Hs256(Vec<u8>),
+    /// RSA PKCS#1 v1.5 SHA-256 with a private key in DER encoding (PKCS#8 or PKCS#1)
+    Rs256(Vec<u8>),
+}
+
+impl JwtSigningKey {
+    /// Reads an HMAC secret from a file, without the trailing newline of e.g. `echo`, which
+    /// would otherwise be part of the key and fail the verification of the token.
+    pub fn hs256_from_file(path: &std::path::Path) -> Result<Self> {
+        let mut secret = std::fs::read(path)
+            .with_context(|| format!("Failed to read secret file {}", path.display()))?;
+        while secret.last().is_some_and(|byte| *byte == b'\n' || *byte == b'\r') {
+            secret.pop();
+        }
+        Ok(JwtSigningKey::Hs256(secret))
+    }
+
+    /// Reads an RSA private key from a PEM file (`BEGIN PRIVATE KEY` or `BEGIN RSA PRIVATE KEY`).
+    pub fn rs256_from_pem_file(path: &std::path::Path) -> Result<Self> {
+        let pem = std::fs::read(path)
+            .with_context(|| format!("Failed to read key file {}", path.display()))?;
+        let key = rustls_pemfile::private_key(&mut pem.as_slice())
+            .with_context(|| format!("Failed to parse PEM key file {}", path.display()))?
+            .ok_or_else(|| anyhow::anyhow!("No private key found in {}", path.display()))?;
+        Ok(JwtSigningKey::Rs256(key.secret_der().to_vec()))
+    }
+
+    fn alg(&self) -> &'static str {
+        match self {
+            JwtSigningKey::Unsigned => "none",
+            JwtSigningKey::Hs256(_) => "HS256",
+            JwtSigningKey::Rs256(_) => "RS256",
+        }
+    }
+
+    fn sign(&self, message: &[u8]) -> Result<Vec<u8>> {
+        match self {
+            JwtSigningKey::Unsigned => Ok(Vec::new()),
+            JwtSigningKey::Hs256(secret) => {
+                let key = ring::hmac::Key::new(ring::hmac::HMAC_SHA256, secret);
+                Ok(ring::hmac::sign(&key, message).as_ref().to_vec())
+            }
+            JwtSigningKey::Rs256(der) => {
+                let key_pair = ring::signature::RsaKeyPair::from_pkcs8(der)
+                    .or_else(|_| ring::signature::RsaKeyPair::from_der(der))
+                    .map_err(|e| anyhow::anyhow!("Invalid RSA private key: {}", e))?;
+                let mut signature = vec![0u8; key_pair.public().modulus_len()];
+                key_pair
+                    .sign(
+                        &ring::signature::RSA_PKCS1_SHA256,
+                        &ring::rand::SystemRandom::new(),
+                        message,
+                        &mut signature,
+                    )
+                    .map_err(|e| anyhow::anyhow!("Failed to sign JWT: {}", e))?;
+                Ok(signature)
+            }
+        }
+    }
+}
+
+/// Claims of a Ledger API access token minted by `signed_jwt`
+#[derive(Debug, Clone)]
+pub struct JwtClaims {
+    pub user_id: String,
+    pub audience: String,
+    pub scope: String,
+    pub issuer: Option<String>,
+    pub expires_in: Duration,
+}
+
+impl JwtClaims {
+    /// Claims with the same defaults as the fake Sandbox tokens, valid for 24 hours.
+    pub fn for_user(user_id: &str) -> Self {
+        Self {
+            user_id: user_id.to_string(),
+            audience: "someParticipantId".to_string(),
+            scope: "daml_ledger_api".to_string(),
+            issuer: Some("someIdpId".to_string()),
+            expires_in: Duration::hours(24),
+        }
+    }
+}
+
+/// Mints a JWT for the given claims, signed with the given key.
+/// With `JwtSigningKey::Unsigned` the token has an empty signature, like `fake_jwt`.
+///
+/// # Arguments
+/// * `claims` - The user id, audience, scope, issuer and lifetime of the token
+/// * `key` - The signing key
+///
+/// # Returns
+/// * `Result<String>` - The encoded token
+pub fn signed_jwt(claims: &JwtClaims, key: &JwtSigningKey) -> Result<String> {
+    let header = json!({
+        "alg": key.alg(),
+        "typ": "JWT"
+    });
+
+    let mut payload = json!({
+        "aud": claims.audience,
+        "sub": claims.user_id,
+        "scope": claims.scope,
+        "exp": (Utc::now() + claims.expires_in).timestamp()
+    });
+    if let Some(issuer) = &claims.issuer {
+        payload["iss"] = json!(issuer);
+    }
+
+    let header_enc = general_purpose::URL_SAFE_NO_PAD.encode(header.to_string());
+    let payload_enc = general_purpose::URL_SAFE_NO_PAD.encode(payload.to_string());
+    let signing_input = format!("{}.{}", header_enc, payload_enc);
+    let signature = key.sign(signing_input.as_bytes())?;
+
+    Ok(format!(
+        "{}.{}",
+        signing_input,
+        general_purpose::URL_SAFE_NO_PAD.encode(signature)
+    ))
+}
+
 /// Authentication method for Keycloak
 #[derive(Debug, Clone, Deserialize)]
 #[serde(tag = "grant_type", rename_all = "snake_case")]
@@ -356,6 +484,8 @@ pub struct TokenManager {
     state: Arc<RwLock<Option<TokenState>>>,
     /// Renewal threshold as a fraction (0.8 = renew when 80% of lifetime elapsed)
     renewal_threshold: f64,
+    /// Number of tokens obtained from the source
+    refreshes: AtomicU64,
 }
 
 impl TokenManager {
@@ -365,6 +495,7 @@ impl TokenManager {
             source,
             state: Arc::new(RwLock::new(None)),
             renewal_threshold: 0.8,
+            refreshes: AtomicU64::new(0),
         }
     }
 
@@ -375,6 +506,7 @@ impl TokenManager {
             source,
             state: Arc::new(RwLock::new(None)),
             renewal_threshold: threshold.clamp(0.1, 0.95),
+            refreshes: AtomicU64::new(0),
         }
     }
 
@@ -448,10 +580,16 @@ impl TokenManager {
             expires_in_secs,
         });
 
+        self.refreshes.fetch_add(1, Ordering::Relaxed);
         info!("JWT token refreshed (expires in {} seconds)", expires_in_secs);
         Ok(token)
     }
 
+    /// The number of tokens obtained from the source so far, the first one included
+    pub fn refresh_count(&self) -> u64 {
+        self.refreshes.load(Ordering::Relaxed)
+    }
+
     /// Returns the time until the next refresh is needed, if known.
     pub async fn time_until_refresh(&self) -> Option<StdDuration> {
         let state = self.state.read().await;
@@ -489,4 +627,45 @@ impl TokenManager {
             }
         })
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signed_jwt_hs256() {
+        let secret = b"secret".to_vec();
+        let token = signed_jwt(&JwtClaims::for_user("alice"), &JwtSigningKey::Hs256(secret.clone()))
+            .expect("Failed to sign token");
+
+        let parts: Vec<&str> = token.split('.').collect();
+        assert_eq!(parts.len(), 3);
+
+        let header: serde_json::Value = serde_json::from_slice(
+            &general_purpose::URL_SAFE_NO_PAD.decode(parts[0]).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(header["alg"], "HS256");
+
+        let payload: serde_json::Value = serde_json::from_slice(
+            &general_purpose::URL_SAFE_NO_PAD.decode(parts[1]).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(payload["sub"], "alice");
+        assert_eq!(payload["scope"], "daml_ledger_api");
+
+        let key = ring::hmac::Key::new(ring::hmac::HMAC_SHA256, &secret);
+        let signature = general_purpose::URL_SAFE_NO_PAD.decode(parts[2]).unwrap();
+        ring::hmac::verify(&key, format!("{}.{}", parts[0], parts[1]).as_bytes(), &signature)
+            .expect("Signature should verify");
+    }
+
+    #[test]
+    fn test_hs256_secret_file_without_trailing_newline() {
+        let path = std::env::temp_dir().join(format!("rct-secret-{}", std::process::id()));
+        std::fs::write(&path, "secret\r\n").unwrap();
+        let key = JwtSigningKey::hs256_from_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(key, JwtSigningKey::Hs256(secret) if secret == b"secret"));
+    }
+}
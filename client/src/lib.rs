@@ -1,4 +1,5 @@
 pub mod ledger_end;
+pub mod version;
 pub mod stream_updates;
 pub mod utils;
 pub mod jwt;
@@ -9,4 +10,9 @@ pub mod run_script;
 pub mod party_management;
 pub mod user_management;
 pub mod get_blob;
-pub mod active_contracts;
\ No newline at end of file
+pub mod active_contracts;
+pub mod output;
+pub mod api_record_to_json;
+pub mod export;
+pub mod event_query;
+pub mod resumable_stream;
\ No newline at end of file
@@ -1,7 +1,12 @@
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
+use std::path::PathBuf;
 
-use client::jwt::fake_jwt;
+use client::event_query::get_events_by_contract_id;
+use client::export::export_transactions_to_csv;
+use client::jwt::{JwtClaims, JwtSigningKey, get_user_for_party, signed_jwt};
 use client::ledger_end::get_ledger_end;
+use client::output::{OutputFormat, print_listing};
+use client::resumable_stream::{ReconnectConfig, ResumableUpdate, follow_updates};
 use client::stream_updates::stream_updates;
 
 use futures_util::StreamExt;
@@ -17,6 +22,14 @@ struct Cli {
     command: Commands,
 }
 
+/// Signing algorithm of the `token` subcommand
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum TokenAlg {
+    None,
+    Hs256,
+    Rs256,
+}
+
 #[derive(Subcommand, Debug)]
 enum Commands {
     /// Get the ledger end
@@ -26,12 +39,36 @@ enum Commands {
         #[arg(long)]
         access_token: String,
     },
-    /// Create fake access token for Sandbox
-    FakeAccessToken {
+    /// Create an access token, unsigned for Sandbox or signed for participants with JWT validation
+    #[command(alias = "fake-access-token")]
+    Token {
+        /// Ledger API URL, used to look up the user whose primary party is --party
+        #[arg(long, required_unless_present = "user")]
+        url: Option<String>,
+        #[arg(long, required_unless_present = "user", conflicts_with = "user")]
+        party: Option<String>,
+        /// User id to put in the token subject, instead of looking it up by party
         #[arg(long)]
-        url: String,
+        user: Option<String>,
+        #[arg(long, value_enum, default_value_t = TokenAlg::None)]
+        alg: TokenAlg,
+        /// HS256 shared secret
+        #[arg(long, conflicts_with = "secret_file")]
+        secret: Option<String>,
+        /// File containing the HS256 shared secret
         #[arg(long)]
-        party: String,
+        secret_file: Option<PathBuf>,
+        /// PEM file containing the RS256 private key
+        #[arg(long)]
+        key_file: Option<PathBuf>,
+        #[arg(long, default_value = "someParticipantId")]
+        audience: String,
+        #[arg(long, default_value = "daml_ledger_api")]
+        scope: String,
+        #[arg(long, default_value = "someIdpId")]
+        issuer: String,
+        #[arg(long, default_value_t = 86400)]
+        expires_in_secs: i64,
     },
     /// Stream ledger updates for a party
     StreamUpdates {
@@ -41,10 +78,13 @@ enum Commands {
         access_token: String,
         #[arg(long)]
         party: String,
-        #[arg(long)]
-        begin_exclusive: i64,
-        #[arg(long)]
+        #[arg(long, required_unless_present = "follow", conflicts_with = "follow")]
+        begin_exclusive: Option<i64>,
+        #[arg(long, conflicts_with = "follow")]
         end_inclusive: Option<i64>,
+        /// Start at the current ledger end and keep streaming across disconnects
+        #[arg(long)]
+        follow: bool,
     },
     /// Stream transactions for a party
     StreamTransactions {
@@ -67,6 +107,67 @@ enum Commands {
         access_token: String,
         #[arg(long)]
         filter: Option<String>,
+        #[arg(long)]
+        identity_provider_id: Option<String>,
+        #[arg(long, default_value_t = 100)]
+        page_size: i32,
+        #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+        format: OutputFormat,
+    },
+    /// Export the created and exercised events of a party's transactions to CSV
+    Export {
+        #[arg(long)]
+        url: String,
+        #[arg(long)]
+        access_token: String,
+        #[arg(long)]
+        party: String,
+        #[arg(long)]
+        begin_exclusive: i64,
+        /// Defaults to the current ledger end
+        #[arg(long)]
+        end_inclusive: Option<i64>,
+        #[arg(long)]
+        output: PathBuf,
+    },
+    /// Show the create event, status and archiving exercise of a contract
+    Contract {
+        #[arg(long)]
+        url: String,
+        #[arg(long)]
+        access_token: String,
+        #[arg(long)]
+        id: String,
+        #[arg(long)]
+        party: String,
+    },
+    /// Inspect DAR files
+    Dar {
+        #[command(subcommand)]
+        command: DarCommands,
+    },
+    /// List users, optionally filtered by identity provider
+    Users {
+        #[arg(long)]
+        url: String,
+        #[arg(long)]
+        access_token: String,
+        #[arg(long)]
+        identity_provider_id: Option<String>,
+        #[arg(long, default_value_t = 100)]
+        page_size: i32,
+        #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+        format: OutputFormat,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum DarCommands {
+    /// Print the main package id, name, version, modules, templates and choices of a DAR
+    Inspect {
+        path: PathBuf,
+        #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+        format: OutputFormat,
     },
 }
 
@@ -83,13 +184,52 @@ async fn main() -> Result<()> {
             info!("Ledger end: {}", ledger_end);
             Ok(())
         }
-        Commands::FakeAccessToken { url, party } => {
-            let channel = tonic::transport::Channel::from_shared(url)?
-                .connect()
-                .await?;
-            let mut user_management_client = UserManagementServiceClient::new(channel);
-            let token = fake_jwt(&mut user_management_client, &party).await?;
-            info!("Fake access token: {}", token);
+        Commands::Token {
+            url,
+            party,
+            user,
+            alg,
+            secret,
+            secret_file,
+            key_file,
+            audience,
+            scope,
+            issuer,
+            expires_in_secs,
+        } => {
+            let user_id = match (user, party, url) {
+                (Some(user), _, _) => user,
+                (None, Some(party), Some(url)) => {
+                    let channel = tonic::transport::Channel::from_shared(url)?
+                        .connect()
+                        .await?;
+                    let mut user_management_client = UserManagementServiceClient::new(channel);
+                    get_user_for_party(&mut user_management_client, &party)
+                        .await?
+                        .ok_or_else(|| anyhow::anyhow!("No user found with primary party {}", party))?
+                }
+                _ => anyhow::bail!("Either --user, or --party together with --url is required"),
+            };
+            let key = match alg {
+                TokenAlg::None => JwtSigningKey::Unsigned,
+                TokenAlg::Hs256 => match (secret, secret_file) {
+                    (Some(secret), _) => JwtSigningKey::Hs256(secret.into_bytes()),
+                    (None, Some(path)) => JwtSigningKey::hs256_from_file(&path)?,
+                    (None, None) => anyhow::bail!("--alg hs256 requires --secret or --secret-file"),
+                },
+                TokenAlg::Rs256 => match key_file {
+                    Some(path) => JwtSigningKey::rs256_from_pem_file(&path)?,
+                    None => anyhow::bail!("--alg rs256 requires --key-file"),
+                },
+            };
+            let claims = JwtClaims {
+                user_id,
+                audience,
+                scope,
+                issuer: Some(issuer),
+                expires_in: chrono::Duration::seconds(expires_in_secs),
+            };
+            println!("{}", signed_jwt(&claims, &key)?);
             Ok(())
         }
         Commands::StreamUpdates {
@@ -98,7 +238,30 @@ async fn main() -> Result<()> {
             url,
             begin_exclusive,
             end_inclusive,
+            follow,
         } => {
+            if follow {
+                info!("StreamUpdates following the ledger end, party: {:?}, url: {}", party, url);
+                let mut stream = follow_updates(
+                    Some(access_token),
+                    vec![party],
+                    url,
+                    ReconnectConfig::default(),
+                )
+                .await?;
+                while let Some(item) = stream.next().await {
+                    match item {
+                        ResumableUpdate::Update(update) => info!("{:#?}", update),
+                        ResumableUpdate::Reconnecting { last_offset, reason, retry_in } => info!(
+                            "Stream interrupted ({}), reconnecting in {:?} and continuing after offset {}",
+                            reason, retry_in, last_offset
+                        ),
+                    }
+                }
+                return Ok(());
+            }
+            // clap guarantees begin_exclusive is present unless --follow is set
+            let begin_exclusive = begin_exclusive.unwrap_or_default();
             info!(
                 "StreamUpdates called with begin_exclusive: {}, end_inclusive: {:?}, party: {:?}, url: {}",
                 begin_exclusive, end_inclusive, party, url
@@ -148,15 +311,193 @@ async fn main() -> Result<()> {
             }
             Ok(())
         }
-        Commands::Parties { filter, url, access_token } => {
-            let parties = client::party_management::get_parties::get_parties(url, Some(&access_token), filter).await?;
-            if parties.is_empty() {
-                info!("No parties found.");
-            } else {
-                info!("Known parties: {:?}", parties);
+        Commands::Parties { filter, url, access_token, identity_provider_id, page_size, format } => {
+            let parties = client::party_management::get_parties::list_party_details(
+                url,
+                Some(&access_token),
+                identity_provider_id,
+                page_size,
+            )
+            .await?
+            .into_iter()
+            .filter(|p| filter.as_ref().is_none_or(|f| p.party.contains(f)))
+            .collect::<Vec<_>>();
+            let rows = parties
+                .iter()
+                .map(|p| vec![p.party.clone(), p.is_local.to_string(), p.identity_provider_id.clone()])
+                .collect::<Vec<_>>();
+            let json = serde_json::Value::Array(
+                parties
+                    .iter()
+                    .map(|p| {
+                        serde_json::json!({
+                            "party": p.party,
+                            "is_local": p.is_local,
+                            "identity_provider_id": p.identity_provider_id,
+                            "annotations": p.local_metadata.as_ref().map(|m| m.annotations.clone()).unwrap_or_default(),
+                        })
+                    })
+                    .collect(),
+            );
+            print_listing(format, &["PARTY", "LOCAL", "IDENTITY PROVIDER"], &rows, &json);
+            Ok(())
+        }
+        Commands::Export { url, access_token, party, begin_exclusive, end_inclusive, output } => {
+            let end_inclusive = match end_inclusive {
+                Some(end) => end,
+                None => get_ledger_end(&url, Some(&access_token)).await?,
+            };
+            let rows = export_transactions_to_csv(
+                url,
+                Some(&access_token),
+                party,
+                begin_exclusive,
+                end_inclusive,
+                &output,
+            )
+            .await?;
+            info!("Exported {} events to {}", rows, output.display());
+            Ok(())
+        }
+        Commands::Contract { url, access_token, id, party } => {
+            let events = get_events_by_contract_id(url, Some(&access_token), id.clone(), vec![party]).await?;
+            println!("Contract: {}", id);
+            println!("Status: {}", if events.is_active() { "active" } else { "archived" });
+            println!("Synchronizer: {}", events.synchronizer_id);
+            if let Some(created) = &events.created {
+                let template_id = created
+                    .template_id
+                    .as_ref()
+                    .map(|t| format!("{}:{}:{}", t.package_id, t.module_name, t.entity_name))
+                    .unwrap_or_default();
+                println!("Created at offset {} (node {})", created.offset, created.node_id);
+                println!("Template: {}", template_id);
+                println!("Signatories: {}", created.signatories.join(", "));
+                println!("Observers: {}", created.observers.join(", "));
+                let payload = created
+                    .create_arguments
+                    .as_ref()
+                    .map(client::api_record_to_json::api_record_to_json)
+                    .unwrap_or_default();
+                println!("Payload: {}", serde_json::to_string_pretty(&payload)?);
+            }
+            if let Some(archived) = &events.archived {
+                println!("Archived at offset {} (node {})", archived.offset, archived.node_id);
             }
+            if let Some(exercise) = &events.archiving_exercise {
+                println!("Archived by choice: {}", exercise.choice);
+                println!("Acting parties: {}", exercise.acting_parties.join(", "));
+                let argument = exercise
+                    .choice_argument
+                    .as_ref()
+                    .map(client::api_record_to_json::api_value_to_json)
+                    .unwrap_or_default();
+                println!("Choice argument: {}", serde_json::to_string_pretty(&argument)?);
+            }
+            Ok(())
+        }
+        Commands::Dar { command: DarCommands::Inspect { path, format } } => {
+            let dar_path = path
+                .to_str()
+                .ok_or_else(|| anyhow::anyhow!("DAR path is not valid UTF-8: {:?}", path))?;
+            let dar_info = codegen::dar_inspect::inspect_dar(dar_path)?;
+            match format {
+                OutputFormat::Json => {
+                    let modules = dar_info
+                        .modules
+                        .iter()
+                        .map(|m| {
+                            let templates = |ts: &[codegen::dar_inspect::TemplateInfo]| {
+                                ts.iter()
+                                    .map(|t| {
+                                        serde_json::json!({
+                                            "name": t.name,
+                                            "choices": t.choices.iter().map(|c| serde_json::json!({
+                                                "name": c.name,
+                                                "consuming": c.consuming,
+                                            })).collect::<Vec<_>>(),
+                                        })
+                                    })
+                                    .collect::<Vec<_>>()
+                            };
+                            serde_json::json!({
+                                "name": m.name,
+                                "templates": templates(&m.templates),
+                                "interfaces": templates(&m.interfaces),
+                            })
+                        })
+                        .collect::<Vec<_>>();
+                    println!(
+                        "{}",
+                        serde_json::json!({
+                            "main_package_id": dar_info.main_package_id,
+                            "package_name": dar_info.package_name,
+                            "package_version": dar_info.package_version,
+                            "modules": modules,
+                        })
+                    );
+                }
+                OutputFormat::Table => {
+                    println!("Main package id: {}", dar_info.main_package_id);
+                    println!("Package: {} {}", dar_info.package_name, dar_info.package_version);
+                    let rows = dar_info
+                        .modules
+                        .iter()
+                        .flat_map(|m| {
+                            let kinds = m.templates.iter().map(|t| ("template", t))
+                                .chain(m.interfaces.iter().map(|i| ("interface", i)));
+                            kinds.map(|(kind, t)| {
+                                let choices = t
+                                    .choices
+                                    .iter()
+                                    .map(|c| if c.consuming { c.name.clone() } else { format!("{} (nonconsuming)", c.name) })
+                                    .collect::<Vec<_>>()
+                                    .join(", ");
+                                vec![m.name.clone(), t.name.clone(), kind.to_string(), choices]
+                            })
+                            .collect::<Vec<_>>()
+                        })
+                        .collect::<Vec<_>>();
+                    println!("{}", client::output::render_table(&["MODULE", "NAME", "KIND", "CHOICES"], &rows));
+                }
+            }
+            Ok(())
+        }
+        Commands::Users { url, access_token, identity_provider_id, page_size, format } => {
+            let users = client::user_management::list_users::list_users_with_page_size(
+                url,
+                Some(&access_token),
+                identity_provider_id,
+                page_size,
+            )
+            .await?;
+            let rows = users
+                .iter()
+                .map(|u| {
+                    vec![
+                        u.id.clone(),
+                        u.primary_party.clone(),
+                        u.is_deactivated.to_string(),
+                        u.identity_provider_id.clone(),
+                    ]
+                })
+                .collect::<Vec<_>>();
+            let json = serde_json::Value::Array(
+                users
+                    .iter()
+                    .map(|u| {
+                        serde_json::json!({
+                            "id": u.id,
+                            "primary_party": u.primary_party,
+                            "is_deactivated": u.is_deactivated,
+                            "identity_provider_id": u.identity_provider_id,
+                            "annotations": u.metadata.as_ref().map(|m| m.annotations.clone()).unwrap_or_default(),
+                        })
+                    })
+                    .collect(),
+            );
+            print_listing(format, &["ID", "PRIMARY PARTY", "DEACTIVATED", "IDENTITY PROVIDER"], &rows, &json);
             Ok(())
         }
-        
     }
 }
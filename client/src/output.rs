@@ -0,0 +1,84 @@
+use clap::ValueEnum;
+use serde_json::Value;
+
+/// Output format of CLI subcommands producing listings.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// One JSON array on stdout, suitable for scripting (e.g. piping into `jq`)
+    Json,
+    /// Human readable, column aligned table
+    #[default]
+    Table,
+}
+
+/// Renders rows as a column aligned plain text table with a header line.
+///
+/// # Arguments
+/// * `headers` - The column headers
+/// * `rows` - The table rows, each with as many cells as there are headers
+///
+/// # Returns
+/// The rendered table, one line per row, without a trailing newline
+pub fn render_table(headers: &[&str], rows: &[Vec<String>]) -> String {
+    let mut widths: Vec<usize> = headers.iter().map(|h| h.len()).collect();
+    for row in rows {
+        for (i, cell) in row.iter().enumerate() {
+            if i < widths.len() {
+                widths[i] = widths[i].max(cell.chars().count());
+            }
+        }
+    }
+
+    let render_line = |cells: Vec<&str>| {
+        cells
+            .iter()
+            .zip(widths.iter())
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect::<Vec<String>>()
+            .join("  ")
+            .trim_end()
+            .to_string()
+    };
+
+    let mut lines = Vec::with_capacity(rows.len() + 2);
+    lines.push(render_line(headers.to_vec()));
+    let separators: Vec<String> = widths.iter().map(|w| "-".repeat(*w)).collect();
+    lines.push(render_line(separators.iter().map(|s| s.as_str()).collect()));
+    for row in rows {
+        lines.push(render_line(row.iter().map(|s| s.as_str()).collect()));
+    }
+    lines.join("\n")
+}
+
+/// Prints a listing to stdout in the requested format.
+///
+/// # Arguments
+/// * `format` - The output format
+/// * `headers` - The table column headers, used by the table format only
+/// * `rows` - The table rows, used by the table format only
+/// * `json` - The JSON representation of the listing, used by the JSON format only
+pub fn print_listing(format: OutputFormat, headers: &[&str], rows: &[Vec<String>], json: &Value) {
+    match format {
+        OutputFormat::Json => println!("{}", json),
+        OutputFormat::Table => println!("{}", render_table(headers, rows)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_table_aligns_columns() {
+        let rows = vec![
+            vec!["alice-user".to_string(), "Alice::1220".to_string()],
+            vec!["bob".to_string(), "".to_string()],
+        ];
+        let table = render_table(&["ID", "PRIMARY PARTY"], &rows);
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines[0], "ID          PRIMARY PARTY");
+        assert_eq!(lines[1], "----------  -------------");
+        assert_eq!(lines[2], "alice-user  Alice::1220");
+        assert_eq!(lines[3], "bob");
+    }
+}
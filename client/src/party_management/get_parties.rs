@@ -1,5 +1,5 @@
 use ledger_api::v2::admin::{
-    ListKnownPartiesRequest, party_management_service_client::PartyManagementServiceClient,
+    ListKnownPartiesRequest, PartyDetails, party_management_service_client::PartyManagementServiceClient,
 };
 use tonic::Request;
 use tonic::metadata::MetadataValue;
@@ -10,21 +10,8 @@ pub async fn get_parties(
     access_token: Option<&str>,
     filter: Option<String>,
 ) -> Result<Vec<String>> {
-    let mut client = PartyManagementServiceClient::connect(url).await?;
-    let request = ListKnownPartiesRequest {
-        page_token: "".to_string(),
-        page_size: 0,
-        identity_provider_id: "".to_string(),
-    };
-    let mut req = Request::new(request);
-    if let Some(token) = access_token {
-        let meta = MetadataValue::try_from(format!("Bearer {}", token))?;
-        req.metadata_mut().insert("authorization", meta);
-    }
-    let response = client.list_known_parties(req).await?;
-    let parties = response
-        .into_inner()
-        .party_details
+    let parties = list_party_details(url, access_token, None, 0)
+        .await?
         .into_iter()
         .map(|party_detail| party_detail.party)
         .filter(|party| {
@@ -38,4 +25,47 @@ pub async fn get_parties(
     Ok(parties)
 }
 
+/// Lists the details of all parties known to the participant node, following pagination.
+///
+/// # Arguments
+/// * `url` - The gRPC endpoint URL of the participant node
+/// * `access_token` - Optional bearer token for authentication
+/// * `identity_provider_id` - Optional identity provider ID filter
+/// * `page_size` - Number of parties requested per page, 0 means the server default
+///
+/// # Returns
+/// The details of all known parties
+pub async fn list_party_details(
+    url: String,
+    access_token: Option<&str>,
+    identity_provider_id: Option<String>,
+    page_size: i32,
+) -> Result<Vec<PartyDetails>> {
+    let mut client = PartyManagementServiceClient::connect(url).await?;
+    let mut all_parties = Vec::new();
+    let mut page_token = String::new();
+
+    loop {
+        let request = ListKnownPartiesRequest {
+            page_token: page_token.clone(),
+            page_size,
+            identity_provider_id: identity_provider_id.clone().unwrap_or_default(),
+        };
+        let mut req = Request::new(request);
+        if let Some(token) = access_token {
+            let meta = MetadataValue::try_from(format!("Bearer {}", token))?;
+            req.metadata_mut().insert("authorization", meta);
+        }
+        let response = client.list_known_parties(req).await?;
+        let inner = response.into_inner();
+
+        all_parties.extend(inner.party_details);
 
+        if inner.next_page_token.is_empty() {
+            break;
+        }
+        page_token = inner.next_page_token;
+    }
+
+    Ok(all_parties)
+}
@@ -0,0 +1,164 @@
+use anyhow::Result;
+use async_stream::stream;
+use futures::Stream;
+use ledger_api::v2::GetUpdatesResponse;
+use ledger_api::v2::get_updates_response::Update;
+use std::pin::Pin;
+use std::time::Duration;
+use tokio_stream::StreamExt;
+use tracing::{debug, warn};
+
+use crate::stream_updates::stream_updates;
+
+/// Reconnection backoff configuration of the resumable update stream
+#[derive(Debug, Clone)]
+pub struct ReconnectConfig {
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+    pub multiplier: f64,
+}
+
+impl Default for ReconnectConfig {
+    fn default() -> Self {
+        Self {
+            initial_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(60),
+            multiplier: 2.0,
+        }
+    }
+}
+
+/// Item of the resumable update stream
+#[derive(Debug)]
+pub enum ResumableUpdate {
+    /// An update received from the ledger
+    Update(Box<GetUpdatesResponse>),
+    /// The stream was interrupted and will be reopened after `retry_in`, continuing after `last_offset`
+    Reconnecting {
+        last_offset: i64,
+        reason: String,
+        retry_in: Duration,
+    },
+}
+
+/// Returns the offset of an update, if the response carries one.
+pub fn update_offset(response: &GetUpdatesResponse) -> Option<i64> {
+    response.update.as_ref().map(|u| match u {
+        Update::Transaction(tx) => tx.offset,
+        Update::Reassignment(r) => r.offset,
+        Update::OffsetCheckpoint(c) => c.offset,
+        Update::TopologyTransaction(t) => t.offset,
+    })
+}
+
+/// Streams ledger updates for the given parties, starting after `begin_exclusive`, and keeps the
+/// stream alive across disconnects.
+///
+/// Whenever the underlying gRPC stream fails or is closed by the server, a
+/// `ResumableUpdate::Reconnecting` item is yielded and the stream is reopened after the last
+/// offset seen, with exponential backoff between failed attempts. The stream never ends on its own.
+///
+/// # Arguments
+/// * `access_token` - Optional bearer token for authentication
+/// * `begin_exclusive` - The offset after which to start streaming
+/// * `parties` - The parties whose updates to stream
+/// * `url` - The gRPC endpoint URL of the ledger API
+/// * `reconnect_config` - The backoff applied between reconnection attempts
+pub fn resumable_stream_updates(
+    access_token: Option<String>,
+    begin_exclusive: i64,
+    parties: Vec<String>,
+    url: String,
+    reconnect_config: ReconnectConfig,
+) -> Pin<Box<dyn Stream<Item = ResumableUpdate> + Send>> {
+    let output_stream = stream! {
+        let mut last_offset = begin_exclusive;
+        let mut current_delay = reconnect_config.initial_delay;
+
+        loop {
+            let reason = match stream_updates(
+                access_token.as_deref(),
+                last_offset,
+                None,
+                parties.clone(),
+                url.clone(),
+            ).await {
+                Ok(mut updates) => {
+                    let mut received_any = false;
+                    let mut reason = "stream closed by server".to_string();
+                    while let Some(update) = updates.next().await {
+                        match update {
+                            Ok(response) => {
+                                if let Some(offset) = update_offset(&response) {
+                                    last_offset = offset;
+                                }
+                                received_any = true;
+                                yield ResumableUpdate::Update(Box::new(response));
+                            }
+                            Err(e) => {
+                                reason = format!("stream error: {}", e);
+                                break;
+                            }
+                        }
+                    }
+                    if received_any {
+                        current_delay = reconnect_config.initial_delay;
+                    }
+                    reason
+                }
+                Err(e) => format!("failed to open stream: {}", e),
+            };
+
+            warn!("Update stream interrupted after offset {}: {}", last_offset, reason);
+            yield ResumableUpdate::Reconnecting {
+                last_offset,
+                reason,
+                retry_in: current_delay,
+            };
+            tokio::time::sleep(current_delay).await;
+            debug!("Reopening update stream after offset {}", last_offset);
+            current_delay = std::cmp::min(
+                Duration::from_secs_f64(current_delay.as_secs_f64() * reconnect_config.multiplier),
+                reconnect_config.max_delay,
+            );
+        }
+    };
+
+    Box::pin(output_stream)
+}
+
+/// Follows the ledger like `tail -f`: streams updates from the current ledger end onwards,
+/// reconnecting on disconnects.
+///
+/// # Arguments
+/// * `access_token` - Optional bearer token for authentication
+/// * `parties` - The parties whose updates to stream
+/// * `url` - The gRPC endpoint URL of the ledger API
+/// * `reconnect_config` - The backoff applied between reconnection attempts
+pub async fn follow_updates(
+    access_token: Option<String>,
+    parties: Vec<String>,
+    url: String,
+    reconnect_config: ReconnectConfig,
+) -> Result<Pin<Box<dyn Stream<Item = ResumableUpdate> + Send>>> {
+    let ledger_end = crate::ledger_end::get_ledger_end(&url, access_token.as_deref()).await?;
+    Ok(resumable_stream_updates(access_token, ledger_end, parties, url, reconnect_config))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ledger_api::v2::OffsetCheckpoint;
+
+    #[test]
+    fn test_update_offset() {
+        let checkpoint = GetUpdatesResponse {
+            update: Some(Update::OffsetCheckpoint(OffsetCheckpoint {
+                offset: 42,
+                ..Default::default()
+            })),
+        };
+        assert_eq!(update_offset(&checkpoint), Some(42));
+        assert_eq!(update_offset(&GetUpdatesResponse { update: None }), None);
+    }
+}
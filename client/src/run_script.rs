@@ -128,7 +128,7 @@ pub fn run_script_in_dir(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::testutils::start_sandbox;
+    use crate::testutils::{allocate_ports, start_sandbox_with_ports};
     use std::path::PathBuf;
 
     #[tokio::test]
@@ -146,19 +146,22 @@ mod tests {
         let main_dar = main_dir.join(".daml").join("dist").join("daml-ticketoffer-explicit-disclosure-0.0.1.dar");
         let test_dar = test_dir.join(".daml").join("dist").join("daml-ticketoffer-explicit-disclosure-test-0.0.1.dar");
 
-        let sandbox_port = 6865;
-        let _guard = start_sandbox(main_dir, main_dar, sandbox_port)
+        let ports = allocate_ports().expect("Failed to allocate ports");
+        let sandbox_port = ports.ledger_api;
+        let guard = start_sandbox_with_ports(main_dir, main_dar, ports)
             .await
             .expect("Failed to start sandbox");
 
-        let result = run_script(
-            "localhost",
-            sandbox_port,
-            &test_dar,
-            "Setup:setup",
-        )?;
-
-        info!("Script result: {}", result);
-        Ok(())
+        guard.run(async {
+            let result = run_script(
+                "localhost",
+                sandbox_port,
+                &test_dar,
+                "Setup:setup",
+            )?;
+
+            info!("Script result: {}", result);
+            Ok(())
+        }).await
     }
 }
@@ -2,7 +2,7 @@ use ledger_api::v2::{
     EventFormat, GetUpdatesRequest, TransactionFormat, TransactionShape, UpdateFormat,
     update_service_client::UpdateServiceClient,
 };
-use crate::utils::build_filters_by_party;
+use crate::utils::event_format_for_parties;
 use tonic::metadata::MetadataValue;
 use anyhow::{Context, Result};
 use tracing::{info, debug, error};
@@ -15,15 +15,33 @@ pub async fn stream_updates(
     end_inclusive: Option<i64>,
     parties: Vec<String>,
     url: String,
+) -> Result<tonic::Streaming<ledger_api::v2::GetUpdatesResponse>> {
+    stream_updates_with_event_format(
+        access_token,
+        begin_exclusive,
+        end_inclusive,
+        event_format_for_parties(&parties, &[]),
+        url,
+    )
+    .await
+}
+
+/// Like `stream_updates`, with the event format selecting the events of the transactions
+pub async fn stream_updates_with_event_format(
+    access_token: Option<&str>,
+    begin_exclusive: i64,
+    end_inclusive: Option<i64>,
+    event_format: EventFormat,
+    url: String,
 ) -> Result<tonic::Streaming<ledger_api::v2::GetUpdatesResponse>> {
     info!(
-        "Starting stream_updates at {}:{}: url={}, begin_exclusive={}, end_inclusive={:?}, parties={:?}",
+        "Starting stream_updates at {}:{}: url={}, begin_exclusive={}, end_inclusive={:?}, filters_by_party={:?}",
         file!(),
         line!(),
         url,
         begin_exclusive,
         end_inclusive,
-        parties
+        event_format.filters_by_party.keys().collect::<Vec<_>>()
     );
 
     debug!("Connecting to update service at {}:{}: {}", file!(), line!(), url);
@@ -38,15 +56,6 @@ pub async fn stream_updates(
         }
     };
 
-    let filters_by_party = build_filters_by_party(&parties);
-    debug!("Built filters_by_party at {}:{}: {:?}", file!(), line!(), filters_by_party);
-
-    let event_format = EventFormat {
-        filters_by_party,
-        filters_for_any_party: None,
-        verbose: true,
-    };
-
     let transaction_format = TransactionFormat {
         event_format: Some(event_format),
         transaction_shape: TransactionShape::LedgerEffects as i32,
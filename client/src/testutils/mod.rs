@@ -0,0 +1,442 @@
+pub mod setup;
+
+use anyhow::Result;
+use ledger_api::v2::admin::package_management_service_client::PackageManagementServiceClient;
+use nix::libc;
+use nix::sys::signal::{Signal, killpg};
+use nix::unistd::Pid;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{debug, info};
+
+/// How long `start_sandbox` waits for the ledger API of the sandbox to respond
+pub const DEFAULT_READY_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// The ports of a sandbox, passed to `dpm sandbox`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SandboxPorts {
+    pub ledger_api: u16,
+    pub admin_api: u16,
+    pub json_api: u16,
+    pub sequencer_public: u16,
+    pub sequencer_admin: u16,
+    pub mediator_admin: u16,
+}
+
+impl SandboxPorts {
+    /// The URL of the ledger API
+    pub fn ledger_url(&self) -> String {
+        format!("http://localhost:{}", self.ledger_api)
+    }
+
+    fn args(&self) -> Vec<String> {
+        [
+            ("--ledger-api-port", self.ledger_api),
+            ("--admin-api-port", self.admin_api),
+            ("--json-api-port", self.json_api),
+            ("--sequencer-public-port", self.sequencer_public),
+            ("--sequencer-admin-port", self.sequencer_admin),
+            ("--mediator-admin-port", self.mediator_admin),
+        ]
+        .into_iter()
+        .flat_map(|(flag, port)| [flag.to_string(), port.to_string()])
+        .collect()
+    }
+}
+
+/// Free ports for a sandbox, distinct from each other, so that tests can run sandboxes in
+/// parallel. The ports are bound to ephemeral ports and released, another process could take
+/// one of them before the sandbox binds it.
+pub fn allocate_ports() -> Result<SandboxPorts> {
+    let listeners = (0..6)
+        .map(|_| std::net::TcpListener::bind(("127.0.0.1", 0)))
+        .collect::<std::io::Result<Vec<_>>>()?;
+    let ports = listeners
+        .iter()
+        .map(|listener| Ok(listener.local_addr()?.port()))
+        .collect::<std::io::Result<Vec<_>>>()?;
+    Ok(SandboxPorts {
+        ledger_api: ports[0],
+        admin_api: ports[1],
+        json_api: ports[2],
+        sequencer_public: ports[3],
+        sequencer_admin: ports[4],
+        mediator_admin: ports[5],
+    })
+}
+
+/// Starts the Daml sandbox in the background, with its ledger API on the port and its other
+/// ports allocated by `allocate_ports`.
+/// Returns Ok(SandboxGuard) if the process starts successfully.
+pub async fn start_sandbox(package_root: PathBuf, dar_path: PathBuf, sandbox_port: u16) -> Result<SandboxGuard> {
+    let ports = SandboxPorts { ledger_api: sandbox_port, ..allocate_ports()? };
+    start_sandbox_with_timeout(package_root, dar_path, ports, DEFAULT_READY_TIMEOUT).await
+}
+
+/// Starts the Daml sandbox in the background on the ports, e.g. of `allocate_ports`.
+pub async fn start_sandbox_with_ports(package_root: PathBuf, dar_path: PathBuf, ports: SandboxPorts) -> Result<SandboxGuard> {
+    start_sandbox_with_timeout(package_root, dar_path, ports, DEFAULT_READY_TIMEOUT).await
+}
+
+/// Starts the Daml sandbox in the background on the ports, and waits until its ledger API
+/// responds, at most `ready_timeout`.
+pub async fn start_sandbox_with_timeout(
+    package_root: PathBuf,
+    dar_path: PathBuf,
+    ports: SandboxPorts,
+    ready_timeout: Duration,
+) -> Result<SandboxGuard> {
+    info!("Starting sandbox on {:?}", ports);
+    let mut child;
+    unsafe {
+        child = Command::new("dpm")
+            .args(["sandbox", "--dar", dar_path.to_str().unwrap()])
+            .args(ports.args())
+            .current_dir(&package_root)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .pre_exec(|| {
+                // SAFETY: setpgid is required to create a new process group for the child.
+                if libc::setpgid(0, 0) != 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+                Ok(())
+            })
+            .spawn()
+            .map_err(|e| anyhow::anyhow!("Failed to start sandbox: {}", e))?;
+    }
+
+    let log_file = sandbox_log_file(&ports)?;
+    info!("Sandbox output in {}", log_file.display());
+    capture_sandbox_output(&mut child, &log_file)?;
+    // Dropped, and so the sandbox killed, if it does not get ready
+    let guard = SandboxGuard {
+        child: Some(child),
+        ports,
+        working_dir: package_root,
+        log_file,
+        failed: AtomicBool::new(false),
+    };
+    if let Err(e) = crate::version::wait_for_ledger_api(&ports.ledger_url(), ready_timeout).await {
+        return Err(e.context(format!(
+            "sandbox output, last lines of {}:\n{}",
+            guard.log_file.display(),
+            guard.tail(SANDBOX_TAIL_LINES).join("\n")
+        )));
+    }
+    info!("Sandbox is ready!");
+    Ok(guard)
+}
+
+/// The environment variable of the directory of the sandbox log files, the `rct-sandbox-logs`
+/// directory of the temporary directory if not set
+pub const SANDBOX_LOG_DIR_ENV: &str = "RCT_SANDBOX_LOG_DIR";
+
+/// How many lines of the sandbox output a failure to start shows
+const SANDBOX_TAIL_LINES: usize = 50;
+
+/// A new log file for a run of a sandbox, named by its ledger API port and the start time
+fn sandbox_log_file(ports: &SandboxPorts) -> Result<PathBuf> {
+    let dir = std::env::var_os(SANDBOX_LOG_DIR_ENV)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| std::env::temp_dir().join("rct-sandbox-logs"));
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| anyhow::anyhow!("Failed to create sandbox log directory {}: {}", dir.display(), e))?;
+    let millis = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis();
+    Ok(dir.join(format!("sandbox-{}-{}.log", ports.ledger_api, millis)))
+}
+
+/// Copies the lines of the sandbox stdout and stderr to the log file from a thread each, for
+/// the whole life of the sandbox, so that it does not block on a full pipe and its output after
+/// it got ready is kept
+fn capture_sandbox_output(child: &mut Child, log_file: &Path) -> Result<()> {
+    let log = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(log_file)
+        .map_err(|e| anyhow::anyhow!("Failed to open sandbox log file {}: {}", log_file.display(), e))?;
+    let stdout = child
+        .stdout
+        .take()
+        .expect("Failed to capture sandbox stdout");
+    let stderr = child
+        .stderr
+        .take()
+        .expect("Failed to capture sandbox stderr");
+    copy_lines(stdout, log.try_clone()?, "stdout");
+    copy_lines(stderr, log, "stderr");
+    Ok(())
+}
+
+fn copy_lines(output: impl Read + Send + 'static, mut log: File, name: &'static str) {
+    std::thread::spawn(move || {
+        for line in BufReader::new(output).lines() {
+            let Ok(line) = line else { break };
+            debug!("Sandbox {} line: {}", name, line);
+            // A write per line to the file opened to append, not to interleave with the other thread
+            if log.write_all(format!("{}\n", line).as_bytes()).is_err() {
+                break;
+            }
+        }
+    });
+}
+
+/// The last lines of the log file, of all its lines if fewer, or none if it cannot be read
+pub fn tail_lines(log_file: &Path, lines: usize) -> Vec<String> {
+    let Ok(contents) = std::fs::read_to_string(log_file) else {
+        return Vec::new();
+    };
+    let all: Vec<&str> = contents.lines().collect();
+    all[all.len().saturating_sub(lines)..].iter().map(|line| line.to_string()).collect()
+}
+
+/// Closes the Daml sandbox process.
+pub fn close_sandbox(child: &mut Child) -> anyhow::Result<()> {
+    let pgid = child.id(); // Process group ID is the PID of the leader
+    killpg(Pid::from_raw(pgid as i32), Signal::SIGKILL)
+        .map_err(|e| anyhow::anyhow!("Failed to send SIGKILL to sandbox process group: {}", e))?;
+    child
+        .wait()
+        .map_err(|e| anyhow::anyhow!("Failed to wait for sandbox to exit: {}", e))?;
+    Ok(())
+}
+
+/// The environment variable keeping sandboxes alive for debugging: with `1`, the sandbox of a
+/// failing test is not killed, of a test which panics or whose body run by `SandboxGuard::run`
+/// returns an `Err`
+pub const KEEP_SANDBOX_ENV: &str = "RCT_KEEP_SANDBOX";
+
+/// Whether the guard being dropped keeps its sandbox alive, see `KEEP_SANDBOX_ENV`
+fn keep_sandbox(failed: &AtomicBool) -> bool {
+    std::env::var(KEEP_SANDBOX_ENV).as_deref() == Ok("1") && (failed.load(Ordering::Relaxed) || std::thread::panicking())
+}
+
+/// Runs the body of a test, marking the guard failed if it returns an `Err`
+async fn run_marking_failure<T>(failed: &AtomicBool, body: impl Future<Output = Result<T>>) -> Result<T> {
+    let result = body.await;
+    if result.is_err() {
+        failed.store(true, Ordering::Relaxed);
+    }
+    result
+}
+
+pub struct SandboxGuard {
+    pub child: Option<std::process::Child>,
+    /// The ports the sandbox listens on
+    pub ports: SandboxPorts,
+    /// The working directory of the sandbox, where it writes its logs
+    pub working_dir: PathBuf,
+    /// The file the stdout and stderr of the sandbox are copied to
+    pub log_file: PathBuf,
+    /// Whether the test of the sandbox failed, see `run`
+    failed: AtomicBool,
+}
+
+impl SandboxGuard {
+    /// Runs the body of the test of the sandbox, so that with `KEEP_SANDBOX_ENV` the sandbox is
+    /// kept alive if it returns an `Err`, not only if it panics
+    ///
+    /// ```ignore
+    /// let guard = start_sandbox_with_ports(package_root, dar_path, ports).await?;
+    /// guard.run(async {
+    ///     let parties = get_parties(guard.ports.ledger_url(), None, None).await?;
+    ///     Ok(())
+    /// }).await
+    /// ```
+    pub async fn run<T>(&self, body: impl Future<Output = Result<T>>) -> Result<T> {
+        run_marking_failure(&self.failed, body).await
+    }
+
+    /// Marks the test of the sandbox failed, see `run`
+    pub fn mark_failed(&self) {
+        self.failed.store(true, Ordering::Relaxed);
+    }
+
+    /// The last lines of the stdout and stderr of the sandbox, e.g. to print when a test fails
+    pub fn tail(&self, lines: usize) -> Vec<String> {
+        tail_lines(&self.log_file, lines)
+    }
+}
+
+impl Drop for SandboxGuard {
+    fn drop(&mut self) {
+        let Some(child) = &mut self.child else { return };
+        if keep_sandbox(&self.failed) {
+            eprintln!(
+                "{} is set, keeping the sandbox with pid {} alive: ledger API at {}, ports {:?}, output in {}, logs in {}. \
+                 Stop it with `kill -9 -{}`.",
+                KEEP_SANDBOX_ENV,
+                child.id(),
+                self.ports.ledger_url(),
+                self.ports,
+                self.log_file.display(),
+                self.working_dir.join("log").display(),
+                child.id()
+            );
+            return;
+        }
+        let _ = close_sandbox(child);
+    }
+}
+
+/// Starts a Canton sandbox in a Docker container of the image, with the `docker` CLI, and
+/// uploads the DARs over gRPC once its ledger API responds, failing if one is rejected, for
+/// machines without the Daml tooling. The image must serve the ledger API on `container_port`
+/// on all interfaces, e.g. 6865 for the default of Canton, which is mapped to a free port of
+/// the host.
+pub async fn start_sandbox_docker(image: &str, container_port: u16, dars: &[PathBuf]) -> Result<DockerSandboxGuard> {
+    let ledger_api_port = allocate_ports()?.ledger_api;
+    info!("Starting sandbox container of {} with its ledger API on port {}", image, ledger_api_port);
+    let output = Command::new("docker")
+        .args([
+            "run",
+            "--detach",
+            "--rm",
+            "--publish",
+            &format!("127.0.0.1:{}:{}", ledger_api_port, container_port),
+            image,
+        ])
+        .output()
+        .map_err(|e| anyhow::anyhow!("Failed to run docker: {}", e))?;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "Failed to start sandbox container of {}: {}",
+            image,
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+    // Dropped, and so the container removed, if it does not get ready
+    let guard = DockerSandboxGuard {
+        container_id: String::from_utf8_lossy(&output.stdout).trim().to_string(),
+        ledger_api_port,
+        failed: AtomicBool::new(false),
+    };
+    crate::version::wait_for_ledger_api(&guard.ledger_url(), DEFAULT_READY_TIMEOUT).await?;
+    if !dars.is_empty() {
+        let mut package_client = PackageManagementServiceClient::connect(guard.ledger_url()).await?;
+        for dar in dars {
+            crate::upload_dar::upload_dar(&mut package_client, dar).await?;
+        }
+    }
+    info!("Sandbox container {} is ready!", guard.container_id);
+    Ok(guard)
+}
+
+/// The container of `start_sandbox_docker`, removed when dropped
+pub struct DockerSandboxGuard {
+    pub container_id: String,
+    /// The port of the host the ledger API is mapped to
+    pub ledger_api_port: u16,
+    /// Whether the test of the sandbox failed, see `run`
+    failed: AtomicBool,
+}
+
+impl DockerSandboxGuard {
+    /// Runs the body of the test of the container, like `SandboxGuard::run`
+    pub async fn run<T>(&self, body: impl Future<Output = Result<T>>) -> Result<T> {
+        run_marking_failure(&self.failed, body).await
+    }
+
+    /// Marks the test of the container failed, see `run`
+    pub fn mark_failed(&self) {
+        self.failed.store(true, Ordering::Relaxed);
+    }
+
+    /// The URL of the ledger API
+    pub fn ledger_url(&self) -> String {
+        format!("http://localhost:{}", self.ledger_api_port)
+    }
+}
+
+impl Drop for DockerSandboxGuard {
+    fn drop(&mut self) {
+        if keep_sandbox(&self.failed) {
+            eprintln!(
+                "{} is set, keeping the sandbox container {} alive: ledger API at {}, logs with `docker logs {}`. \
+                 Stop it with `docker rm --force {}`.",
+                KEEP_SANDBOX_ENV,
+                self.container_id,
+                self.ledger_url(),
+                self.container_id,
+                self.container_id
+            );
+            return;
+        }
+        let _ = Command::new("docker")
+            .args(["rm", "--force", &self.container_id])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio;
+
+    #[tokio::test]
+    async fn test_start_and_close_sandbox() {
+        tracing_subscriber::fmt::init();
+        let package_root = PathBuf::from("/Users/gyorgybalazsi/rust-client-toolbox/_daml/daml-asset");
+        let dar_path = PathBuf::from("/Users/gyorgybalazsi/rust-client-toolbox/_daml/daml-asset/.daml/dist/daml-asset-0.0.1.dar");
+        let ports = allocate_ports().expect("Failed to allocate ports");
+        let guard = start_sandbox_with_ports(package_root, dar_path, ports)
+            .await
+            .expect("Failed to start sandbox");
+        assert_eq!(guard.ports, ports);
+    }
+
+    #[test]
+    fn test_allocate_ports_distinct() {
+        let ports = allocate_ports().unwrap();
+        let mut all = ports.args().into_iter().skip(1).step_by(2).collect::<Vec<_>>();
+        all.sort();
+        all.dedup();
+        assert_eq!(all.len(), 6);
+        assert!(!all.contains(&"0".to_string()));
+        assert_eq!(ports.ledger_url(), format!("http://localhost:{}", ports.ledger_api));
+    }
+
+    #[tokio::test]
+    async fn test_run_marks_failure_of_err() {
+        let failed = AtomicBool::new(false);
+        assert_eq!(run_marking_failure(&failed, async { Ok(1) }).await.unwrap(), 1);
+        assert!(!failed.load(Ordering::Relaxed));
+        let result: Result<()> = run_marking_failure(&failed, async { Err(anyhow::anyhow!("no contract")) }).await;
+        assert!(result.is_err());
+        assert!(failed.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn test_tail_lines_of_sandbox_output() {
+        let mut child = Command::new("sh")
+            .args(["-c", "echo one; echo two >&2; echo three"])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let log_file = std::env::temp_dir().join(format!("rct-sandbox-test-{}.log", std::process::id()));
+        let _ = std::fs::remove_file(&log_file);
+        capture_sandbox_output(&mut child, &log_file).unwrap();
+        child.wait().unwrap();
+        let mut lines = Vec::new();
+        for _ in 0..50 {
+            lines = tail_lines(&log_file, 10);
+            if lines.len() == 3 {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        lines.sort();
+        assert_eq!(lines, ["one", "three", "two"]);
+        assert_eq!(tail_lines(&log_file, 1).len(), 1);
+        std::fs::remove_file(&log_file).unwrap();
+        assert!(tail_lines(&log_file, 1).is_empty());
+    }
+}
@@ -0,0 +1,172 @@
+//! The setup of the Setup.daml scripts of the tests, with the gRPC services of the client
+//! library instead of `dpm script`: allocating the parties, creating the users with their
+//! rights, minting an access token per user, and creating seed contracts, referring to the
+//! parties by their hints.
+//!
+//! ```ignore
+//! let setup = Setup::new()
+//!     .party("Alice")
+//!     .party("Bob")
+//!     .user(HintedUser::new("alice", "Alice"))
+//!     .user(HintedUser::new("bob", "Bob").read_as("Alice"))
+//!     .contract("Alice", "#daml-asset:Main:Asset", |parties| asset(&parties["Alice"]))
+//!     .run(&guard.ports.ledger_url())
+//!     .await?;
+//! let alice = setup.party("Alice")?;
+//! let alice_token = setup.token("alice")?;
+//! ```
+use crate::jwt::fake_jwt_for_user;
+use crate::party_management::allocate_parties::allocate_parties;
+use crate::submit_commands::{CommandResult, submit_commands};
+use crate::user_management::hinted_user::{HintedUser, create_hinted_user, party};
+use crate::utils::parse_identifier;
+use anyhow::{Context, Result};
+use ledger_api::v2::command_service_client::CommandServiceClient;
+use ledger_api::v2::{Command, Commands, CreateCommand, Record};
+use std::collections::BTreeMap;
+use tracing::info;
+
+/// The create arguments of a seed contract, from the allocated party ids by their hints
+type Arguments = Box<dyn Fn(&BTreeMap<String, String>) -> Record + Send + Sync>;
+
+/// The parties, users and seed contracts to set up, see `Setup::run`
+#[derive(Default)]
+pub struct Setup {
+    parties: Vec<String>,
+    users: Vec<HintedUser>,
+    contracts: Vec<SeedContract>,
+}
+
+struct SeedContract {
+    submitter: String,
+    template_id: String,
+    arguments: Arguments,
+}
+
+/// What the setup allocated and created
+#[derive(Debug, Clone, Default)]
+pub struct SetupResult {
+    /// The allocated party ids by their hints
+    pub parties: BTreeMap<String, String>,
+    /// Unsigned tokens of the users by their ids, accepted by the sandbox
+    pub tokens: BTreeMap<String, String>,
+    /// The contract ids of the seed contracts, in the order they were added
+    pub contracts: Vec<String>,
+}
+
+impl SetupResult {
+    /// The party id of the hint
+    pub fn party(&self, hint: &str) -> Result<String> {
+        party(&self.parties, hint)
+    }
+
+    /// The token of the user
+    pub fn token(&self, user_id: &str) -> Result<String> {
+        self.tokens
+            .get(user_id)
+            .cloned()
+            .with_context(|| format!("user '{}' is not in the users of the setup", user_id))
+    }
+}
+
+impl Setup {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a party to allocate by its hint
+    pub fn party(mut self, hint: &str) -> Self {
+        self.parties.push(hint.to_string());
+        self
+    }
+
+    pub fn user(mut self, user: HintedUser) -> Self {
+        self.users.push(user);
+        self
+    }
+
+    /// Adds a seed contract of the template, `<package>:<Module.Name>:<Entity>`, created by the
+    /// party of the hint, through a user of the setup acting as it
+    pub fn contract(
+        mut self,
+        submitter: &str,
+        template_id: &str,
+        arguments: impl Fn(&BTreeMap<String, String>) -> Record + Send + Sync + 'static,
+    ) -> Self {
+        self.contracts.push(SeedContract {
+            submitter: submitter.to_string(),
+            template_id: template_id.to_string(),
+            arguments: Box::new(arguments),
+        });
+        self
+    }
+
+    /// Allocates the parties, creates the users, then creates the seed contracts on the ledger
+    /// API at the url, e.g. of a sandbox of `start_sandbox_with_ports`
+    pub async fn run(&self, url: &str) -> Result<SetupResult> {
+        let allocated = allocate_parties(url.to_string(), None, self.parties.clone()).await?;
+        let parties: BTreeMap<String, String> = self.parties.iter().cloned().zip(allocated).collect();
+        info!("Allocated parties {:?}", parties);
+
+        let mut tokens = BTreeMap::new();
+        for user in &self.users {
+            let user_id = create_hinted_user(url, None, user, &parties).await?;
+            tokens.insert(user_id.clone(), fake_jwt_for_user(&user_id));
+        }
+
+        let mut contracts = Vec::new();
+        if !self.contracts.is_empty() {
+            let mut command_service_client = CommandServiceClient::connect(url.to_string()).await?;
+            for contract in &self.contracts {
+                let user_id = self.submitting_user(&contract.submitter)?;
+                let commands = Commands {
+                    act_as: vec![party(&parties, &contract.submitter)?],
+                    user_id: user_id.to_string(),
+                    command_id: format!("setup-{}", uuid::Uuid::new_v4()),
+                    commands: vec![Command {
+                        command: Some(ledger_api::v2::command::Command::Create(CreateCommand {
+                            template_id: Some(parse_identifier(&contract.template_id)?),
+                            create_arguments: Some((contract.arguments)(&parties)),
+                        })),
+                    }],
+                    ..Default::default()
+                };
+                let results = submit_commands(&mut command_service_client, Some(&tokens[user_id]), commands, None).await?;
+                let Some(CommandResult::Created { contract_id, .. }) = results.into_iter().next() else {
+                    anyhow::bail!("No contract created for the seed contract of {}", contract.template_id);
+                };
+                info!("Created seed contract {} of {}", contract_id, contract.template_id);
+                contracts.push(contract_id);
+            }
+        }
+        Ok(SetupResult { parties, tokens, contracts })
+    }
+
+    /// The first user of the setup acting as the party of the hint
+    fn submitting_user(&self, hint: &str) -> Result<&str> {
+        self.users
+            .iter()
+            .find(|user| user.act_as.iter().any(|party| party == hint))
+            .map(|user| user.id.as_str())
+            .with_context(|| format!("no user of the setup acts as party '{}'", hint))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seed_contracts_submitted_by_a_user_acting_as_their_party() {
+        let setup = Setup::new()
+            .party("Alice")
+            .party("Bank")
+            .user(HintedUser::without_party("all_reader_user").read_as("Alice").read_as("Bank"))
+            .user(HintedUser::new("aliceuser", "Alice"))
+            .user(HintedUser::new("bankuser", "Bank").read_as("Alice"))
+            .contract("Bank", "#daml-asset:Main:Asset", |_| Record::default());
+        assert_eq!(setup.submitting_user("Bank").unwrap(), "bankuser");
+        assert_eq!(setup.submitting_user("Alice").unwrap(), "aliceuser");
+        assert!(setup.submitting_user("Bob").is_err());
+    }
+}
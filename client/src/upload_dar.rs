@@ -1,15 +1,14 @@
 use ledger_api::v2::admin::{
     package_management_service_client::PackageManagementServiceClient,
-    UploadDarFileRequest,
+    ListKnownPackagesRequest, PackageDetails, UploadDarFileRequest,
 };
+use tonic::metadata::MetadataValue;
 use tonic::transport::Channel;
 use tracing::{info, error};
-use anyhow::Result;
+use anyhow::{Context, Result};
 use std::fs::File;
-use std::io::{Read, BufRead, BufReader};
-use std::process::Command;
+use std::io::Read;
 use std::path::Path;
-use zip::ZipArchive;
 
 /// Uploads a list of DAR files to the ledger via gRPC PackageManagementService.
 /// `ledger_api` is a PathBuf to the ledger API endpoint (e.g., "http://localhost:6865").
@@ -45,6 +44,22 @@ pub async fn upload_dars(
     Ok(())
 }
 
+/// Uploads the DAR file to the ledger, failing if it cannot be read or the participant rejects
+/// it, unlike `upload_dars`
+pub async fn upload_dar(client: &mut PackageManagementServiceClient<Channel>, dar_path: &Path) -> Result<()> {
+    let dar_bytes = std::fs::read(dar_path).with_context(|| format!("Failed to read DAR {:?}", dar_path))?;
+    let request = UploadDarFileRequest {
+        dar_file: dar_bytes,
+        submission_id: uuid::Uuid::new_v4().to_string(),
+    };
+    info!("Uploading DAR file {:?}", dar_path);
+    client
+        .upload_dar_file(request)
+        .await
+        .with_context(|| format!("Failed to upload DAR {:?}", dar_path))?;
+    Ok(())
+}
+
 pub async fn list_dars(ledger_api: &std::path::PathBuf) -> Result<Vec<String>> {
     let url = ledger_api.to_string_lossy().into_owned();
     let channel = Channel::from_shared(url)
@@ -68,84 +83,41 @@ pub async fn list_dars(ledger_api: &std::path::PathBuf) -> Result<Vec<String>> {
     Ok(packages)
 }
 
-/// Extracts the package ID from a DAR file using the `daml damlc inspect-dar` CLI.
-/// Returns Ok(package_id) if successful, otherwise an error.
-pub fn extract_package_id_from_dar(dar_path: &Path) -> anyhow::Result<String> {
-    let output = Command::new("daml")
-        .args(&["damlc", "inspect-dar", dar_path.to_str().unwrap(), "--json"])
-        .output()
-        .map_err(|e| anyhow::anyhow!("Failed to run daml damlc inspect-dar: {}", e))?;
-
-    if !output.status.success() {
-        return Err(anyhow::anyhow!(
-            "daml damlc inspect-dar failed: {}",
-            String::from_utf8_lossy(&output.stderr)
-        ));
+/// Lists the details of the packages known to the participant node: id, name, version, size and
+/// since when the participant knows it.
+pub async fn list_package_details(url: &str, access_token: Option<&str>) -> Result<Vec<PackageDetails>> {
+    let mut client = PackageManagementServiceClient::connect(url.to_string()).await?;
+    let mut request = tonic::Request::new(ListKnownPackagesRequest {});
+    if let Some(token) = access_token {
+        let meta = MetadataValue::try_from(format!("Bearer {}", token))?;
+        request.metadata_mut().insert("authorization", meta);
     }
-
-    let json: serde_json::Value = serde_json::from_slice(&output.stdout)?;
-    let package_id = json
-        .get("main_package_id")
-        .and_then(|v| v.as_str())
-        .ok_or_else(|| anyhow::anyhow!("main_package_id not found in inspect-dar output"))?;
-
-    Ok(package_id.to_string())
+    let response = client
+        .list_known_packages(request)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to list known packages: {}", e))?;
+    Ok(response.into_inner().package_details)
 }
 
-/// Extracts the package ID from the MANIFEST.MF file inside a DAR directory.
+/// Extracts the main package ID from a DAR file by decoding its main DALF archive.
 /// Returns Ok(package_id) if successful, otherwise an error.
-pub fn package_id_from_dar(dar_path: &Path) -> anyhow::Result<String> {
-    let file = File::open(dar_path)
-        .map_err(|e| anyhow::anyhow!("Failed to open DAR file: {}", e))?;
-    let mut archive = ZipArchive::new(file)
-        .map_err(|e| anyhow::anyhow!("Failed to open DAR as zip archive: {}", e))?;
-
-    // Find the index of the MANIFEST.MF file inside META-INF
-    let mut manifest_index = None;
-    for i in 0..archive.len() {
-        let name = archive.by_index(i)?.name().to_string();
-        if name.ends_with("META-INF/MANIFEST.MF") {
-            manifest_index = Some(i);
-            break;
-        }
-    }
-
-    let mut manifest = match manifest_index {
-        Some(idx) => archive.by_index(idx)?,
-        None => return Err(anyhow::anyhow!("MANIFEST.MF not found in DAR")),
-    };
-
-    let reader = BufReader::new(&mut manifest);
-    let mut main_dalf_line = String::new();
-    for line in reader.lines() {
-        let line = line?;
-        if line.starts_with("Main-Dalf:") {
-            main_dalf_line.push_str(line.trim_start_matches("Main-Dalf:").trim());
-        } else if !main_dalf_line.is_empty() && line.starts_with(' ') {
-            // Continuation line (starts with a space)
-            main_dalf_line.push_str(line.trim());
-        } else if !main_dalf_line.is_empty() {
-            break;
-        }
-    }
-
-    // Find the package id in the main_dalf_line
-    let re = regex::Regex::new(r"-([a-f0-9]{40,})/").unwrap();
-    if let Some(caps) = re.captures(&main_dalf_line) {
-        let package_id = caps.get(1).unwrap().as_str();
-        Ok(package_id.to_string())
-    } else {
-        Err(anyhow::anyhow!("Failed to extract package id from MANIFEST.MF"))
-    }
+pub fn extract_package_id_from_dar(dar_path: &Path) -> anyhow::Result<String> {
+    package_id_from_dar(dar_path)
 }
 
-
-
+/// Reads the main package ID of a DAR file from its main DALF archive, see
+/// `codegen::package::package_info_from_dar` for the package name, version and dependencies.
+pub fn package_id_from_dar(dar_path: &Path) -> anyhow::Result<String> {
+    let dar_path = dar_path
+        .to_str()
+        .ok_or_else(|| anyhow::anyhow!("DAR path is not valid UTF-8: {:?}", dar_path))?;
+    Ok(codegen::package::package_info_from_dar(dar_path)?.package_id)
+}
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::testutils::start_sandbox;
+    use crate::testutils::{allocate_ports, start_sandbox_with_ports};
     use tokio;
     use std::path::PathBuf;
 
@@ -161,48 +133,51 @@ mod tests {
             .join("daml-interface-example")
             .join("test");
         let dar_path = package_root.join(".daml").join("dist").join("daml-interface-example-test-1.0.0.dar");
-        let sandbox_port = 6865;
-        let _guard = start_sandbox(package_root, dar_path, sandbox_port)
+        let ports = allocate_ports().expect("Failed to allocate ports");
+        let sandbox_port = ports.ledger_api;
+        let guard = start_sandbox_with_ports(package_root, dar_path, ports)
             .await
             .expect("Failed to start sandbox");
 
-        let ledger_api = PathBuf::from(format!("http://localhost:{}", sandbox_port));
-        let dar_paths = vec![
-            PathBuf::from(&crate_root)
-                .join("..")
-                .join("_daml")
-                .join("daml-interface-example")
-                .join("interfaces")
-                .join(".daml")
-                .join("dist")
-                .join("daml-interface-example-interfaces-1.0.0.dar"),
-            PathBuf::from(&crate_root)
-                .join("..")
-                .join("_daml")
-                .join("daml-interface-example")
-                .join("main")
-                .join(".daml")
-                .join("dist")
-                .join("daml-interface-example-main-1.0.0.dar"),
-        ];
-
-        upload_dars(&ledger_api, &dar_paths).await?;
-
-        let known_packages = list_dars(&ledger_api).await?;
-
-        for dar_path in &dar_paths {
-            let package_id = package_id_from_dar(dar_path)
-                .expect("Failed to extract package id from DAR");
-
-            info!("Uploaded DAR package id: {}", package_id);
-            assert!(
-                known_packages.iter().any(|pkg| pkg == &package_id),
-                "Uploaded DAR package id '{}' not found in known packages: {:?}",
-                package_id,
-                known_packages
-            );
-        }
-        Ok(())
+        guard.run(async {
+            let ledger_api = PathBuf::from(format!("http://localhost:{}", sandbox_port));
+            let dar_paths = vec![
+                PathBuf::from(&crate_root)
+                    .join("..")
+                    .join("_daml")
+                    .join("daml-interface-example")
+                    .join("interfaces")
+                    .join(".daml")
+                    .join("dist")
+                    .join("daml-interface-example-interfaces-1.0.0.dar"),
+                PathBuf::from(&crate_root)
+                    .join("..")
+                    .join("_daml")
+                    .join("daml-interface-example")
+                    .join("main")
+                    .join(".daml")
+                    .join("dist")
+                    .join("daml-interface-example-main-1.0.0.dar"),
+            ];
+
+            upload_dars(&ledger_api, &dar_paths).await?;
+
+            let known_packages = list_dars(&ledger_api).await?;
+
+            for dar_path in &dar_paths {
+                let package_id = package_id_from_dar(dar_path)
+                    .expect("Failed to extract package id from DAR");
+
+                info!("Uploaded DAR package id: {}", package_id);
+                assert!(
+                    known_packages.iter().any(|pkg| pkg == &package_id),
+                    "Uploaded DAR package id '{}' not found in known packages: {:?}",
+                    package_id,
+                    known_packages
+                );
+            }
+            Ok(())
+        }).await
     }
 }
 
@@ -89,7 +89,7 @@ pub fn participant_admin() -> Right {
 mod tests {
     use super::*;
     use crate::party_management::allocate_parties::allocate_parties;
-    use crate::testutils::start_sandbox;
+    use crate::testutils::{allocate_ports, start_sandbox_with_ports};
     use std::path::PathBuf;
 
     #[tokio::test]
@@ -103,10 +103,11 @@ mod tests {
             .join("_daml")
             .join("daml-asset");
         let dar_path = package_root.join(".daml").join("dist").join("daml-asset-0.0.1.dar");
-        let sandbox_port = 6865;
+        let ports = allocate_ports().expect("Failed to allocate ports");
+        let sandbox_port = ports.ledger_api;
 
         tracing::info!(?package_root, ?dar_path, sandbox_port, "Starting sandbox");
-        let _guard = start_sandbox(package_root, dar_path, sandbox_port)
+        let _guard = start_sandbox_with_ports(package_root, dar_path, ports)
             .await
             .expect("Failed to start sandbox");
         tracing::info!("Sandbox started successfully");
@@ -0,0 +1,105 @@
+//! Users referring to their parties by the hints the parties are allocated with, as in the
+//! setup of the tests in `testutils::setup` and the bootstrap file of sandbox-init, created once
+//! the parties are allocated.
+use crate::user_management::create_user::{can_act_as, can_read_as, create_user, participant_admin};
+use anyhow::{Context, Result};
+use ledger_api::v2::admin::Right;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+/// A user to create, with the hints of its parties
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HintedUser {
+    pub id: String,
+    /// Hint of the primary party of the user
+    pub primary_party: Option<String>,
+    /// Hints of the parties the user can act as
+    #[serde(default)]
+    pub act_as: Vec<String>,
+    /// Hints of the parties the user can read as
+    #[serde(default)]
+    pub read_as: Vec<String>,
+    #[serde(default)]
+    pub participant_admin: bool,
+}
+
+impl HintedUser {
+    /// A user with the party as primary party, acting as it
+    pub fn new(id: &str, party: &str) -> Self {
+        Self {
+            id: id.to_string(),
+            primary_party: Some(party.to_string()),
+            act_as: vec![party.to_string()],
+            ..Default::default()
+        }
+    }
+
+    /// A user without primary party nor rights, e.g. to read as all parties
+    pub fn without_party(id: &str) -> Self {
+        Self { id: id.to_string(), ..Default::default() }
+    }
+
+    pub fn read_as(mut self, party: &str) -> Self {
+        self.read_as.push(party.to_string());
+        self
+    }
+
+    /// The rights of the user, with the party hints resolved to the allocated parties
+    pub fn rights(&self, parties: &BTreeMap<String, String>) -> Result<Vec<Right>> {
+        let mut rights = Vec::new();
+        for hint in &self.act_as {
+            rights.push(can_act_as(&party(parties, hint)?));
+        }
+        for hint in &self.read_as {
+            rights.push(can_read_as(&party(parties, hint)?));
+        }
+        if self.participant_admin {
+            rights.push(participant_admin());
+        }
+        Ok(rights)
+    }
+}
+
+/// Creates the user on the ledger API at the url with its rights, returning its id
+pub async fn create_hinted_user(
+    url: &str,
+    access_token: Option<&str>,
+    user: &HintedUser,
+    parties: &BTreeMap<String, String>,
+) -> Result<String> {
+    let rights = user.rights(parties)?;
+    let primary_party = user.primary_party.as_deref().map(|hint| party(parties, hint)).transpose()?;
+    create_user(url.to_string(), access_token, user.id.clone(), primary_party, rights).await
+}
+
+/// The allocated party id of the hint
+pub fn party(parties: &BTreeMap<String, String>, hint: &str) -> Result<String> {
+    parties
+        .get(hint)
+        .cloned()
+        .with_context(|| format!("party '{}' is not in the allocated parties", hint))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ledger_api::v2::admin::right::Kind;
+
+    #[test]
+    fn test_rights_refer_to_allocated_parties() {
+        let parties = BTreeMap::from([
+            ("Alice".to_string(), "Alice::1220".to_string()),
+            ("Bob".to_string(), "Bob::1220".to_string()),
+        ]);
+        let user = HintedUser { participant_admin: true, ..HintedUser::new("alice", "Alice").read_as("Bob") };
+        let rights = user.rights(&parties).unwrap();
+        assert_eq!(rights.len(), 3);
+        assert!(matches!(&rights[0].kind, Some(Kind::CanActAs(right)) if right.party == "Alice::1220"));
+        assert!(matches!(&rights[1].kind, Some(Kind::CanReadAs(right)) if right.party == "Bob::1220"));
+        assert!(matches!(&rights[2].kind, Some(Kind::ParticipantAdmin(_))));
+
+        assert!(HintedUser::without_party("reader").rights(&parties).unwrap().is_empty());
+        assert!(HintedUser::new("carol", "Carol").rights(&parties).is_err());
+    }
+}
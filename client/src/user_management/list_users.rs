@@ -19,6 +19,25 @@ pub async fn list_users(
     url: String,
     access_token: Option<&str>,
     identity_provider_id: Option<String>,
+) -> Result<Vec<User>> {
+    list_users_with_page_size(url, access_token, identity_provider_id, 100).await
+}
+
+/// Lists all users on the participant node, requesting them in pages of `page_size`.
+///
+/// # Arguments
+/// * `url` - The gRPC endpoint URL of the participant node
+/// * `access_token` - Optional bearer token for authentication
+/// * `identity_provider_id` - Optional identity provider ID filter
+/// * `page_size` - Number of users requested per page, 0 means the server default
+///
+/// # Returns
+/// A list of all users
+pub async fn list_users_with_page_size(
+    url: String,
+    access_token: Option<&str>,
+    identity_provider_id: Option<String>,
+    page_size: i32,
 ) -> Result<Vec<User>> {
     let mut client = UserManagementServiceClient::connect(url).await?;
     let mut all_users = Vec::new();
@@ -27,7 +46,7 @@ pub async fn list_users(
     loop {
         let request = ListUsersRequest {
             page_token: page_token.clone(),
-            page_size: 100,
+            page_size,
             identity_provider_id: identity_provider_id.clone().unwrap_or_default(),
         };
 
@@ -56,7 +75,7 @@ mod tests {
     use super::*;
     use crate::party_management::allocate_parties::allocate_parties;
     use crate::user_management::create_user::{create_user, can_act_as, can_read_as};
-    use crate::testutils::start_sandbox;
+    use crate::testutils::{allocate_ports, start_sandbox_with_ports};
     use std::path::PathBuf;
 
     #[tokio::test]
@@ -70,10 +89,11 @@ mod tests {
             .join("_daml")
             .join("daml-asset");
         let dar_path = package_root.join(".daml").join("dist").join("daml-asset-0.0.1.dar");
-        let sandbox_port = 6865;
+        let ports = allocate_ports().expect("Failed to allocate ports");
+        let sandbox_port = ports.ledger_api;
 
         tracing::info!(?package_root, ?dar_path, sandbox_port, "Starting sandbox");
-        let _guard = start_sandbox(package_root, dar_path, sandbox_port)
+        let _guard = start_sandbox_with_ports(package_root, dar_path, ports)
             .await
             .expect("Failed to start sandbox");
         tracing::info!("Sandbox started successfully");
@@ -1,2 +1,3 @@
 pub mod create_user;
+pub mod hinted_user;
 pub mod list_users;
\ No newline at end of file
@@ -1,50 +1,112 @@
 use ledger_api::v2::Value;
 
 use ledger_api::v2::{
-    Filters, WildcardFilter,
+    EventFormat, Filters, Identifier, InterfaceFilter, WildcardFilter,
 };
 use std::collections::HashMap;
 
-/// Recursively extracts all contract IDs from a Value.
-/// Returns a Vec<String> of contract IDs found.
+/// Recursively extracts all contract IDs from a Value, also from records, variants, optionals
+/// and maps. Returns a Vec<String> of contract IDs found, in the order they appear.
 pub fn extract_contract_ids_from_value(value: &Option<Value>) -> Vec<String> {
     let mut result = Vec::new();
     if let Some(val) = value {
-        match &val.sum {
-            Some(ledger_api::v2::value::Sum::ContractId(cid)) => {
-                result.push(cid.clone());
-            }
-            Some(ledger_api::v2::value::Sum::List(list)) => {
-                for v in &list.elements {
-                    result.extend(extract_contract_ids_from_value(&Some(v.clone())));
-                }
-            }
-            _ => {}
-        }
+        collect_contract_ids(val, &mut result);
     }
     result
 }
 
+fn collect_contract_ids(value: &Value, result: &mut Vec<String>) {
+    use ledger_api::v2::value::Sum;
+    match &value.sum {
+        Some(Sum::ContractId(cid)) => result.push(cid.clone()),
+        Some(Sum::List(list)) => list.elements.iter().for_each(|v| collect_contract_ids(v, result)),
+        Some(Sum::Optional(optional)) => optional.value.iter().for_each(|v| collect_contract_ids(v, result)),
+        Some(Sum::Record(record)) => record.fields.iter().flat_map(|f| &f.value).for_each(|v| collect_contract_ids(v, result)),
+        Some(Sum::Variant(variant)) => variant.value.iter().for_each(|v| collect_contract_ids(v, result)),
+        Some(Sum::TextMap(map)) => map.entries.iter().flat_map(|e| &e.value).for_each(|v| collect_contract_ids(v, result)),
+        Some(Sum::GenMap(map)) => map
+            .entries
+            .iter()
+            .flat_map(|e| e.key.iter().chain(&e.value))
+            .for_each(|v| collect_contract_ids(v, result)),
+        _ => {}
+    }
+}
+
 /// Helper function to build filters_by_party for a list of parties.
 pub fn build_filters_by_party(parties: &[String]) -> HashMap<String, Filters> {
-    let mut filters_by_party = HashMap::new();
-    for party in parties {
-        filters_by_party.insert(
-            party.clone(),
-            Filters {
-                cumulative: vec![ledger_api::v2::CumulativeFilter {
-                    identifier_filter: Some(
-                        ledger_api::v2::cumulative_filter::IdentifierFilter::WildcardFilter(
-                            WildcardFilter {
-                                include_created_event_blob: true,
-                            },
-                        ),
-                    ),
-                }],
-            },
-        );
+    build_filters_by_party_with_interfaces(parties, &[])
+}
+
+/// Like `build_filters_by_party`, also requesting the views of the interfaces on created events
+/// of contracts implementing them. All contracts still match the wildcard filter.
+pub fn build_filters_by_party_with_interfaces(
+    parties: &[String],
+    interfaces: &[Identifier],
+) -> HashMap<String, Filters> {
+    let filters = filters_with_interfaces(interfaces);
+    parties
+        .iter()
+        .map(|party| (party.clone(), filters.clone()))
+        .collect()
+}
+
+fn filters_with_interfaces(interfaces: &[Identifier]) -> Filters {
+    let mut cumulative = vec![ledger_api::v2::CumulativeFilter {
+        identifier_filter: Some(
+            ledger_api::v2::cumulative_filter::IdentifierFilter::WildcardFilter(
+                WildcardFilter {
+                    include_created_event_blob: true,
+                },
+            ),
+        ),
+    }];
+    cumulative.extend(interfaces.iter().map(|interface_id| ledger_api::v2::CumulativeFilter {
+        identifier_filter: Some(
+            ledger_api::v2::cumulative_filter::IdentifierFilter::InterfaceFilter(
+                InterfaceFilter {
+                    interface_id: Some(interface_id.clone()),
+                    include_interface_view: true,
+                    include_created_event_blob: true,
+                },
+            ),
+        ),
+    }));
+    Filters { cumulative }
+}
+
+/// The verbose event format of `build_filters_by_party_with_interfaces`
+pub fn event_format_for_parties(parties: &[String], interfaces: &[Identifier]) -> EventFormat {
+    EventFormat {
+        filters_by_party: build_filters_by_party_with_interfaces(parties, interfaces),
+        filters_for_any_party: None,
+        verbose: true,
+    }
+}
+
+/// The verbose event format of the events visible to any party the user can read as, with the
+/// filters of `build_filters_by_party_with_interfaces`
+pub fn event_format_for_any_party(interfaces: &[Identifier]) -> EventFormat {
+    EventFormat {
+        filters_by_party: HashMap::new(),
+        filters_for_any_party: Some(filters_with_interfaces(interfaces)),
+        verbose: true,
+    }
+}
+
+/// Parses an identifier of the form `<package>:<Module.Name>:<Entity>`, where the package is a
+/// package id or a package name reference `#<package-name>`
+pub fn parse_identifier(identifier: &str) -> anyhow::Result<Identifier> {
+    match identifier.splitn(3, ':').collect::<Vec<_>>().as_slice() {
+        [package_id, module_name, entity_name] if !package_id.is_empty() && !module_name.is_empty() && !entity_name.is_empty() => {
+            Ok(Identifier {
+                package_id: package_id.to_string(),
+                module_name: module_name.to_string(),
+                entity_name: entity_name.to_string(),
+            })
+        }
+        _ => anyhow::bail!("Invalid identifier '{}', expected <package>:<Module.Name>:<Entity>", identifier),
     }
-    filters_by_party
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
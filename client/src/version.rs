@@ -0,0 +1,53 @@
+use ledger_api::v2::{GetLedgerApiVersionRequest, version_service_client::VersionServiceClient};
+use anyhow::Result;
+use std::time::{Duration, Instant};
+use tracing::{debug, info};
+
+/// How often `wait_for_ledger_api` retries while the ledger API does not respond
+const READINESS_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Get the version of the Ledger API, which does not require an access token.
+pub async fn get_ledger_api_version(url: &str) -> Result<String> {
+    let mut version_service_client = VersionServiceClient::connect(url.to_string()).await?;
+    let response = version_service_client
+        .get_ledger_api_version(GetLedgerApiVersionRequest {})
+        .await?;
+    Ok(response.into_inner().version)
+}
+
+/// Polls `VersionService.GetLedgerApiVersion` until the ledger API at the url responds, and
+/// returns its version. Fails with the last error if it does not respond within the timeout.
+/// Unlike waiting for a readiness message of the sandbox, this does not depend on its output.
+pub async fn wait_for_ledger_api(url: &str, timeout: Duration) -> Result<String> {
+    let start = Instant::now();
+    loop {
+        match get_ledger_api_version(url).await {
+            Ok(version) => {
+                info!("Ledger API at {} is ready, version {}", url, version);
+                return Ok(version);
+            }
+            Err(e) if start.elapsed() >= timeout => {
+                return Err(anyhow::anyhow!(
+                    "Ledger API at {} not ready after {:?}: {}",
+                    url,
+                    timeout,
+                    e
+                ));
+            }
+            Err(e) => debug!("Ledger API at {} not ready yet: {}", url, e),
+        }
+        tokio::time::sleep(READINESS_POLL_INTERVAL).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_wait_for_ledger_api_times_out() {
+        // Nothing listens on port 1
+        let result = wait_for_ledger_api("http://127.0.0.1:1", Duration::from_millis(100)).await;
+        assert!(result.unwrap_err().to_string().contains("not ready after"));
+    }
+}
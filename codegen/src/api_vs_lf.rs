@@ -1,138 +1,173 @@
-use ledger_api::v2::{Record as ApiRecord, RecordField as ApiRecordField, Value as ApiValue};
-use crate::lf_protobuf::com::daml::daml_lf_2::{self, FieldWithExpr, Expr, expr, FieldWithType, Type, BuiltinLit, builtin_lit};
+use ledger_api::v2::value::Sum as ApiSum;
+use ledger_api::v2::{
+    Enum as ApiEnum, GenMap as ApiGenMap, List as ApiList, Optional as ApiOptional, Record as ApiRecord,
+    RecordField as ApiRecordField, TextMap as ApiTextMap, Value as ApiValue, Variant as ApiVariant, gen_map, text_map,
+};
+use crate::lf_protobuf::com::daml::daml_lf_2::{
+    self, BuiltinFunction, BuiltinLit, Expr, FieldWithExpr, FieldWithType, Type, builtin_lit, expr, r#type,
+};
 use std::collections::HashMap;
 
-/// Converts an API Record to a lf_protobuf Record (Vec<FieldWithExpr>)
+/// The interned strings of a package, to which encoding adds the strings of a value which are not
+/// interned yet
+#[derive(Debug, Clone, Default)]
+pub struct InternedStrings {
+    strings: Vec<String>,
+    indices: HashMap<String, i32>,
+}
+
+impl InternedStrings {
+    pub fn new(strings: Vec<String>) -> Self {
+        let indices = strings.iter().enumerate().map(|(i, s)| (s.clone(), i as i32)).collect();
+        InternedStrings { strings, indices }
+    }
+
+    /// The index of the string, appending it if it is not interned yet
+    pub fn intern(&mut self, s: &str) -> i32 {
+        if let Some(index) = self.indices.get(s) {
+            return *index;
+        }
+        let index = self.strings.len() as i32;
+        self.strings.push(s.to_string());
+        self.indices.insert(s.to_string(), index);
+        index
+    }
+
+    pub fn strings(&self) -> &[String] {
+        &self.strings
+    }
+
+    pub fn into_strings(self) -> Vec<String> {
+        self.strings
+    }
+}
+
+/// Converts an API Record to a lf_protobuf Record (Vec<FieldWithExpr>). Fields are labelled with the
+/// labels of `field_types` where given, else with their own interned labels.
 pub fn api_record_to_lf_record(
     api_record: &ApiRecord,
     field_types: &[FieldWithType],
-    string_to_interned: &HashMap<String, i32>,
+    interned_strings: &mut InternedStrings,
 ) -> Vec<FieldWithExpr> {
-    api_record.fields.iter().zip(field_types.iter()).map(|(api_field, field_type)| {
+    api_record.fields.iter().enumerate().map(|(i, api_field)| {
+        let field_type = field_types.get(i);
         FieldWithExpr {
-            field_interned_str: field_type.field_interned_str,
-            expr: api_value_to_lf_expr(api_field.value.as_ref(), field_type.r#type.as_ref(), string_to_interned),
+            field_interned_str: match field_type {
+                Some(field_type) => field_type.field_interned_str,
+                None => interned_strings.intern(&api_field.label),
+            },
+            expr: api_value_to_lf_expr(
+                api_field.value.as_ref(),
+                field_type.and_then(|field_type| field_type.r#type.as_ref()),
+                interned_strings,
+            ),
         }
     }).collect()
 }
 
-/// Converts an API Value to a lf_protobuf Expr
+fn lf_expr(sum: expr::Sum) -> Expr {
+    Expr { location: None, sum: Some(sum) }
+}
+
+fn lf_lit(sum: builtin_lit::Sum) -> Expr {
+    lf_expr(expr::Sum::BuiltinLit(BuiltinLit { sum: Some(sum) }))
+}
+
+/// The `index`th argument of a built-in type, e.g. the element type of a list
+fn builtin_arg(typ: Option<&Type>, index: usize) -> Option<&Type> {
+    match typ?.sum.as_ref()? {
+        r#type::Sum::Builtin(builtin) => builtin.args.get(index),
+        _ => None,
+    }
+}
+
+/// `insert(key, value, map)` applications of the `TEXTMAP_INSERT` or `GENMAP_INSERT` built-in on the
+/// empty map, Daml-LF having no map literals
+fn lf_map(empty: BuiltinFunction, insert: BuiltinFunction, entries: Vec<(Expr, Expr)>) -> Expr {
+    entries.into_iter().fold(lf_expr(expr::Sum::Builtin(empty as i32)), |map, (key, value)| {
+        lf_expr(expr::Sum::App(Box::new(expr::App {
+            fun: Some(Box::new(lf_expr(expr::Sum::Builtin(insert as i32)))),
+            args: vec![key, value, map],
+        })))
+    })
+}
+
+/// Converts an API Value to a lf_protobuf Expr. Parties and contract ids become text literals, as
+/// Daml-LF has no literals for them.
 fn api_value_to_lf_expr(
     api_value: Option<&ApiValue>,
     field_type: Option<&Type>,
-    string_to_interned: &HashMap<String, i32>,
+    interned_strings: &mut InternedStrings,
 ) -> Option<Expr> {
-    match api_value {
-        Some(val) => {
-            match &val.sum {
-                Some(ledger_api::v2::value::Sum::Text(s)) => {
-                    let idx = string_to_interned.get(s).cloned().unwrap_or(0);
-                    Some(Expr {
-                        location: None,
-                        sum: Some(expr::Sum::BuiltinLit(BuiltinLit {
-                            sum: Some(builtin_lit::Sum::TextInternedStr(idx)),
-                        })),
-                    })
-                }
-                Some(ledger_api::v2::value::Sum::Int64(i)) => {
-                    Some(Expr {
-                        location: None,
-                        sum: Some(expr::Sum::BuiltinLit(BuiltinLit {
-                            sum: Some(builtin_lit::Sum::Int64(*i)),
-                        })),
-                    })
-                }
-                Some(ledger_api::v2::value::Sum::Bool(b)) => {
-                    let con = if *b { daml_lf_2::BuiltinCon::ConTrue as i32 } else { daml_lf_2::BuiltinCon::ConFalse as i32 };
-                    Some(Expr {
-                        location: None,
-                        sum: Some(expr::Sum::BuiltinCon(con)),
-                    })
-                }
-                Some(ledger_api::v2::value::Sum::Numeric(n)) => {
-                    let idx = string_to_interned.get(n).cloned().unwrap_or(0);
-                    Some(Expr {
-                        location: None,
-                        sum: Some(expr::Sum::BuiltinLit(BuiltinLit {
-                            sum: Some(builtin_lit::Sum::NumericInternedStr(idx)),
-                        })),
-                    })
-                }
-                Some(ledger_api::v2::value::Sum::Party(p)) => {
-                    let idx = string_to_interned.get(p).cloned().unwrap_or(0);
-                    Some(Expr {
-                        location: None,
-                        sum: Some(expr::Sum::BuiltinLit(BuiltinLit {
-                            sum: Some(builtin_lit::Sum::TextInternedStr(idx)),
-                        })),
-                    })
-                }
-                Some(ledger_api::v2::value::Sum::ContractId(cid)) => {
-                    let idx = string_to_interned.get(cid).cloned().unwrap_or(0);
-                    Some(Expr {
-                        location: None,
-                        sum: Some(expr::Sum::BuiltinLit(BuiltinLit {
-                            sum: Some(builtin_lit::Sum::TextInternedStr(idx)),
-                        })),
-                    })
-                }
-                Some(ledger_api::v2::value::Sum::Record(rec)) => {
-                    // Recursively convert fields
-                    let fields = rec.fields.iter().map(|f| {
-                        let idx = string_to_interned.get(&f.label).cloned().unwrap_or(0);
-                        FieldWithExpr {
-                            field_interned_str: idx,
-                            expr: api_value_to_lf_expr(f.value.as_ref(), None, string_to_interned),
-                        }
-                    }).collect();
-                    Some(Expr {
-                        location: None,
-                        sum: Some(expr::Sum::RecCon(expr::RecCon {
-                            tycon: None,
-                            fields,
-                        })),
-                    })
-                }
-                Some(ledger_api::v2::value::Sum::Optional(opt)) => {
-                    match &opt.value {
-                        Some(inner) => {
-                            Some(Expr {
-                                location: None,
-                                sum: Some(expr::Sum::OptionalSome(Box::new(expr::OptionalSome {
-                                    r#type: field_type.cloned(),
-                                    value: api_value_to_lf_expr(Some(inner), field_type, string_to_interned).map(Box::new),
-                                }))),
-                            })
-                        }
-                        None => {
-                            Some(Expr {
-                                location: None,
-                                sum: Some(expr::Sum::OptionalNone(expr::OptionalNone {
-                                    r#type: field_type.cloned(),
-                                })),
-                            })
-                        }
-                    }
-                }
-                Some(ledger_api::v2::value::Sum::List(list)) => {
-                    let elements: Vec<Expr> = list.elements.iter()
-                        .filter_map(|v| api_value_to_lf_expr(Some(v), field_type, string_to_interned))
-                        .collect();
-                    Some(Expr {
-                        location: None,
-                        sum: Some(expr::Sum::Cons(Box::new(expr::Cons {
-                            r#type: field_type.cloned(),
-                            front: elements,
-                            tail: None,
-                        }))),
-                    })
-                }
-                // Add handling for TextMap, GenMap, Variant, Enum, etc. as needed
-                _ => None,
+    let expr = match api_value?.sum.as_ref()? {
+        ApiSum::Unit(_) => lf_expr(expr::Sum::BuiltinCon(daml_lf_2::BuiltinCon::ConUnit as i32)),
+        ApiSum::Text(s) | ApiSum::Party(s) | ApiSum::ContractId(s) => {
+            lf_lit(builtin_lit::Sum::TextInternedStr(interned_strings.intern(s)))
+        }
+        ApiSum::Int64(i) => lf_lit(builtin_lit::Sum::Int64(*i)),
+        ApiSum::Bool(b) => {
+            let con = if *b { daml_lf_2::BuiltinCon::ConTrue as i32 } else { daml_lf_2::BuiltinCon::ConFalse as i32 };
+            lf_expr(expr::Sum::BuiltinCon(con))
+        }
+        ApiSum::Numeric(n) => lf_lit(builtin_lit::Sum::NumericInternedStr(interned_strings.intern(n))),
+        ApiSum::Date(days) => lf_lit(builtin_lit::Sum::Date(*days)),
+        ApiSum::Timestamp(micros) => lf_lit(builtin_lit::Sum::Timestamp(*micros)),
+        ApiSum::Record(rec) => lf_expr(expr::Sum::RecCon(expr::RecCon {
+            tycon: None,
+            fields: api_record_to_lf_record(rec, &[], interned_strings),
+        })),
+        ApiSum::Optional(opt) => {
+            let element_type = builtin_arg(field_type, 0);
+            match &opt.value {
+                Some(inner) => lf_expr(expr::Sum::OptionalSome(Box::new(expr::OptionalSome {
+                    r#type: element_type.cloned(),
+                    value: api_value_to_lf_expr(Some(inner), element_type, interned_strings).map(Box::new),
+                }))),
+                None => lf_expr(expr::Sum::OptionalNone(expr::OptionalNone { r#type: element_type.cloned() })),
             }
         }
-        None => None,
-    }
+        ApiSum::List(list) => {
+            let element_type = builtin_arg(field_type, 0);
+            let elements: Vec<Expr> = list.elements.iter()
+                .filter_map(|v| api_value_to_lf_expr(Some(v), element_type, interned_strings))
+                .collect();
+            if elements.is_empty() {
+                lf_expr(expr::Sum::Nil(expr::Nil { r#type: element_type.cloned() }))
+            } else {
+                lf_expr(expr::Sum::Cons(Box::new(expr::Cons {
+                    r#type: element_type.cloned(),
+                    front: elements,
+                    tail: None,
+                })))
+            }
+        }
+        ApiSum::TextMap(map) => {
+            let value_type = builtin_arg(field_type, 0);
+            let entries = map.entries.iter().filter_map(|entry| {
+                let key = lf_lit(builtin_lit::Sum::TextInternedStr(interned_strings.intern(&entry.key)));
+                Some((key, api_value_to_lf_expr(entry.value.as_ref(), value_type, interned_strings)?))
+            }).collect();
+            lf_map(BuiltinFunction::TextmapEmpty, BuiltinFunction::TextmapInsert, entries)
+        }
+        ApiSum::GenMap(map) => {
+            let (key_type, value_type) = (builtin_arg(field_type, 0), builtin_arg(field_type, 1));
+            let entries = map.entries.iter().filter_map(|entry| {
+                let key = api_value_to_lf_expr(entry.key.as_ref(), key_type, interned_strings)?;
+                Some((key, api_value_to_lf_expr(entry.value.as_ref(), value_type, interned_strings)?))
+            }).collect();
+            lf_map(BuiltinFunction::GenmapEmpty, BuiltinFunction::GenmapInsert, entries)
+        }
+        ApiSum::Variant(variant) => lf_expr(expr::Sum::VariantCon(Box::new(expr::VariantCon {
+            tycon: None,
+            variant_con_interned_str: interned_strings.intern(&variant.constructor),
+            variant_arg: api_value_to_lf_expr(variant.value.as_deref(), None, interned_strings).map(Box::new),
+        }))),
+        ApiSum::Enum(enum_value) => lf_expr(expr::Sum::EnumCon(expr::EnumCon {
+            tycon: None,
+            enum_con_interned_str: interned_strings.intern(&enum_value.constructor),
+        })),
+    };
+    Some(expr)
 }
 
 /// Converts a lf_protobuf Record (Vec<FieldWithExpr>) to an API Record
@@ -144,59 +179,172 @@ pub fn lf_record_to_api_record(
         record_id: None,
         fields: lf_proto_fields.iter().map(|field| {
             ApiRecordField {
-                label: interned_strings.get(field.field_interned_str as usize).cloned().unwrap_or_default(),
+                label: interned_string(interned_strings, field.field_interned_str),
                 value: field.expr.as_ref().map(|e| lf_expr_to_api_value(e, interned_strings)),
             }
         }).collect(),
     }
 }
 
-/// Converts a lf_protobuf Expr to an API Value
+fn interned_string(interned_strings: &[String], index: i32) -> String {
+    interned_strings.get(index as usize).cloned().unwrap_or_default()
+}
+
+fn api_value(sum: ApiSum) -> ApiValue {
+    ApiValue { sum: Some(sum) }
+}
+
+/// Converts a lf_protobuf Expr to an API Value. Text literals become `Text`, also those encoded from
+/// parties and contract ids.
 fn lf_expr_to_api_value(expr: &Expr, interned_strings: &[String]) -> ApiValue {
     match &expr.sum {
         Some(expr::Sum::BuiltinLit(lit)) => {
             match &lit.sum {
-                Some(builtin_lit::Sum::Int64(i)) => ApiValue { sum: Some(ledger_api::v2::value::Sum::Int64(*i)) },
-                Some(builtin_lit::Sum::TextInternedStr(idx)) => {
-                    let s = interned_strings.get(*idx as usize).cloned().unwrap_or_default();
-                    ApiValue { sum: Some(ledger_api::v2::value::Sum::Text(s)) }
-                }
+                Some(builtin_lit::Sum::Int64(i)) => api_value(ApiSum::Int64(*i)),
+                Some(builtin_lit::Sum::TextInternedStr(idx)) => api_value(ApiSum::Text(interned_string(interned_strings, *idx))),
                 Some(builtin_lit::Sum::NumericInternedStr(idx)) => {
-                    let n = interned_strings.get(*idx as usize).cloned().unwrap_or_default();
-                    ApiValue { sum: Some(ledger_api::v2::value::Sum::Numeric(n)) }
+                    api_value(ApiSum::Numeric(interned_string(interned_strings, *idx)))
                 }
+                Some(builtin_lit::Sum::Date(days)) => api_value(ApiSum::Date(*days)),
+                Some(builtin_lit::Sum::Timestamp(micros)) => api_value(ApiSum::Timestamp(*micros)),
                 _ => ApiValue { sum: None },
             }
         }
         Some(expr::Sum::BuiltinCon(con)) => {
             match *con {
-                x if x == daml_lf_2::BuiltinCon::ConTrue as i32 => ApiValue { sum: Some(ledger_api::v2::value::Sum::Bool(true)) },
-                x if x == daml_lf_2::BuiltinCon::ConFalse as i32 => ApiValue { sum: Some(ledger_api::v2::value::Sum::Bool(false)) },
+                x if x == daml_lf_2::BuiltinCon::ConUnit as i32 => api_value(ApiSum::Unit(())),
+                x if x == daml_lf_2::BuiltinCon::ConTrue as i32 => api_value(ApiSum::Bool(true)),
+                x if x == daml_lf_2::BuiltinCon::ConFalse as i32 => api_value(ApiSum::Bool(false)),
                 _ => ApiValue { sum: None },
             }
         }
-        Some(expr::Sum::RecCon(rec_con)) => {
-            let fields = rec_con.fields.iter().map(|f| {
-                ApiRecordField {
-                    label: interned_strings.get(f.field_interned_str as usize).cloned().unwrap_or_default(),
-                    value: f.expr.as_ref().map(|e| lf_expr_to_api_value(e, interned_strings)),
-                }
-            }).collect();
-            ApiValue { sum: Some(ledger_api::v2::value::Sum::Record(ApiRecord { record_id: None, fields })) }
-        }
+        Some(expr::Sum::RecCon(rec_con)) => api_value(ApiSum::Record(lf_record_to_api_record(&rec_con.fields, interned_strings))),
         Some(expr::Sum::OptionalSome(opt_some)) => {
             let value = opt_some.value.as_ref().map(|e| Box::new(lf_expr_to_api_value(e, interned_strings)));
-            ApiValue { sum: Some(ledger_api::v2::value::Sum::Optional(Box::new(ledger_api::v2::Optional { value }))) }
-        }
-        Some(expr::Sum::OptionalNone(_)) => {
-            ApiValue { sum: Some(ledger_api::v2::value::Sum::Optional(Box::new(ledger_api::v2::Optional { value: None }))) }
+            api_value(ApiSum::Optional(Box::new(ApiOptional { value })))
         }
+        Some(expr::Sum::OptionalNone(_)) => api_value(ApiSum::Optional(Box::new(ApiOptional { value: None }))),
+        Some(expr::Sum::Nil(_)) => api_value(ApiSum::List(ApiList { elements: vec![] })),
         Some(expr::Sum::Cons(cons)) => {
             let elements = cons.front.iter().map(|e| lf_expr_to_api_value(e, interned_strings)).collect();
-            ApiValue { sum: Some(ledger_api::v2::value::Sum::List(ledger_api::v2::List { elements })) }
+            api_value(ApiSum::List(ApiList { elements }))
+        }
+        Some(expr::Sum::Builtin(builtin)) if *builtin == BuiltinFunction::TextmapEmpty as i32 => {
+            api_value(ApiSum::TextMap(ApiTextMap { entries: vec![] }))
         }
-        // Add handling for TextMap, GenMap, Variant, Enum, etc. as needed
+        Some(expr::Sum::Builtin(builtin)) if *builtin == BuiltinFunction::GenmapEmpty as i32 => {
+            api_value(ApiSum::GenMap(ApiGenMap { entries: vec![] }))
+        }
+        Some(expr::Sum::App(app)) => lf_map_insert_to_api_value(app, interned_strings).unwrap_or(ApiValue { sum: None }),
+        Some(expr::Sum::VariantCon(variant_con)) => api_value(ApiSum::Variant(Box::new(ApiVariant {
+            variant_id: None,
+            constructor: interned_string(interned_strings, variant_con.variant_con_interned_str),
+            value: variant_con.variant_arg.as_ref().map(|e| Box::new(lf_expr_to_api_value(e, interned_strings))),
+        }))),
+        Some(expr::Sum::EnumCon(enum_con)) => api_value(ApiSum::Enum(ApiEnum {
+            enum_id: None,
+            constructor: interned_string(interned_strings, enum_con.enum_con_interned_str),
+        })),
         _ => ApiValue { sum: None },
     }
 }
 
+/// The map of an `insert(key, value, map)` application of `TEXTMAP_INSERT` or `GENMAP_INSERT`, `None`
+/// for other applications
+fn lf_map_insert_to_api_value(app: &expr::App, interned_strings: &[String]) -> Option<ApiValue> {
+    let Some(expr::Sum::Builtin(builtin)) = app.fun.as_ref()?.sum else {
+        return None;
+    };
+    let [key, value, map] = app.args.as_slice() else {
+        return None;
+    };
+    let value = Some(lf_expr_to_api_value(value, interned_strings));
+    match (BuiltinFunction::try_from(builtin).ok()?, lf_expr_to_api_value(map, interned_strings).sum?) {
+        (BuiltinFunction::TextmapInsert, ApiSum::TextMap(mut map)) => {
+            let Some(ApiSum::Text(key)) = lf_expr_to_api_value(key, interned_strings).sum else {
+                return None;
+            };
+            map.entries.push(text_map::Entry { key, value });
+            Some(api_value(ApiSum::TextMap(map)))
+        }
+        (BuiltinFunction::GenmapInsert, ApiSum::GenMap(mut map)) => {
+            let key = Some(lf_expr_to_api_value(key, interned_strings));
+            map.entries.push(gen_map::Entry { key, value });
+            Some(api_value(ApiSum::GenMap(map)))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn field(label: &str, sum: ApiSum) -> ApiRecordField {
+        ApiRecordField { label: label.to_string(), value: Some(api_value(sum)) }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let text = |s: &str| api_value(ApiSum::Text(s.to_string()));
+        let record = ApiRecord {
+            record_id: None,
+            fields: vec![
+                field("unit", ApiSum::Unit(())),
+                field("bool", ApiSum::Bool(true)),
+                field("int", ApiSum::Int64(-3)),
+                field("numeric", ApiSum::Numeric("1.5000000000".to_string())),
+                field("date", ApiSum::Date(19_000)),
+                field("timestamp", ApiSum::Timestamp(1_700_000_000_000_000)),
+                field("none", ApiSum::Optional(Box::new(ApiOptional { value: None }))),
+                field("some", ApiSum::Optional(Box::new(ApiOptional { value: Some(Box::new(text("a"))) }))),
+                field("empty", ApiSum::List(ApiList { elements: vec![] })),
+                field("list", ApiSum::List(ApiList { elements: vec![text("a"), text("b")] })),
+                field(
+                    "textMap",
+                    ApiSum::TextMap(ApiTextMap {
+                        entries: vec![
+                            text_map::Entry { key: "x".to_string(), value: Some(api_value(ApiSum::Int64(1))) },
+                            text_map::Entry { key: "y".to_string(), value: Some(api_value(ApiSum::Int64(2))) },
+                        ],
+                    }),
+                ),
+                field("emptyMap", ApiSum::GenMap(ApiGenMap { entries: vec![] })),
+                field(
+                    "genMap",
+                    ApiSum::GenMap(ApiGenMap {
+                        entries: vec![gen_map::Entry { key: Some(api_value(ApiSum::Int64(1))), value: Some(text("one")) }],
+                    }),
+                ),
+                field(
+                    "variant",
+                    ApiSum::Variant(Box::new(ApiVariant {
+                        variant_id: None,
+                        constructor: "Circle".to_string(),
+                        value: Some(Box::new(api_value(ApiSum::Record(ApiRecord {
+                            record_id: None,
+                            fields: vec![field("radius", ApiSum::Int64(2))],
+                        })))),
+                    })),
+                ),
+                field("enum", ApiSum::Enum(ApiEnum { enum_id: None, constructor: "Green".to_string() })),
+            ],
+        };
+
+        // The field labels are interned, the other strings are added when encoding
+        let labels: Vec<String> = record.fields.iter().map(|field| field.label.clone()).collect();
+        let field_types: Vec<FieldWithType> = (0..labels.len() as i32)
+            .map(|field_interned_str| FieldWithType { field_interned_str, r#type: None })
+            .collect();
+        let mut interned_strings = InternedStrings::new(labels.clone());
+        let lf_record = api_record_to_lf_record(&record, &field_types, &mut interned_strings);
+        assert!(interned_strings.strings().len() > labels.len());
+        assert_eq!(lf_record_to_api_record(&lf_record, interned_strings.strings()), record);
+
+        // Parties are text literals in Daml-LF
+        let party = ApiRecord { record_id: None, fields: vec![field("owner", ApiSum::Party("Alice".to_string()))] };
+        let lf_record = api_record_to_lf_record(&party, &[], &mut interned_strings);
+        let decoded = lf_record_to_api_record(&lf_record, interned_strings.strings());
+        assert_eq!(decoded.fields[0], field("owner", ApiSum::Text("Alice".to_string())));
+    }
+}
@@ -6,35 +6,61 @@ use anyhow::{Context, Result};
 use crate::lf_protobuf::com::daml::daml_lf_dev::Archive;
 
 pub fn archive_from_dar(dar_path: &str) -> Result<Archive> {
+    let mut archive = open_dar(dar_path)?;
+    let main_dalf = main_dalf(&mut archive)?;
+    read_dalf(&mut archive, &main_dalf)
+}
+
+/// Reads the archives of all packages of a DAR, the main package first followed by its dependencies.
+pub fn archives_from_dar(dar_path: &str) -> Result<Vec<Archive>> {
+    let mut archive = open_dar(dar_path)?;
+    let main_dalf = main_dalf(&mut archive)?;
+    let mut dependency_dalfs: Vec<String> = archive
+        .file_names()
+        .filter(|name| name.ends_with(".dalf") && *name != main_dalf)
+        .map(String::from)
+        .collect();
+    dependency_dalfs.sort();
+
+    let mut archives = vec![read_dalf(&mut archive, &main_dalf)?];
+    for dalf in &dependency_dalfs {
+        archives.push(read_dalf(&mut archive, dalf)?);
+    }
+    Ok(archives)
+}
+
+fn open_dar(dar_path: &str) -> Result<ZipArchive<Cursor<Vec<u8>>>> {
     let mut file = File::open(dar_path)
         .with_context(|| format!("Failed to open DAR file '{}'", dar_path))?;
     let mut buf = Vec::new();
     file.read_to_end(&mut buf)
         .with_context(|| format!("Failed to read DAR file '{}'", dar_path))?;
 
-    let mut archive = ZipArchive::new(Cursor::new(buf))
-        .with_context(|| format!("Failed to open zip archive '{}'", dar_path))?;
+    ZipArchive::new(Cursor::new(buf))
+        .with_context(|| format!("Failed to open zip archive '{}'", dar_path))
+}
 
-    // Parse META-INF/MANIFEST.MF to find Main-Dalf
-    let main_dalf = {
-        let mut manifest = archive.by_name("META-INF/MANIFEST.MF")
-            .with_context(|| "Failed to find META-INF/MANIFEST.MF in archive")?;
-        let mut manifest_str = String::new();
-        manifest.read_to_string(&mut manifest_str)
-            .with_context(|| "Failed to read META-INF/MANIFEST.MF")?;
+/// Parses META-INF/MANIFEST.MF to find Main-Dalf
+fn main_dalf(archive: &mut ZipArchive<Cursor<Vec<u8>>>) -> Result<String> {
+    let mut manifest = archive.by_name("META-INF/MANIFEST.MF")
+        .with_context(|| "Failed to find META-INF/MANIFEST.MF in archive")?;
+    let mut manifest_str = String::new();
+    manifest.read_to_string(&mut manifest_str)
+        .with_context(|| "Failed to read META-INF/MANIFEST.MF")?;
 
-        parse_manifest_main_dalf(&manifest_str)
-            .context("Main-Dalf not found in MANIFEST.MF")?
-    };
+    parse_manifest_main_dalf(&manifest_str)
+        .context("Main-Dalf not found in MANIFEST.MF")
+}
 
-    let mut dalf_file = archive.by_name(&main_dalf)
-        .with_context(|| format!("Failed to find DALF file '{}' in archive", main_dalf))?;
+fn read_dalf(archive: &mut ZipArchive<Cursor<Vec<u8>>>, dalf: &str) -> Result<Archive> {
+    let mut dalf_file = archive.by_name(dalf)
+        .with_context(|| format!("Failed to find DALF file '{}' in archive", dalf))?;
     let mut dalf_bytes = Vec::new();
     dalf_file.read_to_end(&mut dalf_bytes)
-        .with_context(|| format!("Failed to read DALF file '{}'", main_dalf))?;
+        .with_context(|| format!("Failed to read DALF file '{}'", dalf))?;
 
     Archive::decode(&*dalf_bytes)
-        .with_context(|| format!("Failed to decode Archive from '{}'", main_dalf))
+        .with_context(|| format!("Failed to decode Archive from '{}'", dalf))
 }
 
 fn parse_manifest_main_dalf(manifest_str: &str) -> Option<String> {
@@ -0,0 +1,172 @@
+//! Generating the Rust bindings of DARs from the `build.rs` of the crate using them
+pub use crate::codegen::package_code::CodegenOptions;
+use crate::codegen::package_code::{dar_code_with_options, dars_code, name_and_version};
+use crate::manifest::{dar_manifest, dars_manifests};
+use crate::package::packages_from_dar;
+use crate::registry::{dars_registry, registry_json};
+use anyhow::{Context, Result, bail};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// Generates the bindings of each DAR into `<out_dir>/<package_name>.rs`, as
+/// `generate_rust_code_from_dar` does, and tells cargo to rerun the build script when a DAR
+/// changes. Files are only written if their content changed. Returns the generated files in the
+/// order of `dar_paths`.
+///
+/// In `build.rs`:
+/// ```ignore
+/// codegen::build::generate(&["../daml/.daml/dist/daml-asset-0.0.1.dar"], std::env::var("OUT_DIR")?)?;
+/// ```
+/// and in the crate, `-` in the package name being replaced by `_`:
+/// ```ignore
+/// include!(concat!(env!("OUT_DIR"), "/daml_asset.rs"));
+/// ```
+pub fn generate<P: AsRef<Path>>(dar_paths: &[P], out_dir: impl AsRef<Path>) -> Result<Vec<PathBuf>> {
+    generate_with_options(dar_paths, out_dir, &CodegenOptions::default())
+}
+
+/// `generate` with options, e.g. to also derive the JSON codec and serde traits:
+/// ```ignore
+/// let options = codegen::build::CodegenOptions { json: true, ..Default::default() };
+/// codegen::build::generate_with_options(&[dar_path], std::env::var("OUT_DIR")?, &options)?;
+/// ```
+/// With `manifest`, `<out_dir>/manifest.json` is a list of the `Manifest`s of the DARs, and with
+/// `registry`, `<out_dir>/registry.json` their `TemplateRegistry`. With
+/// `combined`, the DARs are generated into `<out_dir>/daml.rs`, to be included with
+/// `include!(concat!(env!("OUT_DIR"), "/daml.rs"));`.
+pub fn generate_with_options<P: AsRef<Path>>(
+    dar_paths: &[P],
+    out_dir: impl AsRef<Path>,
+    options: &CodegenOptions,
+) -> Result<Vec<PathBuf>> {
+    for dar_path in dar_paths {
+        println!("cargo:rerun-if-changed={}", dar_path.as_ref().display());
+    }
+    write_files(&generated_files(dar_paths, options)?, out_dir)
+}
+
+/// The files `generate_with_options` writes, as file names with their content
+pub fn generated_files<P: AsRef<Path>>(dar_paths: &[P], options: &CodegenOptions) -> Result<Vec<(String, String)>> {
+    let dars = dar_paths
+        .iter()
+        .map(|dar_path| {
+            let dar_path = dar_path.as_ref();
+            let dar_path_str = dar_path.to_str().context("DAR path is not valid UTF-8")?;
+            packages_from_dar(dar_path_str)
+                .with_context(|| format!("Failed to read packages from '{}'", dar_path.display()))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let registry = if options.registry {
+        Some(("registry.json".to_string(), registry_json(&dars_registry(&dars)?)?))
+    } else {
+        None
+    };
+    if options.combined {
+        let mut files = vec![("daml.rs".to_string(), dars_code(&dars, options)?)];
+        if options.manifest {
+            let json = serde_json::to_string_pretty(&dars_manifests(&dars)?).context("Failed to serialize manifest")?;
+            files.push(("manifest.json".to_string(), json));
+        }
+        files.extend(registry);
+        return Ok(files);
+    }
+
+    let mut files = Vec::new();
+    let mut file_names = HashSet::new();
+    let mut manifests = Vec::new();
+    for (dar_path, packages) in dar_paths.iter().zip(&dars) {
+        let dar_path = dar_path.as_ref();
+        let (_, main_package) = packages.first().context("DAR without packages")?;
+        let file_name = format!("{}.rs", name_and_version(main_package)?.0.replace('-', "_"));
+        if !file_names.insert(file_name.clone()) {
+            bail!("Two DARs have the main package name of '{}'", dar_path.display());
+        }
+        files.push((file_name, dar_code_with_options(packages, options)?));
+        if options.manifest {
+            manifests.push(dar_manifest(packages)?);
+        }
+    }
+    if options.manifest {
+        let json = serde_json::to_string_pretty(&manifests).context("Failed to serialize manifest")?;
+        files.push(("manifest.json".to_string(), json));
+    }
+    files.extend(registry);
+    Ok(files)
+}
+
+/// Writes the files into `out_dir`, skipping the ones whose content did not change, and returns
+/// their paths
+pub fn write_files(files: &[(String, String)], out_dir: impl AsRef<Path>) -> Result<Vec<PathBuf>> {
+    let out_dir = out_dir.as_ref();
+    std::fs::create_dir_all(out_dir)
+        .with_context(|| format!("Failed to create output directory '{}'", out_dir.display()))?;
+    files
+        .iter()
+        .map(|(file_name, content)| {
+            let path = out_dir.join(file_name);
+            write_if_changed(&path, content)?;
+            Ok(path)
+        })
+        .collect()
+}
+
+/// Regenerates the files of the DARs in memory and returns the files of `out_dir` which are missing
+/// or differ, e.g. to verify in CI that committed bindings match the committed DARs
+pub fn check<P: AsRef<Path>>(dar_paths: &[P], out_dir: impl AsRef<Path>, options: &CodegenOptions) -> Result<Vec<PathBuf>> {
+    Ok(outdated_files(&generated_files(dar_paths, options)?, out_dir.as_ref()))
+}
+
+fn outdated_files(files: &[(String, String)], out_dir: &Path) -> Vec<PathBuf> {
+    files
+        .iter()
+        .map(|(file_name, content)| (out_dir.join(file_name), content))
+        .filter(|(path, content)| !std::fs::read_to_string(path).is_ok_and(|existing| existing == **content))
+        .map(|(path, _)| path)
+        .collect()
+}
+
+/// Writes the file unless it already has the content, keeping its modification time so that cargo
+/// does not recompile the including crate
+fn write_if_changed(path: &Path, content: &str) -> Result<()> {
+    if std::fs::read_to_string(path).is_ok_and(|existing| existing == content) {
+        return Ok(());
+    }
+    std::fs::write(path, content).with_context(|| format!("Failed to write output file '{}'", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_if_changed() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("codegen-build-{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        let path = dir.join("daml_asset.rs");
+        write_if_changed(&path, "pub mod main {}")?;
+        let modified = std::fs::metadata(&path)?.modified()?;
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        write_if_changed(&path, "pub mod main {}")?;
+        assert_eq!(std::fs::metadata(&path)?.modified()?, modified);
+        write_if_changed(&path, "pub mod asset {}")?;
+        assert_eq!(std::fs::read_to_string(&path)?, "pub mod asset {}");
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_outdated_files() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("codegen-check-{}", std::process::id()));
+        let files = vec![
+            ("daml_asset.rs".to_string(), "pub mod main {}".to_string()),
+            ("manifest.json".to_string(), "[]".to_string()),
+        ];
+        assert_eq!(outdated_files(&files, &dir), vec![dir.join("daml_asset.rs"), dir.join("manifest.json")]);
+        write_files(&files, &dir)?;
+        assert!(outdated_files(&files, &dir).is_empty());
+        std::fs::write(dir.join("daml_asset.rs"), "pub mod asset {}")?;
+        assert_eq!(outdated_files(&files, &dir), vec![dir.join("daml_asset.rs")]);
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
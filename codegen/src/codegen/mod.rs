@@ -1 +1,2 @@
-pub mod record_struct;
\ No newline at end of file
+pub mod record_struct;
+pub mod package_code;
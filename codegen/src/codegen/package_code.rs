@@ -0,0 +1,1541 @@
+use crate::codegen::record_struct::sanitize_ident;
+use crate::daml_custom_data_type_reps::enum_rep::DamlEnumRep;
+use crate::daml_custom_data_type_reps::record::DamlRecordRep;
+use crate::daml_custom_data_type_reps::template::{ChoiceRep, TemplateRep};
+use crate::daml_custom_data_type_reps::variant::DamlVariantRep;
+use crate::dar_inspect::{dotted_name, interned_string};
+use crate::lf_protobuf::com::daml::daml_lf_2::def_data_type::DataCons;
+use crate::lf_protobuf::com::daml::daml_lf_2::{BuiltinType, DefDataType, Module, Package, Type, r#type};
+use crate::resolve_type::{GeneratedPackages, PackageRef, STDLIB_PACKAGES, TypeRep, type_rep};
+use anyhow::{Context, Result, bail};
+use proc_macro2::{Ident, TokenStream};
+use quote::{format_ident, quote};
+use syn::ext::IdentExt;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Given a DAR file path, generates the Rust bindings of its main package and the dependencies
+/// it refers to, and writes them to a file.
+pub fn generate_rust_code_from_dar(dar_path: &str, output_path: &str) -> Result<()> {
+    let packages = crate::package::packages_from_dar(dar_path)
+        .with_context(|| format!("Failed to read packages from '{}'", dar_path))?;
+    let code = dar_code(&packages)?;
+    std::fs::write(output_path, code)
+        .with_context(|| format!("Failed to write output file '{}'", output_path))
+}
+
+/// Generates one Rust module per Daml module of the package, `Splice.Amulet` in `splice::amulet`,
+/// with a struct per record type and an enum per variant and enum type, deriving `LapiAccess` and
+/// implementing `DamlValue`.
+/// Template types also derive `ToCreateArguments` and `Template`, choice argument records `Choice`
+/// and come with an `exercise_<choice>` function. Interfaces become marker types deriving `Interface`.
+/// Data types which cannot be generated are listed with the reason in a comment at the top.
+pub fn package_code(package: &Package) -> Result<String> {
+    generate(&[("", package)], &CodegenOptions::default())
+}
+
+/// Generates the code of the main package of a DAR as `package_code` does, and the code of the
+/// dependencies whose data types it refers to in `deps::<package name>`. Data types of the standard
+/// library are mapped to `daml-type-rep` types where there is one.
+/// `packages` are the packages of the DAR with their package ids, the main package first.
+pub fn dar_code(packages: &[(String, Package)]) -> Result<String> {
+    dar_code_with_options(packages, &CodegenOptions::default())
+}
+
+/// Options of the generated code
+#[derive(Debug, Clone, Default)]
+pub struct CodegenOptions {
+    /// Also derive `JsonCodec` and serde `Serialize` and `Deserialize` on the generated data types,
+    /// for the HTTP JSON API and JSON fixtures. The crate including the code then depends on `serde`.
+    pub json: bool,
+    /// `codegen::build` also writes `manifest.json`, describing the templates and interfaces of the
+    /// DARs and the paths of their generated items, see `codegen::manifest`
+    pub manifest: bool,
+    /// `codegen::build` also writes `registry.json`, the `TemplateRegistry` of the DARs, see
+    /// `codegen::registry`
+    pub registry: bool,
+    /// `codegen::build` generates all DARs into one `daml.rs` with `dars_code`, sharing their
+    /// dependencies, instead of one file per DAR
+    pub combined: bool,
+}
+
+/// `dar_code` with options
+pub fn dar_code_with_options(packages: &[(String, Package)], options: &CodegenOptions) -> Result<String> {
+    let packages: Vec<(&str, &Package)> = packages.iter().map(|(id, package)| (id.as_str(), package)).collect();
+    generate(&packages, options)
+}
+
+/// Generates the code of several DARs, e.g. of an interface package and the packages implementing
+/// and using it, into one tree: the main package of each DAR in `<package name>`, and the other
+/// packages they refer to once in `deps::<package name>`. Packages are told apart by package id, so
+/// a DAR whose main package is a dependency of another DAR is referred to in `<package name>`.
+/// `dars` are the packages of each DAR with their package ids, the main package first.
+pub fn dars_code(dars: &[Vec<(String, Package)>], options: &CodegenOptions) -> Result<String> {
+    let (packages, main_packages) = combined_packages(dars)?;
+    generate_packages(&packages, &main_packages, options)
+}
+
+/// The packages of several DARs without duplicates, and their main packages with the Rust module
+/// named after the package
+#[allow(clippy::type_complexity)]
+pub(crate) fn combined_packages(
+    dars: &[Vec<(String, Package)>],
+) -> Result<(Vec<(&str, &Package)>, Vec<(&str, Option<String>)>)> {
+    let mut packages: Vec<(&str, &Package)> = Vec::new();
+    for (package_id, package) in dars.iter().flatten() {
+        if !packages.iter().any(|(id, _)| id == package_id) {
+            packages.push((package_id, package));
+        }
+    }
+    let mut main_packages: Vec<(&str, Option<String>)> = Vec::new();
+    for (package_id, package) in dars.iter().filter_map(|dar| dar.first()) {
+        if main_packages.iter().any(|(id, _)| id == package_id) {
+            continue;
+        }
+        let module_name = name_and_version(package)?.0.replace('-', "_");
+        if main_packages.iter().any(|(_, other)| other.as_ref() == Some(&module_name)) {
+            bail!("Two DARs have the main package name {}", name_and_version(package)?.0);
+        }
+        main_packages.push((package_id, Some(module_name)));
+    }
+    if main_packages.is_empty() {
+        bail!("No DARs to generate code for");
+    }
+    Ok((packages, main_packages))
+}
+
+fn generate(packages: &[(&str, &Package)], options: &CodegenOptions) -> Result<String> {
+    let (main_package_id, _) = *packages.first().context("No packages to generate code for")?;
+    generate_packages(packages, &[(main_package_id, None)], options)
+}
+
+/// Generates the code of the main packages, in their Rust module or at the root, and of the
+/// dependencies they refer to
+fn generate_packages(
+    packages: &[(&str, &Package)],
+    main_packages: &[(&str, Option<String>)],
+    options: &CodegenOptions,
+) -> Result<String> {
+    let packages_by_id: HashMap<&str, &Package> = packages.iter().copied().collect();
+    let generated = generated_packages(packages, main_packages)?;
+    let mut dependency_ids: Vec<&String> = generated.dependencies.keys().collect();
+    dependency_ids.sort();
+
+    let mut skipped = Vec::new();
+    let mut modules = Vec::new();
+    let mut descriptions = Vec::new();
+    for (package_id, module_name) in main_packages {
+        let package = packages_by_id.get(package_id).context("Main package not found")?;
+        let (package_name, package_version) = name_and_version(package)?;
+        descriptions.push(format!("{} {}", package_name, package_version));
+        match module_name {
+            None => modules.extend(package_modules(package_id, package, &generated, options, &mut skipped)?),
+            Some(module_name) => {
+                let mut package_skipped = Vec::new();
+                let package_modules = package_modules(package_id, package, &generated, options, &mut package_skipped)?;
+                skipped.extend(package_skipped.into_iter().map(|reason| format!("{} {}", package_name, reason)));
+                let package_ident = sanitize_ident(module_name);
+                modules.push(quote! {
+                    pub mod #package_ident {
+                        #( #package_modules )*
+                    }
+                });
+            }
+        }
+    }
+    let mut dependencies = Vec::new();
+    for package_id in dependency_ids {
+        let package = packages_by_id[package_id.as_str()];
+        let mut dependency_skipped = Vec::new();
+        let dependency_modules = package_modules(package_id, package, &generated, options, &mut dependency_skipped)?;
+        let package_name = generated.package_name(package_id);
+        skipped.extend(dependency_skipped.into_iter().map(|reason| format!("{} {}", package_name, reason)));
+        let package_ident = sanitize_ident(&generated.dependencies[package_id]);
+        dependencies.push(quote! {
+            pub mod #package_ident {
+                #( #dependency_modules )*
+            }
+        });
+    }
+    let deps = (!dependencies.is_empty()).then(|| {
+        quote! {
+            pub mod deps {
+                #( #dependencies )*
+            }
+        }
+    });
+
+    let file: syn::File = syn::parse2(quote! { #( #modules )* #deps })
+        .with_context(|| "Failed to parse generated code")?;
+    let mut code = format!(
+        "// Generated from the Daml {} {}, do not edit.\n",
+        if descriptions.len() == 1 { "package" } else { "packages" },
+        descriptions.join(", ")
+    );
+    if !skipped.is_empty() {
+        code.push_str("// Not generated:\n");
+        for reason in &skipped {
+            code.push_str(&format!("// - {}\n", reason));
+        }
+    }
+    code.push('\n');
+    code.push_str(&prettyplease::unparse(&file));
+    Ok(code)
+}
+
+/// The packages with generated code: the main packages with their Rust module, `None` for the main
+/// package of a single DAR at the root, and the dependencies whose data types they refer to,
+/// directly or through other dependencies
+pub(crate) fn generated_packages(
+    packages: &[(&str, &Package)],
+    main_packages: &[(&str, Option<String>)],
+) -> Result<GeneratedPackages> {
+    let packages_by_id: HashMap<&str, &Package> = packages.iter().copied().collect();
+    let mut generated = GeneratedPackages {
+        main_packages: main_packages
+            .iter()
+            .map(|(package_id, module_name)| (package_id.to_string(), module_name.clone()))
+            .collect(),
+        package_names: packages
+            .iter()
+            .map(|(id, package)| Ok((id.to_string(), name_and_version(package)?.0)))
+            .collect::<Result<_>>()?,
+        dependencies: HashMap::new(),
+    };
+
+    // Dependencies are generated if the types of a generated package refer to them
+    let mut dependency_ids = BTreeMap::new();
+    let mut pending: Vec<&str> = main_packages.iter().map(|(package_id, _)| *package_id).collect();
+    while let Some(package_id) = pending.pop() {
+        let Some(package) = packages_by_id.get(package_id) else {
+            continue;
+        };
+        for referenced in referenced_packages(package) {
+            let Some((referenced_id, _)) = packages.iter().find(|(id, _)| *id == referenced) else {
+                continue;
+            };
+            let name = generated.package_name(referenced_id).to_string();
+            if !generated.main_packages.contains_key(*referenced_id)
+                && !STDLIB_PACKAGES.contains(&name.as_str())
+                && !dependency_ids.contains_key(*referenced_id)
+            {
+                dependency_ids.insert(referenced_id.to_string(), name);
+                pending.push(referenced_id);
+            }
+        }
+    }
+    // Versions of the same package are told apart by a package id prefix
+    for (package_id, name) in &dependency_ids {
+        let duplicate = dependency_ids.values().filter(|other| *other == name).count() > 1;
+        let module_name = if duplicate {
+            format!("{}_{}", name, &package_id[..package_id.len().min(8)])
+        } else {
+            name.clone()
+        };
+        generated.dependencies.insert(package_id.clone(), module_name.replace('-', "_"));
+    }
+    Ok(generated)
+}
+
+pub(crate) fn name_and_version(package: &Package) -> Result<(String, String)> {
+    let metadata = package
+        .metadata
+        .as_ref()
+        .context("Package has no metadata, templates cannot be referenced by package name")?;
+    Ok((
+        interned_string(package, metadata.name_interned_str)?,
+        interned_string(package, metadata.version_interned_str)?,
+    ))
+}
+
+/// The ids of the packages referred to by the data types, choices and interfaces of a package
+fn referenced_packages(package: &Package) -> HashSet<String> {
+    let mut package_ids = HashSet::new();
+    let mut add = |typ: Option<&Type>| {
+        if let Some(Ok(type_rep)) = typ.map(|typ| type_rep(typ, package)) {
+            type_rep.referenced_packages(&mut package_ids);
+        }
+    };
+    for module in &package.modules {
+        for def_data_type in &module.data_types {
+            match &def_data_type.data_cons {
+                Some(DataCons::Record(fields)) | Some(DataCons::Variant(fields)) => {
+                    fields.fields.iter().for_each(|field| add(field.r#type.as_ref()));
+                }
+                _ => {}
+            }
+        }
+        for choice in module.templates.iter().flat_map(|template| &template.choices) {
+            add(choice.ret_type.as_ref());
+        }
+        // The interfaces implemented by templates, to exercise their choices
+        for implements in module.templates.iter().flat_map(|template| &template.implements) {
+            let interface = Type {
+                sum: Some(r#type::Sum::Con(r#type::Con { tycon: implements.interface, args: vec![] })),
+            };
+            add(Some(&interface));
+        }
+        for interface in &module.interfaces {
+            interface.choices.iter().for_each(|choice| add(choice.ret_type.as_ref()));
+            add(interface.view.as_ref());
+        }
+    }
+    package_ids
+}
+
+/// The module being generated, to find the Rust types of Daml types
+struct ModuleContext<'a> {
+    package: &'a Package,
+    package_id: &'a str,
+    generated: &'a GeneratedPackages,
+    options: &'a CodegenOptions,
+    module: &'a Module,
+    module_name: String,
+}
+
+impl ModuleContext<'_> {
+    /// The derives of every generated data type, with `Default` if all its fields are defaultable
+    fn value_derives(&self, default: bool) -> TokenStream {
+        let default = default.then(|| quote! { Default, });
+        if self.options.json {
+            quote! { Clone, Debug, #default PartialEq, LapiAccess, JsonCodec, serde::Serialize, serde::Deserialize }
+        } else {
+            quote! { Clone, Debug, #default PartialEq, LapiAccess }
+        }
+    }
+
+    /// A `#[serde(...)]` attribute, if serde is derived
+    fn serde_attr(&self, args: TokenStream) -> Option<TokenStream> {
+        self.options.json.then(|| quote! { #[serde(#args)] })
+    }
+
+    /// The Rust type representing a Daml-LF type, see `TypeRep::rust_type`
+    fn rust_type(&self, typ: &Type) -> Result<TokenStream> {
+        type_rep(typ, self.package)?.rust_type(self.generated, self.package_id, &self.module_name)
+    }
+
+    /// The name of a type defined in this module, without type arguments
+    fn local_type_name(&self, typ: &Type) -> Result<Option<String>> {
+        match type_rep(typ, self.package)? {
+            TypeRep::Con { package: PackageRef::SelfPackage, module, name, args }
+                if module == self.module_name && args.is_empty() =>
+            {
+                Ok(Some(name))
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
+/// The Rust modules of the Daml modules of a package, adding the data types which cannot be
+/// generated to `skipped`
+fn package_modules(
+    package_id: &str,
+    package: &Package,
+    generated: &GeneratedPackages,
+    options: &CodegenOptions,
+    skipped: &mut Vec<String>,
+) -> Result<Vec<TokenStream>> {
+    let (package_name, package_version) = name_and_version(package)?;
+    let package_ref = format!("#{}", package_name);
+    let mut modules = ModuleTree::default();
+    // Dotted module names by Rust module path, to skip modules with the same Rust name
+    let mut module_paths = HashMap::new();
+    // The generated templates, with their module
+    let mut generated_templates = Vec::new();
+    for (module_name, module) in sorted_modules(package)? {
+        let ctx = ModuleContext {
+            package,
+            package_id,
+            generated,
+            options,
+            module,
+            module_name,
+        };
+        let module_name = &ctx.module_name;
+        let module_path = module_idents(module_name);
+        if let Some(other) = module_paths.insert(module_path.clone(), module_name.clone()) {
+            skipped.push(format!("{}: the Rust module name collides with the one of {}", module_name, other));
+            continue;
+        }
+        let type_idents = module
+            .data_types
+            .iter()
+            .map(|def_data_type| Ok(sanitize_ident(&dotted_name(package, def_data_type.name_interned_dname)?)))
+            .collect::<Result<HashSet<_>>>()?;
+        let templates = module
+            .templates
+            .iter()
+            .map(|template| dotted_name(package, template.tycon_interned_dname))
+            .collect::<Result<HashSet<_>>>()?;
+
+        // Choice argument records by name, with the template or interface and the Rust type of the
+        // result, and the Rust types of the contract keys by template name
+        let mut choices = HashMap::new();
+        let mut keys = HashMap::new();
+        let mut add_choice = |owner: &str, choice: &ChoiceRep, skipped: &mut Vec<String>| -> Result<()> {
+            let Some(arg_name) = choice
+                .arg_type
+                .as_ref()
+                .map(|arg_type| ctx.local_type_name(arg_type))
+                .transpose()?
+                .flatten()
+            else {
+                return Ok(());
+            };
+            let return_type = choice.return_type.as_ref().context("Choice without return type");
+            match return_type.and_then(|return_type| ctx.rust_type(return_type)) {
+                Ok(result) => {
+                    choices.insert(arg_name, (owner.to_string(), choice.clone(), result));
+                }
+                Err(e) => skipped.push(format!(
+                    "{}:{}:{} choice result: {}",
+                    module_name, owner, choice.choice_name, e
+                )),
+            }
+            Ok(())
+        };
+        for def_template in &module.templates {
+            let template = TemplateRep::try_from((def_template, module, package))?;
+            if let Some(key_type) = &template.key_type {
+                let key_ident = format_ident!("{}ContractKey", sanitize_ident(&template.record.record_name));
+                match ctx.rust_type(key_type) {
+                    Ok(_) if type_idents.contains(&key_ident) => skipped.push(format!(
+                        "{}:{} key: {} collides with a data type",
+                        module_name, template.record.record_name, key_ident
+                    )),
+                    Ok(key) => {
+                        keys.insert(template.record.record_name.clone(), key);
+                    }
+                    Err(e) => skipped.push(format!("{}:{} key: {}", module_name, template.record.record_name, e)),
+                }
+            }
+            for choice in &template.choices {
+                add_choice(&template.record.record_name, choice, skipped)?;
+            }
+        }
+
+        // Interface marker types, and the interfaces of the view records by name
+        let mut items = Vec::new();
+        let mut views = HashMap::new();
+        let interfaces = sorted_by_name(package, &module.interfaces, |interface| interface.tycon_interned_dname)?;
+        for (interface_name, def_interface) in interfaces {
+            items.push(interface_item(&interface_name, &package_ref, module_name));
+            for choice in &def_interface.choices {
+                add_choice(&interface_name, &ChoiceRep::try_from((choice, package))?, skipped)?;
+            }
+            let view_name = def_interface
+                .view
+                .as_ref()
+                .map(|view| ctx.local_type_name(view))
+                .transpose()?
+                .flatten();
+            match view_name {
+                Some(view_name) if views.contains_key(&view_name) => skipped.push(format!(
+                    "{}:{} view: {} is already the view of {}",
+                    module_name, interface_name, view_name, views[&view_name]
+                )),
+                Some(view_name) => {
+                    views.insert(view_name, interface_name);
+                }
+                None => skipped.push(format!(
+                    "{}:{} view: the view type is not a record of the module of the interface",
+                    module_name, interface_name
+                )),
+            }
+        }
+
+        let data_types = sorted_by_name(package, &module.data_types, |data_type| data_type.name_interned_dname)?;
+        for (name, def_data_type) in data_types {
+            let choice = choices.get(&name);
+            let mut roles = RecordRoles::default();
+            if templates.contains(&name) {
+                roles.derives.extend([quote! { ToCreateArguments }, quote! { Template }, quote! { FromCreatedEvent }]);
+                roles
+                    .attrs
+                    .push(quote! { #[template(package = #package_ref, module = #module_name, entity = #name)] });
+            }
+            if let Some(choice) = choice {
+                roles.derives.push(quote! { Choice });
+                roles.attrs.push(choice_attr(choice));
+            }
+            if let Some(interface_name) = views.get(&name) {
+                roles.derives.push(quote! { InterfaceView });
+                roles.attrs.push(
+                    quote! { #[interface(package = #package_ref, module = #module_name, entity = #interface_name)] },
+                );
+            }
+            match data_type_item(&ctx, def_data_type, roles) {
+                Ok(Some(item)) => {
+                    items.push(item);
+                    match builder_item(&ctx, def_data_type, &type_idents) {
+                        Ok(Some(builder)) => items.push(builder),
+                        Ok(None) => {}
+                        Err(e) => skipped.push(format!("{}:{} builder: {}", module_name, name, e)),
+                    }
+                    if templates.contains(&name) {
+                        items.push(package_version_item(&name, &package_version));
+                        generated_templates.push((module_name.clone(), name.clone()));
+                    }
+                    if let Some(key) = keys.get(&name) {
+                        items.push(key_item(&name, key));
+                    }
+                    if let Some(choice) = choice {
+                        items.push(exercise_fn(&name, choice));
+                        if keys.contains_key(&choice.0) {
+                            items.push(exercise_by_key_fn(&name, choice));
+                        }
+                    }
+                }
+                Ok(None) => {}
+                Err(e) => skipped.push(format!("{}:{}: {}", module_name, name, e)),
+            }
+        }
+
+        modules.insert(&module_path, quote! {
+            #![allow(unused_imports)]
+            use daml_type_rep::built_in_types::*;
+            use daml_type_rep::lapi_access::{LapiAccess, ToCreateArguments};
+            use derive_lapi_access::{
+                Choice, ContractKey, FromCreatedEvent, Interface, InterfaceView, JsonCodec, LapiAccess, Template,
+                ToCreateArguments,
+            };
+            use ledger_api::v2::Record;
+
+            #( #items )*
+        });
+    }
+    let mut items = modules.into_modules();
+    if !generated_templates.is_empty() {
+        items.push(any_template_item(&package_name, package_id, &generated_templates));
+    }
+    Ok(items)
+}
+
+/// `AnyTemplate` at the root of the code of a package, with a variant per template holding its
+/// decoded contract, and `from_created_event` decoding a created event of any of the templates
+fn any_template_item(package_name: &str, package_id: &str, templates: &[(String, String)]) -> TokenStream {
+    // Templates of the same name in different modules are told apart by the module name
+    let variant_ident = |module_name: &str, name: &str| {
+        if templates.iter().filter(|(_, other)| other == name).count() > 1 {
+            sanitize_ident(&format!("{}{}", module_name.replace('.', ""), name))
+        } else {
+            sanitize_ident(name)
+        }
+    };
+    let mut variants = Vec::new();
+    let mut arms = Vec::new();
+    for (module_name, name) in templates {
+        let variant = variant_ident(module_name, name);
+        let module_path = module_idents(module_name);
+        let template_ident = sanitize_ident(name);
+        let doc = format!(" `{}:{}`", module_name, name);
+        variants.push(quote! {
+            #[doc = #doc]
+            #variant(daml_type_rep::contract::Contract<#( #module_path:: )* #template_ident>)
+        });
+        arms.push(quote! {
+            (#module_name, #name) => {
+                <#( #module_path:: )* #template_ident as daml_type_rep::contract::FromCreatedEvent>::from_created_event(event)
+                    .map(AnyTemplate::#variant)
+            }
+        });
+    }
+    // Upgraded versions of the package have other package ids, but the same name
+    let other_package_id = (!package_id.is_empty()).then(|| quote! { && template_id.package_id != #package_id });
+    let doc = format!(" A contract of any template of the package `{}`", package_name);
+    quote! {
+        #[doc = #doc]
+        #[derive(Clone, Debug, PartialEq)]
+        pub enum AnyTemplate {
+            #( #variants, )*
+        }
+
+        impl AnyTemplate {
+            /// Decodes a created event of a template of the package, `None` for other templates or
+            /// if the create arguments do not match
+            pub fn from_created_event(event: &ledger_api::v2::CreatedEvent) -> Option<AnyTemplate> {
+                let template_id = event.template_id.as_ref()?;
+                if event.package_name != #package_name #other_package_id {
+                    return None;
+                }
+                match (template_id.module_name.as_str(), template_id.entity_name.as_str()) {
+                    #( #arms )*
+                    _ => None,
+                }
+            }
+        }
+    }
+}
+
+/// The Rust modules of the Daml modules of a package, nested like the dotted module names
+#[derive(Default)]
+struct ModuleTree {
+    /// The contents of the module, `None` for a module only containing other modules, e.g. `splice`
+    /// if there is a `Splice.Amulet` module but no `Splice` module
+    contents: Option<TokenStream>,
+    /// The nested modules in the order of the Daml modules
+    children: Vec<(Ident, ModuleTree)>,
+}
+
+impl ModuleTree {
+    fn insert(&mut self, path: &[Ident], contents: TokenStream) {
+        let Some((first, rest)) = path.split_first() else {
+            self.contents = Some(contents);
+            return;
+        };
+        let index = match self.children.iter().position(|(ident, _)| ident == first) {
+            Some(index) => index,
+            None => {
+                self.children.push((first.clone(), ModuleTree::default()));
+                self.children.len() - 1
+            }
+        };
+        self.children[index].1.insert(rest, contents);
+    }
+
+    /// The `pub mod` items of the nested modules
+    fn into_modules(self) -> Vec<TokenStream> {
+        self.children
+            .into_iter()
+            .map(|(ident, tree)| {
+                let contents = tree.contents.clone();
+                let children = tree.into_modules();
+                quote! {
+                    pub mod #ident {
+                        #contents
+                        #( #children )*
+                    }
+                }
+            })
+            .collect()
+    }
+}
+
+/// The modules of a package with their dotted names, by name, so that the generated code does not
+/// depend on the order of the modules in the archive
+pub(crate) fn sorted_modules(package: &Package) -> Result<Vec<(String, &Module)>> {
+    sorted_by_name(package, &package.modules, |module| module.name_interned_dname)
+}
+
+/// Definitions with their dotted names, by name
+pub(crate) fn sorted_by_name<'a, T>(
+    package: &Package,
+    definitions: &'a [T],
+    name_interned_dname: impl Fn(&T) -> i32,
+) -> Result<Vec<(String, &'a T)>> {
+    let mut named = definitions
+        .iter()
+        .map(|definition| Ok((dotted_name(package, name_interned_dname(definition))?, definition)))
+        .collect::<Result<Vec<_>>>()?;
+    named.sort_by(|(a, _), (b, _)| a.cmp(b));
+    Ok(named)
+}
+
+/// The derives and attributes of a record besides `LapiAccess`, for the records of templates,
+/// choice arguments and interface views
+#[derive(Default)]
+struct RecordRoles {
+    derives: Vec<TokenStream>,
+    attrs: Vec<TokenStream>,
+}
+
+/// The marker type of an interface, which its choices are defined on
+fn interface_item(interface_name: &str, package_ref: &str, module_name: &str) -> TokenStream {
+    let interface_ident = sanitize_ident(interface_name);
+    let doc = format!(" The Daml interface `{}`, exercise its choices with the `exercise_<choice>` functions", interface_name);
+    quote! {
+        #[doc = #doc]
+        #[derive(Clone, Copy, Debug, PartialEq, Interface)]
+        #[interface(package = #package_ref, module = #module_name, entity = #interface_name)]
+        pub struct #interface_ident;
+    }
+}
+
+/// `PACKAGE_VERSION` of a template, telling which version of an upgradable package the bindings
+/// were generated from
+fn package_version_item(template_name: &str, package_version: &str) -> TokenStream {
+    let template_ident = sanitize_ident(template_name);
+    quote! {
+        impl #template_ident {
+            /// The version of the Daml package the template was generated from
+            pub const PACKAGE_VERSION: &str = #package_version;
+        }
+    }
+}
+
+/// The `#[choice(...)]` attribute of a choice argument record
+fn choice_attr((template_name, choice, result): &(String, ChoiceRep, TokenStream)) -> TokenStream {
+    let template_ident = sanitize_ident(template_name);
+    let choice_name = &choice.choice_name;
+    let result = result.to_string();
+    let nonconsuming = (!choice.consuming).then(|| quote! { , nonconsuming });
+    quote! {
+        #[choice(on = #template_ident, name = #choice_name, result = #result #nonconsuming)]
+    }
+}
+
+/// `exercise_<choice>`, exercising the choice as a single party and decoding the result
+fn exercise_fn(arg_name: &str, (template_name, choice, result): &(String, ChoiceRep, TokenStream)) -> TokenStream {
+    let fn_ident = format_ident!("exercise_{}", camel_to_snake(&choice.choice_name));
+    let arg_ident = sanitize_ident(arg_name);
+    let doc = format!(
+        " Exercises `{}` on the `{}` contract `contract_id` as `actor` and decodes the choice result",
+        choice.choice_name, template_name
+    );
+    quote! {
+        #[doc = #doc]
+        pub async fn #fn_ident(
+            command_service_client: &mut ledger_api::v2::command_service_client::CommandServiceClient<tonic::transport::Channel>,
+            access_token: Option<&str>,
+            actor: &str,
+            contract_id: String,
+            arg: #arg_ident,
+        ) -> anyhow::Result<#result> {
+            submit::exercise_choice::exercise(
+                command_service_client,
+                access_token,
+                &submit::submit_command::SubmitOptions::act_as(vec![actor.to_string()]),
+                contract_id,
+                arg,
+                None,
+            )
+            .await
+        }
+    }
+}
+
+/// `<Template>ContractKey`, wrapping the Rust type of the contract key of a template
+fn key_item(template_name: &str, key: &TokenStream) -> TokenStream {
+    let template_ident = sanitize_ident(template_name);
+    let key_ident = format_ident!("{}ContractKey", template_ident);
+    let doc = format!(" The contract key of `{}`", template_name);
+    quote! {
+        #[doc = #doc]
+        #[derive(Clone, Debug, PartialEq, ContractKey)]
+        #[contract_key(template = #template_ident)]
+        pub struct #key_ident(pub #key);
+
+        impl LapiAccess for #key_ident {
+            fn to_lapi_value(&self) -> ledger_api::v2::Value {
+                self.0.to_lapi_value()
+            }
+
+            fn from_lapi_value(value: &ledger_api::v2::Value) -> Option<Self> {
+                Self::try_from_lapi_value(value).ok()
+            }
+
+            fn try_from_lapi_value(
+                value: &ledger_api::v2::Value,
+            ) -> Result<Self, daml_type_rep::decode_error::LapiDecodeError> {
+                <#key as LapiAccess>::try_from_lapi_value(value).map(#key_ident)
+            }
+        }
+    }
+}
+
+/// `exercise_<choice>_by_key`, exercising the choice on the contract with the given key
+fn exercise_by_key_fn(arg_name: &str, (template_name, choice, result): &(String, ChoiceRep, TokenStream)) -> TokenStream {
+    let fn_ident = format_ident!("exercise_{}_by_key", camel_to_snake(&choice.choice_name));
+    let arg_ident = sanitize_ident(arg_name);
+    let key_ident = format_ident!("{}ContractKey", sanitize_ident(template_name));
+    let doc = format!(
+        " Exercises `{}` on the `{}` contract with the key `key` as `actor` and decodes the choice result",
+        choice.choice_name, template_name
+    );
+    quote! {
+        #[doc = #doc]
+        pub async fn #fn_ident(
+            command_service_client: &mut ledger_api::v2::command_service_client::CommandServiceClient<tonic::transport::Channel>,
+            access_token: Option<&str>,
+            actor: &str,
+            key: #key_ident,
+            arg: #arg_ident,
+        ) -> anyhow::Result<#result> {
+            submit::exercise_choice::exercise_by_key(
+                command_service_client,
+                access_token,
+                &submit::submit_command::SubmitOptions::act_as(vec![actor.to_string()]),
+                key,
+                arg,
+                None,
+            )
+            .await
+        }
+    }
+}
+
+/// The Rust item of a data type, `None` for data types without a Rust representation of their own.
+/// `roles` are added to records, interfaces are generated by `interface_item`.
+fn data_type_item(ctx: &ModuleContext, def_data_type: &DefDataType, roles: RecordRoles) -> Result<Option<TokenStream>> {
+    let (package, module) = (ctx.package, ctx.module);
+    if !def_data_type.params.is_empty() {
+        bail!("generic data types are not supported");
+    }
+    let name = dotted_name(package, def_data_type.name_interned_dname)?;
+    let type_ident = sanitize_ident(&name);
+    let value_derives = ctx.value_derives(false);
+    let item = match &def_data_type.data_cons {
+        // Records of variant constructors are generated as the fields of the variant
+        Some(DataCons::Record(_)) if name.contains('.') => return Ok(None),
+        Some(DataCons::Record(_)) => {
+            let record = DamlRecordRep::try_from((def_data_type, module, package))?;
+            let fields = record_fields(ctx, &record, quote! { pub })?;
+            let fields = fields.iter().map(|field| &field.item);
+            let value_derives = ctx.value_derives(record_defaultable(ctx, &record, &mut vec![])?);
+            let RecordRoles { derives, attrs } = roles;
+            let omit_trailing_none =
+                (trailing_optional_fields(ctx, &record)? > 0).then(|| quote! { #[lapi(omit_trailing_none)] });
+            let serde_attr = ctx.serde_attr(quote! { rename_all = "camelCase" });
+            quote! {
+                #[derive(#value_derives #(, #derives)*)]
+                #( #attrs )*
+                #omit_trailing_none
+                #serde_attr
+                pub struct #type_ident {
+                    #( #fields, )*
+                }
+            }
+        }
+        Some(DataCons::Variant(_)) => {
+            let variant = DamlVariantRep::try_from((def_data_type, module, package))?;
+            let mut rust_variants = Vec::new();
+            for constructor in &variant.constructors {
+                let arg_type = constructor.arg_type.as_ref().context("Constructor without argument type")?;
+                let variant_ident = sanitize_ident(&constructor.constructor_name);
+                let constructor_attr = (variant_ident != constructor.constructor_name)
+                    .then(|| constructor_attrs(ctx, &constructor.constructor_name));
+                let record_name = format!("{}.{}", name, constructor.constructor_name);
+                let (serde_attr, fields) = match constructor_record(ctx, arg_type, &record_name)? {
+                    Some(record) => {
+                        let fields = record_fields(ctx, &record, quote! {})?;
+                        let fields = fields.iter().map(|field| &field.item);
+                        (ctx.serde_attr(quote! { rename_all = "camelCase" }), quote! { { #( #fields, )* } })
+                    }
+                    None => {
+                        let rust_type = ctx
+                            .rust_type(arg_type)
+                            .with_context(|| format!("constructor {}", constructor.constructor_name))?;
+                        (None, quote! { (#rust_type) })
+                    }
+                };
+                rust_variants.push(quote! {
+                    #constructor_attr
+                    #serde_attr
+                    #variant_ident #fields
+                });
+            }
+            quote! {
+                #[derive(#value_derives)]
+                pub enum #type_ident {
+                    #( #rust_variants, )*
+                }
+            }
+        }
+        Some(DataCons::Enum(_)) => {
+            let daml_enum = DamlEnumRep::try_from((def_data_type, module, package))?;
+            let rust_variants = daml_enum.constructors.iter().map(|constructor| {
+                let variant_ident = sanitize_ident(constructor);
+                let constructor_attr = (variant_ident != constructor).then(|| constructor_attrs(ctx, constructor));
+                quote! {
+                    #constructor_attr
+                    #variant_ident
+                }
+            });
+            quote! {
+                #[derive(#value_derives)]
+                pub enum #type_ident {
+                    #( #rust_variants, )*
+                }
+            }
+        }
+        Some(DataCons::Interface(_)) | None => return Ok(None),
+    };
+    Ok(Some(quote! {
+        #item
+        impl DamlValue for #type_ident {}
+    }))
+}
+
+/// The `constructor` attribute of a variant whose Rust name differs from the Daml constructor
+fn constructor_attrs(ctx: &ModuleContext, constructor: &str) -> TokenStream {
+    let serde_attr = ctx.serde_attr(quote! { rename = #constructor });
+    quote! {
+        #[lapi(constructor = #constructor)]
+        #serde_attr
+    }
+}
+
+/// A field of a generated struct or enum variant
+struct GeneratedField {
+    ident: Ident,
+    label: String,
+    type_rep: TypeRep,
+    rust_type: TokenStream,
+    /// The field with its attributes
+    item: TokenStream,
+}
+
+/// The fields of a record as Rust struct or enum variant fields, with a `label` attribute where the
+/// snake_case field name does not convert back to the Daml label. Labels converting to the same
+/// field name, e.g. `myField` and `my_field`, get a numbered suffix from the second one on.
+/// The trailing `Optional` fields are `#[lapi(default)]`, as records of an older package version
+/// lack the fields added by upgrades.
+fn record_fields(ctx: &ModuleContext, record: &DamlRecordRep, visibility: TokenStream) -> Result<Vec<GeneratedField>> {
+    let mut fields = Vec::new();
+    let mut field_idents = HashSet::new();
+    let first_trailing_optional = record.fields.len() - trailing_optional_fields(ctx, record)?;
+    for (index, field) in record.fields.iter().enumerate() {
+        let field_type = field.field_type.as_ref().context("Field without type")?;
+        let rust_type = ctx
+            .rust_type(field_type)
+            .with_context(|| format!("field {}", field.field_name))?;
+        let mut field_ident = field_ident(&field.field_name);
+        for suffix in 2.. {
+            if field_idents.insert(field_ident.clone()) {
+                break;
+            }
+            field_ident = sanitize_ident(&format!("{}_{}", camel_to_snake(&field.field_name), suffix));
+        }
+        let label = &field.field_name;
+        let label_attr = (snake_to_camel(&field_ident.unraw().to_string()) != *label).then(|| {
+            let serde_attr = ctx.serde_attr(quote! { rename = #label });
+            quote! { #[lapi(label = #label)] #serde_attr }
+        });
+        let default_attr = (index >= first_trailing_optional).then(|| {
+            let serde_attr = ctx.serde_attr(quote! { default });
+            quote! { #[lapi(default)] #serde_attr }
+        });
+        let item = quote! {
+            #label_attr
+            #default_attr
+            #visibility #field_ident: #rust_type
+        };
+        fields.push(GeneratedField {
+            ident: field_ident,
+            label: label.clone(),
+            type_rep: type_rep(field_type, ctx.package)?,
+            rust_type,
+            item,
+        });
+    }
+    Ok(fields)
+}
+
+/// `<Record>Builder` of a record struct, with a setter per field and `build`, which fails if a field
+/// is not set. `Optional`, list, map and set fields default to empty.
+fn builder_item(ctx: &ModuleContext, def_data_type: &DefDataType, type_idents: &HashSet<Ident>) -> Result<Option<TokenStream>> {
+    let name = dotted_name(ctx.package, def_data_type.name_interned_dname)?;
+    if !matches!(def_data_type.data_cons, Some(DataCons::Record(_))) || name.contains('.') {
+        return Ok(None);
+    }
+    let record = DamlRecordRep::try_from((def_data_type, ctx.module, ctx.package))?;
+    let fields = record_fields(ctx, &record, quote! {})?;
+    let type_ident = sanitize_ident(&name);
+    let builder_ident = format_ident!("{}Builder", type_ident);
+    if type_idents.contains(&builder_ident) {
+        bail!("{} collides with a data type", builder_ident);
+    }
+    if fields.iter().any(|field| field.ident == "build") {
+        bail!("the field build collides with the build method");
+    }
+    let builder_fields = fields.iter().map(|GeneratedField { ident, rust_type, .. }| quote! { #ident: Option<#rust_type> });
+    let setters = fields.iter().map(|GeneratedField { ident, rust_type, .. }| {
+        quote! {
+            pub fn #ident(mut self, #ident: impl Into<#rust_type>) -> Self {
+                self.#ident = Some(#ident.into());
+                self
+            }
+        }
+    });
+    let field_values = fields.iter().map(|field| {
+        let ident = &field.ident;
+        if empty_by_default(ctx, &field.type_rep) {
+            quote! { #ident: self.#ident.unwrap_or_default() }
+        } else {
+            let message = format!("{}: missing field {}", name, field.label);
+            quote! { #ident: self.#ident.ok_or_else(|| anyhow::anyhow!(#message))? }
+        }
+    });
+    let doc = format!(" Builds a `{}`, see `{}::builder`", name, type_ident);
+    Ok(Some(quote! {
+        #[doc = #doc]
+        #[derive(Clone, Debug, Default, PartialEq)]
+        pub struct #builder_ident {
+            #( #builder_fields, )*
+        }
+
+        impl #builder_ident {
+            #( #setters )*
+
+            pub fn build(self) -> anyhow::Result<#type_ident> {
+                Ok(#type_ident {
+                    #( #field_values, )*
+                })
+            }
+        }
+
+        impl #type_ident {
+            pub fn builder() -> #builder_ident {
+                #builder_ident::default()
+            }
+        }
+    }))
+}
+
+/// Whether builders leave a field empty unless set: `Optional`, list, map and set fields
+fn empty_by_default(ctx: &ModuleContext, type_rep: &TypeRep) -> bool {
+    match type_rep {
+        TypeRep::Builtin { builtin, .. } => {
+            matches!(builtin, BuiltinType::Optional | BuiltinType::List | BuiltinType::Textmap | BuiltinType::Genmap)
+        }
+        TypeRep::Con { package, module, name, .. } => {
+            is_stdlib(ctx, package) && module == "DA.Set.Types" && name == "Set"
+        }
+        _ => false,
+    }
+}
+
+fn is_stdlib(ctx: &ModuleContext, package: &PackageRef) -> bool {
+    match package {
+        PackageRef::SelfPackage => STDLIB_PACKAGES.contains(&ctx.generated.package_name(ctx.package_id)),
+        PackageRef::Imported(package_id) => STDLIB_PACKAGES.contains(&ctx.generated.package_name(package_id)),
+    }
+}
+
+/// Whether the generated struct of a record can derive `Default`, i.e. the Rust types of all its
+/// fields implement it. `visiting` are the records being checked, as records may be recursive.
+fn record_defaultable(ctx: &ModuleContext, record: &DamlRecordRep, visiting: &mut Vec<(String, String)>) -> Result<bool> {
+    for field in &record.fields {
+        let field_type = field.field_type.as_ref().context("Field without type")?;
+        if !defaultable(ctx, &type_rep(field_type, ctx.package)?, visiting)? {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+/// Whether the Rust type of a Daml type implements `Default`: the built-in types but `DamlNumeric`,
+/// the standard library types mapped to them, and the defaultable records of the same package.
+/// Data types of other packages are taken not to.
+fn defaultable(ctx: &ModuleContext, type_rep: &TypeRep, visiting: &mut Vec<(String, String)>) -> Result<bool> {
+    match type_rep {
+        TypeRep::Builtin { builtin: BuiltinType::Numeric, args } => Ok(args.first() == Some(&TypeRep::Nat(10))),
+        TypeRep::Builtin { .. } => Ok(true),
+        TypeRep::Con { package, module, name, args } if is_stdlib(ctx, package) => match (module.as_str(), name.as_str()) {
+            ("DA.Types", "Tuple2" | "Tuple3") => {
+                for arg in args {
+                    if !defaultable(ctx, arg, visiting)? {
+                        return Ok(false);
+                    }
+                }
+                Ok(true)
+            }
+            ("DA.Set.Types", "Set") | ("DA.Time.Types", "RelTime") => Ok(true),
+            _ => Ok(false),
+        },
+        TypeRep::Con { package: PackageRef::SelfPackage, module, name, args } if args.is_empty() => {
+            let key = (module.clone(), name.clone());
+            if visiting.contains(&key) || name.contains('.') {
+                return Ok(false);
+            }
+            let Some(record) = package_record(ctx, module, name)? else {
+                return Ok(false);
+            };
+            visiting.push(key);
+            let defaultable = record_defaultable(ctx, &record, visiting);
+            visiting.pop();
+            defaultable
+        }
+        _ => Ok(false),
+    }
+}
+
+/// The non-generic record `name` of the module `module_name` of the package
+fn package_record(ctx: &ModuleContext, module_name: &str, name: &str) -> Result<Option<DamlRecordRep>> {
+    for module in &ctx.package.modules {
+        if dotted_name(ctx.package, module.name_interned_dname)? != module_name {
+            continue;
+        }
+        for def_data_type in &module.data_types {
+            if dotted_name(ctx.package, def_data_type.name_interned_dname)? == name
+                && def_data_type.params.is_empty()
+                && matches!(def_data_type.data_cons, Some(DataCons::Record(_)))
+            {
+                return DamlRecordRep::try_from((def_data_type, module, ctx.package)).map(Some);
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// The number of `Optional` fields at the end of a record, the ones a package upgrade may have added
+fn trailing_optional_fields(ctx: &ModuleContext, record: &DamlRecordRep) -> Result<usize> {
+    let mut count = 0;
+    for field in record.fields.iter().rev() {
+        let field_type = field.field_type.as_ref().context("Field without type")?;
+        match type_rep(field_type, ctx.package)? {
+            TypeRep::Builtin { builtin: BuiltinType::Optional, .. } => count += 1,
+            _ => break,
+        }
+    }
+    Ok(count)
+}
+
+/// The record a variant constructor was declared with in Daml, i.e. the argument is the
+/// `<Variant>.<Constructor>` record of the same module
+fn constructor_record(ctx: &ModuleContext, arg_type: &Type, record_name: &str) -> Result<Option<DamlRecordRep>> {
+    if ctx.local_type_name(arg_type)?.as_deref() != Some(record_name) {
+        return Ok(None);
+    }
+    for def_data_type in &ctx.module.data_types {
+        if dotted_name(ctx.package, def_data_type.name_interned_dname)? == record_name {
+            return DamlRecordRep::try_from((def_data_type, ctx.module, ctx.package)).map(Some);
+        }
+    }
+    Ok(None)
+}
+
+/// The path of the Rust module of a Daml module, `Main.Asset` becomes `main::asset`
+pub(crate) fn module_idents(module_name: &str) -> Vec<Ident> {
+    module_name.split('.').map(|segment| sanitize_ident(&camel_to_snake(segment))).collect()
+}
+
+fn field_ident(label: &str) -> Ident {
+    sanitize_ident(&camel_to_snake(label))
+}
+
+/// `maybeDescription` becomes `maybe_description`, `URL` becomes `url`
+pub(crate) fn camel_to_snake(name: &str) -> String {
+    let chars: Vec<char> = name.chars().collect();
+    let mut result = String::new();
+    for (i, c) in chars.iter().enumerate() {
+        if c.is_ascii_uppercase() {
+            let previous = i.checked_sub(1).map(|j| chars[j]);
+            let next = chars.get(i + 1);
+            let word_start = match previous {
+                Some(p) if p.is_ascii_lowercase() || p.is_ascii_digit() => true,
+                Some(p) if p.is_ascii_uppercase() => next.is_some_and(|n| n.is_ascii_lowercase()),
+                _ => false,
+            };
+            if word_start && !result.ends_with('_') {
+                result.push('_');
+            }
+            result.push(c.to_ascii_lowercase());
+        } else {
+            result.push(*c);
+        }
+    }
+    result
+}
+
+/// The label `LapiAccess` derives from a field name, see `derive_lapi_access`
+fn snake_to_camel(name: &str) -> String {
+    let mut result = String::new();
+    let mut uppercase_next = false;
+    for c in name.chars() {
+        if c == '_' {
+            uppercase_next = true;
+        } else if uppercase_next {
+            result.push(c.to_ascii_uppercase());
+            uppercase_next = false;
+        } else {
+            result.push(c);
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lf_protobuf::com::daml::daml_lf_2::r#type::Sum;
+    use crate::lf_protobuf::com::daml::daml_lf_2::{
+        BuiltinType, DefTemplate, FieldWithType, InternedDottedName, ModuleId, PackageMetadata, SelfOrImportedPackageId,
+        DefInterface, TemplateChoice, TypeConId, Unit, VarWithType, def_data_type, def_template, r#type, self_or_imported_package_id,
+    };
+
+    fn builtin(builtin_type: BuiltinType, args: Vec<Type>) -> Type {
+        Type {
+            sum: Some(Sum::Builtin(r#type::Builtin { builtin: builtin_type as i32, args })),
+        }
+    }
+
+    fn self_con(module_name_interned_dname: i32, name_interned_dname: i32) -> Type {
+        Type {
+            sum: Some(Sum::Con(r#type::Con {
+                tycon: Some(TypeConId {
+                    module: Some(ModuleId {
+                        package_id: Some(SelfOrImportedPackageId {
+                            sum: Some(self_or_imported_package_id::Sum::SelfPackageId(Unit {})),
+                        }),
+                        module_name_interned_dname,
+                    }),
+                    name_interned_dname,
+                }),
+                args: vec![],
+            })),
+        }
+    }
+
+    fn fields(fields: Vec<(i32, Type)>) -> def_data_type::Fields {
+        def_data_type::Fields {
+            fields: fields
+                .into_iter()
+                .map(|(field_interned_str, typ)| FieldWithType { field_interned_str, r#type: Some(typ) })
+                .collect(),
+        }
+    }
+
+    fn data_type(name_interned_dname: i32, data_cons: DataCons) -> DefDataType {
+        DefDataType {
+            name_interned_dname,
+            data_cons: Some(data_cons),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_package_code() -> Result<()> {
+        let strings = [
+            "daml-asset", "0.0.1", "Main", "Asset", "Rgb", "owner", "maybeRgb", "red", "price", "Shape", "Circle",
+            "radius", "Dot", "Tagged", "Color", "Green", "Give", "newOwner", "arg",
+        ];
+        let package = Package {
+            interned_strings: strings.map(String::from).to_vec(),
+            interned_dotted_names: vec![
+                InternedDottedName { segments_interned_str: vec![2] },
+                InternedDottedName { segments_interned_str: vec![3] },
+                InternedDottedName { segments_interned_str: vec![4] },
+                InternedDottedName { segments_interned_str: vec![9] },
+                InternedDottedName { segments_interned_str: vec![9, 10] },
+                InternedDottedName { segments_interned_str: vec![14] },
+                InternedDottedName { segments_interned_str: vec![16] },
+            ],
+            metadata: Some(PackageMetadata {
+                name_interned_str: 0,
+                version_interned_str: 1,
+                upgraded_package_id: None,
+            }),
+            modules: vec![Module {
+                name_interned_dname: 0,
+                data_types: vec![
+                    data_type(
+                        1,
+                        DataCons::Record(fields(vec![
+                            (5, builtin(BuiltinType::Party, vec![])),
+                            (6, builtin(BuiltinType::Optional, vec![self_con(0, 2)])),
+                            (8, builtin(BuiltinType::Numeric, vec![Type { sum: Some(Sum::Nat(10)) }])),
+                        ])),
+                    ),
+                    data_type(2, DataCons::Record(fields(vec![(7, builtin(BuiltinType::Int64, vec![]))]))),
+                    data_type(
+                        3,
+                        DataCons::Variant(fields(vec![
+                            (10, self_con(0, 4)),
+                            (12, builtin(BuiltinType::Unit, vec![])),
+                            (13, builtin(BuiltinType::Text, vec![])),
+                        ])),
+                    ),
+                    data_type(4, DataCons::Record(fields(vec![(11, builtin(BuiltinType::Int64, vec![]))]))),
+                    data_type(
+                        5,
+                        DataCons::Enum(def_data_type::EnumConstructors { constructors_interned_str: vec![15] }),
+                    ),
+                    data_type(6, DataCons::Record(fields(vec![(17, builtin(BuiltinType::Party, vec![]))]))),
+                ],
+                templates: vec![DefTemplate {
+                    tycon_interned_dname: 1,
+                    choices: vec![TemplateChoice {
+                        name_interned_str: 16,
+                        consuming: true,
+                        arg_binder: Some(VarWithType { var_interned_str: 18, r#type: Some(self_con(0, 6)) }),
+                        ret_type: Some(builtin(BuiltinType::ContractId, vec![self_con(0, 1)])),
+                        ..Default::default()
+                    }],
+                    key: Some(def_template::DefKey { r#type: Some(builtin(BuiltinType::Party, vec![])), ..Default::default() }),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let code = package_code(&package)?;
+        assert!(code.contains("pub mod main {"));
+        assert!(code.contains("#[template(package = \"#daml-asset\", module = \"Main\", entity = \"Asset\")]"));
+        assert!(code.contains("pub maybe_rgb: DamlOptional<Rgb>,"));
+        assert!(code.contains("pub price: DamlDecimal,"));
+        assert!(code.contains("#[derive(Clone, Debug, Default, PartialEq, LapiAccess)]\n    pub struct Rgb {"));
+        assert!(code.contains("pub enum Shape {\n        Circle { radius: DamlInt },\n        Dot(DamlUnit),\n        Tagged(DamlText),\n    }"));
+        assert!(code.contains("pub enum Color {\n        Green,\n    }"));
+        assert!(!code.contains("Shape_Circle"));
+        assert!(code.contains("#[derive(Clone, Debug, Default, PartialEq, LapiAccess, Choice)]\n    #[choice(on = Asset, name = \"Give\", result = \"DamlContractId\")]\n    pub struct Give {"));
+        assert!(code.contains("pub async fn exercise_give("));
+        assert!(code.contains("Asset(daml_type_rep::contract::Contract<main::Asset>),"));
+        assert!(code.contains("(\"Main\", \"Asset\") => {\n                <main::Asset as daml_type_rep::contract::FromCreatedEvent>"));
+        assert!(code.contains(") -> anyhow::Result<DamlContractId> {"));
+        assert!(code.contains("#[contract_key(template = Asset)]\n    pub struct AssetContractKey(pub DamlParty);"));
+        assert!(code.contains("pub async fn exercise_give_by_key("));
+        assert_eq!(camel_to_snake("URLPath2go"), "url_path2go");
+        Ok(())
+    }
+
+    #[test]
+    fn test_interface_code() -> Result<()> {
+        // interface Asset where viewtype View; nonconsuming choice GetView : View
+        let mut package = package(
+            &["daml-interface-example-interfaces", "1.0.0", "Asset", "View", "assetOwner", "description", "GetView", "arg"],
+            vec![vec![2], vec![3], vec![6]],
+            vec![
+                data_type(0, DataCons::Interface(Unit {})),
+                data_type(
+                    1,
+                    DataCons::Record(fields(vec![
+                        (4, builtin(BuiltinType::Party, vec![])),
+                        (5, builtin(BuiltinType::Text, vec![])),
+                    ])),
+                ),
+                data_type(2, DataCons::Record(fields(vec![]))),
+            ],
+        );
+        package.modules[0].interfaces = vec![DefInterface {
+            tycon_interned_dname: 0,
+            choices: vec![TemplateChoice {
+                name_interned_str: 6,
+                consuming: false,
+                arg_binder: Some(VarWithType { var_interned_str: 7, r#type: Some(self_con(0, 2)) }),
+                ret_type: Some(self_con(0, 1)),
+                ..Default::default()
+            }],
+            view: Some(self_con(0, 1)),
+            ..Default::default()
+        }];
+
+        let code = package_code(&package)?;
+        assert!(code.contains("#[derive(Clone, Copy, Debug, PartialEq, Interface)]"));
+        assert!(code.contains("entity = \"Asset\"\n    )]\n    pub struct Asset;"));
+        assert!(code.contains("#[derive(Clone, Debug, Default, PartialEq, LapiAccess, InterfaceView)]"));
+        assert!(code.contains("entity = \"Asset\"\n    )]\n    pub struct View {"));
+        assert!(code.contains("#[choice(on = Asset, name = \"GetView\", result = \"View\", nonconsuming)]"));
+        assert!(code.contains("pub async fn exercise_get_view("));
+        assert!(!code.contains("// Not generated:"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_dotted_module_names() -> Result<()> {
+        // Splice.Amulet:Amulet { amount : Splice.Types.Amount }, Splice.Types:Amount { value : Int }
+        let mut package = package(
+            &["splice-amulet", "0.1.0", "Splice", "Amulet", "Types", "Amount", "amount", "value"],
+            vec![vec![2, 3], vec![2, 4], vec![3], vec![5]],
+            vec![data_type(2, DataCons::Record(fields(vec![(6, self_con(1, 3))])))],
+        );
+        package.modules.push(Module {
+            name_interned_dname: 1,
+            data_types: vec![data_type(3, DataCons::Record(fields(vec![(7, builtin(BuiltinType::Int64, vec![]))])))],
+            ..Default::default()
+        });
+
+        let code = package_code(&package)?;
+        assert!(code.contains("pub mod splice {\n    pub mod amulet {"));
+        assert!(code.contains("pub amount: super::super::splice::types::Amount,"));
+        assert!(code.contains("    pub mod types {"));
+        assert!(!code.contains("splice_amulet"));
+        // The output does not depend on the order of the modules in the archive
+        package.modules.reverse();
+        assert_eq!(package_code(&package)?, code);
+        Ok(())
+    }
+
+    #[test]
+    fn test_keyword_and_colliding_identifiers() -> Result<()> {
+        let package = package(
+            &["daml-asset", "0.0.1", "Main", "Asset", "type", "self", "myField", "my_field"],
+            vec![vec![2], vec![3]],
+            vec![data_type(
+                1,
+                DataCons::Record(fields(vec![
+                    (4, builtin(BuiltinType::Text, vec![])),
+                    (5, builtin(BuiltinType::Text, vec![])),
+                    (6, builtin(BuiltinType::Int64, vec![])),
+                    (7, builtin(BuiltinType::Int64, vec![])),
+                ])),
+            )],
+        );
+
+        let code = package_code(&package)?;
+        assert!(code.contains("pub struct Asset {\n        pub r#type: DamlText,\n        pub self_: DamlText,"));
+        assert!(code.contains("pub my_field: DamlInt,\n        #[lapi(label = \"my_field\")]\n        pub my_field_2: DamlInt,"));
+        assert_eq!(sanitize_ident("1st").to_string(), "_1st");
+        assert_eq!(sanitize_ident("Self").to_string(), "Self_");
+        Ok(())
+    }
+
+    #[test]
+    fn test_upgradable_optional_fields() -> Result<()> {
+        // template Asset with owner : Party; maybeRgb : Optional Int; price : Int; note : Optional Text
+        let optional = |typ| builtin(BuiltinType::Optional, vec![typ]);
+        let mut package = package(
+            &["daml-asset", "0.0.2", "Main", "Asset", "owner", "maybeRgb", "price", "note"],
+            vec![vec![2], vec![3]],
+            vec![data_type(
+                1,
+                DataCons::Record(fields(vec![
+                    (4, builtin(BuiltinType::Party, vec![])),
+                    (5, optional(builtin(BuiltinType::Int64, vec![]))),
+                    (6, builtin(BuiltinType::Int64, vec![])),
+                    (7, optional(builtin(BuiltinType::Text, vec![]))),
+                ])),
+            )],
+        );
+        package.modules[0].templates = vec![DefTemplate { tycon_interned_dname: 1, ..Default::default() }];
+
+        let code = package_code(&package)?;
+        assert!(code.contains("#[lapi(omit_trailing_none)]\n    pub struct Asset {"));
+        assert!(code.contains("pub maybe_rgb: DamlOptional<DamlInt>,\n        pub price: DamlInt,\n        #[lapi(default)]\n        pub note: DamlOptional<DamlText>,"));
+        assert!(code.contains("pub const PACKAGE_VERSION: &str = \"0.0.2\";"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_json_option() -> Result<()> {
+        // data Asset = Asset with owner : Party; note : Optional Text
+        let package = package(
+            &["daml-asset", "0.0.1", "Main", "Asset", "owner", "note"],
+            vec![vec![2], vec![3]],
+            vec![data_type(
+                1,
+                DataCons::Record(fields(vec![
+                    (4, builtin(BuiltinType::Party, vec![])),
+                    (5, builtin(BuiltinType::Optional, vec![builtin(BuiltinType::Text, vec![])])),
+                ])),
+            )],
+        );
+        let packages = vec![("abc".to_string(), package)];
+
+        let code = dar_code(&packages)?;
+        assert!(!code.contains("serde"));
+
+        let code = dar_code_with_options(&packages, &CodegenOptions { json: true, ..Default::default() })?;
+        assert!(code.contains("JsonCodec,\n        serde::Serialize,\n        serde::Deserialize\n    )]"));
+        assert!(code.contains("#[serde(rename_all = \"camelCase\")]\n    pub struct Asset {"));
+        assert!(code.contains("#[lapi(default)]\n        #[serde(default)]\n        pub note: DamlOptional<DamlText>,"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder_and_default() -> Result<()> {
+        // data Asset = Asset with owner : Party; tags : [Text]
+        // data Holding = Holding with asset : Asset; rate : Numeric 5
+        let numeric = builtin(BuiltinType::Numeric, vec![Type { sum: Some(Sum::Nat(5)) }]);
+        let package = package(
+            &["daml-asset", "0.0.1", "Main", "Asset", "owner", "tags", "Holding", "asset", "rate"],
+            vec![vec![2], vec![3], vec![6]],
+            vec![
+                data_type(
+                    1,
+                    DataCons::Record(fields(vec![
+                        (4, builtin(BuiltinType::Party, vec![])),
+                        (5, builtin(BuiltinType::List, vec![builtin(BuiltinType::Text, vec![])])),
+                    ])),
+                ),
+                data_type(2, DataCons::Record(fields(vec![(7, self_con(0, 1)), (8, numeric)]))),
+            ],
+        );
+
+        let code = package_code(&package)?;
+        assert!(code.contains("#[derive(Clone, Debug, Default, PartialEq, LapiAccess)]\n    pub struct Asset {"));
+        assert!(code.contains("#[derive(Clone, Debug, PartialEq, LapiAccess)]\n    pub struct Holding {"));
+        assert!(code.contains("pub struct AssetBuilder {\n        owner: Option<DamlParty>,"));
+        assert!(code.contains("pub fn owner(mut self, owner: impl Into<DamlParty>) -> Self {"));
+        assert!(code.contains("tags: self.tags.unwrap_or_default(),"));
+        assert!(code.contains("\"Holding: missing field rate\""));
+        assert!(code.contains("pub fn builder() -> HoldingBuilder {"));
+        Ok(())
+    }
+
+    fn package(strings: &[&str], dotted_names: Vec<Vec<i32>>, data_types: Vec<DefDataType>) -> Package {
+        Package {
+            interned_strings: strings.iter().map(|s| s.to_string()).collect(),
+            interned_dotted_names: dotted_names
+                .into_iter()
+                .map(|segments_interned_str| InternedDottedName { segments_interned_str })
+                .collect(),
+            metadata: Some(PackageMetadata {
+                name_interned_str: 0,
+                version_interned_str: 1,
+                upgraded_package_id: None,
+            }),
+            modules: vec![Module { name_interned_dname: 0, data_types, ..Default::default() }],
+            ..Default::default()
+        }
+    }
+
+    fn imported_con(package_id_interned_str: i32, module_name_interned_dname: i32, name_interned_dname: i32, args: Vec<Type>) -> Type {
+        Type {
+            sum: Some(Sum::Con(r#type::Con {
+                tycon: Some(TypeConId {
+                    module: Some(ModuleId {
+                        package_id: Some(SelfOrImportedPackageId {
+                            sum: Some(self_or_imported_package_id::Sum::ImportedPackageIdInternedStr(package_id_interned_str)),
+                        }),
+                        module_name_interned_dname,
+                    }),
+                    name_interned_dname,
+                }),
+                args,
+            })),
+        }
+    }
+
+    #[test]
+    fn test_dar_code_with_dependencies() -> Result<()> {
+        // Main:Wallet { token : Iface.Token, pair : (Int, Text) }
+        let main = package(
+            &["wallet", "1.0.0", "Main", "Wallet", "token", "pair", "iface-id", "Iface", "Token", "prim-id", "DA", "Types", "Tuple2"],
+            vec![vec![2], vec![3], vec![7], vec![8], vec![10, 11], vec![12]],
+            vec![data_type(
+                1,
+                DataCons::Record(fields(vec![
+                    (4, imported_con(6, 2, 3, vec![])),
+                    (
+                        5,
+                        imported_con(
+                            9,
+                            4,
+                            5,
+                            vec![builtin(BuiltinType::Int64, vec![]), builtin(BuiltinType::Text, vec![])],
+                        ),
+                    ),
+                ])),
+            )],
+        );
+        let iface = package(
+            &["asset-iface", "1.0.0", "Iface", "Token", "amount"],
+            vec![vec![2], vec![3]],
+            vec![data_type(1, DataCons::Record(fields(vec![(4, builtin(BuiltinType::Int64, vec![]))])))],
+        );
+        let prim = package(&["daml-prim", "1.0.0", "DA.Types"], vec![vec![2]], vec![]);
+        let packages = vec![
+            ("main-id".to_string(), main),
+            ("iface-id".to_string(), iface),
+            ("prim-id".to_string(), prim),
+        ];
+
+        let code = dar_code(&packages)?;
+        assert!(code.contains("pub token: super::deps::asset_iface::iface::Token,"));
+        assert!(code.contains("pub pair: DamlTuple2<DamlInt, DamlText>,"));
+        assert!(code.contains("pub mod deps {\n    pub mod asset_iface {\n        pub mod iface {"));
+        assert!(!code.contains("daml_prim"));
+
+        // The DAR of the interface package and the one using it in one tree, without `deps`
+        let iface_dar = vec![packages[1].clone()];
+        let code = dars_code(&[iface_dar, packages], &CodegenOptions::default())?;
+        assert!(code.starts_with("// Generated from the Daml packages asset-iface 1.0.0, wallet 1.0.0, do not edit."));
+        assert!(code.contains("pub mod asset_iface {\n    pub mod iface {"));
+        assert!(code.contains("pub mod wallet {\n    pub mod main {"));
+        assert!(code.contains("pub token: super::super::asset_iface::iface::Token,"));
+        assert!(!code.contains("pub mod deps"));
+        Ok(())
+    }
+}
@@ -30,8 +30,21 @@ pub fn generate_rust_structs_from_dar(dar_path: &str, output_path: &str) -> Resu
     Ok(())
 }
 
-/// Sanitizes a string to a valid Rust identifier
-fn sanitize_ident(name: &str) -> Ident {
+/// Rust keywords, used as raw identifiers like `r#type`
+const KEYWORDS: [&str; 48] = [
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "do", "dyn", "else",
+    "enum", "extern", "false", "final", "fn", "for", "gen", "if", "impl", "in", "let", "loop", "macro", "match",
+    "mod", "move", "mut", "override", "priv", "pub", "ref", "return", "static", "struct", "trait", "true",
+    "try", "type", "typeof", "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+/// Keywords without a raw identifier form, which get a `_` suffix instead
+const NON_RAW_KEYWORDS: [&str; 5] = ["self", "Self", "super", "crate", "_"];
+
+/// Sanitizes a string to a valid Rust identifier: characters other than ASCII letters and digits
+/// become `_`, a leading digit gets a `_` prefix, and keywords become raw identifiers, or get a `_`
+/// suffix where there is no raw form, e.g. `self_`
+pub(crate) fn sanitize_ident(name: &str) -> Ident {
     let mut s = name.replace(|c: char| !c.is_ascii_alphanumeric(), "_");
     if !s
         .chars()
@@ -41,7 +54,13 @@ fn sanitize_ident(name: &str) -> Ident {
     {
         s = format!("_{}", s);
     }
-    Ident::new(&s, proc_macro2::Span::call_site())
+    if NON_RAW_KEYWORDS.contains(&s.as_str()) {
+        Ident::new(&format!("{}_", s), proc_macro2::Span::call_site())
+    } else if KEYWORDS.contains(&s.as_str()) {
+        Ident::new_raw(&s, proc_macro2::Span::call_site())
+    } else {
+        Ident::new(&s, proc_macro2::Span::call_site())
+    }
 }
 
 /// Generates Rust struct code from a DamlRecordRep using the quote! macro and prettyplease for formatting
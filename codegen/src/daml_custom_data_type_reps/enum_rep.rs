@@ -0,0 +1,36 @@
+use crate::daml_custom_data_type_reps::record::module_name;
+use crate::dar_inspect::{dotted_name, interned_string};
+use crate::lf_protobuf::com::daml::daml_lf_2::def_data_type::DataCons::Enum;
+use crate::lf_protobuf::com::daml::daml_lf_2::{DefDataType, Module, Package};
+use anyhow::{Result, bail};
+use std::convert::TryFrom;
+
+/// A Daml enum, a variant whose constructors take no argument
+#[derive(Debug, Clone)]
+pub struct DamlEnumRep {
+    pub module_name: String,
+    pub enum_name: String,
+    pub constructors: Vec<String>,
+}
+
+impl<'a> TryFrom<(&'a DefDataType, &'a Module, &'a Package)> for DamlEnumRep {
+    type Error = anyhow::Error;
+
+    fn try_from(
+        (def_data_type, module, package): (&'a DefDataType, &'a Module, &'a Package),
+    ) -> Result<Self> {
+        let Some(Enum(enum_constructors)) = &def_data_type.data_cons else {
+            bail!("Data type is not an enum");
+        };
+        let constructors = enum_constructors
+            .constructors_interned_str
+            .iter()
+            .map(|constructor| interned_string(package, *constructor))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(DamlEnumRep {
+            module_name: module_name(module, package)?,
+            enum_name: dotted_name(package, def_data_type.name_interned_dname)?,
+            constructors,
+        })
+    }
+}
@@ -1 +1,4 @@
-pub mod record;
\ No newline at end of file
+pub mod record;
+pub mod variant;
+pub mod enum_rep;
+pub mod template;
@@ -1,6 +1,8 @@
+use crate::dar_inspect::dotted_name;
 use crate::lf_protobuf::com::daml::daml_lf_2::DefDataType;
 use crate::lf_protobuf::com::daml::daml_lf_2::Module;
 use crate::lf_protobuf::com::daml::daml_lf_2::Package; // <-- Add this import
+use crate::lf_protobuf::com::daml::daml_lf_2::Type;
 use crate::lf_protobuf::com::daml::daml_lf_2::def_data_type::DataCons::Record;
 use crate::resolve_type::resolve_type;
 use anyhow::{Context, Ok, Result, bail};
@@ -14,23 +16,13 @@ pub struct DamlRecordRep {
     pub fields: Vec<DamlRecordFieldRep>,
 }
 
-#[derive(Debug, Clone)]
-#[allow(dead_code)]
-pub struct TemplateRep {
-    pub record: DamlRecordRep,
-}
-
-#[derive(Debug, Clone)]
-#[allow(dead_code)]
-pub struct ChoiceRep {
-    pub record: DamlRecordRep,
-}
-
 #[derive(Debug, Clone)]
 #[allow(dead_code)]
 pub struct DamlRecordFieldRep {
     pub field_name: String,
     pub type_name: String,
+    /// The Daml-LF type of the field, used by the code generator
+    pub field_type: Option<Type>,
 }
 
 impl<'a> TryFrom<(&'a DefDataType, &'a Module, &'a Package)> for DamlRecordRep {
@@ -50,35 +42,14 @@ impl<'a> TryFrom<(&'a DefDataType, &'a Module, &'a Package)> for DamlRecordRep {
     }
 }
 
-fn module_name(module: &Module, package: &Package) -> Result<String> {
-    let interned_strings = &package.interned_strings;
-    let interned_dotted_names = &package.interned_dotted_names;
-
-    let dotted_name = interned_dotted_names
-        .get(module.name_interned_dname as usize)
-        .context("module.name_interned_dname not found in interned_dotted_names")?;
-
-    let name = interned_strings
-        .get(dotted_name.segments_interned_str[0] as usize)
-        .cloned()
-        .context("module_interned_dotted_name not found in interned_strings")?;
-
-    Ok(name)
+/// The dotted name of a module, e.g. `Splice.Amulet`
+pub(crate) fn module_name(module: &Module, package: &Package) -> Result<String> {
+    dotted_name(package, module.name_interned_dname).context("module.name_interned_dname not resolved")
 }
-fn def_data_type_name(def_data_type: &DefDataType, package: &Package) -> Result<String> {
-    let interned_strings = &package.interned_strings;
-    let interned_dotted_names = &package.interned_dotted_names;
-
-    let dotted_name = interned_dotted_names
-        .get(def_data_type.name_interned_dname as usize)
-        .context("def_data_type.name_interned_dname not found in interned_dotted_names")?;
 
-    let name = interned_strings
-        .get(dotted_name.segments_interned_str[0] as usize)
-        .cloned()
-        .context("def_data_type_interned_dotted_name not found in interned_strings")?;
-
-    Ok(name)
+/// The dotted name of a data type, e.g. `Shape.Circle` for the record of a variant constructor
+fn def_data_type_name(def_data_type: &DefDataType, package: &Package) -> Result<String> {
+    dotted_name(package, def_data_type.name_interned_dname).context("def_data_type.name_interned_dname not resolved")
 }
 
 #[allow(unused)]
@@ -105,14 +76,15 @@ fn def_data_type_record_fields(
                 .unwrap_or_else(|| "<invalid>".to_string());
             let field_type = field.r#type.as_ref().map_or_else(
                 || "<unknown type>".to_string(),
-                |typ| resolve_type(typ, interned_types, interned_strings, &[]),
+                |typ| resolve_type(typ, interned_types, interned_strings, &package.interned_dotted_names),
             );
-            (field_name, field_type)
+            (field_name, field_type, field.r#type.clone())
         });
         Ok(fields
-            .map(|(field_name, field_type)| DamlRecordFieldRep {
+            .map(|(field_name, type_name, field_type)| DamlRecordFieldRep {
                 field_name,
-                type_name: field_type,
+                type_name,
+                field_type,
             })
             .collect())
     } else {
@@ -0,0 +1,80 @@
+use crate::daml_custom_data_type_reps::record::DamlRecordRep;
+use crate::dar_inspect::{dotted_name, interned_string};
+use crate::lf_protobuf::com::daml::daml_lf_2::{DefTemplate, Module, Package, TemplateChoice, Type};
+use crate::resolve_type::resolve_type;
+use anyhow::{Context, Result};
+use std::convert::TryFrom;
+
+/// A template with its payload record and choices. Signatories, observers, preconditions and the
+/// choice bodies are expressions, and are not captured.
+#[derive(Debug, Clone)]
+pub struct TemplateRep {
+    pub record: DamlRecordRep,
+    pub choices: Vec<ChoiceRep>,
+    pub key_type_name: Option<String>,
+    /// The Daml-LF type of the contract key, if the template has a key
+    pub key_type: Option<Type>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ChoiceRep {
+    pub choice_name: String,
+    pub consuming: bool,
+    pub arg_type_name: String,
+    /// The Daml-LF type of the choice argument, usually a record named after the choice
+    pub arg_type: Option<Type>,
+    pub return_type_name: String,
+    /// The Daml-LF type of the choice result
+    pub return_type: Option<Type>,
+}
+
+impl<'a> TryFrom<(&'a DefTemplate, &'a Module, &'a Package)> for TemplateRep {
+    type Error = anyhow::Error;
+
+    fn try_from(
+        (def_template, module, package): (&'a DefTemplate, &'a Module, &'a Package),
+    ) -> Result<Self> {
+        let name = dotted_name(package, def_template.tycon_interned_dname)?;
+        let def_data_type = module
+            .data_types
+            .iter()
+            .find(|def_data_type| def_data_type.name_interned_dname == def_template.tycon_interned_dname)
+            .with_context(|| format!("Record type of template {} not found", name))?;
+        let record = DamlRecordRep::try_from((def_data_type, module, package))?;
+        let choices = def_template
+            .choices
+            .iter()
+            .map(|choice| ChoiceRep::try_from((choice, package)))
+            .collect::<Result<Vec<_>>>()?;
+        let key_type = def_template.key.as_ref().and_then(|key| key.r#type.clone());
+        Ok(TemplateRep {
+            record,
+            choices,
+            key_type_name: key_type.as_ref().map(|key_type| type_name(Some(key_type), package)),
+            key_type,
+        })
+    }
+}
+
+impl<'a> TryFrom<(&'a TemplateChoice, &'a Package)> for ChoiceRep {
+    type Error = anyhow::Error;
+
+    fn try_from((choice, package): (&'a TemplateChoice, &'a Package)) -> Result<Self> {
+        let arg_type = choice.arg_binder.as_ref().and_then(|binder| binder.r#type.clone());
+        Ok(ChoiceRep {
+            choice_name: interned_string(package, choice.name_interned_str)?,
+            consuming: choice.consuming,
+            arg_type_name: type_name(arg_type.as_ref(), package),
+            arg_type,
+            return_type_name: type_name(choice.ret_type.as_ref(), package),
+            return_type: choice.ret_type.clone(),
+        })
+    }
+}
+
+fn type_name(typ: Option<&Type>, package: &Package) -> String {
+    typ.map_or_else(
+        || "<unknown type>".to_string(),
+        |typ| resolve_type(typ, &package.interned_types, &package.interned_strings, &package.interned_dotted_names),
+    )
+}
@@ -0,0 +1,55 @@
+use crate::daml_custom_data_type_reps::record::module_name;
+use crate::dar_inspect::{dotted_name, interned_string};
+use crate::lf_protobuf::com::daml::daml_lf_2::def_data_type::DataCons::Variant;
+use crate::lf_protobuf::com::daml::daml_lf_2::{DefDataType, Module, Package, Type};
+use crate::resolve_type::resolve_type;
+use anyhow::{Result, bail};
+use std::convert::TryFrom;
+
+#[derive(Debug, Clone)]
+pub struct DamlVariantRep {
+    pub module_name: String,
+    pub variant_name: String,
+    pub constructors: Vec<DamlVariantConstructorRep>,
+}
+
+/// A variant constructor with its single argument. Constructors written with record syntax in Daml
+/// take a record named `<Variant>.<Constructor>`, constructors without argument take `Unit`.
+#[derive(Debug, Clone)]
+pub struct DamlVariantConstructorRep {
+    pub constructor_name: String,
+    pub type_name: String,
+    /// The Daml-LF type of the argument, used by the code generator
+    pub arg_type: Option<Type>,
+}
+
+impl<'a> TryFrom<(&'a DefDataType, &'a Module, &'a Package)> for DamlVariantRep {
+    type Error = anyhow::Error;
+
+    fn try_from(
+        (def_data_type, module, package): (&'a DefDataType, &'a Module, &'a Package),
+    ) -> Result<Self> {
+        let Some(Variant(variant)) = &def_data_type.data_cons else {
+            bail!("Data type is not a variant");
+        };
+        let constructors = variant
+            .fields
+            .iter()
+            .map(|constructor| {
+                Ok(DamlVariantConstructorRep {
+                    constructor_name: interned_string(package, constructor.field_interned_str)?,
+                    type_name: constructor.r#type.as_ref().map_or_else(
+                        || "<unknown type>".to_string(),
+                        |typ| resolve_type(typ, &package.interned_types, &package.interned_strings, &package.interned_dotted_names),
+                    ),
+                    arg_type: constructor.r#type.clone(),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(DamlVariantRep {
+            module_name: module_name(module, package)?,
+            variant_name: dotted_name(package, def_data_type.name_interned_dname)?,
+            constructors,
+        })
+    }
+}
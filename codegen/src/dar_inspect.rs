@@ -0,0 +1,177 @@
+use crate::archive::archive_from_dar;
+use crate::lf_protobuf::com::daml::daml_lf_2::{Package, TemplateChoice};
+use crate::package::package_from_archive;
+use anyhow::{Context, Result};
+
+/// Summary of the main package of a DAR, as printed by `daml damlc inspect-dar`
+#[derive(Debug, Clone, PartialEq)]
+pub struct DarInfo {
+    pub main_package_id: String,
+    pub package_name: String,
+    pub package_version: String,
+    pub modules: Vec<ModuleInfo>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModuleInfo {
+    /// Dotted module name, e.g. `Main.Asset`
+    pub name: String,
+    pub templates: Vec<TemplateInfo>,
+    pub interfaces: Vec<TemplateInfo>,
+}
+
+/// A template or an interface with its choices
+#[derive(Debug, Clone, PartialEq)]
+pub struct TemplateInfo {
+    pub name: String,
+    pub choices: Vec<ChoiceInfo>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChoiceInfo {
+    pub name: String,
+    pub consuming: bool,
+}
+
+/// Reads the main package of a DAR and summarizes its modules, templates, interfaces and choices.
+pub fn inspect_dar(dar_path: &str) -> Result<DarInfo> {
+    let archive = archive_from_dar(dar_path)
+        .with_context(|| format!("Failed to read archive from '{}'", dar_path))?;
+    let package = package_from_archive(&archive)?;
+    inspect_package(archive.hash, &package)
+}
+
+/// Summarizes a decoded package, `package_id` being the hash of its archive.
+pub fn inspect_package(package_id: String, package: &Package) -> Result<DarInfo> {
+    let (package_name, package_version) = metadata_name_and_version(package)?;
+
+    let modules = package
+        .modules
+        .iter()
+        .map(|module| {
+            let templates = module
+                .templates
+                .iter()
+                .map(|template| {
+                    Ok(TemplateInfo {
+                        name: dotted_name(package, template.tycon_interned_dname)?,
+                        choices: choices(package, &template.choices)?,
+                    })
+                })
+                .collect::<Result<Vec<_>>>()?;
+            let interfaces = module
+                .interfaces
+                .iter()
+                .map(|interface| {
+                    Ok(TemplateInfo {
+                        name: dotted_name(package, interface.tycon_interned_dname)?,
+                        choices: choices(package, &interface.choices)?,
+                    })
+                })
+                .collect::<Result<Vec<_>>>()?;
+            Ok(ModuleInfo {
+                name: dotted_name(package, module.name_interned_dname)?,
+                templates,
+                interfaces,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(DarInfo {
+        main_package_id: package_id,
+        package_name,
+        package_version,
+        modules,
+    })
+}
+
+/// The name and version of a package, empty if it has no metadata
+pub(crate) fn metadata_name_and_version(package: &Package) -> Result<(String, String)> {
+    match &package.metadata {
+        Some(metadata) => Ok((
+            interned_string(package, metadata.name_interned_str)?,
+            interned_string(package, metadata.version_interned_str)?,
+        )),
+        None => Ok((String::new(), String::new())),
+    }
+}
+
+fn choices(package: &Package, choices: &[TemplateChoice]) -> Result<Vec<ChoiceInfo>> {
+    choices
+        .iter()
+        .map(|choice| {
+            Ok(ChoiceInfo {
+                name: interned_string(package, choice.name_interned_str)?,
+                consuming: choice.consuming,
+            })
+        })
+        .collect()
+}
+
+pub(crate) fn interned_string(package: &Package, index: i32) -> Result<String> {
+    package
+        .interned_strings
+        .get(index as usize)
+        .cloned()
+        .with_context(|| format!("Interned string {} not found", index))
+}
+
+pub(crate) fn dotted_name(package: &Package, index: i32) -> Result<String> {
+    let dotted_name = package
+        .interned_dotted_names
+        .get(index as usize)
+        .with_context(|| format!("Interned dotted name {} not found", index))?;
+    let segments = dotted_name
+        .segments_interned_str
+        .iter()
+        .map(|segment| interned_string(package, *segment))
+        .collect::<Result<Vec<_>>>()?;
+    Ok(segments.join("."))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lf_protobuf::com::daml::daml_lf_2::{
+        DefTemplate, InternedDottedName, Module, PackageMetadata,
+    };
+
+    #[test]
+    fn test_inspect_package() -> Result<()> {
+        let package = Package {
+            interned_strings: ["daml-asset", "0.0.1", "Main", "Asset", "Give", "Archive"]
+                .map(String::from)
+                .to_vec(),
+            interned_dotted_names: vec![
+                InternedDottedName { segments_interned_str: vec![2, 3] },
+                InternedDottedName { segments_interned_str: vec![3] },
+            ],
+            metadata: Some(PackageMetadata {
+                name_interned_str: 0,
+                version_interned_str: 1,
+                upgraded_package_id: None,
+            }),
+            modules: vec![Module {
+                name_interned_dname: 0,
+                templates: vec![DefTemplate {
+                    tycon_interned_dname: 1,
+                    choices: vec![
+                        TemplateChoice { name_interned_str: 4, consuming: true, ..Default::default() },
+                        TemplateChoice { name_interned_str: 5, consuming: true, ..Default::default() },
+                    ],
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let info = inspect_package("abc".to_string(), &package)?;
+        assert_eq!(info.package_name, "daml-asset");
+        assert_eq!(info.package_version, "0.0.1");
+        assert_eq!(info.modules[0].name, "Main.Asset");
+        assert_eq!(info.modules[0].templates[0].name, "Asset");
+        assert_eq!(info.modules[0].templates[0].choices[0].name, "Give");
+        Ok(())
+    }
+}
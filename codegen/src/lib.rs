@@ -4,4 +4,8 @@ pub mod daml_custom_data_type_reps;
 pub mod api_vs_lf;
 pub mod resolve_type;
 pub mod package;
-pub mod codegen;
\ No newline at end of file
+pub mod codegen;
+pub mod dar_inspect;
+pub mod build;
+pub mod manifest;
+pub mod registry;
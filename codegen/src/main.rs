@@ -1,5 +1,62 @@
-fn main() {
-}
+use anyhow::Result;
+use clap::Parser;
+use codegen::build::{CodegenOptions, check, generated_files, write_files};
+use std::path::PathBuf;
+
+#[derive(Parser, Debug)]
+#[command(name = "codegen")]
+#[command(about = "Generates the Rust bindings of DARs, one <package_name>.rs per DAR")]
+struct Cli {
+    /// Paths to the DAR files
+    #[arg(required = true)]
+    dars: Vec<PathBuf>,
+
+    /// Directory of the generated files
+    #[arg(long)]
+    out_dir: PathBuf,
+
+    /// Also derive the JSON codec and serde traits on the generated types
+    #[arg(long)]
+    json: bool,
 
+    /// Also write manifest.json describing the templates and the generated items
+    #[arg(long)]
+    manifest: bool,
 
+    /// Also write registry.json, the payload schemas of the templates, for the ledger explorer
+    #[arg(long)]
+    registry: bool,
 
+    /// Generate all DARs into one daml.rs, sharing their dependencies
+    #[arg(long)]
+    combined: bool,
+
+    /// Do not write, exit with status 1 if the files in the output directory differ from the
+    /// generated ones
+    #[arg(long)]
+    check: bool,
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let options = CodegenOptions {
+        json: cli.json,
+        manifest: cli.manifest,
+        registry: cli.registry,
+        combined: cli.combined,
+    };
+    if cli.check {
+        let outdated = check(&cli.dars, &cli.out_dir, &options)?;
+        if !outdated.is_empty() {
+            for path in &outdated {
+                eprintln!("{} is not up to date", path.display());
+            }
+            std::process::exit(1);
+        }
+    } else {
+        for path in write_files(&generated_files(&cli.dars, &options)?, &cli.out_dir)? {
+            println!("{}", path.display());
+        }
+    }
+    Ok(())
+}
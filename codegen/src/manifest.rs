@@ -0,0 +1,266 @@
+//! A machine-readable description of the generated bindings of a DAR, for tools which would
+//! otherwise parse the DAR themselves
+use crate::codegen::package_code::{
+    camel_to_snake, combined_packages, generated_packages, name_and_version, sorted_by_name, sorted_modules,
+};
+use crate::codegen::record_struct::sanitize_ident;
+use crate::daml_custom_data_type_reps::template::{ChoiceRep, TemplateRep};
+use crate::lf_protobuf::com::daml::daml_lf_2::{Package, Type};
+use crate::resolve_type::{GeneratedPackages, resolve_type, type_rep};
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+/// The main package of a DAR with its templates and interfaces, and the paths of their generated
+/// Rust items from the root of the generated code
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Manifest {
+    pub package_id: String,
+    pub package_name: String,
+    pub package_version: String,
+    pub templates: Vec<TemplateManifest>,
+    pub interfaces: Vec<InterfaceManifest>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TemplateManifest {
+    /// Dotted Daml module name
+    pub module: String,
+    pub entity: String,
+    /// The generated struct, e.g. `main::asset::Asset`
+    pub rust_path: String,
+    /// The contract key type, `rust_type` being the generated `<Template>ContractKey` type
+    pub key: Option<TypeManifest>,
+    pub choices: Vec<ChoiceManifest>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct InterfaceManifest {
+    pub module: String,
+    pub entity: String,
+    /// The generated marker type
+    pub rust_path: String,
+    pub view: Option<TypeManifest>,
+    pub choices: Vec<ChoiceManifest>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ChoiceManifest {
+    pub name: String,
+    pub consuming: bool,
+    pub argument: TypeManifest,
+    pub result: TypeManifest,
+    /// The generated `exercise_<choice>` function, if the argument is a record of the module
+    pub exercise_fn: Option<String>,
+}
+
+/// A Daml type and the Rust type representing it
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TypeManifest {
+    /// Daml syntax of the type, e.g. `Optional (ContractId Asset)`
+    pub daml_type: String,
+    /// `None` for types without a generated representation
+    pub rust_type: Option<String>,
+}
+
+/// Describes the main package of a DAR, the first of `packages`
+pub fn dar_manifest(packages: &[(String, Package)]) -> Result<Manifest> {
+    let packages: Vec<(&str, &Package)> = packages.iter().map(|(id, package)| (id.as_str(), package)).collect();
+    let (package_id, package) = *packages.first().context("No packages to describe")?;
+    let generated = generated_packages(&packages, &[(package_id, None)])?;
+    package_manifest(package_id, package, &generated)
+}
+
+/// Describes the main packages of DARs generated together by `dars_code`
+pub fn dars_manifests(dars: &[Vec<(String, Package)>]) -> Result<Vec<Manifest>> {
+    let (packages, main_packages) = combined_packages(dars)?;
+    let generated = generated_packages(&packages, &main_packages)?;
+    main_packages
+        .iter()
+        .map(|(package_id, _)| {
+            let (_, package) = packages.iter().find(|(id, _)| id == package_id).context("Main package not found")?;
+            package_manifest(package_id, package, &generated)
+        })
+        .collect()
+}
+
+fn package_manifest(package_id: &str, package: &Package, generated: &GeneratedPackages) -> Result<Manifest> {
+    let (package_name, package_version) = name_and_version(package)?;
+    let describer = Describer { package, package_id, generated };
+
+    let mut templates = Vec::new();
+    let mut interfaces = Vec::new();
+    for (module_name, module) in sorted_modules(package)? {
+        let module_path = format!("{}::", generated.module_path(package_id, &module_name)?).replace(' ', "");
+        for (_, def_template) in sorted_by_name(package, &module.templates, |template| template.tycon_interned_dname)? {
+            let template = TemplateRep::try_from((def_template, module, package))?;
+            let entity = template.record.record_name.clone();
+            let key = template.key_type.as_ref().map(|key_type| {
+                let mut key = describer.type_manifest(key_type);
+                if key.rust_type.is_some() {
+                    key.rust_type = Some(format!("{}{}ContractKey", module_path, sanitize_ident(&entity)));
+                }
+                key
+            });
+            templates.push(TemplateManifest {
+                rust_path: format!("{}{}", module_path, sanitize_ident(&entity)),
+                key,
+                choices: describer.choices(&template.choices, &module_path),
+                module: module_name.clone(),
+                entity,
+            });
+        }
+        for (entity, def_interface) in sorted_by_name(package, &module.interfaces, |interface| interface.tycon_interned_dname)? {
+            let choices = def_interface
+                .choices
+                .iter()
+                .map(|choice| ChoiceRep::try_from((choice, package)))
+                .collect::<Result<Vec<_>>>()?;
+            interfaces.push(InterfaceManifest {
+                rust_path: format!("{}{}", module_path, sanitize_ident(&entity)),
+                view: def_interface.view.as_ref().map(|view| describer.type_manifest(view)),
+                choices: describer.choices(&choices, &module_path),
+                module: module_name.clone(),
+                entity,
+            });
+        }
+    }
+
+    Ok(Manifest {
+        package_id: package_id.to_string(),
+        package_name,
+        package_version,
+        templates,
+        interfaces,
+    })
+}
+
+/// The manifest as pretty-printed JSON
+pub fn manifest_json(manifest: &Manifest) -> Result<String> {
+    serde_json::to_string_pretty(manifest).context("Failed to serialize manifest")
+}
+
+struct Describer<'a> {
+    package: &'a Package,
+    package_id: &'a str,
+    generated: &'a GeneratedPackages,
+}
+
+impl Describer<'_> {
+    fn type_manifest(&self, typ: &Type) -> TypeManifest {
+        let package = self.package;
+        TypeManifest {
+            daml_type: resolve_type(typ, &package.interned_types, &package.interned_strings, &package.interned_dotted_names),
+            rust_type: type_rep(typ, package)
+                .and_then(|type_rep| type_rep.root_rust_type(self.generated, self.package_id))
+                .ok()
+                .map(|rust_type| rust_type.to_string().replace(' ', "")),
+        }
+    }
+
+    fn choices(&self, choices: &[ChoiceRep], module_path: &str) -> Vec<ChoiceManifest> {
+        choices
+            .iter()
+            .map(|choice| {
+                let argument = choice.arg_type.as_ref().map(|arg_type| self.type_manifest(arg_type));
+                let result = choice.return_type.as_ref().map(|return_type| self.type_manifest(return_type));
+                let unknown = |daml_type: &str| TypeManifest { daml_type: daml_type.to_string(), rust_type: None };
+                // Choices get an exercise function if the argument is a record of their module
+                let exercise_fn = argument
+                    .as_ref()
+                    .and_then(|argument| argument.rust_type.as_ref())
+                    .filter(|rust_type| rust_type.strip_prefix(module_path).is_some_and(|name| !name.contains("::")))
+                    .and(result.as_ref().and_then(|result| result.rust_type.as_ref()))
+                    .map(|_| format!("{}exercise_{}", module_path, camel_to_snake(&choice.choice_name)));
+                ChoiceManifest {
+                    name: choice.choice_name.clone(),
+                    consuming: choice.consuming,
+                    argument: argument.unwrap_or_else(|| unknown(&choice.arg_type_name)),
+                    result: result.unwrap_or_else(|| unknown(&choice.return_type_name)),
+                    exercise_fn,
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lf_protobuf::com::daml::daml_lf_2::def_data_type::DataCons;
+    use crate::lf_protobuf::com::daml::daml_lf_2::def_template::DefKey;
+    use crate::lf_protobuf::com::daml::daml_lf_2::{
+        BuiltinType, DefDataType, DefTemplate, InternedDottedName, Module, ModuleId, PackageMetadata,
+        SelfOrImportedPackageId, TemplateChoice, TypeConId, Unit, VarWithType, self_or_imported_package_id, r#type,
+    };
+
+    fn builtin(builtin: BuiltinType) -> Type {
+        Type { sum: Some(r#type::Sum::Builtin(r#type::Builtin { builtin: builtin as i32, args: vec![] })) }
+    }
+
+    fn self_con(name_interned_dname: i32) -> Type {
+        Type {
+            sum: Some(r#type::Sum::Con(r#type::Con {
+                tycon: Some(TypeConId {
+                    module: Some(ModuleId {
+                        package_id: Some(SelfOrImportedPackageId {
+                            sum: Some(self_or_imported_package_id::Sum::SelfPackageId(Unit {})),
+                        }),
+                        module_name_interned_dname: 0,
+                    }),
+                    name_interned_dname,
+                }),
+                args: vec![],
+            })),
+        }
+    }
+
+    #[test]
+    fn test_dar_manifest() -> Result<()> {
+        // module Main.Asset: template Asset with owner : Party, key owner, choice Give : Int
+        let record = |name_interned_dname| DefDataType {
+            name_interned_dname,
+            data_cons: Some(DataCons::Record(Default::default())),
+            ..Default::default()
+        };
+        let package = Package {
+            interned_strings: ["daml-asset", "0.0.1", "Main", "Asset", "Give"].map(String::from).to_vec(),
+            interned_dotted_names: vec![
+                InternedDottedName { segments_interned_str: vec![2, 3] },
+                InternedDottedName { segments_interned_str: vec![3] },
+                InternedDottedName { segments_interned_str: vec![4] },
+            ],
+            metadata: Some(PackageMetadata { name_interned_str: 0, version_interned_str: 1, upgraded_package_id: None }),
+            modules: vec![Module {
+                name_interned_dname: 0,
+                data_types: vec![record(1), record(2)],
+                templates: vec![DefTemplate {
+                    tycon_interned_dname: 1,
+                    key: Some(DefKey { r#type: Some(builtin(BuiltinType::Party)), ..Default::default() }),
+                    choices: vec![TemplateChoice {
+                        name_interned_str: 4,
+                        consuming: true,
+                        arg_binder: Some(VarWithType { r#type: Some(self_con(2)), ..Default::default() }),
+                        ret_type: Some(builtin(BuiltinType::Int64)),
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let manifest = dar_manifest(&[("abc".to_string(), package)])?;
+        assert_eq!(manifest.package_name, "daml-asset");
+        let template = &manifest.templates[0];
+        assert_eq!((template.module.as_str(), template.entity.as_str()), ("Main.Asset", "Asset"));
+        assert_eq!(template.rust_path, "main::asset::Asset");
+        assert_eq!(template.key.as_ref().and_then(|key| key.rust_type.as_deref()), Some("main::asset::AssetContractKey"));
+        let choice = &template.choices[0];
+        assert_eq!(choice.argument.rust_type.as_deref(), Some("main::asset::Give"));
+        assert_eq!(choice.result, TypeManifest { daml_type: "Int".to_string(), rust_type: Some("DamlInt".to_string()) });
+        assert_eq!(choice.exercise_fn.as_deref(), Some("main::asset::exercise_give"));
+        assert!(manifest_json(&manifest)?.contains("\"package_id\": \"abc\""));
+        Ok(())
+    }
+}
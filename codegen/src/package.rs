@@ -1,25 +1,146 @@
-use crate::archive::archive_from_dar;
+use crate::archive::{archive_from_dar, archives_from_dar};
+use crate::dar_inspect::metadata_name_and_version;
 use anyhow::{Context, Result};
 use crate::lf_protobuf::com::daml::daml_lf_2::Package;
-use crate::lf_protobuf::com::daml::daml_lf_dev::ArchivePayload;
+use crate::lf_protobuf::com::daml::daml_lf_dev::archive_payload::Sum;
+use crate::lf_protobuf::com::daml::daml_lf_dev::{Archive, ArchivePayload};
 use prost::Message;
 
 
 pub fn package_from_dar(path: &str) -> Result<Package> {
     let archive = archive_from_dar(path)
         .with_context(|| format!("Failed to read archive from '{}'", path))?;
+    package_from_archive(&archive)
+}
 
+/// Reads all packages of a DAR with their package ids, the main package first.
+pub fn packages_from_dar(path: &str) -> Result<Vec<(String, Package)>> {
+    let archives = archives_from_dar(path)
+        .with_context(|| format!("Failed to read archives from '{}'", path))?;
+    archives
+        .into_iter()
+        .map(|archive| Ok((archive.hash.clone(), package_from_archive(&archive)?)))
+        .collect()
+}
+
+/// The identity of the main package of a DAR, from its archive metadata
+#[derive(Debug, Clone, PartialEq)]
+pub struct PackageInfo {
+    pub package_id: String,
+    pub name: String,
+    pub version: String,
+    /// `<major>.<minor>`, e.g. `2.1`
+    pub lf_version: String,
+    /// The package ids of the other DALFs of the DAR, the dependencies of the main package
+    pub dependencies: Vec<String>,
+}
+
+/// Reads the package id, name, version and Daml-LF version of the main package of a DAR, and the
+/// package ids of its dependencies. Name and version are empty for Daml-LF 1 packages, which
+/// cannot be decoded.
+pub fn package_info_from_dar(path: &str) -> Result<PackageInfo> {
+    let archives = archives_from_dar(path)
+        .with_context(|| format!("Failed to read archives from '{}'", path))?;
+    package_info(&archives)
+}
+
+fn package_info(archives: &[Archive]) -> Result<PackageInfo> {
+    let (main, dependencies) = archives.split_first().context("DAR without packages")?;
+    let capability = dalf_capability(main)?;
+    let (name, version) = match capability.unsupported {
+        None => metadata_name_and_version(&package_from_archive(main)?)?,
+        Some(_) => (String::new(), String::new()),
+    };
+    Ok(PackageInfo {
+        package_id: main.hash.clone(),
+        name,
+        version,
+        lf_version: capability.lf_version,
+        dependencies: dependencies.iter().map(|archive| archive.hash.clone()).collect(),
+    })
+}
+
+/// The Daml-LF version of a DALF of a DAR, and whether its package can be decoded
+#[derive(Debug, Clone, PartialEq)]
+pub struct DalfCapability {
+    pub package_id: String,
+    /// `<major>.<minor>`, e.g. `1.15` or `2.1`
+    pub lf_version: String,
+    /// Why the package cannot be decoded, `None` if it can
+    pub unsupported: Option<String>,
+}
+
+/// Reports for each DALF of a DAR, the main package first, whether it can be inspected and
+/// code-generated. Only Daml-LF 2 packages, built with Daml SDK 3, are supported; the packages of
+/// a DAR are all Daml-LF 1 or all Daml-LF 2.
+pub fn dalf_capabilities(path: &str) -> Result<Vec<DalfCapability>> {
+    let archives = archives_from_dar(path)
+        .with_context(|| format!("Failed to read archives from '{}'", path))?;
+    archives.iter().map(dalf_capability).collect()
+}
+
+fn dalf_capability(archive: &Archive) -> Result<DalfCapability> {
+    let payload = ArchivePayload::decode(&*archive.payload)
+        .with_context(|| "Failed to decode ArchivePayload")?;
+    let (lf_version, unsupported) = match &payload.sum {
+        Some(Sum::DamlLf1(_)) => {
+            let lf_version = format!("1.{}", payload.minor);
+            let reason = format!(
+                "Daml-LF {} is not supported, only Daml-LF 2; rebuild the DAR with Daml SDK 3",
+                lf_version
+            );
+            (lf_version, Some(reason))
+        }
+        Some(Sum::DamlLf2(_)) => (format!("2.{}", payload.minor), None),
+        None => (String::new(), Some("ArchivePayload without package".to_string())),
+    };
+    Ok(DalfCapability { package_id: archive.hash.clone(), lf_version, unsupported })
+}
+
+pub(crate) fn package_from_archive(archive: &Archive) -> Result<Package> {
     let payload = ArchivePayload::decode(&*archive.payload)
         .with_context(|| "Failed to decode ArchivePayload")?;
 
-    if let Some(crate::lf_protobuf::com::daml::daml_lf_dev::archive_payload::Sum::DamlLf2(
-        dalf_bytes,
-    )) = payload.sum
-    {
-        let package = crate::lf_protobuf::com::daml::daml_lf_2::Package::decode(&*dalf_bytes)
+    if let Some(Sum::DamlLf2(dalf_bytes)) = payload.sum {
+        let package = Package::decode(&*dalf_bytes)
             .with_context(|| "Failed to decode Package from DALF bytes")?;
         Ok(package)
     } else {
-        anyhow::bail!("Expected DamlLf2 variant in ArchivePayload");
+        let reason = dalf_capability(archive)?.unsupported.unwrap_or_default();
+        anyhow::bail!("Package {}: {}", archive.hash, reason);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn archive(hash: &str, payload: ArchivePayload) -> Archive {
+        Archive { hash: hash.to_string(), payload: payload.encode_to_vec(), ..Default::default() }
+    }
+
+    #[test]
+    fn test_dalf_capability() -> Result<()> {
+        let lf1 = archive("abc", ArchivePayload { minor: "15".to_string(), sum: Some(Sum::DamlLf1(vec![])) });
+        let capability = dalf_capability(&lf1)?;
+        assert_eq!(capability.lf_version, "1.15");
+        assert!(capability.unsupported.is_some());
+        let error = package_from_archive(&lf1).unwrap_err().to_string();
+        assert!(error.starts_with("Package abc: Daml-LF 1.15 is not supported"), "{}", error);
+
+        let lf2 = archive(
+            "def",
+            ArchivePayload { minor: "1".to_string(), sum: Some(Sum::DamlLf2(Package::default().encode_to_vec())) },
+        );
+        assert_eq!(
+            dalf_capability(&lf2)?,
+            DalfCapability { package_id: "def".to_string(), lf_version: "2.1".to_string(), unsupported: None }
+        );
+        assert!(package_from_archive(&lf2).is_ok());
+
+        let info = package_info(&[lf1, lf2])?;
+        assert_eq!((info.package_id.as_str(), info.lf_version.as_str()), ("abc", "1.15"));
+        assert_eq!(info.dependencies, vec!["def".to_string()]);
+        Ok(())
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,274 @@
+//! The `TemplateRegistry` of DARs: schemas of the payloads, keys and choice arguments of their
+//! templates, with the data types they refer to, for tools encoding the values of these templates
+//! without the generated code, like the ledger explorer
+use crate::codegen::package_code::sorted_modules;
+use crate::daml_custom_data_type_reps::template::TemplateRep;
+use crate::dar_inspect::{dotted_name, interned_string};
+use crate::lf_protobuf::com::daml::daml_lf_2::def_data_type::DataCons;
+use crate::lf_protobuf::com::daml::daml_lf_2::{BuiltinType, FieldWithType, Package, Type};
+use crate::resolve_type::{PackageRef, TypeRep, type_rep};
+use anyhow::{Context, Result, bail};
+use daml_type_rep::schema::{DataTypeDefinition, DataTypeSchema, FieldSchema, TemplateRegistry, TemplateSchema, TypeSchema};
+use std::collections::HashMap;
+
+/// The registry of the templates of the main package of a DAR, the first of `packages`
+pub fn dar_registry(packages: &[(String, Package)]) -> Result<TemplateRegistry> {
+    registry(&[packages])
+}
+
+/// The registry of the templates of the main packages of DARs
+pub fn dars_registry(dars: &[Vec<(String, Package)>]) -> Result<TemplateRegistry> {
+    registry(&dars.iter().map(Vec::as_slice).collect::<Vec<_>>())
+}
+
+/// The registry as pretty-printed JSON
+pub fn registry_json(registry: &TemplateRegistry) -> Result<String> {
+    serde_json::to_string_pretty(registry).context("Failed to serialize template registry")
+}
+
+fn registry(dars: &[&[(String, Package)]]) -> Result<TemplateRegistry> {
+    let packages: HashMap<&str, &Package> =
+        dars.iter().flat_map(|packages| packages.iter()).map(|(id, package)| (id.as_str(), package)).collect();
+    let mut builder = RegistryBuilder { packages, registry: TemplateRegistry::default(), pending: Vec::new() };
+    for packages in dars {
+        let (package_id, package) = packages.first().context("DAR without packages")?;
+        for (module_name, module) in sorted_modules(package)? {
+            for def_template in &module.templates {
+                let template = TemplateRep::try_from((def_template, module, package))?;
+                let name = format!("{}.{}", module_name, template.record.record_name);
+                let payload = builder.data_type_ref(package_id, &module_name, &template.record.record_name, vec![]);
+                let key = template.key_type.as_ref().map(|key| builder.type_schema(package_id, package, key));
+                let mut choices = std::collections::BTreeMap::new();
+                for choice in &template.choices {
+                    let argument = match &choice.arg_type {
+                        Some(arg_type) => builder.type_schema(package_id, package, arg_type),
+                        None => TypeSchema::Unknown,
+                    };
+                    choices.insert(choice.choice_name.clone(), argument);
+                }
+                let schema = TemplateSchema { payload, key, choices };
+                if builder.registry.templates.insert(name.clone(), schema).is_some() {
+                    bail!("Two DARs have the template {}", name);
+                }
+            }
+        }
+    }
+    builder.define_pending()?;
+    Ok(builder.registry)
+}
+
+struct RegistryBuilder<'a> {
+    packages: HashMap<&'a str, &'a Package>,
+    registry: TemplateRegistry,
+    /// Data types referred to but not defined yet, as package id, module and name
+    pending: Vec<(String, String, String)>,
+}
+
+impl RegistryBuilder<'_> {
+    /// Types which cannot be resolved are `Unknown`, encoded like untyped values
+    fn type_schema(&mut self, package_id: &str, package: &Package, typ: &Type) -> TypeSchema {
+        match type_rep(typ, package) {
+            Ok(type_rep) => self.type_rep_schema(package_id, &type_rep),
+            Err(_) => TypeSchema::Unknown,
+        }
+    }
+
+    fn type_rep_schema(&mut self, package_id: &str, type_rep: &TypeRep) -> TypeSchema {
+        let mut arg = |args: &[TypeRep], index: usize| {
+            Box::new(args.get(index).map(|arg| self.type_rep_schema(package_id, arg)).unwrap_or(TypeSchema::Unknown))
+        };
+        match type_rep {
+            TypeRep::Builtin { builtin, args } => match builtin {
+                BuiltinType::Unit => TypeSchema::Unit,
+                BuiltinType::Bool => TypeSchema::Bool,
+                BuiltinType::Int64 => TypeSchema::Int64,
+                BuiltinType::Numeric => TypeSchema::Numeric,
+                BuiltinType::Text => TypeSchema::Text,
+                BuiltinType::Party => TypeSchema::Party,
+                BuiltinType::ContractId => TypeSchema::ContractId,
+                BuiltinType::Date => TypeSchema::Date,
+                BuiltinType::Timestamp => TypeSchema::Timestamp,
+                BuiltinType::Optional => TypeSchema::Optional { element: arg(args, 0) },
+                BuiltinType::List => TypeSchema::List { element: arg(args, 0) },
+                BuiltinType::Textmap => TypeSchema::TextMap { value: arg(args, 0) },
+                BuiltinType::Genmap => TypeSchema::GenMap { key: arg(args, 0), value: arg(args, 1) },
+                _ => TypeSchema::Unknown,
+            },
+            TypeRep::Con { package, module, name, args } => {
+                let defining_package = match package {
+                    PackageRef::SelfPackage => package_id,
+                    PackageRef::Imported(imported) => imported.as_str(),
+                };
+                let args = args.iter().map(|arg| self.type_rep_schema(package_id, arg)).collect();
+                self.data_type_ref(defining_package, module, name, args)
+            }
+            TypeRep::Var { name, args } if args.is_empty() => TypeSchema::Var { name: name.clone() },
+            _ => TypeSchema::Unknown,
+        }
+    }
+
+    fn data_type_ref(&mut self, package_id: &str, module: &str, name: &str, args: Vec<TypeSchema>) -> TypeSchema {
+        let id = format!("{}:{}:{}", package_id, module, name);
+        if !self.registry.data_types.contains_key(&id) {
+            self.pending.push((package_id.to_string(), module.to_string(), name.to_string()));
+        }
+        TypeSchema::DataType { id, args }
+    }
+
+    /// Defines the referred data types, and the ones their definitions refer to. Data types of
+    /// packages missing from the DARs are left out, their values are not encoded by schema.
+    fn define_pending(&mut self) -> Result<()> {
+        while let Some((package_id, module_name, name)) = self.pending.pop() {
+            let id = format!("{}:{}:{}", package_id, module_name, name);
+            if self.registry.data_types.contains_key(&id) {
+                continue;
+            }
+            let Some(package) = self.packages.get(package_id.as_str()).copied() else {
+                continue;
+            };
+            let Some(def_data_type) = package
+                .modules
+                .iter()
+                .filter(|module| dotted_name(package, module.name_interned_dname).is_ok_and(|m| m == module_name))
+                .flat_map(|module| &module.data_types)
+                .find(|data_type| dotted_name(package, data_type.name_interned_dname).is_ok_and(|n| n == name))
+            else {
+                continue;
+            };
+            let params = def_data_type
+                .params
+                .iter()
+                .map(|param| interned_string(package, param.var_interned_str))
+                .collect::<Result<Vec<_>>>()?;
+            let mut fields = |fields: &[FieldWithType]| -> Result<Vec<FieldSchema>> {
+                fields
+                    .iter()
+                    .map(|field| {
+                        let typ = field.r#type.as_ref().context("Field without type")?;
+                        Ok(FieldSchema {
+                            name: interned_string(package, field.field_interned_str)?,
+                            schema: self.type_schema(&package_id, package, typ),
+                        })
+                    })
+                    .collect()
+            };
+            let definition = match &def_data_type.data_cons {
+                Some(DataCons::Record(record)) => DataTypeDefinition::Record { fields: fields(&record.fields)? },
+                Some(DataCons::Variant(variant)) => DataTypeDefinition::Variant { constructors: fields(&variant.fields)? },
+                Some(DataCons::Enum(enum_constructors)) => DataTypeDefinition::Enum {
+                    constructors: enum_constructors
+                        .constructors_interned_str
+                        .iter()
+                        .map(|constructor| interned_string(package, *constructor))
+                        .collect::<Result<Vec<_>>>()?,
+                },
+                _ => continue,
+            };
+            self.registry.data_types.insert(id, DataTypeSchema { params, definition });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lf_protobuf::com::daml::daml_lf_2::def_data_type::Fields;
+    use crate::lf_protobuf::com::daml::daml_lf_2::{
+        DefDataType, DefTemplate, InternedDottedName, Module, ModuleId, PackageMetadata, SelfOrImportedPackageId,
+        TemplateChoice, TypeConId, Unit, VarWithType, r#type, self_or_imported_package_id,
+    };
+
+    fn builtin(builtin: BuiltinType, args: Vec<Type>) -> Type {
+        Type { sum: Some(r#type::Sum::Builtin(r#type::Builtin { builtin: builtin as i32, args })) }
+    }
+
+    fn self_con(name_interned_dname: i32) -> Type {
+        Type {
+            sum: Some(r#type::Sum::Con(r#type::Con {
+                tycon: Some(TypeConId {
+                    module: Some(ModuleId {
+                        package_id: Some(SelfOrImportedPackageId {
+                            sum: Some(self_or_imported_package_id::Sum::SelfPackageId(Unit {})),
+                        }),
+                        module_name_interned_dname: 0,
+                    }),
+                    name_interned_dname,
+                }),
+                args: vec![],
+            })),
+        }
+    }
+
+    #[test]
+    fn test_dar_registry() -> Result<()> {
+        // template Asset with owner : Party; note : Optional Text, choice Give with newOwner : Party
+        let record = |name_interned_dname, fields: Vec<(i32, Type)>| DefDataType {
+            name_interned_dname,
+            data_cons: Some(DataCons::Record(Fields {
+                fields: fields
+                    .into_iter()
+                    .map(|(field_interned_str, typ)| FieldWithType { field_interned_str, r#type: Some(typ) })
+                    .collect(),
+            })),
+            ..Default::default()
+        };
+        let package = Package {
+            interned_strings: ["daml-asset", "0.0.1", "Main", "Asset", "Give", "owner", "note", "newOwner"]
+                .map(String::from)
+                .to_vec(),
+            interned_dotted_names: vec![
+                InternedDottedName { segments_interned_str: vec![2] },
+                InternedDottedName { segments_interned_str: vec![3] },
+                InternedDottedName { segments_interned_str: vec![4] },
+            ],
+            metadata: Some(PackageMetadata { name_interned_str: 0, version_interned_str: 1, upgraded_package_id: None }),
+            modules: vec![Module {
+                name_interned_dname: 0,
+                data_types: vec![
+                    record(
+                        1,
+                        vec![
+                            (5, builtin(BuiltinType::Party, vec![])),
+                            (6, builtin(BuiltinType::Optional, vec![builtin(BuiltinType::Text, vec![])])),
+                        ],
+                    ),
+                    record(2, vec![(7, builtin(BuiltinType::Party, vec![]))]),
+                ],
+                templates: vec![DefTemplate {
+                    tycon_interned_dname: 1,
+                    choices: vec![TemplateChoice {
+                        name_interned_str: 4,
+                        arg_binder: Some(VarWithType { r#type: Some(self_con(2)), ..Default::default() }),
+                        ret_type: Some(builtin(BuiltinType::Unit, vec![])),
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let registry = dar_registry(&[("abc".to_string(), package)])?;
+        let template = registry.template("Main", "Asset").context("Asset not registered")?;
+        let data_type = |id: &str| TypeSchema::DataType { id: id.to_string(), args: vec![] };
+        assert_eq!(template.payload, data_type("abc:Main:Asset"));
+        assert_eq!(template.choices["Give"], data_type("abc:Main:Give"));
+        assert_eq!(
+            registry.data_types["abc:Main:Asset"].definition,
+            DataTypeDefinition::Record {
+                fields: vec![
+                    FieldSchema { name: "owner".to_string(), schema: TypeSchema::Party },
+                    FieldSchema {
+                        name: "note".to_string(),
+                        schema: TypeSchema::Optional { element: Box::new(TypeSchema::Text) },
+                    },
+                ],
+            }
+        );
+        assert!(registry.data_types.contains_key("abc:Main:Give"));
+        assert!(registry_json(&registry)?.contains("\"abc:Main:Asset\""));
+        Ok(())
+    }
+}
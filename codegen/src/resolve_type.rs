@@ -1,77 +1,350 @@
+use crate::codegen::package_code::module_idents;
+use crate::codegen::record_struct::sanitize_ident;
 use crate::lf_protobuf::com::daml::daml_lf_2::InternedDottedName;
-use crate::lf_protobuf::com::daml::daml_lf_2::{BuiltinType, Type, r#type::Sum};
+use crate::lf_protobuf::com::daml::daml_lf_2::self_or_imported_package_id;
+use crate::lf_protobuf::com::daml::daml_lf_2::{BuiltinType, Package, Type, r#type::Sum};
+use anyhow::{Context, Result, anyhow, bail};
+use proc_macro2::TokenStream;
+use quote::quote;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 
-pub struct TypeRep {
-    pub name: String,
-    pub interned_type: Type,
+/// A Daml-LF type with interned names and types resolved
+#[derive(Debug, Clone, PartialEq)]
+pub enum TypeRep {
+    /// A builtin type applied to its arguments, e.g. `Optional Text`
+    Builtin { builtin: BuiltinType, args: Vec<TypeRep> },
+    /// A data type applied to its arguments
+    Con {
+        package: PackageRef,
+        /// Dotted module name
+        module: String,
+        /// Dotted type name, e.g. `Shape.Circle` for the record of a variant constructor
+        name: String,
+        args: Vec<TypeRep>,
+    },
+    /// A type variable, in the definition of a generic data type
+    Var { name: String, args: Vec<TypeRep> },
+    /// A type level natural number, the scale of a `Numeric`
+    Nat(i64),
+    Struct(Vec<(String, TypeRep)>),
 }
 
+/// The package a data type is defined in
+#[derive(Debug, Clone, PartialEq)]
+pub enum PackageRef {
+    /// The package containing the reference
+    SelfPackage,
+    /// A dependency, by package id
+    Imported(String),
+}
+
+struct Interned<'a> {
+    types: &'a [Type],
+    strings: &'a [String],
+    dotted_names: &'a [InternedDottedName],
+}
+
+impl Interned<'_> {
+    fn string(&self, index: i32) -> Result<String> {
+        self.strings
+            .get(index as usize)
+            .cloned()
+            .with_context(|| format!("Interned string {} not found", index))
+    }
+
+    fn dotted_name(&self, index: i32) -> Result<String> {
+        let dotted_name = self
+            .dotted_names
+            .get(index as usize)
+            .with_context(|| format!("Interned dotted name {} not found", index))?;
+        let segments = dotted_name
+            .segments_interned_str
+            .iter()
+            .map(|segment| self.string(*segment))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(segments.join("."))
+    }
+
+    fn type_rep(&self, typ: &Type) -> Result<TypeRep> {
+        let args = |args: &[Type]| args.iter().map(|arg| self.type_rep(arg)).collect::<Result<Vec<_>>>();
+        match &typ.sum {
+            Some(Sum::InternedType(index)) => {
+                let interned = self
+                    .types
+                    .get(*index as usize)
+                    .with_context(|| format!("Interned type {} not found", index))?;
+                self.type_rep(interned)
+            }
+            Some(Sum::Builtin(builtin)) => Ok(TypeRep::Builtin {
+                builtin: BuiltinType::try_from(builtin.builtin)
+                    .map_err(|_| anyhow!("unknown builtin type {}", builtin.builtin))?,
+                args: args(&builtin.args)?,
+            }),
+            Some(Sum::Con(con)) => {
+                let tycon = con.tycon.as_ref().context("Type constructor without name")?;
+                let module_id = tycon.module.as_ref().context("Type constructor without module")?;
+                let package = match module_id.package_id.as_ref().and_then(|p| p.sum.as_ref()) {
+                    Some(self_or_imported_package_id::Sum::SelfPackageId(_)) => PackageRef::SelfPackage,
+                    Some(self_or_imported_package_id::Sum::ImportedPackageIdInternedStr(index)) => {
+                        PackageRef::Imported(self.string(*index)?)
+                    }
+                    None => bail!("Type constructor without package"),
+                };
+                Ok(TypeRep::Con {
+                    package,
+                    module: self.dotted_name(module_id.module_name_interned_dname)?,
+                    name: self.dotted_name(tycon.name_interned_dname)?,
+                    args: args(&con.args)?,
+                })
+            }
+            Some(Sum::Var(var)) => Ok(TypeRep::Var {
+                name: self.string(var.var_interned_str)?,
+                args: args(&var.args)?,
+            }),
+            Some(Sum::Nat(n)) => Ok(TypeRep::Nat(*n)),
+            Some(Sum::Struct(r#struct)) => Ok(TypeRep::Struct(
+                r#struct
+                    .fields
+                    .iter()
+                    .map(|field| {
+                        let typ = field.r#type.as_ref().context("Struct field without type")?;
+                        Ok((self.string(field.field_interned_str)?, self.type_rep(typ)?))
+                    })
+                    .collect::<Result<Vec<_>>>()?,
+            )),
+            Some(Sum::Forall(_)) => bail!("type abstractions are not serializable"),
+            Some(Sum::Syn(_)) => bail!("type synonyms are not serializable"),
+            None => bail!("Type without sum"),
+        }
+    }
+}
+
+/// Resolves a Daml-LF type of the package to a `TypeRep`
+pub fn type_rep(typ: &Type, package: &Package) -> Result<TypeRep> {
+    Interned {
+        types: &package.interned_types,
+        strings: &package.interned_strings,
+        dotted_names: &package.interned_dotted_names,
+    }
+    .type_rep(typ)
+}
+
+/// The Daml syntax of a Daml-LF type, e.g. `Optional (ContractId Asset)`
 pub fn resolve_type(
     typ: &Type,
     interned_types: &[Type],
     interned_strings: &[String],
     interned_dotted_names: &[InternedDottedName],
 ) -> String {
-    match &typ.sum {
-        Some(Sum::InternedType(idx)) => {
-            if let Some(interned_type) = interned_types.get(*idx as usize) {
-                resolve_type(
-                    interned_type,
-                    interned_types,
-                    interned_strings,
-                    interned_dotted_names,
-                )
-            } else {
-                format!("<invalid interned type idx {}>", idx)
+    let interned = Interned {
+        types: interned_types,
+        strings: interned_strings,
+        dotted_names: interned_dotted_names,
+    };
+    interned
+        .type_rep(typ)
+        .map(|type_rep| type_rep.to_string())
+        .unwrap_or_else(|e| format!("<{}>", e))
+}
+
+/// Daml standard library packages, whose data types are not generated. The ones with a
+/// `daml-type-rep` representation are listed in `stdlib_rust_type`.
+pub const STDLIB_PACKAGES: [&str; 3] = ["daml-prim", "daml-stdlib", "ghc-stdlib"];
+
+/// The packages of one or more DARs with generated code: the modules of the main package of a DAR
+/// are at the root of the generated code, or in `<package>` when generating several DARs, the
+/// modules of dependencies in `deps::<package>`
+#[derive(Debug, Clone, Default)]
+pub struct GeneratedPackages {
+    /// Rust module names of the main packages by package id, `None` for a main package at the root
+    pub main_packages: HashMap<String, Option<String>>,
+    /// Package names of the packages of the DARs by package id
+    pub package_names: HashMap<String, String>,
+    /// Rust module names of the dependencies with generated code by package id
+    pub dependencies: HashMap<String, String>,
+}
+
+impl GeneratedPackages {
+    /// The path from the root of the generated code to a Daml module
+    pub(crate) fn module_path(&self, package_id: &str, module: &str) -> Result<TokenStream> {
+        let module_idents = module_idents(module);
+        if let Some(main_package) = self.main_packages.get(package_id) {
+            let package_path = main_package.as_deref().map(sanitize_ident).map(|ident| quote! { #ident:: });
+            Ok(quote! { #package_path #( #module_idents )::* })
+        } else {
+            let package_ident = sanitize_ident(self.dependencies.get(package_id).with_context(|| {
+                format!("types of package {} are not generated", self.package_name(package_id))
+            })?);
+            Ok(quote! { deps::#package_ident::#( #module_idents )::* })
+        }
+    }
+
+    /// How deep a module of a package is nested in the generated code, `Splice.Amulet` of the main
+    /// package being in `splice::amulet`
+    fn depth(&self, package_id: &str, module: &str) -> usize {
+        let segments = module.split('.').count();
+        match self.main_packages.get(package_id) {
+            Some(None) => segments,
+            Some(Some(_)) => 1 + segments,
+            None => 2 + segments,
+        }
+    }
+
+    /// The package name, or the package id of packages not in the DAR
+    pub fn package_name<'a>(&'a self, package_id: &'a str) -> &'a str {
+        self.package_names.get(package_id).map_or(package_id, String::as_str)
+    }
+}
+
+/// The `daml-type-rep` types representing standard library data types
+fn stdlib_rust_type(module: &str, name: &str, args: &[TokenStream]) -> Option<TokenStream> {
+    match (module, name, args) {
+        ("DA.Types", "Tuple2", [a, b]) => Some(quote! { DamlTuple2<#a, #b> }),
+        ("DA.Types", "Tuple3", [a, b, c]) => Some(quote! { DamlTuple3<#a, #b, #c> }),
+        ("DA.Set.Types", "Set", [a]) => Some(quote! { DamlSet<#a> }),
+        ("DA.Time.Types", "RelTime", []) => Some(quote! { DamlRelTime }),
+        _ => None,
+    }
+}
+
+impl TypeRep {
+    /// The Rust type representing this type in the code generated for the module `current_module`
+    /// of the package `package_id`, using the `daml-type-rep` built-in types and the generated types
+    pub fn rust_type(&self, packages: &GeneratedPackages, package_id: &str, current_module: &str) -> Result<TokenStream> {
+        self.rust_type_in(packages, package_id, Some(current_module))
+    }
+
+    /// The Rust type representing this type, with the paths of generated types starting at the root
+    /// of the generated code, e.g. `main::asset::Asset`
+    pub fn root_rust_type(&self, packages: &GeneratedPackages, package_id: &str) -> Result<TokenStream> {
+        self.rust_type_in(packages, package_id, None)
+    }
+
+    fn rust_type_in(&self, packages: &GeneratedPackages, package_id: &str, current_module: Option<&str>) -> Result<TokenStream> {
+        match self {
+            TypeRep::Builtin { builtin, args } => {
+                let arg = |index: usize| args[index].rust_type_in(packages, package_id, current_module);
+                let tokens = match (builtin, args.len()) {
+                    (BuiltinType::Unit, 0) => quote! { DamlUnit },
+                    (BuiltinType::Bool, 0) => quote! { DamlBool },
+                    (BuiltinType::Int64, 0) => quote! { DamlInt },
+                    (BuiltinType::Date, 0) => quote! { DamlDate },
+                    (BuiltinType::Timestamp, 0) => quote! { DamlTimestamp },
+                    (BuiltinType::Party, 0) => quote! { DamlParty },
+                    (BuiltinType::Text, 0) => quote! { DamlText },
+                    (BuiltinType::Numeric, 1) if args[0] == TypeRep::Nat(10) => quote! { DamlDecimal },
+                    (BuiltinType::Numeric, 1) => quote! { DamlNumeric },
+                    (BuiltinType::ContractId, 1) => quote! { DamlContractId },
+                    (BuiltinType::Optional, 1) => {
+                        let a = arg(0)?;
+                        quote! { DamlOptional<#a> }
+                    }
+                    (BuiltinType::List, 1) => {
+                        let a = arg(0)?;
+                        quote! { DamlList<#a> }
+                    }
+                    (BuiltinType::Textmap, 1) => {
+                        let v = arg(0)?;
+                        quote! { DamlTextMap<#v> }
+                    }
+                    (BuiltinType::Genmap, 2) => {
+                        let (k, v) = (arg(0)?, arg(1)?);
+                        quote! { DamlGenMap<#k, #v> }
+                    }
+                    (other, n) => bail!("builtin type {:?} with {} arguments is not supported", other, n),
+                };
+                Ok(tokens)
+            }
+            TypeRep::Con { package, module, name, args } => {
+                let target_package = match package {
+                    PackageRef::SelfPackage => package_id,
+                    PackageRef::Imported(imported) => imported.as_str(),
+                };
+                let package_name = packages.package_name(target_package);
+                if STDLIB_PACKAGES.contains(&package_name) {
+                    let args = args
+                        .iter()
+                        .map(|arg| arg.rust_type_in(packages, package_id, current_module))
+                        .collect::<Result<Vec<_>>>()?;
+                    return stdlib_rust_type(module, name, &args)
+                        .with_context(|| format!("type {}:{} of package {} is not supported", module, name, package_name));
+                }
+                if !args.is_empty() {
+                    bail!("applied type constructors are not supported");
+                }
+                let type_ident = sanitize_ident(name);
+                if target_package == package_id && Some(module.as_str()) == current_module {
+                    return Ok(quote! { #type_ident });
+                }
+                let depth = current_module.map_or(0, |current_module| packages.depth(package_id, current_module));
+                let up = (0..depth).map(|_| quote! { super:: });
+                let module_path = packages.module_path(target_package, module)?;
+                Ok(quote! { #( #up )* #module_path::#type_ident })
             }
+            other => bail!("type {} is not supported", other),
         }
-        Some(Sum::Con(con)) => {
-            if let Some(tycon_id) = &con.tycon {
-                let dotted_name = interned_dotted_names
-                    .get(tycon_id.name_interned_dname as usize)
-                    .unwrap();
-                dbg!(&dotted_name);
-                let name = interned_strings
-                    .get(dotted_name.segments_interned_str[0] as usize)
-                    .cloned()
-                    .unwrap();
-                name.to_string()
-            } else {
-                "<unknown Con>".to_string()
+    }
+
+    /// Adds the ids of the packages whose data types this type refers to
+    pub fn referenced_packages(&self, package_ids: &mut HashSet<String>) {
+        match self {
+            TypeRep::Con { package, args, .. } => {
+                if let PackageRef::Imported(package_id) = package {
+                    package_ids.insert(package_id.clone());
+                }
+                args.iter().for_each(|arg| arg.referenced_packages(package_ids));
+            }
+            TypeRep::Builtin { args, .. } | TypeRep::Var { args, .. } => {
+                args.iter().for_each(|arg| arg.referenced_packages(package_ids));
             }
+            TypeRep::Struct(fields) => fields.iter().for_each(|(_, typ)| typ.referenced_packages(package_ids)),
+            TypeRep::Nat(_) => {}
         }
-        Some(Sum::Builtin(builtin)) => std::convert::TryFrom::try_from(builtin.builtin)
-            .map(|b: BuiltinType| format!("{:?}", b))
-            .unwrap_or_else(|_| format!("<unknown builtin {}>", builtin.builtin)),
-        Some(Sum::Var(var)) => {
-            let name = interned_strings
-                .get(var.var_interned_str as usize)
-                .cloned()
-                .unwrap_or_else(|| "<invalid>".to_string());
-            name
+    }
+
+    fn is_atom(&self) -> bool {
+        match self {
+            TypeRep::Builtin { builtin: BuiltinType::List, .. } => true,
+            TypeRep::Builtin { args, .. } | TypeRep::Con { args, .. } | TypeRep::Var { args, .. } => args.is_empty(),
+            TypeRep::Nat(_) | TypeRep::Struct(_) => true,
         }
-        Some(Sum::Struct(r#struct)) => {
-            let fields: Vec<String> = r#struct
-                .fields
-                .iter()
-                .map(|f| {
-                    let fname = interned_strings
-                        .get(f.field_interned_str as usize)
-                        .cloned()
-                        .unwrap_or_else(|| "<invalid>".to_string());
-                    let ftype = f
-                        .r#type
-                        .as_ref()
-                        .map(|t| {
-                            resolve_type(t, interned_types, interned_strings, interned_dotted_names)
-                        })
-                        .unwrap_or_else(|| "<unknown>".to_string());
-                    format!("{}: {}", fname, ftype)
-                })
-                .collect();
-            format!("{{ {} }}", fields.join(", "))
+    }
+}
+
+impl fmt::Display for TypeRep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let write_applied = |f: &mut fmt::Formatter<'_>, name: &str, args: &[TypeRep]| {
+            write!(f, "{}", name)?;
+            for arg in args {
+                if arg.is_atom() {
+                    write!(f, " {}", arg)?;
+                } else {
+                    write!(f, " ({})", arg)?;
+                }
+            }
+            Ok(())
+        };
+        match self {
+            TypeRep::Builtin { builtin: BuiltinType::List, args } if args.len() == 1 => write!(f, "[{}]", args[0]),
+            TypeRep::Builtin { builtin, args } => {
+                let name = match builtin {
+                    BuiltinType::Int64 => "Int".to_string(),
+                    BuiltinType::Genmap => "Map".to_string(),
+                    BuiltinType::Textmap => "TextMap".to_string(),
+                    BuiltinType::ContractId => "ContractId".to_string(),
+                    other => format!("{:?}", other),
+                };
+                write_applied(f, &name, args)
+            }
+            TypeRep::Con { name, args, .. } => write_applied(f, name, args),
+            TypeRep::Var { name, args } => write_applied(f, name, args),
+            TypeRep::Nat(n) => write!(f, "{}", n),
+            TypeRep::Struct(fields) => {
+                let fields: Vec<String> = fields.iter().map(|(name, typ)| format!("{}: {}", name, typ)).collect();
+                write!(f, "{{ {} }}", fields.join(", "))
+            }
         }
-        _ => format!("{:?}", &typ.sum),
     }
 }
 
@@ -100,4 +373,32 @@ mod tests {
         );
         dbg!(resolved);
     }
+
+    #[test]
+    fn test_display_type_rep() {
+        let builtin = |builtin: BuiltinType, args: Vec<TypeRep>| TypeRep::Builtin { builtin, args };
+        let text = builtin(BuiltinType::Text, vec![]);
+        let asset = TypeRep::Con {
+            package: PackageRef::SelfPackage,
+            module: "Main".to_string(),
+            name: "Asset".to_string(),
+            args: vec![],
+        };
+        let optional_cid = builtin(
+            BuiltinType::Optional,
+            vec![builtin(BuiltinType::ContractId, vec![asset])],
+        );
+        assert_eq!(optional_cid.to_string(), "Optional (ContractId Asset)");
+        assert_eq!(builtin(BuiltinType::List, vec![builtin(BuiltinType::Int64, vec![])]).to_string(), "[Int]");
+        let map = builtin(BuiltinType::Genmap, vec![text, builtin(BuiltinType::Party, vec![])]);
+        assert_eq!(map.to_string(), "Map Text Party");
+        let numeric = builtin(BuiltinType::Numeric, vec![TypeRep::Nat(10)]);
+        assert_eq!(numeric.to_string(), "Numeric 10");
+        let packages = GeneratedPackages::default();
+        assert_eq!(numeric.rust_type(&packages, "", "Main").unwrap().to_string(), "DamlDecimal");
+        assert_eq!(
+            optional_cid.rust_type(&packages, "", "Main").unwrap().to_string(),
+            "DamlOptional < DamlContractId >"
+        );
+    }
 }
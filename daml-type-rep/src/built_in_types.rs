@@ -5,7 +5,20 @@ use std::fmt::Display;
 
 pub trait DamlValue {} // Marker trait for all Daml value types
 
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, serde::Serialize)]
+// The std types with a LapiAccess encoding, so that they can be the elements of the Daml
+// containers too, e.g. the values of a `DamlTextMap<i64>`
+impl DamlValue for String {}
+impl DamlValue for bool {}
+impl DamlValue for i64 {}
+impl DamlValue for () {}
+impl DamlValue for chrono::NaiveDate {}
+impl DamlValue for chrono::DateTime<chrono::Utc> {}
+impl<T: DamlValue> DamlValue for Option<T> {}
+impl<T: DamlValue> DamlValue for Vec<T> {}
+impl<K: DamlValue, V: DamlValue> DamlValue for std::collections::BTreeMap<K, V> {}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, serde::Serialize, serde::Deserialize)]
+#[serde(transparent)]
 pub struct DamlInt{value: i64}
 
 impl DamlInt {
@@ -19,7 +32,14 @@ impl DamlInt {
 
 impl DamlValue for DamlInt {}
 
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, serde::Serialize)]
+impl From<i64> for DamlInt {
+    fn from(value: i64) -> Self {
+        DamlInt::new(value)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, serde::Serialize, serde::Deserialize)]
+#[serde(transparent)]
 pub struct DamlText{value: String}
 
 impl DamlText {
@@ -33,7 +53,26 @@ impl DamlText {
 
 impl DamlValue for DamlText {}
 
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, serde::Serialize)]
+impl From<&str> for DamlText {
+    fn from(value: &str) -> Self {
+        DamlText::new(value)
+    }
+}
+
+impl From<String> for DamlText {
+    fn from(value: String) -> Self {
+        DamlText::new(value)
+    }
+}
+
+impl From<DamlText> for String {
+    fn from(text: DamlText) -> Self {
+        text.value
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, serde::Serialize, serde::Deserialize)]
+#[serde(transparent)]
 pub struct DamlBool{value: bool}
 
 impl DamlBool {
@@ -47,7 +86,14 @@ impl DamlBool {
 
 impl DamlValue for DamlBool {}
 
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, serde::Serialize)]
+impl From<bool> for DamlBool {
+    fn from(value: bool) -> Self {
+        DamlBool::new(value)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, serde::Serialize, serde::Deserialize)]
+#[serde(transparent)]
 pub struct DamlDate {
     pub value: chrono::NaiveDate,
 }
@@ -63,7 +109,8 @@ impl DamlDate {
 
 impl DamlValue for DamlDate {}
 
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, serde::Serialize)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, serde::Serialize, serde::Deserialize)]
+#[serde(transparent)]
 pub struct DamlTime {
     pub value: chrono::NaiveTime,
 }
@@ -79,7 +126,8 @@ impl DamlTime {
 
 impl DamlValue for DamlTime {}
 
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, serde::Serialize)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, serde::Serialize, serde::Deserialize)]
+#[serde(transparent)]
 pub struct DamlTimestamp {
     pub value: chrono::DateTime<chrono::Utc>,
 }
@@ -95,7 +143,60 @@ impl DamlTimestamp {
 
 impl DamlValue for DamlTimestamp {}
 
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, serde::Serialize)]
+/// Daml `RelTime`, a duration in microseconds, which can be negative
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, serde::Serialize, serde::Deserialize)]
+#[serde(transparent)]
+pub struct DamlRelTime {
+    pub microseconds: i64,
+}
+
+impl DamlRelTime {
+    pub fn new(microseconds: i64) -> Self {
+        DamlRelTime { microseconds }
+    }
+    pub fn microseconds(&self) -> i64 {
+        self.microseconds
+    }
+}
+
+impl DamlValue for DamlRelTime {}
+
+impl TryFrom<std::time::Duration> for DamlRelTime {
+    type Error = anyhow::Error;
+    fn try_from(duration: std::time::Duration) -> anyhow::Result<Self> {
+        let microseconds = i64::try_from(duration.as_micros())
+            .map_err(|_| anyhow::anyhow!("Duration {:?} does not fit into a RelTime", duration))?;
+        Ok(DamlRelTime::new(microseconds))
+    }
+}
+
+impl TryFrom<DamlRelTime> for std::time::Duration {
+    type Error = anyhow::Error;
+    fn try_from(rel_time: DamlRelTime) -> anyhow::Result<Self> {
+        let microseconds = u64::try_from(rel_time.microseconds)
+            .map_err(|_| anyhow::anyhow!("Negative RelTime {} is not a std Duration", rel_time.microseconds))?;
+        Ok(std::time::Duration::from_micros(microseconds))
+    }
+}
+
+impl TryFrom<chrono::Duration> for DamlRelTime {
+    type Error = anyhow::Error;
+    fn try_from(duration: chrono::Duration) -> anyhow::Result<Self> {
+        let microseconds = duration
+            .num_microseconds()
+            .ok_or_else(|| anyhow::anyhow!("Duration {} does not fit into a RelTime", duration))?;
+        Ok(DamlRelTime::new(microseconds))
+    }
+}
+
+impl From<DamlRelTime> for chrono::Duration {
+    fn from(rel_time: DamlRelTime) -> Self {
+        chrono::Duration::microseconds(rel_time.microseconds)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, serde::Serialize, serde::Deserialize)]
+#[serde(transparent)]
 pub struct DamlUnit {
     pub value: (),
 }
@@ -111,7 +212,14 @@ impl DamlUnit {
 
 impl DamlValue for DamlUnit {}
 
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, serde::Serialize)]
+impl Default for DamlUnit {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, serde::Serialize, serde::Deserialize)]
+#[serde(transparent)]
 pub struct DamlList<T> {
     pub value: Vec<T>,
 }
@@ -127,7 +235,14 @@ impl<T: DamlValue> DamlList<T> {
 
 impl <T: DamlValue> DamlValue for DamlList<T> {}
 
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, serde::Serialize)]
+impl<T> Default for DamlList<T> {
+    fn default() -> Self {
+        DamlList { value: Vec::new() }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, serde::Serialize, serde::Deserialize)]
+#[serde(transparent)]
 pub struct DamlOptional<T> {
     pub value: Option<T>,
 }
@@ -143,7 +258,14 @@ impl<T> DamlOptional<T> {
 
 impl<T: DamlValue> DamlValue for DamlOptional<T> {}
 
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, serde::Serialize)]
+impl<T> Default for DamlOptional<T> {
+    fn default() -> Self {
+        DamlOptional { value: None }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, serde::Serialize, serde::Deserialize)]
+#[serde(transparent, bound(deserialize = "K: Ord + serde::Deserialize<'de>, V: serde::Deserialize<'de>"))]
 pub struct DamlMap<K, V> {
     pub value: std::collections::BTreeMap<K, V>,
 }
@@ -159,24 +281,153 @@ impl<K: DamlValue, V: DamlValue> DamlMap<K, V> {
 
 impl<K: DamlValue, V: DamlValue> DamlValue for DamlMap<K, V> {}
 
-// TODO String key is ok?
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, serde::Serialize)]
-pub struct DamlTextMap<V> {
-    pub value: std::collections::BTreeMap<String, V>,
+impl<K, V> Default for DamlMap<K, V> {
+    fn default() -> Self {
+        DamlMap { value: std::collections::BTreeMap::new() }
+    }
 }
 
-impl<V: DamlValue> DamlTextMap<V> {
-    pub fn new(value: std::collections::BTreeMap<String, V>) -> Self {
+/// A Daml `Map` as the list of its entries in ledger order. Unlike `DamlMap`, keys are compared
+/// by equality only, so they need not be `Ord`, and re-encoding keeps the order of the entries.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(transparent)]
+pub struct DamlGenMap<K, V> {
+    pub value: Vec<(K, V)>,
+}
+
+impl<K: DamlValue + PartialEq, V: DamlValue> DamlGenMap<K, V> {
+    pub fn new(value: Vec<(K, V)>) -> Self {
+        DamlGenMap { value }
+    }
+    pub fn value(&self) -> &Vec<(K, V)> {
+        &self.value
+    }
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.value.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+    }
+    /// Replaces the value of an existing key in place, or appends the entry
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        match self.value.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, v)) => Some(std::mem::replace(v, value)),
+            None => {
+                self.value.push((key, value));
+                None
+            }
+        }
+    }
+    pub fn len(&self) -> usize {
+        self.value.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.value.is_empty()
+    }
+}
+
+impl<K: DamlValue, V: DamlValue> DamlValue for DamlGenMap<K, V> {}
+
+impl<K, V> Default for DamlGenMap<K, V> {
+    fn default() -> Self {
+        DamlGenMap { value: Vec::new() }
+    }
+}
+
+impl<K, V> From<DamlMap<K, V>> for DamlGenMap<K, V> {
+    fn from(map: DamlMap<K, V>) -> Self {
+        DamlGenMap { value: map.value.into_iter().collect() }
+    }
+}
+
+/// Key of a `DamlTextMap`, converted from and to the `Text` keys on the ledger, e.g. `DamlText`,
+/// `String` or a newtype of the model
+pub trait TextMapKey: Clone + Ord + Into<String> + From<String> {}
+
+impl<K: Clone + Ord + Into<String> + From<String>> TextMapKey for K {}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, serde::Serialize, serde::Deserialize)]
+#[serde(transparent, bound(deserialize = "K: Ord + serde::Deserialize<'de>, V: serde::Deserialize<'de>"))]
+pub struct DamlTextMap<V, K = DamlText> {
+    pub value: std::collections::BTreeMap<K, V>,
+}
+
+impl<V: DamlValue, K: TextMapKey> DamlTextMap<V, K> {
+    pub fn new(value: std::collections::BTreeMap<K, V>) -> Self {
         DamlTextMap { value }
     }
-    pub fn value(&self) -> &std::collections::BTreeMap<String, V> {
+    pub fn value(&self) -> &std::collections::BTreeMap<K, V> {
+        &self.value
+    }
+}
+
+impl<V: DamlValue, K: TextMapKey> DamlValue for DamlTextMap<V, K> {}
+
+impl<V, K> Default for DamlTextMap<V, K> {
+    fn default() -> Self {
+        DamlTextMap { value: std::collections::BTreeMap::new() }
+    }
+}
+
+/// `DA.Types.Tuple2`, the record `{ _1 : a, _2 : b }`
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, serde::Serialize, serde::Deserialize)]
+#[serde(transparent)]
+pub struct DamlTuple2<A, B> {
+    pub value: (A, B),
+}
+
+impl<A: DamlValue, B: DamlValue> DamlTuple2<A, B> {
+    pub fn new(first: A, second: B) -> Self {
+        DamlTuple2 { value: (first, second) }
+    }
+    pub fn value(&self) -> &(A, B) {
         &self.value
     }
 }
 
-impl<V: DamlValue> DamlValue for DamlTextMap<V> {}
+impl<A: DamlValue, B: DamlValue> DamlValue for DamlTuple2<A, B> {}
+
+/// `DA.Types.Tuple3`, the record `{ _1 : a, _2 : b, _3 : c }`
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, serde::Serialize, serde::Deserialize)]
+#[serde(transparent)]
+pub struct DamlTuple3<A, B, C> {
+    pub value: (A, B, C),
+}
 
-#[derive(Debug, Eq, PartialEq, PartialOrd, Ord, Clone, serde::Serialize)]
+impl<A: DamlValue, B: DamlValue, C: DamlValue> DamlTuple3<A, B, C> {
+    pub fn new(first: A, second: B, third: C) -> Self {
+        DamlTuple3 { value: (first, second, third) }
+    }
+    pub fn value(&self) -> &(A, B, C) {
+        &self.value
+    }
+}
+
+impl<A: DamlValue, B: DamlValue, C: DamlValue> DamlValue for DamlTuple3<A, B, C> {}
+
+/// `DA.Set.Types.Set`, the record `{ map : Map k () }`
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, serde::Serialize, serde::Deserialize)]
+#[serde(transparent, bound(deserialize = "T: Ord + serde::Deserialize<'de>"))]
+pub struct DamlSet<T> {
+    pub value: std::collections::BTreeSet<T>,
+}
+
+impl<T: DamlValue + Ord> DamlSet<T> {
+    pub fn new(value: std::collections::BTreeSet<T>) -> Self {
+        DamlSet { value }
+    }
+    pub fn value(&self) -> &std::collections::BTreeSet<T> {
+        &self.value
+    }
+}
+
+impl<T: DamlValue> DamlValue for DamlSet<T> {}
+
+impl<T> Default for DamlSet<T> {
+    fn default() -> Self {
+        DamlSet { value: std::collections::BTreeSet::new() }
+    }
+}
+
+#[derive(Debug, Default, Eq, PartialEq, PartialOrd, Ord, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(transparent)]
 pub struct DamlParty {
     pub party_id: String,
 }
@@ -187,13 +438,36 @@ impl Display for DamlParty {
     }
 }
 
+/// Longest party id accepted by the ledger
+const PARTY_ID_MAX_LENGTH: usize = 255;
+
 impl DamlParty {
+    /// Wraps a party id as is, see `parse` for a validating constructor
     pub fn new(party: impl Into<String>) -> Self {
         Self {
             party_id: party.into(),
         }
     }
 
+    /// Validates a party id of the form `<hint>::<fingerprint>`, the hint made of ASCII letters,
+    /// digits, `-`, `_` and spaces, and the fingerprint of hex digits.
+    pub fn parse(party: impl Into<String>) -> anyhow::Result<Self> {
+        let party_id = party.into();
+        if party_id.len() > PARTY_ID_MAX_LENGTH {
+            anyhow::bail!("Party id '{}' is longer than {} characters", party_id, PARTY_ID_MAX_LENGTH);
+        }
+        let Some((hint, fingerprint)) = party_id.split_once("::") else {
+            anyhow::bail!("Party id '{}' is not of the form <hint>::<fingerprint>", party_id);
+        };
+        if hint.is_empty() || !hint.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | ' ')) {
+            anyhow::bail!("Party id '{}' has an invalid hint '{}'", party_id, hint);
+        }
+        if fingerprint.is_empty() || !fingerprint.chars().all(|c| c.is_ascii_hexdigit()) {
+            anyhow::bail!("Party id '{}' has an invalid fingerprint '{}'", party_id, fingerprint);
+        }
+        Ok(Self { party_id })
+    }
+
     pub fn as_str(&self) -> &str {
         self.party_id.as_str()
     }
@@ -201,18 +475,58 @@ impl DamlParty {
 
 impl DamlValue for DamlParty {}
 
-#[derive(Debug, Eq, PartialEq, PartialOrd, Ord, Clone, serde::Serialize)]
+impl From<&str> for DamlParty {
+    fn from(party: &str) -> Self {
+        DamlParty::new(party)
+    }
+}
+
+impl From<String> for DamlParty {
+    fn from(party: String) -> Self {
+        DamlParty::new(party)
+    }
+}
+
+#[derive(Debug, Default, Eq, PartialEq, PartialOrd, Ord, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(transparent)]
 pub struct DamlContractId {
     pub contract_id: String,
 }
 
+/// Hex digits of a contract id before the suffix: the version prefix and the 32-byte discriminator
+const CONTRACT_ID_MIN_LENGTH: usize = 2 + 64;
+/// Hex digits of the longest contract id accepted by the ledger, 255 bytes
+const CONTRACT_ID_MAX_LENGTH: usize = 2 * 255;
+
 impl DamlContractId {
+    /// Wraps a contract id as is, see `parse` for a validating constructor
     pub fn new(contract_id: impl Into<String>) -> Self {
         Self {
             contract_id: contract_id.into(),
         }
     }
 
+    /// Validates a contract id: hex digits, starting with the `00` or `01` version prefix and a
+    /// 32-byte discriminator, followed by an optional suffix.
+    pub fn parse(contract_id: impl Into<String>) -> anyhow::Result<Self> {
+        let contract_id = contract_id.into();
+        if !contract_id.chars().all(|c| c.is_ascii_hexdigit()) {
+            anyhow::bail!("Contract id '{}' contains non-hex characters", contract_id);
+        }
+        if !(CONTRACT_ID_MIN_LENGTH..=CONTRACT_ID_MAX_LENGTH).contains(&contract_id.len()) {
+            anyhow::bail!(
+                "Contract id '{}' must have {} to {} hex digits",
+                contract_id,
+                CONTRACT_ID_MIN_LENGTH,
+                CONTRACT_ID_MAX_LENGTH
+            );
+        }
+        if !(contract_id.starts_with("00") || contract_id.starts_with("01")) {
+            anyhow::bail!("Contract id '{}' has an unknown version prefix", contract_id);
+        }
+        Ok(Self { contract_id })
+    }
+
     pub fn as_str(&self) -> &str {
         self.contract_id.as_str()
     }
@@ -220,17 +534,132 @@ impl DamlContractId {
 
 impl DamlValue for DamlContractId {}
 
-#[derive(Debug, Eq, PartialEq, PartialOrd, Ord, Clone, serde::Serialize)]
+impl From<String> for DamlContractId {
+    fn from(contract_id: String) -> Self {
+        DamlContractId::new(contract_id)
+    }
+}
+
+#[derive(Debug, Default, Eq, PartialEq, PartialOrd, Ord, Clone, serde::Serialize)]
+#[serde(transparent)]
 pub struct DamlDecimal {
     pub value: rust_decimal::Decimal,
 }
 
+/// Scale of the Daml `Decimal` type, i.e. `Numeric 10`
+pub const DECIMAL_SCALE: u32 = 10;
+/// Daml numerics have at most 38 digits, of which `DECIMAL_SCALE` are after the decimal point
+const DECIMAL_MAX_INTEGER_DIGITS: u32 = 38 - DECIMAL_SCALE;
+
 impl DamlDecimal {
+    /// Converts a float, rounded to 10 decimal places.
+    /// Floats are imprecise and panic if not finite, prefer `try_from_str` or `from_decimal`.
     pub fn new(value: f64) -> Self {
         DamlDecimal {
-            value: rust_decimal::Decimal::from_f64(value).unwrap().round_dp(10),
+            value: rust_decimal::Decimal::from_f64(value).unwrap().round_dp(DECIMAL_SCALE),
+        }
+    }
+
+    /// Exact conversion, failing if the value has more than 10 decimal places or 28 integer digits.
+    pub fn from_decimal(value: rust_decimal::Decimal) -> anyhow::Result<Self> {
+        let normalized = value.normalize();
+        if normalized.scale() > DECIMAL_SCALE {
+            anyhow::bail!("Decimal {} has more than {} decimal places", value, DECIMAL_SCALE);
+        }
+        let integer_limit = rust_decimal::Decimal::from_i128_with_scale(10i128.pow(DECIMAL_MAX_INTEGER_DIGITS), 0);
+        if normalized.trunc().abs() >= integer_limit {
+            anyhow::bail!("Decimal {} has more than {} integer digits", value, DECIMAL_MAX_INTEGER_DIGITS);
+        }
+        Ok(DamlDecimal { value: normalized })
+    }
+
+    /// Parses a decimal string like `"-12.5"` exactly, with the limits of `from_decimal`.
+    /// `rust_decimal` holds up to 28 significant digits, longer values are an error rather than rounded.
+    pub fn try_from_str(value: &str) -> anyhow::Result<Self> {
+        let decimal = rust_decimal::Decimal::from_str_exact(value.trim())
+            .map_err(|e| anyhow::anyhow!("Invalid decimal '{}': {}", value, e))?;
+        Self::from_decimal(decimal)
+    }
+
+    pub fn from_i64(value: i64) -> Self {
+        DamlDecimal { value: rust_decimal::Decimal::from(value) }
+    }
+
+    /// `units * 10^-scale`, e.g. `from_scaled_i64(1250, 2)` is 12.50
+    pub fn from_scaled_i64(units: i64, scale: u32) -> anyhow::Result<Self> {
+        if scale > DECIMAL_SCALE {
+            anyhow::bail!("Scale {} is more than {}", scale, DECIMAL_SCALE);
+        }
+        Self::from_decimal(rust_decimal::Decimal::new(units, scale))
+    }
+
+    pub fn value(&self) -> &rust_decimal::Decimal {
+        &self.value
+    }
+}
+
+impl From<i64> for DamlDecimal {
+    fn from(value: i64) -> Self {
+        DamlDecimal::from_i64(value)
+    }
+}
+
+impl TryFrom<rust_decimal::Decimal> for DamlDecimal {
+    type Error = anyhow::Error;
+    fn try_from(value: rust_decimal::Decimal) -> anyhow::Result<Self> {
+        DamlDecimal::from_decimal(value)
+    }
+}
+
+impl std::str::FromStr for DamlDecimal {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        DamlDecimal::try_from_str(s)
+    }
+}
+
+// Deserialized from a string or number, with the limits of `from_decimal`
+impl<'de> serde::Deserialize<'de> for DamlDecimal {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = <rust_decimal::Decimal as serde::Deserialize>::deserialize(deserializer)?;
+        DamlDecimal::from_decimal(value).map_err(serde::de::Error::custom)
+    }
+}
+
+// Arithmetic follows the Daml `Numeric` semantics: results are rounded half to even at the scale,
+// and exceeding 38 digits or dividing by zero is an error, where Daml aborts the transaction.
+
+fn numeric_result(
+    value: Option<rust_decimal::Decimal>,
+    scale: u32,
+    operation: &str,
+) -> anyhow::Result<rust_decimal::Decimal> {
+    let value = value.ok_or_else(|| anyhow::anyhow!("Numeric {} overflows or divides by zero", operation))?;
+    let rounded = value.round_dp_with_strategy(scale, rust_decimal::RoundingStrategy::MidpointNearestEven);
+    // rust_decimal holds at most 28 integer digits, larger limits are never reached
+    let max_integer_digits = 38u32.saturating_sub(scale);
+    if max_integer_digits <= 28 {
+        let integer_limit = rust_decimal::Decimal::from_i128_with_scale(10i128.pow(max_integer_digits), 0);
+        if rounded.trunc().abs() >= integer_limit {
+            anyhow::bail!("Numeric {} result {} has more than {} integer digits", operation, rounded, max_integer_digits);
         }
     }
+    Ok(rounded)
+}
+
+impl DamlDecimal {
+    pub fn checked_add(&self, other: &DamlDecimal) -> anyhow::Result<Self> {
+        numeric_result(self.value.checked_add(other.value), DECIMAL_SCALE, "addition").and_then(Self::from_decimal)
+    }
+    pub fn checked_sub(&self, other: &DamlDecimal) -> anyhow::Result<Self> {
+        numeric_result(self.value.checked_sub(other.value), DECIMAL_SCALE, "subtraction").and_then(Self::from_decimal)
+    }
+    pub fn checked_mul(&self, other: &DamlDecimal) -> anyhow::Result<Self> {
+        numeric_result(self.value.checked_mul(other.value), DECIMAL_SCALE, "multiplication").and_then(Self::from_decimal)
+    }
+    pub fn checked_div(&self, other: &DamlDecimal) -> anyhow::Result<Self> {
+        numeric_result(self.value.checked_div(other.value), DECIMAL_SCALE, "division").and_then(Self::from_decimal)
+    }
 }
 
 impl fmt::Display for DamlDecimal {
@@ -241,10 +670,11 @@ impl fmt::Display for DamlDecimal {
 
 impl DamlValue for DamlDecimal {}
 
-#[derive(Clone, Debug, PartialEq, serde::Serialize)]
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(transparent)]
 pub struct NumericScale(pub u32);
 
-#[derive(Clone, Debug, PartialEq, serde::Serialize)]
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct DamlNumeric {
     pub value: rust_decimal::Decimal,
     pub scale: NumericScale,
@@ -267,6 +697,56 @@ impl DamlNumeric {
     }
 }
 
+impl DamlNumeric {
+    // Daml only combines numerics of the same scale
+    fn checked_op(
+        &self,
+        other: &DamlNumeric,
+        operation: &str,
+        op: impl FnOnce(rust_decimal::Decimal, rust_decimal::Decimal) -> Option<rust_decimal::Decimal>,
+    ) -> anyhow::Result<Self> {
+        if self.scale != other.scale {
+            anyhow::bail!("Numeric {} of scales {} and {}", operation, self.scale.0, other.scale.0);
+        }
+        let value = numeric_result(op(self.value, other.value), self.scale.0, operation)?;
+        Ok(DamlNumeric { value, scale: self.scale.clone() })
+    }
+
+    pub fn checked_add(&self, other: &DamlNumeric) -> anyhow::Result<Self> {
+        self.checked_op(other, "addition", |a, b| a.checked_add(b))
+    }
+    pub fn checked_sub(&self, other: &DamlNumeric) -> anyhow::Result<Self> {
+        self.checked_op(other, "subtraction", |a, b| a.checked_sub(b))
+    }
+    pub fn checked_mul(&self, other: &DamlNumeric) -> anyhow::Result<Self> {
+        self.checked_op(other, "multiplication", |a, b| a.checked_mul(b))
+    }
+    pub fn checked_div(&self, other: &DamlNumeric) -> anyhow::Result<Self> {
+        self.checked_op(other, "division", |a, b| a.checked_div(b))
+    }
+}
+
+// The operators panic where the checked methods fail, like integer overflow in debug builds
+macro_rules! numeric_operator {
+    ($ty:ty, $trait:ident, $method:ident, $checked:ident) => {
+        impl std::ops::$trait for $ty {
+            type Output = $ty;
+            fn $method(self, rhs: $ty) -> $ty {
+                self.$checked(&rhs).unwrap_or_else(|e| panic!("{}", e))
+            }
+        }
+    };
+}
+
+numeric_operator!(DamlDecimal, Add, add, checked_add);
+numeric_operator!(DamlDecimal, Sub, sub, checked_sub);
+numeric_operator!(DamlDecimal, Mul, mul, checked_mul);
+numeric_operator!(DamlDecimal, Div, div, checked_div);
+numeric_operator!(DamlNumeric, Add, add, checked_add);
+numeric_operator!(DamlNumeric, Sub, sub, checked_sub);
+numeric_operator!(DamlNumeric, Mul, mul, checked_mul);
+numeric_operator!(DamlNumeric, Div, div, checked_div);
+
 impl fmt::Display for DamlNumeric {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{} (scale: {})", self.value, self.scale.0)
@@ -275,5 +755,109 @@ impl fmt::Display for DamlNumeric {
 
 impl DamlValue for DamlNumeric {}
 
-
-
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decimal_constructors() {
+        let d = DamlDecimal::try_from_str("123456789012345678.1234567891").unwrap();
+        assert_eq!(d.to_string(), "123456789012345678.1234567891");
+        assert!(DamlDecimal::try_from_str("12345678901234567890.1234567891").is_err());
+        assert_eq!(DamlDecimal::from_scaled_i64(1250, 2).unwrap(), DamlDecimal::try_from_str("12.5").unwrap());
+        assert_eq!(DamlDecimal::from_i64(3).to_string(), "3");
+        assert!(DamlDecimal::try_from_str("0.12345678901").is_err());
+        assert!(DamlDecimal::try_from_str("10000000000000000000000000000").is_err());
+        assert!(DamlDecimal::try_from_str("1.5x").is_err());
+        // Trailing zeros beyond the scale are not a loss of precision
+        assert!(DamlDecimal::try_from_str("1.500000000000").is_ok());
+    }
+
+    #[test]
+    fn test_deserialize_from_plain_json() {
+        #[derive(serde::Deserialize, serde::Serialize, Debug, PartialEq)]
+        struct Fixture {
+            owner: DamlParty,
+            cid: DamlContractId,
+            amount: DamlDecimal,
+            count: DamlInt,
+            due: DamlDate,
+            at: DamlTimestamp,
+            tags: DamlList<DamlText>,
+            note: DamlOptional<DamlText>,
+        }
+        let json = r#"{
+            "owner": "Alice::1220",
+            "cid": "00abcd",
+            "amount": "12.5",
+            "count": 3,
+            "due": "2025-01-31",
+            "at": "2025-01-31T12:00:00Z",
+            "tags": ["a", "b"],
+            "note": null
+        }"#;
+        let fixture: Fixture = serde_json::from_str(json).unwrap();
+        assert_eq!(fixture.owner, DamlParty::new("Alice::1220"));
+        assert_eq!(fixture.amount, DamlDecimal::from_scaled_i64(125, 1).unwrap());
+        assert_eq!(fixture.due.value().to_string(), "2025-01-31");
+        assert_eq!(fixture.note, DamlOptional::new(None));
+
+        let serialized = serde_json::to_string(&fixture).unwrap();
+        assert_eq!(serde_json::from_str::<Fixture>(&serialized).unwrap(), fixture);
+        assert!(serde_json::from_str::<DamlDecimal>("\"0.12345678901\"").is_err());
+    }
+
+    #[test]
+    fn test_gen_map_keeps_entry_order() {
+        let mut map = DamlGenMap::new(vec![(DamlText::new("b"), DamlInt::new(1))]);
+        assert_eq!(map.insert(DamlText::new("a"), DamlInt::new(2)), None);
+        assert_eq!(map.insert(DamlText::new("b"), DamlInt::new(3)), Some(DamlInt::new(1)));
+        assert_eq!(map.get(&DamlText::new("b")), Some(&DamlInt::new(3)));
+        let keys: Vec<_> = map.value().iter().map(|(k, _)| k.value().to_string()).collect();
+        assert_eq!(keys, ["b", "a"]);
+    }
+
+    #[test]
+    fn test_parse_party_and_contract_id() {
+        let fingerprint = "1220f2fe29866fd6a0009ecc8a64ccdc09f1958bd0f801166baaee469d1251b2eb72";
+        assert!(DamlParty::parse(format!("Alice-1::{}", fingerprint)).is_ok());
+        assert!(DamlParty::parse("").is_err());
+        assert!(DamlParty::parse("Alice").is_err());
+        assert!(DamlParty::parse(format!("::{}", fingerprint)).is_err());
+        assert!(DamlParty::parse("Alice::not-hex").is_err());
+
+        let contract_id = format!("00{}ca1012", "ab".repeat(32));
+        assert!(DamlContractId::parse(contract_id.clone()).is_ok());
+        assert!(DamlContractId::parse("").is_err());
+        assert!(DamlContractId::parse(&contract_id[..20]).is_err());
+        assert!(DamlContractId::parse(contract_id.replacen("00", "07", 1)).is_err());
+        assert!(DamlContractId::parse(contract_id.replace('a', "x")).is_err());
+    }
+
+    #[test]
+    fn test_decimal_arithmetic() {
+        let d = |s: &str| DamlDecimal::try_from_str(s).unwrap();
+        assert_eq!(d("0.1") + d("0.2"), d("0.3"));
+        assert_eq!(d("10") - d("12.5"), d("-2.5"));
+        // Rounded half to even at 10 decimal places
+        assert_eq!(d("0.0000000001") * d("0.5"), d("0"));
+        assert_eq!(d("0.0000000003") * d("0.5"), d("0.0000000002"));
+        assert_eq!(d("2") / d("3"), d("0.6666666667"));
+        assert!(d("1").checked_div(&d("0")).is_err());
+        assert!(d("9999999999999999999999999999").checked_mul(&d("10")).is_err());
+
+        let n = |s: &str, scale: u32| DamlNumeric::from_numeric(s.parse().unwrap(), NumericScale(scale));
+        assert_eq!(n("1.25", 2) * n("0.50", 2), n("0.62", 2));
+        assert_eq!(n("1", 0) / n("3", 0), n("0", 0));
+        assert!(n("1", 2).checked_add(&n("1", 3)).is_err());
+    }
+
+    #[test]
+    fn test_rel_time_conversions() {
+        let rel_time = DamlRelTime::try_from(std::time::Duration::from_millis(1500)).unwrap();
+        assert_eq!(rel_time.microseconds(), 1_500_000);
+        assert_eq!(chrono::Duration::from(rel_time), chrono::Duration::milliseconds(1500));
+        assert_eq!(DamlRelTime::try_from(chrono::Duration::seconds(-2)).unwrap().microseconds(), -2_000_000);
+        assert!(std::time::Duration::try_from(DamlRelTime::new(-1)).is_err());
+    }
+}
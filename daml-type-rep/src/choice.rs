@@ -0,0 +1,15 @@
+use crate::lapi_access::LapiAccess;
+use crate::template_id::DamlTemplate;
+
+/// A Rust type representing the argument of a Daml choice, linked to its template and result type.
+/// Usually derived with `#[derive(Choice)]`.
+pub trait DamlChoice: LapiAccess {
+    /// The template the choice is defined on
+    type Template: DamlTemplate;
+    /// The Rust representation of the choice result
+    type Result: LapiAccess;
+    /// The choice name
+    const NAME: &'static str;
+    /// Whether exercising the choice archives the contract
+    const CONSUMING: bool;
+}
@@ -0,0 +1,44 @@
+use crate::template_id::TemplateId;
+use chrono::{DateTime, Utc};
+use ledger_api::v2::CreatedEvent;
+
+/// A decoded active contract: the payload with the contract id and metadata of its create event
+#[derive(Clone, Debug, PartialEq)]
+pub struct Contract<T> {
+    pub contract_id: String,
+    pub template_id: Option<TemplateId>,
+    pub payload: T,
+    pub signatories: Vec<String>,
+    pub observers: Vec<String>,
+    pub created_at: Option<DateTime<Utc>>,
+    /// The offset at which the contract was created
+    pub offset: i64,
+}
+
+impl<T> Contract<T> {
+    /// Combines an already decoded payload with the metadata of its create event.
+    pub fn new(event: &CreatedEvent, payload: T) -> Self {
+        Contract {
+            contract_id: event.contract_id.clone(),
+            template_id: event
+                .template_id
+                .as_ref()
+                .map(|id| TemplateId::new(&id.package_id, &id.module_name, &id.entity_name)),
+            payload,
+            signatories: event.signatories.clone(),
+            observers: event.observers.clone(),
+            created_at: event
+                .created_at
+                .as_ref()
+                .and_then(|ts| DateTime::from_timestamp(ts.seconds, ts.nanos as u32)),
+            offset: event.offset,
+        }
+    }
+}
+
+/// A Rust type which can be decoded from the create arguments of a `CreatedEvent`.
+/// Usually derived with `#[derive(FromCreatedEvent)]` next to `#[derive(LapiAccess)]`.
+pub trait FromCreatedEvent: Sized {
+    /// Decodes the create arguments and metadata, `None` if the arguments do not match the type
+    fn from_created_event(event: &CreatedEvent) -> Option<Contract<Self>>;
+}
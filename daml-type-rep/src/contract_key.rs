@@ -0,0 +1,58 @@
+use crate::lapi_access::LapiAccess;
+use crate::template_id::DamlTemplate;
+use ledger_api::v2::{CreatedEvent, PrefetchContractKey, Value};
+
+/// A Rust type representing the contract key of a Daml template.
+/// Usually derived with `#[derive(ContractKey)]` next to `#[derive(LapiAccess)]`.
+pub trait DamlContractKey: LapiAccess {
+    /// The template the key belongs to
+    type Template: DamlTemplate;
+
+    /// The key as a ledger API value, e.g. for `ExerciseByKeyCommand`
+    fn to_key_value(&self) -> Value {
+        self.to_lapi_value()
+    }
+
+    /// The key for `Commands::prefetch_contract_keys`, to look up the contract before interpretation
+    fn to_prefetch_contract_key(&self) -> PrefetchContractKey {
+        PrefetchContractKey {
+            template_id: Some(Self::Template::template_id().to_template_id()),
+            contract_key: Some(self.to_key_value()),
+        }
+    }
+
+    /// Decodes the key of a created event, `None` if the event is of another template or has no
+    /// key of this type
+    fn from_created_event(event: &CreatedEvent) -> Option<Self>
+    where
+        Self: Sized,
+    {
+        let template_id = Self::Template::template_id();
+        let event_template = event.template_id.as_ref()?;
+        // Only module and entity are compared, the package may be given as a `#<package-name>` reference
+        if event_template.module_name != template_id.module_name()
+            || event_template.entity_name != template_id.entity_name()
+        {
+            return None;
+        }
+        Self::from_lapi_value(event.contract_key.as_ref()?)
+    }
+}
+
+/// A template with a contract key, implemented by `#[derive(ContractKey)]` on the key type
+pub trait DamlKeyedTemplate: DamlTemplate {
+    type Key: DamlContractKey<Template = Self>;
+}
+
+/// Finds the created event with the given key among the events, e.g. an ACS snapshot.
+pub fn find_by_key<'a, K>(
+    events: impl IntoIterator<Item = &'a CreatedEvent>,
+    key: &K,
+) -> Option<&'a CreatedEvent>
+where
+    K: DamlContractKey + PartialEq,
+{
+    events
+        .into_iter()
+        .find(|event| K::from_created_event(event).as_ref() == Some(key))
+}
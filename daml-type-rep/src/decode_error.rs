@@ -0,0 +1,150 @@
+//! Decoding errors of `LapiAccess::try_from_lapi_value`, locating the failing part of a value
+use ledger_api::v2::{Value, value::Sum};
+use std::fmt;
+
+/// A step from a value into one of its parts
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PathSegment {
+    /// A record field label, or a variant constructor
+    Field(String),
+    /// The position in a list or in the entries of a map
+    Index(usize),
+    /// The key of a text map entry
+    Key(String),
+}
+
+/// Why and where decoding a Ledger API value failed, e.g.
+/// `coordinates.rgb.red: expected DamlInt, found Text`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LapiDecodeError {
+    /// From the outermost value to the part that failed to decode
+    pub path: Vec<PathSegment>,
+    pub message: String,
+}
+
+impl LapiDecodeError {
+    pub fn new(message: impl Into<String>) -> Self {
+        LapiDecodeError {
+            path: vec![],
+            message: message.into(),
+        }
+    }
+
+    /// The value does not have the shape of the `expected` type
+    pub fn unexpected(expected: &str, found: &Value) -> Self {
+        Self::new(format!("expected {}, found {}", expected, value_kind(found)))
+    }
+
+    /// A record has no field with the label
+    pub fn missing_field(label: &str) -> Self {
+        Self::new("missing field").in_field(label)
+    }
+
+    /// The constructor of a variant or enum is not one of the `expected` type
+    pub fn unknown_constructor(expected: &str, constructor: &str) -> Self {
+        Self::new(format!("unknown constructor {} of {}", constructor, expected))
+    }
+
+    /// The error occurred in the record field or variant constructor `label`
+    pub fn in_field(self, label: &str) -> Self {
+        self.in_segment(PathSegment::Field(label.to_string()))
+    }
+
+    /// The error occurred in the list element or map entry at `index`
+    pub fn in_index(self, index: usize) -> Self {
+        self.in_segment(PathSegment::Index(index))
+    }
+
+    /// The error occurred in the text map entry with `key`
+    pub fn in_key(self, key: &str) -> Self {
+        self.in_segment(PathSegment::Key(key.to_string()))
+    }
+
+    fn in_segment(mut self, segment: PathSegment) -> Self {
+        self.path.insert(0, segment);
+        self
+    }
+}
+
+impl fmt::Display for LapiDecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, segment) in self.path.iter().enumerate() {
+            match segment {
+                PathSegment::Field(label) if i == 0 => write!(f, "{}", label)?,
+                PathSegment::Field(label) => write!(f, ".{}", label)?,
+                PathSegment::Index(index) => write!(f, "[{}]", index)?,
+                PathSegment::Key(key) => write!(f, "[{:?}]", key)?,
+            }
+        }
+        if self.path.is_empty() {
+            write!(f, "{}", self.message)
+        } else {
+            write!(f, ": {}", self.message)
+        }
+    }
+}
+
+impl std::error::Error for LapiDecodeError {}
+
+/// The name of the Ledger API value type, e.g. `Int64` or `Record`
+pub fn value_kind(value: &Value) -> &'static str {
+    match &value.sum {
+        None => "no value",
+        Some(Sum::Unit(_)) => "Unit",
+        Some(Sum::Bool(_)) => "Bool",
+        Some(Sum::Int64(_)) => "Int64",
+        Some(Sum::Date(_)) => "Date",
+        Some(Sum::Timestamp(_)) => "Timestamp",
+        Some(Sum::Numeric(_)) => "Numeric",
+        Some(Sum::Party(_)) => "Party",
+        Some(Sum::Text(_)) => "Text",
+        Some(Sum::ContractId(_)) => "ContractId",
+        Some(Sum::Optional(_)) => "Optional",
+        Some(Sum::List(_)) => "List",
+        Some(Sum::TextMap(_)) => "TextMap",
+        Some(Sum::GenMap(_)) => "GenMap",
+        Some(Sum::Record(_)) => "Record",
+        Some(Sum::Variant(_)) => "Variant",
+        Some(Sum::Enum(_)) => "Enum",
+    }
+}
+
+/// The Rust type name without module paths, e.g. `Option<DamlInt>`
+pub(crate) fn short_type_name<T: ?Sized>() -> String {
+    let full = std::any::type_name::<T>();
+    let mut result = String::new();
+    let mut segment_start = 0;
+    let mut chars = full.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == ':' && chars.peek() == Some(&':') {
+            chars.next();
+            result.truncate(segment_start);
+        } else {
+            result.push(c);
+            if !(c.is_alphanumeric() || c == '_') {
+                segment_start = result.len();
+            }
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_path() {
+        let found = Value { sum: Some(Sum::Text("red".to_string())) };
+        let error = LapiDecodeError::unexpected("DamlInt", &found)
+            .in_field("red")
+            .in_index(2)
+            .in_field("colors");
+        assert_eq!(error.to_string(), "colors[2].red: expected DamlInt, found Text");
+        assert_eq!(LapiDecodeError::missing_field("owner").to_string(), "owner: missing field");
+        assert_eq!(
+            short_type_name::<Option<crate::built_in_types::DamlInt>>(),
+            "Option<DamlInt>"
+        );
+    }
+}
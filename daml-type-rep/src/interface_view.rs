@@ -0,0 +1,43 @@
+use crate::lapi_access::LapiAccess;
+use crate::template_id::{DamlTemplate, InterfaceId, TemplateId};
+use ledger_api::v2::{CreatedEvent, InterfaceView, Value, value::Sum};
+
+/// A Rust marker type standing for a Daml interface. Usually derived with `#[derive(Interface)]`.
+/// Choices on an interface are exercised by giving the interface id as the template id of the
+/// command, so the `DamlTemplate` impl returns the interface id and interface choices can be
+/// derived with `#[choice(on = <Interface>, ...)]`.
+pub trait DamlInterface: DamlTemplate {
+    /// The interface id, where the package is either a package id or a `#<package-name>` reference
+    fn interface_id() -> InterfaceId;
+}
+
+/// A Rust type representing the view type of a Daml interface.
+/// Usually derived with `#[derive(InterfaceView)]` next to `#[derive(LapiAccess)]`.
+pub trait DamlInterfaceView: LapiAccess + Sized {
+    /// The interface id, where the package is either a package id or a `#<package-name>` reference
+    fn interface_id() -> TemplateId;
+
+    /// Decodes the view value, `None` if the view is of another interface, failed to compute, or
+    /// does not match the type
+    fn from_interface_view(view: &InterfaceView) -> Option<Self> {
+        let interface_id = Self::interface_id();
+        let view_interface = view.interface_id.as_ref()?;
+        // Only module and entity are compared, events carry package ids while the interface
+        // may be given as a `#<package-name>` reference
+        if view_interface.module_name != interface_id.module_name()
+            || view_interface.entity_name != interface_id.entity_name()
+        {
+            return None;
+        }
+        let value = Value {
+            sum: Some(Sum::Record(view.view_value.clone()?)),
+        };
+        Self::from_lapi_value(&value)
+    }
+
+    /// Decodes the view of this interface among the interface views of a created event.
+    /// The views are only present if the interface was requested with `include_interface_view`.
+    fn from_created_event(event: &CreatedEvent) -> Option<Self> {
+        event.interface_views.iter().find_map(Self::from_interface_view)
+    }
+}
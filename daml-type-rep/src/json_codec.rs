@@ -0,0 +1,478 @@
+//! The Daml-LF JSON encoding of values, as used by the HTTP JSON API,
+//! see <https://docs.daml.com/json-api/lf-value-specification.html>
+use crate::built_in_types::*;
+use std::collections::BTreeMap;
+
+/// Re-exported for the code generated by `#[derive(JsonCodec)]`
+pub use serde_json;
+use serde_json::{Map, Value};
+
+pub trait JsonCodec {
+    fn to_lf_json(&self) -> Value;
+    fn from_lf_json(value: &Value) -> Option<Self> where Self: Sized;
+    /// Whether the type is a Daml `Optional`, nested optionals are encoded as `[]` and `[x]`
+    const IS_OPTIONAL: bool = false;
+}
+
+// Int64 is a string, numbers are accepted when decoding
+fn int64_from_lf_json(value: &Value) -> Option<i64> {
+    match value {
+        Value::String(s) => s.parse().ok(),
+        Value::Number(n) => n.as_i64(),
+        _ => None,
+    }
+}
+
+// Numeric is a string, numbers are accepted when decoding
+fn decimal_from_lf_json(value: &Value) -> Option<rust_decimal::Decimal> {
+    match value {
+        Value::String(s) => s.parse().ok(),
+        Value::Number(n) => n.to_string().parse().ok(),
+        _ => None,
+    }
+}
+
+fn text_from_lf_json(value: &Value) -> Option<String> {
+    value.as_str().map(str::to_string)
+}
+
+// Implementations for built-in types
+
+impl JsonCodec for DamlInt {
+    fn to_lf_json(&self) -> Value {
+        Value::String(self.value().to_string())
+    }
+    fn from_lf_json(value: &Value) -> Option<Self> {
+        int64_from_lf_json(value).map(DamlInt::new)
+    }
+}
+
+impl JsonCodec for DamlText {
+    fn to_lf_json(&self) -> Value {
+        Value::String(self.value().to_string())
+    }
+    fn from_lf_json(value: &Value) -> Option<Self> {
+        text_from_lf_json(value).map(DamlText::new)
+    }
+}
+
+impl JsonCodec for DamlBool {
+    fn to_lf_json(&self) -> Value {
+        Value::Bool(self.value())
+    }
+    fn from_lf_json(value: &Value) -> Option<Self> {
+        value.as_bool().map(DamlBool::new)
+    }
+}
+
+impl JsonCodec for DamlDate {
+    fn to_lf_json(&self) -> Value {
+        Value::String(self.value().format("%Y-%m-%d").to_string())
+    }
+    fn from_lf_json(value: &Value) -> Option<Self> {
+        chrono::NaiveDate::parse_from_str(value.as_str()?, "%Y-%m-%d").ok().map(DamlDate::new)
+    }
+}
+
+impl JsonCodec for DamlTime {
+    fn to_lf_json(&self) -> Value {
+        Value::String(self.value().format("%H:%M:%S").to_string())
+    }
+    fn from_lf_json(value: &Value) -> Option<Self> {
+        chrono::NaiveTime::parse_from_str(value.as_str()?, "%H:%M:%S").ok().map(DamlTime::new)
+    }
+}
+
+impl JsonCodec for DamlTimestamp {
+    fn to_lf_json(&self) -> Value {
+        Value::String(self.value().to_rfc3339_opts(chrono::SecondsFormat::AutoSi, true))
+    }
+    fn from_lf_json(value: &Value) -> Option<Self> {
+        chrono::DateTime::parse_from_rfc3339(value.as_str()?)
+            .ok()
+            .map(|ts| DamlTimestamp::new(ts.with_timezone(&chrono::Utc)))
+    }
+}
+
+// RelTime is the record `{"microseconds": "<Int64>"}`
+impl JsonCodec for DamlRelTime {
+    fn to_lf_json(&self) -> Value {
+        serde_json::json!({ "microseconds": self.microseconds.to_string() })
+    }
+    fn from_lf_json(value: &Value) -> Option<Self> {
+        int64_from_lf_json(value.get("microseconds")?).map(DamlRelTime::new)
+    }
+}
+
+impl JsonCodec for DamlUnit {
+    fn to_lf_json(&self) -> Value {
+        Value::Object(Map::new())
+    }
+    fn from_lf_json(value: &Value) -> Option<Self> {
+        value.as_object().filter(|o| o.is_empty()).map(|_| DamlUnit::new())
+    }
+}
+
+impl JsonCodec for DamlParty {
+    fn to_lf_json(&self) -> Value {
+        Value::String(self.party_id.clone())
+    }
+    fn from_lf_json(value: &Value) -> Option<Self> {
+        text_from_lf_json(value).map(DamlParty::new)
+    }
+}
+
+impl JsonCodec for DamlContractId {
+    fn to_lf_json(&self) -> Value {
+        Value::String(self.contract_id.clone())
+    }
+    fn from_lf_json(value: &Value) -> Option<Self> {
+        text_from_lf_json(value).map(DamlContractId::new)
+    }
+}
+
+impl JsonCodec for DamlDecimal {
+    fn to_lf_json(&self) -> Value {
+        Value::String(self.value.to_string())
+    }
+    fn from_lf_json(value: &Value) -> Option<Self> {
+        decimal_from_lf_json(value).and_then(|value| DamlDecimal::from_decimal(value).ok())
+    }
+}
+
+impl JsonCodec for DamlNumeric {
+    fn to_lf_json(&self) -> Value {
+        Value::String(self.value.to_string())
+    }
+    fn from_lf_json(value: &Value) -> Option<Self> {
+        decimal_from_lf_json(value).map(|v| DamlNumeric::from_numeric(v, NumericScale(10)))
+    }
+}
+
+impl<T: JsonCodec + DamlValue> JsonCodec for DamlList<T> {
+    fn to_lf_json(&self) -> Value {
+        self.value().to_lf_json()
+    }
+    fn from_lf_json(value: &Value) -> Option<Self> {
+        Vec::<T>::from_lf_json(value).map(DamlList::new)
+    }
+}
+
+impl<T: JsonCodec> JsonCodec for DamlOptional<T> {
+    const IS_OPTIONAL: bool = true;
+    fn to_lf_json(&self) -> Value {
+        self.value().to_lf_json()
+    }
+    fn from_lf_json(value: &Value) -> Option<Self> {
+        Option::<T>::from_lf_json(value).map(DamlOptional::new)
+    }
+}
+
+impl<V: JsonCodec + DamlValue, K: TextMapKey> JsonCodec for DamlTextMap<V, K> {
+    fn to_lf_json(&self) -> Value {
+        Value::Object(self.value().iter().map(|(k, v)| (k.clone().into(), v.to_lf_json())).collect())
+    }
+    fn from_lf_json(value: &Value) -> Option<Self> {
+        let entries = value
+            .as_object()?
+            .iter()
+            .map(|(k, v)| Some((K::from(k.clone()), V::from_lf_json(v)?)))
+            .collect::<Option<BTreeMap<_, _>>>()?;
+        Some(DamlTextMap::new(entries))
+    }
+}
+
+impl<K, V> JsonCodec for DamlMap<K, V>
+where
+    K: JsonCodec + DamlValue + Ord,
+    V: JsonCodec + DamlValue,
+{
+    fn to_lf_json(&self) -> Value {
+        self.value().to_lf_json()
+    }
+    fn from_lf_json(value: &Value) -> Option<Self> {
+        BTreeMap::<K, V>::from_lf_json(value).map(DamlMap::new)
+    }
+}
+
+impl<A: JsonCodec + DamlValue, B: JsonCodec + DamlValue> JsonCodec for DamlTuple2<A, B> {
+    fn to_lf_json(&self) -> Value {
+        let (first, second) = self.value();
+        serde_json::json!({ "_1": first.to_lf_json(), "_2": second.to_lf_json() })
+    }
+    fn from_lf_json(value: &Value) -> Option<Self> {
+        Some(DamlTuple2::new(A::from_lf_json(value.get("_1")?)?, B::from_lf_json(value.get("_2")?)?))
+    }
+}
+
+impl<A, B, C> JsonCodec for DamlTuple3<A, B, C>
+where
+    A: JsonCodec + DamlValue,
+    B: JsonCodec + DamlValue,
+    C: JsonCodec + DamlValue,
+{
+    fn to_lf_json(&self) -> Value {
+        let (first, second, third) = self.value();
+        serde_json::json!({ "_1": first.to_lf_json(), "_2": second.to_lf_json(), "_3": third.to_lf_json() })
+    }
+    fn from_lf_json(value: &Value) -> Option<Self> {
+        Some(DamlTuple3::new(
+            A::from_lf_json(value.get("_1")?)?,
+            B::from_lf_json(value.get("_2")?)?,
+            C::from_lf_json(value.get("_3")?)?,
+        ))
+    }
+}
+
+// A Set is the record {"map": [[element, {}], ...]}
+impl<T: JsonCodec + DamlValue + Ord> JsonCodec for DamlSet<T> {
+    fn to_lf_json(&self) -> Value {
+        serde_json::json!({ "map": pairs_to_lf_json(self.value().iter().map(|element| (element, &()))) })
+    }
+    fn from_lf_json(value: &Value) -> Option<Self> {
+        let map: BTreeMap<T, ()> = pairs_from_lf_json(value.get("map")?)?;
+        Some(DamlSet::new(map.into_keys().collect()))
+    }
+}
+
+impl<K, V> JsonCodec for DamlGenMap<K, V>
+where
+    K: JsonCodec + DamlValue + PartialEq,
+    V: JsonCodec + DamlValue,
+{
+    fn to_lf_json(&self) -> Value {
+        pairs_to_lf_json(self.value().iter().map(|(k, v)| (k, v)))
+    }
+    fn from_lf_json(value: &Value) -> Option<Self> {
+        pairs_from_lf_json(value).map(DamlGenMap::new)
+    }
+}
+
+// Implementations for std types, matching their `LapiAccess` encoding
+
+impl JsonCodec for i64 {
+    fn to_lf_json(&self) -> Value {
+        Value::String(self.to_string())
+    }
+    fn from_lf_json(value: &Value) -> Option<Self> {
+        int64_from_lf_json(value)
+    }
+}
+
+impl JsonCodec for String {
+    fn to_lf_json(&self) -> Value {
+        Value::String(self.clone())
+    }
+    fn from_lf_json(value: &Value) -> Option<Self> {
+        text_from_lf_json(value)
+    }
+}
+
+impl JsonCodec for bool {
+    fn to_lf_json(&self) -> Value {
+        Value::Bool(*self)
+    }
+    fn from_lf_json(value: &Value) -> Option<Self> {
+        value.as_bool()
+    }
+}
+
+impl JsonCodec for () {
+    fn to_lf_json(&self) -> Value {
+        Value::Object(Map::new())
+    }
+    fn from_lf_json(value: &Value) -> Option<Self> {
+        value.as_object().filter(|o| o.is_empty()).map(|_| ())
+    }
+}
+
+impl JsonCodec for chrono::NaiveDate {
+    fn to_lf_json(&self) -> Value {
+        DamlDate::new(*self).to_lf_json()
+    }
+    fn from_lf_json(value: &Value) -> Option<Self> {
+        DamlDate::from_lf_json(value).map(|date| *date.value())
+    }
+}
+
+impl JsonCodec for chrono::DateTime<chrono::Utc> {
+    fn to_lf_json(&self) -> Value {
+        DamlTimestamp::new(*self).to_lf_json()
+    }
+    fn from_lf_json(value: &Value) -> Option<Self> {
+        DamlTimestamp::from_lf_json(value).map(|ts| *ts.value())
+    }
+}
+
+// None is null, Some(x) is x, or [x] if x is itself an Optional, whose None is []
+impl<T: JsonCodec> JsonCodec for Option<T> {
+    const IS_OPTIONAL: bool = true;
+    fn to_lf_json(&self) -> Value {
+        match self {
+            None => Value::Null,
+            Some(x) if T::IS_OPTIONAL => match x.to_lf_json() {
+                Value::Null => Value::Array(vec![]),
+                inner => Value::Array(vec![inner]),
+            },
+            Some(x) => x.to_lf_json(),
+        }
+    }
+    fn from_lf_json(value: &Value) -> Option<Self> {
+        match value {
+            Value::Null => Some(None),
+            Value::Array(elements) if T::IS_OPTIONAL => match elements.as_slice() {
+                [] => T::from_lf_json(&Value::Null).map(Some),
+                [inner] => T::from_lf_json(inner).map(Some),
+                _ => None,
+            },
+            _ => T::from_lf_json(value).map(Some),
+        }
+    }
+}
+
+impl<T: JsonCodec> JsonCodec for Vec<T> {
+    fn to_lf_json(&self) -> Value {
+        Value::Array(self.iter().map(|x| x.to_lf_json()).collect())
+    }
+    fn from_lf_json(value: &Value) -> Option<Self> {
+        value.as_array()?.iter().map(T::from_lf_json).collect()
+    }
+}
+
+// A Map is a list of [key, value] pairs
+fn pairs_to_lf_json<'a, K, V>(entries: impl Iterator<Item = (&'a K, &'a V)>) -> Value
+where
+    K: JsonCodec + 'a,
+    V: JsonCodec + 'a,
+{
+    Value::Array(entries.map(|(k, v)| Value::Array(vec![k.to_lf_json(), v.to_lf_json()])).collect())
+}
+
+fn pairs_from_lf_json<K: JsonCodec, V: JsonCodec, C: FromIterator<(K, V)>>(value: &Value) -> Option<C> {
+    value
+        .as_array()?
+        .iter()
+        .map(|entry| match entry.as_array()?.as_slice() {
+            [k, v] => Some((K::from_lf_json(k)?, V::from_lf_json(v)?)),
+            _ => None,
+        })
+        .collect()
+}
+
+impl<K: JsonCodec + Ord, V: JsonCodec> JsonCodec for BTreeMap<K, V> {
+    fn to_lf_json(&self) -> Value {
+        pairs_to_lf_json(self.iter())
+    }
+    fn from_lf_json(value: &Value) -> Option<Self> {
+        pairs_from_lf_json(value)
+    }
+}
+
+/// Encodes an untyped Ledger API value, e.g. the create arguments of an event of unknown
+/// template. Records with labels become objects, positional records arrays.
+pub fn lapi_value_to_lf_json(value: &ledger_api::v2::Value) -> Value {
+    use ledger_api::v2::value::Sum;
+    match &value.sum {
+        None => Value::Null,
+        Some(Sum::Unit(_)) => Value::Object(Map::new()),
+        Some(Sum::Bool(b)) => Value::Bool(*b),
+        Some(Sum::Int64(i)) => Value::String(i.to_string()),
+        Some(Sum::Date(days)) => crate::lapi_access::date_from_days(*days)
+            .map(|date| Value::String(date.format("%Y-%m-%d").to_string()))
+            .unwrap_or(Value::Null),
+        Some(Sum::Timestamp(micros)) => chrono::DateTime::<chrono::Utc>::from_timestamp_micros(*micros)
+            .map(|ts| Value::String(ts.to_rfc3339_opts(chrono::SecondsFormat::AutoSi, true)))
+            .unwrap_or(Value::Null),
+        Some(Sum::Numeric(s)) | Some(Sum::Party(s)) | Some(Sum::Text(s)) | Some(Sum::ContractId(s)) => {
+            Value::String(s.clone())
+        }
+        Some(Sum::Optional(optional)) => match optional.value.as_deref() {
+            None => Value::Null,
+            Some(inner @ ledger_api::v2::Value { sum: Some(Sum::Optional(_)) }) => {
+                match lapi_value_to_lf_json(inner) {
+                    Value::Null => Value::Array(vec![]),
+                    inner => Value::Array(vec![inner]),
+                }
+            }
+            Some(inner) => lapi_value_to_lf_json(inner),
+        },
+        Some(Sum::List(list)) => Value::Array(list.elements.iter().map(lapi_value_to_lf_json).collect()),
+        Some(Sum::TextMap(map)) => Value::Object(
+            map.entries
+                .iter()
+                .map(|entry| {
+                    let value = entry.value.as_ref().map(lapi_value_to_lf_json).unwrap_or(Value::Null);
+                    (entry.key.clone(), value)
+                })
+                .collect(),
+        ),
+        Some(Sum::GenMap(map)) => Value::Array(
+            map.entries
+                .iter()
+                .map(|entry| {
+                    let encode = |v: &Option<ledger_api::v2::Value>| {
+                        v.as_ref().map(lapi_value_to_lf_json).unwrap_or(Value::Null)
+                    };
+                    Value::Array(vec![encode(&entry.key), encode(&entry.value)])
+                })
+                .collect(),
+        ),
+        Some(Sum::Record(record)) => lapi_record_to_lf_json(record),
+        Some(Sum::Variant(variant)) => serde_json::json!({
+            "tag": variant.constructor,
+            "value": variant.value.as_deref().map(lapi_value_to_lf_json).unwrap_or(Value::Null),
+        }),
+        Some(Sum::Enum(e)) => Value::String(e.constructor.clone()),
+    }
+}
+
+/// Encodes an untyped Ledger API record, see [`lapi_value_to_lf_json`].
+pub fn lapi_record_to_lf_json(record: &ledger_api::v2::Record) -> Value {
+    let encode = |f: &ledger_api::v2::RecordField| {
+        f.value.as_ref().map(lapi_value_to_lf_json).unwrap_or(Value::Null)
+    };
+    if record.fields.iter().all(|f| !f.label.is_empty()) {
+        Value::Object(record.fields.iter().map(|f| (f.label.clone(), encode(f))).collect())
+    } else {
+        Value::Array(record.fields.iter().map(encode).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lapi_access::LapiAccess;
+    use serde_json::json;
+
+    #[test]
+    fn test_lf_json_encoding() {
+        assert_eq!(DamlInt::new(42).to_lf_json(), json!("42"));
+        assert_eq!(DamlInt::from_lf_json(&json!(42)), Some(DamlInt::new(42)));
+        assert_eq!(DamlUnit::new().to_lf_json(), json!({}));
+        let date = chrono::NaiveDate::from_ymd_opt(2025, 1, 31).unwrap();
+        assert_eq!(DamlDate::new(date).to_lf_json(), json!("2025-01-31"));
+
+        let nested: Option<Option<i64>> = Some(None);
+        assert_eq!(nested.to_lf_json(), json!([]));
+        assert_eq!(Some(Some(1i64)).to_lf_json(), json!(["1"]));
+        assert_eq!(Option::<Option<i64>>::from_lf_json(&json!([])), Some(Some(None)));
+        assert_eq!(Option::<i64>::from_lf_json(&json!(null)), Some(None));
+
+        let map = BTreeMap::from([("a".to_string(), 1i64)]);
+        assert_eq!(map.to_lf_json(), json!([["a", "1"]]));
+        assert_eq!(BTreeMap::<String, i64>::from_lf_json(&json!([["a", "1"]])), Some(map));
+
+        let record = ledger_api::v2::Record {
+            record_id: None,
+            fields: vec![
+                DamlInt::new(42).to_lapi_record_field("count"),
+                DamlDate::new(date).to_lapi_record_field("due"),
+                Some(Some(1i64)).to_lapi_record_field("nested"),
+            ],
+        };
+        assert_eq!(
+            lapi_record_to_lf_json(&record),
+            json!({ "count": "42", "due": "2025-01-31", "nested": ["1"] })
+        );
+    }
+}
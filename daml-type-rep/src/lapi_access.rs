@@ -1,6 +1,6 @@
 use crate::built_in_types::*;
+use crate::decode_error::{LapiDecodeError, short_type_name};
 use ledger_api::v2::{Value, value::Sum, RecordField, Record};
-use chrono::Datelike;
 
 // Traits
 pub trait LapiAccess {
@@ -15,12 +15,139 @@ pub trait LapiAccess {
     fn from_lapi_value(_value: &Value) -> Option<Self> where Self: Sized {
         None
     }
+    /// Like `from_lapi_value`, telling which part of the value failed to decode
+    fn try_from_lapi_value(value: &Value) -> Result<Self, LapiDecodeError> where Self: Sized {
+        Self::from_lapi_value(value).ok_or_else(|| LapiDecodeError::unexpected(&short_type_name::<Self>(), value))
+    }
 }
 
 pub trait ToCreateArguments  {
     fn to_create_arguments(&self) -> Record;
 }
 
+/// Removes the trailing fields whose value is an empty `Optional`. A package upgrade only adds
+/// `Optional` fields at the end of a record, and the ledger accepts records without the trailing
+/// empty ones for both the old and the new version of a package.
+pub fn omit_trailing_none(fields: &mut Vec<RecordField>) {
+    while fields
+        .last()
+        .is_some_and(|field| matches!(&field.value, Some(Value { sum: Some(Sum::Optional(optional)) }) if optional.value.is_none()))
+    {
+        fields.pop();
+    }
+}
+
+// Daml dates are days since the Unix epoch
+const UNIX_EPOCH_DAYS_FROM_CE: i32 = 719_163;
+
+fn date_to_days(date: &chrono::NaiveDate) -> i32 {
+    use chrono::Datelike;
+    date.num_days_from_ce() - UNIX_EPOCH_DAYS_FROM_CE
+}
+
+pub(crate) fn date_from_days(days: i32) -> Option<chrono::NaiveDate> {
+    chrono::NaiveDate::from_num_days_from_ce_opt(days.checked_add(UNIX_EPOCH_DAYS_FROM_CE)?)
+}
+
+// Decoders of the containers, shared by the Daml* wrappers and the std types
+
+fn try_list<T: LapiAccess>(value: &Value, expected: &str) -> Result<Vec<T>, LapiDecodeError> {
+    match &value.sum {
+        Some(Sum::List(list)) => list
+            .elements
+            .iter()
+            .enumerate()
+            .map(|(i, v)| T::try_from_lapi_value(v).map_err(|e| e.in_index(i)))
+            .collect(),
+        _ => Err(LapiDecodeError::unexpected(expected, value)),
+    }
+}
+
+fn try_optional<T: LapiAccess>(value: &Value, expected: &str) -> Result<Option<T>, LapiDecodeError> {
+    match &value.sum {
+        Some(Sum::Optional(opt)) => opt.value.as_deref().map(T::try_from_lapi_value).transpose(),
+        _ => Err(LapiDecodeError::unexpected(expected, value)),
+    }
+}
+
+fn entry_value(value: &Option<Value>) -> Result<&Value, LapiDecodeError> {
+    value.as_ref().ok_or_else(|| LapiDecodeError::new("missing map entry value"))
+}
+
+fn try_text_map<K: TextMapKey, V: LapiAccess>(
+    value: &Value,
+    expected: &str,
+) -> Result<std::collections::BTreeMap<K, V>, LapiDecodeError> {
+    match &value.sum {
+        Some(Sum::TextMap(map)) => map
+            .entries
+            .iter()
+            .map(|entry| {
+                let v = entry_value(&entry.value)
+                    .and_then(V::try_from_lapi_value)
+                    .map_err(|e| e.in_key(&entry.key))?;
+                Ok((K::from(entry.key.clone()), v))
+            })
+            .collect(),
+        _ => Err(LapiDecodeError::unexpected(expected, value)),
+    }
+}
+
+// A TextMap is accepted if `accept_text_map`, with the keys decoded from Text values
+fn try_gen_map<K: LapiAccess, V: LapiAccess, C: FromIterator<(K, V)>>(
+    value: &Value,
+    expected: &str,
+    accept_text_map: bool,
+) -> Result<C, LapiDecodeError> {
+    match &value.sum {
+        Some(Sum::GenMap(gen_map)) => gen_map
+            .entries
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| {
+                let decode = || {
+                    let key = entry.key.as_ref().ok_or_else(|| LapiDecodeError::new("missing map entry key"))?;
+                    let k = K::try_from_lapi_value(key)?;
+                    let v = V::try_from_lapi_value(entry_value(&entry.value)?)?;
+                    Ok((k, v))
+                };
+                decode().map_err(|e: LapiDecodeError| e.in_index(i))
+            })
+            .collect(),
+        Some(Sum::TextMap(text_map)) if accept_text_map => text_map
+            .entries
+            .iter()
+            .map(|entry| {
+                let decode = || {
+                    let k = K::try_from_lapi_value(&Value { sum: Some(Sum::Text(entry.key.clone())) })?;
+                    let v = V::try_from_lapi_value(entry_value(&entry.value)?)?;
+                    Ok((k, v))
+                };
+                decode().map_err(|e: LapiDecodeError| e.in_key(&entry.key))
+            })
+            .collect(),
+        _ => Err(LapiDecodeError::unexpected(expected, value)),
+    }
+}
+
+// The field with `label`, or the one at `index` if the ledger sent the record without labels
+fn try_record_field<T: LapiAccess>(record: &Record, label: &str, index: usize) -> Result<T, LapiDecodeError> {
+    let field = record
+        .fields
+        .iter()
+        .find(|f| f.label == label)
+        .or_else(|| record.fields.get(index).filter(|f| f.label.is_empty()))
+        .and_then(|f| f.value.as_ref())
+        .ok_or_else(|| LapiDecodeError::missing_field(label))?;
+    T::try_from_lapi_value(field).map_err(|e| e.in_field(label))
+}
+
+fn record_value(fields: Vec<RecordField>) -> Value {
+    Value {
+        sum: Some(Sum::Record(Record { record_id: None, fields })),
+    }
+}
+
 // Implementations for built-in types
 
 // DamlInt
@@ -72,12 +199,12 @@ impl LapiAccess for DamlBool {
 impl LapiAccess for DamlDate {
     fn to_lapi_value(&self) -> Value {
         Value {
-            sum: Some(Sum::Date(self.value().num_days_from_ce())),
+            sum: Some(Sum::Date(date_to_days(self.value()))),
         }
     }
     fn from_lapi_value(value: &Value) -> Option<Self> {
         match &value.sum {
-            Some(Sum::Date(days)) => chrono::NaiveDate::from_num_days_from_ce_opt(*days).map(DamlDate::new),
+            Some(Sum::Date(days)) => date_from_days(*days).map(DamlDate::new),
             _ => None,
         }
     }
@@ -118,6 +245,31 @@ impl LapiAccess for DamlTimestamp {
     }
 }
 
+// DamlRelTime, which the ledger API represents as the `DA.Time.Types.RelTime` record
+impl LapiAccess for DamlRelTime {
+    fn to_lapi_value(&self) -> Value {
+        Value {
+            sum: Some(Sum::Record(Record {
+                record_id: None,
+                fields: vec![self.microseconds.to_lapi_record_field("microseconds")],
+            })),
+        }
+    }
+    fn from_lapi_value(value: &Value) -> Option<Self> {
+        match &value.sum {
+            Some(Sum::Record(record)) => {
+                let field = record
+                    .fields
+                    .iter()
+                    .find(|f| f.label == "microseconds")
+                    .or_else(|| record.fields.first())?;
+                i64::from_lapi_value(field.value.as_ref()?).map(DamlRelTime::new)
+            }
+            _ => None,
+        }
+    }
+}
+
 // DamlUnit
 impl LapiAccess for DamlUnit {
     fn to_lapi_value(&self) -> Value {
@@ -143,13 +295,10 @@ impl<T: LapiAccess + DamlValue> LapiAccess for DamlList<T> {
         }
     }
     fn from_lapi_value(value: &Value) -> Option<Self> {
-        match &value.sum {
-            Some(Sum::List(list)) => {
-                let elements: Option<Vec<T>> = list.elements.iter().map(|v| T::from_lapi_value(v)).collect();
-                elements.map(DamlList::new)
-            },
-            _ => None,
-        }
+        Self::try_from_lapi_value(value).ok()
+    }
+    fn try_from_lapi_value(value: &Value) -> Result<Self, LapiDecodeError> {
+        try_list(value, &short_type_name::<Self>()).map(DamlList::new)
     }
 }
 
@@ -163,25 +312,127 @@ impl<T: LapiAccess + DamlValue> LapiAccess for DamlOptional<T> {
         }
     }
     fn from_lapi_value(value: &Value) -> Option<Self> {
+        Self::try_from_lapi_value(value).ok()
+    }
+    fn try_from_lapi_value(value: &Value) -> Result<Self, LapiDecodeError> {
+        try_optional(value, &short_type_name::<Self>()).map(DamlOptional::new)
+    }
+}
+
+// DamlGenMap, keeping the order of the entries
+impl<K, V> LapiAccess for DamlGenMap<K, V>
+where
+    K: LapiAccess + DamlValue + PartialEq,
+    V: LapiAccess + DamlValue,
+{
+    fn to_lapi_value(&self) -> Value {
+        Value {
+            sum: Some(Sum::GenMap(ledger_api::v2::GenMap {
+                entries: self.value().iter().map(|(k, v)| {
+                    ledger_api::v2::gen_map::Entry {
+                        key: Some(k.to_lapi_value()),
+                        value: Some(v.to_lapi_value()),
+                    }
+                }).collect(),
+            })),
+        }
+    }
+    fn from_lapi_value(value: &Value) -> Option<Self> {
+        Self::try_from_lapi_value(value).ok()
+    }
+    fn try_from_lapi_value(value: &Value) -> Result<Self, LapiDecodeError> {
+        try_gen_map(value, &short_type_name::<Self>(), false).map(DamlGenMap::new)
+    }
+}
+
+// DamlTuple2 as the record DA.Types.Tuple2
+impl<A: LapiAccess + DamlValue, B: LapiAccess + DamlValue> LapiAccess for DamlTuple2<A, B> {
+    fn to_lapi_value(&self) -> Value {
+        let (first, second) = self.value();
+        record_value(vec![first.to_lapi_record_field("_1"), second.to_lapi_record_field("_2")])
+    }
+    fn from_lapi_value(value: &Value) -> Option<Self> {
+        Self::try_from_lapi_value(value).ok()
+    }
+    fn try_from_lapi_value(value: &Value) -> Result<Self, LapiDecodeError> {
         match &value.sum {
-            Some(Sum::Optional(opt)) => {
-                let inner = opt.value.as_ref().map(|v| T::from_lapi_value(v)).flatten();
-                Some(DamlOptional::new(inner))
-            },
-            _ => None,
+            Some(Sum::Record(record)) => Ok(DamlTuple2::new(
+                try_record_field(record, "_1", 0)?,
+                try_record_field(record, "_2", 1)?,
+            )),
+            _ => Err(LapiDecodeError::unexpected(&short_type_name::<Self>(), value)),
+        }
+    }
+}
+
+// DamlTuple3 as the record DA.Types.Tuple3
+impl<A, B, C> LapiAccess for DamlTuple3<A, B, C>
+where
+    A: LapiAccess + DamlValue,
+    B: LapiAccess + DamlValue,
+    C: LapiAccess + DamlValue,
+{
+    fn to_lapi_value(&self) -> Value {
+        let (first, second, third) = self.value();
+        record_value(vec![
+            first.to_lapi_record_field("_1"),
+            second.to_lapi_record_field("_2"),
+            third.to_lapi_record_field("_3"),
+        ])
+    }
+    fn from_lapi_value(value: &Value) -> Option<Self> {
+        Self::try_from_lapi_value(value).ok()
+    }
+    fn try_from_lapi_value(value: &Value) -> Result<Self, LapiDecodeError> {
+        match &value.sum {
+            Some(Sum::Record(record)) => Ok(DamlTuple3::new(
+                try_record_field(record, "_1", 0)?,
+                try_record_field(record, "_2", 1)?,
+                try_record_field(record, "_3", 2)?,
+            )),
+            _ => Err(LapiDecodeError::unexpected(&short_type_name::<Self>(), value)),
+        }
+    }
+}
+
+// DamlSet as the record DA.Set.Types.Set, whose `map` field is a GenMap with Unit values
+impl<T: LapiAccess + DamlValue + Ord> LapiAccess for DamlSet<T> {
+    fn to_lapi_value(&self) -> Value {
+        let map = Value {
+            sum: Some(Sum::GenMap(ledger_api::v2::GenMap {
+                entries: self.value().iter().map(|element| {
+                    ledger_api::v2::gen_map::Entry {
+                        key: Some(element.to_lapi_value()),
+                        value: Some(DamlUnit::new().to_lapi_value()),
+                    }
+                }).collect(),
+            })),
+        };
+        record_value(vec![RecordField { label: "map".to_string(), value: Some(map) }])
+    }
+    fn from_lapi_value(value: &Value) -> Option<Self> {
+        Self::try_from_lapi_value(value).ok()
+    }
+    fn try_from_lapi_value(value: &Value) -> Result<Self, LapiDecodeError> {
+        match &value.sum {
+            Some(Sum::Record(record)) => {
+                let map: std::collections::BTreeMap<T, DamlUnit> = try_record_field(record, "map", 0)?;
+                Ok(DamlSet::new(map.into_keys().collect()))
+            }
+            _ => Err(LapiDecodeError::unexpected(&short_type_name::<Self>(), value)),
         }
     }
 }
 
 // DamlTextMap
-impl<V: LapiAccess + DamlValue> LapiAccess for DamlTextMap<V> {
+impl<V: LapiAccess + DamlValue, K: TextMapKey> LapiAccess for DamlTextMap<V, K> {
     fn to_lapi_value(&self) -> Value {
         Value {
             sum: Some(Sum::TextMap(ledger_api::v2::TextMap {
                 entries: self.value()
                     .iter()
                     .map(|(k, v)| ledger_api::v2::text_map::Entry {
-                        key: k.clone(),
+                        key: k.clone().into(),
                         value: Some(v.to_lapi_value()),
                     })
                     .collect(),
@@ -189,24 +440,10 @@ impl<V: LapiAccess + DamlValue> LapiAccess for DamlTextMap<V> {
         }
     }
     fn from_lapi_value(value: &Value) -> Option<Self> {
-        match &value.sum {
-            Some(Sum::TextMap(map)) => {
-                let mut result = std::collections::BTreeMap::new();
-                for entry in &map.entries {
-                    if let (Some(v), k) = (entry.value.as_ref(), &entry.key) {
-                        if let Some(val) = V::from_lapi_value(v) {
-                            result.insert(k.clone(), val);
-                        } else {
-                            return None;
-                        }
-                    } else {
-                        return None;
-                    }
-                }
-                Some(DamlTextMap::new(result))
-            },
-            _ => None,
-        }
+        Self::try_from_lapi_value(value).ok()
+    }
+    fn try_from_lapi_value(value: &Value) -> Result<Self, LapiDecodeError> {
+        try_text_map(value, &short_type_name::<Self>()).map(DamlTextMap::new)
     }
 }
 
@@ -229,22 +466,10 @@ where
         }
     }
     fn from_lapi_value(value: &Value) -> Option<Self> {
-        match &value.sum {
-            Some(Sum::GenMap(gen_map)) => {
-                let mut result = std::collections::BTreeMap::new();
-                for entry in &gen_map.entries {
-                    if let (Some(kv), Some(vv)) = (entry.key.as_ref(), entry.value.as_ref()) {
-                        let k = K::from_lapi_value(kv)?;
-                        let v = V::from_lapi_value(vv)?;
-                        result.insert(k, v);
-                    } else {
-                        return None;
-                    }
-                }
-                Some(DamlMap::new(result))
-            },
-            _ => None,
-        }
+        Self::try_from_lapi_value(value).ok()
+    }
+    fn try_from_lapi_value(value: &Value) -> Result<Self, LapiDecodeError> {
+        try_gen_map(value, &short_type_name::<Self>(), false).map(DamlMap::new)
     }
 }
 
@@ -287,7 +512,7 @@ impl LapiAccess for DamlDecimal {
     }
     fn from_lapi_value(value: &Value) -> Option<Self> {
         match &value.sum {
-            Some(Sum::Numeric(s)) => s.parse().ok().map(DamlDecimal::new),
+            Some(Sum::Numeric(s)) => DamlDecimal::try_from_str(s).ok(),
             _ => None,
         }
     }
@@ -302,7 +527,7 @@ impl LapiAccess for DamlNumeric {
     }
     fn from_lapi_value(value: &Value) -> Option<Self> {
         match &value.sum {
-            Some(Sum::Numeric(s)) => s.parse().ok().map(|v| DamlNumeric::new(v, NumericScale(10))),
+            Some(Sum::Numeric(s)) => s.parse().ok().map(|v| DamlNumeric::from_numeric(v, NumericScale(10))),
             _ => None,
         }
     }
@@ -323,3 +548,219 @@ impl LapiAccess for i64 {
     }
 }
 
+
+// Implementations for std types, so application structs can use them directly instead of the
+// Daml* wrappers
+
+// String as Text
+impl LapiAccess for String {
+    fn to_lapi_value(&self) -> Value {
+        Value {
+            sum: Some(Sum::Text(self.clone())),
+        }
+    }
+    fn from_lapi_value(value: &Value) -> Option<Self> {
+        match &value.sum {
+            Some(Sum::Text(s)) => Some(s.clone()),
+            _ => None,
+        }
+    }
+}
+
+// bool as Bool
+impl LapiAccess for bool {
+    fn to_lapi_value(&self) -> Value {
+        Value {
+            sum: Some(Sum::Bool(*self)),
+        }
+    }
+    fn from_lapi_value(value: &Value) -> Option<Self> {
+        match &value.sum {
+            Some(Sum::Bool(b)) => Some(*b),
+            _ => None,
+        }
+    }
+}
+
+// Option<T> as Optional
+impl<T: LapiAccess> LapiAccess for Option<T> {
+    fn to_lapi_value(&self) -> Value {
+        Value {
+            sum: Some(Sum::Optional(Box::new(ledger_api::v2::Optional {
+                value: self.as_ref().map(|x| Box::new(x.to_lapi_value())),
+            }))),
+        }
+    }
+    fn from_lapi_value(value: &Value) -> Option<Self> {
+        Self::try_from_lapi_value(value).ok()
+    }
+    fn try_from_lapi_value(value: &Value) -> Result<Self, LapiDecodeError> {
+        try_optional(value, &short_type_name::<Self>())
+    }
+}
+
+// Vec<T> as List
+impl<T: LapiAccess> LapiAccess for Vec<T> {
+    fn to_lapi_value(&self) -> Value {
+        Value {
+            sum: Some(Sum::List(ledger_api::v2::List {
+                elements: self.iter().map(|x| x.to_lapi_value()).collect(),
+            })),
+        }
+    }
+    fn from_lapi_value(value: &Value) -> Option<Self> {
+        Self::try_from_lapi_value(value).ok()
+    }
+    fn try_from_lapi_value(value: &Value) -> Result<Self, LapiDecodeError> {
+        try_list(value, &short_type_name::<Self>())
+    }
+}
+
+// BTreeMap<K, V> as Map (GenMap), also with String keys: a Daml TextMap is a `DamlTextMap<V>`.
+// A TextMap is also accepted when decoding, with the keys decoded from Text values, so
+// `BTreeMap<String, V>` can read both.
+impl<K, V> LapiAccess for std::collections::BTreeMap<K, V>
+where
+    K: LapiAccess + Ord,
+    V: LapiAccess,
+{
+    fn to_lapi_value(&self) -> Value {
+        Value {
+            sum: Some(Sum::GenMap(ledger_api::v2::GenMap {
+                entries: self.iter().map(|(k, v)| {
+                    ledger_api::v2::gen_map::Entry {
+                        key: Some(k.to_lapi_value()),
+                        value: Some(v.to_lapi_value()),
+                    }
+                }).collect(),
+            })),
+        }
+    }
+    fn from_lapi_value(value: &Value) -> Option<Self> {
+        Self::try_from_lapi_value(value).ok()
+    }
+    fn try_from_lapi_value(value: &Value) -> Result<Self, LapiDecodeError> {
+        try_gen_map(value, &short_type_name::<Self>(), true)
+    }
+}
+
+// () as Unit
+impl LapiAccess for () {
+    fn to_lapi_value(&self) -> Value {
+        Value {
+            sum: Some(Sum::Unit(())),
+        }
+    }
+    fn from_lapi_value(value: &Value) -> Option<Self> {
+        match &value.sum {
+            Some(Sum::Unit(_)) => Some(()),
+            _ => None,
+        }
+    }
+}
+
+// chrono::NaiveDate as Date
+impl LapiAccess for chrono::NaiveDate {
+    fn to_lapi_value(&self) -> Value {
+        Value {
+            sum: Some(Sum::Date(date_to_days(self))),
+        }
+    }
+    fn from_lapi_value(value: &Value) -> Option<Self> {
+        match &value.sum {
+            Some(Sum::Date(days)) => date_from_days(*days),
+            _ => None,
+        }
+    }
+}
+
+// chrono::DateTime<Utc> as Timestamp, with microsecond precision
+impl LapiAccess for chrono::DateTime<chrono::Utc> {
+    fn to_lapi_value(&self) -> Value {
+        Value {
+            sum: Some(Sum::Timestamp(self.timestamp_micros())),
+        }
+    }
+    fn from_lapi_value(value: &Value) -> Option<Self> {
+        match &value.sum {
+            Some(Sum::Timestamp(micros)) => chrono::DateTime::<chrono::Utc>::from_timestamp_micros(*micros),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+    struct Sku(String);
+
+    impl From<String> for Sku {
+        fn from(s: String) -> Self {
+            Sku(s)
+        }
+    }
+
+    impl From<Sku> for String {
+        fn from(sku: Sku) -> Self {
+            sku.0
+        }
+    }
+
+    #[test]
+    fn test_tuple_and_set_records() {
+        let tuple = DamlTuple2::new(DamlText::new("a"), DamlInt::new(1));
+        let value = tuple.to_lapi_value();
+        assert_eq!(DamlTuple2::from_lapi_value(&value), Some(tuple));
+
+        // Records of non-verbose transactions have no labels
+        let unlabeled = record_value(vec![
+            DamlInt::new(1).to_lapi_record_field(""),
+            DamlInt::new(2).to_lapi_record_field(""),
+            DamlInt::new(3).to_lapi_record_field(""),
+        ]);
+        let triple = DamlTuple3::<DamlInt, DamlInt, DamlInt>::from_lapi_value(&unlabeled).unwrap();
+        assert_eq!(triple.value(), &(DamlInt::new(1), DamlInt::new(2), DamlInt::new(3)));
+
+        let set = DamlSet::new([DamlParty::new("Bob"), DamlParty::new("Alice")].into());
+        let value = set.to_lapi_value();
+        let Some(Sum::Record(record)) = &value.sum else {
+            panic!("Expected a record");
+        };
+        assert_eq!(record.fields[0].label, "map");
+        assert_eq!(DamlSet::from_lapi_value(&value), Some(set));
+    }
+
+    #[test]
+    fn test_btreemap_of_string_keys_round_trips_a_gen_map() {
+        // A Daml `Map Text Int`
+        let limits = BTreeMap::from([("daily".to_string(), 100i64)]);
+        let value = limits.to_lapi_value();
+        let Some(Sum::GenMap(map)) = &value.sum else { panic!("not a GenMap: {:?}", value) };
+        assert_eq!(map.entries[0].key, Some("daily".to_string().to_lapi_value()));
+        assert_eq!(BTreeMap::<String, i64>::from_lapi_value(&value), Some(limits.clone()));
+
+        // A Daml `TextMap Int`
+        let text_map = DamlTextMap::<i64, String>::new(limits);
+        let value = text_map.to_lapi_value();
+        assert!(matches!(value.sum, Some(Sum::TextMap(_))));
+        assert_eq!(DamlTextMap::<i64, String>::from_lapi_value(&value), Some(text_map));
+    }
+
+    #[test]
+    fn test_text_map_with_typed_keys() {
+        let stock: DamlTextMap<DamlInt, Sku> =
+            DamlTextMap::new(BTreeMap::from([(Sku("tv".to_string()), DamlInt::new(3))]));
+        let value = stock.to_lapi_value();
+        let Some(Sum::TextMap(map)) = &value.sum else {
+            panic!("Expected a text map");
+        };
+        assert_eq!(map.entries[0].key, "tv");
+        assert_eq!(DamlTextMap::<DamlInt, Sku>::from_lapi_value(&value), Some(stock));
+
+        let untyped = DamlTextMap::<DamlInt>::from_lapi_value(&value).unwrap();
+        assert_eq!(untyped.value().get(&DamlText::new("tv")), Some(&DamlInt::new(3)));
+    }
+}
@@ -1,5 +1,11 @@
 pub mod built_in_types;
 pub mod numeric_scale;
 pub mod lapi_access;
+pub mod decode_error;
 pub mod template_id;
-
+pub mod choice;
+pub mod contract;
+pub mod contract_key;
+pub mod interface_view;
+pub mod json_codec;
+pub mod schema;
@@ -0,0 +1,285 @@
+//! Schemas of the payloads and choice arguments of templates, generated by `codegen` from DARs, to
+//! encode Ledger API values of known templates in the Daml-LF JSON encoding without compiling
+//! their generated Rust types
+use crate::json_codec::lapi_value_to_lf_json;
+use ledger_api::v2::value::Sum;
+use ledger_api::v2::{Record, Value};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// A Daml type
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TypeSchema {
+    Unit,
+    Bool,
+    Int64,
+    Numeric,
+    Text,
+    Party,
+    ContractId,
+    Date,
+    Timestamp,
+    Optional { element: Box<TypeSchema> },
+    List { element: Box<TypeSchema> },
+    TextMap { value: Box<TypeSchema> },
+    GenMap { key: Box<TypeSchema>, value: Box<TypeSchema> },
+    /// A parameter of the generic data type being defined
+    Var { name: String },
+    /// A data type of `TemplateRegistry::data_types` applied to its arguments
+    DataType { id: String, args: Vec<TypeSchema> },
+    /// A type without schema, encoded like an untyped value
+    Unknown,
+}
+
+/// A named field of a record, or a constructor of a variant with the type of its argument
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FieldSchema {
+    pub name: String,
+    pub schema: TypeSchema,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum DataTypeDefinition {
+    Record { fields: Vec<FieldSchema> },
+    Variant { constructors: Vec<FieldSchema> },
+    Enum { constructors: Vec<String> },
+}
+
+/// A data type with the names of its type parameters
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DataTypeSchema {
+    pub params: Vec<String>,
+    pub definition: DataTypeDefinition,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TemplateSchema {
+    pub payload: TypeSchema,
+    pub key: Option<TypeSchema>,
+    /// Choice argument types by choice name
+    pub choices: BTreeMap<String, TypeSchema>,
+}
+
+/// The templates of DARs by `<Module.Name>.<Entity>`, and the data types they refer to by
+/// `<package id>:<Module.Name>:<Name>`
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct TemplateRegistry {
+    pub templates: BTreeMap<String, TemplateSchema>,
+    pub data_types: BTreeMap<String, DataTypeSchema>,
+}
+
+impl TemplateRegistry {
+    pub fn from_json(json: &str) -> anyhow::Result<Self> {
+        serde_json::from_str(json).map_err(|e| anyhow::anyhow!("Failed to parse template registry: {}", e))
+    }
+
+    pub fn template(&self, module_name: &str, entity_name: &str) -> Option<&TemplateSchema> {
+        self.templates.get(&format!("{}.{}", module_name, entity_name))
+    }
+
+    /// Encodes a value of the type, `None` if it does not match the schema, e.g. a record with more
+    /// fields than the data type, of a newer version of its package
+    pub fn encode(&self, schema: &TypeSchema, value: &Value) -> Option<serde_json::Value> {
+        self.encode_in(schema, &HashMap::new(), value)
+    }
+
+    /// Encodes a record of the type, see [`TemplateRegistry::encode`]
+    pub fn encode_record(&self, schema: &TypeSchema, record: &Record) -> Option<serde_json::Value> {
+        self.encode(schema, &Value { sum: Some(Sum::Record(record.clone())) })
+    }
+
+    /// Encodes with the type parameters of the enclosing data type bound to their arguments
+    fn encode_in(
+        &self,
+        schema: &TypeSchema,
+        env: &HashMap<String, TypeSchema>,
+        value: &Value,
+    ) -> Option<serde_json::Value> {
+        let sum = value.sum.as_ref()?;
+        match (schema, sum) {
+            (TypeSchema::Var { name }, _) => self.encode(env.get(name)?, value),
+            (TypeSchema::Unknown, _)
+            | (TypeSchema::Unit, Sum::Unit(_))
+            | (TypeSchema::Bool, Sum::Bool(_))
+            | (TypeSchema::Int64, Sum::Int64(_))
+            | (TypeSchema::Numeric, Sum::Numeric(_))
+            | (TypeSchema::Text, Sum::Text(_))
+            | (TypeSchema::Party, Sum::Party(_))
+            | (TypeSchema::ContractId, Sum::ContractId(_))
+            | (TypeSchema::Date, Sum::Date(_))
+            | (TypeSchema::Timestamp, Sum::Timestamp(_)) => Some(lapi_value_to_lf_json(value)),
+            (TypeSchema::Optional { element }, Sum::Optional(optional)) => match optional.value.as_deref() {
+                None => Some(serde_json::Value::Null),
+                Some(inner) => {
+                    let encoded = self.encode_in(element, env, inner)?;
+                    // Nested optionals are encoded as `[]` and `[x]`
+                    if self.is_optional(element, env) {
+                        Some(serde_json::Value::Array(if encoded.is_null() { vec![] } else { vec![encoded] }))
+                    } else {
+                        Some(encoded)
+                    }
+                }
+            },
+            (TypeSchema::List { element }, Sum::List(list)) => list
+                .elements
+                .iter()
+                .map(|element_value| self.encode_in(element, env, element_value))
+                .collect::<Option<Vec<_>>>()
+                .map(serde_json::Value::Array),
+            (TypeSchema::TextMap { value: value_schema }, Sum::TextMap(map)) => map
+                .entries
+                .iter()
+                .map(|entry| Some((entry.key.clone(), self.encode_in(value_schema, env, entry.value.as_ref()?)?)))
+                .collect::<Option<serde_json::Map<_, _>>>()
+                .map(serde_json::Value::Object),
+            (TypeSchema::GenMap { key, value: value_schema }, Sum::GenMap(map)) => map
+                .entries
+                .iter()
+                .map(|entry| {
+                    let key = self.encode_in(key, env, entry.key.as_ref()?)?;
+                    Some(serde_json::Value::Array(vec![key, self.encode_in(value_schema, env, entry.value.as_ref()?)?]))
+                })
+                .collect::<Option<Vec<_>>>()
+                .map(serde_json::Value::Array),
+            (TypeSchema::DataType { id, args }, _) => {
+                let data_type = self.data_types.get(id)?;
+                let args = args.iter().map(|arg| substitute(arg, env));
+                let env: HashMap<String, TypeSchema> = data_type.params.iter().cloned().zip(args).collect();
+                self.encode_data_type(&data_type.definition, &env, sum)
+            }
+            _ => None,
+        }
+    }
+
+    fn encode_data_type(
+        &self,
+        definition: &DataTypeDefinition,
+        env: &HashMap<String, TypeSchema>,
+        sum: &Sum,
+    ) -> Option<serde_json::Value> {
+        match (definition, sum) {
+            // Fields are matched by position, labels are only sent by verbose streams. Missing
+            // trailing fields are the optional fields of a newer version of the package.
+            (DataTypeDefinition::Record { fields }, Sum::Record(record)) if record.fields.len() <= fields.len() => record
+                .fields
+                .iter()
+                .zip(fields)
+                .map(|(field, field_schema)| {
+                    Some((field_schema.name.clone(), self.encode_in(&field_schema.schema, env, field.value.as_ref()?)?))
+                })
+                .collect::<Option<serde_json::Map<_, _>>>()
+                .map(serde_json::Value::Object),
+            (DataTypeDefinition::Variant { constructors }, Sum::Variant(variant)) => {
+                let constructor = constructors.iter().find(|constructor| constructor.name == variant.constructor)?;
+                let value = self.encode_in(&constructor.schema, env, variant.value.as_deref()?)?;
+                Some(serde_json::json!({ "tag": constructor.name, "value": value }))
+            }
+            (DataTypeDefinition::Enum { constructors }, Sum::Enum(enum_value)) if constructors.contains(&enum_value.constructor) => {
+                Some(serde_json::Value::String(enum_value.constructor.clone()))
+            }
+            _ => None,
+        }
+    }
+
+    fn is_optional(&self, schema: &TypeSchema, env: &HashMap<String, TypeSchema>) -> bool {
+        match schema {
+            TypeSchema::Optional { .. } => true,
+            TypeSchema::Var { name } => env.get(name).is_some_and(|schema| self.is_optional(schema, &HashMap::new())),
+            _ => false,
+        }
+    }
+}
+
+/// The type with the type parameters of the enclosing data type replaced by their arguments
+fn substitute(schema: &TypeSchema, env: &HashMap<String, TypeSchema>) -> TypeSchema {
+    let boxed = |schema: &TypeSchema| Box::new(substitute(schema, env));
+    match schema {
+        TypeSchema::Var { name } => env.get(name).cloned().unwrap_or(TypeSchema::Unknown),
+        TypeSchema::Optional { element } => TypeSchema::Optional { element: boxed(element) },
+        TypeSchema::List { element } => TypeSchema::List { element: boxed(element) },
+        TypeSchema::TextMap { value } => TypeSchema::TextMap { value: boxed(value) },
+        TypeSchema::GenMap { key, value } => TypeSchema::GenMap { key: boxed(key), value: boxed(value) },
+        TypeSchema::DataType { id, args } => TypeSchema::DataType {
+            id: id.clone(),
+            args: args.iter().map(|arg| substitute(arg, env)).collect(),
+        },
+        other => other.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ledger_api::v2::{Optional, RecordField, Variant};
+
+    fn value(sum: Sum) -> Value {
+        Value { sum: Some(sum) }
+    }
+
+    #[test]
+    fn test_encode_with_schema() {
+        // data Asset = Asset with owner : Party; amount : Int; tag : Optional (Tagged Int)
+        // data Tagged a = Tagged a | Untagged
+        let data_type = |id: &str, args| TypeSchema::DataType { id: id.to_string(), args };
+        let field = |name: &str, schema| FieldSchema { name: name.to_string(), schema };
+        let mut registry = TemplateRegistry::default();
+        registry.data_types.insert(
+            "abc:Main:Asset".to_string(),
+            DataTypeSchema {
+                params: vec![],
+                definition: DataTypeDefinition::Record {
+                    fields: vec![
+                        field("owner", TypeSchema::Party),
+                        field("amount", TypeSchema::Int64),
+                        field(
+                            "tag",
+                            TypeSchema::Optional { element: Box::new(data_type("abc:Main:Tagged", vec![TypeSchema::Int64])) },
+                        ),
+                    ],
+                },
+            },
+        );
+        registry.data_types.insert(
+            "abc:Main:Tagged".to_string(),
+            DataTypeSchema {
+                params: vec!["a".to_string()],
+                definition: DataTypeDefinition::Variant {
+                    constructors: vec![field("Tagged", TypeSchema::Var { name: "a".to_string() }), field("Untagged", TypeSchema::Unit)],
+                },
+            },
+        );
+        let asset = data_type("abc:Main:Asset", vec![]);
+
+        // A positional record, as sent by non-verbose streams
+        let tagged = value(Sum::Variant(Box::new(Variant {
+            variant_id: None,
+            constructor: "Tagged".to_string(),
+            value: Some(Box::new(value(Sum::Int64(7)))),
+        })));
+        let record = Record {
+            record_id: None,
+            fields: vec![
+                RecordField { label: String::new(), value: Some(value(Sum::Party("Alice".to_string()))) },
+                RecordField { label: String::new(), value: Some(value(Sum::Int64(3))) },
+                RecordField {
+                    label: String::new(),
+                    value: Some(value(Sum::Optional(Box::new(Optional { value: Some(Box::new(tagged)) })))),
+                },
+            ],
+        };
+        assert_eq!(
+            registry.encode_record(&asset, &record),
+            Some(serde_json::json!({ "owner": "Alice", "amount": "3", "tag": { "tag": "Tagged", "value": "7" } }))
+        );
+
+        // Values which do not match the schema are not encoded
+        let mut mismatch = record.clone();
+        mismatch.fields[1].value = Some(value(Sum::Text("3".to_string())));
+        assert_eq!(registry.encode_record(&asset, &mismatch), None);
+
+        let json = serde_json::to_string(&registry).unwrap();
+        assert_eq!(TemplateRegistry::from_json(&json).unwrap(), registry);
+    }
+}
@@ -1,8 +1,14 @@
 use ledger_api::v2::Identifier;
+use std::fmt;
 
-#[derive(Clone)]
+/// A template or interface id, spelled `<package>:<Module.Name>:<Entity>`, where the package is a
+/// package id or a `#<package-name>` reference
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct TemplateId(String, String, String);
 
+/// Interfaces are identified like templates
+pub type InterfaceId = TemplateId;
+
 impl TemplateId {
     pub fn new(package_id: &str, module_name: &str, entity_name: &str) -> Self {
         TemplateId(package_id.to_string(), module_name.to_string(), entity_name.to_string())
@@ -15,4 +21,84 @@ impl TemplateId {
             entity_name: self.2.clone(),
         }
     }
-}
\ No newline at end of file
+
+    pub fn package_id(&self) -> &str {
+        &self.0
+    }
+
+    pub fn module_name(&self) -> &str {
+        &self.1
+    }
+
+    pub fn entity_name(&self) -> &str {
+        &self.2
+    }
+
+    /// The package name, if the package is given as a `#<package-name>` reference
+    pub fn package_name(&self) -> Option<&str> {
+        self.0.strip_prefix('#')
+    }
+
+    pub fn is_package_name_reference(&self) -> bool {
+        self.package_name().is_some()
+    }
+
+    pub fn from_identifier(identifier: &Identifier) -> Self {
+        TemplateId::new(&identifier.package_id, &identifier.module_name, &identifier.entity_name)
+    }
+}
+
+impl fmt::Display for TemplateId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.0, self.1, self.2)
+    }
+}
+
+impl std::str::FromStr for TemplateId {
+    type Err = anyhow::Error;
+
+    /// Parses `<package>:<Module.Name>:<Entity>`, the inverse of `Display`
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let parts: Vec<&str> = s.split(':').collect();
+        let [package, module_name, entity_name] = parts.as_slice() else {
+            anyhow::bail!("Template id '{}' is not of the form <package>:<Module>:<Entity>", s);
+        };
+        if package.is_empty() || *package == "#" {
+            anyhow::bail!("Template id '{}' has an empty package", s);
+        }
+        for name in [module_name, entity_name] {
+            if name.is_empty() || name.split('.').any(|segment| segment.is_empty()) {
+                anyhow::bail!("Template id '{}' has an invalid name '{}'", s, name);
+            }
+        }
+        Ok(TemplateId::new(package, module_name, entity_name))
+    }
+}
+
+/// A Rust type representing the payload of a Daml template.
+/// Usually derived with `#[derive(Template)]`.
+pub trait DamlTemplate {
+    /// The template id, where the package is either a package id or a `#<package-name>` reference
+    fn template_id() -> TemplateId;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_display() {
+        let id: TemplateId = "#daml-asset:Main.Asset:Asset".parse().unwrap();
+        assert_eq!(id, TemplateId::new("#daml-asset", "Main.Asset", "Asset"));
+        assert_eq!(id.package_name(), Some("daml-asset"));
+        assert_eq!(id.to_string(), "#daml-asset:Main.Asset:Asset");
+
+        let id: InterfaceId = "abc123:Interfaces:IAsset".parse().unwrap();
+        assert!(!id.is_package_name_reference());
+        assert_eq!(id.to_string().parse::<TemplateId>().unwrap(), id);
+
+        for invalid in ["", "pkg:Main", "pkg:Main:Asset:Extra", ":Main:Asset", "#:Main:Asset", "pkg:Main.:Asset"] {
+            assert!(invalid.parse::<TemplateId>().is_err(), "{} should not parse", invalid);
+        }
+    }
+}
@@ -0,0 +1,295 @@
+use syn::ext::IdentExt;
+use syn::{Attribute, DeriveInput, Field, LitStr, Path, Type, Variant};
+
+/// Options of a field, given as `#[lapi(...)]` attributes
+#[derive(Default)]
+pub(crate) struct FieldAttrs {
+    /// `#[lapi(label = "...")]`: the Daml record field label, instead of the camelCase field name
+    pub label: Option<String>,
+    /// `#[lapi(skip)]`: client-side only field, not sent to the ledger and set to `Default::default()` when decoding
+    pub skip: bool,
+    /// `#[lapi(default)]`: decoding uses `Default::default()` if the field is missing, e.g. an Optional field
+    /// added by a package upgrade
+    pub default: bool,
+}
+
+/// Options of an enum variant, given as `#[lapi(...)]` attributes
+#[derive(Default)]
+pub(crate) struct VariantAttrs {
+    /// `#[lapi(constructor = "...")]`: the Daml constructor name, instead of the variant name
+    pub constructor: Option<String>,
+    /// `#[lapi(unwrapped)]` on a variant with a single field: the field value is the variant value,
+    /// for Daml constructors taking a single non-record argument
+    pub unwrapped: bool,
+}
+
+/// How field names are turned into Daml labels, `#[lapi(rename_all = "...")]` on the type
+#[derive(Clone, Copy, Default, PartialEq)]
+pub(crate) enum RenameAll {
+    /// `snake_case` field names become `camelCase` labels
+    #[default]
+    CamelCase,
+    /// Field names are used as labels unchanged
+    Preserve,
+}
+
+impl RenameAll {
+    pub fn apply(self, name: &str) -> String {
+        match self {
+            RenameAll::CamelCase => snake_to_camel(name),
+            RenameAll::Preserve => name.to_string(),
+        }
+    }
+}
+
+/// Options of a struct or enum, given as `#[lapi(...)]` attributes on the type
+#[derive(Default)]
+pub(crate) struct ContainerAttrs {
+    /// `#[lapi(rename_all = "camelCase" | "preserve")]`, applied by both `LapiAccess` and `ToCreateArguments`
+    pub rename_all: RenameAll,
+    /// `#[lapi(roundtrip_test)]`: `LapiAccess` also emits a test decoding the encoded `Default` value
+    pub roundtrip_test: bool,
+    /// `#[lapi(omit_trailing_none)]` on a struct: trailing empty `Optional` fields are left out when
+    /// encoding, so records can be sent to ledgers with an older version of the package
+    pub omit_trailing_none: bool,
+}
+
+pub(crate) fn container_attrs(ast: &DeriveInput) -> syn::Result<ContainerAttrs> {
+    let mut result = ContainerAttrs::default();
+    for attr in lapi_attrs(&ast.attrs) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename_all") {
+                let rule: LitStr = meta.value()?.parse()?;
+                result.rename_all = match rule.value().as_str() {
+                    "camelCase" => RenameAll::CamelCase,
+                    "preserve" => RenameAll::Preserve,
+                    _ => {
+                        return Err(syn::Error::new_spanned(
+                            rule,
+                            "unsupported rename_all rule, expected \"camelCase\" or \"preserve\"",
+                        ));
+                    }
+                };
+                Ok(())
+            } else if meta.path.is_ident("roundtrip_test") {
+                result.roundtrip_test = true;
+                Ok(())
+            } else if meta.path.is_ident("omit_trailing_none") {
+                result.omit_trailing_none = true;
+                Ok(())
+            } else {
+                Err(meta.error(
+                    "unsupported lapi attribute, expected `rename_all`, `roundtrip_test` or `omit_trailing_none`",
+                ))
+            }
+        })?;
+    }
+    Ok(result)
+}
+
+fn lapi_attrs(attrs: &[Attribute]) -> impl Iterator<Item = &Attribute> {
+    attrs.iter().filter(|attr| attr.path().is_ident("lapi"))
+}
+
+pub(crate) fn field_attrs(field: &Field) -> syn::Result<FieldAttrs> {
+    let mut result = FieldAttrs::default();
+    for attr in lapi_attrs(&field.attrs) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("label") {
+                let label: LitStr = meta.value()?.parse()?;
+                result.label = Some(label.value());
+                Ok(())
+            } else if meta.path.is_ident("skip") {
+                result.skip = true;
+                Ok(())
+            } else if meta.path.is_ident("default") {
+                result.default = true;
+                Ok(())
+            } else {
+                Err(meta.error("unsupported lapi field attribute, expected `label`, `skip` or `default`"))
+            }
+        })?;
+    }
+    Ok(result)
+}
+
+pub(crate) fn variant_attrs(variant: &Variant) -> syn::Result<VariantAttrs> {
+    let mut result = VariantAttrs::default();
+    for attr in lapi_attrs(&variant.attrs) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("constructor") {
+                let constructor: LitStr = meta.value()?.parse()?;
+                result.constructor = Some(constructor.value());
+                Ok(())
+            } else if meta.path.is_ident("unwrapped") {
+                result.unwrapped = true;
+                Ok(())
+            } else {
+                Err(meta.error("unsupported lapi variant attribute, expected `constructor` or `unwrapped`"))
+            }
+        })?;
+    }
+    Ok(result)
+}
+
+pub(crate) fn snake_to_camel(s: &str) -> String {
+    let mut result = String::new();
+    let mut uppercase_next = false;
+    for c in s.chars() {
+        if c == '_' {
+            uppercase_next = true;
+        } else if uppercase_next {
+            result.push(c.to_ascii_uppercase());
+            uppercase_next = false;
+        } else {
+            result.push(c);
+        }
+    }
+    result
+}
+
+/// The Daml label of a named field: the `label` attribute, or the field name converted by the
+/// `rename_all` rule of the type. Raw identifiers are used without `r#`, `r#type` is labelled `type`.
+pub(crate) fn field_label(field: &Field, rename_all: RenameAll) -> syn::Result<String> {
+    let attrs = field_attrs(field)?;
+    Ok(attrs.label.unwrap_or_else(|| {
+        let ident = field.ident.as_ref().expect("named field");
+        rename_all.apply(&ident.unraw().to_string())
+    }))
+}
+
+/// Whether a field is sent to and read from the ledger, i.e. not marked `#[lapi(skip)]`.
+pub(crate) fn is_ledger_field(field: &Field) -> syn::Result<bool> {
+    Ok(!field_attrs(field)?.skip)
+}
+
+/// The Daml constructor of a variant: the `constructor` attribute, or the variant name without `r#`.
+pub(crate) fn variant_constructor(variant: &Variant) -> syn::Result<String> {
+    let attrs = variant_attrs(variant)?;
+    Ok(attrs.constructor.unwrap_or_else(|| variant.ident.unraw().to_string()))
+}
+
+/// Options of a template or interface type, given as
+/// `#[template(package = "...", module = "...", entity = "...")]` or `#[interface(...)]`
+pub(crate) struct TemplateAttrs {
+    /// Package id, or a `#<package-name>` reference
+    pub package: String,
+    /// Dotted Daml module name
+    pub module: String,
+    /// Daml template or interface name, defaults to the type name
+    pub entity: String,
+}
+
+pub(crate) fn template_attrs(ast: &DeriveInput) -> syn::Result<TemplateAttrs> {
+    qualified_name_attrs(ast, "template")
+}
+
+pub(crate) fn interface_attrs(ast: &DeriveInput) -> syn::Result<TemplateAttrs> {
+    qualified_name_attrs(ast, "interface")
+}
+
+fn qualified_name_attrs(ast: &DeriveInput, attr_name: &str) -> syn::Result<TemplateAttrs> {
+    let mut package = None;
+    let mut module = None;
+    let mut entity = None;
+    for attr in ast.attrs.iter().filter(|attr| attr.path().is_ident(attr_name)) {
+        attr.parse_nested_meta(|meta| {
+            let target = if meta.path.is_ident("package") {
+                &mut package
+            } else if meta.path.is_ident("module") {
+                &mut module
+            } else if meta.path.is_ident("entity") {
+                &mut entity
+            } else {
+                return Err(meta.error(format!(
+                    "unsupported {} attribute, expected `package`, `module` or `entity`",
+                    attr_name
+                )));
+            };
+            let value: LitStr = meta.value()?.parse()?;
+            *target = Some(value.value());
+            Ok(())
+        })?;
+    }
+    let missing = |what: &str| {
+        syn::Error::new_spanned(
+            &ast.ident,
+            format!("missing `{}` in #[{}(package = \"...\", module = \"...\")]", what, attr_name),
+        )
+    };
+    Ok(TemplateAttrs {
+        package: package.ok_or_else(|| missing("package"))?,
+        module: module.ok_or_else(|| missing("module"))?,
+        entity: entity.unwrap_or_else(|| ast.ident.to_string()),
+    })
+}
+
+/// Options of a choice argument type, given as
+/// `#[choice(on = Asset, name = "Give", result = "DamlContractId", nonconsuming)]`
+pub(crate) struct ChoiceAttrs {
+    /// The template type the choice is defined on
+    pub on: Path,
+    /// Daml choice name, defaults to the type name
+    pub name: String,
+    /// Rust type of the choice result, defaults to `DamlUnit`
+    pub result: Type,
+    pub consuming: bool,
+}
+
+pub(crate) fn choice_attrs(ast: &DeriveInput) -> syn::Result<ChoiceAttrs> {
+    let mut on = None;
+    let mut name = None;
+    let mut result = None;
+    let mut consuming = true;
+    for attr in ast.attrs.iter().filter(|attr| attr.path().is_ident("choice")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("on") {
+                on = Some(meta.value()?.parse::<Path>()?);
+            } else if meta.path.is_ident("name") {
+                name = Some(meta.value()?.parse::<LitStr>()?.value());
+            } else if meta.path.is_ident("result") {
+                result = Some(meta.value()?.parse::<LitStr>()?.parse::<Type>()?);
+            } else if meta.path.is_ident("nonconsuming") {
+                consuming = false;
+            } else {
+                return Err(meta.error(
+                    "unsupported choice attribute, expected `on`, `name`, `result` or `nonconsuming`",
+                ));
+            }
+            Ok(())
+        })?;
+    }
+    Ok(ChoiceAttrs {
+        on: on.ok_or_else(|| {
+            syn::Error::new_spanned(&ast.ident, "missing `on` in #[choice(on = Template, ...)]")
+        })?,
+        name: name.unwrap_or_else(|| ast.ident.to_string()),
+        result: result.unwrap_or_else(|| syn::parse_quote!(daml_type_rep::built_in_types::DamlUnit)),
+        consuming,
+    })
+}
+
+/// Options of a contract key type, given as `#[contract_key(template = Asset)]`
+pub(crate) struct ContractKeyAttrs {
+    /// The template type the key belongs to
+    pub template: Path,
+}
+
+pub(crate) fn contract_key_attrs(ast: &DeriveInput) -> syn::Result<ContractKeyAttrs> {
+    let mut template = None;
+    for attr in ast.attrs.iter().filter(|attr| attr.path().is_ident("contract_key")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("template") {
+                template = Some(meta.value()?.parse::<Path>()?);
+                Ok(())
+            } else {
+                Err(meta.error("unsupported contract_key attribute, expected `template`"))
+            }
+        })?;
+    }
+    Ok(ContractKeyAttrs {
+        template: template.ok_or_else(|| {
+            syn::Error::new_spanned(&ast.ident, "missing `template` in #[contract_key(template = Template)]")
+        })?,
+    })
+}
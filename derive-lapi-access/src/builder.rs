@@ -0,0 +1,101 @@
+use proc_macro::TokenStream;
+use quote::{format_ident, quote};
+use syn::{Data, DeriveInput, Fields};
+
+use crate::attrs::field_attrs;
+
+/// Generates `<Name>Builder`, tracking the required fields in its type parameters: each starts
+/// as `()` and becomes `(T,)` once set, and `build()` only exists when all of them are set.
+/// Fields marked `#[lapi(skip)]` or `#[lapi(default)]` are optional and start as `Default::default()`.
+pub(crate) fn impl_builder(ast: &DeriveInput) -> syn::Result<TokenStream> {
+    let name = &ast.ident;
+    let vis = &ast.vis;
+    let builder = format_ident!("{}Builder", name);
+    if !ast.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(&ast.generics, "LapiBuilder does not support generic types"));
+    }
+    let fields = match &ast.data {
+        Data::Struct(data_struct) => match &data_struct.fields {
+            Fields::Named(fields_named) => &fields_named.named,
+            _ => panic!("LapiBuilder only supports named fields"),
+        },
+        _ => panic!("LapiBuilder only supports structs"),
+    };
+
+    let mut required = Vec::new();
+    let mut optional = Vec::new();
+    for field in fields {
+        let attrs = field_attrs(field)?;
+        if attrs.skip || attrs.default {
+            optional.push(field);
+        } else {
+            required.push(field);
+        }
+    }
+    let params: Vec<_> = (0..required.len()).map(|i| format_ident!("__F{}", i)).collect();
+    let required_idents: Vec<_> = required.iter().map(|f| f.ident.as_ref().unwrap()).collect();
+    let required_types: Vec<_> = required.iter().map(|f| &f.ty).collect();
+    let optional_idents: Vec<_> = optional.iter().map(|f| f.ident.as_ref().unwrap()).collect();
+    let optional_types: Vec<_> = optional.iter().map(|f| &f.ty).collect();
+
+    let required_setters = required.iter().enumerate().map(|(i, field)| {
+        let ident = field.ident.as_ref().unwrap();
+        let ty = &field.ty;
+        let other_params: Vec<_> = params.iter().enumerate().filter(|(j, _)| *j != i).map(|(_, p)| p).collect();
+        let before = params.iter().enumerate().map(|(j, p)| if j == i { quote!(()) } else { quote!(#p) });
+        let after = params.iter().enumerate().map(|(j, p)| if j == i { quote!((#ty,)) } else { quote!(#p) });
+        let moved = required_idents.iter().enumerate().map(|(j, other)| {
+            if j == i { quote!(#other: (value.into(),)) } else { quote!(#other: self.#other) }
+        });
+        quote! {
+            impl<#( #other_params ),*> #builder<#( #before ),*> {
+                pub fn #ident(self, value: impl Into<#ty>) -> #builder<#( #after ),*> {
+                    #builder {
+                        #( #moved, )*
+                        #( #optional_idents: self.#optional_idents, )*
+                    }
+                }
+            }
+        }
+    });
+
+    let builder_doc = format!("Builder of [`{0}`], created with `{0}::builder()`", name);
+    let expanded = quote! {
+        #[doc = #builder_doc]
+        #vis struct #builder<#( #params = () ),*> {
+            #( #required_idents: #params, )*
+            #( #optional_idents: #optional_types, )*
+        }
+
+        impl #name {
+            /// Starts building a value, `build()` becomes available once all required fields are set
+            #vis fn builder() -> #builder {
+                #builder {
+                    #( #required_idents: (), )*
+                    #( #optional_idents: ::core::default::Default::default(), )*
+                }
+            }
+        }
+
+        #( #required_setters )*
+
+        impl<#( #params ),*> #builder<#( #params ),*> {
+            #(
+                pub fn #optional_idents(mut self, value: impl Into<#optional_types>) -> Self {
+                    self.#optional_idents = value.into();
+                    self
+                }
+            )*
+        }
+
+        impl #builder<#( (#required_types,) ),*> {
+            pub fn build(self) -> #name {
+                #name {
+                    #( #required_idents: self.#required_idents.0, )*
+                    #( #optional_idents: self.#optional_idents, )*
+                }
+            }
+        }
+    };
+    Ok(expanded.into())
+}
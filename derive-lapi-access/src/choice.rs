@@ -0,0 +1,46 @@
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::DeriveInput;
+
+use crate::attrs::choice_attrs;
+
+pub(crate) fn impl_choice(ast: &DeriveInput) -> syn::Result<TokenStream> {
+    let name = &ast.ident;
+    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+    let attrs = choice_attrs(ast)?;
+    let template = &attrs.on;
+    let choice_name = &attrs.name;
+    let result = &attrs.result;
+    let consuming = attrs.consuming;
+
+    let expanded = quote! {
+        impl #impl_generics #name #ty_generics #where_clause {
+            /// The command exercising this choice on a contract
+            pub fn exercise(self, contract_id: impl Into<String>) -> ledger_api::v2::ExerciseCommand {
+                ledger_api::v2::ExerciseCommand {
+                    template_id: Some(
+                        <#template as daml_type_rep::template_id::DamlTemplate>::template_id().to_template_id(),
+                    ),
+                    contract_id: contract_id.into(),
+                    choice: #choice_name.to_string(),
+                    choice_argument: Some(daml_type_rep::lapi_access::LapiAccess::to_lapi_value(&self)),
+                }
+            }
+
+            /// The exercise command wrapped as a `Command`, ready to be put into `Commands`
+            pub fn into_command(self, contract_id: impl Into<String>) -> ledger_api::v2::Command {
+                ledger_api::v2::Command {
+                    command: Some(ledger_api::v2::command::Command::Exercise(self.exercise(contract_id))),
+                }
+            }
+        }
+
+        impl #impl_generics daml_type_rep::choice::DamlChoice for #name #ty_generics #where_clause {
+            type Template = #template;
+            type Result = #result;
+            const NAME: &'static str = #choice_name;
+            const CONSUMING: bool = #consuming;
+        }
+    };
+    Ok(expanded.into())
+}
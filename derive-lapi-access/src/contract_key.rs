@@ -0,0 +1,22 @@
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::DeriveInput;
+
+use crate::attrs::contract_key_attrs;
+
+pub(crate) fn impl_contract_key(ast: &DeriveInput) -> syn::Result<TokenStream> {
+    let name = &ast.ident;
+    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+    let template = contract_key_attrs(ast)?.template;
+
+    let expanded = quote! {
+        impl #impl_generics daml_type_rep::contract_key::DamlContractKey for #name #ty_generics #where_clause {
+            type Template = #template;
+        }
+
+        impl daml_type_rep::contract_key::DamlKeyedTemplate for #template {
+            type Key = #name;
+        }
+    };
+    Ok(expanded.into())
+}
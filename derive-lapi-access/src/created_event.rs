@@ -0,0 +1,23 @@
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::DeriveInput;
+
+pub(crate) fn impl_from_created_event(ast: &DeriveInput) -> syn::Result<TokenStream> {
+    let name = &ast.ident;
+    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+
+    let expanded = quote! {
+        impl #impl_generics daml_type_rep::contract::FromCreatedEvent for #name #ty_generics #where_clause {
+            fn from_created_event(
+                event: &ledger_api::v2::CreatedEvent,
+            ) -> Option<daml_type_rep::contract::Contract<Self>> {
+                let arguments = ledger_api::v2::Value {
+                    sum: Some(ledger_api::v2::value::Sum::Record(event.create_arguments.clone()?)),
+                };
+                let payload = <Self as daml_type_rep::lapi_access::LapiAccess>::from_lapi_value(&arguments)?;
+                Some(daml_type_rep::contract::Contract::new(event, payload))
+            }
+        }
+    };
+    Ok(expanded.into())
+}
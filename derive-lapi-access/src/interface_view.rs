@@ -0,0 +1,30 @@
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::DeriveInput;
+
+use crate::attrs::interface_attrs;
+
+pub(crate) fn impl_interface_view(ast: &DeriveInput) -> syn::Result<TokenStream> {
+    let name = &ast.ident;
+    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+    let attrs = interface_attrs(ast)?;
+    let package = &attrs.package;
+    let module = &attrs.module;
+    let entity = &attrs.entity;
+
+    let expanded = quote! {
+        impl #impl_generics #name #ty_generics #where_clause {
+            /// The id of the interface this is the view of
+            pub fn interface_id() -> daml_type_rep::template_id::TemplateId {
+                daml_type_rep::template_id::TemplateId::new(#package, #module, #entity)
+            }
+        }
+
+        impl #impl_generics daml_type_rep::interface_view::DamlInterfaceView for #name #ty_generics #where_clause {
+            fn interface_id() -> daml_type_rep::template_id::TemplateId {
+                Self::interface_id()
+            }
+        }
+    };
+    Ok(expanded.into())
+}
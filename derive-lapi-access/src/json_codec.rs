@@ -0,0 +1,198 @@
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{Data, DeriveInput, Fields, FieldsNamed, FieldsUnnamed};
+
+use crate::attrs::{RenameAll, container_attrs, field_attrs, field_label, variant_attrs, variant_constructor};
+use crate::{ledger_fields, unwrapped_field};
+
+/// Expression decoding a named field from the JSON object `obj` in scope.
+/// A missing field decodes like `null`, so omitted Optional fields are `None`.
+fn json_field_decoder(field: &syn::Field, rename_all: RenameAll) -> syn::Result<proc_macro2::TokenStream> {
+    let attrs = field_attrs(field)?;
+    let ty = &field.ty;
+    if attrs.skip {
+        return Ok(quote! { ::core::default::Default::default() });
+    }
+    let label = field_label(field, rename_all)?;
+    if attrs.default {
+        Ok(quote! {
+            match obj.get(#label) {
+                Some(value) => <#ty as daml_type_rep::json_codec::JsonCodec>::from_lf_json(value)?,
+                None => ::core::default::Default::default(),
+            }
+        })
+    } else {
+        Ok(quote! {
+            <#ty as daml_type_rep::json_codec::JsonCodec>::from_lf_json(
+                obj.get(#label).unwrap_or(&daml_type_rep::json_codec::serde_json::Value::Null),
+            )?
+        })
+    }
+}
+
+/// Expression encoding the named fields, bound to their own names, as a JSON object
+fn json_object(fields: &[&syn::Field], rename_all: RenameAll) -> syn::Result<proc_macro2::TokenStream> {
+    let idents: Vec<_> = fields.iter().map(|f| f.ident.as_ref().unwrap()).collect();
+    let labels = fields.iter().map(|f| field_label(f, rename_all)).collect::<syn::Result<Vec<_>>>()?;
+    Ok(quote! {
+        {
+            let mut obj = daml_type_rep::json_codec::serde_json::Map::new();
+            #(
+                obj.insert(#labels.to_string(), daml_type_rep::json_codec::JsonCodec::to_lf_json(#idents));
+            )*
+            daml_type_rep::json_codec::serde_json::Value::Object(obj)
+        }
+    })
+}
+
+pub(crate) fn impl_json_codec(ast: &DeriveInput) -> syn::Result<TokenStream> {
+    let name = &ast.ident;
+    let rename_all = container_attrs(ast)?.rename_all;
+    let (to_body, from_body) = match &ast.data {
+        Data::Struct(data_struct) => {
+            let fields = match &data_struct.fields {
+                Fields::Named(fields_named) => &fields_named.named,
+                _ => panic!("JsonCodec only supports named fields for structs"),
+            };
+            let sent_fields = ledger_fields(fields)?;
+            let sent_idents: Vec<_> = sent_fields.iter().map(|f| f.ident.as_ref().unwrap()).collect();
+            let object = json_object(&sent_fields, rename_all)?;
+            let field_idents: Vec<_> = fields.iter().map(|f| f.ident.as_ref().unwrap()).collect();
+            let field_decoders = fields
+                .iter()
+                .map(|f| json_field_decoder(f, rename_all))
+                .collect::<syn::Result<Vec<_>>>()?;
+            (
+                quote! {
+                    let Self { #( #sent_idents, )* .. } = self;
+                    #object
+                },
+                quote! {
+                    let obj = value.as_object()?;
+                    Some(Self {
+                        #( #field_idents: #field_decoders ),*
+                    })
+                },
+            )
+        }
+        // An enum of unit variants only is a Daml enum, encoded as the constructor name.
+        // Otherwise it is a Daml variant, encoded as `{"tag": constructor, "value": argument}`.
+        Data::Enum(data_enum) => {
+            let is_daml_enum = data_enum.variants.iter().all(|v| matches!(v.fields, Fields::Unit));
+            let mut to_arms = Vec::new();
+            let mut from_arms = Vec::new();
+            for variant in &data_enum.variants {
+                let v_ident = &variant.ident;
+                let constructor = variant_constructor(variant)?;
+                match &variant.fields {
+                    Fields::Unit if is_daml_enum => {
+                        to_arms.push(quote! {
+                            #name::#v_ident => daml_type_rep::json_codec::serde_json::Value::String(#constructor.to_string()),
+                        });
+                        from_arms.push(quote! { #constructor => Some(#name::#v_ident), });
+                    }
+                    Fields::Unit => {
+                        to_arms.push(quote! {
+                            #name::#v_ident => (#constructor, daml_type_rep::json_codec::serde_json::json!({})),
+                        });
+                        from_arms.push(quote! { #constructor => Some(#name::#v_ident), });
+                    }
+                    Fields::Named(FieldsNamed { named, .. }) if variant_attrs(variant)?.unwrapped => {
+                        let field = unwrapped_field(variant, named)?;
+                        let ident = field.ident.as_ref().unwrap();
+                        let ty = &field.ty;
+                        let skipped: Vec<_> = named
+                            .iter()
+                            .filter(|f| f.ident.as_ref() != Some(ident))
+                            .map(|f| f.ident.as_ref().unwrap())
+                            .collect();
+                        to_arms.push(quote! {
+                            #name::#v_ident { #ident, .. } => (#constructor, daml_type_rep::json_codec::JsonCodec::to_lf_json(#ident)),
+                        });
+                        from_arms.push(quote! {
+                            #constructor => Some(#name::#v_ident {
+                                #ident: <#ty as daml_type_rep::json_codec::JsonCodec>::from_lf_json(argument)?,
+                                #( #skipped: ::core::default::Default::default(), )*
+                            }),
+                        });
+                    }
+                    Fields::Named(FieldsNamed { named, .. }) => {
+                        let sent_fields = ledger_fields(named)?;
+                        let sent_idents: Vec<_> = sent_fields.iter().map(|f| f.ident.as_ref().unwrap()).collect();
+                        let object = json_object(&sent_fields, rename_all)?;
+                        let field_idents: Vec<_> = named.iter().map(|f| f.ident.as_ref().unwrap()).collect();
+                        let field_decoders = named
+                            .iter()
+                            .map(|f| json_field_decoder(f, rename_all))
+                            .collect::<syn::Result<Vec<_>>>()?;
+                        to_arms.push(quote! {
+                            #name::#v_ident { #( #sent_idents, )* .. } => (#constructor, #object),
+                        });
+                        from_arms.push(quote! {
+                            #constructor => {
+                                let obj = argument.as_object()?;
+                                Some(#name::#v_ident {
+                                    #( #field_idents: #field_decoders ),*
+                                })
+                            }
+                        });
+                    }
+                    Fields::Unnamed(FieldsUnnamed { unnamed, .. }) if unnamed.len() == 1 => {
+                        let ty = &unnamed[0].ty;
+                        to_arms.push(quote! {
+                            #name::#v_ident(value) => (#constructor, daml_type_rep::json_codec::JsonCodec::to_lf_json(value)),
+                        });
+                        from_arms.push(quote! {
+                            #constructor => Some(#name::#v_ident(
+                                <#ty as daml_type_rep::json_codec::JsonCodec>::from_lf_json(argument)?,
+                            )),
+                        });
+                    }
+                    Fields::Unnamed(FieldsUnnamed { .. }) => {
+                        panic!("JsonCodec only supports tuple variants with a single field")
+                    }
+                }
+            }
+            if is_daml_enum {
+                (
+                    quote! { match self { #( #to_arms )* } },
+                    quote! {
+                        match value.as_str()? {
+                            #( #from_arms )*
+                            _ => None,
+                        }
+                    },
+                )
+            } else {
+                (
+                    quote! {
+                        let (tag, argument) = match self { #( #to_arms )* };
+                        daml_type_rep::json_codec::serde_json::json!({ "tag": tag, "value": argument })
+                    },
+                    quote! {
+                        let obj = value.as_object()?;
+                        let argument = obj.get("value")?;
+                        match obj.get("tag")?.as_str()? {
+                            #( #from_arms )*
+                            _ => None,
+                        }
+                    },
+                )
+            }
+        }
+        _ => panic!("JsonCodec can only be derived for enums or structs with named fields"),
+    };
+
+    let expanded = quote! {
+        impl daml_type_rep::json_codec::JsonCodec for #name {
+            fn to_lf_json(&self) -> daml_type_rep::json_codec::serde_json::Value {
+                #to_body
+            }
+
+            fn from_lf_json(value: &daml_type_rep::json_codec::serde_json::Value) -> Option<Self> {
+                #from_body
+            }
+        }
+    };
+    Ok(expanded.into())
+}
@@ -2,16 +2,124 @@ use proc_macro::TokenStream;
 use quote::quote;
 use syn::{Data, DeriveInput, Fields, FieldsNamed, FieldsUnnamed, parse_macro_input};
 
-#[proc_macro_derive(ToCreateArguments)]
+mod attrs;
+mod builder;
+mod choice;
+mod contract_key;
+mod created_event;
+mod interface;
+mod interface_view;
+mod json_codec;
+mod template;
+
+use attrs::{
+    RenameAll, container_attrs, field_attrs, field_label, is_ledger_field, variant_attrs, variant_constructor,
+};
+
+/// The fields sent to the ledger, i.e. the ones not marked `#[lapi(skip)]`
+fn ledger_fields<'a>(
+    fields: impl IntoIterator<Item = &'a syn::Field>,
+) -> syn::Result<Vec<&'a syn::Field>> {
+    let mut result = Vec::new();
+    for field in fields {
+        if is_ledger_field(field)? {
+            result.push(field);
+        }
+    }
+    Ok(result)
+}
+
+/// The single ledger field of a `#[lapi(unwrapped)]` variant, sent as the variant value itself
+fn unwrapped_field<'a>(
+    variant: &syn::Variant,
+    fields: impl IntoIterator<Item = &'a syn::Field>,
+) -> syn::Result<&'a syn::Field> {
+    match ledger_fields(fields)?.as_slice() {
+        [field] => Ok(field),
+        _ => Err(syn::Error::new_spanned(
+            &variant.ident,
+            "#[lapi(unwrapped)] requires exactly one field which is not skipped",
+        )),
+    }
+}
+
+/// Expression decoding a named field from the ledger API record `rec` in scope, returning a
+/// `LapiDecodeError` located at the field on failure
+fn field_decoder(field: &syn::Field, rename_all: RenameAll) -> syn::Result<proc_macro2::TokenStream> {
+    let attrs = field_attrs(field)?;
+    let ty = &field.ty;
+    if attrs.skip {
+        return Ok(quote! { ::core::default::Default::default() });
+    }
+    let label = field_label(field, rename_all)?;
+    if attrs.default {
+        Ok(quote! {
+            match rec.fields.iter().find(|f| f.label == #label).and_then(|f| f.value.as_ref()) {
+                Some(value) => <#ty as LapiAccess>::try_from_lapi_value(value).map_err(|e| e.in_field(#label))?,
+                None => ::core::default::Default::default(),
+            }
+        })
+    } else {
+        Ok(quote! {
+            {
+                let value = rec
+                    .fields
+                    .iter()
+                    .find(|f| f.label == #label)
+                    .and_then(|f| f.value.as_ref())
+                    .ok_or_else(|| daml_type_rep::decode_error::LapiDecodeError::missing_field(#label))?;
+                <#ty as LapiAccess>::try_from_lapi_value(value).map_err(|e| e.in_field(#label))?
+            }
+        })
+    }
+}
+
+/// Drops the trailing empty `Optional` fields from `fields` for `#[lapi(omit_trailing_none)]`
+fn omit_trailing_none(enabled: bool) -> proc_macro2::TokenStream {
+    if enabled {
+        quote! { daml_type_rep::lapi_access::omit_trailing_none(&mut fields); }
+    } else {
+        proc_macro2::TokenStream::new()
+    }
+}
+
+/// A test module checking that the `Default` value of the type survives encoding and decoding
+fn roundtrip_test(name: &syn::Ident) -> proc_macro2::TokenStream {
+    let module = quote::format_ident!("lapi_roundtrip_test_{}", name.to_string().to_lowercase());
+    quote! {
+        #[cfg(test)]
+        mod #module {
+            use super::*;
+
+            #[test]
+            fn roundtrip() {
+                let value = <#name as ::core::default::Default>::default();
+                let encoded = <#name as LapiAccess>::to_lapi_value(&value);
+                assert_eq!(
+                    <#name as LapiAccess>::from_lapi_value(&encoded),
+                    Some(value),
+                    "{} does not decode its own encoding {:?}",
+                    stringify!(#name),
+                    encoded,
+                );
+            }
+        }
+    }
+}
+
+#[proc_macro_derive(ToCreateArguments, attributes(lapi))]
 pub fn derive_to_create_arguments(input: TokenStream) -> TokenStream {
     let ast = parse_macro_input!(input as DeriveInput);
-    impl_to_create_arguments(&ast)
+    impl_to_create_arguments(&ast).unwrap_or_else(|e| e.to_compile_error().into())
 }
 
 // TODO make module name parameter
 // OR omit?
-fn impl_to_create_arguments(ast: &DeriveInput) -> TokenStream {
+fn impl_to_create_arguments(ast: &DeriveInput) -> syn::Result<TokenStream> {
     let name = &ast.ident;
+    let container = container_attrs(ast)?;
+    let rename_all = container.rename_all;
+    let omit_trailing_none = omit_trailing_none(container.omit_trailing_none);
 
     let fields = match &ast.data {
         Data::Struct(data_struct) => match &data_struct.fields {
@@ -21,14 +129,9 @@ fn impl_to_create_arguments(ast: &DeriveInput) -> TokenStream {
         _ => panic!("ToUpdateInput  only supports structs"),
     };
 
+    let fields = ledger_fields(fields)?;
     let field_names: Vec<_> = fields.iter().map(|f| &f.ident).collect();
-    let field_labels: Vec<_> = fields
-        .iter()
-        .map(|f| {
-            let ident = &f.ident;
-            ident.as_ref().unwrap().to_string()
-        })
-        .collect();
+    let field_labels = fields.iter().map(|f| field_label(f, rename_all)).collect::<syn::Result<Vec<_>>>()?;
 
     let generated = quote! {
         impl ToCreateArguments  for #name {
@@ -37,6 +140,7 @@ fn impl_to_create_arguments(ast: &DeriveInput) -> TokenStream {
                 #(
                     fields.push(self.#field_names.to_lapi_record_field(#field_labels));
                 )*
+                #omit_trailing_none
                 Record {
                     record_id: None,
                     fields,
@@ -44,38 +148,30 @@ fn impl_to_create_arguments(ast: &DeriveInput) -> TokenStream {
             }
         }
     };
-    generated.into()
-}
-
-fn snake_to_camel(s: &str) -> String {
-    let mut result = String::new();
-    let mut uppercase_next = false;
-    for c in s.chars() {
-        if c == '_' {
-            uppercase_next = true;
-        } else if uppercase_next {
-            result.push(c.to_ascii_uppercase());
-            uppercase_next = false;
-        } else {
-            result.push(c);
-        }
-    }
-    result
+    Ok(generated.into())
 }
 
-#[proc_macro_derive(LapiAccess)]
+#[proc_macro_derive(LapiAccess, attributes(lapi))]
 pub fn derive_lapi_access(input: TokenStream) -> TokenStream {
     let ast = parse_macro_input!(input as DeriveInput);
-    impl_lapi_access(&ast)
+    impl_lapi_access(&ast).unwrap_or_else(|e| e.to_compile_error().into())
 }
 
-fn impl_lapi_access(ast: &syn::DeriveInput) -> TokenStream {
+fn impl_lapi_access(ast: &syn::DeriveInput) -> syn::Result<TokenStream> {
     let name = &ast.ident;
+    let container = container_attrs(ast)?;
+    let rename_all = container.rename_all;
+    let roundtrip_test = if container.roundtrip_test {
+        roundtrip_test(name)
+    } else {
+        proc_macro2::TokenStream::new()
+    };
     match &ast.data {
         Data::Enum(data_enum) => {
             let mut match_arms = Vec::new();
             for variant in &data_enum.variants {
                 let v_ident = &variant.ident;
+                let constructor = variant_constructor(variant)?;
                 match &variant.fields {
                     Fields::Unit => {
                         match_arms.push(quote! {
@@ -83,23 +179,37 @@ fn impl_lapi_access(ast: &syn::DeriveInput) -> TokenStream {
                                 ledger_api::v2::Value {
                                     sum: Some(ledger_api::v2::value::Sum::Enum(ledger_api::v2::Enum {
                                         enum_id: None,
-                                        constructor: stringify!(#v_ident).to_string(),
+                                        constructor: #constructor.to_string(),
                                     }))
                                 }
                             }
                         });
                     }
+                    Fields::Named(FieldsNamed { named, .. }) if variant_attrs(variant)?.unwrapped => {
+                        let field_ident = unwrapped_field(variant, named)?.ident.as_ref().unwrap();
+                        match_arms.push(quote! {
+                            #name::#v_ident { #field_ident, .. } => {
+                                ledger_api::v2::Value {
+                                    sum: Some(ledger_api::v2::value::Sum::Variant(Box::new(ledger_api::v2::Variant {
+                                        variant_id: None,
+                                        constructor: #constructor.to_string(),
+                                        value: Some(Box::new(#field_ident.to_lapi_value())),
+                                    })))
+                                }
+                            }
+                        });
+                    }
                     Fields::Named(FieldsNamed { named, .. }) => {
+                        let sent_fields = ledger_fields(named)?;
                         let field_idents: Vec<_> =
-                            named.iter().map(|f| f.ident.as_ref().unwrap()).collect();
-                        let field_labels: Vec<_> =
-                            field_idents.iter().map(|id| snake_to_camel(&id.to_string())).collect();
+                            sent_fields.iter().map(|f| f.ident.as_ref().unwrap()).collect();
+                        let field_labels = sent_fields.iter().map(|f| field_label(f, rename_all)).collect::<syn::Result<Vec<_>>>()?;
                         match_arms.push(quote! {
-                            #name::#v_ident { #( #field_idents ),* } => {
+                            #name::#v_ident { #( #field_idents, )* .. } => {
                                 ledger_api::v2::Value {
                                     sum: Some(ledger_api::v2::value::Sum::Variant(Box::new(ledger_api::v2::Variant {
                                         variant_id: None,
-                                        constructor: stringify!(#v_ident).to_string(),
+                                        constructor: #constructor.to_string(),
                                         value: Some(Box::new(ledger_api::v2::Value {
                                             sum: Some(ledger_api::v2::value::Sum::Record(ledger_api::v2::Record {
                                                 record_id: None,
@@ -118,47 +228,85 @@ fn impl_lapi_access(ast: &syn::DeriveInput) -> TokenStream {
                             }
                         });
                     }
+                    Fields::Unnamed(FieldsUnnamed { unnamed, .. }) if unnamed.len() == 1 => {
+                        match_arms.push(quote! {
+                            #name::#v_ident(value) => {
+                                ledger_api::v2::Value {
+                                    sum: Some(ledger_api::v2::value::Sum::Variant(Box::new(ledger_api::v2::Variant {
+                                        variant_id: None,
+                                        constructor: #constructor.to_string(),
+                                        value: Some(Box::new(value.to_lapi_value())),
+                                    })))
+                                }
+                            }
+                        });
+                    }
                     Fields::Unnamed(FieldsUnnamed { .. }) => {
-                        panic!("LapiAccess does not support tuple variants")
+                        panic!("LapiAccess only supports tuple variants with a single field")
                     }
                 }
             }
             let mut from_match_arms = Vec::new();
             for variant in &data_enum.variants {
                 let v_ident = &variant.ident;
+                let constructor = variant_constructor(variant)?;
                 match &variant.fields {
                     Fields::Unit => {
                         from_match_arms.push(quote! {
-                            (stringify!(#v_ident), None) => Some(#name::#v_ident),
+                            (#constructor, None) => Ok(#name::#v_ident),
+                        });
+                    }
+                    Fields::Named(FieldsNamed { named, .. }) if variant_attrs(variant)?.unwrapped => {
+                        let unwrapped = unwrapped_field(variant, named)?;
+                        let field_ident = unwrapped.ident.as_ref().unwrap();
+                        let ty = &unwrapped.ty;
+                        let skipped_idents: Vec<_> = named
+                            .iter()
+                            .filter(|f| f.ident.as_ref() != Some(field_ident))
+                            .map(|f| f.ident.as_ref().unwrap())
+                            .collect();
+                        from_match_arms.push(quote! {
+                            (#constructor, Some(boxed_val)) => {
+                                Ok(#name::#v_ident {
+                                    #field_ident: <#ty as LapiAccess>::try_from_lapi_value(boxed_val)
+                                        .map_err(|e| e.in_field(#constructor))?,
+                                    #( #skipped_idents: ::core::default::Default::default(), )*
+                                })
+                            },
                         });
                     }
                     Fields::Named(FieldsNamed { named, .. }) => {
                         let field_idents: Vec<_> =
                             named.iter().map(|f| f.ident.as_ref().unwrap()).collect();
-                        let field_types: Vec<_> = named.iter().map(|f| &f.ty).collect();
-                        let field_labels: Vec<_> = field_idents
-                            .iter()
-                            .map(|id| snake_to_camel(&id.to_string()))
-                            .collect::<Vec<_>>();
+                        let field_decoders = named.iter().map(|f| field_decoder(f, rename_all)).collect::<syn::Result<Vec<_>>>()?;
                         from_match_arms.push(quote! {
-                                (stringify!(#v_ident), Some(ref boxed_val)) => {
+                                (#constructor, Some(ref boxed_val)) => {
                                     if let ledger_api::v2::Value { sum: Some(ledger_api::v2::value::Sum::Record(rec)), .. } = &**boxed_val {
-                                        Some(#name::#v_ident {
-                                            #(
-                                                #field_idents: {
-                                                    let field = rec.fields.iter().find(|f| f.label == #field_labels)?;
-                                                    <#field_types as LapiAccess>::from_lapi_value(field.value.as_ref()?)?
-                                                }
-                                            ),*
-                                        })
+                                        let decode = || -> Result<Self, daml_type_rep::decode_error::LapiDecodeError> {
+                                            Ok(#name::#v_ident {
+                                                #( #field_idents: #field_decoders ),*
+                                            })
+                                        };
+                                        decode().map_err(|e| e.in_field(#constructor))
                                     } else {
-                                        None
+                                        Err(daml_type_rep::decode_error::LapiDecodeError::unexpected("Record", boxed_val)
+                                            .in_field(#constructor))
                                     }
                                 },
                             });
                     }
+                    Fields::Unnamed(FieldsUnnamed { unnamed, .. }) if unnamed.len() == 1 => {
+                        let ty = &unnamed[0].ty;
+                        from_match_arms.push(quote! {
+                            (#constructor, Some(boxed_val)) => {
+                                Ok(#name::#v_ident(
+                                    <#ty as LapiAccess>::try_from_lapi_value(boxed_val).map_err(|e| e.in_field(#constructor))?
+                                ))
+                            },
+                        });
+                    }
                     Fields::Unnamed(FieldsUnnamed { .. }) => {
-                        panic!("LapiAccess does not support tuple variants")
+                        panic!("LapiAccess only supports tuple variants with a single field")
                     }
                 }
             }
@@ -171,25 +319,32 @@ fn impl_lapi_access(ast: &syn::DeriveInput) -> TokenStream {
                     }
 
                     fn from_lapi_value(value: &ledger_api::v2::Value) -> Option<Self> {
-                        match value.sum.as_ref()? {
-                            ledger_api::v2::value::Sum::Enum(e) => {
+                        Self::try_from_lapi_value(value).ok()
+                    }
+
+                    fn try_from_lapi_value(
+                        value: &ledger_api::v2::Value,
+                    ) -> Result<Self, daml_type_rep::decode_error::LapiDecodeError> {
+                        use daml_type_rep::decode_error::LapiDecodeError;
+                        match &value.sum {
+                            Some(ledger_api::v2::value::Sum::Enum(e)) => {
                                 match (e.constructor.as_str(), None as Option<&ledger_api::v2::Value>) {
                                     #(#from_match_arms)*
-                                    _ => None
+                                    (constructor, _) => Err(LapiDecodeError::unknown_constructor(stringify!(#name), constructor)),
                                 }
                             },
-                            ledger_api::v2::value::Sum::Variant(var) => {
+                            Some(ledger_api::v2::value::Sum::Variant(var)) => {
                                 match (var.constructor.as_str(), var.value.as_deref()) {
                                     #(#from_match_arms)*
-                                    _ => None
+                                    (constructor, _) => Err(LapiDecodeError::unknown_constructor(stringify!(#name), constructor)),
                                 }
                             },
-                            _ => None
+                            _ => Err(LapiDecodeError::unexpected(stringify!(#name), value)),
                         }
                     }
                 }
             };
-            expanded.into()
+            Ok(quote! { #expanded #roundtrip_test }.into())
         }
         Data::Struct(data_struct) => {
             let fields = match &data_struct.fields {
@@ -197,22 +352,19 @@ fn impl_lapi_access(ast: &syn::DeriveInput) -> TokenStream {
                 _ => panic!("LapiAccess only supports named fields for structs"),
             };
             let field_names: Vec<_> = fields.iter().map(|f| &f.ident).collect();
-            let field_types: Vec<_> = fields.iter().map(|f| &f.ty).collect();
-            let field_labels: Vec<_> = fields
-                .iter()
-                .map(|f| {
-                    let ident = &f.ident;
-                    let snake = ident.as_ref().unwrap().to_string();
-                    snake_to_camel(&snake)
-                })
-                .collect::<Vec<_>>();
+            let field_decoders = fields.iter().map(|f| field_decoder(f, rename_all)).collect::<syn::Result<Vec<_>>>()?;
+            let sent_fields = ledger_fields(fields)?;
+            let sent_field_names: Vec<_> = sent_fields.iter().map(|f| &f.ident).collect();
+            let field_labels = sent_fields.iter().map(|f| field_label(f, rename_all)).collect::<syn::Result<Vec<_>>>()?;
+            let omit_trailing_none = omit_trailing_none(container.omit_trailing_none);
             let expanded = quote! {
                 impl LapiAccess for #name {
                     fn to_lapi_value(&self) -> ledger_api::v2::Value {
                         let mut fields = vec![];
                         #(
-                            fields.push(self.#field_names.to_lapi_record_field(#field_labels));
+                            fields.push(self.#sent_field_names.to_lapi_record_field(#field_labels));
                         )*
+                        #omit_trailing_none
                         ledger_api::v2::Value {
                             sum: Some(ledger_api::v2::value::Sum::Record(Record {
                                 record_id: None,
@@ -222,27 +374,99 @@ fn impl_lapi_access(ast: &syn::DeriveInput) -> TokenStream {
                     }
 
                     fn from_lapi_value(value: &ledger_api::v2::Value) -> Option<Self> {
+                        Self::try_from_lapi_value(value).ok()
+                    }
+
+                    fn try_from_lapi_value(
+                        value: &ledger_api::v2::Value,
+                    ) -> Result<Self, daml_type_rep::decode_error::LapiDecodeError> {
                         if let ledger_api::v2::Value { sum: Some(ledger_api::v2::value::Sum::Record(rec)), .. } = value {
-                            Some(Self {
-                                #(
-                                    #field_names: {
-                                        let field = rec.fields.iter().find(|f| f.label == #field_labels)?;
-                                        <#field_types as LapiAccess>::from_lapi_value(field.value.as_ref()?)?
-                                    }
-                                ),*
+                            Ok(Self {
+                                #( #field_names: #field_decoders ),*
                             })
                         } else {
-                            None
+                            Err(daml_type_rep::decode_error::LapiDecodeError::unexpected(stringify!(#name), value))
                         }
                     }
                 }
             };
-            expanded.into()
+            Ok(quote! { #expanded #roundtrip_test }.into())
         }
         _ => panic!("LapiAccess can only be derived for enums or structs with named fields"),
     }
 }
 
+/// Derives `DamlTemplate` and an associated `template_id()` function from
+/// `#[template(package = "#daml-asset", module = "Main", entity = "Asset")]`.
+/// `package` is a package id or a `#<package-name>` reference, `entity` defaults to the type name.
+#[proc_macro_derive(Template, attributes(template))]
+pub fn derive_template(input: TokenStream) -> TokenStream {
+    let ast = parse_macro_input!(input as DeriveInput);
+    template::impl_template(&ast).unwrap_or_else(|e| e.to_compile_error().into())
+}
+
+/// Derives `DamlChoice` for a choice argument type from
+/// `#[choice(on = Asset, name = "Give", result = "DamlContractId")]`.
+/// `name` defaults to the type name, `result` to `DamlUnit`; add `nonconsuming` for nonconsuming choices.
+#[proc_macro_derive(Choice, attributes(choice))]
+pub fn derive_choice(input: TokenStream) -> TokenStream {
+    let ast = parse_macro_input!(input as DeriveInput);
+    choice::impl_choice(&ast).unwrap_or_else(|e| e.to_compile_error().into())
+}
+
+/// Derives `DamlContractKey` for a contract key type from `#[contract_key(template = Asset)]`,
+/// and `DamlKeyedTemplate` for the template.
+/// The type must also implement `LapiAccess`, which converts the key to and from a `Value`.
+#[proc_macro_derive(ContractKey, attributes(contract_key))]
+pub fn derive_contract_key(input: TokenStream) -> TokenStream {
+    let ast = parse_macro_input!(input as DeriveInput);
+    contract_key::impl_contract_key(&ast).unwrap_or_else(|e| e.to_compile_error().into())
+}
+
+/// Derives a typed builder, `Asset::builder().issuer(p).owner(p).name("x").build()`.
+/// Leaving out a required field is a compile error; `#[lapi(skip)]` and `#[lapi(default)]`
+/// fields are optional and default to `Default::default()`.
+#[proc_macro_derive(LapiBuilder, attributes(lapi))]
+pub fn derive_lapi_builder(input: TokenStream) -> TokenStream {
+    let ast = parse_macro_input!(input as DeriveInput);
+    builder::impl_builder(&ast).unwrap_or_else(|e| e.to_compile_error().into())
+}
+
+/// Derives `DamlInterface` and `DamlTemplate` for a marker type of a Daml interface from
+/// `#[interface(package = "#daml-interface-example-interfaces", module = "Asset", entity = "Asset")]`.
+/// Choices on the interface are derived with `#[choice(on = <marker type>, ...)]`, exercising
+/// them sets the interface id as the template id of the command.
+#[proc_macro_derive(Interface, attributes(interface))]
+pub fn derive_interface(input: TokenStream) -> TokenStream {
+    let ast = parse_macro_input!(input as DeriveInput);
+    interface::impl_interface(&ast).unwrap_or_else(|e| e.to_compile_error().into())
+}
+
+/// Derives `DamlInterfaceView` for an interface view type from
+/// `#[interface(package = "#daml-interface-example-interfaces", module = "Asset", entity = "Asset")]`.
+/// The type must also implement `LapiAccess`, which decodes the view value.
+#[proc_macro_derive(InterfaceView, attributes(interface))]
+pub fn derive_interface_view(input: TokenStream) -> TokenStream {
+    let ast = parse_macro_input!(input as DeriveInput);
+    interface_view::impl_interface_view(&ast).unwrap_or_else(|e| e.to_compile_error().into())
+}
+
+/// Derives `JsonCodec`, the Daml-LF JSON encoding used by the HTTP JSON API. Labels, constructors
+/// and the `lapi` field and variant attributes are the same as for `LapiAccess`.
+#[proc_macro_derive(JsonCodec, attributes(lapi))]
+pub fn derive_json_codec(input: TokenStream) -> TokenStream {
+    let ast = parse_macro_input!(input as DeriveInput);
+    json_codec::impl_json_codec(&ast).unwrap_or_else(|e| e.to_compile_error().into())
+}
+
+/// Derives `FromCreatedEvent`, decoding a whole `CreatedEvent` into a `Contract<Self>`.
+/// The type must also implement `LapiAccess`, which decodes the create arguments.
+#[proc_macro_derive(FromCreatedEvent)]
+pub fn derive_from_created_event(input: TokenStream) -> TokenStream {
+    let ast = parse_macro_input!(input as DeriveInput);
+    created_event::impl_from_created_event(&ast).unwrap_or_else(|e| e.to_compile_error().into())
+}
+
 // Test to understand the difference between field names and labels
 #[cfg(test)]
 #[test]
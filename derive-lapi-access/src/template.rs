@@ -0,0 +1,30 @@
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::DeriveInput;
+
+use crate::attrs::template_attrs;
+
+pub(crate) fn impl_template(ast: &DeriveInput) -> syn::Result<TokenStream> {
+    let name = &ast.ident;
+    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+    let attrs = template_attrs(ast)?;
+    let package = &attrs.package;
+    let module = &attrs.module;
+    let entity = &attrs.entity;
+
+    let expanded = quote! {
+        impl #impl_generics #name #ty_generics #where_clause {
+            /// The template id of this template
+            pub fn template_id() -> daml_type_rep::template_id::TemplateId {
+                daml_type_rep::template_id::TemplateId::new(#package, #module, #entity)
+            }
+        }
+
+        impl #impl_generics daml_type_rep::template_id::DamlTemplate for #name #ty_generics #where_clause {
+            fn template_id() -> daml_type_rep::template_id::TemplateId {
+                Self::template_id()
+            }
+        }
+    };
+    Ok(expanded.into())
+}
@@ -1,9 +1,19 @@
 // In derive-lapi-access/tests/derive_lapi_access.rs
 extern crate derive_lapi_access;
-use daml_type_rep::built_in_types::{DamlInt, DamlParty, DamlText, DamlOptional, DamlList, DamlMap};
-use daml_type_rep::lapi_access::LapiAccess;
-use derive_lapi_access::LapiAccess;
+use daml_type_rep::built_in_types::{DamlContractId, DamlInt, DamlParty, DamlText, DamlOptional, DamlList, DamlMap};
+use daml_type_rep::lapi_access::{LapiAccess, ToCreateArguments};
+use daml_type_rep::template_id::{DamlTemplate, TemplateId};
+use daml_type_rep::choice::DamlChoice;
+use daml_type_rep::contract::FromCreatedEvent;
+use daml_type_rep::contract_key::{DamlContractKey, DamlKeyedTemplate, find_by_key};
+use daml_type_rep::interface_view::{DamlInterface, DamlInterfaceView};
+use daml_type_rep::json_codec::JsonCodec;
+use derive_lapi_access::{
+    Choice, ContractKey, FromCreatedEvent, Interface, InterfaceView, JsonCodec, LapiAccess, LapiBuilder, Template,
+    ToCreateArguments,
+};
 use ledger_api::v2::Record;
+use std::collections::BTreeMap;
 
 #[derive(Debug, PartialEq, LapiAccess)]
 struct MyStruct {
@@ -14,20 +24,140 @@ struct MyStruct {
     map: DamlMap<DamlText, DamlInt>,
 }
 
-#[derive(Debug, PartialEq, LapiAccess)]
+#[derive(Debug, PartialEq, LapiAccess, JsonCodec)]
 pub enum Color {
     Red,
     Green,
     Blue,
 }
 
-#[derive(Debug, PartialEq, LapiAccess)]
+#[derive(Debug, PartialEq, LapiAccess, JsonCodec)]
 pub enum Price {
     USD { amount: DamlInt, color: Color },
     EUR { amount: DamlInt, color: Color },
     GBP,
 }
 
+#[derive(Debug, PartialEq, LapiAccess, ToCreateArguments)]
+struct Renamed {
+    owner_party: DamlParty,
+    #[lapi(label = "desc")]
+    description: DamlText,
+}
+
+#[derive(Debug, PartialEq, LapiAccess, ToCreateArguments)]
+struct Keywords {
+    r#type: DamlText,
+    r#match: DamlInt,
+}
+
+#[derive(Debug, PartialEq, LapiAccess)]
+pub enum Currency {
+    #[lapi(constructor = "USD")]
+    UsDollar,
+    #[lapi(constructor = "EUR")]
+    Euro { cents: DamlInt },
+}
+
+#[derive(Debug, PartialEq, LapiAccess, ToCreateArguments)]
+struct WithClientFields {
+    owner: DamlParty,
+    #[lapi(skip)]
+    local_note: String,
+    #[lapi(default)]
+    added_in_upgrade: DamlOptional<DamlText>,
+}
+
+#[derive(Debug, PartialEq, LapiAccess, ToCreateArguments)]
+#[lapi(omit_trailing_none)]
+struct UpgradedFields {
+    owner: DamlParty,
+    #[lapi(default)]
+    note: DamlOptional<DamlText>,
+    #[lapi(default)]
+    tag: DamlOptional<DamlText>,
+}
+
+#[derive(Debug, PartialEq, LapiAccess, ToCreateArguments, Template, FromCreatedEvent)]
+#[template(package = "#daml-asset", module = "Main")]
+struct Asset {
+    issuer: DamlParty,
+}
+
+#[derive(Debug, PartialEq, LapiAccess, Choice)]
+#[choice(on = Asset, name = "Give", result = "DamlContractId")]
+struct GiveAsset {
+    new_owner: DamlParty,
+}
+
+#[derive(Debug, PartialEq, LapiAccess, Choice)]
+#[choice(on = Asset, nonconsuming)]
+struct Inspect {}
+
+#[derive(Debug, Default, PartialEq, LapiAccess, ToCreateArguments, JsonCodec)]
+#[lapi(roundtrip_test)]
+struct NativeFields {
+    owner: String,
+    active: bool,
+    quantity: i64,
+    note: Option<String>,
+    tags: Vec<String>,
+    limits: BTreeMap<String, i64>,
+}
+
+#[derive(Debug, PartialEq, LapiAccess, ContractKey)]
+#[contract_key(template = Asset)]
+struct AssetKey {
+    issuer: DamlParty,
+}
+
+#[derive(Debug, Default, PartialEq, LapiAccess, ToCreateArguments)]
+#[lapi(rename_all = "preserve", roundtrip_test)]
+struct SnakeCaseLabels {
+    due_date: DamlText,
+    #[lapi(label = "totalAmount")]
+    total_amount: DamlInt,
+}
+
+#[derive(Debug, PartialEq, LapiAccess, JsonCodec)]
+enum Payment {
+    #[lapi(unwrapped)]
+    Cash { amount: DamlInt },
+    Card(DamlText),
+    Transfer { iban: DamlText },
+}
+
+#[derive(Debug, PartialEq, LapiAccess, ToCreateArguments, LapiBuilder)]
+struct Listing {
+    issuer: DamlParty,
+    owner: DamlParty,
+    name: DamlText,
+    #[lapi(default)]
+    description: DamlOptional<DamlText>,
+}
+
+#[derive(Debug, PartialEq, LapiAccess, InterfaceView)]
+#[interface(package = "#daml-interface-example-interfaces", module = "Asset", entity = "Asset")]
+struct AssetView {
+    asset_owner: DamlParty,
+    description: DamlText,
+}
+
+#[derive(Interface)]
+#[interface(package = "#daml-interface-example-interfaces", module = "Asset", entity = "Asset")]
+struct AssetInterface;
+
+#[derive(Debug, PartialEq, LapiAccess, Choice)]
+#[choice(on = AssetInterface, name = "GetView", result = "AssetView", nonconsuming)]
+struct GetView {}
+
+#[derive(Debug, PartialEq, LapiAccess, JsonCodec)]
+struct ChronoFields {
+    due: chrono::NaiveDate,
+    created: chrono::DateTime<chrono::Utc>,
+    marker: (),
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -87,4 +217,371 @@ mod tests {
         dbg!(&deserialized);
         assert_eq!(price, deserialized);
 }
+
+    #[test]
+    fn test_label_and_constructor_renaming() {
+        let s = Renamed {
+            owner_party: DamlParty::new("Alice"),
+            description: DamlText::new("Hello"),
+        };
+        let labels: Vec<String> = s.to_create_arguments().fields.into_iter().map(|f| f.label).collect();
+        assert_eq!(labels, vec!["ownerParty", "desc"]);
+        let deserialized = Renamed::from_lapi_value(&s.to_lapi_value()).expect("Deserialization failed");
+        assert_eq!(s, deserialized);
+
+        let keywords = Keywords { r#type: DamlText::new("cash"), r#match: DamlInt::new(1) };
+        let labels: Vec<String> = keywords.to_create_arguments().fields.into_iter().map(|f| f.label).collect();
+        assert_eq!(labels, vec!["type", "match"]);
+        assert_eq!(Keywords::from_lapi_value(&keywords.to_lapi_value()), Some(keywords));
+
+        for currency in [Currency::UsDollar, Currency::Euro { cents: DamlInt::new(5) }] {
+            let value = currency.to_lapi_value();
+            let constructor = match value.sum.as_ref().unwrap() {
+                ledger_api::v2::value::Sum::Enum(e) => e.constructor.clone(),
+                ledger_api::v2::value::Sum::Variant(v) => v.constructor.clone(),
+                other => panic!("Unexpected value {:?}", other),
+            };
+            assert!(constructor == "USD" || constructor == "EUR");
+            let deserialized = Currency::from_lapi_value(&value).expect("Deserialization failed");
+            assert_eq!(currency, deserialized);
+        }
+    }
+
+    #[test]
+    fn test_skip_and_default_fields() {
+        let s = WithClientFields {
+            owner: DamlParty::new("Alice"),
+            local_note: "not on the ledger".to_string(),
+            added_in_upgrade: DamlOptional::new(Some(DamlText::new("v2"))),
+        };
+        let labels: Vec<String> = s.to_create_arguments().fields.into_iter().map(|f| f.label).collect();
+        assert_eq!(labels, vec!["owner", "addedInUpgrade"]);
+
+        let deserialized = WithClientFields::from_lapi_value(&s.to_lapi_value()).expect("Deserialization failed");
+        assert_eq!(deserialized.local_note, "");
+        assert_eq!(deserialized.added_in_upgrade, s.added_in_upgrade);
+
+        // A record of the package version before the upgrade lacks the Optional field
+        let old_version = ledger_api::v2::Value {
+            sum: Some(ledger_api::v2::value::Sum::Record(Record {
+                record_id: None,
+                fields: vec![DamlParty::new("Alice").to_lapi_record_field("owner")],
+            })),
+        };
+        let deserialized = WithClientFields::from_lapi_value(&old_version).expect("Deserialization failed");
+        assert_eq!(deserialized.added_in_upgrade, DamlOptional::default());
+
+        // Trailing empty Optional fields are left out, so an older package version accepts the record
+        let upgraded = UpgradedFields {
+            owner: DamlParty::new("Alice"),
+            note: DamlOptional::new(Some(DamlText::new("v2"))),
+            tag: DamlOptional::default(),
+        };
+        let labels: Vec<String> = upgraded.to_create_arguments().fields.into_iter().map(|f| f.label).collect();
+        assert_eq!(labels, vec!["owner", "note"]);
+        assert_eq!(UpgradedFields::from_lapi_value(&upgraded.to_lapi_value()), Some(upgraded));
+    }
+
+    #[test]
+    fn test_template_id() {
+        assert_eq!(Asset::template_id(), TemplateId::new("#daml-asset", "Main", "Asset"));
+        assert_eq!(<Asset as DamlTemplate>::template_id().to_template_id().package_id, "#daml-asset");
+    }
+
+    #[test]
+    fn test_choice_metadata() {
+        assert_eq!(GiveAsset::NAME, "Give");
+        assert_eq!(Inspect::NAME, "Inspect");
+        assert_eq!((GiveAsset::CONSUMING, Inspect::CONSUMING), (true, false));
+        assert_eq!(<GiveAsset as DamlChoice>::Template::template_id().entity_name(), "Asset");
+        let result = DamlContractId::new("cid").to_lapi_value();
+        assert!(<GiveAsset as DamlChoice>::Result::from_lapi_value(&result).is_some());
+    }
+
+    #[test]
+    fn test_from_created_event() {
+        let asset = Asset {
+            issuer: DamlParty::new("Alice"),
+        };
+        let event = ledger_api::v2::CreatedEvent {
+            offset: 12,
+            contract_id: "cid-1".to_string(),
+            template_id: Some(Asset::template_id().to_template_id()),
+            create_arguments: Some(asset.to_create_arguments()),
+            signatories: vec!["Alice".to_string()],
+            observers: vec!["Bob".to_string()],
+            created_at: Some(prost_types::Timestamp { seconds: 1_700_000_000, nanos: 0 }),
+            ..Default::default()
+        };
+
+        let contract = Asset::from_created_event(&event).expect("Decoding failed");
+        assert_eq!(contract.contract_id, "cid-1");
+        assert_eq!(contract.template_id, Some(Asset::template_id()));
+        assert_eq!(contract.payload, asset);
+        assert_eq!(contract.signatories, vec!["Alice"]);
+        assert_eq!(contract.observers, vec!["Bob"]);
+        assert_eq!(contract.created_at.map(|t| t.timestamp()), Some(1_700_000_000));
+        assert_eq!(contract.offset, 12);
+
+        let no_arguments = ledger_api::v2::CreatedEvent { create_arguments: None, ..event };
+        assert!(Asset::from_created_event(&no_arguments).is_none());
+    }
+
+    #[test]
+    fn test_native_type_fields() {
+        let s = NativeFields {
+            owner: "Alice".to_string(),
+            active: true,
+            quantity: 3,
+            note: None,
+            tags: vec!["a".to_string(), "b".to_string()],
+            limits: BTreeMap::from([("daily".to_string(), 100)]),
+        };
+        let value = s.to_lapi_value();
+        let deserialized = NativeFields::from_lapi_value(&value).expect("Deserialization failed");
+        assert_eq!(s, deserialized);
+
+        let labels: Vec<String> = s.to_create_arguments().fields.into_iter().map(|f| f.label).collect();
+        assert_eq!(labels, vec!["owner", "active", "quantity", "note", "tags", "limits"]);
+
+        // A TextMap decodes into a BTreeMap with String keys as well
+        let text_map = ledger_api::v2::Value {
+            sum: Some(ledger_api::v2::value::Sum::TextMap(ledger_api::v2::TextMap {
+                entries: vec![ledger_api::v2::text_map::Entry {
+                    key: "daily".to_string(),
+                    value: Some(100i64.to_lapi_value()),
+                }],
+            })),
+        };
+        assert_eq!(BTreeMap::<String, i64>::from_lapi_value(&text_map), Some(s.limits));
+    }
+
+    #[test]
+    fn test_contract_key() {
+        let key = AssetKey { issuer: DamlParty::new("Alice") };
+        let created = |contract_id: &str, issuer: &str, entity_name: &str| ledger_api::v2::CreatedEvent {
+            contract_id: contract_id.to_string(),
+            template_id: Some(TemplateId::new("pkg", "Main", entity_name).to_template_id()),
+            contract_key: Some(AssetKey { issuer: DamlParty::new(issuer) }.to_key_value()),
+            ..Default::default()
+        };
+        let events = vec![
+            created("cid-1", "Bob", "Asset"),
+            created("cid-2", "Alice", "Other"),
+            created("cid-3", "Alice", "Asset"),
+        ];
+
+        assert_eq!(AssetKey::from_created_event(&events[0]), Some(AssetKey { issuer: DamlParty::new("Bob") }));
+        assert_eq!(AssetKey::from_created_event(&events[1]), None);
+        assert_eq!(find_by_key(&events, &key).map(|e| e.contract_id.as_str()), Some("cid-3"));
+
+        let prefetch = <Asset as DamlKeyedTemplate>::Key::to_prefetch_contract_key(&key);
+        assert_eq!(prefetch.template_id.map(|id| id.entity_name), Some("Asset".to_string()));
+        assert_eq!(prefetch.contract_key, Some(key.to_key_value()));
+    }
+
+    #[test]
+    fn test_rename_all_preserve() {
+        let s = SnakeCaseLabels {
+            due_date: DamlText::new("2025-01-01"),
+            total_amount: DamlInt::new(10),
+        };
+        let labels: Vec<String> = s.to_create_arguments().fields.into_iter().map(|f| f.label).collect();
+        assert_eq!(labels, vec!["due_date", "totalAmount"]);
+        let value = s.to_lapi_value();
+        let Some(ledger_api::v2::value::Sum::Record(record)) = &value.sum else {
+            panic!("Expected a record");
+        };
+        assert_eq!(record.fields[0].label, "due_date");
+        assert_eq!(SnakeCaseLabels::from_lapi_value(&value), Some(s));
+    }
+
+    #[test]
+    fn test_single_value_variants() {
+        let variant_value = |payment: &Payment| match payment.to_lapi_value().sum {
+            Some(ledger_api::v2::value::Sum::Variant(v)) => v.value.unwrap().sum.unwrap(),
+            _ => panic!("Expected a variant"),
+        };
+
+        let cash = Payment::Cash { amount: DamlInt::new(5) };
+        assert_eq!(variant_value(&cash), ledger_api::v2::value::Sum::Int64(5));
+        let card = Payment::Card(DamlText::new("1234"));
+        assert_eq!(variant_value(&card), ledger_api::v2::value::Sum::Text("1234".to_string()));
+        let transfer = Payment::Transfer { iban: DamlText::new("HU12") };
+        assert!(matches!(variant_value(&transfer), ledger_api::v2::value::Sum::Record(_)));
+
+        for payment in [cash, card, transfer] {
+            assert_eq!(Payment::from_lapi_value(&payment.to_lapi_value()), Some(payment));
+        }
+    }
+
+    #[test]
+    fn test_builder() {
+        // Required fields can be set in any order, optional ones can be left out
+        let listing = Listing::builder().name("TV").owner("Bob").issuer("Alice").build();
+        assert_eq!(
+            listing,
+            Listing {
+                issuer: DamlParty::new("Alice"),
+                owner: DamlParty::new("Bob"),
+                name: DamlText::new("TV"),
+                description: DamlOptional::new(None),
+            }
+        );
+
+        let described = Listing::builder()
+            .issuer("Alice")
+            .description(DamlOptional::new(Some(DamlText::new("large"))))
+            .owner("Bob")
+            .name("TV")
+            .build();
+        assert_eq!(described.description, DamlOptional::new(Some(DamlText::new("large"))));
+    }
+
+    #[test]
+    fn test_interface_view() {
+        let view = AssetView {
+            asset_owner: DamlParty::new("Bob"),
+            description: DamlText::new("IOU (C1)"),
+        };
+        let Some(ledger_api::v2::value::Sum::Record(view_value)) = view.to_lapi_value().sum else {
+            panic!("Expected a record");
+        };
+        let interface_view = |module_name: &str| ledger_api::v2::InterfaceView {
+            interface_id: Some(TemplateId::new("pkg", module_name, "Asset").to_template_id()),
+            view_status: None,
+            view_value: Some(view_value.clone()),
+        };
+        let event = ledger_api::v2::CreatedEvent {
+            interface_views: vec![interface_view("Other"), interface_view("Asset")],
+            ..Default::default()
+        };
+
+        assert_eq!(AssetView::interface_id().module_name(), "Asset");
+        assert_eq!(AssetView::from_interface_view(&interface_view("Other")), None);
+        assert_eq!(<AssetView as DamlInterfaceView>::from_created_event(&event), Some(view));
+
+        // Interface choices are exercised with the interface id as template id
+        assert_eq!(<AssetInterface as DamlInterface>::interface_id(), AssetView::interface_id());
+        let command = GetView {}.exercise("cid");
+        assert_eq!(command.template_id, Some(AssetInterface::interface_id().to_template_id()));
+        assert_eq!(command.choice, "GetView");
+    }
+
+    #[test]
+    fn test_lf_json_codec() {
+        use daml_type_rep::json_codec::serde_json::json;
+
+        let native = NativeFields {
+            owner: "Alice".to_string(),
+            active: true,
+            quantity: 3,
+            note: None,
+            tags: vec!["a".to_string()],
+            limits: BTreeMap::from([("daily".to_string(), 100)]),
+        };
+        let encoded = native.to_lf_json();
+        assert_eq!(
+            encoded,
+            json!({
+                "owner": "Alice",
+                "active": true,
+                "quantity": "3",
+                "note": null,
+                "tags": ["a"],
+                "limits": [["daily", "100"]],
+            })
+        );
+        assert_eq!(NativeFields::from_lf_json(&encoded), Some(native));
+
+        let price = Price::USD { amount: DamlInt::new(5), color: Color::Red };
+        assert_eq!(price.to_lf_json(), json!({"tag": "USD", "value": {"amount": "5", "color": "Red"}}));
+        assert_eq!(Price::from_lf_json(&price.to_lf_json()), Some(price));
+        assert_eq!(Price::GBP.to_lf_json(), json!({"tag": "GBP", "value": {}}));
+        assert_eq!(Payment::Card(DamlText::new("1234")).to_lf_json(), json!({"tag": "Card", "value": "1234"}));
+        assert_eq!(
+            Payment::from_lf_json(&json!({"tag": "Cash", "value": "7"})),
+            Some(Payment::Cash { amount: DamlInt::new(7) })
+        );
+
+        // Omitted Optional fields decode as None
+        let without_note = json!({"owner": "Alice", "active": false, "quantity": "0", "tags": [], "limits": []});
+        let expected = NativeFields {
+            owner: "Alice".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(NativeFields::from_lf_json(&without_note), Some(expected));
+    }
+
+    #[test]
+    fn test_choice_exercise_helpers() {
+        let give = || GiveAsset { new_owner: DamlParty::new("Bob") };
+        let command = give().exercise("cid-1");
+        assert_eq!(command.template_id, Some(Asset::template_id().to_template_id()));
+        assert_eq!(command.contract_id, "cid-1");
+        assert_eq!(command.choice, "Give");
+        assert_eq!(command.choice_argument, Some(give().to_lapi_value()));
+
+        let wrapped = give().into_command("cid-1".to_string());
+        assert_eq!(wrapped.command, Some(ledger_api::v2::command::Command::Exercise(command)));
+    }
+
+    #[test]
+    fn test_chrono_and_unit_fields() {
+        let s = ChronoFields {
+            due: chrono::NaiveDate::from_ymd_opt(1970, 1, 11).unwrap(),
+            created: chrono::DateTime::from_timestamp_micros(1_700_000_000_123_456).unwrap(),
+            marker: (),
+        };
+        let value = s.to_lapi_value();
+        let Some(ledger_api::v2::value::Sum::Record(record)) = &value.sum else {
+            panic!("Expected a record");
+        };
+        // Dates are days since the Unix epoch
+        assert_eq!(record.fields[0].value.as_ref().unwrap().sum, Some(ledger_api::v2::value::Sum::Date(10)));
+        assert_eq!(ChronoFields::from_lapi_value(&value), Some(s));
+
+        let s = ChronoFields::from_lapi_value(&value).unwrap();
+        assert_eq!(ChronoFields::from_lf_json(&s.to_lf_json()), Some(s));
+    }
+
+    #[test]
+    fn test_decode_error_path() {
+        use ledger_api::v2::value::Sum;
+        let text = || ledger_api::v2::Value { sum: Some(Sum::Text("ten".to_string())) };
+
+        let mut value = Price::USD { amount: DamlInt::new(10), color: Color::Red }.to_lapi_value();
+        if let Some(Sum::Variant(variant)) = &mut value.sum
+            && let Some(Sum::Record(record)) = &mut variant.value.as_mut().unwrap().sum
+        {
+            record.fields[0].value = Some(text());
+        }
+        let error = Price::try_from_lapi_value(&value).unwrap_err();
+        assert_eq!(error.to_string(), "USD.amount: expected DamlInt, found Text");
+        assert_eq!(Price::from_lapi_value(&value), None);
+
+        let s = MyStruct {
+            party: DamlParty::new("Alice"),
+            text: DamlText::new("text"),
+            optional: DamlOptional::new(None),
+            list: DamlList::new(vec![DamlInt::new(1), DamlInt::new(2)]),
+            map: DamlMap::new(BTreeMap::new()),
+        };
+        let mut value = s.to_lapi_value();
+        let Some(Sum::Record(record)) = &mut value.sum else {
+            panic!("Expected a record");
+        };
+        record.fields.remove(0);
+        let error = MyStruct::try_from_lapi_value(&value).unwrap_err();
+        assert_eq!(error.to_string(), "party: missing field");
+
+        let mut value = s.to_lapi_value();
+        if let Some(Sum::Record(record)) = &mut value.sum
+            && let Some(Sum::List(list)) = &mut record.fields[3].value.as_mut().unwrap().sum
+        {
+            list.elements[1] = text();
+        }
+        let error = MyStruct::try_from_lapi_value(&value).unwrap_err();
+        assert_eq!(error.to_string(), "list[1]: expected DamlInt, found Text");
+    }
 }
@@ -1,11 +1,16 @@
 use anyhow::{Context, Result};
-use serde::Deserialize;
+use crate::events::EventType;
+use client::utils::{event_format_for_any_party, event_format_for_parties};
+use ledger_api::v2::cumulative_filter::IdentifierFilter;
+use ledger_api::v2::{EventFormat, Identifier};
+use serde::{Deserialize, Deserializer};
 use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
 
 /// Top-level config file structure with profile support
 #[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct ConfigFile {
     pub logging: LoggingConfig,
     pub neo4j: Neo4jConfig,
@@ -13,10 +18,42 @@ pub struct ConfigFile {
     pub active_profile: String,
     /// Named profiles containing ledger and keycloak settings
     pub profiles: HashMap<String, ProfileConfig>,
+    /// Path of the registry.json written by `codegen --registry` for the DARs of the ledger,
+    /// to store payloads by the schema of their template
+    #[serde(default)]
+    pub template_registry: Option<String>,
+    #[serde(default)]
+    pub metrics: MetricsConfig,
+    #[serde(default)]
+    pub party_metadata: PartyMetadataConfig,
+    #[serde(default)]
+    pub package_catalog: PackageCatalogConfig,
+    /// Kafka sink of the `publish` command
+    #[serde(default)]
+    pub kafka: Option<KafkaConfig>,
+    /// CSV file sink of the `export` command
+    #[serde(default)]
+    pub export: Option<ExportConfig>,
+    /// Query API of the `serve` command
+    #[serde(default)]
+    pub serve: ServeConfig,
+    /// Labels and promoted payload fields of the Created nodes, by template name
+    #[serde(default)]
+    pub node_mapping: HashMap<String, NodeMapping>,
+    /// Profiles whose ledgers `sync` writes into the graph together, each with its own offsets,
+    /// instead of the ledger of the active profile
+    #[serde(default)]
+    pub sources: Vec<String>,
+    /// Notifications of the events of the sync matching the rules
+    #[serde(default)]
+    pub webhooks: Vec<WebhookRule>,
+    #[serde(default)]
+    pub opentelemetry: OpenTelemetryConfig,
 }
 
 /// A named profile containing environment-specific settings
 #[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct ProfileConfig {
     pub ledger: LedgerConfig,
     pub keycloak: Option<KeycloakConfig>,
@@ -29,6 +66,27 @@ pub struct Config {
     pub neo4j: Neo4jConfig,
     pub ledger: LedgerConfig,
     pub keycloak: Option<KeycloakConfig>,
+    pub template_registry: Option<String>,
+    pub metrics: MetricsConfig,
+    pub party_metadata: PartyMetadataConfig,
+    pub package_catalog: PackageCatalogConfig,
+    pub kafka: Option<KafkaConfig>,
+    pub export: Option<ExportConfig>,
+    pub serve: ServeConfig,
+    pub node_mapping: HashMap<String, NodeMapping>,
+    /// The ledgers of the `sources` profiles, empty to sync the ledger of the profile
+    pub sources: Vec<Source>,
+    pub webhooks: Vec<WebhookRule>,
+    pub opentelemetry: OpenTelemetryConfig,
+}
+
+/// A ledger synced into a graph shared by several ledgers, its nodes tagged with the name of its
+/// profile as `ledger_id`
+#[derive(Debug, Clone)]
+pub struct Source {
+    pub ledger_id: String,
+    pub ledger: LedgerConfig,
+    pub keycloak: Option<KeycloakConfig>,
 }
 
 /// Authentication method for Keycloak
@@ -58,6 +116,7 @@ pub struct KeycloakConfig {
 }
 
 #[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct LoggingConfig {
     #[serde(default = "default_log_level")]
     pub level: String,
@@ -68,10 +127,19 @@ fn default_log_level() -> String {
 }
 
 #[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct Neo4jConfig {
     pub uri: String,
     pub user: String,
     pub password: String,
+    /// Database of the graph on Neo4j 4+ and Aura with several databases, the default database
+    /// of the user if not set
+    #[serde(default)]
+    pub database: Option<String>,
+    /// Scopes the nodes synced to this name like the ledgers of `sources`, so that several
+    /// environments share a database
+    #[serde(default)]
+    pub namespace: Option<String>,
     /// Number of updates to batch before committing to Neo4j
     #[serde(default = "default_batch_size")]
     pub batch_size: usize,
@@ -83,6 +151,34 @@ pub struct Neo4jConfig {
     /// so a live stream is never silent for long.
     #[serde(default = "default_idle_timeout")]
     pub idle_timeout_secs: u64,
+    /// Number of batches committed to Neo4j concurrently, 1 commits them one after the other
+    #[serde(default = "default_writer_concurrency")]
+    pub writer_concurrency: usize,
+    /// Whether to request the created event blobs of the contracts and store them on their
+    /// Created nodes, for explicit disclosure
+    #[serde(default)]
+    pub created_event_blobs: bool,
+    /// Number of batches submitted to the writers and not yet committed beyond which the stream
+    /// and the ACS loading wait, twice writer_concurrency by default
+    #[serde(default)]
+    pub max_in_flight_batches: Option<usize>,
+    /// Seconds the oldest batch not yet committed may wait before the stream and the ACS loading
+    /// wait for it to be committed
+    #[serde(default)]
+    pub lag_target_secs: Option<u64>,
+}
+
+impl Neo4jConfig {
+    /// The `ledger_id` the nodes of the source are scoped to: the namespace, the ledger id of
+    /// the source in the namespace as `<namespace>/<ledger id>`, or `None` for a graph of a
+    /// single ledger without namespace
+    pub fn ledger_id(&self, source: Option<&str>) -> Option<String> {
+        match (&self.namespace, source) {
+            (Some(namespace), Some(source)) => Some(format!("{}/{}", namespace, source)),
+            (Some(namespace), None) => Some(namespace.clone()),
+            (None, source) => source.map(str::to_string),
+        }
+    }
 }
 
 fn default_batch_size() -> usize {
@@ -97,10 +193,327 @@ fn default_idle_timeout() -> u64 {
     60
 }
 
+fn default_writer_concurrency() -> usize {
+    1
+}
+
+/// The Prometheus metrics endpoint of the sync daemon, see `metrics`
+#[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct MetricsConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Address the `/metrics` endpoint listens on
+    #[serde(default = "default_metrics_listen_address")]
+    pub listen_address: String,
+}
+
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        Self { enabled: false, listen_address: default_metrics_listen_address() }
+    }
+}
+
+fn default_metrics_listen_address() -> String {
+    "0.0.0.0:9464".to_string()
+}
+
+/// The export of the spans of the sync to an OpenTelemetry collector, see `telemetry`
+#[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct OpenTelemetryConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Base URL of the OTLP/HTTP endpoint of the collector, the spans are posted to `/v1/traces`
+    #[serde(default = "default_otlp_endpoint")]
+    pub endpoint: String,
+    /// The `service.name` of the spans
+    #[serde(default = "default_service_name")]
+    pub service_name: String,
+}
+
+impl Default for OpenTelemetryConfig {
+    fn default() -> Self {
+        Self { enabled: false, endpoint: default_otlp_endpoint(), service_name: default_service_name() }
+    }
+}
+
+fn default_otlp_endpoint() -> String {
+    "http://localhost:4318".to_string()
+}
+
+fn default_service_name() -> String {
+    "ledger-explorer".to_string()
+}
+
+/// Refreshing the metadata of the Party nodes during the sync, see `parties`
+#[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct PartyMetadataConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_party_refresh_interval")]
+    pub refresh_interval_secs: u64,
+}
+
+impl Default for PartyMetadataConfig {
+    fn default() -> Self {
+        Self { enabled: false, refresh_interval_secs: default_party_refresh_interval() }
+    }
+}
+
+fn default_party_refresh_interval() -> u64 {
+    300
+}
+
+/// Refreshing the Package nodes of the packages known to the participant during the sync, see
+/// `packages`
+#[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct PackageCatalogConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_package_refresh_interval")]
+    pub refresh_interval_secs: u64,
+}
+
+impl Default for PackageCatalogConfig {
+    fn default() -> Self {
+        Self { enabled: false, refresh_interval_secs: default_package_refresh_interval() }
+    }
+}
+
+fn default_package_refresh_interval() -> u64 {
+    3600
+}
+
+/// The read-only HTTP API of the `serve` command, see `serve`
+#[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ServeConfig {
+    #[serde(default = "default_serve_listen_address")]
+    pub listen_address: String,
+    /// Maximum number of rows of a response, the cap of the `limit` parameter
+    #[serde(default = "default_serve_max_rows")]
+    pub max_rows: i64,
+}
+
+impl Default for ServeConfig {
+    fn default() -> Self {
+        Self { listen_address: default_serve_listen_address(), max_rows: default_serve_max_rows() }
+    }
+}
+
+fn default_serve_listen_address() -> String {
+    "127.0.0.1:8080".to_string()
+}
+
+fn default_serve_max_rows() -> i64 {
+    1000
+}
+
+/// The label and the payload fields stored as properties of the Created nodes of a template,
+/// beyond the `Created` label and the payload JSON strings, e.g. for
+/// `[node_mapping."Splice.Amulet.Amulet"]`:
+/// `label = "Amulet"` and `properties = ["owner", "amount.initialAmount"]`
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct NodeMapping {
+    /// Label added to the Created nodes of the template
+    pub label: Option<String>,
+    /// Paths of the payload fields, stored as the property named by the path with `_` for `.`,
+    /// e.g. `amount_initialAmount`
+    #[serde(default)]
+    pub properties: Vec<String>,
+}
+
+impl NodeMapping {
+    /// The paths of the promoted payload fields with their property names
+    pub fn properties(&self) -> impl Iterator<Item = (&str, String)> {
+        self.properties.iter().map(|path| (path.as_str(), path.replace('.', "_")))
+    }
+}
+
+/// A notification of the events of the sync matching all of the matchers given: the event POSTed
+/// as JSON to `url`, or written to the standard input of `command`, e.g. for `[[webhooks]]`:
+/// `name = "transfers"`, `templates = ["Main.Asset"]`, `choices = ["Transfer"]` and
+/// `url = "https://hooks.example.com/transfers"`
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct WebhookRule {
+    /// Name of the rule in the notifications and logs
+    pub name: String,
+    /// Template names (`Module.Entity`) of the events, any if empty
+    #[serde(default)]
+    pub templates: Vec<String>,
+    /// Choices of the exercised events, any event if empty
+    #[serde(default)]
+    pub choices: Vec<String>,
+    /// Types of the events, any if empty
+    #[serde(default)]
+    pub event_types: Vec<EventType>,
+    /// Parties of which one must be a signatory, acting party or witness of the event, any if empty
+    #[serde(default)]
+    pub parties: Vec<String>,
+    pub url: Option<String>,
+    /// Program and arguments
+    pub command: Option<Vec<String>>,
+}
+
+/// Checks that each webhook rule has either a url or a command
+fn validate_webhooks(webhooks: &[WebhookRule]) -> Result<()> {
+    for rule in webhooks {
+        match (&rule.url, &rule.command) {
+            (Some(_), None) => {}
+            (None, Some(command)) if !command.is_empty() => {}
+            _ => anyhow::bail!("webhook '{}' needs either a url or a command", rule.name),
+        }
+    }
+    Ok(())
+}
+
+/// Labels of the nodes written by the sync, which are not mapped labels
+const NODE_LABELS: [&str; 9] =
+    ["Created", "Exercised", "Transaction", "Party", "Key", "Interface", "Template", "Package", "SyncState"];
+
+/// Properties of the Created nodes written by the sync, which promoted fields do not overwrite
+const CREATED_PROPERTIES: [&str; 13] = [
+    "contract_id",
+    "template_name",
+    "label",
+    "signatories",
+    "offset",
+    "node_id",
+    "created_at",
+    "create_arguments",
+    "create_arguments_json",
+    "interface_views",
+    "active",
+    "archived_at_offset",
+    "from_acs",
+];
+
+/// Checks that the labels and property names are identifiers, usable in Cypher without quoting,
+/// and distinct from the ones of the sync
+fn validate_node_mapping(node_mapping: &HashMap<String, NodeMapping>) -> Result<()> {
+    let is_identifier = |name: &str| {
+        name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    };
+    for (template_name, mapping) in node_mapping {
+        if let Some(label) = &mapping.label
+            && (!is_identifier(label) || NODE_LABELS.contains(&label.as_str()))
+        {
+            anyhow::bail!("invalid label '{}' in node_mapping of '{}'", label, template_name);
+        }
+        for (path, name) in mapping.properties() {
+            if !is_identifier(&name) || CREATED_PROPERTIES.contains(&name.as_str()) {
+                anyhow::bail!("invalid property '{}' in node_mapping of '{}'", path, template_name);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Publishing events to Kafka with the `publish` command, see `kafka`
+#[derive(Debug, Deserialize, Clone)]
+pub struct KafkaConfig {
+    /// `host:port` of brokers to get the cluster metadata from
+    pub bootstrap_servers: Vec<String>,
+    /// Topic of the events of the types without topic in `topics`
+    pub topic: String,
+    #[serde(default)]
+    pub topics: KafkaTopics,
+    #[serde(default)]
+    pub key: KafkaKey,
+    #[serde(default = "default_kafka_client_id")]
+    pub client_id: String,
+    /// File storing the offset of the last update published
+    #[serde(default = "default_kafka_checkpoint_file")]
+    pub checkpoint_file: String,
+    #[serde(flatten)]
+    pub batch: SinkBatchConfig,
+}
+
+/// Writing the events of the ledger to CSV files with the `export` command, see `export`
+#[derive(Debug, Deserialize, Clone)]
+pub struct ExportConfig {
+    /// Directory of the `date=<YYYY-MM-DD>` partitions
+    pub directory: String,
+    /// File storing the offset of the last update written, `export.offset` in the directory by default
+    pub checkpoint_file: Option<String>,
+    #[serde(flatten)]
+    pub batch: SinkBatchConfig,
+}
+
+/// Batching of the updates written by the sinks other than Neo4j, like in the `[neo4j]` section
 #[derive(Debug, Deserialize, Clone)]
+pub struct SinkBatchConfig {
+    /// Number of updates to batch before writing them
+    #[serde(default = "default_batch_size")]
+    pub batch_size: usize,
+    /// Flush timeout in seconds - write even if batch isn't full after this duration
+    #[serde(default = "default_flush_timeout")]
+    pub flush_timeout_secs: u64,
+    /// Idle timeout in seconds - reconnect if no updates received for this duration
+    #[serde(default = "default_idle_timeout")]
+    pub idle_timeout_secs: u64,
+}
+
+/// Topics of the events of each type, overriding `KafkaConfig::topic`
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct KafkaTopics {
+    pub created: Option<String>,
+    pub exercised: Option<String>,
+    pub archived: Option<String>,
+}
+
+/// The key of the Kafka records of events: by contract id, the events of a contract stay in order
+/// in a partition, by offset, the events of a transaction
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum KafkaKey {
+    #[default]
+    ContractId,
+    Offset,
+}
+
+impl KafkaConfig {
+    /// The topic of the events of the type
+    pub fn topic(&self, event_type: EventType) -> &str {
+        let topic = match event_type {
+            EventType::Created => &self.topics.created,
+            EventType::Exercised => &self.topics.exercised,
+            EventType::Archived => &self.topics.archived,
+        };
+        topic.as_deref().unwrap_or(&self.topic)
+    }
+
+    pub fn all_topics(&self) -> Vec<&str> {
+        let mut topics: Vec<&str> =
+            [EventType::Created, EventType::Exercised, EventType::Archived].map(|event_type| self.topic(event_type)).to_vec();
+        topics.dedup();
+        topics
+    }
+}
+
+fn default_kafka_client_id() -> String {
+    "ledger-explorer".to_string()
+}
+
+fn default_kafka_checkpoint_file() -> String {
+    "kafka.offset".to_string()
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct LedgerConfig {
     pub fake_jwt_user: String,
-    pub parties: Option<Vec<String>>,
+    pub parties: Option<Parties>,
+    /// Interfaces whose views are stored on the Created nodes of contracts implementing them,
+    /// as `<package>:<Module.Name>:<Entity>`, e.g. `#my-package:Main:Asset`
+    pub interfaces: Option<Vec<String>>,
     pub url: String,
     /// Starting offset for sync when Neo4j has no data.
     /// Positive value: absolute offset. Negative value: relative to ledger end (e.g., -5000000).
@@ -108,12 +521,137 @@ pub struct LedgerConfig {
     pub starting_offset: Option<i64>,
 }
 
-/// Read and parse the config file
+/// The parties to subscribe to: `parties = ["Alice::1220..."]`, or `parties = "any"` for all the
+/// parties the user can read as, also the ones allocated after the sync started
+#[derive(Debug, Clone, PartialEq)]
+pub enum Parties {
+    Any,
+    Listed(Vec<String>),
+}
+
+impl Default for Parties {
+    fn default() -> Self {
+        Parties::Listed(Vec::new())
+    }
+}
+
+impl Parties {
+    /// The event format of the events visible to the parties, with the views of the interfaces,
+    /// and the created event blobs if `created_event_blobs`
+    pub fn event_format(&self, interfaces: &[Identifier], created_event_blobs: bool) -> EventFormat {
+        let mut event_format = match self {
+            Parties::Any => event_format_for_any_party(interfaces),
+            Parties::Listed(parties) => event_format_for_parties(parties, interfaces),
+        };
+        let filters = event_format.filters_by_party.values_mut().chain(event_format.filters_for_any_party.as_mut());
+        for filter in filters.flat_map(|filters| filters.cumulative.iter_mut()) {
+            match &mut filter.identifier_filter {
+                Some(IdentifierFilter::WildcardFilter(filter)) => filter.include_created_event_blob = created_event_blobs,
+                Some(IdentifierFilter::InterfaceFilter(filter)) => filter.include_created_event_blob = created_event_blobs,
+                Some(IdentifierFilter::TemplateFilter(filter)) => filter.include_created_event_blob = created_event_blobs,
+                None => {}
+            }
+        }
+        event_format
+    }
+}
+
+impl<'de> Deserialize<'de> for Parties {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum RawParties {
+            Keyword(String),
+            Listed(Vec<String>),
+        }
+        match RawParties::deserialize(deserializer)? {
+            RawParties::Keyword(keyword) if keyword == "any" => Ok(Parties::Any),
+            RawParties::Keyword(keyword) => Err(serde::de::Error::custom(format!(
+                "invalid parties '{}', expected a list of party ids or \"any\"",
+                keyword
+            ))),
+            RawParties::Listed(parties) => Ok(Parties::Listed(parties)),
+        }
+    }
+}
+
+/// Prefix of the environment variables overriding the keys of the config file, the path of the
+/// key in upper case separated by `__`, e.g. `LEDGER_EXPLORER__NEO4J__PASSWORD`
+pub const ENV_PREFIX: &str = "LEDGER_EXPLORER__";
+
+/// Keys whose value can be read from a file with `<key>_file`, e.g. `password_file`
+const SECRET_KEYS: [&str; 2] = ["password", "client_secret"];
+
+/// Read and parse the config file, with the overrides of the `LEDGER_EXPLORER__` environment
+/// variables and the secrets of the `<key>_file` keys
 pub fn read_config_file<P: AsRef<Path>>(path: P) -> Result<ConfigFile> {
     let s = fs::read_to_string(&path)
         .with_context(|| format!("failed to read config file '{}'", path.as_ref().display()))?;
-    let cfg: ConfigFile = toml::from_str(&s).context("failed to parse TOML config")?;
-    Ok(cfg)
+    parse_config_file(&s, std::env::vars())
+}
+
+/// Parses the config, applying the overrides of the environment variables then reading the
+/// secret files, and validates it against the schema: missing and unknown keys are reported with
+/// the section they are in, unknown keys with the ones expected there
+fn parse_config_file(s: &str, vars: impl IntoIterator<Item = (String, String)>) -> Result<ConfigFile> {
+    let mut table: toml::Table = toml::from_str(s).context("failed to parse TOML config")?;
+    apply_env_overrides(&mut table, vars)?;
+    read_secret_files(&mut table)?;
+    toml::Value::Table(table).try_into().context("invalid config")
+}
+
+/// Sets the keys of the `LEDGER_EXPLORER__` variables, creating their tables if needed. A value
+/// replacing a string is a string, any other value is parsed as a TOML value, e.g. `500` or
+/// `["Alice::1220"]`, falling back to a string.
+fn apply_env_overrides(table: &mut toml::Table, vars: impl IntoIterator<Item = (String, String)>) -> Result<()> {
+    for (name, value) in vars {
+        let Some(path) = name.strip_prefix(ENV_PREFIX) else {
+            continue;
+        };
+        let keys: Vec<String> = path.split("__").map(|key| key.to_lowercase()).collect();
+        let Some((key, tables)) = keys.split_last() else {
+            continue;
+        };
+        let mut parent = &mut *table;
+        for (depth, section) in tables.iter().enumerate() {
+            parent = parent
+                .entry(section.clone())
+                .or_insert_with(|| toml::Value::Table(toml::Table::new()))
+                .as_table_mut()
+                .with_context(|| format!("{}: '{}' is not a table", name, tables[..=depth].join(".")))?;
+        }
+        let value = match parent.get(key) {
+            Some(toml::Value::String(_)) => toml::Value::String(value),
+            _ => toml::from_str::<toml::Table>(&format!("value = {}", value))
+                .ok()
+                .and_then(|mut parsed| parsed.remove("value"))
+                .unwrap_or(toml::Value::String(value)),
+        };
+        parent.insert(key.clone(), value);
+    }
+    Ok(())
+}
+
+/// Replaces the `<key>_file` keys of the secrets, in any table, by `<key>` set to the content of
+/// the file, without the trailing newline
+fn read_secret_files(table: &mut toml::Table) -> Result<()> {
+    for key in SECRET_KEYS {
+        let file_key = format!("{}_file", key);
+        if let Some(file) = table.remove(&file_key) {
+            let file = file.as_str().with_context(|| format!("{} must be a path", file_key))?;
+            if table.contains_key(key) {
+                anyhow::bail!("both {} and {} are set", key, file_key);
+            }
+            let secret = fs::read_to_string(file).with_context(|| format!("failed to read {} '{}'", file_key, file))?;
+            table.insert(key.to_string(), toml::Value::String(secret.trim_end_matches(['\r', '\n']).to_string()));
+        }
+    }
+    for (_, value) in table.iter_mut() {
+        if let Some(nested) = value.as_table_mut() {
+            read_secret_files(nested)?;
+        }
+    }
+    Ok(())
 }
 
 /// Read config file and resolve with the specified profile (or active_profile if None)
@@ -132,12 +670,36 @@ pub fn resolve_config(config_file: ConfigFile, profile_override: Option<&str>) -
             profile_name,
             config_file.profiles.keys().collect::<Vec<_>>()
         ))?;
+    validate_node_mapping(&config_file.node_mapping)?;
+    validate_webhooks(&config_file.webhooks)?;
+    let sources = config_file
+        .sources
+        .iter()
+        .map(|name| {
+            let source = config_file
+                .profiles
+                .get(name)
+                .with_context(|| format!("source profile '{}' not found", name))?;
+            Ok(Source { ledger_id: name.clone(), ledger: source.ledger.clone(), keycloak: source.keycloak.clone() })
+        })
+        .collect::<Result<Vec<_>>>()?;
 
     Ok(Config {
         logging: config_file.logging,
         neo4j: config_file.neo4j,
         ledger: profile.ledger.clone(),
         keycloak: profile.keycloak.clone(),
+        template_registry: config_file.template_registry,
+        metrics: config_file.metrics,
+        party_metadata: config_file.party_metadata,
+        package_catalog: config_file.package_catalog,
+        kafka: config_file.kafka,
+        export: config_file.export,
+        serve: config_file.serve,
+        node_mapping: config_file.node_mapping,
+        sources,
+        webhooks: config_file.webhooks,
+        opentelemetry: config_file.opentelemetry,
     })
 }
 
@@ -184,4 +746,68 @@ mod tests {
         assert!(!cfg.ledger.url.is_empty());
         Ok(())
     }
+
+    #[test]
+    fn test_env_overrides_secret_files_and_unknown_keys() -> Result<()> {
+        let example = include_str!("../config/config.toml.example");
+        let password_file = std::env::temp_dir().join(format!("ledger-explorer-password-{}", std::process::id()));
+        fs::write(&password_file, "from-file\n")?;
+        let vars = [
+            ("LEDGER_EXPLORER__NEO4J__BATCH_SIZE", "1000".to_string()),
+            ("LEDGER_EXPLORER__NEO4J__USER", "1234".to_string()),
+            ("LEDGER_EXPLORER__PROFILES__LOCAL__LEDGER__PARTIES", "[\"Alice::1220\"]".to_string()),
+            ("LEDGER_EXPLORER__NEO4J__PASSWORD_FILE", password_file.display().to_string()),
+            ("OTHER__NEO4J__USER", "ignored".to_string()),
+        ];
+        let vars = vars.map(|(name, value)| (name.to_string(), value));
+        let example_without_password = example.replace("password = \"password\"", "");
+        let cfg = parse_config_file(&example_without_password, vars)?;
+        fs::remove_file(&password_file)?;
+        assert_eq!(cfg.neo4j.batch_size, 1000);
+        assert_eq!(cfg.neo4j.user, "1234");
+        assert_eq!(cfg.neo4j.password, "from-file");
+        assert_eq!(cfg.profiles["local"].ledger.parties, Some(Parties::Listed(vec!["Alice::1220".to_string()])));
+
+        let missing = parse_config_file(&example_without_password, []).unwrap_err();
+        assert!(format!("{:#}", missing).contains("missing field `password`"));
+        let misspelled = format!("{}\n[serve]\nmax_row = 10\n", example);
+        let unknown = format!("{:#}", parse_config_file(&misspelled, []).unwrap_err());
+        assert!(unknown.contains("unknown field `max_row`, expected `listen_address` or `max_rows`"), "{}", unknown);
+        Ok(())
+    }
+
+    #[test]
+    fn test_namespace_scopes_ledger_ids() -> Result<()> {
+        let mut neo4j: Neo4jConfig = toml::from_str("uri = \"neo4j://localhost\"\nuser = \"neo4j\"\npassword = \"pw\"")?;
+        assert_eq!(neo4j.ledger_id(None), None);
+        assert_eq!(neo4j.ledger_id(Some("local")), Some("local".to_string()));
+        neo4j.namespace = Some("staging".to_string());
+        assert_eq!(neo4j.ledger_id(None), Some("staging".to_string()));
+        assert_eq!(neo4j.ledger_id(Some("local")), Some("staging/local".to_string()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parties_any_or_listed() -> Result<()> {
+        let ledger = |parties: &str| -> Result<LedgerConfig> {
+            let toml = format!("fake_jwt_user = \"alice\"\nurl = \"http://localhost:6865\"\n{}", parties);
+            Ok(toml::from_str(&toml)?)
+        };
+        let any = ledger("parties = \"any\"")?.parties.unwrap_or_default();
+        assert_eq!(any, Parties::Any);
+        assert!(any.event_format(&[], false).filters_for_any_party.is_some());
+        let blob_requested = |event_format: EventFormat| match &event_format.filters_for_any_party.unwrap().cumulative[0].identifier_filter {
+            Some(IdentifierFilter::WildcardFilter(filter)) => filter.include_created_event_blob,
+            _ => panic!("expected a wildcard filter"),
+        };
+        assert!(blob_requested(any.event_format(&[], true)));
+        assert!(!blob_requested(any.event_format(&[], false)));
+
+        let listed = ledger("parties = [\"Alice::1220\"]")?.parties.unwrap_or_default();
+        assert_eq!(listed, Parties::Listed(vec!["Alice::1220".to_string()]));
+        assert!(listed.event_format(&[], false).filters_by_party.contains_key("Alice::1220"));
+
+        assert!(ledger("parties = \"all\"").is_err());
+        Ok(())
+    }
 }
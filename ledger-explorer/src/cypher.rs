@@ -1,11 +1,16 @@
+use base64::{Engine as _, engine::general_purpose};
 use chrono::DateTime;
+use client::api_record_to_json::api_record_to_json;
 use client::utils::{
     extract_contract_ids_from_value, extract_edges, structure_markers_from_transaction,
 };
 use ledger_api::v2::{CreatedEvent, GetUpdatesResponse, get_updates_response::Update, event::Event};
 use neo4rs::{Query, BoltType};
 use serde_json::json;
-use crate::api_record_to_json::{api_record_to_json, choice_argument_json};
+use std::collections::{BTreeMap, HashMap};
+use daml_type_rep::schema::TemplateRegistry;
+use crate::config::NodeMapping;
+use crate::template_registry::{contract_key_json, create_arguments_json, exercised_argument_json};
 
 /// Wrapper around neo4rs::Query that preserves the cypher string and params for debugging
 #[derive(Clone)]
@@ -13,6 +18,8 @@ pub struct CypherQuery {
     pub cypher: String,
     pub params: Vec<(String, String)>,
     pub query: Query,
+    /// The name of the list parameter and its rows for `UNWIND` queries, see `batch_unwind_queries`
+    pub rows: Option<(String, Vec<serde_json::Value>)>,
 }
 
 impl std::fmt::Debug for CypherQuery {
@@ -20,6 +27,7 @@ impl std::fmt::Debug for CypherQuery {
         f.debug_struct("CypherQuery")
             .field("cypher", &self.cypher)
             .field("params", &self.params)
+            .field("rows", &self.rows)
             .finish()
     }
 }
@@ -47,9 +55,17 @@ impl CypherQuery {
             query: Query::new(cypher.clone()),
             cypher,
             params: Vec::new(),
+            rows: None,
         }
     }
 
+    /// A query beginning with `UNWIND $<param> AS ...` over the rows
+    pub fn unwind(cypher: String, param: &str, rows: Vec<serde_json::Value>) -> Self {
+        let mut query = Self::new(cypher).with_json_param(param, serde_json::Value::Array(rows.clone()));
+        query.rows = Some((param.to_string(), rows));
+        query
+    }
+
     pub fn with_param<T: Into<BoltType>>(mut self, key: &str, value: T) -> Self {
         self.query = self.query.param(key, value);
         self
@@ -61,32 +77,158 @@ impl CypherQuery {
         self.query = self.query.param(key, bolt_value);
         self
     }
+
+    /// The query with the nodes of the `LEDGER_SCOPED_LABELS` it matches or merges restricted to
+    /// the ledger, the `ledger_id` property part of their key: `(c:Created { contract_id: .. })`
+    /// becomes `(c:Created { ledger_id: $ledger_id, contract_id: .. })`, and `(t:Transaction)`
+    /// or `(c:Created:Asset WHERE ..)` get a `{ ledger_id: $ledger_id }` map, see
+    /// `scope_node_patterns`. Unchanged without ledger.
+    pub fn scoped_to_ledger(self, ledger_id: Option<&str>) -> Self {
+        let Some(ledger_id) = ledger_id else {
+            return self;
+        };
+        let cypher = scope_node_patterns(&self.cypher);
+        let query = Query::new(cypher.clone())
+            .with_params(self.query.get_params().clone())
+            .param("ledger_id", ledger_id);
+        Self { cypher, query, ..self }
+    }
+}
+
+/// Labels of the nodes of each ledger synced into a graph shared by several ledgers, see
+/// `CypherQuery::scoped_to_ledger`
+pub const LEDGER_SCOPED_LABELS: [&str; 9] =
+    ["Transaction", "Created", "Exercised", "Key", "Interface", "Template", "Package", "Party", "SyncState"];
+
+/// The cypher with a `ledger_id: $ledger_id` property in each node pattern with one of the
+/// `LEDGER_SCOPED_LABELS` among its labels, whatever the whitespace around them. The node patterns
+/// are parsed from the `(` of the pattern, its variable and labels, skipping string literals and
+/// backquoted names, rather than matched as text, so that no pattern is left unscoped.
+fn scope_node_patterns(cypher: &str) -> String {
+    let chars: Vec<char> = cypher.chars().collect();
+    let mut scoped = String::with_capacity(cypher.len() + 64);
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if matches!(c, '\'' | '"' | '`') {
+            let end = quoted_end(&chars, i);
+            scoped.extend(&chars[i..end]);
+            i = end;
+            continue;
+        }
+        scoped.push(c);
+        i += 1;
+        if c != '(' {
+            continue;
+        }
+        let mut end = name_end(&chars, skip_whitespace(&chars, i));
+        let mut labeled = false;
+        loop {
+            let colon = skip_whitespace(&chars, end);
+            if chars.get(colon) != Some(&':') {
+                break;
+            }
+            let start = skip_whitespace(&chars, colon + 1);
+            end = name_end(&chars, start);
+            let label: String = chars[start..end].iter().filter(|c| **c != '`').collect();
+            labeled |= LEDGER_SCOPED_LABELS.contains(&label.as_str());
+        }
+        if !labeled {
+            continue;
+        }
+        scoped.extend(&chars[i..end]);
+        i = end;
+        let brace = skip_whitespace(&chars, end);
+        if chars.get(brace) == Some(&'{') {
+            scoped.extend(&chars[i..=brace]);
+            i = brace + 1;
+            let empty = chars.get(skip_whitespace(&chars, i)) == Some(&'}');
+            scoped.push_str(if empty { " ledger_id: $ledger_id " } else { " ledger_id: $ledger_id," });
+        } else {
+            scoped.push_str(" { ledger_id: $ledger_id }");
+        }
+    }
+    scoped
+}
+
+/// Panics unless each `:<label>` of the `LEDGER_SCOPED_LABELS` in the scoped cypher, after the
+/// other labels of its node, is followed by the `ledger_id` property, e.g. for the tests of the
+/// queries of each module
+#[cfg(test)]
+pub(crate) fn assert_scoped_to_ledger(scoped: &str) {
+    for label in LEDGER_SCOPED_LABELS {
+        let pattern = format!(":{}", label);
+        for (at, _) in scoped.match_indices(&pattern) {
+            let rest = &scoped[at + pattern.len()..];
+            if rest.starts_with(|c: char| c.is_alphanumeric() || c == '_') {
+                continue;
+            }
+            let mut rest = rest.trim_start();
+            while let Some(label) = rest.strip_prefix(':') {
+                rest = label.trim_start_matches(|c: char| c.is_alphanumeric() || c == '_' || c == '`').trim_start();
+            }
+            assert!(rest.starts_with("{ ledger_id: $ledger_id"), "{} not scoped in: {}", pattern, scoped);
+        }
+    }
+}
+
+fn skip_whitespace(chars: &[char], mut i: usize) -> usize {
+    while chars.get(i).is_some_and(|c| c.is_whitespace()) {
+        i += 1;
+    }
+    i
 }
 
-macro_rules! cypher_query {
-    ($cypher:expr, $($key:ident = $value:expr),* $(,)?) => {{
-        let cypher_str = $cypher.to_string();
-        let query = Query::new(cypher_str.clone())
-            $(.param(stringify!($key), $value.clone()))*;
-        let params = vec![
-            $((stringify!($key).to_string(), format!("{:?}", $value))),*
-        ];
-        CypherQuery { cypher: cypher_str, params, query }
-    }};
+/// The end of the variable or label name at `i`, backquoted or not, `i` if there is none
+fn name_end(chars: &[char], mut i: usize) -> usize {
+    if chars.get(i) == Some(&'`') {
+        return quoted_end(chars, i);
+    }
+    while chars.get(i).is_some_and(|c| c.is_alphanumeric() || *c == '_') {
+        i += 1;
+    }
+    i
+}
+
+/// The index after the closing quote of the string literal or backquoted name opened at `start`
+fn quoted_end(chars: &[char], start: usize) -> usize {
+    let quote = chars[start];
+    let mut i = start + 1;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' if quote != '`' => i += 2,
+            c if c == quote => return i + 1,
+            _ => i += 1,
+        }
+    }
+    chars.len()
 }
 
+/// Version of the graph layout, stored on the SyncState node
+pub const SCHEMA_VERSION: i64 = 1;
+
 /// Converts a GetUpdatesResponse directly into a Vec of Cypher statements.
 /// Uses UNWIND for batched operations to minimize round-trips.
-/// Returns an empty vector if update is None or not a Transaction.
-pub fn get_updates_response_to_cypher(response: &GetUpdatesResponse) -> Vec<CypherQuery> {
+/// Returns an empty vector if update is None. Updates other than transactions, e.g. offset
+/// checkpoints, only advance the SyncState offset.
+/// Payloads of templates in the registry are stored as Daml-LF JSON of their type. Created nodes
+/// get the label and the payload properties of the node mapping of their template.
+pub fn get_updates_response_to_cypher(
+    response: &GetUpdatesResponse,
+    registry: Option<&TemplateRegistry>,
+    node_mapping: &HashMap<String, NodeMapping>,
+) -> Vec<CypherQuery> {
     let mut cypher_statements = Vec::new();
 
     let Some(update) = &response.update else {
         return cypher_statements;
     };
 
-    let Update::Transaction(transaction) = update else {
-        return cypher_statements;
+    let transaction = match update {
+        Update::Transaction(transaction) => transaction,
+        Update::Reassignment(r) => return vec![sync_state_to_cypher(r.offset, None)],
+        Update::OffsetCheckpoint(c) => return vec![sync_state_to_cypher(c.offset, None)],
+        Update::TopologyTransaction(t) => return vec![sync_state_to_cypher(t.offset, None)],
     };
 
     let offset = transaction.offset;
@@ -120,34 +262,53 @@ pub fn get_updates_response_to_cypher(response: &GetUpdatesResponse) -> Vec<Cyph
         .unwrap_or_default();
     let label = format!("TX@{}", transaction.offset);
     // Use MERGE to avoid duplicates on reconnection
-    cypher_statements.push(cypher_query!(
-        "MERGE (t:Transaction { offset: $offset }) \
+    cypher_statements.push(CypherQuery::unwind(
+        "UNWIND $transactions AS p \
+        MERGE (t:Transaction { offset: p.offset }) \
         ON CREATE SET \
-        t.label = $label, \
-        t.update_id = $update_id, \
-        t.command_id = $command_id, \
-        t.workflow_id = $workflow_id, \
-        t.synchronizer_id = $synchronizer_id, \
-        t.effective_at = $effective_at, \
-        t.record_time = $record_time, \
-        t.traceparent = $traceparent, \
-        t.tracestate = $tracestate",
-        label = label,
-        update_id = transaction.update_id.clone(),
-        command_id = transaction.command_id.clone(),
-        workflow_id = transaction.workflow_id.clone(),
-        offset = transaction.offset,
-        synchronizer_id = transaction.synchronizer_id.clone(),
-        effective_at = effective_at.clone(),
-        record_time = record_time.clone(),
-        traceparent = traceparent.clone(),
-        tracestate = tracestate.clone(),
+        t.label = p.label, \
+        t.update_id = p.update_id, \
+        t.command_id = p.command_id, \
+        t.workflow_id = p.workflow_id, \
+        t.synchronizer_id = p.synchronizer_id, \
+        t.effective_at = p.effective_at, \
+        t.record_time = p.record_time, \
+        t.traceparent = p.traceparent, \
+        t.tracestate = p.tracestate".to_string(),
+        TRANSACTIONS_PARAM,
+        vec![json!({
+            "label": label,
+            "update_id": transaction.update_id,
+            "command_id": transaction.command_id,
+            "workflow_id": transaction.workflow_id,
+            "offset": transaction.offset,
+            "synchronizer_id": transaction.synchronizer_id,
+            "effective_at": effective_at,
+            "record_time": record_time,
+            "traceparent": traceparent,
+            "tracestate": tracestate
+        })],
     ));
 
     // Collect Created events for batch insert
     let mut created_events: Vec<serde_json::Value> = Vec::new();
+    // Collect contract keys of Created events for batch insert
+    let mut contract_keys: Vec<serde_json::Value> = Vec::new();
+    // Collect interfaces implemented by Created events for batch insert
+    let mut implemented_interfaces: Vec<serde_json::Value> = Vec::new();
+    // Collect templates of Created events for batch insert
+    let mut instance_of: Vec<serde_json::Value> = Vec::new();
+    // Collect labels of Created events by the node mapping for batch insert
+    let mut mapped_labels: Vec<(String, serde_json::Value)> = Vec::new();
     // Collect Exercised events for batch insert
     let mut exercised_events: Vec<serde_json::Value> = Vec::new();
+    // Collect the contracts in the results of Exercised events for batch insert
+    let mut result_rels: Vec<serde_json::Value> = Vec::new();
+    // Collect the signatories and observers of Created events and the acting parties of
+    // Exercised events for batch insert
+    let mut signatories: Vec<serde_json::Value> = Vec::new();
+    let mut observers: Vec<serde_json::Value> = Vec::new();
+    let mut acting_parties: Vec<serde_json::Value> = Vec::new();
 
     for event in &transaction.events {
         match &event.event {
@@ -176,17 +337,18 @@ pub fn get_updates_response_to_cypher(response: &GetUpdatesResponse) -> Vec<Cyph
                         dt.map(|d| d.format("%Y-%m-%dT%H:%M:%SZ").to_string())
                     })
                     .unwrap_or_default();
-                let create_arguments_json = created
-                    .create_arguments
-                    .as_ref()
-                    .map(|args| api_record_to_json(args))
-                    .map(|json| serde_json::to_string(&json).unwrap_or("null".to_string()))
-                    .unwrap_or("null".to_string());
+                let payload = create_arguments_json(registry, created);
+                let create_arguments_json = serde_json::to_string(&payload).unwrap_or("null".to_string());
                 let create_arguments = created
                     .create_arguments
                     .as_ref()
                     .map(|args| serde_json::to_string(args).unwrap_or("null".to_string()))
                     .unwrap_or("null".to_string());
+                let interface_views = interface_views(created);
+                implemented_interfaces.extend(implemented_interfaces_rows(created, &interface_views));
+                let mapping = node_mapping.get(&template_name);
+                mapped_labels.extend(mapped_label_row(mapping, created));
+                instance_of.extend(instance_of_row(created));
 
                 created_events.push(json!({
                     "contract_id": created.contract_id,
@@ -197,8 +359,15 @@ pub fn get_updates_response_to_cypher(response: &GetUpdatesResponse) -> Vec<Cyph
                     "node_id": created.node_id,
                     "created_at": created_at,
                     "create_arguments": create_arguments,
-                    "create_arguments_json": create_arguments_json
+                    "create_arguments_json": create_arguments_json,
+                    "interface_views": serde_json::to_string(&interface_views).unwrap_or("[]".to_string()),
+                    "created_event_blob": created_event_blob(created),
+                    "synchronizer_id": transaction.synchronizer_id,
+                    "properties": promoted_properties(mapping, &payload)
                 }));
+                contract_keys.extend(contract_key_row(registry, created, &template_name));
+                signatories.extend(contract_party_rows(&created.contract_id, &created.signatories));
+                observers.extend(contract_party_rows(&created.contract_id, &created.observers));
             }
             Some(Event::Exercised(exercised)) => {
                 let label = format!("{}@{}", exercised.choice, exercised.offset);
@@ -217,7 +386,15 @@ pub fn get_updates_response_to_cypher(response: &GetUpdatesResponse) -> Vec<Cyph
                         dt.map(|d| d.format("%Y-%m-%dT%H:%M:%SZ").to_string())
                     })
                     .unwrap_or_default();
-                let choice_argument_json_val = choice_argument_json(&exercised.choice_argument);
+                let result_contract_ids = extract_contract_ids_from_value(&exercised.exercise_result);
+                result_rels.extend(result_contract_ids.iter().map(|contract_id| {
+                    json!({
+                        "offset": exercised.offset,
+                        "node_id": exercised.node_id,
+                        "contract_id": contract_id
+                    })
+                }));
+                let choice_argument_json_val = exercised_argument_json(registry, exercised);
                 let choice_argument_json = serde_json::to_string(&choice_argument_json_val).unwrap_or("null".to_string());
                 let choice_argument = exercised
                     .choice_argument
@@ -233,12 +410,15 @@ pub fn get_updates_response_to_cypher(response: &GetUpdatesResponse) -> Vec<Cyph
                     "offset": exercised.offset,
                     "node_id": exercised.node_id,
                     "consuming": exercised.consuming,
-                    "result_contract_ids": serde_json::to_string(&extract_contract_ids_from_value(&exercised.exercise_result)).unwrap_or("[]".to_string()),
+                    "result_contract_ids": serde_json::to_string(&result_contract_ids).unwrap_or("[]".to_string()),
                     "last_descendant_node_id": exercised.last_descendant_node_id,
                     "transaction_effective_at": transaction_effective_at,
                     "choice_argument": choice_argument,
                     "choice_argument_json": choice_argument_json
                 }));
+                acting_parties.extend(exercised.acting_parties.iter().map(|party| {
+                    json!({ "party_id": party, "offset": exercised.offset, "node_id": exercised.node_id })
+                }));
             }
             _ => {}
         }
@@ -246,10 +426,10 @@ pub fn get_updates_response_to_cypher(response: &GetUpdatesResponse) -> Vec<Cyph
 
     // Batch MERGE Created nodes (use contract_id as unique key to avoid duplicates)
     if !created_events.is_empty() {
-        let cypher = CypherQuery::new(
+        let cypher = CypherQuery::unwind(
             "UNWIND $props AS p \
             MERGE (c:Created { contract_id: p.contract_id }) \
-            ON CREATE SET \
+            SET \
             c.template_name = p.template_name, \
             c.label = p.label, \
             c.signatories = p.signatories, \
@@ -257,14 +437,39 @@ pub fn get_updates_response_to_cypher(response: &GetUpdatesResponse) -> Vec<Cyph
             c.node_id = p.node_id, \
             c.created_at = p.created_at, \
             c.create_arguments = p.create_arguments, \
-            c.create_arguments_json = p.create_arguments_json".to_string()
-        ).with_json_param("props", serde_json::Value::Array(created_events));
+            c.create_arguments_json = p.create_arguments_json, \
+            c.interface_views = p.interface_views, \
+            c.created_event_blob = coalesce(p.created_event_blob, c.created_event_blob), \
+            c.synchronizer_id = p.synchronizer_id, \
+            c += p.properties, \
+            c.active = coalesce(c.active, true)".to_string(),
+            "props",
+            created_events,
+        );
         cypher_statements.push(cypher);
     }
 
+    // Batch SET the labels of the node mapping on Created nodes
+    cypher_statements.extend(mapped_labels_to_cypher(mapped_labels));
+
+    // Batch MERGE Template and Package nodes, and INSTANCE_OF and DEFINES edges
+    if !instance_of.is_empty() {
+        cypher_statements.push(instance_of_to_cypher(instance_of));
+    }
+
+    // Batch MERGE Key nodes shared by the contracts with the same key, and KEYED_BY edges
+    if !contract_keys.is_empty() {
+        cypher_statements.push(contract_keys_to_cypher(contract_keys));
+    }
+
+    // Batch MERGE Interface nodes and IMPLEMENTS edges
+    if !implemented_interfaces.is_empty() {
+        cypher_statements.push(implemented_interfaces_to_cypher(implemented_interfaces));
+    }
+
     // Batch MERGE Exercised nodes (use offset + node_id as unique key to avoid duplicates)
     if !exercised_events.is_empty() {
-        let cypher = CypherQuery::new(
+        let cypher = CypherQuery::unwind(
             "UNWIND $props AS p \
             MERGE (e:Exercised { offset: p.offset, node_id: p.node_id }) \
             ON CREATE SET \
@@ -277,8 +482,10 @@ pub fn get_updates_response_to_cypher(response: &GetUpdatesResponse) -> Vec<Cyph
             e.last_descendant_node_id = p.last_descendant_node_id, \
             e.transaction_effective_at = p.transaction_effective_at, \
             e.choice_argument = p.choice_argument, \
-            e.choice_argument_json = p.choice_argument_json".to_string()
-        ).with_json_param("props", serde_json::Value::Array(exercised_events));
+            e.choice_argument_json = p.choice_argument_json".to_string(),
+            "props",
+            exercised_events,
+        );
         cypher_statements.push(cypher);
     }
 
@@ -314,32 +521,47 @@ pub fn get_updates_response_to_cypher(response: &GetUpdatesResponse) -> Vec<Cyph
 
         // Exercised -> Created edges (uses exercised_offset_node and created_offset_node indexes)
         if !edges_to_created.is_empty() {
-            let cypher = CypherQuery::new(
+            let cypher = CypherQuery::unwind(
                 "UNWIND $edges AS e \
                 MATCH (parent:Exercised {offset: e.offset, node_id: e.parent_id}), \
                 (child:Created {offset: e.offset, node_id: e.child_id}) \
-                MERGE (parent)-[:CONSEQUENCE]->(child)".to_string()
-            ).with_json_param("edges", serde_json::Value::Array(edges_to_created));
+                MERGE (parent)-[:CONSEQUENCE]->(child)".to_string(),
+                "edges",
+                edges_to_created,
+            );
             cypher_statements.push(cypher);
         }
 
         // Exercised -> Exercised edges (uses exercised_offset_node index for both)
         if !edges_to_exercised.is_empty() {
-            let cypher = CypherQuery::new(
+            let cypher = CypherQuery::unwind(
                 "UNWIND $edges AS e \
                 MATCH (parent:Exercised {offset: e.offset, node_id: e.parent_id}), \
                 (child:Exercised {offset: e.offset, node_id: e.child_id}) \
-                MERGE (parent)-[:CONSEQUENCE]->(child)".to_string()
-            ).with_json_param("edges", serde_json::Value::Array(edges_to_exercised));
+                MERGE (parent)-[:CONSEQUENCE]->(child)".to_string(),
+                "edges",
+                edges_to_exercised,
+            );
             cypher_statements.push(cypher);
         }
     }
 
-    // Batch TARGET and CONSUMES relationships for Exercised events
+    // Batch TARGET and CONSUMES relationships for Exercised events, and ARCHIVES relationships
+    // for consuming exercises and, in ACS delta transactions, Archived events. The Created node
+    // of the contract is MERGEd: it may be written by a concurrent writer's batch, whose commit
+    // the MERGE waits for on the uniqueness constraint; contracts never created in the graph get
+    // a node with only the contract_id.
     let mut target_rels: Vec<serde_json::Value> = Vec::new();
     let mut consumes_rels: Vec<serde_json::Value> = Vec::new();
+    let mut archived_rels: Vec<serde_json::Value> = Vec::new();
 
     for event in &transaction.events {
+        if let Some(Event::Archived(archived)) = &event.event {
+            archived_rels.push(json!({
+                "offset": archived.offset,
+                "contract_id": archived.contract_id
+            }));
+        }
         if let Some(Event::Exercised(exercised)) = &event.event {
             target_rels.push(json!({
                 "offset": exercised.offset,
@@ -357,22 +579,55 @@ pub fn get_updates_response_to_cypher(response: &GetUpdatesResponse) -> Vec<Cyph
     }
 
     if !target_rels.is_empty() {
-        let cypher = CypherQuery::new(
+        let cypher = CypherQuery::unwind(
             "UNWIND $rels AS r \
-            MATCH (e:Exercised {offset: r.offset, node_id: r.node_id}), \
-            (c:Created {contract_id: r.target_contract_id}) \
-            MERGE (e)-[:TARGET]->(c)".to_string()
-        ).with_json_param("rels", serde_json::Value::Array(target_rels));
+            MATCH (e:Exercised {offset: r.offset, node_id: r.node_id}) \
+            MERGE (c:Created {contract_id: r.target_contract_id}) \
+            MERGE (e)-[:TARGET]->(c)".to_string(),
+            "rels",
+            target_rels,
+        );
         cypher_statements.push(cypher);
     }
 
     if !consumes_rels.is_empty() {
-        let cypher = CypherQuery::new(
+        let cypher = CypherQuery::unwind(
+            "UNWIND $rels AS r \
+            MATCH (e:Exercised {offset: r.offset, node_id: r.node_id}) \
+            MERGE (c:Created {contract_id: r.target_contract_id}) \
+            MERGE (e)-[:CONSUMES]->(c) \
+            MERGE (e)-[:ARCHIVES]->(c) \
+            SET c.active = false, c.archived_at_offset = r.offset".to_string(),
+            "rels",
+            consumes_rels,
+        );
+        cypher_statements.push(cypher);
+    }
+
+    // Batch RESULT_OF relationships from Exercised events to the contracts in their results,
+    // usually created by their consequences
+    if !result_rels.is_empty() {
+        let cypher = CypherQuery::unwind(
+            "UNWIND $rels AS r \
+            MATCH (e:Exercised {offset: r.offset, node_id: r.node_id}) \
+            MERGE (c:Created {contract_id: r.contract_id}) \
+            MERGE (e)-[:RESULT_OF]->(c)".to_string(),
+            "rels",
+            result_rels,
+        );
+        cypher_statements.push(cypher);
+    }
+
+    if !archived_rels.is_empty() {
+        let cypher = CypherQuery::unwind(
             "UNWIND $rels AS r \
-            MATCH (e:Exercised {offset: r.offset, node_id: r.node_id}), \
-            (c:Created {contract_id: r.target_contract_id}) \
-            MERGE (e)-[:CONSUMES]->(c)".to_string()
-        ).with_json_param("rels", serde_json::Value::Array(consumes_rels));
+            MATCH (t:Transaction {offset: r.offset}) \
+            MERGE (c:Created {contract_id: r.contract_id}) \
+            MERGE (t)-[:ARCHIVES]->(c) \
+            SET c.active = false, c.archived_at_offset = r.offset".to_string(),
+            "rels",
+            archived_rels,
+        );
         cypher_statements.push(cypher);
     }
 
@@ -411,23 +666,27 @@ pub fn get_updates_response_to_cypher(response: &GetUpdatesResponse) -> Vec<Cyph
 
     // Batch ACTION relationships for root-level Exercised events
     if !root_exercised.is_empty() {
-        let cypher = CypherQuery::new(
+        let cypher = CypherQuery::unwind(
             "UNWIND $rels AS r \
             MATCH (t:Transaction {offset: r.offset}), \
             (e:Exercised {offset: r.offset, node_id: r.node_id}) \
-            MERGE (t)-[:ACTION]->(e)".to_string()
-        ).with_json_param("rels", serde_json::Value::Array(root_exercised));
+            MERGE (t)-[:ACTION]->(e)".to_string(),
+            "rels",
+            root_exercised,
+        );
         cypher_statements.push(cypher);
     }
 
     // Batch ACTION relationships for root-level Created events
     if !root_created.is_empty() {
-        let cypher = CypherQuery::new(
+        let cypher = CypherQuery::unwind(
             "UNWIND $rels AS r \
             MATCH (t:Transaction {offset: r.offset}), \
             (c:Created {offset: r.offset, node_id: r.node_id}) \
-            MERGE (t)-[:ACTION]->(c)".to_string()
-        ).with_json_param("rels", serde_json::Value::Array(root_created));
+            MERGE (t)-[:ACTION]->(c)".to_string(),
+            "rels",
+            root_created,
+        );
         cypher_statements.push(cypher);
     }
 
@@ -439,29 +698,117 @@ pub fn get_updates_response_to_cypher(response: &GetUpdatesResponse) -> Vec<Cyph
             .collect();
 
         // First MERGE all parties
-        let merge_cypher = CypherQuery::new(
+        let merge_cypher = CypherQuery::unwind(
             "UNWIND $parties AS p \
-            MERGE (party:Party {party_id: p.party_id})".to_string()
-        ).with_json_param("parties", serde_json::Value::Array(parties.clone()));
+            MERGE (party:Party {party_id: p.party_id})".to_string(),
+            "parties",
+            parties.clone(),
+        );
         cypher_statements.push(merge_cypher);
 
         // Then MERGE REQUESTED relationships (avoid duplicates on reconnection)
-        let rel_cypher = CypherQuery::new(
+        let rel_cypher = CypherQuery::unwind(
             "UNWIND $parties AS p \
             MATCH (party:Party {party_id: p.party_id}), \
             (t:Transaction {offset: p.offset}) \
-            MERGE (party)-[:REQUESTED]->(t)".to_string()
-        ).with_json_param("parties", serde_json::Value::Array(parties));
+            MERGE (party)-[:REQUESTED]->(t)".to_string(),
+            "parties",
+            parties,
+        );
         cypher_statements.push(rel_cypher);
     }
 
+    // Batch Party MERGE and SIGNATORY, OBSERVER and ACTED_BY relationships
+    if !signatories.is_empty() {
+        cypher_statements.push(contract_parties_to_cypher("SIGNATORY", signatories));
+    }
+    if !observers.is_empty() {
+        cypher_statements.push(contract_parties_to_cypher("OBSERVER", observers));
+    }
+    if !acting_parties.is_empty() {
+        cypher_statements.push(CypherQuery::unwind(
+            "UNWIND $acting_parties AS p \
+            MATCH (e:Exercised { offset: p.offset, node_id: p.node_id }) \
+            MERGE (party:Party { party_id: p.party_id }) \
+            MERGE (party)-[:ACTED_BY]->(e)".to_string(),
+            "acting_parties",
+            acting_parties,
+        ));
+    }
+
+    // Advance the resume point in the same Neo4j transaction as the writes
+    cypher_statements.push(sync_state_to_cypher(transaction.offset, Some(&transaction.update_id)));
+
     cypher_statements
 }
 
-/// Converts a CreatedEvent (from ACS) into Cypher statements to create a Created node.
+const SYNC_STATES_PARAM: &str = "sync_states";
+const TRANSACTIONS_PARAM: &str = "transactions";
+
+/// Whether the query is a `sync_state_to_cypher` query, possibly batched
+pub fn is_sync_state(query: &CypherQuery) -> bool {
+    query.rows.as_ref().is_some_and(|(param, _)| param == SYNC_STATES_PARAM)
+}
+
+/// The offset a `sync_state_to_cypher` query, possibly batched, advances the SyncState to
+pub fn sync_state_offset(query: &CypherQuery) -> Option<i64> {
+    let (_, rows) = query.rows.as_ref().filter(|_| is_sync_state(query))?;
+    rows.iter().filter_map(|row| row["offset"].as_i64()).max()
+}
+
+/// The number of transactions a query, possibly batched, writes the Transaction nodes of
+pub fn transaction_count(query: &CypherQuery) -> usize {
+    match &query.rows {
+        Some((param, rows)) if param == TRANSACTIONS_PARAM => rows.len(),
+        _ => 0,
+    }
+}
+
+/// Advances the offset of the singleton SyncState node, the resume point of the sync. Offsets of
+/// replayed updates, lower than the stored one, leave it unchanged.
+/// Batched over several updates, the SyncState gets the highest offset and the update id of the
+/// last transaction (`collect` skips the null update ids of other updates).
+pub fn sync_state_to_cypher(offset: i64, update_id: Option<&str>) -> CypherQuery {
+    CypherQuery::unwind(
+        format!(
+            "UNWIND $sync_states AS r \
+            WITH max(r.offset) AS offset, last(collect(r.update_id)) AS update_id \
+            MERGE (s:SyncState {{ id: 'sync' }}) \
+            WITH s, offset, update_id, coalesce(s.last_offset, -1) < offset AS advanced \
+            SET s.schema_version = {}, \
+            s.last_offset = CASE WHEN advanced THEN offset ELSE s.last_offset END, \
+            s.last_update_id = CASE WHEN advanced THEN coalesce(update_id, s.last_update_id) ELSE s.last_update_id END",
+            SCHEMA_VERSION
+        ),
+        SYNC_STATES_PARAM,
+        vec![json!({ "offset": offset, "update_id": update_id })],
+    )
+}
+
+/// Records on the SyncState node that the ACS was loaded at the offset, streaming resumes from
+/// there if no update was processed yet
+pub fn acs_loaded_to_cypher(acs_offset: i64) -> CypherQuery {
+    CypherQuery::new(
+        "MERGE (s:SyncState { id: 'sync' }) \
+        SET s.acs_loaded = true, \
+        s.acs_offset = $acs_offset, \
+        s.schema_version = $schema_version, \
+        s.last_offset = CASE WHEN coalesce(s.last_offset, -1) < $acs_offset THEN $acs_offset ELSE s.last_offset END".to_string()
+    )
+    .with_json_param("acs_offset", json!(acs_offset))
+    .with_json_param("schema_version", json!(SCHEMA_VERSION))
+}
+
+/// Converts a CreatedEvent (from ACS) of the synchronizer into Cypher statements to create a
+/// Created node.
 /// The offset is set to -1 to indicate this is from ACS (pre-existing contract).
 /// The node_id is set to 0 since there's no transaction structure for ACS contracts.
-pub fn created_event_to_cypher(created: &CreatedEvent) -> Vec<CypherQuery> {
+pub fn created_event_to_cypher(
+    created: &CreatedEvent,
+    synchronizer_id: &str,
+    registry: Option<&TemplateRegistry>,
+    node_mapping: &HashMap<String, NodeMapping>,
+) -> Vec<CypherQuery> {
     let mut cypher_statements = Vec::new();
 
     let label = created
@@ -488,41 +835,658 @@ pub fn created_event_to_cypher(created: &CreatedEvent) -> Vec<CypherQuery> {
             dt.map(|d| d.format("%Y-%m-%dT%H:%M:%SZ").to_string())
         })
         .unwrap_or_default();
-    let create_arguments_json = created
-        .create_arguments
-        .as_ref()
-        .map(|args| api_record_to_json(args))
-        .map(|json| serde_json::to_string(&json).unwrap_or("null".to_string()))
-        .unwrap_or("null".to_string());
+    let payload = create_arguments_json(registry, created);
+    let create_arguments_json = serde_json::to_string(&payload).unwrap_or("null".to_string());
     let create_arguments = created
         .create_arguments
         .as_ref()
         .map(|args| serde_json::to_string(args).unwrap_or("null".to_string()))
         .unwrap_or("null".to_string());
+    let interface_views = interface_views(created);
+    let mapping = node_mapping.get(&template_name);
 
     // Use MERGE to avoid duplicates if contract already exists
-    cypher_statements.push(cypher_query!(
-        "MERGE (c:Created { contract_id: $contract_id }) \
-        ON CREATE SET \
-        c.template_name = $template_name, \
-        c.label = $label, \
-        c.signatories = $signatories, \
-        c.offset = $offset, \
-        c.node_id = $node_id, \
-        c.created_at = $created_at, \
-        c.create_arguments = $create_arguments, \
-        c.create_arguments_json = $create_arguments_json, \
-        c.from_acs = true",
-        contract_id = created.contract_id.clone(),
-        template_name = template_name.clone(),
-        label = label.clone(),
-        signatories = signatories_str.clone(),
-        offset = -1i64, // ACS contracts have no specific offset
-        node_id = 0i32,
-        created_at = created_at.clone(),
-        create_arguments = create_arguments,
-        create_arguments_json = create_arguments_json,
+    cypher_statements.push(CypherQuery::unwind(
+        "UNWIND $props AS p \
+        MERGE (c:Created { contract_id: p.contract_id }) \
+        SET \
+        c.template_name = p.template_name, \
+        c.label = p.label, \
+        c.signatories = p.signatories, \
+        c.offset = p.offset, \
+        c.node_id = p.node_id, \
+        c.created_at = p.created_at, \
+        c.create_arguments = p.create_arguments, \
+        c.create_arguments_json = p.create_arguments_json, \
+        c.interface_views = p.interface_views, \
+        c.created_event_blob = coalesce(p.created_event_blob, c.created_event_blob), \
+        c.synchronizer_id = p.synchronizer_id, \
+        c += p.properties, \
+        c.active = coalesce(c.active, true), \
+        c.from_acs = true".to_string(),
+        "props",
+        vec![json!({
+            "contract_id": created.contract_id,
+            "template_name": template_name,
+            "label": label,
+            "signatories": signatories_str,
+            "offset": -1i64, // ACS contracts have no specific offset
+            "node_id": 0i32,
+            "created_at": created_at,
+            "create_arguments": create_arguments,
+            "create_arguments_json": create_arguments_json,
+            "interface_views": serde_json::to_string(&interface_views).unwrap_or("[]".to_string()),
+            "created_event_blob": created_event_blob(created),
+            "synchronizer_id": synchronizer_id,
+            "properties": promoted_properties(mapping, &payload)
+        })],
     ));
 
+    cypher_statements.extend(mapped_labels_to_cypher(mapped_label_row(mapping, created).into_iter().collect()));
+
+    let implemented_interfaces = implemented_interfaces_rows(created, &interface_views);
+    if !implemented_interfaces.is_empty() {
+        cypher_statements.push(implemented_interfaces_to_cypher(implemented_interfaces));
+    }
+
+    if let Some(contract_key) = contract_key_row(registry, created, &template_name) {
+        cypher_statements.push(contract_keys_to_cypher(vec![contract_key]));
+    }
+
+    if let Some(instance_of) = instance_of_row(created) {
+        cypher_statements.push(instance_of_to_cypher(vec![instance_of]));
+    }
+
+    for (rel, parties) in [("SIGNATORY", &created.signatories), ("OBSERVER", &created.observers)] {
+        if !parties.is_empty() {
+            cypher_statements.push(contract_parties_to_cypher(rel, contract_party_rows(&created.contract_id, parties)));
+        }
+    }
+
     cypher_statements
 }
+
+/// The rows of the edges from the parties to the Created node of the contract
+fn contract_party_rows(contract_id: &str, parties: &[String]) -> Vec<serde_json::Value> {
+    parties
+        .iter()
+        .map(|party| json!({ "party_id": party, "contract_id": contract_id }))
+        .collect()
+}
+
+/// The Party nodes of the rows and their `rel` edges, `SIGNATORY` or `OBSERVER`, to the Created
+/// nodes of the contracts
+fn contract_parties_to_cypher(rel: &str, rows: Vec<serde_json::Value>) -> CypherQuery {
+    CypherQuery::unwind(
+        format!(
+            "UNWIND $contract_parties AS p \
+            MATCH (c:Created {{ contract_id: p.contract_id }}) \
+            MERGE (party:Party {{ party_id: p.party_id }}) \
+            MERGE (party)-[:{}]->(c)",
+            rel
+        ),
+        "contract_parties",
+        rows,
+    )
+}
+
+/// The created event blob of the contract in base64, for explicit disclosure, `None` unless
+/// requested, which keeps the blob stored earlier
+fn created_event_blob(created: &CreatedEvent) -> Option<String> {
+    (!created.created_event_blob.is_empty()).then(|| general_purpose::STANDARD.encode(&created.created_event_blob))
+}
+
+/// The payload fields of the node mapping of a template as properties of its Created nodes, by
+/// the property names of their paths. Records and lists are stored as JSON strings, missing
+/// fields as null, which removes the property.
+fn promoted_properties(mapping: Option<&NodeMapping>, payload: &serde_json::Value) -> serde_json::Value {
+    let properties = mapping
+        .into_iter()
+        .flat_map(|mapping| mapping.properties())
+        .map(|(path, name)| {
+            let value = match path.split('.').try_fold(payload, |value, field| value.get(field)) {
+                Some(value @ (serde_json::Value::Object(_) | serde_json::Value::Array(_))) => {
+                    serde_json::Value::String(value.to_string())
+                }
+                Some(value) => value.clone(),
+                None => serde_json::Value::Null,
+            };
+            (name, value)
+        })
+        .collect();
+    serde_json::Value::Object(properties)
+}
+
+fn mapped_label_row(mapping: Option<&NodeMapping>, created: &CreatedEvent) -> Option<(String, serde_json::Value)> {
+    let label = mapping?.label.clone()?;
+    Some((label, json!({ "contract_id": created.contract_id })))
+}
+
+/// A query per label, as labels cannot be parameters. The labels are identifiers, see
+/// `config::NodeMapping`.
+fn mapped_labels_to_cypher(mapped_labels: Vec<(String, serde_json::Value)>) -> Vec<CypherQuery> {
+    let mut by_label: BTreeMap<String, Vec<serde_json::Value>> = BTreeMap::new();
+    for (label, row) in mapped_labels {
+        by_label.entry(label).or_default().push(row);
+    }
+    by_label
+        .into_iter()
+        .map(|(label, rows)| {
+            CypherQuery::unwind(
+                format!(
+                    "UNWIND $labeled AS l \
+                    MATCH (c:Created {{ contract_id: l.contract_id }}) \
+                    SET c:`{}`",
+                    label
+                ),
+                "labeled",
+                rows,
+            )
+        })
+        .collect()
+}
+
+fn contract_key_row(registry: Option<&TemplateRegistry>, created: &CreatedEvent, template_name: &str) -> Option<serde_json::Value> {
+    let (key_hash, key_json) = contract_key(registry, created, template_name)?;
+    Some(json!({
+        "contract_id": created.contract_id,
+        "template_name": template_name,
+        "key_hash": key_hash,
+        "key_json": key_json
+    }))
+}
+
+fn contract_keys_to_cypher(contract_keys: Vec<serde_json::Value>) -> CypherQuery {
+    CypherQuery::unwind(
+        "UNWIND $keys AS k \
+        MATCH (c:Created { contract_id: k.contract_id }) \
+        MERGE (key:Key { key_hash: k.key_hash }) \
+        ON CREATE SET \
+        key.template_name = k.template_name, \
+        key.key_json = k.key_json \
+        MERGE (c)-[:KEYED_BY]->(key)".to_string(),
+        "keys",
+        contract_keys,
+    )
+}
+
+fn instance_of_row(created: &CreatedEvent) -> Option<serde_json::Value> {
+    let id = created.template_id.as_ref()?;
+    Some(json!({
+        "contract_id": created.contract_id,
+        "template_id": format!("{}:{}:{}", id.package_id, id.module_name, id.entity_name),
+        "template_name": format!("{}.{}", id.module_name, id.entity_name),
+        "package_id": id.package_id,
+        "package_name": created.package_name
+    }))
+}
+
+/// The Template node of each template id seen, in the Package node of its package, which the
+/// package catalog completes, see `packages`
+fn instance_of_to_cypher(instance_of: Vec<serde_json::Value>) -> CypherQuery {
+    CypherQuery::unwind(
+        "UNWIND $templates AS t \
+        MATCH (c:Created { contract_id: t.contract_id }) \
+        MERGE (template:Template { template_id: t.template_id }) \
+        ON CREATE SET \
+        template.template_name = t.template_name, \
+        template.package_id = t.package_id \
+        MERGE (package:Package { package_id: t.package_id }) \
+        SET package.name = coalesce(package.name, t.package_name) \
+        MERGE (package)-[:DEFINES]->(template) \
+        MERGE (c)-[:INSTANCE_OF]->(template)".to_string(),
+        "templates",
+        instance_of,
+    )
+}
+
+fn implemented_interfaces_rows(created: &CreatedEvent, interface_views: &[serde_json::Value]) -> Vec<serde_json::Value> {
+    interface_views
+        .iter()
+        .map(|view| {
+            json!({
+                "contract_id": created.contract_id,
+                "interface_id": view["interface_id"],
+                "interface_name": view["interface_name"]
+            })
+        })
+        .collect()
+}
+
+fn implemented_interfaces_to_cypher(implemented_interfaces: Vec<serde_json::Value>) -> CypherQuery {
+    CypherQuery::unwind(
+        "UNWIND $interfaces AS i \
+        MATCH (c:Created { contract_id: i.contract_id }) \
+        MERGE (interface:Interface { interface_id: i.interface_id }) \
+        ON CREATE SET interface.interface_name = i.interface_name \
+        MERGE (c)-[:IMPLEMENTS]->(interface)".to_string(),
+        "interfaces",
+        implemented_interfaces,
+    )
+}
+
+/// Merges the `UNWIND` queries of the same cypher, e.g. of the updates of one Neo4j transaction,
+/// into one query over all their rows, to write each kind of node and relationship with a single
+/// statement. The merged queries which only MERGE nodes come first, in the order of their first
+/// query, then the ones MATCHing nodes: the nodes matched may be written by later updates of the
+/// batch than the first matching query. Other queries, like `acs_loaded_to_cypher`, are kept in
+/// place, queries are not merged across them.
+pub fn batch_unwind_queries(queries: impl IntoIterator<Item = CypherQuery>) -> Vec<CypherQuery> {
+    let mut batched = Vec::new();
+    // Queries since the last other query which only merge nodes and which match nodes, with
+    // whether rows of other queries were merged into them
+    let mut merging: Vec<(CypherQuery, bool)> = Vec::new();
+    let mut matching: Vec<(CypherQuery, bool)> = Vec::new();
+    let mut groups: HashMap<String, (bool, usize)> = HashMap::new();
+    let flush = |batched: &mut Vec<CypherQuery>, merging: &mut Vec<(CypherQuery, bool)>, matching: &mut Vec<(CypherQuery, bool)>| {
+        batched.extend(merging.drain(..).chain(matching.drain(..)).map(|(query, merged)| match query.rows {
+            Some((param, rows)) if merged => CypherQuery::unwind(query.cypher, &param, rows),
+            _ => query,
+        }));
+    };
+    for mut query in queries {
+        let Some((param, rows)) = query.rows.take() else {
+            flush(&mut batched, &mut merging, &mut matching);
+            groups.clear();
+            batched.push(query);
+            continue;
+        };
+        match groups.get(&query.cypher) {
+            Some(&(is_matching, index)) => {
+                let (group, merged) = if is_matching { &mut matching[index] } else { &mut merging[index] };
+                if let Some((_, group_rows)) = group.rows.as_mut() {
+                    group_rows.extend(rows);
+                }
+                *merged = true;
+            }
+            None => {
+                let is_matching = query.cypher.contains("MATCH");
+                let group = if is_matching { &mut matching } else { &mut merging };
+                groups.insert(query.cypher.clone(), (is_matching, group.len()));
+                query.rows = Some((param, rows));
+                group.push((query, false));
+            }
+        }
+    }
+    flush(&mut batched, &mut merging, &mut matching);
+    batched
+}
+
+/// The interface views of a created event as `{interface_id, interface_name, view}`, the view
+/// `null` if the ledger failed to compute it
+fn interface_views(created: &CreatedEvent) -> Vec<serde_json::Value> {
+    created
+        .interface_views
+        .iter()
+        .filter_map(|view| {
+            let id = view.interface_id.as_ref()?;
+            Some(json!({
+                "interface_id": format!("{}:{}:{}", id.package_id, id.module_name, id.entity_name),
+                "interface_name": format!("{}.{}", id.module_name, id.entity_name),
+                "view": view.view_value.as_ref().map(api_record_to_json)
+            }))
+        })
+        .collect()
+}
+
+/// The hash identifying the contract key of a created event, and the key as JSON. Keys are unique
+/// per template, so the hash is of the template name and the key JSON: the 64-bit FNV-1a hash as
+/// hex, which is stable across runs, unlike the hasher of the standard library.
+fn contract_key(registry: Option<&TemplateRegistry>, created: &CreatedEvent, template_name: &str) -> Option<(String, String)> {
+    let key_json = serde_json::to_string(&contract_key_json(registry, created)?).ok()?;
+    let hash = template_name
+        .bytes()
+        .chain(std::iter::once(0))
+        .chain(key_json.bytes())
+        .fold(0xcbf29ce484222325u64, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x100000001b3));
+    Some((format!("{:016x}", hash), key_json))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ledger_api::v2::value::Sum;
+    use ledger_api::v2::{ArchivedEvent, Event as LedgerEvent, ExercisedEvent, Identifier, InterfaceView, OffsetCheckpoint, Record, RecordField, Transaction, Value};
+
+    fn response(events: Vec<Event>) -> GetUpdatesResponse {
+        GetUpdatesResponse {
+            update: Some(Update::Transaction(Transaction {
+                offset: 7,
+                events: events.into_iter().map(|event| LedgerEvent { event: Some(event) }).collect(),
+                ..Default::default()
+            })),
+        }
+    }
+
+    #[test]
+    fn test_archiving_marks_contracts_inactive() {
+        let consuming = Event::Exercised(ExercisedEvent {
+            offset: 7,
+            contract_id: "c1".to_string(),
+            consuming: true,
+            ..Default::default()
+        });
+        let archived = Event::Archived(ArchivedEvent { offset: 7, contract_id: "c2".to_string(), ..Default::default() });
+
+        let queries = get_updates_response_to_cypher(&response(vec![consuming, archived]), None, &HashMap::new());
+        let archives: Vec<&CypherQuery> = queries.iter().filter(|q| q.cypher.contains("ARCHIVES")).collect();
+        assert_eq!(archives.len(), 2);
+        assert!(archives[0].cypher.contains("(e)-[:ARCHIVES]->(c)"));
+        assert!(archives[1].cypher.contains("(t)-[:ARCHIVES]->(c)"));
+        assert!(archives.iter().all(|q| q.cypher.contains("SET c.active = false, c.archived_at_offset = r.offset")));
+    }
+
+    #[test]
+    fn test_writes_are_idempotent() {
+        let created = Event::Created(CreatedEvent { offset: 7, contract_id: "c1".to_string(), ..Default::default() });
+        let consuming = Event::Exercised(ExercisedEvent {
+            offset: 7,
+            node_id: 1,
+            contract_id: "c1".to_string(),
+            consuming: true,
+            acting_parties: vec!["Alice".to_string()],
+            ..Default::default()
+        });
+        let archived = Event::Archived(ArchivedEvent { offset: 7, contract_id: "c2".to_string(), ..Default::default() });
+        let mut queries = get_updates_response_to_cypher(&response(vec![created, consuming, archived]), None, &HashMap::new());
+        queries.extend(created_event_to_cypher(&CreatedEvent::default(), "", None, &HashMap::new()));
+
+        // Replays MERGE on the natural keys instead of creating duplicates
+        for query in &queries {
+            let without_on_create = query.cypher.replace("ON CREATE", "");
+            assert!(!without_on_create.contains("CREATE"), "{}", query.cypher);
+            assert!(query.cypher.contains("MERGE"), "{}", query.cypher);
+        }
+    }
+
+    #[test]
+    fn test_updates_advance_sync_state() {
+        let queries = get_updates_response_to_cypher(&response(vec![]), None, &HashMap::new());
+        let sync_state = queries.last().unwrap();
+        assert!(sync_state.cypher.contains("MERGE (s:SyncState { id: 'sync' })"));
+
+        let checkpoint = GetUpdatesResponse {
+            update: Some(Update::OffsetCheckpoint(OffsetCheckpoint { offset: 9, synchronizer_times: vec![] })),
+        };
+        let queries = get_updates_response_to_cypher(&checkpoint, None, &HashMap::new());
+        assert_eq!(queries.len(), 1);
+        assert!(queries[0].cypher.contains("s.last_offset = CASE WHEN advanced THEN offset"));
+    }
+
+    #[test]
+    fn test_batch_unwind_queries() {
+        // The first transaction only consumes, the second one creates the contract consumed by the third
+        let consuming = |contract_id: &str| {
+            Event::Exercised(ExercisedEvent {
+                offset: 7,
+                contract_id: contract_id.to_string(),
+                consuming: true,
+                ..Default::default()
+            })
+        };
+        let created = Event::Created(CreatedEvent { offset: 7, contract_id: "c2".to_string(), ..Default::default() });
+        let updates = [vec![consuming("c1")], vec![created], vec![consuming("c2")]];
+        let queries: Vec<CypherQuery> = updates
+            .into_iter()
+            .flat_map(|events| get_updates_response_to_cypher(&response(events), None, &HashMap::new()))
+            .chain([acs_loaded_to_cypher(7)])
+            .collect();
+        let unbatched = queries.len();
+
+        let batched = batch_unwind_queries(queries);
+        assert!(batched.len() < unbatched);
+        let position = |fragment: &str| batched.iter().position(|q| q.cypher.contains(fragment)).unwrap();
+        // Nodes are merged before the relationships matching them
+        assert!(position("MERGE (c:Created") < position("MERGE (e)-[:CONSUMES]->(c)"));
+        let transactions = &batched[position("MERGE (t:Transaction")];
+        assert_eq!(transactions.rows.as_ref().unwrap().1.len(), 3);
+        assert_eq!(batched.iter().filter(|q| q.cypher.contains("SyncState")).count(), 2);
+        // Queries without rows stay in place
+        assert!(batched.last().unwrap().rows.is_none());
+    }
+
+    #[test]
+    fn test_batches_commit_in_any_order() {
+        let consuming = Event::Exercised(ExercisedEvent {
+            offset: 8,
+            contract_id: "c1".to_string(),
+            consuming: true,
+            ..Default::default()
+        });
+        let created = Event::Created(CreatedEvent { offset: 7, contract_id: "c1".to_string(), ..Default::default() });
+        let consumes = batch_unwind_queries(get_updates_response_to_cypher(&response(vec![consuming]), None, &HashMap::new()));
+        let creates = batch_unwind_queries(get_updates_response_to_cypher(&response(vec![created]), None, &HashMap::new()));
+
+        // Consuming a contract not written yet merges a placeholder, which the create completes
+        // without reactivating it
+        let consume = consumes.iter().find(|q| q.cypher.contains("[:CONSUMES]")).unwrap();
+        assert!(!consume.cypher.contains("MATCH (c:Created"));
+        assert!(consume.cypher.contains("MERGE (c:Created"));
+        let create = creates.iter().find(|q| q.cypher.contains("MERGE (c:Created")).unwrap();
+        assert!(create.cypher.contains("coalesce(c.active, true)"));
+
+        // The SyncState update can be held back from each batch
+        assert_eq!(consumes.iter().filter(|q| is_sync_state(q)).count(), 1);
+        assert!(consumes.iter().filter(|q| is_sync_state(q)).all(|q| q.cypher.contains("SyncState")));
+    }
+
+    #[test]
+    fn test_contracts_with_same_key_share_key_node() {
+        let created = |contract_id: &str| CreatedEvent {
+            contract_id: contract_id.to_string(),
+            template_id: Some(Identifier {
+                package_id: "abc".to_string(),
+                module_name: "Main".to_string(),
+                entity_name: "Account".to_string(),
+            }),
+            contract_key: Some(Value { sum: Some(Sum::Party("Alice".to_string())) }),
+            ..Default::default()
+        };
+        let first = contract_key(None, &created("c1"), "Main.Account").unwrap();
+        assert_eq!(first, contract_key(None, &created("c2"), "Main.Account").unwrap());
+        assert_ne!(first.0, contract_key(None, &created("c1"), "Main.Other").unwrap().0);
+        assert_eq!(contract_key(None, &CreatedEvent::default(), "Main.Account"), None);
+
+        let queries = created_event_to_cypher(&created("c1"), "", None, &HashMap::new());
+        assert!(queries[1].cypher.contains("MERGE (c)-[:KEYED_BY]->(key)"));
+    }
+
+    #[test]
+    fn test_interface_views() {
+        let created = CreatedEvent {
+            contract_id: "c1".to_string(),
+            interface_views: vec![InterfaceView {
+                interface_id: Some(Identifier {
+                    package_id: "abc".to_string(),
+                    module_name: "Interfaces".to_string(),
+                    entity_name: "Asset".to_string(),
+                }),
+                view_status: None,
+                view_value: Some(Record { record_id: None, fields: vec![] }),
+            }],
+            ..Default::default()
+        };
+        assert_eq!(
+            interface_views(&created),
+            vec![json!({ "interface_id": "abc:Interfaces:Asset", "interface_name": "Interfaces.Asset", "view": {} })]
+        );
+
+        let response = response(vec![Event::Created(created)]);
+        let queries = get_updates_response_to_cypher(&response, None, &HashMap::new());
+        assert!(queries.iter().any(|q| q.cypher.contains("MERGE (c)-[:IMPLEMENTS]->(interface)")));
+    }
+
+    #[test]
+    fn test_node_mapping_labels_and_properties() {
+        let created = Event::Created(CreatedEvent {
+            offset: 7,
+            contract_id: "c1".to_string(),
+            template_id: Some(Identifier {
+                package_id: "abc".to_string(),
+                module_name: "Splice.Amulet".to_string(),
+                entity_name: "Amulet".to_string(),
+            }),
+            ..Default::default()
+        });
+        let node_mapping = HashMap::from([(
+            "Splice.Amulet.Amulet".to_string(),
+            NodeMapping { label: Some("Amulet".to_string()), properties: vec!["owner".to_string(), "amount.initialAmount".to_string()] },
+        )]);
+        let queries = get_updates_response_to_cypher(&response(vec![created]), None, &node_mapping);
+        let labels = queries.iter().find(|q| q.cypher.contains("SET c:`Amulet`")).unwrap();
+        assert_eq!(labels.rows.as_ref().unwrap().1, vec![json!({ "contract_id": "c1" })]);
+
+        let payload = json!({ "owner": "Alice", "amount": { "initialAmount": "10.0", "ratePerRound": { "rate": "0.1" } } });
+        let mapping = NodeMapping {
+            label: None,
+            properties: vec!["amount.initialAmount".to_string(), "amount.ratePerRound".to_string(), "dso".to_string()],
+        };
+        assert_eq!(
+            promoted_properties(Some(&mapping), &payload),
+            json!({ "amount_initialAmount": "10.0", "amount_ratePerRound": "{\"rate\":\"0.1\"}", "dso": null })
+        );
+        assert_eq!(promoted_properties(None, &payload), json!({}));
+    }
+
+    #[test]
+    fn test_exercise_results_link_created_contracts() {
+        let contract_id = |contract_id: &str| Value { sum: Some(Sum::ContractId(contract_id.to_string())) };
+        let result = Value {
+            sum: Some(Sum::Record(Record {
+                record_id: None,
+                fields: vec![
+                    RecordField { label: "created".to_string(), value: Some(Value { sum: Some(Sum::List(ledger_api::v2::List { elements: vec![contract_id("c2"), contract_id("c3")] })) }) },
+                    RecordField { label: "change".to_string(), value: Some(contract_id("c4")) },
+                ],
+            })),
+        };
+        let exercised = Event::Exercised(ExercisedEvent {
+            offset: 7,
+            node_id: 0,
+            contract_id: "c1".to_string(),
+            exercise_result: Some(result),
+            ..Default::default()
+        });
+        let queries = get_updates_response_to_cypher(&response(vec![exercised]), None, &HashMap::new());
+        let results = queries.iter().find(|q| q.cypher.contains("MERGE (e)-[:RESULT_OF]->(c)")).unwrap();
+        let contract_ids: Vec<&str> = results.rows.as_ref().unwrap().1.iter().filter_map(|row| row["contract_id"].as_str()).collect();
+        assert_eq!(contract_ids, vec!["c2", "c3", "c4"]);
+    }
+
+    #[test]
+    fn test_contracts_are_instances_of_templates() {
+        let created = CreatedEvent {
+            contract_id: "c1".to_string(),
+            template_id: Some(Identifier {
+                package_id: "abc".to_string(),
+                module_name: "Main".to_string(),
+                entity_name: "Asset".to_string(),
+            }),
+            package_name: "my-package".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            instance_of_row(&created),
+            Some(json!({
+                "contract_id": "c1",
+                "template_id": "abc:Main:Asset",
+                "template_name": "Main.Asset",
+                "package_id": "abc",
+                "package_name": "my-package"
+            }))
+        );
+        assert_eq!(instance_of_row(&CreatedEvent::default()), None);
+
+        let queries = created_event_to_cypher(&created, "", None, &HashMap::new());
+        assert!(queries.iter().any(|q| q.cypher.contains("MERGE (c)-[:INSTANCE_OF]->(template)")));
+    }
+
+    #[test]
+    fn test_created_event_blobs_with_their_synchronizer() {
+        let created = CreatedEvent {
+            contract_id: "c1".to_string(),
+            created_event_blob: vec![1, 2, 3],
+            ..Default::default()
+        };
+        let queries = created_event_to_cypher(&created, "sync::1220", None, &HashMap::new());
+        let (_, rows) = queries[0].rows.as_ref().unwrap();
+        assert_eq!(rows[0]["created_event_blob"], json!("AQID"));
+        assert_eq!(rows[0]["synchronizer_id"], json!("sync::1220"));
+        assert!(queries[0].cypher.contains("c.created_event_blob = coalesce(p.created_event_blob, c.created_event_blob)"));
+        assert_eq!(created_event_blob(&CreatedEvent::default()), None);
+    }
+
+    #[test]
+    fn test_party_edges_of_signatories_observers_and_actors() {
+        let created = Event::Created(CreatedEvent {
+            offset: 7,
+            contract_id: "c1".to_string(),
+            signatories: vec!["Alice".to_string()],
+            observers: vec!["Bob".to_string(), "Carol".to_string()],
+            ..Default::default()
+        });
+        let exercised = Event::Exercised(ExercisedEvent {
+            offset: 7,
+            node_id: 1,
+            contract_id: "c1".to_string(),
+            acting_parties: vec!["Alice".to_string()],
+            ..Default::default()
+        });
+        let queries = get_updates_response_to_cypher(&response(vec![created, exercised]), None, &HashMap::new());
+        let rows = |rel: &str| {
+            let query = queries.iter().find(|q| q.cypher.contains(&format!("MERGE (party)-[:{}]->", rel))).unwrap();
+            query.rows.as_ref().unwrap().1.clone()
+        };
+        assert_eq!(rows("SIGNATORY"), vec![json!({ "party_id": "Alice", "contract_id": "c1" })]);
+        assert_eq!(rows("OBSERVER").len(), 2);
+        assert_eq!(rows("ACTED_BY"), vec![json!({ "party_id": "Alice", "offset": 7, "node_id": 1 })]);
+
+        let acs = created_event_to_cypher(
+            &CreatedEvent { contract_id: "c2".to_string(), signatories: vec!["Alice".to_string()], ..Default::default() },
+            "",
+            None,
+            &HashMap::new(),
+        );
+        assert!(acs.iter().any(|q| q.cypher.contains("MERGE (party)-[:SIGNATORY]->(c)")));
+        assert!(!acs.iter().any(|q| q.cypher.contains("OBSERVER")));
+    }
+
+    #[test]
+    fn test_queries_scoped_to_ledger() {
+        let query = sync_state_to_cypher(7, None);
+        assert_eq!(query.clone().scoped_to_ledger(None).cypher, query.cypher);
+        let scoped = query.scoped_to_ledger(Some("participant1"));
+        assert!(scoped.cypher.contains("MERGE (s:SyncState { ledger_id: $ledger_id, id: 'sync' })"));
+        assert!(scoped.query.has_param_key("ledger_id"));
+        assert!(scoped.query.has_param_key("sync_states"));
+        assert_eq!(scoped.rows.unwrap().1.len(), 1);
+
+        let scoped = CypherQuery::new("MATCH (t:Transaction) RETURN max(t.offset)".to_string()).scoped_to_ledger(Some("participant1"));
+        assert_eq!(scoped.cypher, "MATCH (t:Transaction { ledger_id: $ledger_id }) RETURN max(t.offset)");
+        // Labels after others, before WHERE or a line break, and backquoted, but not in literals
+        let scoped = |cypher: &str| CypherQuery::new(cypher.to_string()).scoped_to_ledger(Some("participant1")).cypher;
+        assert_eq!(
+            scoped("MATCH (c:Created:Asset WHERE c.active), (:`Party`\n    {party_id: 'Alice'}), (k:Key {}) RETURN ':Created)'"),
+            "MATCH (c:Created:Asset { ledger_id: $ledger_id } WHERE c.active), (:`Party`\n    { ledger_id: $ledger_id,party_id: 'Alice'}), \
+             (k:Key { ledger_id: $ledger_id }) RETURN ':Created)'"
+        );
+        assert_eq!(scoped("RETURN count(c), (c.offset)"), "RETURN count(c), (c.offset)");
+
+        // Every query of the sync
+        let created = Event::Created(CreatedEvent { offset: 7, contract_id: "c1".to_string(), ..Default::default() });
+        let consuming = Event::Exercised(ExercisedEvent {
+            offset: 7,
+            node_id: 1,
+            contract_id: "c1".to_string(),
+            consuming: true,
+            acting_parties: vec!["Alice".to_string()],
+            ..Default::default()
+        });
+        let archived = Event::Archived(ArchivedEvent { offset: 7, contract_id: "c2".to_string(), ..Default::default() });
+        let mut queries = get_updates_response_to_cypher(&response(vec![created, consuming, archived]), None, &HashMap::new());
+        queries.push(acs_loaded_to_cypher(7));
+        for query in batch_unwind_queries(queries) {
+            assert_scoped_to_ledger(&query.scoped_to_ledger(Some("participant1")).cypher);
+        }
+    }
+}
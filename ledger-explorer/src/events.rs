@@ -0,0 +1,124 @@
+//! Flattened records of the created, exercised and archived events of updates, written by the
+//! sinks other than Neo4j, see `sink`. Payloads are encoded like on the Created and Exercised
+//! nodes, by the template registry if there is one.
+use crate::template_registry::{contract_key_json, create_arguments_json, exercised_argument_json};
+use chrono::DateTime;
+use daml_type_rep::schema::TemplateRegistry;
+use ledger_api::v2::{GetUpdatesResponse, Identifier, event::Event, get_updates_response::Update};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventType {
+    Created,
+    Exercised,
+    Archived,
+}
+
+impl EventType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EventType::Created => "created",
+            EventType::Exercised => "exercised",
+            EventType::Archived => "archived",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EventRecord {
+    pub event_type: EventType,
+    pub offset: i64,
+    pub node_id: i32,
+    pub update_id: String,
+    pub command_id: String,
+    pub workflow_id: String,
+    /// RFC 3339 timestamps of the transaction
+    pub record_time: String,
+    pub effective_at: String,
+    pub contract_id: String,
+    /// `<package id>:<Module.Name>:<Entity>`
+    pub template_id: String,
+    /// `<Module.Name>.<Entity>`
+    pub template_name: String,
+    /// The choice of exercised events
+    pub choice: Option<String>,
+    pub consuming: Option<bool>,
+    /// Signatories of created events, acting parties of exercised events
+    pub parties: Vec<String>,
+    pub witness_parties: Vec<String>,
+    /// Create arguments of created events, choice argument of exercised events
+    pub payload: serde_json::Value,
+    /// The contract key of created events of templates with key
+    pub contract_key: Option<serde_json::Value>,
+}
+
+/// The offset of the update, `None` without update
+pub fn update_offset(response: &GetUpdatesResponse) -> Option<i64> {
+    response.update.as_ref().map(|update| match update {
+        Update::Transaction(tx) => tx.offset,
+        Update::Reassignment(r) => r.offset,
+        Update::OffsetCheckpoint(c) => c.offset,
+        Update::TopologyTransaction(t) => t.offset,
+    })
+}
+
+/// The events of a transaction in node order, none for other updates
+pub fn event_records(response: &GetUpdatesResponse, registry: Option<&TemplateRegistry>) -> Vec<EventRecord> {
+    let Some(Update::Transaction(transaction)) = &response.update else {
+        return Vec::new();
+    };
+    let timestamp = |ts: Option<&prost_types::Timestamp>| {
+        ts.and_then(|ts| DateTime::from_timestamp(ts.seconds, ts.nanos as u32))
+            .map(|dt| dt.format("%Y-%m-%dT%H:%M:%SZ").to_string())
+            .unwrap_or_default()
+    };
+    let record = |event_type, node_id, contract_id: &str, template_id: Option<&Identifier>| EventRecord {
+        event_type,
+        offset: transaction.offset,
+        node_id,
+        update_id: transaction.update_id.clone(),
+        command_id: transaction.command_id.clone(),
+        workflow_id: transaction.workflow_id.clone(),
+        record_time: timestamp(transaction.record_time.as_ref()),
+        effective_at: timestamp(transaction.effective_at.as_ref()),
+        contract_id: contract_id.to_string(),
+        template_id: template_id
+            .map(|id| format!("{}:{}:{}", id.package_id, id.module_name, id.entity_name))
+            .unwrap_or_default(),
+        template_name: template_id
+            .map(|id| format!("{}.{}", id.module_name, id.entity_name))
+            .unwrap_or_else(|| "unknown".to_string()),
+        choice: None,
+        consuming: None,
+        parties: Vec::new(),
+        witness_parties: Vec::new(),
+        payload: serde_json::Value::Null,
+        contract_key: None,
+    };
+    transaction
+        .events
+        .iter()
+        .filter_map(|event| match event.event.as_ref()? {
+            Event::Created(created) => Some(EventRecord {
+                parties: created.signatories.clone(),
+                witness_parties: created.witness_parties.clone(),
+                payload: create_arguments_json(registry, created),
+                contract_key: contract_key_json(registry, created),
+                ..record(EventType::Created, created.node_id, &created.contract_id, created.template_id.as_ref())
+            }),
+            Event::Exercised(exercised) => Some(EventRecord {
+                choice: Some(exercised.choice.clone()),
+                consuming: Some(exercised.consuming),
+                parties: exercised.acting_parties.clone(),
+                witness_parties: exercised.witness_parties.clone(),
+                payload: exercised_argument_json(registry, exercised),
+                ..record(EventType::Exercised, exercised.node_id, &exercised.contract_id, exercised.template_id.as_ref())
+            }),
+            Event::Archived(archived) => Some(EventRecord {
+                witness_parties: archived.witness_parties.clone(),
+                ..record(EventType::Archived, archived.node_id, &archived.contract_id, archived.template_id.as_ref())
+            }),
+        })
+        .collect()
+}
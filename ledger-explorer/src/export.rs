@@ -0,0 +1,196 @@
+//! The file sink of the `export` command: the events of the ledger as flattened CSV rows, in a
+//! file per event type in a `date=<YYYY-MM-DD>` directory per record date, the Hive partitioning
+//! read by DuckDB and Spark. List fields, payloads and keys are JSON.
+use crate::events::EventRecord;
+use crate::sink::EventBatch;
+use crate::sync::Sink;
+use anyhow::{Context, Result};
+use client::active_contracts::ActiveContract;
+use ledger_api::v2::GetUpdatesResponse;
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+const COLUMNS: [&str; 17] = [
+    "event_type",
+    "offset",
+    "node_id",
+    "update_id",
+    "command_id",
+    "workflow_id",
+    "record_time",
+    "effective_at",
+    "contract_id",
+    "template_id",
+    "template_name",
+    "choice",
+    "consuming",
+    "parties",
+    "witness_parties",
+    "payload",
+    "contract_key",
+];
+
+pub struct CsvSink {
+    directory: PathBuf,
+    batch: EventBatch,
+}
+
+impl CsvSink {
+    pub fn new(directory: impl Into<PathBuf>, batch: EventBatch) -> Self {
+        Self { directory: directory.into(), batch }
+    }
+
+    /// Appends the events to the files of their date and event type
+    fn write(&self, events: &[EventRecord]) -> Result<()> {
+        let mut files: BTreeMap<PathBuf, Vec<&EventRecord>> = BTreeMap::new();
+        for event in events {
+            files.entry(self.path(event)).or_default().push(event);
+        }
+        for (path, events) in files {
+            Self::append(&path, &events)?;
+        }
+        Ok(())
+    }
+
+    /// The file of the event, by the date of its record time
+    fn path(&self, event: &EventRecord) -> PathBuf {
+        let date = event.record_time.get(..10).filter(|date| !date.is_empty()).unwrap_or("unknown");
+        self.directory.join(format!("date={}", date)).join(format!("{}.csv", event.event_type.as_str()))
+    }
+
+    /// Appends the rows, with a header to new files, and syncs the file to disk
+    fn append(path: &Path, events: &[&EventRecord]) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).with_context(|| format!("failed to create '{}'", parent.display()))?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("failed to open '{}'", path.display()))?;
+        let is_new = file.metadata()?.len() == 0;
+        let mut writer = BufWriter::new(file);
+        if is_new {
+            writeln!(writer, "{}", COLUMNS.join(","))?;
+        }
+        for event in events {
+            writeln!(writer, "{}", csv_row(event)?)?;
+        }
+        let file: File = writer.into_inner().map_err(|e| e.into_error())?;
+        file.sync_all().with_context(|| format!("failed to sync '{}'", path.display()))
+    }
+}
+
+impl Sink for CsvSink {
+    fn name(&self) -> &str {
+        "CSV"
+    }
+
+    async fn acs_loaded(&mut self) -> Result<bool> {
+        Ok(true)
+    }
+
+    async fn load_acs_batch(&mut self, _acs_offset: i64, _contracts: Vec<ActiveContract>, _complete: bool) -> Result<()> {
+        Ok(())
+    }
+
+    fn apply_update(&mut self, response: &GetUpdatesResponse) -> Result<()> {
+        self.batch.apply_update(response);
+        Ok(())
+    }
+
+    /// Appends the events of the batch, checkpointing its offset once the files are synced
+    async fn flush(&mut self) -> Result<()> {
+        let Some((offset, events)) = self.batch.take() else {
+            return Ok(());
+        };
+        self.write(&events)?;
+        self.batch.checkpoint(offset)
+    }
+
+    async fn last_offset(&mut self) -> Result<Option<i64>> {
+        self.batch.last_offset()
+    }
+}
+
+/// The event as a CSV row of `COLUMNS`
+fn csv_row(event: &EventRecord) -> Result<String> {
+    let fields = [
+        event.event_type.as_str().to_string(),
+        event.offset.to_string(),
+        event.node_id.to_string(),
+        event.update_id.clone(),
+        event.command_id.clone(),
+        event.workflow_id.clone(),
+        event.record_time.clone(),
+        event.effective_at.clone(),
+        event.contract_id.clone(),
+        event.template_id.clone(),
+        event.template_name.clone(),
+        event.choice.clone().unwrap_or_default(),
+        event.consuming.map(|consuming| consuming.to_string()).unwrap_or_default(),
+        serde_json::to_string(&event.parties)?,
+        serde_json::to_string(&event.witness_parties)?,
+        serde_json::to_string(&event.payload)?,
+        event.contract_key.as_ref().map(serde_json::to_string).transpose()?.unwrap_or_default(),
+    ];
+    Ok(fields.iter().map(|field| csv_field(field)).collect::<Vec<_>>().join(","))
+}
+
+/// Quotes fields with separators, quotes or line breaks, doubling the quotes, as in RFC 4180
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::EventType;
+
+    #[test]
+    fn test_csv_partitions_by_date_and_event_type() -> Result<()> {
+        let directory = std::env::temp_dir().join(format!("ledger-explorer-export-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&directory);
+        let event = |event_type, offset, record_time: &str| EventRecord {
+            event_type,
+            offset,
+            node_id: 0,
+            update_id: format!("u{}", offset),
+            command_id: String::new(),
+            workflow_id: String::new(),
+            record_time: record_time.to_string(),
+            effective_at: record_time.to_string(),
+            contract_id: "c1".to_string(),
+            template_id: "abc:Main:Asset".to_string(),
+            template_name: "Main.Asset".to_string(),
+            choice: None,
+            consuming: None,
+            parties: vec!["Alice".to_string()],
+            witness_parties: vec![],
+            payload: serde_json::json!({ "note": "a, \"b\"" }),
+            contract_key: None,
+        };
+        let sink = CsvSink::new(&directory, EventBatch::new(directory.join("export.offset"), None));
+        sink.write(&[
+            event(EventType::Created, 1, "2026-10-13T23:59:59Z"),
+            event(EventType::Created, 2, "2026-10-14T00:00:00Z"),
+        ])?;
+        sink.write(&[event(EventType::Created, 3, "2026-10-14T00:00:01Z")])?;
+
+        let csv = std::fs::read_to_string(directory.join("date=2026-10-14").join("created.csv"))?;
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], COLUMNS.join(","));
+        assert!(lines[1].starts_with("created,2,0,u2,"));
+        assert!(lines[1].ends_with(r#","[""Alice""]",[],"{""note"":""a, \""b\""""}","#));
+        assert!(directory.join("date=2026-10-13").join("created.csv").exists());
+        std::fs::remove_dir_all(&directory)?;
+        Ok(())
+    }
+}
@@ -1,17 +1,41 @@
-use futures_util::Stream;
 use tracing::{debug, info, warn, error};
-use neo4rs::{Graph, query};
-use std::time::{Duration, Instant};
-use tokio_stream::StreamExt;
-use anyhow::Result;
+use neo4rs::{ConfigBuilder, Graph, query};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+use tokio::sync::{Mutex, mpsc};
+use tokio::task::JoinHandle;
+use anyhow::{Context, Result, anyhow};
+use daml_type_rep::schema::TemplateRegistry;
+use client::active_contracts::ActiveContract;
+use ledger_api::v2::GetUpdatesResponse;
+use serde_json::json;
 
 pub use crate::cypher::CypherQuery;
+use crate::cypher::{
+    acs_loaded_to_cypher, batch_unwind_queries, created_event_to_cypher, get_updates_response_to_cypher, is_sync_state,
+    sync_state_offset, transaction_count,
+};
+use crate::config::NodeMapping;
+use crate::metrics::METRICS;
+use crate::telemetry::{Span, SpanExporter};
+use crate::webhooks::Notifier;
+use crate::sync::{Sink, SyncConfig};
 
 const MAX_COMMIT_RETRIES: u32 = 3;
 const INITIAL_RETRY_DELAY: Duration = Duration::from_millis(100);
 
+/// Connects to the database, the default database of the user if `None`
+pub fn connect_graph(uri: &str, user: &str, password: &str, database: Option<&str>) -> Result<Graph> {
+    let mut config = ConfigBuilder::default().uri(uri).user(user).password(password);
+    if let Some(database) = database {
+        config = config.db(database);
+    }
+    Ok(Graph::connect(config.build()?)?)
+}
+
 /// Commits a batch of queries to Neo4j with retry logic for transient errors (e.g., deadlocks).
-async fn commit_with_retry(graph: &Graph, queries: Vec<neo4rs::Query>) -> std::result::Result<(), Box<dyn std::error::Error>> {
+async fn commit_with_retry(graph: &Graph, queries: Vec<neo4rs::Query>) -> Result<()> {
     let mut delay = INITIAL_RETRY_DELAY;
     for attempt in 1..=MAX_COMMIT_RETRIES {
         let mut txn = graph.start_txn().await?;
@@ -23,7 +47,7 @@ async fn commit_with_retry(graph: &Graph, queries: Vec<neo4rs::Query>) -> std::r
                     tokio::time::sleep(delay).await;
                     delay *= 2;
                 }
-                Err(e) => return Err(Box::new(e)),
+                Err(e) => return Err(e.into()),
             },
             Err(e) if is_transient_error(&e) && attempt < MAX_COMMIT_RETRIES => {
                 warn!("Transient Neo4j error on run_queries (attempt {}/{}): {}. Retrying in {:?}", attempt, MAX_COMMIT_RETRIES, e, delay);
@@ -31,11 +55,11 @@ async fn commit_with_retry(graph: &Graph, queries: Vec<neo4rs::Query>) -> std::r
                 tokio::time::sleep(delay).await;
                 delay *= 2;
             }
-            Err(e) => return Err(Box::new(e)),
+            Err(e) => return Err(e.into()),
         }
     }
     error!("Exhausted all {} retries for Neo4j commit", MAX_COMMIT_RETRIES);
-    Err("Exhausted all retries for Neo4j commit".into())
+    Err(anyhow!("Exhausted all retries for Neo4j commit"))
 }
 
 /// Checks if a Neo4j error is transient and worth retrying.
@@ -45,181 +69,653 @@ fn is_transient_error(e: &neo4rs::Error) -> bool {
         || msg.contains("TransientError")
 }
 
-/// Queries Neo4j for the maximum offset stored in the graph.
-/// This is used to determine where to resume processing after a restart.
-pub async fn get_last_processed_offset(uri: &str, user: &str, pass: &str) -> Result<Option<i64>> {
-    debug!("Connecting to Neo4j at {} to query last offset", uri);
-    let graph = Graph::new(uri, user, pass)?;
+/// The offset of the SyncState node of the ledger. Graphs synced before SyncState was introduced
+/// fall back to the maximum Transaction offset (uses the transaction_offset index).
+pub async fn last_offset(graph: &Graph, ledger_id: Option<&str>) -> Result<Option<i64>> {
+    let sync_state = CypherQuery::new("MATCH (s:SyncState { id: 'sync' }) RETURN s.last_offset as last_offset".to_string());
+    let mut result = graph.execute(sync_state.scoped_to_ledger(ledger_id).query).await?;
+    if let Some(row) = result.next().await?
+        && let Some(offset) = row.get::<Option<i64>>("last_offset")?
+    {
+        return Ok(Some(offset));
+    }
+    let max_offset = CypherQuery::new("MATCH (t:Transaction) RETURN max(t.offset) as max_offset".to_string());
+    let mut result = graph.execute(max_offset.scoped_to_ledger(ledger_id).query).await?;
+    match result.next().await? {
+        Some(row) => Ok(row.get::<Option<i64>>("max_offset")?),
+        None => Ok(None),
+    }
+}
+
+/// The natural keys MERGEd on: (constraint name, label, key property)
+const NATURAL_KEYS: [(&str, &str, &str); 8] = [
+    ("transaction_offset", "Transaction", "offset"),
+    ("created_contract_id", "Created", "contract_id"),
+    ("key_hash", "Key", "key_hash"),
+    ("interface_id", "Interface", "interface_id"),
+    ("template_id", "Template", "template_id"),
+    ("package_id", "Package", "package_id"),
+    ("party_id", "Party", "party_id"),
+    ("sync_state_id", "SyncState", "id"),
+];
+
+/// Ensures required indexes exist in Neo4j for optimal query performance.
+/// Creates indexes if they don't exist (idempotent).
+///
+/// The natural keys MERGEd on get uniqueness constraints, so that replays and concurrent writers
+/// never duplicate nodes. Graphs synced before replace their plain index on the key; if the
+/// constraint cannot be created, e.g. because of duplicates written earlier, the plain index is
+/// kept. Returns whether all the constraints exist.
+///
+/// In a graph shared by several ledgers the keys are unique per ledger, the constraints are on
+/// the `ledger_id` and the key, replacing the constraints on the key alone.
+async fn ensure_indexes(graph: &Graph, ledger_scoped: bool) -> Result<bool> {
+    info!("Ensuring Neo4j indexes exist...");
+
+    let mut constrained = true;
+    for (name, label, property) in NATURAL_KEYS {
+        // The constraint on the key alone, or the per ledger one, replaced
+        let replaced = if ledger_scoped {
+            format!("DROP CONSTRAINT {}_unique IF EXISTS", name)
+        } else {
+            format!("DROP CONSTRAINT {}_per_ledger_unique IF EXISTS", name)
+        };
+        let constraint_query = if ledger_scoped {
+            format!(
+                "CREATE CONSTRAINT {}_per_ledger_unique IF NOT EXISTS FOR (n:{}) REQUIRE (n.ledger_id, n.{}) IS UNIQUE",
+                name, label, property
+            )
+        } else {
+            format!("CREATE CONSTRAINT {}_unique IF NOT EXISTS FOR (n:{}) REQUIRE n.{} IS UNIQUE", name, label, property)
+        };
+        let drop_index_query = format!("DROP INDEX {} IF EXISTS", name);
+        let index_query = format!("CREATE INDEX {} IF NOT EXISTS FOR (n:{}) ON (n.{})", name, label, property);
+        for drop_query in [&replaced, &drop_index_query] {
+            if let Err(e) = graph.run(query(drop_query)).await {
+                warn!("Failed to drop index replaced by constraint: {} - {}", drop_query, e);
+            }
+        }
+        match graph.run(query(&constraint_query)).await {
+            Ok(_) => debug!("Constraint ensured: {}", constraint_query),
+            Err(e) => {
+                constrained = false;
+                warn!("Failed to create constraint (duplicate nodes?), keeping plain index: {} - {}", constraint_query, e);
+                if let Err(e) = graph.run(query(&index_query)).await {
+                    warn!("Failed to create index: {} - {}", index_query, e);
+                }
+            }
+        }
+    }
+
+    let indexes = [
+        "CREATE INDEX created_offset_node IF NOT EXISTS FOR (c:Created) ON (c.offset, c.node_id)",
+        "CREATE INDEX created_offset IF NOT EXISTS FOR (c:Created) ON (c.offset)",
+        "CREATE INDEX created_template_name IF NOT EXISTS FOR (c:Created) ON (c.template_name)",
+        "CREATE INDEX created_active IF NOT EXISTS FOR (c:Created) ON (c.active)",
+        "CREATE INDEX exercised_offset_node IF NOT EXISTS FOR (e:Exercised) ON (e.offset, e.node_id)",
+        "CREATE INDEX exercised_choice_name IF NOT EXISTS FOR (e:Exercised) ON (e.choice_name)",
+        "CREATE INDEX transaction_id_idx IF NOT EXISTS FOR (t:Transaction) ON (t.transaction_id)",
+    ];
+
+    for index_query in &indexes {
+        match graph.run(query(*index_query)).await {
+            Ok(_) => debug!("Index ensured: {}", index_query),
+            Err(e) => warn!("Failed to create index (may already exist): {} - {}", index_query, e),
+        }
+    }
+
+    info!("Neo4j indexes ready");
+    Ok(constrained)
+}
+
+/// Checks if the ACS of the ledger has already been loaded into Neo4j, by the SyncState node, or
+/// for graphs synced before SyncState was introduced, the presence of any from_acs=true nodes.
+pub async fn is_acs_loaded(graph: &Graph, ledger_id: Option<&str>) -> Result<bool> {
+    let sync_state =
+        CypherQuery::new("MATCH (s:SyncState { id: 'sync' }) RETURN coalesce(s.acs_loaded, false) as acs_loaded".to_string());
+    let mut result = graph.execute(sync_state.scoped_to_ledger(ledger_id).query).await?;
+    if let Some(row) = result.next().await?
+        && row.get::<bool>("acs_loaded")?
+    {
+        return Ok(true);
+    }
+    let from_acs = CypherQuery::new("MATCH (c:Created {from_acs: true}) RETURN count(c) as count LIMIT 1".to_string());
+    let mut result = graph.execute(from_acs.scoped_to_ledger(ledger_id).query).await?;
 
-    // Use Transaction label to leverage the transaction_offset index (fast)
-    let mut result = graph.execute(query("MATCH (t:Transaction) RETURN max(t.offset) as max_offset")).await?;
     match result.next().await {
         Ok(Some(row)) => {
-            let offset = row.get::<Option<i64>>("max_offset")?;
-            info!("Last processed offset from Neo4j: {:?}", offset);
-            Ok(offset)
-        }
-        Ok(None) => {
-            info!("No offset found in Neo4j (empty database)");
-            Ok(None)
-        }
-        Err(e) => {
-            warn!("Failed to query last offset from Neo4j: {}", e);
-            Err(e.into())
-        }
-    }
-}
-
-pub async fn apply_cypher_vec_stream_to_neo4j<S>(
-    uri: &str,
-    user: &str,
-    pass: &str,
-    mut query_stream: S,
-    batch_size: usize,
-    flush_timeout_secs: u64,
-    idle_timeout_secs: u64,
-) -> Result<(Option<i64>, Option<i64>, u128), Box<dyn std::error::Error>>
-where
-    S: Stream<Item = Vec<CypherQuery>> + Unpin,
-{
-    let flush_timeout = Duration::from_secs(flush_timeout_secs);
-    info!("Connecting to Neo4j at {}", uri);
-    let graph = Graph::new(uri, user, pass)?;
-    debug!(uri = %uri, user = %user, "Successfully connected to Neo4j");
-
-    // Query max offset before update
-    debug!("Querying max offset before update");
-    let before_offset = {
-        let mut result = graph.execute(query("MATCH (t:Transaction) RETURN max(t.offset) as max_offset")).await?;
-        match result.next().await {
-            Ok(Some(row)) => row.get::<Option<i64>>("max_offset")?,
-            Ok(None) => None,
-            Err(e) => return Err(Box::new(e)),
+            let count: i64 = row.get("count")?;
+            Ok(count > 0)
         }
-    };
-    info!("Max offset before update: {:?}", before_offset);
-
-    // Measure update time
-    let start_time = Instant::now();
-    info!("Starting to process query stream (batch_size={}, flush_timeout={}s)", batch_size, flush_timeout_secs);
-
-    // Batch multiple updates together for better Neo4j throughput
-    let idle_timeout = Duration::from_secs(idle_timeout_secs);
-    let mut batch_count = 0u64;
-    let mut pending_queries: Vec<neo4rs::Query> = Vec::new();
-    let mut updates_in_batch = 0usize;
-    let mut batch_start_time: Option<Instant> = None;
-    let mut last_update_time = Instant::now();
-
-    loop {
-        // Calculate remaining time until flush timeout
-        let timeout_remaining = batch_start_time
-            .map(|start| flush_timeout.saturating_sub(start.elapsed()))
-            .unwrap_or(flush_timeout);
-
-        // Wait for next update with timeout
-        let next_update = tokio::time::timeout(timeout_remaining, query_stream.next()).await;
-
-        match next_update {
-            Ok(Some(cypher_vec)) => {
-                last_update_time = Instant::now();
-                // Received an update
-                batch_count += 1;
-                let query_count = cypher_vec.len();
-                debug!(batch = batch_count, query_count = query_count, "Received update");
-
-                // Accumulate queries
-                let query_count_this_update = cypher_vec.len();
-                pending_queries.extend(cypher_vec.into_iter().map(|cq| cq.query));
-                updates_in_batch += 1;
-
-                // Start batch timer on first update
-                if batch_start_time.is_none() {
-                    batch_start_time = Some(Instant::now());
-                }
+        Ok(None) => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
 
-                if updates_in_batch == 1 {
-                    info!("First update received, {} queries", query_count_this_update);
-                }
+/// Clears all data from Neo4j database, or in a graph shared by several ledgers the nodes of the
+/// ledger.
+pub async fn clear_neo4j_database(graph: &Graph, ledger_id: Option<&str>) -> Result<()> {
+    info!("Clearing Neo4j database...");
+
+    // Use APOC for efficient deletion if available, otherwise fall back to batched delete
+    let delete_result = match ledger_id {
+        Some(ledger_id) => {
+            graph
+                .run(
+                    query(
+                        "CALL apoc.periodic.iterate('MATCH (n) WHERE n.ledger_id = $ledger_id RETURN n', 'DETACH DELETE n', \
+                        {batchSize: 10000, params: {ledger_id: $ledger_id}})",
+                    )
+                    .param("ledger_id", ledger_id),
+                )
+                .await
+        }
+        None => graph.run(query("CALL apoc.periodic.iterate('MATCH (n) RETURN n', 'DETACH DELETE n', {batchSize: 10000})")).await,
+    };
 
-                // Commit when batch is full
-                if updates_in_batch >= batch_size {
-                    let total_queries = pending_queries.len();
-                    info!("Starting batch commit (full): {} updates, {} queries", updates_in_batch, total_queries);
-                    let commit_start = Instant::now();
-                    commit_with_retry(&graph, pending_queries).await?;
-                    let commit_time = commit_start.elapsed();
-                    info!("Committed batch of {} updates ({} queries) in {:?} ({} total updates)",
-                          updates_in_batch, total_queries, commit_time, batch_count);
-                    pending_queries = Vec::new();
-                    updates_in_batch = 0;
-                    batch_start_time = None;
+    match delete_result {
+        Ok(_) => {
+            info!("Database cleared using APOC");
+        }
+        Err(_) => {
+            // Fall back to regular delete (may be slow for large datasets)
+            warn!("APOC not available, using standard delete (may be slow)");
+            match ledger_id {
+                Some(ledger_id) => {
+                    graph.run(query("MATCH (n) WHERE n.ledger_id = $ledger_id DETACH DELETE n").param("ledger_id", ledger_id)).await?
                 }
+                None => graph.run(query("MATCH (n) DETACH DELETE n")).await?,
             }
-            Ok(None) => {
-                // Stream ended
-                break;
+            info!("Database cleared using standard delete");
+        }
+    }
+
+    Ok(())
+}
+
+/// The Neo4j backend of the sync, see `sync::Sink`: updates are turned into Cypher, and their
+/// batches committed by a `WriterPool` while the next batch is generated. The resume point is the
+/// offset of the SyncState node.
+pub struct Neo4jSink {
+    graph: Graph,
+    writer_concurrency: usize,
+    template_registry: Option<Arc<TemplateRegistry>>,
+    node_mapping: HashMap<String, NodeMapping>,
+    /// The ledger the nodes are scoped to in a graph shared by several ledgers
+    ledger_id: Option<String>,
+    flow_control: FlowControl,
+    /// Whether updates advance the SyncState, not for backfills of earlier offsets
+    sync_state: bool,
+    notifier: Option<Notifier>,
+    exporter: Option<SpanExporter>,
+    pending_queries: Vec<CypherQuery>,
+    pending_updates: usize,
+    /// The spans of the transactions of the pending updates, with the export of spans
+    pending_spans: Vec<Span>,
+    /// Started by the first flush of each stream
+    writers: Option<WriterPool>,
+}
+
+impl Neo4jSink {
+    /// Connects to Neo4j, clearing the database first if `fresh`, and ensures the indexes exist
+    pub async fn connect(config: &SyncConfig, fresh: bool) -> Result<Self> {
+        info!("Connecting to Neo4j at {}, database {}", config.neo4j_uri, config.neo4j_database.as_deref().unwrap_or("(default)"));
+        let graph = connect_graph(&config.neo4j_uri, &config.neo4j_user, &config.neo4j_pass, config.neo4j_database.as_deref())?;
+        if fresh {
+            clear_neo4j_database(&graph, config.ledger_id.as_deref()).await?;
+        }
+
+        let constrained = ensure_indexes(&graph, config.ledger_id.is_some()).await?;
+        let mut writer_concurrency = config.writer_concurrency;
+        // Concurrent writers rely on the uniqueness constraints not to duplicate shared nodes
+        if !constrained && writer_concurrency > 1 {
+            warn!(
+                "Uniqueness constraints missing, committing batches one at a time instead of writer_concurrency={}",
+                writer_concurrency
+            );
+            writer_concurrency = 1;
+        }
+        let flow_control = FlowControl {
+            max_in_flight: config.max_in_flight_batches.unwrap_or(2 * writer_concurrency).max(1),
+            lag_target: config.lag_target_secs.map(Duration::from_secs),
+        };
+        info!("Neo4j writers: {}, at most {} batches in flight", writer_concurrency, flow_control.max_in_flight);
+
+        Ok(Self {
+            graph,
+            writer_concurrency,
+            template_registry: config.template_registry.clone(),
+            node_mapping: config.node_mapping.clone(),
+            ledger_id: config.ledger_id.clone(),
+            flow_control,
+            sync_state: true,
+            notifier: Notifier::start(config.webhooks.clone(), config.template_registry.clone()),
+            exporter: SpanExporter::start(&config.opentelemetry),
+            pending_queries: Vec::new(),
+            pending_updates: 0,
+            pending_spans: Vec::new(),
+            writers: None,
+        })
+    }
+}
+
+impl Neo4jSink {
+    pub fn graph(&self) -> &Graph {
+        &self.graph
+    }
+
+    /// The writers of the current stream, started by its first batch
+    fn writers(&mut self) -> &mut WriterPool {
+        self.writers.get_or_insert_with(|| {
+            WriterPool::start(
+                self.graph.clone(),
+                self.writer_concurrency,
+                self.ledger_id.clone(),
+                self.flow_control,
+                self.exporter.clone(),
+            )
+        })
+    }
+
+    /// The sink writing the updates without advancing the SyncState, for replays of offsets
+    /// which may lie beyond the resume point
+    pub fn without_sync_state(mut self) -> Self {
+        self.sync_state = false;
+        self
+    }
+}
+
+impl Sink for Neo4jSink {
+    fn name(&self) -> &str {
+        "Neo4j"
+    }
+
+    async fn acs_loaded(&mut self) -> Result<bool> {
+        is_acs_loaded(&self.graph, self.ledger_id.as_deref()).await
+    }
+
+    async fn load_acs_batch(&mut self, acs_offset: i64, contracts: Vec<ActiveContract>, complete: bool) -> Result<()> {
+        let mut queries: Vec<CypherQuery> = contracts
+            .iter()
+            .flat_map(|contract| {
+                created_event_to_cypher(
+                    &contract.created_event,
+                    &contract.synchronizer_id,
+                    self.template_registry.as_deref(),
+                    &self.node_mapping,
+                )
+            })
+            .collect();
+        if !complete {
+            // Committed by the writers while the next batch is read, like the batches of updates
+            let result = self.writers().submit(queries, contracts.len(), Vec::new()).await;
+            if result.is_err() {
+                self.writers = None;
             }
-            Err(_) => {
-                // Check for idle timeout (stale/dead stream detection)
-                if last_update_time.elapsed() >= idle_timeout {
-                    warn!(
-                        "No updates received for {}s (idle_timeout={}s), stream appears stale. Triggering reconnect.",
-                        last_update_time.elapsed().as_secs(),
-                        idle_timeout_secs,
-                    );
-                    // Flush any pending queries before returning
-                    if !pending_queries.is_empty() {
-                        let queries_to_flush: Vec<neo4rs::Query> = pending_queries.drain(..).collect();
-                        commit_with_retry(&graph, queries_to_flush).await?;
-                        info!("Flushed {} pending queries before idle disconnect", updates_in_batch);
+            return result;
+        }
+        // The last batch, with the ACS offset on the SyncState node, once all others are committed
+        self.finish().await?;
+        queries.push(acs_loaded_to_cypher(acs_offset));
+        let mut txn = self.graph.start_txn().await?;
+        let queries = batch_unwind_queries(queries).into_iter().map(|cq| cq.scoped_to_ledger(self.ledger_id.as_deref()).query);
+        txn.run_queries(queries).await?;
+        txn.commit().await?;
+        Ok(())
+    }
+
+    fn apply_update(&mut self, response: &GetUpdatesResponse) -> Result<()> {
+        let mut queries = get_updates_response_to_cypher(response, self.template_registry.as_deref(), &self.node_mapping);
+        if !self.sync_state {
+            queries.retain(|query| !is_sync_state(query));
+        }
+        if let Some(notifier) = &self.notifier {
+            notifier.notify(response);
+        }
+        if self.exporter.is_some() {
+            self.pending_spans.extend(Span::transaction(response, self.ledger_id.as_deref()));
+        }
+        debug!(query_count = queries.len(), "Received update");
+        self.pending_queries.extend(queries);
+        self.pending_updates += 1;
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> Result<()> {
+        let queries = std::mem::take(&mut self.pending_queries);
+        let updates = std::mem::take(&mut self.pending_updates);
+        let spans = std::mem::take(&mut self.pending_spans);
+        let writers = self.writers();
+        let result = if queries.is_empty() {
+            // Advance the resume point past the batches committed meanwhile
+            writers.process_completed().await
+        } else {
+            info!("Submitting batch: {} updates, {} queries", updates, queries.len());
+            writers.submit(queries, updates, spans).await
+        };
+        if result.is_err() {
+            self.writers = None;
+        }
+        result
+    }
+
+    async fn finish(&mut self) -> Result<()> {
+        match self.writers.take() {
+            Some(writers) => writers.finish().await,
+            None => Ok(()),
+        }
+    }
+
+    async fn last_offset(&mut self) -> Result<Option<i64>> {
+        last_offset(&self.graph, self.ledger_id.as_deref()).await
+    }
+
+    async fn clear(&mut self) -> Result<()> {
+        clear_neo4j_database(&self.graph, self.ledger_id.as_deref()).await
+    }
+}
+
+/// Bounds of the batches submitted to the writers and not yet committed, so that the stream and
+/// the ACS are read no faster than Neo4j commits them when its writes slow down, instead of
+/// piling up batches in memory
+#[derive(Debug, Clone, Copy)]
+struct FlowControl {
+    /// Number of batches in flight beyond which the next one waits
+    max_in_flight: usize,
+    /// How long the oldest batch in flight may wait to be committed before the next one waits
+    /// for it, `None` bounded by `max_in_flight` only
+    lag_target: Option<Duration>,
+}
+
+impl FlowControl {
+    /// Whether the next batch waits for a batch to be committed, with `oldest` the time the
+    /// oldest batch in flight was submitted for
+    fn throttles(&self, in_flight: usize, oldest: Option<Duration>) -> bool {
+        in_flight >= self.max_in_flight
+            || self.lag_target.zip(oldest).is_some_and(|(lag_target, oldest)| oldest > lag_target)
+    }
+}
+
+/// Ends the spans of the transactions of a batch with the span of its commit, linking them to
+/// each other, and exports them
+fn export_spans(
+    exporter: &SpanExporter,
+    mut spans: Vec<Span>,
+    commit_started_at: SystemTime,
+    attributes: Vec<(&'static str, serde_json::Value)>,
+    error: Option<&String>,
+) {
+    let mut commit = Span::neo4j_batch(commit_started_at, attributes);
+    commit.links = spans.iter().map(|span| span.context.clone()).collect();
+    commit.end(error.map(String::as_str));
+    for span in &mut spans {
+        span.links.push(commit.context.clone());
+        span.end(error.map(String::as_str));
+    }
+    exporter.export(spans.into_iter().chain(std::iter::once(commit)));
+}
+
+/// The queries a writer commits for a batch of whole ledger transactions, and with concurrent
+/// writers the SyncState queries held back from them. A single writer commits the SyncState in
+/// the Neo4j transaction of the batch, so that a crash can neither leave a ledger transaction
+/// half applied nor the resume point ahead of the data; concurrent writers leave it behind the
+/// data until all earlier batches are committed, and a restart replays the batches after it.
+fn split_sync_state(queries: Vec<CypherQuery>, concurrent: bool) -> (Vec<CypherQuery>, Option<Vec<CypherQuery>>) {
+    if !concurrent {
+        return (queries, None);
+    }
+    let (sync_states, others): (Vec<_>, Vec<_>) = queries.into_iter().partition(is_sync_state);
+    (others, Some(sync_states))
+}
+
+/// A batch of updates, or of contracts of the ACS, for the writers, numbered in stream order
+struct WriteBatch {
+    seq: u64,
+    queries: Vec<neo4rs::Query>,
+    updates: usize,
+    transactions: usize,
+    /// The offset the batch advances the SyncState to, if it updates it
+    last_offset: Option<i64>,
+    /// The spans of its transactions, ended with the commit
+    spans: Vec<Span>,
+}
+
+/// Writer tasks committing batches of updates, each in its own Neo4j transaction, fed through a
+/// channel, so that the stream is decoded and its Cypher generated while batches are written.
+/// Batches are submitted while the `FlowControl` allows, else once earlier ones are committed.
+///
+/// A single writer commits the batches in stream order, each with its SyncState update.
+/// Concurrent writers commit batches of non-overlapping offset ranges in any order, Neo4j
+/// serializing their writes of the same nodes on the uniqueness constraints (deadlocks between
+/// them are retried). The SyncState updates are then held back and committed once all earlier
+/// batches are, so that the resume point never skips a batch which is not committed.
+struct WriterPool {
+    graph: Graph,
+    ledger_id: Option<String>,
+    concurrent: bool,
+    batches: Option<mpsc::Sender<WriteBatch>>,
+    completed: mpsc::UnboundedReceiver<(u64, std::result::Result<(), String>)>,
+    writers: Vec<JoinHandle<()>>,
+    next_seq: u64,
+    flow_control: FlowControl,
+    /// When the batches in flight were submitted
+    in_flight: BTreeMap<u64, Instant>,
+    /// With concurrent writers, the SyncState queries of the batches submitted and not yet
+    /// committed in order, with the offset they advance to
+    sync_states: BTreeMap<u64, (Vec<neo4rs::Query>, Option<i64>)>,
+    /// Committed batches after the first one not committed
+    committed: BTreeSet<u64>,
+    next_in_order: u64,
+}
+
+impl WriterPool {
+    fn start(
+        graph: Graph,
+        concurrency: usize,
+        ledger_id: Option<String>,
+        flow_control: FlowControl,
+        exporter: Option<SpanExporter>,
+    ) -> Self {
+        let concurrency = concurrency.max(1);
+        let (batches, receiver) = mpsc::channel::<WriteBatch>(flow_control.max_in_flight);
+        let receiver = Arc::new(Mutex::new(receiver));
+        let (completed_sender, completed) = mpsc::unbounded_channel();
+        let writers = (0..concurrency)
+            .map(|writer| {
+                let graph = graph.clone();
+                let receiver = Arc::clone(&receiver);
+                let completed_sender = completed_sender.clone();
+                let exporter = exporter.clone();
+                tokio::spawn(async move {
+                    loop {
+                        let batch = receiver.lock().await.recv().await;
+                        let Some(batch) = batch else { break };
+                        let commit_start = Instant::now();
+                        let commit_started_at = SystemTime::now();
+                        let query_count = batch.queries.len();
+                        let result = commit_with_retry(&graph, batch.queries).await.map_err(|e| format!("{:#}", e));
+                        if let Some(exporter) = &exporter {
+                            let attributes = vec![
+                                ("neo4j.writer", json!(writer)),
+                                ("neo4j.batch", json!(batch.seq)),
+                                ("neo4j.updates", json!(batch.updates)),
+                                ("neo4j.queries", json!(query_count)),
+                            ];
+                            export_spans(exporter, batch.spans, commit_started_at, attributes, result.as_ref().err());
+                        }
+                        if result.is_ok() {
+                            METRICS.observe_write_latency(commit_start.elapsed());
+                            METRICS.add_transactions(batch.transactions as u64);
+                            // With concurrent writers, the SyncState is advanced separately
+                            if let Some(offset) = batch.last_offset {
+                                METRICS.set_last_processed_offset(offset);
+                            }
+                            info!(
+                                "Writer {} committed batch {} of {} updates ({} queries) in {:?}",
+                                writer, batch.seq, batch.updates, query_count, commit_start.elapsed()
+                            );
+                        }
+                        if completed_sender.send((batch.seq, result)).is_err() {
+                            break;
+                        }
                     }
-                    break;
-                }
+                })
+            })
+            .collect();
+        Self {
+            graph,
+            ledger_id,
+            concurrent: concurrency > 1,
+            batches: Some(batches),
+            completed,
+            writers,
+            next_seq: 0,
+            flow_control,
+            in_flight: BTreeMap::new(),
+            sync_states: BTreeMap::new(),
+            committed: BTreeSet::new(),
+            next_in_order: 0,
+        }
+    }
 
-                // Timeout - flush partial batch if any
-                if !pending_queries.is_empty() {
-                    let total_queries = pending_queries.len();
-                    info!("Starting batch commit (timeout): {} updates, {} queries", updates_in_batch, total_queries);
-                    let commit_start = Instant::now();
-                    commit_with_retry(&graph, pending_queries).await?;
-                    let commit_time = commit_start.elapsed();
-                    info!("Committed batch of {} updates ({} queries) in {:?} ({} total updates)",
-                          updates_in_batch, total_queries, commit_time, batch_count);
-                    pending_queries = Vec::new();
-                    updates_in_batch = 0;
-                    batch_start_time = None;
-                }
+    /// Queues the batch, once the flow control allows
+    async fn submit(&mut self, queries: Vec<CypherQuery>, updates: usize, spans: Vec<Span>) -> Result<()> {
+        self.process_completed().await?;
+        self.throttle().await?;
+        let queries: Vec<CypherQuery> = batch_unwind_queries(queries)
+            .into_iter()
+            .map(|cq| cq.scoped_to_ledger(self.ledger_id.as_deref()))
+            .collect();
+        let transactions = queries.iter().map(transaction_count).sum();
+        let mut last_offset = queries.iter().filter_map(sync_state_offset).max();
+        let (queries, sync_states) = split_sync_state(queries, self.concurrent);
+        if let Some(sync_states) = sync_states {
+            self.sync_states.insert(self.next_seq, (sync_states.into_iter().map(|cq| cq.query).collect(), last_offset.take()));
+        }
+        let batch = WriteBatch {
+            seq: self.next_seq,
+            queries: queries.into_iter().map(|cq| cq.query).collect(),
+            updates,
+            transactions,
+            last_offset,
+            spans,
+        };
+        self.in_flight.insert(self.next_seq, Instant::now());
+        METRICS.set_in_flight_batches(self.in_flight.len());
+        self.next_seq += 1;
+        let batches = self.batches.as_ref().context("Writer pool finished")?;
+        batches.send(batch).await.map_err(|_| anyhow!("Neo4j writers stopped"))?;
+        Ok(())
+    }
+
+    /// Handles the batches committed so far, failing if a writer failed
+    async fn process_completed(&mut self) -> Result<()> {
+        while let Ok(completion) = self.completed.try_recv() {
+            self.complete(completion).await?;
+        }
+        Ok(())
+    }
+
+    /// Waits for batches to be committed while the flow control holds back the next one
+    async fn throttle(&mut self) -> Result<()> {
+        let mut throttled = false;
+        while self.flow_control.throttles(self.in_flight.len(), self.oldest_in_flight()) {
+            if !throttled {
+                debug!(
+                    "Waiting for Neo4j: {} batches in flight, the oldest for {:?}",
+                    self.in_flight.len(),
+                    self.oldest_in_flight().unwrap_or_default()
+                );
+                throttled = true;
             }
+            let completion = self.completed.recv().await.context("Neo4j writers stopped")?;
+            self.complete(completion).await?;
         }
+        Ok(())
     }
 
-    // Commit any remaining queries
-    if !pending_queries.is_empty() {
-        debug!(updates = updates_in_batch, queries = pending_queries.len(), "Committing final batch");
-        commit_with_retry(&graph, pending_queries).await?;
-        info!("Committed final batch of {} updates", updates_in_batch);
+    /// How long the oldest batch in flight was submitted for
+    fn oldest_in_flight(&self) -> Option<Duration> {
+        self.in_flight.values().next().map(|submitted| submitted.elapsed())
     }
 
-    let update_time_ms = start_time.elapsed().as_millis();
-    info!("Processed {} batches in {} ms", batch_count, update_time_ms);
+    /// Waits for all submitted batches to be committed
+    async fn finish(mut self) -> Result<()> {
+        self.batches = None;
+        while !self.in_flight.is_empty() {
+            let completion = self.completed.recv().await.context("Neo4j writers stopped")?;
+            self.complete(completion).await?;
+        }
+        for writer in self.writers.drain(..) {
+            writer.await?;
+        }
+        Ok(())
+    }
 
-    // Query max offset after update
-    debug!("Querying max offset after update");
-    let after_offset = {
-        let mut result = graph.execute(query("MATCH (t:Transaction) RETURN max(t.offset) as max_offset")).await?;
-        match result.next().await {
-            Ok(Some(row)) => row.get::<Option<i64>>("max_offset")?,
-            Ok(None) => None,
-            Err(e) => return Err(Box::new(e)),
+    async fn complete(&mut self, (seq, result): (u64, std::result::Result<(), String>)) -> Result<()> {
+        self.in_flight.remove(&seq);
+        METRICS.set_in_flight_batches(self.in_flight.len());
+        if let Err(e) = result {
+            error!("Writer failed to commit batch {}: {}", seq, e);
+            return Err(anyhow!(e));
         }
-    };
-    info!("Max offset after update: {:?}", after_offset);
+        if !self.concurrent {
+            return Ok(());
+        }
+        self.committed.insert(seq);
+        let mut sync_states = Vec::new();
+        let mut last_offset = None;
+        while self.committed.remove(&self.next_in_order) {
+            let (queries, offset) = self.sync_states.remove(&self.next_in_order).unwrap_or_default();
+            sync_states.extend(queries);
+            last_offset = last_offset.max(offset);
+            self.next_in_order += 1;
+        }
+        if !sync_states.is_empty() {
+            let commit_start = Instant::now();
+            commit_with_retry(&self.graph, sync_states).await?;
+            METRICS.observe_write_latency(commit_start.elapsed());
+            if let Some(offset) = last_offset {
+                METRICS.set_last_processed_offset(offset);
+            }
+            debug!("Advanced SyncState past batch {}", self.next_in_order - 1);
+        }
+        Ok(())
+    }
+}
 
-    info!(
-        "Neo4j update complete: offset {:?} -> {:?}, {} batches in {} ms",
-        before_offset, after_offset, batch_count, update_time_ms
-    );
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    Ok((before_offset, after_offset, update_time_ms))
+    #[test]
+    fn test_flow_control_throttles_on_in_flight_batches_and_lag() {
+        let unbounded_lag = FlowControl { max_in_flight: 2, lag_target: None };
+        assert!(!unbounded_lag.throttles(1, Some(Duration::from_secs(600))));
+        assert!(unbounded_lag.throttles(2, Some(Duration::ZERO)));
+
+        let lag_target = FlowControl { max_in_flight: 4, lag_target: Some(Duration::from_secs(5)) };
+        assert!(!lag_target.throttles(0, None));
+        assert!(!lag_target.throttles(3, Some(Duration::from_secs(1))));
+        assert!(lag_target.throttles(1, Some(Duration::from_secs(6))));
+    }
+
+    #[test]
+    fn test_single_writer_commits_sync_state_with_its_batch() {
+        let batch = || {
+            batch_unwind_queries([
+                CypherQuery::new("MERGE (p:Party { party_id: 'Alice' })".to_string()),
+                crate::cypher::sync_state_to_cypher(7, Some("u7")),
+                crate::cypher::sync_state_to_cypher(8, Some("u8")),
+            ])
+        };
+        let (queries, held_back) = split_sync_state(batch(), false);
+        assert!(held_back.is_none());
+        assert_eq!(queries.iter().filter(|q| is_sync_state(q)).count(), 1);
+        assert_eq!(queries.iter().filter_map(sync_state_offset).max(), Some(8));
+
+        let (queries, held_back) = split_sync_state(batch(), true);
+        assert!(!queries.iter().any(is_sync_state));
+        assert_eq!(held_back.unwrap().iter().filter_map(sync_state_offset).max(), Some(8));
+    }
 }
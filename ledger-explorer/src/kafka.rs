@@ -0,0 +1,568 @@
+//! The Kafka sink of the `publish` command: events as JSON `EventRecord`s, keyed by contract id
+//! or offset, to a topic per event type. Published by a minimal producer speaking the Kafka
+//! protocol: Metadata v4 to find the partition leaders, and Produce v3 of uncompressed record
+//! batches acknowledged by all in-sync replicas. Records with the same key go to the same
+//! partition, chosen by the murmur2 hash of the Java client's default partitioner.
+use crate::config::{KafkaConfig, KafkaKey};
+use crate::events::EventRecord;
+use crate::sink::EventBatch;
+use crate::sync::Sink;
+use anyhow::{Context, Result, anyhow, bail};
+use client::active_contracts::ActiveContract;
+use ledger_api::v2::GetUpdatesResponse;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tracing::{debug, info, warn};
+
+const PRODUCE: (i16, i16) = (0, 3);
+const METADATA: (i16, i16) = (3, 4);
+
+/// Record batches are kept below the default `message.max.bytes` of brokers, 1 MB
+const MAX_BATCH_BYTES: usize = 900_000;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+/// Attempts of a produce request, refreshing the metadata in between
+const PRODUCE_ATTEMPTS: usize = 3;
+
+/// Error codes after which the metadata is refreshed and the request retried
+const RETRIABLE_ERRORS: [i16; 6] = [
+    3,  // UNKNOWN_TOPIC_OR_PARTITION
+    5,  // LEADER_NOT_AVAILABLE
+    6,  // NOT_LEADER_OR_FOLLOWER
+    7,  // REQUEST_TIMED_OUT
+    19, // NOT_ENOUGH_REPLICAS
+    20, // NOT_ENOUGH_REPLICAS_AFTER_APPEND
+];
+
+pub struct KafkaSink {
+    producer: KafkaProducer,
+    config: KafkaConfig,
+    batch: EventBatch,
+}
+
+impl KafkaSink {
+    pub async fn connect(config: KafkaConfig, batch: EventBatch) -> Result<Self> {
+        let mut producer = KafkaProducer::new(config.bootstrap_servers.clone(), config.client_id.clone());
+        let topics: BTreeSet<String> = config.all_topics().into_iter().map(str::to_string).collect();
+        producer.refresh_metadata(&topics).await?;
+        info!("Connected to Kafka, publishing to {:?}", topics);
+        Ok(Self { producer, config, batch })
+    }
+
+    fn record(&self, event: &EventRecord) -> Result<KafkaRecord> {
+        let key = match self.config.key {
+            KafkaKey::ContractId => event.contract_id.clone(),
+            KafkaKey::Offset => event.offset.to_string(),
+        };
+        Ok(KafkaRecord {
+            topic: self.config.topic(event.event_type).to_string(),
+            key: Some(key.into_bytes()),
+            value: serde_json::to_vec(event)?,
+        })
+    }
+}
+
+impl Sink for KafkaSink {
+    fn name(&self) -> &str {
+        "Kafka"
+    }
+
+    async fn acs_loaded(&mut self) -> Result<bool> {
+        Ok(true)
+    }
+
+    async fn load_acs_batch(&mut self, _acs_offset: i64, _contracts: Vec<ActiveContract>, _complete: bool) -> Result<()> {
+        Ok(())
+    }
+
+    fn apply_update(&mut self, response: &GetUpdatesResponse) -> Result<()> {
+        self.batch.apply_update(response);
+        Ok(())
+    }
+
+    /// Publishes the events of the batch, checkpointing its offset once they are acknowledged
+    async fn flush(&mut self) -> Result<()> {
+        let Some((offset, events)) = self.batch.take() else {
+            return Ok(());
+        };
+        if !events.is_empty() {
+            let records = events.iter().map(|event| self.record(event)).collect::<Result<Vec<_>>>()?;
+            self.producer.send(&records).await?;
+        }
+        self.batch.checkpoint(offset)
+    }
+
+    async fn last_offset(&mut self) -> Result<Option<i64>> {
+        self.batch.last_offset()
+    }
+}
+
+pub struct KafkaRecord {
+    pub topic: String,
+    pub key: Option<Vec<u8>>,
+    pub value: Vec<u8>,
+}
+
+pub struct KafkaProducer {
+    bootstrap_servers: Vec<String>,
+    client_id: String,
+    correlation_id: i32,
+    /// Addresses of the brokers by node id
+    brokers: HashMap<i32, String>,
+    connections: HashMap<i32, TcpStream>,
+    /// Node ids of the leaders of the partitions of topics, by partition index
+    leaders: HashMap<String, Vec<i32>>,
+}
+
+impl KafkaProducer {
+    pub fn new(bootstrap_servers: Vec<String>, client_id: String) -> Self {
+        Self {
+            bootstrap_servers,
+            client_id,
+            correlation_id: 0,
+            brokers: HashMap::new(),
+            connections: HashMap::new(),
+            leaders: HashMap::new(),
+        }
+    }
+
+    /// Sends the records, in order within each partition, once all in-sync replicas have them
+    pub async fn send(&mut self, records: &[KafkaRecord]) -> Result<()> {
+        let topics: BTreeSet<String> = records.iter().map(|record| record.topic.clone()).collect();
+        if topics.iter().any(|topic| !self.leaders.contains_key(topic)) {
+            self.refresh_metadata(&topics).await?;
+        }
+
+        let mut partitions: BTreeMap<(&str, i32), Vec<&KafkaRecord>> = BTreeMap::new();
+        for record in records {
+            let partition_count = self.leaders.get(&record.topic).map_or(0, Vec::len);
+            if partition_count == 0 {
+                bail!("Kafka topic '{}' has no partitions", record.topic);
+            }
+            let partition = partition(record.key.as_deref(), partition_count);
+            partitions.entry((record.topic.as_str(), partition)).or_default().push(record);
+        }
+
+        let timestamp_ms = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis() as i64;
+        for ((topic, partition), records) in partitions {
+            for chunk in chunks_by_size(&records) {
+                let batch = encode_record_batch(chunk, timestamp_ms);
+                self.produce(topic, partition, &batch, &topics).await?;
+            }
+            debug!("Published {} records to {}/{}", records.len(), topic, partition);
+        }
+        Ok(())
+    }
+
+    async fn produce(&mut self, topic: &str, partition: i32, batch: &[u8], topics: &BTreeSet<String>) -> Result<()> {
+        let mut body = Vec::new();
+        put_i16(&mut body, -1); // no transactional id
+        put_i16(&mut body, -1); // acks from all in-sync replicas
+        put_i32(&mut body, REQUEST_TIMEOUT.as_millis() as i32);
+        put_i32(&mut body, 1);
+        put_string(&mut body, topic);
+        put_i32(&mut body, 1);
+        put_i32(&mut body, partition);
+        put_i32(&mut body, batch.len() as i32);
+        body.extend_from_slice(batch);
+
+        for attempt in 1..=PRODUCE_ATTEMPTS {
+            let leader = self
+                .leaders
+                .get(topic)
+                .and_then(|leaders| leaders.get(partition as usize))
+                .copied()
+                .with_context(|| format!("no partition {} of Kafka topic '{}'", partition, topic))?;
+            let error_code = match self.request(leader, PRODUCE, &body).await {
+                Ok(response) => produce_error_code(&response, topic, partition)?,
+                Err(e) if attempt < PRODUCE_ATTEMPTS => {
+                    warn!("Kafka produce to {}/{} failed (attempt {}): {:#}", topic, partition, attempt, e);
+                    self.connections.remove(&leader);
+                    self.refresh_metadata(topics).await?;
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+            match error_code {
+                0 => return Ok(()),
+                code if RETRIABLE_ERRORS.contains(&code) && attempt < PRODUCE_ATTEMPTS => {
+                    warn!("Kafka produce to {}/{} failed with error code {} (attempt {})", topic, partition, code, attempt);
+                    tokio::time::sleep(Duration::from_millis(500 * attempt as u64)).await;
+                    self.refresh_metadata(topics).await?;
+                }
+                code => bail!("Kafka produce to {}/{} failed with error code {}", topic, partition, code),
+            }
+        }
+        bail!("Kafka produce to {}/{} failed after {} attempts", topic, partition, PRODUCE_ATTEMPTS)
+    }
+
+    /// Queries the brokers and the partition leaders of the topics from a bootstrap server
+    pub async fn refresh_metadata(&mut self, topics: &BTreeSet<String>) -> Result<()> {
+        let mut body = Vec::new();
+        put_i32(&mut body, topics.len() as i32);
+        for topic in topics {
+            put_string(&mut body, topic);
+        }
+        body.push(0); // allow_auto_topic_creation = false
+
+        let mut last_error = anyhow!("no Kafka bootstrap servers");
+        for server in self.bootstrap_servers.clone() {
+            let response = match connect(&server).await {
+                Ok(mut stream) => {
+                    self.correlation_id += 1;
+                    request(&mut stream, METADATA, self.correlation_id, &self.client_id, &body).await
+                }
+                Err(e) => Err(e),
+            };
+            match response.and_then(|response| parse_metadata(&response)) {
+                Ok(Metadata { brokers, leaders }) => {
+                    for topic in topics {
+                        match leaders.get(topic) {
+                            Some(partitions) if partitions.iter().all(|leader| *leader >= 0) => {}
+                            Some(_) => bail!("Kafka topic '{}' has partitions without leader", topic),
+                            None => bail!("Kafka topic '{}' does not exist", topic),
+                        }
+                    }
+                    // Connections to brokers which moved are reopened
+                    self.connections.retain(|node, _| brokers.get(node) == self.brokers.get(node));
+                    self.brokers = brokers;
+                    self.leaders.extend(leaders);
+                    return Ok(());
+                }
+                Err(e) => {
+                    warn!("Failed to get Kafka metadata from {}: {:#}", server, e);
+                    last_error = e;
+                }
+            }
+        }
+        Err(last_error)
+    }
+
+    async fn request(&mut self, node: i32, api: (i16, i16), body: &[u8]) -> Result<Vec<u8>> {
+        if !self.connections.contains_key(&node) {
+            let address = self.brokers.get(&node).with_context(|| format!("unknown Kafka broker {}", node))?;
+            let stream = connect(address).await?;
+            self.connections.insert(node, stream);
+        }
+        self.correlation_id += 1;
+        let correlation_id = self.correlation_id;
+        let stream = self.connections.get_mut(&node).context("Kafka connection closed")?;
+        let response = request(stream, api, correlation_id, &self.client_id, body).await;
+        if response.is_err() {
+            self.connections.remove(&node);
+        }
+        response
+    }
+}
+
+async fn connect(address: &str) -> Result<TcpStream> {
+    tokio::time::timeout(REQUEST_TIMEOUT, TcpStream::connect(address))
+        .await
+        .with_context(|| format!("timed out connecting to Kafka broker {}", address))?
+        .with_context(|| format!("failed to connect to Kafka broker {}", address))
+}
+
+/// Sends a request with header v1 and returns the response after its header v0
+async fn request(stream: &mut TcpStream, (api_key, api_version): (i16, i16), correlation_id: i32, client_id: &str, body: &[u8]) -> Result<Vec<u8>> {
+    let mut message = Vec::with_capacity(body.len() + 64);
+    put_i32(&mut message, 0); // size, set below
+    put_i16(&mut message, api_key);
+    put_i16(&mut message, api_version);
+    put_i32(&mut message, correlation_id);
+    put_string(&mut message, client_id);
+    message.extend_from_slice(body);
+    let size = (message.len() - 4) as i32;
+    message[..4].copy_from_slice(&size.to_be_bytes());
+
+    let exchange = async {
+        stream.write_all(&message).await?;
+        let size = stream.read_i32().await?;
+        let mut response = vec![0u8; usize::try_from(size)?];
+        stream.read_exact(&mut response).await?;
+        anyhow::Ok(response)
+    };
+    let response = tokio::time::timeout(REQUEST_TIMEOUT, exchange).await.context("Kafka request timed out")??;
+    let mut reader = Reader::new(&response);
+    if reader.i32()? != correlation_id {
+        bail!("Kafka response to another request");
+    }
+    Ok(response[4..].to_vec())
+}
+
+/// A Metadata v4 response, see `KafkaProducer`
+struct Metadata {
+    brokers: HashMap<i32, String>,
+    leaders: HashMap<String, Vec<i32>>,
+}
+
+fn parse_metadata(response: &[u8]) -> Result<Metadata> {
+    let mut reader = Reader::new(response);
+    reader.i32()?; // throttle_time_ms
+    let mut brokers = HashMap::new();
+    for _ in 0..reader.i32()? {
+        let node_id = reader.i32()?;
+        let host = reader.string()?;
+        let port = reader.i32()?;
+        reader.nullable_string()?; // rack
+        brokers.insert(node_id, format!("{}:{}", host, port));
+    }
+    reader.nullable_string()?; // cluster_id
+    reader.i32()?; // controller_id
+    let mut leaders = HashMap::new();
+    for _ in 0..reader.i32()? {
+        let error_code = reader.i16()?;
+        let name = reader.string()?;
+        reader.i8()?; // is_internal
+        let mut partitions = Vec::new();
+        for _ in 0..reader.i32()? {
+            reader.i16()?; // error_code
+            let index = reader.i32()?;
+            let leader = reader.i32()?;
+            reader.i32_array()?; // replica_nodes
+            reader.i32_array()?; // isr_nodes
+            partitions.push((index, leader));
+        }
+        if error_code == 0 {
+            partitions.sort();
+            leaders.insert(name, partitions.into_iter().map(|(_, leader)| leader).collect());
+        }
+    }
+    Ok(Metadata { brokers, leaders })
+}
+
+/// The error code of the partition in a Produce v3 response
+fn produce_error_code(response: &[u8], topic: &str, partition: i32) -> Result<i16> {
+    let mut reader = Reader::new(response);
+    for _ in 0..reader.i32()? {
+        let name = reader.string()?;
+        for _ in 0..reader.i32()? {
+            let index = reader.i32()?;
+            let error_code = reader.i16()?;
+            reader.i64()?; // base_offset
+            reader.i64()?; // log_append_time_ms
+            if name == topic && index == partition {
+                return Ok(error_code);
+            }
+        }
+    }
+    bail!("Kafka produce response without {}/{}", topic, partition)
+}
+
+/// The partition of a record, by the murmur2 hash of its key like the Java client, the first
+/// partition without key
+fn partition(key: Option<&[u8]>, partition_count: usize) -> i32 {
+    key.map_or(0, |key| ((murmur2(key) & 0x7fff_ffff) as usize % partition_count) as i32)
+}
+
+fn murmur2(data: &[u8]) -> u32 {
+    const M: u32 = 0x5bd1_e995;
+    let mut h: u32 = 0x9747_b28c ^ data.len() as u32;
+    let mut chunks = data.chunks_exact(4);
+    for chunk in &mut chunks {
+        let mut k = u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+        k = k.wrapping_mul(M);
+        k ^= k >> 24;
+        k = k.wrapping_mul(M);
+        h = h.wrapping_mul(M);
+        h ^= k;
+    }
+    let rest = chunks.remainder();
+    if !rest.is_empty() {
+        for (i, byte) in rest.iter().enumerate().rev() {
+            h ^= (*byte as u32) << (8 * i);
+        }
+        h = h.wrapping_mul(M);
+    }
+    h ^= h >> 13;
+    h = h.wrapping_mul(M);
+    h ^= h >> 15;
+    h
+}
+
+/// The records split into runs whose record batches stay below `MAX_BATCH_BYTES`
+fn chunks_by_size<'a, 'r>(records: &'a [&'r KafkaRecord]) -> Vec<&'a [&'r KafkaRecord]> {
+    let mut chunks = Vec::new();
+    let (mut start, mut size) = (0, 0);
+    for (i, record) in records.iter().enumerate() {
+        let record_size = record.key.as_ref().map_or(0, Vec::len) + record.value.len() + 32;
+        if i > start && size + record_size > MAX_BATCH_BYTES {
+            chunks.push(&records[start..i]);
+            (start, size) = (i, 0);
+        }
+        size += record_size;
+    }
+    if start < records.len() {
+        chunks.push(&records[start..]);
+    }
+    chunks
+}
+
+/// A record batch of magic 2, without compression, producer id or headers
+fn encode_record_batch(records: &[&KafkaRecord], timestamp_ms: i64) -> Vec<u8> {
+    // From the attributes on, the part covered by the CRC
+    let mut body = Vec::new();
+    put_i16(&mut body, 0); // attributes
+    put_i32(&mut body, records.len() as i32 - 1); // last_offset_delta
+    put_i64(&mut body, timestamp_ms); // base_timestamp
+    put_i64(&mut body, timestamp_ms); // max_timestamp
+    put_i64(&mut body, -1); // producer_id
+    put_i16(&mut body, -1); // producer_epoch
+    put_i32(&mut body, -1); // base_sequence
+    put_i32(&mut body, records.len() as i32);
+    for (offset_delta, record) in records.iter().enumerate() {
+        let mut encoded = vec![0]; // attributes
+        put_varint(&mut encoded, 0); // timestamp_delta
+        put_varint(&mut encoded, offset_delta as i64);
+        match &record.key {
+            Some(key) => {
+                put_varint(&mut encoded, key.len() as i64);
+                encoded.extend_from_slice(key);
+            }
+            None => put_varint(&mut encoded, -1),
+        }
+        put_varint(&mut encoded, record.value.len() as i64);
+        encoded.extend_from_slice(&record.value);
+        put_varint(&mut encoded, 0); // headers
+        put_varint(&mut body, encoded.len() as i64);
+        body.extend_from_slice(&encoded);
+    }
+
+    let mut batch = Vec::with_capacity(body.len() + 21);
+    put_i64(&mut batch, 0); // base_offset, assigned by the broker
+    put_i32(&mut batch, (4 + 1 + 4 + body.len()) as i32); // batch_length
+    put_i32(&mut batch, -1); // partition_leader_epoch
+    batch.push(2); // magic
+    batch.extend_from_slice(&crc32c(&body).to_be_bytes());
+    batch.extend_from_slice(&body);
+    batch
+}
+
+fn crc32c(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ 0x82f6_3b78 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+fn put_i16(buf: &mut Vec<u8>, value: i16) {
+    buf.extend_from_slice(&value.to_be_bytes());
+}
+
+fn put_i32(buf: &mut Vec<u8>, value: i32) {
+    buf.extend_from_slice(&value.to_be_bytes());
+}
+
+fn put_i64(buf: &mut Vec<u8>, value: i64) {
+    buf.extend_from_slice(&value.to_be_bytes());
+}
+
+fn put_string(buf: &mut Vec<u8>, value: &str) {
+    put_i16(buf, value.len() as i16);
+    buf.extend_from_slice(value.as_bytes());
+}
+
+/// A zigzag varint, as in record batches
+fn put_varint(buf: &mut Vec<u8>, value: i64) {
+    let mut zigzag = ((value << 1) ^ (value >> 63)) as u64;
+    while zigzag >= 0x80 {
+        buf.push((zigzag as u8) | 0x80);
+        zigzag >>= 7;
+    }
+    buf.push(zigzag as u8);
+}
+
+struct Reader<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn new(buf: &'a [u8]) -> Self {
+        Self { buf }
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        if self.buf.len() < len {
+            bail!("truncated Kafka response");
+        }
+        let (taken, rest) = self.buf.split_at(len);
+        self.buf = rest;
+        Ok(taken)
+    }
+
+    fn i8(&mut self) -> Result<i8> {
+        Ok(self.take(1)?[0] as i8)
+    }
+
+    fn i16(&mut self) -> Result<i16> {
+        Ok(i16::from_be_bytes(self.take(2)?.try_into()?))
+    }
+
+    fn i32(&mut self) -> Result<i32> {
+        Ok(i32::from_be_bytes(self.take(4)?.try_into()?))
+    }
+
+    fn i64(&mut self) -> Result<i64> {
+        Ok(i64::from_be_bytes(self.take(8)?.try_into()?))
+    }
+
+    fn nullable_string(&mut self) -> Result<Option<String>> {
+        let len = self.i16()?;
+        if len < 0 {
+            return Ok(None);
+        }
+        Ok(Some(String::from_utf8(self.take(len as usize)?.to_vec())?))
+    }
+
+    fn string(&mut self) -> Result<String> {
+        self.nullable_string()?.context("null string in Kafka response")
+    }
+
+    fn i32_array(&mut self) -> Result<Vec<i32>> {
+        let len = self.i32()?.max(0);
+        (0..len).map(|_| self.i32()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kafka_encoding() -> Result<()> {
+        assert_eq!(crc32c(b"123456789"), 0xe306_9283);
+        // Test vectors of the Java client
+        assert_eq!(murmur2(b"21") as i32, -973932308);
+        assert_eq!(murmur2(b"foobar") as i32, -790332482);
+        assert_eq!(murmur2(b"a-little-bit-long-string") as i32, -985981536);
+        assert_eq!(murmur2(b"a-little-bit-longer-string") as i32, -1486304829);
+        assert_eq!(murmur2(b"lkjh234lh9fiuh90y23oiuhsafujhadof229phr9h19h89h8") as i32, -58897971);
+        assert_eq!(murmur2(b"abc") as i32, 479470107);
+
+        let mut varints = Vec::new();
+        for value in [0, -1, 1, 300] {
+            put_varint(&mut varints, value);
+        }
+        assert_eq!(varints, vec![0x00, 0x01, 0x02, 0xd8, 0x04]);
+
+        let record = KafkaRecord { topic: "events".to_string(), key: Some(b"c1".to_vec()), value: b"{}".to_vec() };
+        let batch = encode_record_batch(&[&record, &record], 1_700_000_000_000);
+        let mut reader = Reader::new(&batch);
+        assert_eq!(reader.i64()?, 0);
+        assert_eq!(reader.i32()? as usize, batch.len() - 12);
+        assert_eq!(reader.i32()?, -1);
+        assert_eq!(reader.i8()?, 2);
+        assert_eq!(u32::from_be_bytes(reader.take(4)?.try_into()?), crc32c(&batch[21..]));
+        assert_eq!(reader.i16()?, 0);
+        assert_eq!(reader.i32()?, 1);
+        // The second record, at offset delta 1
+        assert!(batch.ends_with(&[0x14, 0x00, 0x00, 0x02, 0x04, b'c', b'1', 0x04, b'{', b'}', 0x00]));
+
+        assert_eq!(partition(Some(b"21"), 3), ((-973932308i32 & 0x7fff_ffff) % 3));
+        Ok(())
+    }
+}
@@ -1,5 +1,21 @@
 pub mod cypher;
 pub mod graph;
-pub mod api_record_to_json;
+pub mod metrics;
 pub mod config;
-pub mod sync;
\ No newline at end of file
+pub mod events;
+pub mod export;
+pub mod kafka;
+pub mod lineage;
+pub mod packages;
+pub mod parties;
+pub mod queries;
+pub mod reset;
+pub mod serve;
+pub mod sink;
+pub mod stats;
+pub mod status;
+pub mod sync;
+pub mod telemetry;
+pub mod template_registry;
+pub mod verify;
+pub mod webhooks;
\ No newline at end of file
@@ -0,0 +1,174 @@
+//! Rendering of the provenance of a contract, `queries::Lineage`, by the `lineage` command: as an
+//! indented text tree, or as a Graphviz DOT graph of the contracts and the exercises between them.
+use crate::queries::{Lineage, LineageEdge};
+use std::collections::{BTreeSet, HashSet};
+
+/// The contract, how it was archived, the contracts it was created from, and the contracts
+/// created from it, indented by the exercises between them
+pub fn to_text(lineage: &Lineage) -> String {
+    let mut out = format!("{} {}\n", template(&lineage.template_name), lineage.contract_id);
+    match (&lineage.consumed_by, lineage.archived_at_offset) {
+        (Some(exercise), _) => out.push_str(&format!(
+            "  consumed by {} at offset {}{}\n",
+            choice(&exercise.choice_name),
+            exercise.offset,
+            if exercise.acting_parties.is_empty() {
+                String::new()
+            } else {
+                format!(", acting parties: {}", exercise.acting_parties.join(", "))
+            }
+        )),
+        (None, Some(offset)) => out.push_str(&format!("  archived at offset {}\n", offset)),
+        (None, None) => out.push_str("  active\n"),
+    }
+    for (title, edges, ancestors) in [("created from", &lineage.ancestors, true), ("produced", &lineage.descendants, false)] {
+        out.push_str(&format!("{}:\n", title));
+        if edges.is_empty() {
+            out.push_str("  (none)\n");
+        }
+        let mut seen = HashSet::from([lineage.contract_id.as_str()]);
+        write_tree(&mut out, edges, &lineage.contract_id, ancestors, 1, &mut seen);
+    }
+    out
+}
+
+/// Writes the edges from the contract, and recursively the ones from the contracts they reach
+/// which were not written before
+fn write_tree<'a>(
+    out: &mut String,
+    edges: &'a [LineageEdge],
+    contract_id: &str,
+    ancestors: bool,
+    indent: usize,
+    seen: &mut HashSet<&'a str>,
+) {
+    for edge in edges {
+        let (from, to, to_template) = if ancestors {
+            (&edge.child_contract_id, &edge.parent_contract_id, &edge.parent_template_name)
+        } else {
+            (&edge.parent_contract_id, &edge.child_contract_id, &edge.child_template_name)
+        };
+        if from != contract_id {
+            continue;
+        }
+        out.push_str(&format!(
+            "{:indent$}{} {} {} ({})\n",
+            "",
+            if ancestors { "<-" } else { "->" },
+            template(to_template),
+            to,
+            exercise(edge),
+            indent = indent * 2
+        ));
+        if seen.insert(to) {
+            write_tree(out, edges, to, ancestors, indent + 1, seen);
+        }
+    }
+}
+
+/// A digraph of the contracts of the lineage, with an edge labeled by the exercise from each
+/// parent contract to each child contract. The contract of the lineage is bold.
+pub fn to_dot(lineage: &Lineage) -> String {
+    let mut out = String::from("digraph lineage {\n  rankdir=LR;\n  node [shape=box];\n");
+    out.push_str(&format!(
+        "  {} [label={}, style=bold];\n",
+        dot_id(&lineage.contract_id),
+        dot_id(&format!("{}\n{}", template(&lineage.template_name), lineage.contract_id))
+    ));
+    let edges: Vec<&LineageEdge> = lineage.ancestors.iter().chain(&lineage.descendants).collect();
+    let nodes: BTreeSet<(&String, &Option<String>)> = edges
+        .iter()
+        .flat_map(|edge| {
+            [(&edge.parent_contract_id, &edge.parent_template_name), (&edge.child_contract_id, &edge.child_template_name)]
+        })
+        .filter(|(contract_id, _)| **contract_id != lineage.contract_id)
+        .collect();
+    for (contract_id, template_name) in nodes {
+        out.push_str(&format!(
+            "  {} [label={}];\n",
+            dot_id(contract_id),
+            dot_id(&format!("{}\n{}", template(template_name), contract_id))
+        ));
+    }
+    for edge in edges {
+        out.push_str(&format!(
+            "  {} -> {} [label={}];\n",
+            dot_id(&edge.parent_contract_id),
+            dot_id(&edge.child_contract_id),
+            dot_id(&exercise(edge))
+        ));
+    }
+    out.push_str("}\n");
+    out
+}
+
+fn template(template_name: &Option<String>) -> &str {
+    template_name.as_deref().unwrap_or("unknown")
+}
+
+fn choice(choice_name: &Option<String>) -> &str {
+    choice_name.as_deref().unwrap_or("unknown choice")
+}
+
+fn exercise(edge: &LineageEdge) -> String {
+    format!(
+        "{}{} at offset {}",
+        choice(&edge.choice_name),
+        if edge.consuming == Some(true) { ", consuming" } else { "" },
+        edge.exercise_offset
+    )
+}
+
+/// A quoted DOT identifier
+fn dot_id(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::queries::ConsumingExercise;
+
+    #[test]
+    fn test_lineage_text_and_dot() {
+        let edge = |depth, parent: &str, choice_name: &str, offset, child: &str| LineageEdge {
+            depth,
+            parent_contract_id: parent.to_string(),
+            parent_template_name: Some("Main.Asset".to_string()),
+            choice_name: Some(choice_name.to_string()),
+            exercise_offset: offset,
+            consuming: Some(choice_name == "Transfer"),
+            child_contract_id: child.to_string(),
+            child_template_name: Some("Main.Asset".to_string()),
+        };
+        let lineage = Lineage {
+            contract_id: "b".to_string(),
+            template_name: Some("Main.Asset".to_string()),
+            archived_at_offset: Some(20),
+            consumed_by: Some(ConsumingExercise {
+                choice_name: Some("Transfer".to_string()),
+                offset: 20,
+                acting_parties: vec!["Bob".to_string()],
+            }),
+            ancestors: vec![edge(1, "a", "Transfer", 10, "b")],
+            descendants: vec![
+                edge(1, "b", "Transfer", 20, "c"),
+                edge(1, "b", "Split", 20, "d"),
+                edge(2, "c", "Transfer", 30, "e"),
+            ],
+        };
+
+        assert_eq!(
+            to_text(&lineage),
+            "Main.Asset b\n  consumed by Transfer at offset 20, acting parties: Bob\n\
+             created from:\n  <- Main.Asset a (Transfer, consuming at offset 10)\n\
+             produced:\n  -> Main.Asset c (Transfer, consuming at offset 20)\n    -> Main.Asset e (Transfer, consuming at offset 30)\n  -> Main.Asset d (Split at offset 20)\n"
+        );
+        let dot = to_dot(&lineage);
+        assert!(dot.starts_with("digraph lineage {\n"));
+        assert!(dot.contains("  \"b\" [label=\"Main.Asset\\nb\", style=bold];\n"));
+        assert!(dot.contains("  \"e\" [label=\"Main.Asset\\ne\"];\n"));
+        assert!(dot.contains("  \"c\" -> \"e\" [label=\"Transfer, consuming at offset 30\"];\n"));
+        assert_eq!(dot.matches(" -> ").count(), 4);
+    }
+}
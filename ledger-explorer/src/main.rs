@@ -2,11 +2,15 @@ use clap::{Parser, Subcommand};
 use tokio_stream::StreamExt;
 use ledger_explorer::cypher;
 use ledger_explorer::config;
-use ledger_explorer::sync::{run_resilient_sync, SyncConfig, BackoffConfig};
+use ledger_explorer::export::CsvSink;
+use ledger_explorer::kafka::KafkaSink;
+use ledger_explorer::sink::{run_sink_sync, EventBatch, SinkSyncConfig};
+use ledger_explorer::sync::{run_backfill, run_resilient_sync, SyncConfig, BackoffConfig};
 use client::jwt::TokenSource;
-use client::stream_updates::stream_updates;
+use client::stream_updates::{stream_updates, stream_updates_with_event_format};
 use tracing::{info, debug, warn};
 use tracing_subscriber::EnvFilter;
+use std::path::{Path, PathBuf};
 use std::time::Instant;
 
 #[derive(Parser)]
@@ -65,7 +69,196 @@ enum Commands {
         /// Fresh start: clear Neo4j database, load current ACS, and stream from ledger end
         #[arg(long)]
         fresh: bool,
-    }
+        /// If the resume offset was pruned, clear Neo4j and load the ACS at the pruning offset
+        /// instead of failing
+        #[arg(long)]
+        rebootstrap_on_pruning_gap: bool,
+    },
+    /// Publish the events of the ledger to Kafka, configured in the [kafka] section
+    Publish {
+        /// Path to config.toml file (defaults to ./config/config.toml or CARGO_MANIFEST_DIR/config/config.toml)
+        #[arg(long)]
+        config_file: Option<String>,
+        /// Profile to use (overrides active_profile in config)
+        #[arg(long, short)]
+        profile: Option<String>,
+        /// Optional access token (if not provided, will try Keycloak config, then fall back to fake JWT)
+        #[arg(long)]
+        access_token: Option<String>,
+        /// Use Keycloak to obtain a real JWT token (requires keycloak section in profile)
+        #[arg(long)]
+        use_keycloak: bool,
+    },
+    /// Serve a read-only REST API over the synced graph, configured in the [serve] section
+    Serve {
+        /// Path to config.toml file (defaults to ./config/config.toml or CARGO_MANIFEST_DIR/config/config.toml)
+        #[arg(long)]
+        config_file: Option<String>,
+        /// Profile to use (overrides active_profile in config)
+        #[arg(long, short)]
+        profile: Option<String>,
+        /// Address to listen on (overrides listen_address in config)
+        #[arg(long)]
+        listen_address: Option<String>,
+        /// Only serve the nodes of this source in a graph shared by several ledgers
+        #[arg(long)]
+        ledger_id: Option<String>,
+    },
+    /// Print the provenance of a contract: what it was created from and what was created from it
+    Lineage {
+        /// Path to config.toml file (defaults to ./config/config.toml or CARGO_MANIFEST_DIR/config/config.toml)
+        #[arg(long)]
+        config_file: Option<String>,
+        /// Profile to use (overrides active_profile in config)
+        #[arg(long, short)]
+        profile: Option<String>,
+        #[arg(long)]
+        contract_id: String,
+        /// Maximum number of exercises between the contract and the contracts printed
+        #[arg(long, default_value = "10")]
+        depth: u32,
+        /// The source of the contract in a graph shared by several ledgers
+        #[arg(long)]
+        ledger_id: Option<String>,
+        #[arg(long, value_enum, default_value_t = LineageFormat::Text)]
+        format: LineageFormat,
+    },
+    /// Print counts of the contracts per template, of the exercises per choice and of the transactions per day
+    Stats {
+        /// Path to config.toml file (defaults to ./config/config.toml or CARGO_MANIFEST_DIR/config/config.toml)
+        #[arg(long)]
+        config_file: Option<String>,
+        /// Profile to use (overrides active_profile in config)
+        #[arg(long, short)]
+        profile: Option<String>,
+        /// Only count what was created, exercised or committed on or after this date (YYYY-MM-DD)
+        #[arg(long)]
+        since: Option<chrono::NaiveDate>,
+        /// Only count the nodes of this source in a graph shared by several ledgers
+        #[arg(long)]
+        ledger_id: Option<String>,
+        #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+        format: OutputFormat,
+    },
+    /// Replay the updates of an offset range to Neo4j, e.g. after a change of the Cypher
+    /// generation, without moving the resume point of the sync
+    Backfill {
+        /// Path to config.toml file (defaults to ./config/config.toml or CARGO_MANIFEST_DIR/config/config.toml)
+        #[arg(long)]
+        config_file: Option<String>,
+        /// Profile to use (overrides active_profile in config)
+        #[arg(long, short)]
+        profile: Option<String>,
+        /// Optional access token (if not provided, will try Keycloak config, then fall back to fake JWT)
+        #[arg(long)]
+        access_token: Option<String>,
+        /// Use Keycloak to obtain a real JWT token (requires keycloak section in profile)
+        #[arg(long)]
+        use_keycloak: bool,
+        /// First offset to replay
+        #[arg(long)]
+        from: i64,
+        /// Last offset to replay (inclusive)
+        #[arg(long)]
+        to: i64,
+        /// The source to replay in a graph shared by several ledgers
+        #[arg(long)]
+        ledger_id: Option<String>,
+    },
+    /// Compare the active contracts of the ledger with the ones of the graph, reporting the
+    /// missing, extra and mismatched ones. Exits with status 1 on discrepancies.
+    Verify {
+        /// Path to config.toml file (defaults to ./config/config.toml or CARGO_MANIFEST_DIR/config/config.toml)
+        #[arg(long)]
+        config_file: Option<String>,
+        /// Profile to use (overrides active_profile in config)
+        #[arg(long, short)]
+        profile: Option<String>,
+        /// Optional access token (if not provided, will try Keycloak config, then fall back to fake JWT)
+        #[arg(long)]
+        access_token: Option<String>,
+        /// Use Keycloak to obtain a real JWT token (requires keycloak section in profile)
+        #[arg(long)]
+        use_keycloak: bool,
+        /// The source to verify in a graph shared by several ledgers
+        #[arg(long)]
+        ledger_id: Option<String>,
+        #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+        format: OutputFormat,
+    },
+    /// Print the resume point of the sync, its lag behind the ledger end, whether the ACS was
+    /// loaded, and the counts of the nodes per label and of the relationships per type
+    Status {
+        /// Path to config.toml file (defaults to ./config/config.toml or CARGO_MANIFEST_DIR/config/config.toml)
+        #[arg(long)]
+        config_file: Option<String>,
+        /// Profile to use (overrides active_profile in config)
+        #[arg(long, short)]
+        profile: Option<String>,
+        /// Optional access token (if not provided, will try Keycloak config, then fall back to fake JWT)
+        #[arg(long)]
+        access_token: Option<String>,
+        /// Use Keycloak to obtain a real JWT token (requires keycloak section in profile)
+        #[arg(long)]
+        use_keycloak: bool,
+        /// The source to report on in a graph shared by several ledgers
+        #[arg(long)]
+        ledger_id: Option<String>,
+        #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+        format: OutputFormat,
+    },
+    /// Delete part of the synced data from Neo4j: the contracts of templates, the updates from an
+    /// offset on, or everything. Stop the sync first.
+    Reset {
+        /// Path to config.toml file (defaults to ./config/config.toml or CARGO_MANIFEST_DIR/config/config.toml)
+        #[arg(long)]
+        config_file: Option<String>,
+        /// Profile to use (overrides active_profile in config)
+        #[arg(long, short)]
+        profile: Option<String>,
+        /// Only delete the contracts of these templates (Module.Entity), with the exercises on them
+        #[arg(long, num_args = 1.., conflicts_with = "all")]
+        templates: Vec<String>,
+        /// Only delete the updates at this offset and later, the next sync streams them again
+        #[arg(long, conflicts_with = "all")]
+        before_offset: Option<i64>,
+        /// Delete everything, the next sync starts over with the ACS
+        #[arg(long)]
+        all: bool,
+        /// Only delete the nodes of this source in a graph shared by several ledgers
+        #[arg(long)]
+        ledger_id: Option<String>,
+    },
+    /// Export the events of the ledger to CSV files, configured in the [export] section
+    Export {
+        /// Path to config.toml file (defaults to ./config/config.toml or CARGO_MANIFEST_DIR/config/config.toml)
+        #[arg(long)]
+        config_file: Option<String>,
+        /// Profile to use (overrides active_profile in config)
+        #[arg(long, short)]
+        profile: Option<String>,
+        /// Optional access token (if not provided, will try Keycloak config, then fall back to fake JWT)
+        #[arg(long)]
+        access_token: Option<String>,
+        /// Use Keycloak to obtain a real JWT token (requires keycloak section in profile)
+        #[arg(long)]
+        use_keycloak: bool,
+    },
+}
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum LineageFormat {
+    /// Indented trees of the ancestors and descendants
+    Text,
+    Json,
+    /// Graphviz digraph, e.g. for `dot -Tsvg`
+    Dot,
+}
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum OutputFormat {
+    Table,
+    Json,
 }
 
 #[tokio::main]
@@ -74,7 +267,16 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // Determine log level from config file if available (for Sync command), otherwise default to INFO
     let log_level = match &cli.command {
-        Commands::Sync { config_file, profile, .. } => {
+        Commands::Sync { config_file, profile, .. }
+        | Commands::Publish { config_file, profile, .. }
+        | Commands::Export { config_file, profile, .. }
+        | Commands::Serve { config_file, profile, .. }
+        | Commands::Lineage { config_file, profile, .. }
+        | Commands::Stats { config_file, profile, .. }
+        | Commands::Reset { config_file, profile, .. }
+        | Commands::Backfill { config_file, profile, .. }
+        | Commands::Verify { config_file, profile, .. }
+        | Commands::Status { config_file, profile, .. } => {
             let config = match config_file {
                 Some(path) => ledger_explorer::config::read_config(path, profile.as_deref()).ok(),
                 None => ledger_explorer::config::read_config_from_toml(profile.as_deref()).ok(),
@@ -96,7 +298,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             let parties = vec![party];
             let mut update_stream = stream_updates(Some(&access_token), begin_exclusive, end_inclusive, parties, url).await?;
             while let Some(response) = update_stream.next().await {
-                let cypher_queries = cypher::get_updates_response_to_cypher(&response?);
+                let cypher_queries = cypher::get_updates_response_to_cypher(&response?, None, &Default::default());
                 println!("Start transaction");
                 println!("{:?}", cypher_queries);
                 println!("End transaction");
@@ -111,6 +313,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             };
             let parties = config.ledger.parties.unwrap_or_default();
             let ledger_url = config.ledger.url;
+            let template_registry = config
+                .template_registry
+                .map(|path| ledger_explorer::template_registry::read_template_registry(&path))
+                .transpose()?;
 
             // Get token
             let token = if use_keycloak {
@@ -146,7 +352,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             info!("Streaming {} updates from Canton (stream only, no cypher, no neo4j)...", count);
 
             // Benchmark 1: Raw stream only
-            let mut update_stream = stream_updates(Some(&token), start_offset, None, parties.clone(), ledger_url.clone()).await?;
+            let mut update_stream = stream_updates_with_event_format(Some(&token), start_offset, None, parties.event_format(&[], false), ledger_url.clone()).await?;
             let start_time = Instant::now();
             let mut raw_count = 0u64;
             let mut last_offset = start_offset;
@@ -187,7 +393,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
             // Benchmark 2: Stream + Cypher generation
             info!("\nStreaming {} updates with Cypher generation (no neo4j)...", count);
-            let mut update_stream = stream_updates(Some(&token), start_offset, None, parties.clone(), ledger_url.clone()).await?;
+            let mut update_stream = stream_updates_with_event_format(Some(&token), start_offset, None, parties.event_format(&[], false), ledger_url.clone()).await?;
             let start_time = Instant::now();
             let mut cypher_count = 0u64;
             let mut total_queries = 0usize;
@@ -195,7 +401,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             while let Some(response) = update_stream.next().await {
                 match response {
                     Ok(resp) => {
-                        let queries = cypher::get_updates_response_to_cypher(&resp);
+                        let queries = cypher::get_updates_response_to_cypher(&resp, template_registry.as_ref(), &config.node_mapping);
                         total_queries += queries.len();
                         cypher_count += 1;
                         if cypher_count >= count {
@@ -227,7 +433,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             info!("  Cypher overhead: {:.1}%", (1.0 - cypher_rate / raw_rate) * 100.0);
             info!("  (Compare with ledger-explorer sync rate to see Neo4j write overhead)");
         }
-        Commands::Sync { config_file, profile, access_token, use_keycloak, fresh } => {
+        Commands::Sync { config_file, profile, access_token, use_keycloak, fresh, rebootstrap_on_pruning_gap } => {
             info!("Starting resilient sync command (fresh={})", fresh);
 
             debug!(config_path = ?config_file, profile = ?profile, "Reading configuration from TOML file");
@@ -235,76 +441,335 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 Some(path) => ledger_explorer::config::read_config(&path, profile.as_deref()).expect("failed to read config from specified path"),
                 None => ledger_explorer::config::read_config_from_toml(profile.as_deref()).expect("failed to read config from toml"),
             };
-            let fake_jwt_user = config.ledger.fake_jwt_user;
-            let parties = config.ledger.parties.unwrap_or_default();
-            let ledger_url = config.ledger.url;
-            let starting_offset = config.ledger.starting_offset;
-            let neo4j_uri = config.neo4j.uri.clone();
-            let neo4j_user = config.neo4j.user.clone();
-            let neo4j_pass = config.neo4j.password.clone();
-            let keycloak_config = config.keycloak;
-
-            info!(
-                ledger_url = %ledger_url,
-                neo4j_uri = %neo4j_uri,
-                parties = ?parties,
-                starting_offset = ?starting_offset,
-                "Configuration loaded"
-            );
-
-            // Determine token source for automatic renewal
-            let token_source = match access_token {
-                Some(token) => {
-                    info!("Using provided static access token");
-                    TokenSource::Static(token)
-                }
-                None if use_keycloak => {
-                    let kc_config = keycloak_config
-                        .expect("--use-keycloak requires keycloak section in profile");
-                    info!("Using Keycloak for JWT token management at {}", kc_config.token_endpoint);
+            let template_registry = config
+                .template_registry
+                .as_ref()
+                .map(ledger_explorer::template_registry::read_template_registry)
+                .transpose()?
+                .map(std::sync::Arc::new);
+            // The ledgers of the sources, each tagging its nodes with its ledger_id, or else the
+            // ledger of the profile
+            let sources: Vec<(Option<String>, config::LedgerConfig, Option<config::KeycloakConfig>)> = if config.sources.is_empty() {
+                vec![(None, config.ledger.clone(), config.keycloak.clone())]
+            } else {
+                config.sources.iter().map(|source| (Some(source.ledger_id.clone()), source.ledger.clone(), source.keycloak.clone())).collect()
+            };
+            let mut metrics_address = config.metrics.enabled.then(|| config.metrics.listen_address.clone());
+            if sources.len() > 1 && metrics_address.take().is_some() {
+                warn!("Metrics are not supported when syncing several ledgers, disabling the metrics endpoint");
+            }
 
-                    // Convert from ledger-explorer's KeycloakConfig to client's KeycloakConfig
-                    let auth_method = match kc_config.auth_method {
-                        config::KeycloakAuthMethod::ClientCredentials { client_secret } => {
-                            client::jwt::KeycloakAuthMethod::ClientCredentials { client_secret }
-                        }
-                        config::KeycloakAuthMethod::Password { username, password, client_secret } => {
-                            client::jwt::KeycloakAuthMethod::Password { username, password, client_secret }
-                        }
-                    };
+            let mut syncs = Vec::new();
+            for (ledger_id, ledger, keycloak_config) in sources {
+                info!(
+                    ledger_id = ?ledger_id,
+                    ledger_url = %ledger.url,
+                    neo4j_uri = %config.neo4j.uri,
+                    parties = ?ledger.parties,
+                    starting_offset = ?ledger.starting_offset,
+                    "Configuration loaded"
+                );
 
-                    TokenSource::Keycloak(client::jwt::KeycloakConfig {
-                        client_id: kc_config.client_id,
-                        token_endpoint: kc_config.token_endpoint,
-                        auth_method,
-                    })
-                }
-                None => {
-                    info!("Using fake JWT token for user: {}", fake_jwt_user);
-                    TokenSource::FakeJwt(fake_jwt_user)
-                }
-            };
+                // Determine token source for automatic renewal
+                let token_source = token_source(access_token.clone(), use_keycloak, keycloak_config, ledger.fake_jwt_user.clone());
 
-            let sync_config = SyncConfig {
-                ledger_url,
-                parties,
-                neo4j_uri,
-                neo4j_user,
-                neo4j_pass,
-                starting_offset,
-                batch_size: config.neo4j.batch_size,
-                flush_timeout_secs: config.neo4j.flush_timeout_secs,
-                idle_timeout_secs: config.neo4j.idle_timeout_secs,
-            };
+                let mut sync_config = neo4j_sync_config(&config, ledger, ledger_id, template_registry.clone())?;
+                sync_config.metrics_address = metrics_address.clone();
+                sync_config.rebootstrap_on_pruning_gap = rebootstrap_on_pruning_gap;
+                syncs.push(run_resilient_sync(sync_config, token_source, BackoffConfig::default(), fresh));
+            }
 
             if fresh {
                 info!("FRESH START: Will clear Neo4j, load current ACS, and stream from ledger end");
             } else {
                 info!("Starting resilient sync loop (will auto-reconnect on failures, resume from Neo4j checkpoint)");
             }
-            run_resilient_sync(sync_config, token_source, BackoffConfig::default(), fresh).await?;
+            futures_util::future::try_join_all(syncs).await?;
+        }
+        Commands::Publish { config_file, profile, access_token, use_keycloak } => {
+            let config = match config_file {
+                Some(path) => ledger_explorer::config::read_config(&path, profile.as_deref())?,
+                None => ledger_explorer::config::read_config_from_toml(profile.as_deref())?,
+            };
+            let kafka_config = config.kafka.clone().ok_or("publish requires a [kafka] section in the config")?;
+            let (sink_config, batch) =
+                sink_sync_config(&config, &kafka_config.batch, kafka_config.checkpoint_file.clone().into())?;
+            let token_source = token_source(access_token, use_keycloak, config.keycloak, config.ledger.fake_jwt_user);
+            let mut sink = KafkaSink::connect(kafka_config, batch).await?;
+            run_sink_sync(&mut sink, sink_config, token_source, BackoffConfig::default()).await?;
+        }
+        Commands::Serve { config_file, profile, listen_address, ledger_id } => {
+            let mut config = match config_file {
+                Some(path) => ledger_explorer::config::read_config(&path, profile.as_deref())?,
+                None => ledger_explorer::config::read_config_from_toml(profile.as_deref())?,
+            };
+            if let Some(listen_address) = listen_address {
+                config.serve.listen_address = listen_address;
+            }
+            let ledger_id = config.neo4j.ledger_id(ledger_id.as_deref());
+            ledger_explorer::serve::serve(&config.neo4j, &config.serve, ledger_id).await?;
+        }
+        Commands::Lineage { config_file, profile, contract_id, depth, ledger_id, format } => {
+            let config = match config_file {
+                Some(path) => ledger_explorer::config::read_config(&path, profile.as_deref())?,
+                None => ledger_explorer::config::read_config_from_toml(profile.as_deref())?,
+            };
+            let graph = ledger_explorer::graph::connect_graph(
+                &config.neo4j.uri,
+                &config.neo4j.user,
+                &config.neo4j.password,
+                config.neo4j.database.as_deref(),
+            )?;
+            let ledger_id = config.neo4j.ledger_id(ledger_id.as_deref());
+            let lineage = ledger_explorer::queries::lineage(&graph, &contract_id, depth, ledger_id.as_deref())
+                .await?
+                .ok_or_else(|| format!("unknown contract '{}'", contract_id))?;
+            match format {
+                LineageFormat::Text => print!("{}", ledger_explorer::lineage::to_text(&lineage)),
+                LineageFormat::Json => println!("{}", serde_json::to_string_pretty(&lineage)?),
+                LineageFormat::Dot => print!("{}", ledger_explorer::lineage::to_dot(&lineage)),
+            }
+        }
+        Commands::Stats { config_file, profile, since, ledger_id, format } => {
+            let config = match config_file {
+                Some(path) => ledger_explorer::config::read_config(&path, profile.as_deref())?,
+                None => ledger_explorer::config::read_config_from_toml(profile.as_deref())?,
+            };
+            let graph = ledger_explorer::graph::connect_graph(
+                &config.neo4j.uri,
+                &config.neo4j.user,
+                &config.neo4j.password,
+                config.neo4j.database.as_deref(),
+            )?;
+            let since = since.map(|date| date.to_string());
+            let ledger_id = config.neo4j.ledger_id(ledger_id.as_deref());
+            let stats = ledger_explorer::queries::stats(&graph, since.as_deref(), ledger_id.as_deref()).await?;
+            match format {
+                OutputFormat::Table => print!("{}", ledger_explorer::stats::to_table(&stats)),
+                OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&stats)?),
+            }
+        }
+        Commands::Backfill { config_file, profile, access_token, use_keycloak, from, to, ledger_id } => {
+            let config = match config_file {
+                Some(path) => ledger_explorer::config::read_config(&path, profile.as_deref())?,
+                None => ledger_explorer::config::read_config_from_toml(profile.as_deref())?,
+            };
+            let (sync_config, token_source) = source_sync_config(&config, ledger_id, access_token, use_keycloak)?;
+            run_backfill(sync_config, token_source, from, to).await?;
+        }
+        Commands::Verify { config_file, profile, access_token, use_keycloak, ledger_id, format } => {
+            let config = match config_file {
+                Some(path) => ledger_explorer::config::read_config(&path, profile.as_deref())?,
+                None => ledger_explorer::config::read_config_from_toml(profile.as_deref())?,
+            };
+            let (sync_config, token_source) = source_sync_config(&config, ledger_id, access_token, use_keycloak)?;
+            let graph = ledger_explorer::graph::connect_graph(
+                &config.neo4j.uri,
+                &config.neo4j.user,
+                &config.neo4j.password,
+                config.neo4j.database.as_deref(),
+            )?;
+            let report = ledger_explorer::verify::verify(&graph, &sync_config, token_source).await?;
+            match format {
+                OutputFormat::Table => print!("{}", report.to_text()),
+                OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+            }
+            if !report.is_consistent() {
+                std::process::exit(1);
+            }
+        }
+        Commands::Status { config_file, profile, access_token, use_keycloak, ledger_id, format } => {
+            let config = match config_file {
+                Some(path) => ledger_explorer::config::read_config(&path, profile.as_deref())?,
+                None => ledger_explorer::config::read_config_from_toml(profile.as_deref())?,
+            };
+            let (sync_config, token_source) = source_sync_config(&config, ledger_id, access_token, use_keycloak)?;
+            let graph = ledger_explorer::graph::connect_graph(
+                &config.neo4j.uri,
+                &config.neo4j.user,
+                &config.neo4j.password,
+                config.neo4j.database.as_deref(),
+            )?;
+            let status = ledger_explorer::status::status(&graph, &sync_config, token_source).await?;
+            match format {
+                OutputFormat::Table => print!("{}", status.to_text(chrono::Utc::now())),
+                OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&status)?),
+            }
+        }
+        Commands::Reset { config_file, profile, templates, before_offset, all, ledger_id } => {
+            let config = match config_file {
+                Some(path) => ledger_explorer::config::read_config(&path, profile.as_deref())?,
+                None => ledger_explorer::config::read_config_from_toml(profile.as_deref())?,
+            };
+            let graph = ledger_explorer::graph::connect_graph(
+                &config.neo4j.uri,
+                &config.neo4j.user,
+                &config.neo4j.password,
+                config.neo4j.database.as_deref(),
+            )?;
+            let scope = ledger_explorer::reset::ResetScope { templates, before_offset, all };
+            ledger_explorer::reset::reset(&graph, &scope, config.neo4j.ledger_id(ledger_id.as_deref()).as_deref()).await?;
+        }
+        Commands::Export { config_file, profile, access_token, use_keycloak } => {
+            let config = match config_file {
+                Some(path) => ledger_explorer::config::read_config(&path, profile.as_deref())?,
+                None => ledger_explorer::config::read_config_from_toml(profile.as_deref())?,
+            };
+            let export_config = config.export.clone().ok_or("export requires an [export] section in the config")?;
+            let checkpoint_file = export_config
+                .checkpoint_file
+                .map(PathBuf::from)
+                .unwrap_or_else(|| Path::new(&export_config.directory).join("export.offset"));
+            std::fs::create_dir_all(&export_config.directory)?;
+            let (sink_config, batch) = sink_sync_config(&config, &export_config.batch, checkpoint_file)?;
+            let token_source = token_source(access_token, use_keycloak, config.keycloak, config.ledger.fake_jwt_user);
+            let mut sink = CsvSink::new(export_config.directory, batch);
+            run_sink_sync(&mut sink, sink_config, token_source, BackoffConfig::default()).await?;
         }
     }
 
     Ok(())
 }
+
+/// The configuration of `run_sink_sync` from the `[ledger]` section and the batching of the sink,
+/// and the batch of events of the sink checkpointed in the file
+fn sink_sync_config(
+    config: &config::Config,
+    batch: &config::SinkBatchConfig,
+    checkpoint_file: PathBuf,
+) -> anyhow::Result<(SinkSyncConfig, EventBatch)> {
+    let interfaces = config
+        .ledger
+        .interfaces
+        .clone()
+        .unwrap_or_default()
+        .iter()
+        .map(|interface| client::utils::parse_identifier(interface))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let template_registry = config
+        .template_registry
+        .as_ref()
+        .map(ledger_explorer::template_registry::read_template_registry)
+        .transpose()?
+        .map(std::sync::Arc::new);
+    let sink_config = SinkSyncConfig {
+        ledger_url: config.ledger.url.clone(),
+        parties: config.ledger.parties.clone().unwrap_or_default(),
+        interfaces,
+        starting_offset: config.ledger.starting_offset,
+        batch_size: batch.batch_size,
+        flush_timeout_secs: batch.flush_timeout_secs,
+        idle_timeout_secs: batch.idle_timeout_secs,
+    };
+    Ok((sink_config, EventBatch::new(checkpoint_file, template_registry)))
+}
+
+/// The Neo4j sync of the source of the ledger id, or else of the ledger of the profile, and the
+/// source of its tokens
+fn source_sync_config(
+    config: &config::Config,
+    ledger_id: Option<String>,
+    access_token: Option<String>,
+    use_keycloak: bool,
+) -> Result<(SyncConfig, TokenSource), Box<dyn std::error::Error>> {
+    let (ledger, keycloak_config) = match &ledger_id {
+        Some(ledger_id) => {
+            let source = config
+                .sources
+                .iter()
+                .find(|source| &source.ledger_id == ledger_id)
+                .ok_or_else(|| format!("no source '{}' in the sources of the config", ledger_id))?;
+            (source.ledger.clone(), source.keycloak.clone())
+        }
+        None => (config.ledger.clone(), config.keycloak.clone()),
+    };
+    let template_registry = config
+        .template_registry
+        .as_ref()
+        .map(ledger_explorer::template_registry::read_template_registry)
+        .transpose()?
+        .map(std::sync::Arc::new);
+    let token_source = token_source(access_token, use_keycloak, keycloak_config, ledger.fake_jwt_user.clone());
+    Ok((neo4j_sync_config(config, ledger, ledger_id, template_registry)?, token_source))
+}
+
+/// The Neo4j sync of the ledger, without metrics endpoint
+fn neo4j_sync_config(
+    config: &config::Config,
+    ledger: config::LedgerConfig,
+    ledger_id: Option<String>,
+    template_registry: Option<std::sync::Arc<daml_type_rep::schema::TemplateRegistry>>,
+) -> anyhow::Result<SyncConfig> {
+    let interfaces = ledger
+        .interfaces
+        .unwrap_or_default()
+        .iter()
+        .map(|interface| client::utils::parse_identifier(interface))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    Ok(SyncConfig {
+        ledger_url: ledger.url,
+        parties: ledger.parties.unwrap_or_default(),
+        interfaces,
+        neo4j_uri: config.neo4j.uri.clone(),
+        neo4j_user: config.neo4j.user.clone(),
+        neo4j_pass: config.neo4j.password.clone(),
+        neo4j_database: config.neo4j.database.clone(),
+        starting_offset: ledger.starting_offset,
+        batch_size: config.neo4j.batch_size,
+        flush_timeout_secs: config.neo4j.flush_timeout_secs,
+        idle_timeout_secs: config.neo4j.idle_timeout_secs,
+        writer_concurrency: config.neo4j.writer_concurrency,
+        created_event_blobs: config.neo4j.created_event_blobs,
+        max_in_flight_batches: config.neo4j.max_in_flight_batches,
+        lag_target_secs: config.neo4j.lag_target_secs,
+        metrics_address: None,
+        party_refresh_secs: config.party_metadata.enabled.then_some(config.party_metadata.refresh_interval_secs),
+        package_refresh_secs: config.package_catalog.enabled.then_some(config.package_catalog.refresh_interval_secs),
+        template_registry,
+        node_mapping: config.node_mapping.clone(),
+        rebootstrap_on_pruning_gap: false,
+        webhooks: config.webhooks.clone(),
+        opentelemetry: config.opentelemetry.clone(),
+        ledger_id: config.neo4j.ledger_id(ledger_id.as_deref()),
+    })
+}
+
+/// The source of JWT tokens for automatic renewal: the static token, Keycloak, or fake JWTs
+fn token_source(
+    access_token: Option<String>,
+    use_keycloak: bool,
+    keycloak_config: Option<config::KeycloakConfig>,
+    fake_jwt_user: String,
+) -> TokenSource {
+    match access_token {
+        Some(token) => {
+            info!("Using provided static access token");
+            TokenSource::Static(token)
+        }
+        None if use_keycloak => {
+            let kc_config = keycloak_config
+                .expect("--use-keycloak requires keycloak section in profile");
+            info!("Using Keycloak for JWT token management at {}", kc_config.token_endpoint);
+
+            // Convert from ledger-explorer's KeycloakConfig to client's KeycloakConfig
+            let auth_method = match kc_config.auth_method {
+                config::KeycloakAuthMethod::ClientCredentials { client_secret } => {
+                    client::jwt::KeycloakAuthMethod::ClientCredentials { client_secret }
+                }
+                config::KeycloakAuthMethod::Password { username, password, client_secret } => {
+                    client::jwt::KeycloakAuthMethod::Password { username, password, client_secret }
+                }
+            };
+
+            TokenSource::Keycloak(client::jwt::KeycloakConfig {
+                client_id: kc_config.client_id,
+                token_endpoint: kc_config.token_endpoint,
+                auth_method,
+            })
+        }
+        None => {
+            info!("Using fake JWT token for user: {}", fake_jwt_user);
+            TokenSource::FakeJwt(fake_jwt_user)
+        }
+    }
+}
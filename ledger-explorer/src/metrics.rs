@@ -0,0 +1,295 @@
+//! Prometheus metrics of the sync daemon, served in the text exposition format on `/metrics`
+//! when enabled in the `[metrics]` section of the config. The sync records into `METRICS`,
+//! rendered on each scrape.
+use anyhow::{Context, Result};
+use client::jwt::TokenManager;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tracing::{debug, info, warn};
+
+/// Upper bounds of the buckets of the Neo4j write latency histogram, in seconds
+const WRITE_LATENCY_BUCKETS: [f64; 11] = [0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0];
+
+/// How often the ledger end and the transaction rate are updated
+const LEDGER_END_INTERVAL: Duration = Duration::from_secs(15);
+
+pub static METRICS: Metrics = Metrics::new();
+
+pub struct Metrics {
+    /// Offset of the resume point, -1 before the first update
+    last_processed_offset: AtomicI64,
+    /// -1 until queried
+    ledger_end: AtomicI64,
+    transactions: AtomicU64,
+    /// Transactions per second over the last `LEDGER_END_INTERVAL`, as `f64` bits
+    transaction_rate: AtomicU64,
+    reconnects: AtomicU64,
+    /// Batches submitted to the Neo4j writers and not yet committed
+    in_flight_batches: AtomicU64,
+    write_latency: Histogram,
+}
+
+impl Metrics {
+    pub const fn new() -> Self {
+        Self {
+            last_processed_offset: AtomicI64::new(-1),
+            ledger_end: AtomicI64::new(-1),
+            transactions: AtomicU64::new(0),
+            transaction_rate: AtomicU64::new(0),
+            reconnects: AtomicU64::new(0),
+            in_flight_batches: AtomicU64::new(0),
+            write_latency: Histogram::new(),
+        }
+    }
+
+    /// Records the resume point, which only moves forward
+    pub fn set_last_processed_offset(&self, offset: i64) {
+        self.last_processed_offset.fetch_max(offset, Ordering::Relaxed);
+    }
+
+    pub fn set_ledger_end(&self, offset: i64) {
+        self.ledger_end.store(offset, Ordering::Relaxed);
+    }
+
+    pub fn add_transactions(&self, count: u64) {
+        self.transactions.fetch_add(count, Ordering::Relaxed);
+    }
+
+    pub fn inc_reconnects(&self) {
+        self.reconnects.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn set_in_flight_batches(&self, count: usize) {
+        self.in_flight_batches.store(count as u64, Ordering::Relaxed);
+    }
+
+    /// The resume point, `None` before the first update
+    pub fn last_processed_offset(&self) -> Option<i64> {
+        Some(self.last_processed_offset.load(Ordering::Relaxed)).filter(|offset| *offset >= 0)
+    }
+
+    pub fn transactions(&self) -> u64 {
+        self.transactions.load(Ordering::Relaxed)
+    }
+
+    pub fn reconnects(&self) -> u64 {
+        self.reconnects.load(Ordering::Relaxed)
+    }
+
+    /// Records the duration of a Neo4j commit
+    pub fn observe_write_latency(&self, latency: Duration) {
+        self.write_latency.observe(latency);
+    }
+
+    fn set_transaction_rate(&self, rate: f64) {
+        self.transaction_rate.store(rate.to_bits(), Ordering::Relaxed);
+    }
+
+    /// The metrics in the Prometheus text exposition format
+    pub fn render(&self, token_refreshes: u64) -> String {
+        let last_processed_offset = self.last_processed_offset.load(Ordering::Relaxed);
+        let ledger_end = self.ledger_end.load(Ordering::Relaxed);
+        let mut out = String::new();
+        let mut metric = |name: &str, kind: &str, help: &str, value: String| {
+            out.push_str(&format!("# HELP {} {}\n# TYPE {} {}\n{} {}\n", name, help, name, kind, name, value));
+        };
+        metric(
+            "ledger_explorer_last_processed_offset",
+            "gauge",
+            "Offset of the last update written to Neo4j",
+            last_processed_offset.to_string(),
+        );
+        metric("ledger_explorer_ledger_end", "gauge", "Offset of the ledger end", ledger_end.to_string());
+        // Without both offsets, the lag is unknown and left out
+        if last_processed_offset >= 0 && ledger_end >= 0 {
+            metric(
+                "ledger_explorer_lag_offsets",
+                "gauge",
+                "Offsets between the ledger end and the last processed offset",
+                (ledger_end - last_processed_offset).max(0).to_string(),
+            );
+        }
+        metric(
+            "ledger_explorer_transactions_total",
+            "counter",
+            "Transactions written to Neo4j",
+            self.transactions.load(Ordering::Relaxed).to_string(),
+        );
+        metric(
+            "ledger_explorer_transactions_per_second",
+            "gauge",
+            "Transactions written to Neo4j per second",
+            f64::from_bits(self.transaction_rate.load(Ordering::Relaxed)).to_string(),
+        );
+        metric(
+            "ledger_explorer_reconnects_total",
+            "counter",
+            "Reconnections of the update stream",
+            self.reconnects.load(Ordering::Relaxed).to_string(),
+        );
+        metric(
+            "ledger_explorer_in_flight_batches",
+            "gauge",
+            "Batches submitted to the Neo4j writers and not yet committed",
+            self.in_flight_batches.load(Ordering::Relaxed).to_string(),
+        );
+        metric("ledger_explorer_token_refreshes_total", "counter", "JWT token refreshes", token_refreshes.to_string());
+        self.write_latency.render(
+            &mut out,
+            "ledger_explorer_neo4j_write_duration_seconds",
+            "Duration of the Neo4j commits of batches of updates",
+        );
+        out
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A histogram by the `WRITE_LATENCY_BUCKETS`, with its sum in microseconds
+struct Histogram {
+    buckets: [AtomicU64; WRITE_LATENCY_BUCKETS.len()],
+    count: AtomicU64,
+    sum_micros: AtomicU64,
+}
+
+impl Histogram {
+    const fn new() -> Self {
+        Self {
+            buckets: [const { AtomicU64::new(0) }; WRITE_LATENCY_BUCKETS.len()],
+            count: AtomicU64::new(0),
+            sum_micros: AtomicU64::new(0),
+        }
+    }
+
+    fn observe(&self, duration: Duration) {
+        let secs = duration.as_secs_f64();
+        if let Some(bucket) = WRITE_LATENCY_BUCKETS.iter().position(|bound| secs <= *bound) {
+            self.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        }
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum_micros.fetch_add(duration.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    /// Buckets are rendered cumulatively, as Prometheus expects
+    fn render(&self, out: &mut String, name: &str, help: &str) {
+        out.push_str(&format!("# HELP {} {}\n# TYPE {} histogram\n", name, help, name));
+        let mut cumulative = 0;
+        for (bound, bucket) in WRITE_LATENCY_BUCKETS.iter().zip(&self.buckets) {
+            cumulative += bucket.load(Ordering::Relaxed);
+            out.push_str(&format!("{}_bucket{{le=\"{}\"}} {}\n", name, bound, cumulative));
+        }
+        let count = self.count.load(Ordering::Relaxed);
+        out.push_str(&format!("{}_bucket{{le=\"+Inf\"}} {}\n", name, count));
+        out.push_str(&format!("{}_sum {}\n", name, self.sum_micros.load(Ordering::Relaxed) as f64 / 1e6));
+        out.push_str(&format!("{}_count {}\n", name, count));
+    }
+}
+
+/// Serves `METRICS` on `http://<listen_address>/metrics`, and updates the ledger end and the
+/// transaction rate in the background
+pub async fn start_metrics_server(
+    listen_address: &str,
+    ledger_url: String,
+    token_manager: Arc<TokenManager>,
+) -> Result<tokio::task::JoinHandle<()>> {
+    let listener = TcpListener::bind(listen_address)
+        .await
+        .with_context(|| format!("failed to bind metrics endpoint to '{}'", listen_address))?;
+    info!("Serving metrics on http://{}/metrics", listen_address);
+
+    let token_manager_for_ledger_end = Arc::clone(&token_manager);
+    tokio::spawn(async move {
+        let mut prev_transactions = METRICS.transactions.load(Ordering::Relaxed);
+        loop {
+            tokio::time::sleep(LEDGER_END_INTERVAL).await;
+            let transactions = METRICS.transactions.load(Ordering::Relaxed);
+            METRICS.set_transaction_rate((transactions - prev_transactions) as f64 / LEDGER_END_INTERVAL.as_secs_f64());
+            prev_transactions = transactions;
+
+            let ledger_end = match token_manager_for_ledger_end.get_token().await {
+                Ok(token) => client::ledger_end::get_ledger_end(&ledger_url, Some(&token)).await,
+                Err(e) => Err(e),
+            };
+            match ledger_end {
+                Ok(end) => METRICS.set_ledger_end(end),
+                Err(e) => debug!("[Metrics] Failed to get ledger end: {}", e),
+            }
+        }
+    });
+
+    Ok(tokio::spawn(async move {
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => {
+                    let token_manager = Arc::clone(&token_manager);
+                    tokio::spawn(async move {
+                        if let Err(e) = respond(stream, &token_manager).await {
+                            debug!("[Metrics] Failed to respond: {}", e);
+                        }
+                    });
+                }
+                Err(e) => warn!("[Metrics] Failed to accept connection: {}", e),
+            }
+        }
+    }))
+}
+
+/// Answers a single HTTP request, `GET /metrics` with the metrics, anything else with 404
+async fn respond(mut stream: TcpStream, token_manager: &TokenManager) -> std::io::Result<()> {
+    // The request line is all that is needed, the headers fit in the buffer
+    let mut request = [0u8; 4096];
+    let read = stream.read(&mut request).await?;
+    let request_line = String::from_utf8_lossy(&request[..read]);
+    let (status, body) = if request_line.starts_with("GET /metrics ") {
+        ("200 OK", METRICS.render(token_manager.refresh_count()))
+    } else {
+        ("404 Not Found", "Not Found\n".to_string())
+    };
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_metrics() {
+        let metrics = Metrics::new();
+        assert!(!metrics.render(0).contains("ledger_explorer_lag_offsets"));
+
+        metrics.set_last_processed_offset(90);
+        metrics.set_last_processed_offset(80);
+        metrics.set_ledger_end(100);
+        metrics.add_transactions(3);
+        metrics.set_in_flight_batches(2);
+        metrics.observe_write_latency(Duration::from_millis(40));
+        metrics.observe_write_latency(Duration::from_secs(60));
+        let rendered = metrics.render(2);
+
+        assert!(rendered.contains("\nledger_explorer_last_processed_offset 90\n"));
+        assert!(rendered.contains("\nledger_explorer_lag_offsets 10\n"));
+        assert!(rendered.contains("\nledger_explorer_transactions_total 3\n"));
+        assert!(rendered.contains("\nledger_explorer_token_refreshes_total 2\n"));
+        assert!(rendered.contains("\nledger_explorer_in_flight_batches 2\n"));
+        assert!(rendered.contains("# TYPE ledger_explorer_neo4j_write_duration_seconds histogram\n"));
+        assert!(rendered.contains("\nledger_explorer_neo4j_write_duration_seconds_bucket{le=\"0.025\"} 0\n"));
+        assert!(rendered.contains("\nledger_explorer_neo4j_write_duration_seconds_bucket{le=\"0.05\"} 1\n"));
+        assert!(rendered.contains("\nledger_explorer_neo4j_write_duration_seconds_bucket{le=\"30\"} 1\n"));
+        assert!(rendered.contains("\nledger_explorer_neo4j_write_duration_seconds_bucket{le=\"+Inf\"} 2\n"));
+        assert!(rendered.contains("\nledger_explorer_neo4j_write_duration_seconds_count 2\n"));
+    }
+}
@@ -0,0 +1,150 @@
+//! The package catalog: the Package nodes of the packages known to the participant, from the
+//! package management service, with their name and version, and `UPGRADES` edges from each
+//! version of a package to the previous one. Template nodes and the Package nodes of their
+//! packages are written for the templates of the contracts synced, see `cypher`; the catalog
+//! adds the packages without contracts, so the graph can be browsed from the packages down to
+//! the templates and their contracts.
+use crate::cypher::CypherQuery;
+use anyhow::Result;
+use chrono::DateTime;
+use client::jwt::TokenManager;
+use client::upload_dar::list_package_details;
+use ledger_api::v2::admin::PackageDetails;
+use neo4rs::Graph;
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+/// Refreshes the package catalog now and then every `interval`. Failures, e.g. for users without
+/// the admin rights listing packages requires, are logged and retried at the next refresh.
+pub fn start_package_refresh(
+    graph: Graph,
+    ledger_url: String,
+    token_manager: Arc<TokenManager>,
+    interval: Duration,
+    ledger_id: Option<String>,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        loop {
+            match refresh_package_catalog(&graph, &ledger_url, &token_manager, ledger_id.as_deref()).await {
+                Ok(count) => info!("Refreshed the catalog of {} known packages", count),
+                Err(e) => warn!("Failed to refresh package catalog: {:#}", e),
+            }
+            tokio::time::sleep(interval).await;
+        }
+    })
+}
+
+/// Merges the Package nodes of the packages known to the participant and the `UPGRADES` edges
+/// between their versions, returns the number of known packages
+pub async fn refresh_package_catalog(
+    graph: &Graph,
+    ledger_url: &str,
+    token_manager: &TokenManager,
+    ledger_id: Option<&str>,
+) -> Result<usize> {
+    let token = token_manager.get_token().await?;
+    let packages = list_package_details(ledger_url, Some(&token)).await?;
+    if packages.is_empty() {
+        return Ok(0);
+    }
+    let mut txn = graph.start_txn().await?;
+    txn.run_queries(package_catalog_to_cypher(&packages).into_iter().map(|cq| cq.scoped_to_ledger(ledger_id).query)).await?;
+    txn.commit().await?;
+    Ok(packages.len())
+}
+
+fn package_catalog_to_cypher(packages: &[PackageDetails]) -> Vec<CypherQuery> {
+    let rows = packages
+        .iter()
+        .map(|package| {
+            json!({
+                "package_id": package.package_id,
+                "name": package.name,
+                "version": package.version,
+                "package_size": package.package_size,
+                "known_since": package
+                    .known_since
+                    .as_ref()
+                    .and_then(|ts| DateTime::from_timestamp(ts.seconds, ts.nanos as u32))
+                    .map(|dt| dt.format("%Y-%m-%dT%H:%M:%SZ").to_string())
+                    .unwrap_or_default()
+            })
+        })
+        .collect();
+    let mut queries = vec![CypherQuery::unwind(
+        "UNWIND $packages AS p \
+        MERGE (package:Package { package_id: p.package_id }) \
+        SET \
+        package.name = p.name, \
+        package.version = p.version, \
+        package.package_size = p.package_size, \
+        package.known_since = p.known_since".to_string(),
+        "packages",
+        rows,
+    )];
+    let upgrades = upgrade_rows(packages);
+    if !upgrades.is_empty() {
+        queries.push(CypherQuery::unwind(
+            "UNWIND $upgrades AS u \
+            MATCH (newer:Package { package_id: u.newer }), (older:Package { package_id: u.older }) \
+            MERGE (newer)-[:UPGRADES]->(older)".to_string(),
+            "upgrades",
+            upgrades,
+        ));
+    }
+    queries
+}
+
+/// An edge from each version of a package to the previous one, by the numbers of the versions
+fn upgrade_rows(packages: &[PackageDetails]) -> Vec<serde_json::Value> {
+    let mut versions: BTreeMap<&str, Vec<(Vec<u64>, &str)>> = BTreeMap::new();
+    for package in packages.iter().filter(|package| !package.name.is_empty()) {
+        let version = package.version.split('.').map(|part| part.parse().unwrap_or(0)).collect();
+        versions.entry(&package.name).or_default().push((version, &package.package_id));
+    }
+    versions
+        .into_values()
+        .flat_map(|mut versions| {
+            versions.sort();
+            versions
+                .windows(2)
+                .map(|pair| json!({ "newer": pair[1].1, "older": pair[0].1 }))
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_upgrades_link_consecutive_versions() {
+        let package = |package_id: &str, name: &str, version: &str| PackageDetails {
+            package_id: package_id.to_string(),
+            name: name.to_string(),
+            version: version.to_string(),
+            ..Default::default()
+        };
+        let packages = [
+            package("a10", "assets", "1.10.0"),
+            package("a2", "assets", "1.2.0"),
+            package("a1", "assets", "1.0.0"),
+            package("s1", "stdlib", "2.0.0"),
+            package("x", "", ""),
+        ];
+        assert_eq!(
+            upgrade_rows(&packages),
+            vec![json!({ "newer": "a2", "older": "a1" }), json!({ "newer": "a10", "older": "a2" })]
+        );
+        let queries = package_catalog_to_cypher(&packages);
+        assert_eq!(queries.len(), 2);
+        for query in queries {
+            crate::cypher::assert_scoped_to_ledger(&query.scoped_to_ledger(Some("participant1")).cypher);
+        }
+    }
+}
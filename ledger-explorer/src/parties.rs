@@ -0,0 +1,125 @@
+//! Metadata of the Party nodes, from the party management service of the participant: a display
+//! name, whether the party is hosted on the participant, and its identity provider. Party nodes
+//! otherwise only have their id; the metadata is refreshed periodically during the sync.
+use crate::cypher::CypherQuery;
+use anyhow::Result;
+use client::jwt::TokenManager;
+use client::party_management::get_parties::list_party_details;
+use ledger_api::v2::admin::PartyDetails;
+use neo4rs::Graph;
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+/// Annotation of the local metadata of a party holding its display name
+pub const DISPLAY_NAME_ANNOTATION: &str = "display_name";
+
+/// Refreshes the metadata of the Party nodes now and then every `interval`. Failures, e.g. for
+/// users without the admin rights listing parties requires, are logged and retried at the next
+/// refresh.
+pub fn start_party_refresh(
+    graph: Graph,
+    ledger_url: String,
+    token_manager: Arc<TokenManager>,
+    interval: Duration,
+    ledger_id: Option<String>,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        loop {
+            match refresh_party_metadata(&graph, &ledger_url, &token_manager, ledger_id.as_deref()).await {
+                Ok(count) => info!("Refreshed the metadata of {} known parties", count),
+                Err(e) => warn!("Failed to refresh party metadata: {:#}", e),
+            }
+            tokio::time::sleep(interval).await;
+        }
+    })
+}
+
+/// Sets the metadata of the Party nodes of the parties known to the participant, returns the
+/// number of known parties. Parties without a node in the graph yet get it at a later refresh.
+pub async fn refresh_party_metadata(
+    graph: &Graph,
+    ledger_url: &str,
+    token_manager: &TokenManager,
+    ledger_id: Option<&str>,
+) -> Result<usize> {
+    let token = token_manager.get_token().await?;
+    let parties = list_party_details(ledger_url.to_string(), Some(&token), None, 0).await?;
+    if parties.is_empty() {
+        return Ok(0);
+    }
+    let refreshed_at = chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string();
+    let rows = parties.iter().map(|party| party_metadata_row(party, &refreshed_at)).collect();
+    graph.run(party_metadata_to_cypher(rows).scoped_to_ledger(ledger_id).query).await?;
+    Ok(parties.len())
+}
+
+fn party_metadata_to_cypher(rows: Vec<serde_json::Value>) -> CypherQuery {
+    CypherQuery::unwind(
+        "UNWIND $parties AS p \
+        MATCH (party:Party { party_id: p.party_id }) \
+        SET \
+        party.display_name = p.display_name, \
+        party.is_local = p.is_local, \
+        party.identity_provider_id = p.identity_provider_id, \
+        party.annotations = p.annotations, \
+        party.metadata_refreshed_at = p.refreshed_at".to_string(),
+        "parties",
+        rows,
+    )
+}
+
+/// The metadata of a party, with the display name of its annotation or else the hint of its id,
+/// the part before `::`
+fn party_metadata_row(party: &PartyDetails, refreshed_at: &str) -> serde_json::Value {
+    let annotations = party.local_metadata.as_ref().map(|metadata| &metadata.annotations);
+    let display_name = annotations
+        .and_then(|annotations| annotations.get(DISPLAY_NAME_ANNOTATION))
+        .cloned()
+        .unwrap_or_else(|| party.party.split("::").next().unwrap_or_default().to_string());
+    json!({
+        "party_id": party.party,
+        "display_name": display_name,
+        "is_local": party.is_local,
+        "identity_provider_id": party.identity_provider_id,
+        "annotations": serde_json::to_string(annotations.unwrap_or(&HashMap::new())).unwrap_or("{}".to_string()),
+        "refreshed_at": refreshed_at
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ledger_api::v2::admin::ObjectMeta;
+
+    #[test]
+    fn test_party_metadata_row() {
+        let party = |annotations: &[(&str, &str)]| PartyDetails {
+            party: "alice::1220abcd".to_string(),
+            is_local: true,
+            local_metadata: Some(ObjectMeta {
+                resource_version: "1".to_string(),
+                annotations: annotations.iter().map(|(key, value)| (key.to_string(), value.to_string())).collect(),
+            }),
+            identity_provider_id: String::new(),
+        };
+        let row = party_metadata_row(&party(&[]), "2026-10-14T00:00:00Z");
+        assert_eq!(
+            row,
+            json!({
+                "party_id": "alice::1220abcd",
+                "display_name": "alice",
+                "is_local": true,
+                "identity_provider_id": "",
+                "annotations": "{}",
+                "refreshed_at": "2026-10-14T00:00:00Z"
+            })
+        );
+        let row = party_metadata_row(&party(&[(DISPLAY_NAME_ANNOTATION, "Alice Ltd")]), "2026-10-14T00:00:00Z");
+        assert_eq!(row["display_name"], "Alice Ltd");
+        crate::cypher::assert_scoped_to_ledger(&party_metadata_to_cypher(vec![row]).scoped_to_ledger(Some("participant1")).cypher);
+    }
+}
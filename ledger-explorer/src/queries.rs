@@ -0,0 +1,405 @@
+//! Canned read queries over the synced graph, answered as JSON by the `serve` API and printed
+//! by the `lineage` and `stats` commands. Nodes are returned as the maps of their properties.
+//! With a `ledger_id`, the queries only read the nodes of that ledger of a shared graph.
+use crate::cypher::CypherQuery;
+use anyhow::Result;
+use neo4rs::Graph;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// The parent contracts of the contracts of `$contract_ids`: the targets of the exercises
+/// creating them
+const PARENTS: &str = "MATCH (parent:Created)<-[:TARGET]-(e:Exercised)-[:CONSEQUENCE]->(child:Created) \
+    WHERE child.contract_id IN $contract_ids \
+    RETURN parent.contract_id AS parent_contract_id, parent.template_name AS parent_template_name, \
+        e.choice_name AS choice_name, e.offset AS exercise_offset, e.consuming AS consuming, \
+        child.contract_id AS child_contract_id, child.template_name AS child_template_name \
+    ORDER BY e.offset, e.node_id";
+
+/// The child contracts of the contracts of `$contract_ids`: the ones created by the exercises
+/// on them
+const CHILDREN: &str = "MATCH (parent:Created)<-[:TARGET]-(e:Exercised)-[:CONSEQUENCE]->(child:Created) \
+    WHERE parent.contract_id IN $contract_ids \
+    RETURN parent.contract_id AS parent_contract_id, parent.template_name AS parent_template_name, \
+        e.choice_name AS choice_name, e.offset AS exercise_offset, e.consuming AS consuming, \
+        child.contract_id AS child_contract_id, child.template_name AS child_template_name \
+    ORDER BY e.offset, child.node_id";
+
+/// An exercise on a parent contract creating a child contract
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LineageEdge {
+    /// Number of exercises between the contract of the lineage and the farther contract
+    #[serde(default)]
+    pub depth: u32,
+    pub parent_contract_id: String,
+    pub parent_template_name: Option<String>,
+    pub choice_name: Option<String>,
+    pub exercise_offset: i64,
+    /// Whether the exercise consumed the parent contract
+    pub consuming: Option<bool>,
+    pub child_contract_id: String,
+    pub child_template_name: Option<String>,
+}
+
+/// The exercise consuming a contract
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConsumingExercise {
+    pub choice_name: Option<String>,
+    pub offset: i64,
+    #[serde(default)]
+    pub acting_parties: Vec<String>,
+}
+
+/// The contracts a contract was created from, and the ones created from it, transitively
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Lineage {
+    pub contract_id: String,
+    pub template_name: Option<String>,
+    /// The offset of the archival of the contract, `None` while active
+    pub archived_at_offset: Option<i64>,
+    /// The exercise consuming the contract, `None` while active or if archived without exercise
+    pub consumed_by: Option<ConsumingExercise>,
+    pub ancestors: Vec<LineageEdge>,
+    pub descendants: Vec<LineageEdge>,
+}
+
+/// The Created node of the contract, with the exercises on it in offset order, `None` if unknown
+pub async fn contract(
+    graph: &Graph,
+    contract_id: &str,
+    ledger_id: Option<&str>,
+) -> Result<Option<serde_json::Value>> {
+    let mut result = graph
+        .execute(
+            CypherQuery::new(
+                "MATCH (c:Created {contract_id: $contract_id}) \
+                 OPTIONAL MATCH (e:Exercised)-[:TARGET]->(c) \
+                 WITH c, e ORDER BY e.offset, e.node_id \
+                 WITH c, collect(e {.offset, .node_id, .choice_name, .consuming, .acting_parties}) AS exercises \
+                 RETURN c {.*, exercises: exercises} AS contract"
+                    .to_string(),
+            )
+            .with_param("contract_id", contract_id)
+            .scoped_to_ledger(ledger_id)
+            .query,
+        )
+        .await?;
+    match result.next().await? {
+        Some(row) => Ok(Some(row.get("contract")?)),
+        None => Ok(None),
+    }
+}
+
+/// What a submitter needs to disclose a contract explicitly, from the Created node synced with
+/// its created event blob
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DisclosedContract {
+    pub contract_id: String,
+    /// `<package id>:<Module.Name>:<Entity>`
+    pub template_id: Option<String>,
+    /// In base64
+    pub created_event_blob: String,
+    pub synchronizer_id: Option<String>,
+}
+
+/// The disclosure of the contract, `None` if unknown or synced without its blob
+pub async fn disclosed_contract(
+    graph: &Graph,
+    contract_id: &str,
+    ledger_id: Option<&str>,
+) -> Result<Option<DisclosedContract>> {
+    let mut result = graph
+        .execute(
+            CypherQuery::new(
+                "MATCH (c:Created {contract_id: $contract_id}) WHERE c.created_event_blob IS NOT NULL \
+                 OPTIONAL MATCH (c)-[:INSTANCE_OF]->(t:Template) \
+                 RETURN c.contract_id AS contract_id, t.template_id AS template_id, \
+                     c.created_event_blob AS created_event_blob, c.synchronizer_id AS synchronizer_id \
+                 LIMIT 1"
+                    .to_string(),
+            )
+            .with_param("contract_id", contract_id)
+            .scoped_to_ledger(ledger_id)
+            .query,
+        )
+        .await?;
+    match result.next().await? {
+        Some(row) => Ok(Some(row.to::<DisclosedContract>()?)),
+        None => Ok(None),
+    }
+}
+
+/// The lineage of the contract up to `max_depth` exercises away, in breadth-first order, `None`
+/// if the contract is unknown
+pub async fn lineage(
+    graph: &Graph,
+    contract_id: &str,
+    max_depth: u32,
+    ledger_id: Option<&str>,
+) -> Result<Option<Lineage>> {
+    let mut result = graph
+        .execute(
+            CypherQuery::new(
+                "MATCH (c:Created {contract_id: $contract_id}) \
+                 OPTIONAL MATCH (e:Exercised)-[:CONSUMES]->(c) \
+                 RETURN c.template_name AS template_name, c.archived_at_offset AS archived_at_offset, \
+                     e {.choice_name, .offset, .acting_parties} AS consumed_by \
+                 LIMIT 1"
+                    .to_string(),
+            )
+            .with_param("contract_id", contract_id)
+            .scoped_to_ledger(ledger_id)
+            .query,
+        )
+        .await?;
+    let Some(row) = result.next().await? else {
+        return Ok(None);
+    };
+    Ok(Some(Lineage {
+        contract_id: contract_id.to_string(),
+        template_name: row.get("template_name")?,
+        archived_at_offset: row.get("archived_at_offset")?,
+        consumed_by: row.get("consumed_by")?,
+        ancestors: walk(graph, contract_id, max_depth, true, ledger_id).await?,
+        descendants: walk(graph, contract_id, max_depth, false, ledger_id).await?,
+    }))
+}
+
+/// The edges to the ancestors or the descendants of the contract, one query per level
+async fn walk(
+    graph: &Graph,
+    contract_id: &str,
+    max_depth: u32,
+    ancestors: bool,
+    ledger_id: Option<&str>,
+) -> Result<Vec<LineageEdge>> {
+    let mut seen = HashSet::from([contract_id.to_string()]);
+    let mut frontier = vec![contract_id.to_string()];
+    let mut edges = Vec::new();
+    for depth in 1..=max_depth {
+        if frontier.is_empty() {
+            break;
+        }
+        let cypher = if ancestors { PARENTS } else { CHILDREN };
+        let query = CypherQuery::new(cypher.to_string()).with_param("contract_ids", frontier);
+        let mut result = graph.execute(query.scoped_to_ledger(ledger_id).query).await?;
+        let mut level = Vec::new();
+        while let Some(row) = result.next().await? {
+            level.push(LineageEdge { depth, ..row.to::<LineageEdge>()? });
+        }
+        frontier = next_frontier(&level, &mut seen, ancestors);
+        edges.extend(level);
+    }
+    Ok(edges)
+}
+
+/// The contracts reached by the edges of a level which were not reached before
+fn next_frontier(level: &[LineageEdge], seen: &mut HashSet<String>, ancestors: bool) -> Vec<String> {
+    level
+        .iter()
+        .map(|edge| if ancestors { &edge.parent_contract_id } else { &edge.child_contract_id })
+        .filter(|contract_id| seen.insert(contract_id.to_string()))
+        .cloned()
+        .collect()
+}
+
+/// The Transaction nodes of the offset range in offset order, at most `limit`
+pub async fn transactions(
+    graph: &Graph,
+    begin_exclusive: i64,
+    end_inclusive: Option<i64>,
+    limit: i64,
+    ledger_id: Option<&str>,
+) -> Result<Vec<serde_json::Value>> {
+    let mut result = graph
+        .execute(
+            CypherQuery::new(
+                "MATCH (t:Transaction) \
+                 WHERE t.offset > $begin_exclusive AND ($end_inclusive IS NULL OR t.offset <= $end_inclusive) \
+                 RETURN t {.*} AS transaction ORDER BY t.offset LIMIT $limit"
+                    .to_string(),
+            )
+            .with_param("begin_exclusive", begin_exclusive)
+            .with_param("end_inclusive", end_inclusive)
+            .with_param("limit", limit)
+            .scoped_to_ledger(ledger_id)
+            .query,
+        )
+        .await?;
+    let mut transactions = Vec::new();
+    while let Some(row) = result.next().await? {
+        transactions.push(row.get("transaction")?);
+    }
+    Ok(transactions)
+}
+
+/// The active contracts of the template in creation order, at most `limit`
+pub async fn active_contracts(
+    graph: &Graph,
+    template_name: &str,
+    limit: i64,
+    ledger_id: Option<&str>,
+) -> Result<Vec<serde_json::Value>> {
+    let mut result = graph
+        .execute(
+            CypherQuery::new(
+                "MATCH (c:Created {template_name: $template_name, active: true}) \
+                 RETURN c {.*} AS contract ORDER BY c.offset, c.node_id LIMIT $limit"
+                    .to_string(),
+            )
+            .with_param("template_name", template_name)
+            .with_param("limit", limit)
+            .scoped_to_ledger(ledger_id)
+            .query,
+        )
+        .await?;
+    let mut contracts = Vec::new();
+    while let Some(row) = result.next().await? {
+        contracts.push(row.get("contract")?);
+    }
+    Ok(contracts)
+}
+
+/// Contracts of a template
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TemplateStats {
+    pub template_name: String,
+    pub contracts: i64,
+    pub active: i64,
+    pub archived: i64,
+}
+
+/// Exercises of a choice, by the template of the contracts exercised
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChoiceStats {
+    pub template_name: Option<String>,
+    pub choice_name: Option<String>,
+    pub exercises: i64,
+    pub consuming: i64,
+}
+
+/// Transactions of a day of effective time
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DayStats {
+    /// `YYYY-MM-DD`
+    pub date: String,
+    pub transactions: i64,
+}
+
+/// Counts of the contracts, exercises and transactions of the graph
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Stats {
+    /// `YYYY-MM-DD` of the first day counted, `None` for all
+    pub since: Option<String>,
+    pub contracts: i64,
+    pub active: i64,
+    pub archived: i64,
+    /// By number of contracts, descending
+    pub templates: Vec<TemplateStats>,
+    /// By number of exercises, descending
+    pub choices: Vec<ChoiceStats>,
+    pub transactions_per_day: Vec<DayStats>,
+}
+
+/// The statistics of the contracts created, the choices exercised and the transactions
+/// effective on `since` (`YYYY-MM-DD`) or later, or of all of them. Contracts only known as
+/// targets of exercises, without their created event, are not counted.
+pub async fn stats(graph: &Graph, since: Option<&str>, ledger_id: Option<&str>) -> Result<Stats> {
+    let templates: Vec<TemplateStats> = rows(
+        graph,
+        CypherQuery::new(
+            "MATCH (c:Created) \
+             WHERE c.template_name IS NOT NULL AND ($since IS NULL OR c.created_at >= $since) \
+             WITH c.template_name AS template_name, count(c) AS contracts, \
+                 sum(CASE WHEN c.active THEN 1 ELSE 0 END) AS active \
+             RETURN template_name, contracts, active, contracts - active AS archived \
+             ORDER BY contracts DESC, template_name"
+                .to_string(),
+        )
+        .with_param("since", since)
+        .scoped_to_ledger(ledger_id),
+    )
+    .await?;
+    let choices = rows(
+        graph,
+        CypherQuery::new(
+            "MATCH (e:Exercised) \
+             WHERE $since IS NULL OR e.transaction_effective_at >= $since \
+             OPTIONAL MATCH (e)-[:TARGET]->(c:Created) \
+             RETURN c.template_name AS template_name, e.choice_name AS choice_name, count(e) AS exercises, \
+                 sum(CASE WHEN e.consuming THEN 1 ELSE 0 END) AS consuming \
+             ORDER BY exercises DESC, template_name, choice_name"
+                .to_string(),
+        )
+        .with_param("since", since)
+        .scoped_to_ledger(ledger_id),
+    )
+    .await?;
+    let transactions_per_day = rows(
+        graph,
+        CypherQuery::new(
+            "MATCH (t:Transaction) \
+             WHERE t.effective_at <> '' AND ($since IS NULL OR t.effective_at >= $since) \
+             RETURN substring(t.effective_at, 0, 10) AS date, count(t) AS transactions \
+             ORDER BY date"
+                .to_string(),
+        )
+        .with_param("since", since)
+        .scoped_to_ledger(ledger_id),
+    )
+    .await?;
+    Ok(Stats {
+        since: since.map(str::to_string),
+        contracts: templates.iter().map(|template| template.contracts).sum(),
+        active: templates.iter().map(|template| template.active).sum(),
+        archived: templates.iter().map(|template| template.archived).sum(),
+        templates,
+        choices,
+        transactions_per_day,
+    })
+}
+
+/// The rows of the query, by the names of their columns
+async fn rows<T: serde::de::DeserializeOwned>(graph: &Graph, query: CypherQuery) -> Result<Vec<T>> {
+    let mut result = graph.execute(query.query).await?;
+    let mut rows = Vec::new();
+    while let Some(row) = result.next().await? {
+        rows.push(row.to::<T>()?);
+    }
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_frontier_skips_contracts_reached_before() {
+        let edge = |parent: &str, child: &str| LineageEdge {
+            depth: 1,
+            parent_contract_id: parent.to_string(),
+            parent_template_name: None,
+            choice_name: Some("Transfer".to_string()),
+            exercise_offset: 1,
+            consuming: Some(true),
+            child_contract_id: child.to_string(),
+            child_template_name: None,
+        };
+        let mut seen = HashSet::from(["a".to_string()]);
+        let level = [edge("a", "b"), edge("a", "c"), edge("a", "b")];
+        assert_eq!(next_frontier(&level, &mut seen, false), vec!["b", "c"]);
+
+        // A cycle back to a contract of the lineage ends the walk
+        assert!(next_frontier(&[edge("b", "a"), edge("c", "b")], &mut seen, false).is_empty());
+        assert_eq!(next_frontier(&[edge("z", "a")], &mut seen, true), vec!["z"]);
+    }
+
+    #[test]
+    fn test_lineage_walk_scoped_to_ledger() {
+        for cypher in [PARENTS, CHILDREN] {
+            let scoped = CypherQuery::new(cypher.to_string()).scoped_to_ledger(Some("participant1")).cypher;
+            assert_eq!(scoped.matches("{ ledger_id: $ledger_id }").count(), 3, "{}", scoped);
+            crate::cypher::assert_scoped_to_ledger(&scoped);
+        }
+    }
+}
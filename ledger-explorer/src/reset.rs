@@ -0,0 +1,132 @@
+//! Scoped deletion of the synced data by the `reset` command: the contracts of some templates,
+//! the updates from an offset on, or everything, instead of clearing the whole database with
+//! `sync --fresh`. The SyncState is rewound with the updates deleted, so that the next sync
+//! streams them again.
+use crate::cypher::CypherQuery;
+use crate::graph::clear_neo4j_database;
+use anyhow::{Result, bail};
+use neo4rs::Graph;
+use serde_json::json;
+use tracing::info;
+
+/// What `reset` deletes
+#[derive(Debug, Clone, Default)]
+pub struct ResetScope {
+    /// Only the contracts of these template names, with the exercises on them
+    pub templates: Vec<String>,
+    /// Only the updates at this offset and later, rewinding the SyncState to the offset before
+    pub before_offset: Option<i64>,
+    /// Everything, including the SyncState
+    pub all: bool,
+}
+
+/// Deletes the scope from the graph, of the ledger in a graph shared by several ledgers, in one
+/// Neo4j transaction
+pub async fn reset(graph: &Graph, scope: &ResetScope, ledger_id: Option<&str>) -> Result<()> {
+    if scope.all {
+        return clear_neo4j_database(graph, ledger_id).await;
+    }
+    let queries = reset_to_cypher(scope)?;
+    let mut txn = graph.start_txn().await?;
+    txn.run_queries(queries.into_iter().map(|cq| cq.scoped_to_ledger(ledger_id).query)).await?;
+    txn.commit().await?;
+    match scope.before_offset {
+        Some(offset) => info!("Reset the graph to before offset {}, the next sync resumes from offset {}", offset, offset - 1),
+        None => info!("Deleted the contracts of {:?}", scope.templates),
+    }
+    Ok(())
+}
+
+/// The queries deleting the scope, other than `all`.
+///
+/// From an offset on, the Transaction, Created and Exercised nodes of the offsets are deleted,
+/// the contracts they archived active again, and the SyncState rewound; with templates, only
+/// their contracts and the exercises on them, the transactions being merged again by the next
+/// sync. Without offset, the contracts of the templates are deleted whatever their offset, not
+/// to come back before a `--fresh` sync. Keys without contracts left are deleted.
+fn reset_to_cypher(scope: &ResetScope) -> Result<Vec<CypherQuery>> {
+    let templates = (!scope.templates.is_empty()).then_some(&scope.templates);
+    let Some(offset) = scope.before_offset else {
+        let Some(templates) = templates else {
+            bail!("Nothing to reset: select --templates, --before-offset or --all");
+        };
+        return Ok(vec![
+            CypherQuery::new(
+                "MATCH (e:Exercised)-[:TARGET]->(c:Created) WHERE c.template_name IN $templates DETACH DELETE e".to_string(),
+            )
+            .with_json_param("templates", json!(templates)),
+            CypherQuery::new("MATCH (c:Created) WHERE c.template_name IN $templates DETACH DELETE c".to_string())
+                .with_json_param("templates", json!(templates)),
+            CypherQuery::new("MATCH (k:Key) WHERE NOT (k)<-[:KEYED_BY]-() DELETE k".to_string()),
+        ]);
+    };
+    if offset < 1 {
+        bail!("--before-offset must be at least 1, reset everything with --all");
+    }
+    let (of_templates, exercises_of_templates) = match templates {
+        Some(_) => (
+            " AND c.template_name IN $templates",
+            " AND EXISTS { MATCH (e)-[:TARGET]->(c:Created) WHERE c.template_name IN $templates }",
+        ),
+        None => ("", ""),
+    };
+    let mut queries = vec![
+        CypherQuery::new(format!(
+            "MATCH (e:Exercised) WHERE e.offset >= $offset{} DETACH DELETE e",
+            exercises_of_templates
+        )),
+        CypherQuery::new(format!("MATCH (c:Created) WHERE c.offset >= $offset{} DETACH DELETE c", of_templates)),
+        CypherQuery::new(format!(
+            "MATCH (c:Created) WHERE c.archived_at_offset >= $offset{} SET c.active = true REMOVE c.archived_at_offset",
+            of_templates
+        )),
+    ];
+    if templates.is_none() {
+        queries.push(CypherQuery::new("MATCH (t:Transaction) WHERE t.offset >= $offset DETACH DELETE t".to_string()));
+    }
+    queries.push(CypherQuery::new("MATCH (k:Key) WHERE NOT (k)<-[:KEYED_BY]-() DELETE k".to_string()));
+    queries.push(CypherQuery::new(
+        "MATCH (s:SyncState { id: 'sync' }) WHERE s.last_offset >= $offset \
+        SET s.last_offset = $offset - 1, s.last_update_id = null"
+            .to_string(),
+    ));
+    Ok(queries
+        .into_iter()
+        .map(|query| {
+            let query = query.with_json_param("offset", json!(offset));
+            match templates {
+                Some(templates) => query.with_json_param("templates", json!(templates)),
+                None => query,
+            }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reset_to_cypher() -> Result<()> {
+        assert!(reset_to_cypher(&ResetScope::default()).is_err());
+        assert!(reset_to_cypher(&ResetScope { before_offset: Some(0), ..Default::default() }).is_err());
+
+        let rewind = reset_to_cypher(&ResetScope { before_offset: Some(10), ..Default::default() })?;
+        assert!(rewind.iter().any(|q| q.cypher.contains("(t:Transaction) WHERE t.offset >= $offset")));
+        assert!(rewind.last().unwrap().cypher.contains("SET s.last_offset = $offset - 1"));
+        assert!(rewind.iter().all(|q| q.query.has_param_key("offset")));
+
+        let templates = vec!["Main.Asset".to_string()];
+        let rewind_templates = reset_to_cypher(&ResetScope { templates: templates.clone(), before_offset: Some(10), ..Default::default() })?;
+        assert!(!rewind_templates.iter().any(|q| q.cypher.contains("Transaction")));
+        assert!(rewind_templates[0].cypher.contains("EXISTS { MATCH (e)-[:TARGET]->(c:Created)"));
+
+        let delete_templates = reset_to_cypher(&ResetScope { templates, ..Default::default() })?;
+        assert!(!delete_templates.iter().any(|q| q.cypher.contains("SyncState")));
+
+        for query in rewind.into_iter().chain(rewind_templates).chain(delete_templates) {
+            crate::cypher::assert_scoped_to_ledger(&query.scoped_to_ledger(Some("participant1")).cypher);
+        }
+        Ok(())
+    }
+}
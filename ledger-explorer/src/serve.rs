@@ -0,0 +1,194 @@
+//! The `serve` command: a read-only REST API over the synced graph, answering the canned queries
+//! of `queries` as JSON, so that front-ends and scripts can query the graph without speaking Bolt.
+//!
+//! - `GET /contracts/{contract_id}`: the contract with the exercises on it
+//! - `GET /contracts/{contract_id}/disclosure`: the created event blob of the contract, synced
+//!   with `created_event_blobs`, for explicit disclosure
+//! - `GET /contracts/{contract_id}/lineage?depth=`: the contracts it was created from and the
+//!   ones created from it
+//! - `GET /transactions?begin_exclusive=&end_inclusive=&limit=`: the transactions of an offset range
+//! - `GET /templates/{template_name}/active?limit=`: the active contracts of a template
+use crate::config::{Neo4jConfig, ServeConfig};
+use crate::graph::connect_graph;
+use crate::queries;
+use crate::sync::shutdown_signal;
+use anyhow::{Context, Result};
+use axum::Router;
+use axum::extract::{Path, RawQuery, State};
+use axum::http::{StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use neo4rs::Graph;
+use serde::Serialize;
+use std::collections::HashMap;
+use tracing::{error, info};
+
+/// Rows of a response without `limit` parameter, if fewer than `max_rows`
+const DEFAULT_LIMIT: i64 = 100;
+
+/// Exercises between a contract and the farthest contracts of its lineage, by default and at most
+const DEFAULT_LINEAGE_DEPTH: i64 = 10;
+const MAX_LINEAGE_DEPTH: i64 = 100;
+
+#[derive(Clone)]
+struct ApiState {
+    graph: Graph,
+    max_rows: i64,
+    /// The ledger of a shared graph the queries read, `None` for all
+    ledger_id: Option<String>,
+}
+
+/// Serves the API on `http://<listen_address>` until SIGINT or SIGTERM, over the nodes of the
+/// ledger of `ledger_id` in a graph shared by several ledgers
+pub async fn serve(neo4j: &Neo4jConfig, serve_config: &ServeConfig, ledger_id: Option<String>) -> Result<()> {
+    let graph = connect_graph(&neo4j.uri, &neo4j.user, &neo4j.password, neo4j.database.as_deref())?;
+    let listener = tokio::net::TcpListener::bind(&serve_config.listen_address)
+        .await
+        .with_context(|| format!("failed to bind query API to '{}'", serve_config.listen_address))?;
+    info!("Serving the query API on http://{}", serve_config.listen_address);
+    axum::serve(listener, router(graph, serve_config.max_rows, ledger_id))
+        .with_graceful_shutdown(shutdown_signal())
+        .await?;
+    info!("Query API shut down");
+    Ok(())
+}
+
+fn router(graph: Graph, max_rows: i64, ledger_id: Option<String>) -> Router {
+    Router::new()
+        .route("/contracts/{contract_id}", get(contract))
+        .route("/contracts/{contract_id}/disclosure", get(disclosure))
+        .route("/contracts/{contract_id}/lineage", get(lineage))
+        .route("/transactions", get(transactions))
+        .route("/templates/{template_name}/active", get(active_contracts))
+        .with_state(ApiState { graph, max_rows, ledger_id })
+}
+
+async fn contract(State(state): State<ApiState>, Path(contract_id): Path<String>) -> Result<Response, ApiError> {
+    match queries::contract(&state.graph, &contract_id, state.ledger_id.as_deref()).await? {
+        Some(contract) => json(&contract),
+        None => Err(ApiError::NotFound(format!("unknown contract '{}'", contract_id))),
+    }
+}
+
+async fn disclosure(State(state): State<ApiState>, Path(contract_id): Path<String>) -> Result<Response, ApiError> {
+    match queries::disclosed_contract(&state.graph, &contract_id, state.ledger_id.as_deref()).await? {
+        Some(disclosed) => json(&disclosed),
+        None => Err(ApiError::NotFound(format!("no created event blob of contract '{}'", contract_id))),
+    }
+}
+
+async fn lineage(
+    State(state): State<ApiState>,
+    Path(contract_id): Path<String>,
+    RawQuery(raw_query): RawQuery,
+) -> Result<Response, ApiError> {
+    let params = QueryParams::parse(raw_query.as_deref(), &["depth"])?;
+    let depth = params.int("depth")?.unwrap_or(DEFAULT_LINEAGE_DEPTH).clamp(1, MAX_LINEAGE_DEPTH);
+    match queries::lineage(&state.graph, &contract_id, depth as u32, state.ledger_id.as_deref()).await? {
+        Some(lineage) => json(&lineage),
+        None => Err(ApiError::NotFound(format!("unknown contract '{}'", contract_id))),
+    }
+}
+
+async fn transactions(State(state): State<ApiState>, RawQuery(raw_query): RawQuery) -> Result<Response, ApiError> {
+    let params = QueryParams::parse(raw_query.as_deref(), &["begin_exclusive", "end_inclusive", "limit"])?;
+    let limit = params.limit(state.max_rows)?;
+    let begin_exclusive = params.int("begin_exclusive")?.unwrap_or(0);
+    let end_inclusive = params.int("end_inclusive")?;
+    json(&queries::transactions(&state.graph, begin_exclusive, end_inclusive, limit, state.ledger_id.as_deref()).await?)
+}
+
+async fn active_contracts(
+    State(state): State<ApiState>,
+    Path(template_name): Path<String>,
+    RawQuery(raw_query): RawQuery,
+) -> Result<Response, ApiError> {
+    let params = QueryParams::parse(raw_query.as_deref(), &["limit"])?;
+    let limit = params.limit(state.max_rows)?;
+    json(&queries::active_contracts(&state.graph, &template_name, limit, state.ledger_id.as_deref()).await?)
+}
+
+fn json(value: &impl Serialize) -> Result<Response, ApiError> {
+    let body = serde_json::to_string(value).map_err(anyhow::Error::from)?;
+    Ok(([(header::CONTENT_TYPE, "application/json")], body).into_response())
+}
+
+/// The parameters of a query string
+struct QueryParams(HashMap<String, String>);
+
+impl QueryParams {
+    /// Rejects the parameters not in `known`, so that typos are not silently ignored
+    fn parse(raw_query: Option<&str>, known: &[&str]) -> Result<Self, ApiError> {
+        let params: HashMap<String, String> =
+            form_urlencoded::parse(raw_query.unwrap_or_default().as_bytes()).into_owned().collect();
+        if let Some(unknown) = params.keys().find(|name| !known.contains(&name.as_str())) {
+            return Err(ApiError::BadRequest(format!(
+                "unknown parameter '{}', expected one of: {}",
+                unknown,
+                known.join(", ")
+            )));
+        }
+        Ok(Self(params))
+    }
+
+    fn int(&self, name: &str) -> Result<Option<i64>, ApiError> {
+        self.0
+            .get(name)
+            .map(|value| {
+                value
+                    .parse()
+                    .map_err(|_| ApiError::BadRequest(format!("parameter '{}' is not an integer: '{}'", name, value)))
+            })
+            .transpose()
+    }
+
+    /// The `limit` parameter, at most `max_rows`
+    fn limit(&self, max_rows: i64) -> Result<i64, ApiError> {
+        Ok(self.int("limit")?.unwrap_or(DEFAULT_LIMIT).clamp(1, max_rows.max(1)))
+    }
+}
+
+/// An error answered as `{"error": "<message>"}`
+enum ApiError {
+    BadRequest(String),
+    NotFound(String),
+    Internal(anyhow::Error),
+}
+
+impl From<anyhow::Error> for ApiError {
+    fn from(e: anyhow::Error) -> Self {
+        ApiError::Internal(e)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let (status, message) = match self {
+            ApiError::BadRequest(message) => (StatusCode::BAD_REQUEST, message),
+            ApiError::NotFound(message) => (StatusCode::NOT_FOUND, message),
+            ApiError::Internal(e) => {
+                error!("Query failed: {:#}", e);
+                (StatusCode::INTERNAL_SERVER_ERROR, format!("{:#}", e))
+            }
+        };
+        let body = serde_json::json!({ "error": message }).to_string();
+        (status, [(header::CONTENT_TYPE, "application/json")], body).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_query_params() {
+        let params = QueryParams::parse(Some("begin_exclusive=10&limit=5000"), &["begin_exclusive", "limit"]).ok().unwrap();
+        assert_eq!(params.int("begin_exclusive").ok().flatten(), Some(10));
+        assert_eq!(params.limit(1000).ok(), Some(1000));
+        assert_eq!(QueryParams::parse(None, &["limit"]).ok().unwrap().limit(1000).ok(), Some(DEFAULT_LIMIT));
+
+        assert!(matches!(QueryParams::parse(Some("limt=5"), &["limit"]), Err(ApiError::BadRequest(_))));
+        let params = QueryParams::parse(Some("limit=ten"), &["limit"]).ok().unwrap();
+        assert!(matches!(params.limit(1000), Err(ApiError::BadRequest(_))));
+    }
+}
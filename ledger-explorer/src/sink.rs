@@ -0,0 +1,140 @@
+//! Sinks other than Neo4j for the events of the ledger, e.g. `kafka::KafkaSink`, implementing
+//! `sync::Sink` with an `EventBatch`. They are fed by `run_sink_sync` through the sync loop of
+//! the Neo4j sync, `sync::run_sync`, with the same stream decoding, reconnection, token refresh
+//! and graceful shutdown. Their resume point is the offset of the last update written,
+//! checkpointed in a file after each batch, so updates are written at least once: a batch
+//! interrupted before its checkpoint is written again on resume.
+use crate::config::Parties;
+use crate::events::{EventRecord, event_records, update_offset};
+use crate::metrics::METRICS;
+use crate::sync::{BackoffConfig, Sink, StreamConfig, run_sync};
+use anyhow::{Context, Result};
+use client::jwt::{TokenManager, TokenSource};
+use daml_type_rep::schema::TemplateRegistry;
+use ledger_api::v2::{GetUpdatesResponse, Identifier};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tracing::debug;
+
+/// The events of the updates a sink applied since its last flush, and the checkpoint file of the
+/// offset of the last update it wrote, its `sync::Sink::last_offset`
+pub struct EventBatch {
+    checkpoint_file: PathBuf,
+    template_registry: Option<Arc<TemplateRegistry>>,
+    events: Vec<EventRecord>,
+    /// The offset of the last update applied since the last flush
+    offset: Option<i64>,
+}
+
+impl EventBatch {
+    /// Payloads are decoded with the schemas of the registry, see `events::event_records`
+    pub fn new(checkpoint_file: PathBuf, template_registry: Option<Arc<TemplateRegistry>>) -> Self {
+        Self { checkpoint_file, template_registry, events: Vec::new(), offset: None }
+    }
+
+    pub fn apply_update(&mut self, response: &GetUpdatesResponse) {
+        self.offset = self.offset.max(update_offset(response));
+        self.events.extend(event_records(response, self.template_registry.as_deref()));
+    }
+
+    /// The offset of the last update applied and the events to write, `None` if no update was
+    /// applied since the last call
+    pub fn take(&mut self) -> Option<(i64, Vec<EventRecord>)> {
+        let offset = self.offset.take()?;
+        Some((offset, std::mem::take(&mut self.events)))
+    }
+
+    /// Advances the resume point to the offset, once the events up to it are written
+    pub fn checkpoint(&self, offset: i64) -> Result<()> {
+        write_checkpoint(&self.checkpoint_file, offset)?;
+        METRICS.set_last_processed_offset(offset);
+        debug!("Checkpointed offset {}", offset);
+        Ok(())
+    }
+
+    pub fn last_offset(&self) -> Result<Option<i64>> {
+        read_checkpoint(&self.checkpoint_file)
+    }
+}
+
+/// Configuration of `run_sink_sync`, like `sync::SyncConfig` without Neo4j
+pub struct SinkSyncConfig {
+    pub ledger_url: String,
+    pub parties: Parties,
+    /// Interfaces whose views are requested
+    pub interfaces: Vec<Identifier>,
+    /// Starting offset without checkpoint, see `sync::SyncConfig`
+    pub starting_offset: Option<i64>,
+    /// Number of updates to batch before writing them to the sink
+    pub batch_size: usize,
+    /// Flush timeout in seconds - write even if batch isn't full after this duration
+    pub flush_timeout_secs: u64,
+    /// Idle timeout in seconds - reconnect if no updates received for this duration
+    pub idle_timeout_secs: u64,
+}
+
+/// The offset in the checkpoint file, `None` if there is none yet
+pub fn read_checkpoint(path: &Path) -> Result<Option<i64>> {
+    match std::fs::read_to_string(path) {
+        Ok(content) => content
+            .trim()
+            .parse()
+            .map(Some)
+            .with_context(|| format!("invalid checkpoint file '{}'", path.display())),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e).with_context(|| format!("failed to read checkpoint file '{}'", path.display())),
+    }
+}
+
+/// Replaces the checkpoint file by way of a temporary file, so that it is never left half written
+pub fn write_checkpoint(path: &Path, offset: i64) -> Result<()> {
+    let temporary = path.with_extension("tmp");
+    std::fs::write(&temporary, offset.to_string())
+        .with_context(|| format!("failed to write checkpoint file '{}'", temporary.display()))?;
+    std::fs::rename(&temporary, path).with_context(|| format!("failed to replace checkpoint file '{}'", path.display()))
+}
+
+/// Streams the events of the updates after the checkpoint to the sink, reconnecting on failures,
+/// until SIGINT or SIGTERM, see `sync::run_sync`. There is no ACS load: events of contracts
+/// created before the starting offset are written without their created event.
+pub async fn run_sink_sync<S: Sink>(
+    sink: &mut S,
+    config: SinkSyncConfig,
+    token_source: TokenSource,
+    backoff_config: BackoffConfig,
+) -> Result<()> {
+    let stream_config = StreamConfig {
+        ledger_url: config.ledger_url,
+        parties: config.parties,
+        interfaces: config.interfaces,
+        created_event_blobs: false,
+        starting_offset: config.starting_offset,
+        batch_size: config.batch_size,
+        flush_timeout_secs: config.flush_timeout_secs,
+        idle_timeout_secs: config.idle_timeout_secs,
+        metrics_address: None,
+        rebootstrap_on_pruning_gap: false,
+    };
+    run_sync(sink, stream_config, Arc::new(TokenManager::new(token_source)), backoff_config, false).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checkpoint_roundtrip() -> Result<()> {
+        let path = std::env::temp_dir().join(format!("ledger-explorer-checkpoint-{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(read_checkpoint(&path)?, None);
+
+        write_checkpoint(&path, 42)?;
+        write_checkpoint(&path, 43)?;
+        assert_eq!(read_checkpoint(&path)?, Some(43));
+
+        std::fs::write(&path, "not an offset")?;
+        assert!(read_checkpoint(&path).is_err());
+        std::fs::remove_file(&path)?;
+        Ok(())
+    }
+}
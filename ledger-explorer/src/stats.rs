@@ -0,0 +1,132 @@
+//! Rendering of the statistics of the graph, `queries::Stats`, by the `stats` command as plain
+//! text tables.
+use crate::queries::Stats;
+
+/// The totals, then a table per template, per choice and per day
+pub fn to_table(stats: &Stats) -> String {
+    let mut out = String::new();
+    if let Some(since) = &stats.since {
+        out.push_str(&format!("Since {}\n", since));
+    }
+    out.push_str(&format!(
+        "Contracts: {} ({} active, {} archived)\n",
+        stats.contracts, stats.active, stats.archived
+    ));
+    out.push('\n');
+    out.push_str(&table(
+        &["TEMPLATE", "CONTRACTS", "ACTIVE", "ARCHIVED"],
+        stats
+            .templates
+            .iter()
+            .map(|template| {
+                vec![
+                    template.template_name.clone(),
+                    template.contracts.to_string(),
+                    template.active.to_string(),
+                    template.archived.to_string(),
+                ]
+            })
+            .collect(),
+    ));
+    out.push('\n');
+    out.push_str(&table(
+        &["TEMPLATE", "CHOICE", "EXERCISES", "CONSUMING"],
+        stats
+            .choices
+            .iter()
+            .map(|choice| {
+                vec![
+                    choice.template_name.clone().unwrap_or_else(|| "unknown".to_string()),
+                    choice.choice_name.clone().unwrap_or_else(|| "unknown".to_string()),
+                    choice.exercises.to_string(),
+                    choice.consuming.to_string(),
+                ]
+            })
+            .collect(),
+    ));
+    out.push('\n');
+    out.push_str(&table(
+        &["DATE", "TRANSACTIONS"],
+        stats
+            .transactions_per_day
+            .iter()
+            .map(|day| vec![day.date.clone(), day.transactions.to_string()])
+            .collect(),
+    ));
+    out
+}
+
+/// Columns padded to their widest cell, numbers aligned right
+fn table(headers: &[&str], rows: Vec<Vec<String>>) -> String {
+    let mut widths: Vec<usize> = headers.iter().map(|header| header.len()).collect();
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
+        }
+    }
+    let numeric: Vec<bool> = (0..headers.len())
+        .map(|column| !rows.is_empty() && rows.iter().all(|row| row[column].parse::<i64>().is_ok()))
+        .collect();
+    let line = |cells: Vec<&str>| {
+        let padded: Vec<String> = cells
+            .iter()
+            .zip(&widths)
+            .zip(&numeric)
+            .map(|((cell, width), numeric)| {
+                if *numeric {
+                    format!("{:>width$}", cell, width = width)
+                } else {
+                    format!("{:<width$}", cell, width = width)
+                }
+            })
+            .collect();
+        format!("{}\n", padded.join("  ").trim_end())
+    };
+    let mut out = line(headers.to_vec());
+    for row in &rows {
+        out.push_str(&line(row.iter().map(String::as_str).collect()));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::queries::{ChoiceStats, DayStats, TemplateStats};
+
+    #[test]
+    fn test_stats_table() {
+        let stats = Stats {
+            since: Some("2026-10-01".to_string()),
+            contracts: 120,
+            active: 80,
+            archived: 40,
+            templates: vec![
+                TemplateStats { template_name: "Main.Asset".to_string(), contracts: 100, active: 70, archived: 30 },
+                TemplateStats { template_name: "Main.AssetFactory".to_string(), contracts: 20, active: 10, archived: 10 },
+            ],
+            choices: vec![ChoiceStats {
+                template_name: None,
+                choice_name: Some("Transfer".to_string()),
+                exercises: 30,
+                consuming: 30,
+            }],
+            transactions_per_day: vec![DayStats { date: "2026-10-13".to_string(), transactions: 7 }],
+        };
+        assert_eq!(
+            to_table(&stats),
+            "Since 2026-10-01\n\
+             Contracts: 120 (80 active, 40 archived)\n\
+             \n\
+             TEMPLATE           CONTRACTS  ACTIVE  ARCHIVED\n\
+             Main.Asset               100      70        30\n\
+             Main.AssetFactory         20      10        10\n\
+             \n\
+             TEMPLATE  CHOICE    EXERCISES  CONSUMING\n\
+             unknown   Transfer         30         30\n\
+             \n\
+             DATE        TRANSACTIONS\n\
+             2026-10-13             7\n"
+        );
+    }
+}
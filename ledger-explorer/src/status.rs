@@ -0,0 +1,212 @@
+//! The state of the sync by the `status` command: the SyncState of the graph, how far it is
+//! behind the ledger end, whether the ACS was loaded, and the counts of the nodes per label and
+//! of the relationships per type, answering what otherwise takes reading the logs of the sync.
+use crate::cypher::CypherQuery;
+use crate::graph::{is_acs_loaded, last_offset};
+use crate::sync::SyncConfig;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use client::jwt::{TokenManager, TokenSource};
+use client::ledger_end::get_ledger_end;
+use neo4rs::Graph;
+use serde::Serialize;
+use tracing::warn;
+
+/// The state of the sync of a ledger into the graph
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Status {
+    /// The offset the sync resumes from, `None` before the first sync
+    pub last_offset: Option<i64>,
+    pub last_update_id: Option<String>,
+    pub schema_version: Option<i64>,
+    pub acs_loaded: bool,
+    pub acs_offset: Option<i64>,
+    /// `None` if the ledger could not be reached
+    pub ledger_end: Option<i64>,
+    /// The record time of the last transaction synced
+    pub last_record_time: Option<String>,
+    /// The nodes per label and the relationships per type, of the most frequent first
+    pub labels: Vec<(String, i64)>,
+    pub relationships: Vec<(String, i64)>,
+}
+
+impl Status {
+    /// The offsets between the resume point and the ledger end
+    pub fn lag_offsets(&self) -> Option<i64> {
+        Some((self.ledger_end? - self.last_offset.unwrap_or(0)).max(0))
+    }
+
+    /// With offsets to catch up on, how long ago the last transaction synced was recorded, an
+    /// estimate of how far behind the ledger the graph is in time
+    pub fn lag_secs(&self, now: DateTime<Utc>) -> Option<i64> {
+        if self.lag_offsets()? == 0 {
+            return Some(0);
+        }
+        let record_time = DateTime::parse_from_rfc3339(self.last_record_time.as_deref()?).ok()?;
+        Some((now - record_time.with_timezone(&Utc)).num_seconds().max(0))
+    }
+
+    /// A line per fact of the sync, then the counts of the graph
+    pub fn to_text(&self, now: DateTime<Utc>) -> String {
+        let or_none = |value: Option<String>| value.unwrap_or_else(|| "none".to_string());
+        let mut out = format!(
+            "Last offset:    {}\nLast update id: {}\nSchema version: {}\n",
+            or_none(self.last_offset.map(|offset| offset.to_string())),
+            or_none(self.last_update_id.clone()),
+            or_none(self.schema_version.map(|version| version.to_string())),
+        );
+        out.push_str(&format!(
+            "ACS:            {}\n",
+            match (self.acs_loaded, self.acs_offset) {
+                (true, Some(offset)) => format!("loaded at offset {}", offset),
+                (true, None) => "loaded".to_string(),
+                (false, _) => "not loaded".to_string(),
+            }
+        ));
+        out.push_str(&format!(
+            "Ledger end:     {}\n",
+            self.ledger_end.map(|offset| offset.to_string()).unwrap_or_else(|| "unreachable".to_string())
+        ));
+        if let Some(lag) = self.lag_offsets() {
+            out.push_str(&format!("Lag:            {} offsets", lag));
+            if let Some(secs) = self.lag_secs(now).filter(|secs| *secs > 0) {
+                out.push_str(&format!(", about {}s behind", secs));
+            }
+            out.push('\n');
+        }
+        for (title, counts) in [("Nodes", &self.labels), ("Relationships", &self.relationships)] {
+            out.push_str(&format!("\n{}:\n", title));
+            let width = counts.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+            for (name, count) in counts {
+                out.push_str(&format!("  {:<width$}  {}\n", name, count, width = width));
+            }
+        }
+        out
+    }
+}
+
+/// Reads the state of the sync from the graph and the end of the ledger. A ledger which cannot
+/// be reached is reported without ledger end.
+pub async fn status(graph: &Graph, sync_config: &SyncConfig, token_source: TokenSource) -> Result<Status> {
+    let ledger_id = sync_config.ledger_id.as_deref();
+    let offset = last_offset(graph, ledger_id).await?;
+    let acs_loaded = is_acs_loaded(graph, ledger_id).await?;
+
+    let sync_state = CypherQuery::new(
+        "MATCH (s:SyncState { id: 'sync' }) \
+        RETURN s.last_update_id AS last_update_id, s.schema_version AS schema_version, s.acs_offset AS acs_offset"
+            .to_string(),
+    );
+    let mut result = graph.execute(sync_state.scoped_to_ledger(ledger_id).query).await?;
+    let (last_update_id, schema_version, acs_offset) = match result.next().await? {
+        Some(row) => (row.get("last_update_id")?, row.get("schema_version")?, row.get("acs_offset")?),
+        None => (None, None, None),
+    };
+
+    let last_transaction = CypherQuery::new(
+        "MATCH (t:Transaction) WHERE t.offset <= $offset \
+        RETURN t.record_time AS record_time ORDER BY t.offset DESC LIMIT 1"
+            .to_string(),
+    )
+    .with_json_param("offset", serde_json::json!(offset.unwrap_or(i64::MAX)));
+    let mut result = graph.execute(last_transaction.scoped_to_ledger(ledger_id).query).await?;
+    let last_record_time = match result.next().await? {
+        Some(row) => row.get::<Option<String>>("record_time")?.filter(|time| !time.is_empty()),
+        None => None,
+    };
+
+    let labels = counts(
+        graph,
+        "MATCH (n) WHERE $ledger_id IS NULL OR n.ledger_id = $ledger_id \
+        UNWIND labels(n) AS name RETURN name, count(*) AS count ORDER BY count DESC, name",
+        ledger_id,
+    )
+    .await?;
+    let relationships = counts(
+        graph,
+        "MATCH (n)-[r]->() WHERE $ledger_id IS NULL OR n.ledger_id = $ledger_id \
+        RETURN type(r) AS name, count(*) AS count ORDER BY count DESC, name",
+        ledger_id,
+    )
+    .await?;
+
+    let ledger_end = match ledger_end(sync_config, token_source).await {
+        Ok(ledger_end) => Some(ledger_end),
+        Err(e) => {
+            warn!("Failed to read the ledger end from {}: {:#}", sync_config.ledger_url, e);
+            None
+        }
+    };
+    Ok(Status {
+        last_offset: offset,
+        last_update_id,
+        schema_version,
+        acs_loaded,
+        acs_offset,
+        ledger_end,
+        last_record_time,
+        labels,
+        relationships,
+    })
+}
+
+async fn ledger_end(sync_config: &SyncConfig, token_source: TokenSource) -> Result<i64> {
+    let token = TokenManager::new(token_source).get_token().await?;
+    get_ledger_end(&sync_config.ledger_url, Some(&token)).await
+}
+
+/// The `name` and `count` columns of the rows of the query
+async fn counts(graph: &Graph, cypher: &str, ledger_id: Option<&str>) -> Result<Vec<(String, i64)>> {
+    let query = CypherQuery::new(cypher.to_string()).with_json_param("ledger_id", serde_json::json!(ledger_id));
+    let mut result = graph.execute(query.query).await?;
+    let mut counts = Vec::new();
+    while let Some(row) = result.next().await? {
+        counts.push((row.get("name")?, row.get("count")?));
+    }
+    Ok(counts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_lag_and_text() {
+        let now = DateTime::parse_from_rfc3339("2026-10-14T12:00:00Z").unwrap().with_timezone(&Utc);
+        let mut status = Status {
+            last_offset: Some(90),
+            last_update_id: Some("u90".to_string()),
+            schema_version: Some(1),
+            acs_loaded: true,
+            acs_offset: Some(10),
+            ledger_end: Some(100),
+            last_record_time: Some("2026-10-14T11:58:00Z".to_string()),
+            labels: vec![("Created".to_string(), 120), ("SyncState".to_string(), 1)],
+            relationships: vec![("CONSUMES".to_string(), 40)],
+        };
+        assert_eq!(status.lag_offsets(), Some(10));
+        assert_eq!(status.lag_secs(now), Some(120));
+        assert_eq!(
+            status.to_text(now),
+            "Last offset:    90\n\
+             Last update id: u90\n\
+             Schema version: 1\n\
+             ACS:            loaded at offset 10\n\
+             Ledger end:     100\n\
+             Lag:            10 offsets, about 120s behind\n\
+             \n\
+             Nodes:\n  \
+             Created    120\n  \
+             SyncState  1\n\
+             \n\
+             Relationships:\n  \
+             CONSUMES  40\n"
+        );
+
+        status.last_offset = Some(100);
+        assert_eq!(status.lag_secs(now), Some(0));
+        status.ledger_end = None;
+        assert!(status.lag_offsets().is_none());
+        assert!(status.to_text(now).contains("Ledger end:     unreachable\n\nNodes:"));
+    }
+}
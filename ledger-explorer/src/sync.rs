@@ -1,25 +1,40 @@
+use std::future::Future;
+use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::Duration;
-use tokio_stream::StreamExt;
+use tokio::sync::watch;
+use tokio_stream::{Stream, StreamExt};
 use tracing::{debug, error, info, warn};
-use anyhow::Result;
-use neo4rs::{Graph, query};
+use anyhow::{Result, anyhow, bail};
 use std::time::Instant;
 
 use client::jwt::{TokenManager, TokenSource};
-use client::stream_updates::stream_updates;
-use client::active_contracts::stream_active_contracts;
+use client::stream_updates::stream_updates_with_event_format;
+use client::active_contracts::{ActiveContract, stream_active_contracts_with_event_format};
+use crate::config::{NodeMapping, OpenTelemetryConfig, Parties, WebhookRule};
+use crate::events::update_offset;
+use ledger_api::v2::{GetUpdatesResponse, Identifier};
 use client::ledger_end::{get_pruning_offset, get_ledger_end};
-use crate::cypher;
-use crate::graph::{apply_cypher_vec_stream_to_neo4j, get_last_processed_offset};
+use daml_type_rep::schema::TemplateRegistry;
+use crate::graph::Neo4jSink;
+use crate::packages::start_package_refresh;
+use crate::parties::start_party_refresh;
+use crate::metrics::{METRICS, start_metrics_server};
+
+/// Number of contracts of the ACS written to the sink at a time
+const ACS_BATCH_SIZE: usize = 500;
 
 /// Configuration for the resilient sync process
 pub struct SyncConfig {
     pub ledger_url: String,
-    pub parties: Vec<String>,
+    pub parties: Parties,
+    /// Interfaces whose views are stored on Created nodes
+    pub interfaces: Vec<Identifier>,
     pub neo4j_uri: String,
     pub neo4j_user: String,
     pub neo4j_pass: String,
+    /// Database of the graph, the default database of the user if `None`
+    pub neo4j_database: Option<String>,
     /// Starting offset when Neo4j has no data. If None, falls back to pruning offset.
     pub starting_offset: Option<i64>,
     /// Number of updates to batch before committing to Neo4j
@@ -28,6 +43,60 @@ pub struct SyncConfig {
     pub flush_timeout_secs: u64,
     /// Idle timeout in seconds - reconnect if no updates received for this duration
     pub idle_timeout_secs: u64,
+    /// Number of batches committed to Neo4j concurrently
+    pub writer_concurrency: usize,
+    /// Whether to request the created event blobs of the contracts, stored on their Created
+    /// nodes for explicit disclosure
+    pub created_event_blobs: bool,
+    /// Number of batches submitted to the writers and not yet committed beyond which the stream
+    /// and the ACS loading wait, twice the number of writers if `None`
+    pub max_in_flight_batches: Option<usize>,
+    /// Seconds the oldest batch not yet committed may wait before the stream and the ACS loading
+    /// wait for it, `None` without lag target
+    pub lag_target_secs: Option<u64>,
+    /// Address to serve Prometheus metrics on, `None` without metrics endpoint
+    pub metrics_address: Option<String>,
+    /// Interval of the refresh of the metadata of the Party nodes in seconds, `None` without refresh
+    pub party_refresh_secs: Option<u64>,
+    /// Interval of the refresh of the package catalog in seconds, `None` without refresh
+    pub package_refresh_secs: Option<u64>,
+    /// Schemas of the payloads of known templates, see `template_registry`
+    pub template_registry: Option<Arc<TemplateRegistry>>,
+    /// Labels and promoted payload fields of the Created nodes, by template name
+    pub node_mapping: HashMap<String, NodeMapping>,
+    /// Whether to clear the graph and reload the ACS at the pruning offset when the resume point
+    /// was pruned
+    pub rebootstrap_on_pruning_gap: bool,
+    /// Notifications of the events matching the rules, see `webhooks`
+    pub webhooks: Vec<WebhookRule>,
+    /// Export of the spans of the transactions and batches, see `telemetry`
+    pub opentelemetry: OpenTelemetryConfig,
+    /// The `ledger_id` of the nodes of the ledger in a graph shared by several ledgers, `None`
+    /// for a graph of a single ledger
+    pub ledger_id: Option<String>,
+}
+
+/// What `run_sync` streams from the ledger and how it batches the updates, whatever the sink
+pub struct StreamConfig {
+    pub ledger_url: String,
+    pub parties: Parties,
+    /// Interfaces whose views are requested
+    pub interfaces: Vec<Identifier>,
+    /// Whether the created event blobs of the contracts are requested
+    pub created_event_blobs: bool,
+    /// Starting offset when the sink has no resume point. If None, falls back to pruning offset.
+    pub starting_offset: Option<i64>,
+    /// Number of updates to apply before flushing the sink
+    pub batch_size: usize,
+    /// Flush timeout in seconds - flush even if batch isn't full after this duration
+    pub flush_timeout_secs: u64,
+    /// Idle timeout in seconds - reconnect if no updates received for this duration
+    pub idle_timeout_secs: u64,
+    /// Address to serve Prometheus metrics on, `None` without metrics endpoint
+    pub metrics_address: Option<String>,
+    /// Whether to clear the sink and reload the ACS at the pruning offset when the resume point
+    /// was pruned, see `pruning_gap`, instead of failing
+    pub rebootstrap_on_pruning_gap: bool,
 }
 
 /// Exponential backoff configuration
@@ -37,6 +106,13 @@ pub struct BackoffConfig {
     pub multiplier: f64,
 }
 
+impl BackoffConfig {
+    /// The delay after `delay`, multiplied up to the maximum
+    pub fn next_delay(&self, delay: Duration) -> Duration {
+        std::cmp::min(Duration::from_secs_f64(delay.as_secs_f64() * self.multiplier), self.max_delay)
+    }
+}
+
 impl Default for BackoffConfig {
     fn default() -> Self {
         Self {
@@ -47,183 +123,187 @@ impl Default for BackoffConfig {
     }
 }
 
-/// Ensures required indexes exist in Neo4j for optimal query performance.
-/// Creates indexes if they don't exist (idempotent).
-async fn ensure_indexes(neo4j_uri: &str, neo4j_user: &str, neo4j_pass: &str) -> Result<()> {
-    info!("Ensuring Neo4j indexes exist...");
-    let graph = Graph::new(neo4j_uri, neo4j_user, neo4j_pass)?;
-
-    let indexes = [
-        "CREATE INDEX created_contract_id IF NOT EXISTS FOR (c:Created) ON (c.contract_id)",
-        "CREATE INDEX created_offset_node IF NOT EXISTS FOR (c:Created) ON (c.offset, c.node_id)",
-        "CREATE INDEX created_offset IF NOT EXISTS FOR (c:Created) ON (c.offset)",
-        "CREATE INDEX created_template_name IF NOT EXISTS FOR (c:Created) ON (c.template_name)",
-        "CREATE INDEX exercised_offset_node IF NOT EXISTS FOR (e:Exercised) ON (e.offset, e.node_id)",
-        "CREATE INDEX exercised_choice_name IF NOT EXISTS FOR (e:Exercised) ON (e.choice_name)",
-        "CREATE INDEX transaction_offset IF NOT EXISTS FOR (t:Transaction) ON (t.offset)",
-        "CREATE INDEX transaction_id_idx IF NOT EXISTS FOR (t:Transaction) ON (t.transaction_id)",
-        "CREATE INDEX party_id IF NOT EXISTS FOR (p:Party) ON (p.party_id)",
-    ];
-
-    for index_query in &indexes {
-        match graph.run(query(*index_query)).await {
-            Ok(_) => debug!("Index ensured: {}", index_query),
-            Err(e) => warn!("Failed to create index (may already exist): {} - {}", index_query, e),
-        }
+/// A backend the updates of the ledger are synced to by `run_sync`, e.g. `graph::Neo4jSink`.
+///
+/// Updates are applied one at a time and written at the next flush, after `batch_size` updates
+/// or the flush timeout. The resume point only advances past flushed updates. After an error,
+/// the updates not written are dropped, and the loop resumes from `last_offset`.
+pub trait Sink {
+    /// The name of the sink in logs
+    fn name(&self) -> &str;
+
+    /// Whether the ACS was loaded. Sinks without ACS bootstrap always return `true`.
+    fn acs_loaded(&mut self) -> impl Future<Output = Result<bool>> + Send;
+
+    /// Writes a batch of the contracts active at the offset, with their synchronizer. The last batch, `complete`, marks
+    /// the ACS loaded.
+    fn load_acs_batch(
+        &mut self,
+        acs_offset: i64,
+        contracts: Vec<ActiveContract>,
+        complete: bool,
+    ) -> impl Future<Output = Result<()>> + Send;
+
+    /// Applies an update of the stream, written at the next flush
+    fn apply_update(&mut self, response: &GetUpdatesResponse) -> Result<()>;
+
+    /// Writes the updates applied since the last flush, if any. Called when the batch is full
+    /// and on each flush timeout.
+    fn flush(&mut self) -> impl Future<Output = Result<()>> + Send;
+
+    /// Waits for the flushed updates to be written, for sinks writing them in the background.
+    /// Called at the end of each stream.
+    fn finish(&mut self) -> impl Future<Output = Result<()>> + Send {
+        async { Ok(()) }
     }
 
-    info!("Neo4j indexes ready");
-    Ok(())
+    /// The offset of the last update written, the resume point, `None` if there is none yet
+    fn last_offset(&mut self) -> impl Future<Output = Result<Option<i64>>> + Send;
+
+    /// Deletes the data written, so that the ACS is loaded again. Sinks without ACS bootstrap
+    /// cannot be cleared.
+    fn clear(&mut self) -> impl Future<Output = Result<()>> + Send {
+        let name = self.name().to_string();
+        async move { Err(anyhow!("The {} sink cannot be cleared", name)) }
+    }
 }
 
-/// Loads the Active Contract Set (ACS) into Neo4j at a specific offset.
-/// This ensures we have all contracts that were active at that offset before starting to stream updates.
+/// Runs the sync to Neo4j with automatic reconnection and token refresh, see `run_sync`.
 ///
-/// The offset should be the pruning offset (or start offset for streaming) so that all contracts
-/// that will be archived in the stream already exist as Created nodes.
-async fn load_acs_to_neo4j(
-    ledger_url: &str,
-    neo4j_uri: &str,
-    neo4j_user: &str,
-    neo4j_pass: &str,
-    parties: &[String],
-    token: &str,
-    acs_offset: i64,
+/// If `fresh` is true, clears the database and starts from current ledger end.
+pub async fn run_resilient_sync(
+    sync_config: SyncConfig,
+    token_source: TokenSource,
+    backoff_config: BackoffConfig,
+    fresh: bool,
 ) -> Result<()> {
-    info!("Loading Active Contract Set (ACS) into Neo4j at offset {}...", acs_offset);
-    let start_time = Instant::now();
-
-    // Connect to Neo4j
-    let graph = Graph::new(neo4j_uri, neo4j_user, neo4j_pass)?;
-
-    // Stream active contracts at the specified offset
-    let mut acs_stream = stream_active_contracts(
-        Some(token),
-        acs_offset,
-        parties.to_vec(),
-        ledger_url.to_string(),
-    ).await?;
-
-    let mut contract_count = 0u64;
-    let mut batch_queries = Vec::new();
-    const BATCH_SIZE: usize = 500;
-
-    while let Some(contract_result) = acs_stream.next().await {
-        match contract_result {
-            Ok(contract) => {
-                let queries = cypher::created_event_to_cypher(&contract.created_event);
-                batch_queries.extend(queries.into_iter().map(|cq| cq.query));
-                contract_count += 1;
-
-                // Commit in batches
-                if batch_queries.len() >= BATCH_SIZE {
-                    let mut txn = graph.start_txn().await?;
-                    let queries_to_run: Vec<neo4rs::Query> = batch_queries.drain(..).collect();
-                    txn.run_queries(queries_to_run).await?;
-                    txn.commit().await?;
-                    debug!("Committed batch of ACS contracts, total so far: {}", contract_count);
-                }
-            }
-            Err(e) => {
-                error!("Error streaming ACS contract: {}", e);
-                return Err(e);
-            }
-        }
-    }
-
-    // Commit any remaining queries
-    if !batch_queries.is_empty() {
-        let mut txn = graph.start_txn().await?;
-        txn.run_queries(batch_queries).await?;
-        txn.commit().await?;
-    }
-
-    let elapsed = start_time.elapsed();
-    info!(
-        "ACS loading complete: {} contracts loaded in {:.2}s at offset {}",
-        contract_count,
-        elapsed.as_secs_f64(),
-        acs_offset
-    );
-
-    Ok(())
+    let mut sink = Neo4jSink::connect(&sync_config, fresh).await?;
+    let token_manager = Arc::new(TokenManager::new(token_source));
+    let _party_refresh_handle = sync_config.party_refresh_secs.map(|secs| {
+        info!("Started background party metadata refresh (every {} s)", secs);
+        start_party_refresh(
+            sink.graph().clone(),
+            sync_config.ledger_url.clone(),
+            Arc::clone(&token_manager),
+            Duration::from_secs(secs),
+            sync_config.ledger_id.clone(),
+        )
+    });
+    let _package_refresh_handle = sync_config.package_refresh_secs.map(|secs| {
+        info!("Started background package catalog refresh (every {} s)", secs);
+        start_package_refresh(
+            sink.graph().clone(),
+            sync_config.ledger_url.clone(),
+            Arc::clone(&token_manager),
+            Duration::from_secs(secs),
+            sync_config.ledger_id.clone(),
+        )
+    });
+    let stream_config = StreamConfig {
+        ledger_url: sync_config.ledger_url,
+        parties: sync_config.parties,
+        interfaces: sync_config.interfaces,
+        created_event_blobs: sync_config.created_event_blobs,
+        starting_offset: sync_config.starting_offset,
+        batch_size: sync_config.batch_size,
+        flush_timeout_secs: sync_config.flush_timeout_secs,
+        idle_timeout_secs: sync_config.idle_timeout_secs,
+        metrics_address: sync_config.metrics_address,
+        rebootstrap_on_pruning_gap: sync_config.rebootstrap_on_pruning_gap,
+    };
+    run_sync(&mut sink, stream_config, token_manager, backoff_config, fresh).await
 }
 
-/// Checks if the ACS has already been loaded into Neo4j.
-/// We use the presence of any from_acs=true nodes as an indicator.
-async fn is_acs_loaded(neo4j_uri: &str, neo4j_user: &str, neo4j_pass: &str) -> Result<bool> {
-    let graph = Graph::new(neo4j_uri, neo4j_user, neo4j_pass)?;
-    let mut result = graph.execute(query("MATCH (c:Created {from_acs: true}) RETURN count(c) as count LIMIT 1")).await?;
-
-    match result.next().await {
-        Ok(Some(row)) => {
-            let count: i64 = row.get("count")?;
-            Ok(count > 0)
-        }
-        Ok(None) => Ok(false),
-        Err(e) => Err(e.into()),
+/// Replays the updates from offset `from` up to `to`, both inclusive, through the Neo4j sink,
+/// e.g. to rewrite them after a change of the Cypher generation. The writes MERGE, so updates
+/// already synced are rewritten in place; the SyncState is left as it is, a running sync is not
+/// affected. Stops at the first stream error. Returns the number of updates written.
+pub async fn run_backfill(mut sync_config: SyncConfig, token_source: TokenSource, from: i64, to: i64) -> Result<u64> {
+    if from < 1 || to < from {
+        bail!("Invalid backfill range {}..={}", from, to);
     }
-}
-
-/// Clears all data from Neo4j database.
-async fn clear_neo4j_database(neo4j_uri: &str, neo4j_user: &str, neo4j_pass: &str) -> Result<()> {
-    info!("Clearing Neo4j database...");
-    let graph = Graph::new(neo4j_uri, neo4j_user, neo4j_pass)?;
-
-    // Use APOC for efficient deletion if available, otherwise fall back to batched delete
-    let delete_result = graph.run(query("CALL apoc.periodic.iterate('MATCH (n) RETURN n', 'DETACH DELETE n', {batchSize: 10000})")).await;
-
-    match delete_result {
-        Ok(_) => {
-            info!("Database cleared using APOC");
-        }
-        Err(_) => {
-            // Fall back to regular delete (may be slow for large datasets)
-            warn!("APOC not available, using standard delete (may be slow)");
-            graph.run(query("MATCH (n) DETACH DELETE n")).await?;
-            info!("Database cleared using standard delete");
+    let token = TokenManager::new(token_source).get_token().await?;
+    let ledger_end = get_ledger_end(&sync_config.ledger_url, Some(&token)).await?;
+    if to > ledger_end {
+        bail!("Backfill end {} is beyond the ledger end {}", to, ledger_end);
+    }
+    // Historic events are not notified again
+    sync_config.webhooks.clear();
+    let mut sink = Neo4jSink::connect(&sync_config, false).await?.without_sync_state();
+    let update_stream = stream_updates_with_event_format(
+        Some(&token),
+        from - 1,
+        Some(to),
+        sync_config.parties.event_format(&sync_config.interfaces, sync_config.created_event_blobs),
+        sync_config.ledger_url.clone(),
+    )
+    .await?;
+    let stream_error = Arc::new(std::sync::Mutex::new(None));
+    let stream_error_for_stream = Arc::clone(&stream_error);
+    let update_stream = update_stream.map_while(move |update| match update {
+        Ok(update) => Some(update),
+        Err(e) => {
+            *stream_error_for_stream.lock().unwrap() = Some(e);
+            None
         }
+    });
+    let stream_config = StreamConfig {
+        ledger_url: sync_config.ledger_url,
+        parties: sync_config.parties,
+        interfaces: sync_config.interfaces,
+        created_event_blobs: sync_config.created_event_blobs,
+        starting_offset: None,
+        batch_size: sync_config.batch_size,
+        flush_timeout_secs: sync_config.flush_timeout_secs,
+        idle_timeout_secs: sync_config.idle_timeout_secs,
+        metrics_address: None,
+        rebootstrap_on_pruning_gap: false,
+    };
+    info!("Backfilling offsets {} to {}", from, to);
+    let written = write_stream(&mut sink, update_stream, &stream_config).await?;
+    if let Some(e) = stream_error.lock().unwrap().take() {
+        bail!("Backfill stopped after {} updates by a stream error: {}", written, e);
     }
-
-    Ok(())
+    info!("Backfilled {} updates of offsets {} to {}", written, from, to);
+    Ok(written)
 }
 
 /// Runs the sync process with automatic reconnection and token refresh.
 ///
 /// This function will:
 /// 1. Load the Active Contract Set (ACS) if not already loaded
-/// 2. Query Neo4j for the last processed offset (resume point)
+/// 2. Query the sink for the last processed offset (resume point)
 /// 3. Start streaming from that offset
 /// 4. On stream errors, reconnect with exponential backoff
 /// 5. Proactively refresh JWT tokens before they expire
 ///
-/// If `fresh` is true, clears the database and starts from current ledger end.
-pub async fn run_resilient_sync(
-    sync_config: SyncConfig,
-    token_source: TokenSource,
+/// On SIGINT or SIGTERM, it stops consuming the stream, flushes the pending batch, and returns.
+/// A second signal exits immediately.
+///
+/// If `fresh` is true, starts from the starting offset or the current ledger end instead of the
+/// resume point.
+pub async fn run_sync<S: Sink>(
+    sink: &mut S,
+    config: StreamConfig,
+    token_manager: Arc<TokenManager>,
     backoff_config: BackoffConfig,
     fresh: bool,
 ) -> Result<()> {
-    // If fresh start, clear the database first
-    if fresh {
-        clear_neo4j_database(&sync_config.neo4j_uri, &sync_config.neo4j_user, &sync_config.neo4j_pass).await?;
-    }
-
-    // Ensure indexes exist before starting sync
-    ensure_indexes(&sync_config.neo4j_uri, &sync_config.neo4j_user, &sync_config.neo4j_pass).await?;
-
-    let token_manager = Arc::new(TokenManager::new(token_source));
-
     // Start background token refresh
     let token_manager_clone = Arc::clone(&token_manager);
     let _refresh_handle = token_manager_clone.start_background_refresh();
     info!("Started background JWT token refresh");
 
+    let _metrics_handle = match &config.metrics_address {
+        Some(address) => Some(
+            start_metrics_server(address, config.ledger_url.clone(), Arc::clone(&token_manager)).await?,
+        ),
+        None => None,
+    };
+
     // Start background offset progress logger with ETA
-    let neo4j_uri_clone = sync_config.neo4j_uri.clone();
-    let neo4j_user_clone = sync_config.neo4j_user.clone();
-    let neo4j_pass_clone = sync_config.neo4j_pass.clone();
-    let ledger_url_clone = sync_config.ledger_url.clone();
+    let sink_name = sink.name().to_string();
+    let ledger_url_clone = config.ledger_url.clone();
     let token_manager_for_progress = Arc::clone(&token_manager);
     let _progress_handle = tokio::spawn(async move {
         let mut prev_offset: Option<i64> = None;
@@ -232,16 +312,9 @@ pub async fn run_resilient_sync(
         loop {
             tokio::time::sleep(Duration::from_secs(300)).await; // 5 minutes
 
-            let current_offset = match get_last_processed_offset(&neo4j_uri_clone, &neo4j_user_clone, &neo4j_pass_clone).await {
-                Ok(Some(offset)) => offset,
-                Ok(None) => {
-                    info!("[Progress] No offset data in Neo4j yet");
-                    continue;
-                }
-                Err(e) => {
-                    warn!("[Progress] Failed to query Neo4j offset: {}", e);
-                    continue;
-                }
+            let Some(current_offset) = METRICS.last_processed_offset() else {
+                info!("[Progress] No offset data in {} yet", sink_name);
+                continue;
             };
 
             // Get ledger end for ETA calculation
@@ -287,7 +360,7 @@ pub async fn run_resilient_sync(
                 }
             };
 
-            info!("[Progress] Neo4j offset: {}, {}", current_offset, rate_info);
+            info!("[Progress] {} offset: {}, {}", sink_name, current_offset, rate_info);
 
             prev_offset = Some(current_offset);
             prev_time = Some(Instant::now());
@@ -295,22 +368,29 @@ pub async fn run_resilient_sync(
     });
     info!("Started background progress logger (every 5 min)");
 
+    let mut shutdown = listen_for_shutdown();
+    let sync_start = Instant::now();
+    let mut updates_written = 0u64;
+
     let mut current_delay = backoff_config.initial_delay;
     let mut consecutive_failures = 0u32;
     let mut acs_loaded_checked = false;
     let mut fresh_start_offset: Option<i64> = None; // Used only on first iteration when fresh=true
 
     loop {
+        if *shutdown.borrow() {
+            break;
+        }
+
         // Get a fresh token
         let token = match token_manager.get_token().await {
             Ok(t) => t,
             Err(e) => {
                 error!("Failed to get JWT token: {}. Retrying in {:?}", e, current_delay);
-                tokio::time::sleep(current_delay).await;
-                current_delay = std::cmp::min(
-                    Duration::from_secs_f64(current_delay.as_secs_f64() * backoff_config.multiplier),
-                    backoff_config.max_delay,
-                );
+                if sleep_or_shutdown(current_delay, &mut shutdown).await {
+                    break;
+                }
+                current_delay = backoff_config.next_delay(current_delay);
                 continue;
             }
         };
@@ -318,99 +398,66 @@ pub async fn run_resilient_sync(
         // First, determine the starting offset
         let begin_offset = if fresh && fresh_start_offset.is_none() {
             // Fresh start: use starting_offset if configured, otherwise ledger end
-            match sync_config.starting_offset {
-                Some(configured_offset) => {
-                    match get_ledger_end(&sync_config.ledger_url, Some(&token)).await {
-                        Ok(ledger_end) => {
+            match get_ledger_end(&config.ledger_url, Some(&token)).await {
+                Ok(ledger_end) => {
+                    let resolved = match config.starting_offset {
+                        Some(configured_offset) => {
                             let resolved = if configured_offset < 0 {
                                 (ledger_end + configured_offset).max(0)
                             } else {
                                 configured_offset
                             };
                             info!("FRESH START: Using starting_offset {} (resolved to {}, ledger_end={})", configured_offset, resolved, ledger_end);
-                            fresh_start_offset = Some(resolved);
                             resolved
                         }
-                        Err(e) => {
-                            error!("Failed to get ledger end: {}. Retrying in {:?}", e, current_delay);
-                            tokio::time::sleep(current_delay).await;
-                            current_delay = std::cmp::min(
-                                Duration::from_secs_f64(current_delay.as_secs_f64() * backoff_config.multiplier),
-                                backoff_config.max_delay,
-                            );
-                            continue;
-                        }
-                    }
-                }
-                None => {
-                    match get_ledger_end(&sync_config.ledger_url, Some(&token)).await {
-                        Ok(ledger_end) => {
+                        None => {
                             info!("FRESH START: No starting_offset configured, using current ledger end: {}", ledger_end);
-                            fresh_start_offset = Some(ledger_end);
                             ledger_end
                         }
-                        Err(e) => {
-                            error!("Failed to get ledger end: {}. Retrying in {:?}", e, current_delay);
-                            tokio::time::sleep(current_delay).await;
-                            current_delay = std::cmp::min(
-                                Duration::from_secs_f64(current_delay.as_secs_f64() * backoff_config.multiplier),
-                                backoff_config.max_delay,
-                            );
-                            continue;
-                        }
+                    };
+                    fresh_start_offset = Some(resolved);
+                    resolved
+                }
+                Err(e) => {
+                    error!("Failed to get ledger end: {}. Retrying in {:?}", e, current_delay);
+                    if sleep_or_shutdown(current_delay, &mut shutdown).await {
+                        break;
                     }
+                    current_delay = backoff_config.next_delay(current_delay);
+                    continue;
                 }
             }
         } else if let Some(offset) = fresh_start_offset {
             // Fresh start already determined, use that offset
             offset
         } else {
-            // Normal mode: check Neo4j for resume point
-            match get_last_processed_offset(
-                &sync_config.neo4j_uri,
-                &sync_config.neo4j_user,
-                &sync_config.neo4j_pass,
-            ).await {
+            // Normal mode: check the sink for resume point
+            match sink.last_offset().await {
                 Ok(Some(offset)) => {
-                    info!("Resuming from Neo4j offset: {}", offset);
-                    offset
-                }
-                Ok(None) => {
-                    // No data in Neo4j, use configured starting_offset or fall back to pruning offset
-                    // Negative starting_offset means relative to ledger end (e.g., -5000000 = 5M before end)
-                    if let Some(configured_offset) = sync_config.starting_offset {
-                        if configured_offset < 0 {
-                            match get_ledger_end(&sync_config.ledger_url, Some(&token)).await {
-                                Ok(ledger_end) => {
-                                    let resolved = (ledger_end + configured_offset).max(0);
-                                    info!("No existing data in Neo4j, starting from ledger_end ({}) {} = {}", ledger_end, configured_offset, resolved);
-                                    resolved
-                                }
-                                Err(e) => {
-                                    error!("Failed to get ledger end for relative offset: {}. Using offset 0", e);
-                                    0
-                                }
-                            }
-                        } else {
-                            info!("No existing data in Neo4j, starting from configured starting_offset: {}", configured_offset);
-                            configured_offset
-                        }
-                    } else {
-                        match get_pruning_offset(&sync_config.ledger_url, Some(&token)).await {
-                            Ok(pruning_offset) => {
-                                info!("No existing data in Neo4j, starting from ledger pruning offset: {}", pruning_offset);
-                                pruning_offset
-                            }
-                            Err(e) => {
-                                error!("Failed to get pruning offset from ledger: {}. Starting from 0", e);
-                                0
+                    info!("Resuming from {} offset: {}", sink.name(), offset);
+                    match get_pruning_offset(&config.ledger_url, Some(&token)).await {
+                        Ok(pruning_offset) => match pruning_gap(offset, pruning_offset, config.rebootstrap_on_pruning_gap)? {
+                            Some(rebootstrap_offset) => {
+                                sink.clear().await?;
+                                info!("Cleared {}, reloading the ACS at the pruning offset {}", sink.name(), rebootstrap_offset);
+                                acs_loaded_checked = false;
+                                rebootstrap_offset
                             }
+                            None => offset,
+                        },
+                        Err(e) => {
+                            warn!("Failed to get pruning offset from ledger: {}. Not checking for a pruning gap", e);
+                            offset
                         }
                     }
                 }
+                Ok(None) => {
+                    info!("No existing data in {}", sink.name());
+                    initial_offset(&config.ledger_url, config.starting_offset, &token).await
+                }
                 Err(e) => {
-                    warn!("Failed to query Neo4j for last offset: {}. Querying ledger for pruning offset", e);
-                    match get_pruning_offset(&sync_config.ledger_url, Some(&token)).await {
+                    warn!("Failed to query {} for last offset: {}. Querying ledger for pruning offset", sink.name(), e);
+                    match get_pruning_offset(&config.ledger_url, Some(&token)).await {
                         Ok(pruning_offset) => {
                             info!("Starting from ledger pruning offset: {}", pruning_offset);
                             pruning_offset
@@ -426,79 +473,42 @@ pub async fn run_resilient_sync(
 
         // Load ACS on first run if not already loaded (at the starting offset)
         if !acs_loaded_checked {
-            match is_acs_loaded(
-                &sync_config.neo4j_uri,
-                &sync_config.neo4j_user,
-                &sync_config.neo4j_pass,
-            ).await {
-                Ok(true) => {
-                    info!("ACS already loaded, skipping ACS load");
-                    acs_loaded_checked = true;
-                }
-                Ok(false) => {
-                    info!("ACS not yet loaded, loading at offset {}...", begin_offset);
-                    match load_acs_to_neo4j(
-                        &sync_config.ledger_url,
-                        &sync_config.neo4j_uri,
-                        &sync_config.neo4j_user,
-                        &sync_config.neo4j_pass,
-                        &sync_config.parties,
-                        &token,
-                        begin_offset,
-                    ).await {
-                        Ok(()) => {
-                            info!("ACS loaded successfully");
-                            acs_loaded_checked = true;
-                        }
-                        Err(e) => {
-                            error!("Failed to load ACS: {}. Retrying in {:?}", e, current_delay);
-                            tokio::time::sleep(current_delay).await;
-                            current_delay = std::cmp::min(
-                                Duration::from_secs_f64(current_delay.as_secs_f64() * backoff_config.multiplier),
-                                backoff_config.max_delay,
-                            );
-                            continue;
-                        }
-                    }
-                }
-                Err(e) => {
-                    warn!("Failed to check ACS status: {}. Assuming not loaded.", e);
-                    match load_acs_to_neo4j(
-                        &sync_config.ledger_url,
-                        &sync_config.neo4j_uri,
-                        &sync_config.neo4j_user,
-                        &sync_config.neo4j_pass,
-                        &sync_config.parties,
-                        &token,
-                        begin_offset,
-                    ).await {
-                        Ok(()) => {
-                            info!("ACS loaded successfully");
-                            acs_loaded_checked = true;
-                        }
-                        Err(e) => {
-                            error!("Failed to load ACS: {}. Retrying in {:?}", e, current_delay);
-                            tokio::time::sleep(current_delay).await;
-                            current_delay = std::cmp::min(
-                                Duration::from_secs_f64(current_delay.as_secs_f64() * backoff_config.multiplier),
-                                backoff_config.max_delay,
-                            );
-                            continue;
-                        }
+            let acs_loaded = sink.acs_loaded().await.unwrap_or_else(|e| {
+                warn!("Failed to check ACS status: {}. Assuming not loaded.", e);
+                false
+            });
+            if acs_loaded {
+                info!("ACS already loaded, skipping ACS load");
+            } else {
+                info!("ACS not yet loaded, loading at offset {}...", begin_offset);
+                if let Err(e) = load_acs(sink, &config, &token, begin_offset).await {
+                    error!("Failed to load ACS: {}. Retrying in {:?}", e, current_delay);
+                    if sleep_or_shutdown(current_delay, &mut shutdown).await {
+                        break;
                     }
+                    current_delay = backoff_config.next_delay(current_delay);
+                    continue;
                 }
+                info!("ACS loaded successfully");
             }
+            acs_loaded_checked = true;
+        }
+
+        // The ACS load is not interrupted, stop after it
+        if *shutdown.borrow() {
+            break;
         }
 
         info!("Starting stream from offset {}", begin_offset);
+        METRICS.set_last_processed_offset(begin_offset);
 
         // Start the update stream
-        let update_stream = match stream_updates(
+        let update_stream = match stream_updates_with_event_format(
             Some(&token),
             begin_offset,
             None,
-            sync_config.parties.clone(),
-            sync_config.ledger_url.clone(),
+            config.parties.event_format(&config.interfaces, config.created_event_blobs),
+            config.ledger_url.clone(),
         ).await {
             Ok(stream) => stream,
             Err(e) => {
@@ -507,11 +517,10 @@ pub async fn run_resilient_sync(
                     "Failed to connect to ledger (attempt {}): {}. Retrying in {:?}",
                     consecutive_failures, e, current_delay
                 );
-                tokio::time::sleep(current_delay).await;
-                current_delay = std::cmp::min(
-                    Duration::from_secs_f64(current_delay.as_secs_f64() * backoff_config.multiplier),
-                    backoff_config.max_delay,
-                );
+                if sleep_or_shutdown(current_delay, &mut shutdown).await {
+                    break;
+                }
+                current_delay = backoff_config.next_delay(current_delay);
                 continue;
             }
         };
@@ -523,7 +532,7 @@ pub async fn run_resilient_sync(
 
         // Process the stream - take items while they're Ok, stop on first error
         // This allows us to gracefully reconnect when token expires
-        let cypher_stream = update_stream
+        let update_stream = update_stream
             .take_while(|update| {
                 match update {
                     Ok(_) => true,
@@ -533,40 +542,38 @@ pub async fn run_resilient_sync(
                     }
                 }
             })
-            .map(|update| {
-                // Safe to unwrap here because take_while filters out errors
-                let response = update.unwrap();
-                let offset = response.update.as_ref().map(|u| match u {
-                    ledger_api::v2::get_updates_response::Update::Transaction(tx) => tx.offset,
-                    ledger_api::v2::get_updates_response::Update::Reassignment(r) => r.offset,
-                    ledger_api::v2::get_updates_response::Update::OffsetCheckpoint(c) => c.offset,
-                    ledger_api::v2::get_updates_response::Update::TopologyTransaction(t) => t.offset,
-                });
-                debug!(offset = ?offset, "Processing update from stream");
-                cypher::get_updates_response_to_cypher(&response)
-            });
-
-        // Apply to Neo4j - this will return when the stream ends or errors
-        match apply_cypher_vec_stream_to_neo4j(
-            &sync_config.neo4j_uri,
-            &sync_config.neo4j_user,
-            &sync_config.neo4j_pass,
-            cypher_stream,
-            sync_config.batch_size,
-            sync_config.flush_timeout_secs,
-            sync_config.idle_timeout_secs,
-        ).await {
-            Ok((before, after, time)) => {
+            .filter_map(|update| update.ok());
+        // On shutdown, the stream ends after the update being processed, and the pending batch
+        // is flushed like at the end of any stream
+        let mut shutdown_for_stream = shutdown.clone();
+        let update_stream = futures_util::StreamExt::take_until(
+            update_stream,
+            Box::pin(async move {
+                let _ = shutdown_for_stream.wait_for(|requested| *requested).await;
+            }),
+        );
+
+        // Apply to the sink - this will return when the stream ends or errors
+        let stream_start = Instant::now();
+        match write_stream(sink, update_stream, &config).await {
+            Ok(written) => {
+                updates_written += written;
                 info!(
-                    "Stream processing completed. Offset {} -> {}, took {} ms",
-                    before.unwrap_or(-1),
-                    after.unwrap_or(-1),
-                    time
+                    "Stream processing completed: {} updates written to {} in {} ms",
+                    written,
+                    sink.name(),
+                    stream_start.elapsed().as_millis()
                 );
+                if *shutdown.borrow() {
+                    break;
+                }
                 // Stream ended - could be graceful end, server closed, or error filtered out
                 // Either way, reconnect with a fresh token
                 info!("Stream ended, reconnecting in {:?}", backoff_config.initial_delay);
-                tokio::time::sleep(backoff_config.initial_delay).await;
+                METRICS.inc_reconnects();
+                if sleep_or_shutdown(backoff_config.initial_delay, &mut shutdown).await {
+                    break;
+                }
             }
             Err(e) => {
                 consecutive_failures += 1;
@@ -574,12 +581,353 @@ pub async fn run_resilient_sync(
                     "Stream processing failed (attempt {}): {}. Reconnecting in {:?}",
                     consecutive_failures, e, current_delay
                 );
-                tokio::time::sleep(current_delay).await;
-                current_delay = std::cmp::min(
-                    Duration::from_secs_f64(current_delay.as_secs_f64() * backoff_config.multiplier),
-                    backoff_config.max_delay,
-                );
+                METRICS.inc_reconnects();
+                if sleep_or_shutdown(current_delay, &mut shutdown).await {
+                    break;
+                }
+                current_delay = backoff_config.next_delay(current_delay);
             }
         }
     }
+
+    let last_offset = sink.last_offset().await.unwrap_or_else(|e| {
+        warn!("Failed to query the last processed offset: {}", e);
+        None
+    });
+    info!(
+        "{} sync shut down after {:?}: last processed offset {}, {} updates written, {} reconnects",
+        sink.name(),
+        sync_start.elapsed(),
+        last_offset.map_or("none".to_string(), |offset| offset.to_string()),
+        updates_written,
+        METRICS.reconnects(),
+    );
+    Ok(())
+}
+
+/// Loads the Active Contract Set (ACS) into the sink at a specific offset.
+/// This ensures we have all contracts that were active at that offset before starting to stream updates.
+///
+/// The offset should be the pruning offset (or start offset for streaming) so that all contracts
+/// that will be archived in the stream already exist as Created nodes.
+async fn load_acs<S: Sink>(sink: &mut S, config: &StreamConfig, token: &str, acs_offset: i64) -> Result<()> {
+    info!("Loading Active Contract Set (ACS) into {} at offset {}...", sink.name(), acs_offset);
+    let start_time = Instant::now();
+
+    // Stream active contracts at the specified offset
+    let mut acs_stream = stream_active_contracts_with_event_format(
+        Some(token),
+        acs_offset,
+        config.parties.event_format(&config.interfaces, config.created_event_blobs),
+        config.ledger_url.clone(),
+    ).await?;
+
+    let mut contract_count = 0u64;
+    let mut batch = Vec::new();
+
+    while let Some(contract_result) = acs_stream.next().await {
+        match contract_result {
+            Ok(contract) => {
+                batch.push(contract);
+                contract_count += 1;
+
+                // Write in batches
+                if batch.len() >= ACS_BATCH_SIZE {
+                    sink.load_acs_batch(acs_offset, std::mem::take(&mut batch), false).await?;
+                    debug!("Committed batch of ACS contracts, total so far: {}", contract_count);
+                }
+            }
+            Err(e) => {
+                error!("Error streaming ACS contract: {}", e);
+                return Err(e);
+            }
+        }
+    }
+
+    // Write any remaining contracts, marking the ACS loaded
+    sink.load_acs_batch(acs_offset, batch, true).await?;
+
+    let elapsed = start_time.elapsed();
+    info!(
+        "ACS loading complete: {} contracts loaded in {:.2}s at offset {}",
+        contract_count,
+        elapsed.as_secs_f64(),
+        acs_offset
+    );
+
+    Ok(())
+}
+
+/// Applies the updates of the stream to the sink, flushing it when the batch is full and on each
+/// flush timeout, until the stream ends or is idle. Returns the number of updates written.
+async fn write_stream<S, U>(sink: &mut S, mut update_stream: U, config: &StreamConfig) -> Result<u64>
+where
+    S: Sink,
+    U: Stream<Item = GetUpdatesResponse> + Unpin,
+{
+    let flush_timeout = Duration::from_secs(config.flush_timeout_secs);
+    let idle_timeout = Duration::from_secs(config.idle_timeout_secs);
+    info!(
+        "Starting to process update stream (batch_size={}, flush_timeout={}s)",
+        config.batch_size, config.flush_timeout_secs
+    );
+    let mut written = 0u64;
+    let mut updates_in_batch = 0usize;
+    let mut batch_start_time: Option<Instant> = None;
+    let mut last_update_time = Instant::now();
+
+    loop {
+        // Calculate remaining time until flush timeout
+        let timeout_remaining = batch_start_time
+            .map(|start| flush_timeout.saturating_sub(start.elapsed()))
+            .unwrap_or(flush_timeout);
+
+        // Wait for next update with timeout
+        match tokio::time::timeout(timeout_remaining, update_stream.next()).await {
+            Ok(Some(response)) => {
+                last_update_time = Instant::now();
+                debug!(offset = ?update_offset(&response), "Processing update from stream");
+                sink.apply_update(&response)?;
+                updates_in_batch += 1;
+
+                // Start batch timer on first update
+                batch_start_time.get_or_insert_with(Instant::now);
+
+                // Flush when batch is full
+                if updates_in_batch >= config.batch_size {
+                    debug!("Flushing batch (full): {} updates", updates_in_batch);
+                    sink.flush().await?;
+                    written += updates_in_batch as u64;
+                    updates_in_batch = 0;
+                    batch_start_time = None;
+                }
+            }
+            Ok(None) => {
+                // Stream ended
+                break;
+            }
+            Err(_) => {
+                // Timeout - flush partial batch if any, and let the sink catch up with the
+                // batches it wrote meanwhile
+                if updates_in_batch > 0 {
+                    debug!("Flushing batch (timeout): {} updates", updates_in_batch);
+                }
+                sink.flush().await?;
+                written += updates_in_batch as u64;
+                updates_in_batch = 0;
+                batch_start_time = None;
+
+                // Check for idle timeout (stale/dead stream detection)
+                if last_update_time.elapsed() >= idle_timeout {
+                    warn!(
+                        "No updates received for {}s (idle_timeout={}s), stream appears stale. Triggering reconnect.",
+                        last_update_time.elapsed().as_secs(),
+                        config.idle_timeout_secs,
+                    );
+                    break;
+                }
+            }
+        }
+    }
+
+    // Flush any remaining updates
+    sink.flush().await?;
+    written += updates_in_batch as u64;
+    sink.finish().await?;
+    Ok(written)
+}
+
+/// Checks the resume point against the offset the participant is pruned up to: streaming from
+/// below it fails or skips the pruned updates. Fails, unless `rebootstrap`, then returns the
+/// pruning offset to reload the ACS at.
+fn pruning_gap(resume_offset: i64, pruning_offset: i64, rebootstrap: bool) -> Result<Option<i64>> {
+    if resume_offset >= pruning_offset {
+        return Ok(None);
+    }
+    error!(
+        "PRUNING GAP: the resume offset {} is below the offset {} the participant is pruned up to, \
+        the updates in between cannot be streamed and the synced data is incomplete",
+        resume_offset, pruning_offset
+    );
+    if !rebootstrap {
+        bail!(
+            "Resume offset {} was pruned (pruned up to {}). Sync with --rebootstrap-on-pruning-gap to clear \
+            the synced data and reload the ACS at the pruning offset, or with --fresh",
+            resume_offset,
+            pruning_offset
+        );
+    }
+    warn!("Re-bootstrapping: clearing the synced data and reloading the ACS at offset {}", pruning_offset);
+    Ok(Some(pruning_offset))
+}
+
+/// The offset to start from without resume point: the configured starting offset, relative to the
+/// ledger end if negative (e.g., -5000000 = 5M before end), or else the ledger pruning offset
+pub(crate) async fn initial_offset(ledger_url: &str, starting_offset: Option<i64>, token: &str) -> i64 {
+    if let Some(configured_offset) = starting_offset {
+        if configured_offset < 0 {
+            match get_ledger_end(ledger_url, Some(token)).await {
+                Ok(ledger_end) => {
+                    let resolved = (ledger_end + configured_offset).max(0);
+                    info!("Starting from ledger_end ({}) {} = {}", ledger_end, configured_offset, resolved);
+                    resolved
+                }
+                Err(e) => {
+                    error!("Failed to get ledger end for relative offset: {}. Using offset 0", e);
+                    0
+                }
+            }
+        } else {
+            info!("Starting from configured starting_offset: {}", configured_offset);
+            configured_offset
+        }
+    } else {
+        match get_pruning_offset(ledger_url, Some(token)).await {
+            Ok(pruning_offset) => {
+                info!("Starting from ledger pruning offset: {}", pruning_offset);
+                pruning_offset
+            }
+            Err(e) => {
+                error!("Failed to get pruning offset from ledger: {}. Starting from 0", e);
+                0
+            }
+        }
+    }
+}
+
+/// Sets the returned flag on SIGINT or SIGTERM, exiting on a second one
+pub(crate) fn listen_for_shutdown() -> watch::Receiver<bool> {
+    let (sender, receiver) = watch::channel(false);
+    tokio::spawn(async move {
+        shutdown_signal().await;
+        info!("Shutdown requested, committing the pending batch (signal again to exit immediately)");
+        let _ = sender.send(true);
+        shutdown_signal().await;
+        warn!("Second shutdown signal, exiting without committing the pending batch");
+        std::process::exit(130);
+    });
+    receiver
+}
+
+/// Waits for SIGINT (Ctrl+C) or, on Unix, SIGTERM. Never returns if the handlers cannot be
+/// installed.
+pub(crate) async fn shutdown_signal() {
+    let interrupt = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            warn!("Failed to listen for Ctrl+C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                terminate.recv().await;
+            }
+            Err(e) => {
+                warn!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+    tokio::select! {
+        _ = interrupt => {}
+        _ = terminate => {}
+    }
+}
+
+/// Sleeps for the delay, returns `true` if shutdown was requested before it elapsed
+pub(crate) async fn sleep_or_shutdown(delay: Duration, shutdown: &mut watch::Receiver<bool>) -> bool {
+    let requested = tokio::select! {
+        _ = tokio::time::sleep(delay) => false,
+        _ = shutdown.wait_for(|requested| *requested) => true,
+    };
+    requested || *shutdown.borrow()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ledger_api::v2::OffsetCheckpoint;
+    use ledger_api::v2::get_updates_response::Update;
+
+    /// Records the offsets of the updates of each flush
+    #[derive(Default)]
+    struct RecordingSink {
+        pending: Vec<i64>,
+        flushed: Vec<Vec<i64>>,
+        finished: bool,
+    }
+
+    impl Sink for RecordingSink {
+        fn name(&self) -> &str {
+            "recording"
+        }
+
+        async fn acs_loaded(&mut self) -> Result<bool> {
+            Ok(true)
+        }
+
+        async fn load_acs_batch(&mut self, _acs_offset: i64, _contracts: Vec<ActiveContract>, _complete: bool) -> Result<()> {
+            Ok(())
+        }
+
+        fn apply_update(&mut self, response: &GetUpdatesResponse) -> Result<()> {
+            self.pending.extend(update_offset(response));
+            Ok(())
+        }
+
+        async fn flush(&mut self) -> Result<()> {
+            if !self.pending.is_empty() {
+                self.flushed.push(std::mem::take(&mut self.pending));
+            }
+            Ok(())
+        }
+
+        async fn finish(&mut self) -> Result<()> {
+            self.finished = true;
+            Ok(())
+        }
+
+        async fn last_offset(&mut self) -> Result<Option<i64>> {
+            Ok(self.flushed.last().and_then(|batch| batch.last().copied()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_write_stream_flushes_full_batches_and_the_rest() -> Result<()> {
+        let config = StreamConfig {
+            ledger_url: String::new(),
+            parties: Parties::default(),
+            interfaces: Vec::new(),
+            created_event_blobs: false,
+            starting_offset: None,
+            batch_size: 2,
+            flush_timeout_secs: 60,
+            idle_timeout_secs: 60,
+            metrics_address: None,
+            rebootstrap_on_pruning_gap: false,
+        };
+        let updates = tokio_stream::iter((1..=5).map(|offset| GetUpdatesResponse {
+            update: Some(Update::OffsetCheckpoint(OffsetCheckpoint { offset, ..Default::default() })),
+        }));
+        let mut sink = RecordingSink::default();
+
+        assert_eq!(write_stream(&mut sink, updates, &config).await?, 5);
+        assert_eq!(sink.flushed, vec![vec![1, 2], vec![3, 4], vec![5]]);
+        assert!(sink.finished);
+        assert_eq!(sink.last_offset().await?, Some(5));
+        Ok(())
+    }
+
+    #[test]
+    fn test_pruning_gap() -> Result<()> {
+        assert_eq!(pruning_gap(100, 100, false)?, None);
+        assert_eq!(pruning_gap(120, 100, false)?, None);
+        assert!(pruning_gap(80, 100, false).is_err());
+        assert_eq!(pruning_gap(80, 100, true)?, Some(100));
+        Ok(())
+    }
 }
@@ -0,0 +1,287 @@
+//! OpenTelemetry spans of the sync, exported with OTLP over HTTP in its JSON encoding to the
+//! collector of the `[opentelemetry]` section: a span per transaction written, a child of the
+//! span of its submitter by the `traceparent` of the transaction, ending when its batch is
+//! committed, and a span per Neo4j batch commit, linked to the spans of its transactions.
+//! Spans are exported in the background, dropped with a warning if the collector is down.
+use crate::config::OpenTelemetryConfig;
+use ledger_api::v2::{GetUpdatesResponse, get_updates_response::Update};
+use serde_json::{Value, json};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc;
+use tracing::{debug, warn};
+
+/// Number of spans waiting to be exported, beyond which spans are dropped rather than holding
+/// back the sync
+const SPAN_QUEUE_SIZE: usize = 10_000;
+
+/// Maximum number of spans of an export request
+const MAX_EXPORT_BATCH: usize = 512;
+
+/// How often the spans collected are exported
+const EXPORT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// OTLP span kinds
+const SPAN_KIND_CLIENT: u8 = 3;
+const SPAN_KIND_CONSUMER: u8 = 5;
+
+/// The trace and span id of a W3C `traceparent`, as lower case hex
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpanContext {
+    pub trace_id: String,
+    pub span_id: String,
+}
+
+impl SpanContext {
+    /// A span of a new trace
+    fn new_root() -> Self {
+        Self { trace_id: random_hex(16), span_id: random_hex(8) }
+    }
+
+    /// A new span of the same trace
+    fn new_child(&self) -> Self {
+        Self { trace_id: self.trace_id.clone(), span_id: random_hex(8) }
+    }
+}
+
+/// Parses a `traceparent` header value, `<version>-<trace id>-<parent id>-<flags>`, `None` if
+/// it is not valid or its ids are all zeros
+pub fn parse_traceparent(traceparent: &str) -> Option<SpanContext> {
+    let parts: Vec<&str> = traceparent.trim().split('-').collect();
+    let [version, trace_id, span_id, flags, ..] = parts.as_slice() else {
+        return None;
+    };
+    let is_hex = |s: &str, len: usize| s.len() == len && s.bytes().all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b));
+    let is_zero = |s: &str| s.bytes().all(|b| b == b'0');
+    if !is_hex(version, 2) || *version == "ff" || !is_hex(flags, 2) || (*version == "00" && parts.len() != 4) {
+        return None;
+    }
+    if !is_hex(trace_id, 32) || !is_hex(span_id, 16) || is_zero(trace_id) || is_zero(span_id) {
+        return None;
+    }
+    Some(SpanContext { trace_id: trace_id.to_string(), span_id: span_id.to_string() })
+}
+
+/// A span, exported once ended
+#[derive(Debug, Clone)]
+pub struct Span {
+    pub context: SpanContext,
+    parent_span_id: Option<String>,
+    name: String,
+    kind: u8,
+    start: SystemTime,
+    end: SystemTime,
+    attributes: Vec<(&'static str, Value)>,
+    pub links: Vec<SpanContext>,
+    /// The error the span failed with, if any
+    error: Option<String>,
+}
+
+impl Span {
+    /// The span of the processing of a transaction from its receipt, a child of the span of its
+    /// `traceparent` if any, else the root of a new trace. `None` for the other updates.
+    pub fn transaction(response: &GetUpdatesResponse, ledger_id: Option<&str>) -> Option<Self> {
+        let Some(Update::Transaction(transaction)) = &response.update else {
+            return None;
+        };
+        let parent = transaction
+            .trace_context
+            .as_ref()
+            .and_then(|tc| tc.traceparent.as_deref())
+            .and_then(parse_traceparent);
+        let mut attributes = vec![
+            ("ledger.offset", json!(transaction.offset)),
+            ("ledger.update_id", json!(transaction.update_id)),
+            ("ledger.command_id", json!(transaction.command_id)),
+            ("ledger.workflow_id", json!(transaction.workflow_id)),
+            ("ledger.synchronizer_id", json!(transaction.synchronizer_id)),
+            ("ledger.events", json!(transaction.events.len())),
+        ];
+        if let Some(ledger_id) = ledger_id {
+            attributes.push(("ledger.ledger_id", json!(ledger_id)));
+        }
+        let now = SystemTime::now();
+        Some(Self {
+            context: parent.as_ref().map(SpanContext::new_child).unwrap_or_else(SpanContext::new_root),
+            parent_span_id: parent.map(|parent| parent.span_id),
+            name: "ledger-explorer transaction".to_string(),
+            kind: SPAN_KIND_CONSUMER,
+            start: now,
+            end: now,
+            attributes,
+            links: Vec::new(),
+            error: None,
+        })
+    }
+
+    /// The span of the commit of a batch to Neo4j started at `start`, the root of a new trace
+    pub fn neo4j_batch(start: SystemTime, attributes: Vec<(&'static str, Value)>) -> Self {
+        Self {
+            context: SpanContext::new_root(),
+            parent_span_id: None,
+            name: "ledger-explorer neo4j batch".to_string(),
+            kind: SPAN_KIND_CLIENT,
+            start,
+            end: start,
+            attributes,
+            links: Vec::new(),
+            error: None,
+        }
+    }
+
+    /// Ends the span now, failed with the error if any
+    pub fn end(&mut self, error: Option<&str>) {
+        self.end = SystemTime::now();
+        self.error = error.map(str::to_string);
+    }
+
+    /// The span in the OTLP JSON encoding
+    fn to_otlp(&self) -> Value {
+        let nanos = |time: SystemTime| time.duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos().to_string();
+        let mut span = json!({
+            "traceId": self.context.trace_id,
+            "spanId": self.context.span_id,
+            "name": self.name,
+            "kind": self.kind,
+            "startTimeUnixNano": nanos(self.start),
+            "endTimeUnixNano": nanos(self.end),
+            "attributes": self.attributes.iter().map(|(key, value)| attribute(key, value)).collect::<Vec<_>>(),
+            "links": self
+                .links
+                .iter()
+                .map(|link| json!({ "traceId": link.trace_id, "spanId": link.span_id }))
+                .collect::<Vec<_>>(),
+            "status": match &self.error {
+                Some(error) => json!({ "code": 2, "message": error }),
+                None => json!({ "code": 1 }),
+            },
+        });
+        if let Some(parent_span_id) = &self.parent_span_id {
+            span["parentSpanId"] = json!(parent_span_id);
+        }
+        span
+    }
+}
+
+/// An attribute in the OTLP JSON encoding, with 64-bit integers as strings
+fn attribute(key: &str, value: &Value) -> Value {
+    let value = match value {
+        Value::Bool(b) => json!({ "boolValue": b }),
+        Value::Number(n) if n.is_i64() || n.is_u64() => json!({ "intValue": n.to_string() }),
+        Value::Number(n) => json!({ "doubleValue": n.as_f64() }),
+        Value::String(s) => json!({ "stringValue": s }),
+        other => json!({ "stringValue": other.to_string() }),
+    };
+    json!({ "key": key, "value": value })
+}
+
+/// The export request of the spans of the service
+fn export_request(service_name: &str, spans: &[Span]) -> Value {
+    json!({
+        "resourceSpans": [{
+            "resource": { "attributes": [attribute("service.name", &json!(service_name))] },
+            "scopeSpans": [{
+                "scope": { "name": env!("CARGO_PKG_NAME"), "version": env!("CARGO_PKG_VERSION") },
+                "spans": spans.iter().map(Span::to_otlp).collect::<Vec<_>>(),
+            }],
+        }],
+    })
+}
+
+fn random_hex(bytes: usize) -> String {
+    uuid::Uuid::new_v4().as_bytes()[..bytes].iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Exports the spans sent to it from a background task, every `EXPORT_INTERVAL` or
+/// `MAX_EXPORT_BATCH` spans
+#[derive(Clone)]
+pub struct SpanExporter {
+    spans: mpsc::Sender<Span>,
+}
+
+impl SpanExporter {
+    /// Starts the export, `None` unless enabled
+    pub fn start(config: &OpenTelemetryConfig) -> Option<Self> {
+        if !config.enabled {
+            return None;
+        }
+        let url = format!("{}/v1/traces", config.endpoint.trim_end_matches('/'));
+        let service_name = config.service_name.clone();
+        let (spans, mut receiver) = mpsc::channel::<Span>(SPAN_QUEUE_SIZE);
+        tokio::spawn(async move {
+            let client = reqwest::Client::new();
+            let mut batch = Vec::new();
+            let mut interval = tokio::time::interval(EXPORT_INTERVAL);
+            loop {
+                tokio::select! {
+                    span = receiver.recv() => match span {
+                        Some(span) => {
+                            batch.push(span);
+                            if batch.len() < MAX_EXPORT_BATCH {
+                                continue;
+                            }
+                        }
+                        None => {
+                            send(&client, &url, &service_name, &mut batch).await;
+                            break;
+                        }
+                    },
+                    _ = interval.tick() => {}
+                }
+                send(&client, &url, &service_name, &mut batch).await;
+            }
+        });
+        Some(Self { spans })
+    }
+
+    /// Queues the ended spans for export
+    pub fn export(&self, spans: impl IntoIterator<Item = Span>) {
+        for span in spans {
+            if self.spans.try_send(span).is_err() {
+                warn!("Span queue full, dropping span");
+                return;
+            }
+        }
+    }
+}
+
+/// Exports the spans of the batch, if any, and clears it
+async fn send(client: &reqwest::Client, url: &str, service_name: &str, batch: &mut Vec<Span>) {
+    if batch.is_empty() {
+        return;
+    }
+    let request = export_request(service_name, batch);
+    match client.post(url).json(&request).send().await.and_then(|response| response.error_for_status()) {
+        Ok(_) => debug!("Exported {} spans", batch.len()),
+        Err(e) => warn!("Failed to export {} spans to {}: {}", batch.len(), url, e),
+    }
+    batch.clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_traceparent_and_otlp_encoding() {
+        let parent = parse_traceparent("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01").unwrap();
+        assert_eq!(parent.trace_id, "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_eq!(parent.span_id, "00f067aa0ba902b7");
+        assert!(parse_traceparent("").is_none());
+        assert!(parse_traceparent("00-00000000000000000000000000000000-00f067aa0ba902b7-01").is_none());
+        assert!(parse_traceparent("00-4BF92F3577B34DA6A3CE929D0E0E4736-00f067aa0ba902b7-01").is_none());
+        assert!(parse_traceparent("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-extra").is_none());
+        assert!(parse_traceparent("01-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-extra").is_some());
+
+        let mut batch = Span::neo4j_batch(UNIX_EPOCH + Duration::from_secs(1), vec![("neo4j.queries", json!(12))]);
+        batch.links.push(parent.new_child());
+        batch.end(Some("deadlock"));
+        let request = export_request("explorer", &[batch]);
+        let span = &request["resourceSpans"][0]["scopeSpans"][0]["spans"][0];
+        assert_eq!(span["startTimeUnixNano"], "1000000000");
+        assert_eq!(span["attributes"][0], json!({ "key": "neo4j.queries", "value": { "intValue": "12" } }));
+        assert_eq!(span["links"][0]["traceId"], "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_eq!(span["status"], json!({ "code": 2, "message": "deadlock" }));
+        assert_eq!(span["spanId"].as_str().unwrap().len(), 16);
+        assert!(span.get("parentSpanId").is_none());
+    }
+}
@@ -0,0 +1,96 @@
+//! Payload JSON by the schemas of a `TemplateRegistry`, written by `codegen --registry` for the
+//! DARs of the ledger. Payloads of templates in the registry are stored in the Daml-LF JSON
+//! encoding of their type, with field names also for positional records; other payloads, and
+//! payloads which do not match the schema, e.g. of another version of the package, as generic JSON.
+use anyhow::{Context, Result};
+use client::api_record_to_json::{api_record_to_json, api_value_to_json, choice_argument_json};
+use daml_type_rep::schema::TemplateRegistry;
+use ledger_api::v2::value::Sum;
+use ledger_api::v2::{CreatedEvent, ExercisedEvent};
+use std::path::Path;
+
+pub fn read_template_registry<P: AsRef<Path>>(path: P) -> Result<TemplateRegistry> {
+    let json = std::fs::read_to_string(&path)
+        .with_context(|| format!("failed to read template registry '{}'", path.as_ref().display()))?;
+    TemplateRegistry::from_json(&json)
+}
+
+/// The create arguments of a created event as JSON, `null` without arguments
+pub fn create_arguments_json(registry: Option<&TemplateRegistry>, created: &CreatedEvent) -> serde_json::Value {
+    let Some(arguments) = created.create_arguments.as_ref() else {
+        return serde_json::Value::Null;
+    };
+    registry
+        .zip(created.template_id.as_ref())
+        .and_then(|(registry, id)| {
+            let template = registry.template(&id.module_name, &id.entity_name)?;
+            registry.encode_record(&template.payload, arguments)
+        })
+        .unwrap_or_else(|| api_record_to_json(arguments))
+}
+
+/// The contract key of a created event as JSON, `None` for contracts without key
+pub fn contract_key_json(registry: Option<&TemplateRegistry>, created: &CreatedEvent) -> Option<serde_json::Value> {
+    let key = created.contract_key.as_ref()?;
+    let encoded = registry.zip(created.template_id.as_ref()).and_then(|(registry, id)| {
+        let schema = registry.template(&id.module_name, &id.entity_name)?.key.as_ref()?;
+        registry.encode(schema, key)
+    });
+    Some(encoded.unwrap_or_else(|| api_value_to_json(key)))
+}
+
+/// The choice argument of an exercised event as JSON
+pub fn exercised_argument_json(registry: Option<&TemplateRegistry>, exercised: &ExercisedEvent) -> serde_json::Value {
+    registry
+        .zip(exercised.template_id.as_ref())
+        .zip(exercised.choice_argument.as_ref())
+        .and_then(|((registry, id), argument)| {
+            let schema = registry.template(&id.module_name, &id.entity_name)?.choices.get(&exercised.choice)?;
+            // Choice arguments which are not records are not stored
+            matches!(argument.sum, Some(Sum::Record(_))).then_some(())?;
+            registry.encode(schema, argument)
+        })
+        .unwrap_or_else(|| choice_argument_json(&exercised.choice_argument))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use daml_type_rep::schema::{DataTypeDefinition, DataTypeSchema, FieldSchema, TemplateSchema, TypeSchema};
+    use ledger_api::v2::{Identifier, Record, RecordField, Value};
+
+    #[test]
+    fn test_create_arguments_json() {
+        let mut registry = TemplateRegistry::default();
+        let payload = TypeSchema::DataType { id: "abc:Main:Asset".to_string(), args: vec![] };
+        registry.templates.insert(
+            "Main.Asset".to_string(),
+            TemplateSchema { payload, key: None, choices: Default::default() },
+        );
+        registry.data_types.insert(
+            "abc:Main:Asset".to_string(),
+            DataTypeSchema {
+                params: vec![],
+                definition: DataTypeDefinition::Record {
+                    fields: vec![FieldSchema { name: "amount".to_string(), schema: TypeSchema::Int64 }],
+                },
+            },
+        );
+        let created = CreatedEvent {
+            template_id: Some(Identifier {
+                package_id: "abc".to_string(),
+                module_name: "Main".to_string(),
+                entity_name: "Asset".to_string(),
+            }),
+            create_arguments: Some(Record {
+                record_id: None,
+                fields: vec![RecordField { label: String::new(), value: Some(Value { sum: Some(Sum::Int64(3)) }) }],
+            }),
+            ..Default::default()
+        };
+
+        assert_eq!(create_arguments_json(Some(&registry), &created), serde_json::json!({ "amount": "3" }));
+        // Without registry, the generic encoding keeps the empty label and the number
+        assert_eq!(create_arguments_json(None, &created), serde_json::json!({ "": 3 }));
+    }
+}
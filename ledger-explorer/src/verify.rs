@@ -0,0 +1,175 @@
+//! Reconciliation of the graph with the ledger by the `verify` command: the active contracts of
+//! the ledger at the offset the graph is synced to, against the active Created nodes, reporting
+//! the contracts missing from the graph, the extra ones, and the ones whose template or payload
+//! differ.
+use crate::cypher::CypherQuery;
+use crate::graph::last_offset;
+use crate::sync::SyncConfig;
+use crate::template_registry::create_arguments_json;
+use anyhow::{Context, Result};
+use client::active_contracts::stream_active_contracts_with_event_format;
+use client::jwt::{TokenManager, TokenSource};
+use neo4rs::Graph;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use tokio_stream::StreamExt;
+use tracing::info;
+
+/// A contract as compared: its template name and payload JSON
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ContractState {
+    pub template_name: String,
+    pub payload: serde_json::Value,
+}
+
+/// A contract of the graph different from the one of the ledger
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Mismatch {
+    pub contract_id: String,
+    pub ledger: ContractState,
+    pub graph: ContractState,
+}
+
+/// The discrepancies between the active contracts of the ledger and of the graph at an offset
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Report {
+    pub offset: i64,
+    pub ledger_contracts: usize,
+    pub graph_contracts: usize,
+    /// Active on the ledger, not in the graph or archived there, with their template name
+    pub missing: Vec<(String, String)>,
+    /// Active in the graph, not on the ledger, with their template name
+    pub extra: Vec<(String, String)>,
+    pub mismatched: Vec<Mismatch>,
+}
+
+impl Report {
+    pub fn is_consistent(&self) -> bool {
+        self.missing.is_empty() && self.extra.is_empty() && self.mismatched.is_empty()
+    }
+
+    /// A summary line, then a line per discrepancy
+    pub fn to_text(&self) -> String {
+        let mut out = format!(
+            "Offset {}: {} active contracts on the ledger, {} in the graph, {} missing, {} extra, {} mismatched\n",
+            self.offset,
+            self.ledger_contracts,
+            self.graph_contracts,
+            self.missing.len(),
+            self.extra.len(),
+            self.mismatched.len()
+        );
+        for (contract_id, template_name) in &self.missing {
+            out.push_str(&format!("missing    {} {}\n", template_name, contract_id));
+        }
+        for (contract_id, template_name) in &self.extra {
+            out.push_str(&format!("extra      {} {}\n", template_name, contract_id));
+        }
+        for mismatch in &self.mismatched {
+            out.push_str(&format!("mismatched {} {}\n", mismatch.ledger.template_name, mismatch.contract_id));
+            out.push_str(&format!("  ledger: {} {}\n", mismatch.ledger.template_name, mismatch.ledger.payload));
+            out.push_str(&format!("  graph:  {} {}\n", mismatch.graph.template_name, mismatch.graph.payload));
+        }
+        out
+    }
+}
+
+/// Compares the active contracts of the ledger at the SyncState offset of the graph with its
+/// active Created nodes. The sync should be stopped meanwhile, contracts it writes during the
+/// comparison show as discrepancies.
+pub async fn verify(graph: &Graph, sync_config: &SyncConfig, token_source: TokenSource) -> Result<Report> {
+    let ledger_id = sync_config.ledger_id.as_deref();
+    let offset = last_offset(graph, ledger_id).await?.context("The graph is not synced yet")?;
+    let token = TokenManager::new(token_source).get_token().await?;
+
+    info!("Reading the active contracts of the ledger at offset {}...", offset);
+    let mut ledger = BTreeMap::new();
+    let mut acs_stream = stream_active_contracts_with_event_format(
+        Some(&token),
+        offset,
+        sync_config.parties.event_format(&sync_config.interfaces, false),
+        sync_config.ledger_url.clone(),
+    )
+    .await?;
+    while let Some(contract) = acs_stream.next().await {
+        let created = contract?.created_event;
+        let template_name = created
+            .template_id
+            .as_ref()
+            .map(|id| format!("{}.{}", id.module_name, id.entity_name))
+            .unwrap_or_else(|| "unknown".to_string());
+        let payload = create_arguments_json(sync_config.template_registry.as_deref(), &created);
+        ledger.insert(created.contract_id, ContractState { template_name, payload });
+    }
+
+    info!("Reading the active contracts of the graph...");
+    let active = CypherQuery::new(
+        "MATCH (c:Created) WHERE c.active = true \
+        RETURN c.contract_id AS contract_id, c.template_name AS template_name, c.create_arguments_json AS payload"
+            .to_string(),
+    );
+    let mut result = graph.execute(active.scoped_to_ledger(ledger_id).query).await?;
+    let mut in_graph = BTreeMap::new();
+    while let Some(row) = result.next().await? {
+        let payload: Option<String> = row.get("payload")?;
+        in_graph.insert(
+            row.get::<String>("contract_id")?,
+            ContractState {
+                template_name: row.get::<Option<String>>("template_name")?.unwrap_or_else(|| "unknown".to_string()),
+                payload: payload.and_then(|json| serde_json::from_str(&json).ok()).unwrap_or_default(),
+            },
+        );
+    }
+    Ok(compare(offset, ledger, in_graph))
+}
+
+/// The discrepancies between the contracts of the ledger and of the graph, by contract id
+fn compare(offset: i64, ledger: BTreeMap<String, ContractState>, mut graph: BTreeMap<String, ContractState>) -> Report {
+    let (ledger_contracts, graph_contracts) = (ledger.len(), graph.len());
+    let mut missing = Vec::new();
+    let mut mismatched = Vec::new();
+    for (contract_id, ledger_state) in ledger {
+        match graph.remove(&contract_id) {
+            None => missing.push((contract_id, ledger_state.template_name)),
+            Some(graph_state) if graph_state != ledger_state => {
+                mismatched.push(Mismatch { contract_id, ledger: ledger_state, graph: graph_state })
+            }
+            Some(_) => {}
+        }
+    }
+    let extra = graph.into_iter().map(|(contract_id, state)| (contract_id, state.template_name)).collect();
+    Report { offset, ledger_contracts, graph_contracts, missing, extra, mismatched }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_compare_reports_discrepancies() {
+        let state = |template_name: &str, amount: &str| ContractState {
+            template_name: template_name.to_string(),
+            payload: json!({ "amount": amount }),
+        };
+        let ledger = BTreeMap::from([
+            ("c1".to_string(), state("Main.Asset", "10")),
+            ("c2".to_string(), state("Main.Asset", "20")),
+            ("c3".to_string(), state("Main.Asset", "30")),
+        ]);
+        let graph = BTreeMap::from([
+            ("c1".to_string(), state("Main.Asset", "10")),
+            ("c3".to_string(), state("Main.Asset", "31")),
+            ("c4".to_string(), state("Main.Asset", "40")),
+        ]);
+        let report = compare(7, ledger, graph);
+        assert!(!report.is_consistent());
+        assert_eq!(report.missing, vec![("c2".to_string(), "Main.Asset".to_string())]);
+        assert_eq!(report.extra, vec![("c4".to_string(), "Main.Asset".to_string())]);
+        assert_eq!(report.mismatched.len(), 1);
+        assert_eq!(report.mismatched[0].graph.payload, json!({ "amount": "31" }));
+        assert!(report.to_text().starts_with(
+            "Offset 7: 3 active contracts on the ledger, 3 in the graph, 1 missing, 1 extra, 1 mismatched\n"
+        ));
+    }
+}
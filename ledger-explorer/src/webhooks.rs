@@ -0,0 +1,135 @@
+//! Notifications of the events of the sync matching the `[[webhooks]]` rules, see
+//! `config::WebhookRule`: `{"rule": <name>, "event": <event record>}` POSTed to the url of the
+//! rule, or written to the standard input of its command. Events are notified as the sync applies
+//! them, before they are committed; after a reconnect, replayed events are notified again.
+use crate::config::WebhookRule;
+use crate::events::{EventRecord, event_records};
+use daml_type_rep::schema::TemplateRegistry;
+use ledger_api::v2::GetUpdatesResponse;
+use serde_json::json;
+use std::sync::Arc;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc;
+use tracing::{debug, warn};
+
+/// Number of notifications waiting to be sent, beyond which notifications are dropped rather
+/// than holding back the sync
+const NOTIFICATION_QUEUE_SIZE: usize = 1000;
+
+/// Matches the events of the updates against the rules, and sends the notifications of the
+/// matching ones from a background task
+pub struct Notifier {
+    rules: Arc<Vec<WebhookRule>>,
+    template_registry: Option<Arc<TemplateRegistry>>,
+    notifications: mpsc::Sender<(usize, EventRecord)>,
+}
+
+impl Notifier {
+    /// Starts the sending of the notifications, `None` without rules
+    pub fn start(rules: Vec<WebhookRule>, template_registry: Option<Arc<TemplateRegistry>>) -> Option<Self> {
+        if rules.is_empty() {
+            return None;
+        }
+        let rules = Arc::new(rules);
+        let (notifications, mut receiver) = mpsc::channel::<(usize, EventRecord)>(NOTIFICATION_QUEUE_SIZE);
+        let sender_rules = Arc::clone(&rules);
+        tokio::spawn(async move {
+            let client = reqwest::Client::new();
+            while let Some((rule, event)) = receiver.recv().await {
+                let rule = &sender_rules[rule];
+                if let Err(e) = send(&client, rule, &event).await {
+                    warn!("Failed to send notification of webhook '{}' for {}: {:#}", rule.name, event.contract_id, e);
+                }
+            }
+        });
+        Some(Self { rules, template_registry, notifications })
+    }
+
+    /// Queues the notifications of the events of the update matching the rules
+    pub fn notify(&self, response: &GetUpdatesResponse) {
+        for event in event_records(response, self.template_registry.as_deref()) {
+            for (index, rule) in self.rules.iter().enumerate() {
+                if !matches(rule, &event) {
+                    continue;
+                }
+                debug!("Event {} at offset {} matches webhook '{}'", event.contract_id, event.offset, rule.name);
+                if self.notifications.try_send((index, event.clone())).is_err() {
+                    warn!("Notification queue full, dropping notification of webhook '{}'", rule.name);
+                }
+            }
+        }
+    }
+}
+
+/// Whether the event matches every matcher of the rule
+fn matches(rule: &WebhookRule, event: &EventRecord) -> bool {
+    (rule.templates.is_empty() || rule.templates.contains(&event.template_name))
+        && (rule.choices.is_empty() || event.choice.as_ref().is_some_and(|choice| rule.choices.contains(choice)))
+        && (rule.event_types.is_empty() || rule.event_types.contains(&event.event_type))
+        && (rule.parties.is_empty()
+            || event.parties.iter().chain(&event.witness_parties).any(|party| rule.parties.contains(party)))
+}
+
+async fn send(client: &reqwest::Client, rule: &WebhookRule, event: &EventRecord) -> anyhow::Result<()> {
+    let notification = json!({ "rule": rule.name, "event": event });
+    if let Some(url) = &rule.url {
+        client.post(url).json(&notification).send().await?.error_for_status()?;
+    } else if let Some([program, args @ ..]) = rule.command.as_deref() {
+        let mut child = tokio::process::Command::new(program)
+            .args(args)
+            .stdin(std::process::Stdio::piped())
+            .spawn()?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(notification.to_string().as_bytes()).await?;
+        }
+        let status = child.wait().await?;
+        if !status.success() {
+            anyhow::bail!("command exited with {}", status);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::EventType;
+
+    #[test]
+    fn test_rules_match_templates_choices_and_parties() {
+        let event = EventRecord {
+            event_type: EventType::Exercised,
+            offset: 7,
+            node_id: 0,
+            update_id: String::new(),
+            command_id: String::new(),
+            workflow_id: String::new(),
+            record_time: String::new(),
+            effective_at: String::new(),
+            contract_id: "c1".to_string(),
+            template_id: "abc:Main:Asset".to_string(),
+            template_name: "Main.Asset".to_string(),
+            choice: Some("Transfer".to_string()),
+            consuming: Some(true),
+            parties: vec!["Alice".to_string()],
+            witness_parties: vec!["Bob".to_string()],
+            payload: serde_json::Value::Null,
+            contract_key: None,
+        };
+        let rule = |templates: &[&str], choices: &[&str], parties: &[&str]| WebhookRule {
+            name: "rule".to_string(),
+            templates: templates.iter().map(|s| s.to_string()).collect(),
+            choices: choices.iter().map(|s| s.to_string()).collect(),
+            parties: parties.iter().map(|s| s.to_string()).collect(),
+            url: Some("http://localhost".to_string()),
+            ..Default::default()
+        };
+        assert!(matches(&rule(&[], &[], &[]), &event));
+        assert!(matches(&rule(&["Main.Asset"], &["Transfer"], &["Bob"]), &event));
+        assert!(!matches(&rule(&["Main.Other"], &[], &[]), &event));
+        assert!(!matches(&rule(&[], &["Split"], &[]), &event));
+        assert!(!matches(&rule(&[], &[], &["Carol"]), &event));
+        let created_only = WebhookRule { event_types: vec![EventType::Created], ..rule(&[], &[], &[]) };
+        assert!(!matches(&created_only, &event));
+    }
+}
@@ -0,0 +1,117 @@
+//! Declarative bootstrap of the ledger from a `--bootstrap` TOML file, with the gRPC services of
+//! the client library instead of a Daml Script: the DARs to upload, the parties to allocate,
+//! and the users to create with their rights, referring to the parties by their hints.
+//!
+//! ```toml
+//! dars = ["main/.daml/dist/main-1.0.0.dar"]
+//! parties = ["Alice", "Bob"]
+//!
+//! [[users]]
+//! id = "alice"
+//! primary_party = "Alice"
+//! act_as = ["Alice"]
+//! read_as = ["Bob"]
+//! ```
+use anyhow::{Context, Result};
+use client::party_management::allocate_parties::allocate_parties;
+use client::upload_dar::upload_dar;
+use client::user_management::hinted_user::{HintedUser, create_hinted_user};
+use ledger_api::v2::admin::package_management_service_client::PackageManagementServiceClient;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Bootstrap {
+    /// DARs to upload, relative to the directory of the bootstrap file
+    #[serde(default)]
+    pub dars: Vec<PathBuf>,
+    /// Hints of the parties to allocate
+    #[serde(default)]
+    pub parties: Vec<String>,
+    #[serde(default)]
+    pub users: Vec<HintedUser>,
+}
+
+/// What the bootstrap allocated and created
+#[derive(Debug, Clone, Default)]
+pub struct BootstrapResult {
+    /// The allocated party ids by their hints
+    pub parties: BTreeMap<String, String>,
+    pub users: Vec<String>,
+}
+
+/// Reads the bootstrap file, with its DAR paths resolved against its directory
+pub fn read_bootstrap(path: &Path) -> Result<Bootstrap> {
+    let contents = std::fs::read_to_string(path).with_context(|| format!("failed to read {:?}", path))?;
+    let mut bootstrap: Bootstrap = toml::from_str(&contents).with_context(|| format!("invalid bootstrap file {:?}", path))?;
+    let dir = path.parent().unwrap_or(Path::new(""));
+    bootstrap.dars = bootstrap.dars.iter().map(|dar| dir.join(dar)).collect();
+    Ok(bootstrap)
+}
+
+/// Uploads the DARs, allocates the parties, then creates the users of the ledger API at the url.
+/// Fails on the first DAR which cannot be read or is rejected.
+pub async fn run_bootstrap(bootstrap: &Bootstrap, url: &str) -> Result<BootstrapResult> {
+    if !bootstrap.dars.is_empty() {
+        info!("Uploading {} DARs", bootstrap.dars.len());
+        // Connects on the first upload, after reading its DAR
+        let channel = tonic::transport::Channel::from_shared(url.to_string())?.connect_lazy();
+        let mut package_client = PackageManagementServiceClient::new(channel);
+        for dar in &bootstrap.dars {
+            upload_dar(&mut package_client, dar).await?;
+        }
+    }
+
+    let allocated = allocate_parties(url.to_string(), None, bootstrap.parties.clone()).await?;
+    let parties: BTreeMap<String, String> = bootstrap.parties.iter().cloned().zip(allocated).collect();
+    for (hint, party) in &parties {
+        info!("Allocated party {}: {}", hint, party);
+    }
+
+    let mut users = Vec::new();
+    for user in &bootstrap.users {
+        let user_id = create_hinted_user(url, None, user, &parties).await?;
+        info!("Created user {}", user_id);
+        users.push(user_id);
+    }
+    Ok(BootstrapResult { parties, users })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_users_of_the_bootstrap_file() {
+        let bootstrap: Bootstrap = toml::from_str(
+            r#"
+            parties = ["Alice", "Bob"]
+
+            [[users]]
+            id = "alice"
+            primary_party = "Alice"
+            act_as = ["Alice"]
+            read_as = ["Bob"]
+            participant_admin = true
+            "#,
+        )
+        .unwrap();
+        let user = &bootstrap.users[0];
+        assert_eq!(user.primary_party.as_deref(), Some("Alice"));
+        assert_eq!((user.act_as.as_slice(), user.read_as.as_slice()), (&["Alice".to_string()][..], &["Bob".to_string()][..]));
+        assert!(user.participant_admin);
+
+        assert!(toml::from_str::<Bootstrap>("partys = []").is_err());
+        assert!(toml::from_str::<Bootstrap>("[[users]]\nid = \"bob\"\nread_as_all = true").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_unreadable_dar_fails_the_bootstrap() {
+        let bootstrap = Bootstrap { dars: vec![PathBuf::from("missing/main-1.0.0.dar")], ..Default::default() };
+        let error = run_bootstrap(&bootstrap, "http://localhost:1").await.unwrap_err();
+        assert!(format!("{:#}", error).contains("Failed to read DAR \"missing/main-1.0.0.dar\""), "{:#}", error);
+    }
+}
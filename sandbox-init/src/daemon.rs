@@ -0,0 +1,146 @@
+//! Daemon mode of `sandbox-init start --detach` and `sandbox-init stop`, for CI scripts and
+//! Makefiles. `--detach` starts sandbox-init again in the background, in its own session and
+//! with its output appended to the log file, and returns once it wrote its pidfile, after the
+//! initialization; `stop` sends SIGTERM to the process of the pidfile, which kills its sandbox.
+use anyhow::{Context, Result};
+use nix::libc;
+use nix::sys::signal::{Signal, kill};
+use nix::unistd::Pid;
+use std::ffi::OsString;
+use std::fs::OpenOptions;
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+use tracing::info;
+
+/// The pidfile of `start --detach` and `stop` if not given
+pub const DEFAULT_PIDFILE: &str = "sandbox-init.pid";
+
+/// How often the pidfile and the process are checked
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// How long `stop` waits for the process to exit
+const STOP_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Starts sandbox-init with the arguments of this process but `--detach` in the background,
+/// and waits until it is initialized, failing if it exits first
+pub fn detach(log_file: &Path, pidfile: &Path) -> Result<()> {
+    if let Some(pid) = running_pid(pidfile)? {
+        anyhow::bail!("sandbox-init is already running with pid {} of {:?}", pid, pidfile);
+    }
+    let _ = std::fs::remove_file(pidfile);
+    let log = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(log_file)
+        .with_context(|| format!("failed to open log file {:?}", log_file))?;
+    let mut child;
+    unsafe {
+        child = Command::new(std::env::current_exe()?)
+            .args(child_args(std::env::args_os().skip(1), pidfile))
+            .stdin(Stdio::null())
+            .stdout(log.try_clone()?)
+            .stderr(log)
+            .pre_exec(|| {
+                // A session of its own, not to get the signals of the terminal of the caller
+                if libc::setsid() == -1 {
+                    return Err(std::io::Error::last_os_error());
+                }
+                Ok(())
+            })
+            .spawn()
+            .context("Failed to start sandbox-init in the background")?;
+    }
+    info!("Started sandbox-init in the background with pid {}, logging to {:?}", child.id(), log_file);
+    loop {
+        if let Some(status) = child.try_wait()? {
+            anyhow::bail!("sandbox-init exited with {} before it was initialized, see {:?}", status, log_file);
+        }
+        if pidfile.exists() {
+            info!("Sandbox initialized, stop it with `sandbox-init stop --pidfile {}`", pidfile.display());
+            return Ok(());
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// The arguments of the detached process: the ones given without `--detach`, and the pidfile
+fn child_args(args: impl Iterator<Item = OsString>, pidfile: &Path) -> Vec<OsString> {
+    let mut args: Vec<OsString> = args.filter(|arg| arg != "--detach").collect();
+    if !args.iter().any(|arg| arg == "--pidfile" || arg.to_string_lossy().starts_with("--pidfile=")) {
+        args.extend(["--pidfile".into(), pidfile.into()]);
+    }
+    args
+}
+
+/// Stops the process of the pidfile and waits for it to exit
+pub fn stop(pidfile: &Path) -> Result<()> {
+    let Some(pid) = running_pid(pidfile)? else {
+        let _ = std::fs::remove_file(pidfile);
+        anyhow::bail!("sandbox-init is not running, no live process in {:?}", pidfile);
+    };
+    info!("Stopping sandbox-init with pid {}", pid);
+    kill(pid, Signal::SIGTERM).map_err(|e| anyhow::anyhow!("Failed to send SIGTERM to {}: {}", pid, e))?;
+    let start = Instant::now();
+    while kill(pid, None).is_ok() {
+        if start.elapsed() > STOP_TIMEOUT {
+            anyhow::bail!("sandbox-init with pid {} did not exit within {:?}", pid, STOP_TIMEOUT);
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    }
+    let _ = std::fs::remove_file(pidfile);
+    info!("Stopped sandbox-init");
+    Ok(())
+}
+
+/// The pid of the pidfile if the process is alive
+fn running_pid(pidfile: &Path) -> Result<Option<Pid>> {
+    let Ok(contents) = std::fs::read_to_string(pidfile) else {
+        return Ok(None);
+    };
+    let pid: i32 = contents.trim().parse().with_context(|| format!("invalid pidfile {:?}", pidfile))?;
+    let pid = Pid::from_raw(pid);
+    Ok(kill(pid, None).is_ok().then_some(pid))
+}
+
+/// The pidfile of the process, written once it is initialized and removed when dropped
+pub struct Pidfile(PathBuf);
+
+impl Pidfile {
+    pub fn write(path: PathBuf) -> Result<Self> {
+        std::fs::write(&path, std::process::id().to_string()).with_context(|| format!("failed to write pidfile {:?}", path))?;
+        Ok(Self(path))
+    }
+}
+
+impl Drop for Pidfile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detached_process_gets_the_args_and_a_pidfile() {
+        let args = |args: &[&str]| args.iter().map(OsString::from).collect::<Vec<_>>();
+        assert_eq!(
+            child_args(args(&["start", "--detach", "--dar", "a.dar"]).into_iter(), Path::new("ci.pid")),
+            args(&["start", "--dar", "a.dar", "--pidfile", "ci.pid"])
+        );
+        assert_eq!(
+            child_args(args(&["start", "--pidfile=x.pid", "--detach"]).into_iter(), Path::new("ci.pid")),
+            args(&["start", "--pidfile=x.pid"])
+        );
+
+        let pidfile = std::env::temp_dir().join(format!("sandbox-init-test-{}.pid", std::process::id()));
+        let written = Pidfile::write(pidfile.clone()).unwrap();
+        assert_eq!(running_pid(&pidfile).unwrap(), Some(Pid::this()));
+        drop(written);
+        assert!(!pidfile.exists());
+        assert_eq!(running_pid(&pidfile).unwrap(), None);
+    }
+}
@@ -0,0 +1,86 @@
+//! The `--output` JSON file describing the initialized environment, for test harnesses and other
+//! processes to use it without querying the ledger:
+//!
+//! ```json
+//! {
+//!   "ledger_url": "http://localhost:6865",
+//!   "parties": { "Alice": "Alice::1220..." },
+//!   "users": { "alice": "<token>" },
+//!   "packages": { "main/.daml/dist/main-1.0.0.dar": "<package id>" }
+//! }
+//! ```
+use anyhow::{Context, Result};
+use client::jwt::fake_jwt_for_user;
+use client::party_management::get_parties::list_party_details;
+use client::upload_dar::package_id_from_dar;
+use client::user_management::list_users::list_users;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Environment {
+    pub ledger_url: String,
+    /// The party ids by their hints, of the parties of the bootstrap file and of the init scripts
+    pub parties: BTreeMap<String, String>,
+    /// Unsigned tokens of the users by their ids, accepted by the sandbox
+    pub users: BTreeMap<String, String>,
+    /// The main package ids of the DARs uploaded, by their paths
+    pub packages: BTreeMap<String, String>,
+}
+
+/// Reads the parties and users of the ledger once initialized, and the package ids of the DARs
+pub async fn read_environment(ledger_url: &str, dars: &[PathBuf]) -> Result<Environment> {
+    let parties = list_party_details(ledger_url.to_string(), None, None, 0).await?;
+    let users = list_users(ledger_url.to_string(), None, None).await?;
+    let packages = dars
+        .iter()
+        .map(|dar| Ok((dar.display().to_string(), package_id_from_dar(dar)?)))
+        .collect::<Result<_>>()?;
+    Ok(Environment {
+        ledger_url: ledger_url.to_string(),
+        parties: parties.into_iter().map(|details| (party_hint(&details.party).to_string(), details.party)).collect(),
+        users: users.into_iter().map(|user| (user.id.clone(), fake_jwt_for_user(&user.id))).collect(),
+        packages,
+    })
+}
+
+/// The hint of a party id, its part before the `::` of the namespace
+fn party_hint(party: &str) -> &str {
+    party.split_once("::").map_or(party, |(hint, _)| hint)
+}
+
+/// Writes the environment to a temporary file renamed to the path, so that readers polling for
+/// the file never see it half written
+pub fn write_environment(environment: &Environment, path: &Path) -> Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    std::fs::write(&tmp, serde_json::to_string_pretty(environment)?).with_context(|| format!("failed to write {:?}", tmp))?;
+    std::fs::rename(&tmp, path).with_context(|| format!("failed to write {:?}", path))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_environment_file() {
+        assert_eq!(party_hint("Alice::1220abcd"), "Alice");
+        assert_eq!(party_hint("participant_admin"), "participant_admin");
+
+        let environment = Environment {
+            ledger_url: "http://localhost:6865".to_string(),
+            parties: BTreeMap::from([("Alice".to_string(), "Alice::1220abcd".to_string())]),
+            users: BTreeMap::from([("alice".to_string(), "token".to_string())]),
+            packages: BTreeMap::new(),
+        };
+        let path = std::env::temp_dir().join(format!("sandbox-init-{}.json", std::process::id()));
+        write_environment(&environment, &path).unwrap();
+        let written: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(written["parties"]["Alice"], "Alice::1220abcd");
+        assert_eq!(written["users"]["alice"], "token");
+        assert_eq!(written["ledger_url"], "http://localhost:6865");
+    }
+}
@@ -1,5 +1,9 @@
+mod bootstrap;
+mod daemon;
+mod environment;
+
 use anyhow::Result;
-use clap::Parser;
+use clap::{Args, Parser, Subcommand};
 use nix::libc;
 use nix::sys::signal::{killpg, Signal};
 use nix::unistd::Pid;
@@ -7,23 +11,112 @@ use std::io::{BufRead, BufReader};
 use std::os::unix::process::CommandExt;
 use std::path::PathBuf;
 use std::process::{Child, Command, Stdio};
+use std::time::Duration;
 use tracing::info;
 
 #[derive(Parser, Debug)]
 #[command(name = "sandbox-init")]
-#[command(about = "Starts a Daml sandbox and runs an initialization script")]
+#[command(about = "Starts a Daml sandbox and initializes it from a bootstrap file and init scripts")]
+#[command(args_conflicts_with_subcommands = true)]
 struct Cli {
-    /// Path to the Daml model DAR file
+    #[command(subcommand)]
+    command: Option<Commands>,
+
+    /// Without subcommand, starts the sandbox in the foreground like `start`
+    #[command(flatten)]
+    start: StartArgs,
+}
+
+#[derive(Subcommand, Debug)]
+enum Commands {
+    /// Starts and initializes the sandbox, running until Ctrl+C or SIGTERM
+    Start {
+        #[command(flatten)]
+        args: StartArgs,
+
+        /// Run in the background, returning once the sandbox is initialized
+        #[arg(long)]
+        detach: bool,
+
+        /// File the output is appended to with --detach
+        #[arg(long, default_value = "sandbox-init.log")]
+        log_file: PathBuf,
+    },
+    /// Stops a sandbox started with `start --detach`
+    Stop {
+        #[arg(long, default_value = daemon::DEFAULT_PIDFILE)]
+        pidfile: PathBuf,
+    },
+}
+
+#[derive(Args, Debug)]
+struct StartArgs {
+    /// Path to a Daml model DAR file, repeated to upload several
+    #[arg(long)]
+    dar: Vec<PathBuf>,
+
+    /// Path to the DAR file containing the init script, repeated with one per script, or once
+    /// for all scripts
+    #[arg(long)]
+    init_dar: Vec<PathBuf>,
+
+    /// Identifier of an init script (format: Module.Name:Entity.Name), repeated to run several
+    /// in order
     #[arg(long)]
-    dar: PathBuf,
+    init_script_name: Vec<String>,
 
-    /// Path to the DAR file containing the init script
+    /// TOML file of the DARs to upload, parties to allocate and users to create, before the
+    /// init scripts run
     #[arg(long)]
-    init_dar: PathBuf,
+    bootstrap: Option<PathBuf>,
 
-    /// Identifier of the init script (format: Module.Name:Entity.Name)
+    /// JSON file to write the parties, user tokens, package ids and ledger URL of the environment
+    /// to, once initialized
     #[arg(long)]
-    init_script_name: String,
+    output: Option<PathBuf>,
+
+    /// Ledger API port of the sandbox, also used to run the init script
+    #[arg(long, default_value_t = 6865)]
+    port: u16,
+
+    /// Admin API port of the sandbox (sandbox default if not set)
+    #[arg(long)]
+    admin_port: Option<u16>,
+
+    /// JSON API port of the sandbox (sandbox default if not set)
+    #[arg(long)]
+    json_api_port: Option<u16>,
+
+    /// How long to wait for the ledger API of the sandbox to respond, in seconds
+    #[arg(long, default_value_t = 120)]
+    ready_timeout_secs: u64,
+
+    /// File to write the process id to once initialized, removed on exit (sandbox-init.pid
+    /// with --detach)
+    #[arg(long)]
+    pidfile: Option<PathBuf>,
+}
+
+/// The ports of the sandbox, passed through to `dpm sandbox`, so that several sandboxes can run
+/// side by side
+#[derive(Debug, Clone, Copy)]
+struct SandboxPorts {
+    ledger_api: u16,
+    admin_api: Option<u16>,
+    json_api: Option<u16>,
+}
+
+impl SandboxPorts {
+    fn args(&self) -> Vec<String> {
+        let mut args = vec!["--ledger-api-port".to_string(), self.ledger_api.to_string()];
+        if let Some(port) = self.admin_api {
+            args.extend(["--admin-api-port".to_string(), port.to_string()]);
+        }
+        if let Some(port) = self.json_api {
+            args.extend(["--json-api-port".to_string(), port.to_string()]);
+        }
+        args
+    }
 }
 
 struct SandboxGuard {
@@ -48,13 +141,15 @@ fn close_sandbox(child: &mut Child) -> Result<()> {
     Ok(())
 }
 
-fn start_sandbox(dar_path: &PathBuf) -> Result<SandboxGuard> {
-    info!("Starting sandbox with DAR: {:?}", dar_path);
+async fn start_sandbox(dar_paths: &[PathBuf], ports: SandboxPorts, ready_timeout: Duration) -> Result<SandboxGuard> {
+    info!("Starting sandbox with DARs: {:?}", dar_paths);
 
     let mut child;
     unsafe {
         child = Command::new("dpm")
-            .args(&["sandbox", "--dar", dar_path.to_str().unwrap()])
+            .arg("sandbox")
+            .args(dar_paths.iter().flat_map(|dar_path| ["--dar", dar_path.to_str().unwrap()]))
+            .args(ports.args())
             .stdout(Stdio::piped())
             .pre_exec(|| {
                 if libc::setpgid(0, 0) != 0 {
@@ -66,40 +161,57 @@ fn start_sandbox(dar_path: &PathBuf) -> Result<SandboxGuard> {
             .map_err(|e| anyhow::anyhow!("Failed to start sandbox: {}", e))?;
     }
 
-    wait_for_sandbox_ready(&mut child)?;
+    log_sandbox_output(&mut child);
+    let guard = SandboxGuard { child: Some(child) };
 
-    Ok(SandboxGuard { child: Some(child) })
+    // Polls the ledger API rather than waiting for a readiness message, which differs across
+    // SDK versions
+    info!("Waiting for sandbox to be ready...");
+    let url = format!("http://localhost:{}", ports.ledger_api);
+    client::version::wait_for_ledger_api(&url, ready_timeout).await?;
+    info!("Sandbox is ready!");
+
+    Ok(guard)
 }
 
-fn wait_for_sandbox_ready(child: &mut Child) -> Result<()> {
+/// Logs the lines of the sandbox stdout from a thread, so that the sandbox does not block on a
+/// full pipe
+fn log_sandbox_output(child: &mut Child) {
     let stdout = child
         .stdout
         .take()
         .expect("Failed to capture sandbox stdout");
-    info!("Waiting for sandbox to be ready...");
-    let reader = BufReader::new(stdout);
-
-    for line in reader.lines().take(120) {
-        let line = line?;
-        info!("Sandbox: {}", line);
-        if line.contains("Canton sandbox is ready") {
-            info!("Sandbox is ready!");
-            return Ok(());
+    std::thread::spawn(move || {
+        for line in BufReader::new(stdout).lines() {
+            let Ok(line) = line else { break };
+            info!("Sandbox: {}", line);
         }
+    });
+}
+
+/// The init scripts with the DAR of each, in the order given: a DAR per script, or one DAR for
+/// all of them
+fn init_scripts(init_dars: &[PathBuf], names: &[String]) -> Result<Vec<(PathBuf, String)>> {
+    match init_dars {
+        [] if names.is_empty() => Ok(Vec::new()),
+        [init_dar] => Ok(names.iter().map(|name| (init_dar.clone(), name.clone())).collect()),
+        _ if init_dars.len() == names.len() => Ok(init_dars.iter().cloned().zip(names.iter().cloned()).collect()),
+        _ => Err(anyhow::anyhow!(
+            "Expected one --init-dar for all --init-script-name or one per script, got {} DARs for {} scripts",
+            init_dars.len(),
+            names.len()
+        )),
     }
-    Err(anyhow::anyhow!(
-        "Sandbox did not print readiness message in time"
-    ))
 }
 
-fn run_init_script(init_dar: &PathBuf, init_script_name: &str) -> Result<()> {
+fn run_init_script(init_dar: &PathBuf, init_script_name: &str, ledger_port: u16) -> Result<()> {
     info!(
         "Running init script '{}' from DAR {:?}",
         init_script_name, init_dar
     );
 
     let output = Command::new("dpm")
-        .args(&[
+        .args([
             "script",
             "--dar",
             init_dar.to_str().unwrap(),
@@ -108,7 +220,7 @@ fn run_init_script(init_dar: &PathBuf, init_script_name: &str) -> Result<()> {
             "--ledger-host",
             "localhost",
             "--ledger-port",
-            "6865",
+            &ledger_port.to_string(),
         ])
         .output()
         .map_err(|e| anyhow::anyhow!("Failed to run dpm script: {}", e))?;
@@ -143,21 +255,86 @@ async fn main() -> Result<()> {
         .init();
 
     let cli = Cli::parse();
+    match cli.command {
+        None => start(cli.start).await,
+        Some(Commands::Start { args, detach: false, .. }) => start(args).await,
+        Some(Commands::Start { args, detach: true, log_file }) => {
+            daemon::detach(&log_file, args.pidfile.as_deref().unwrap_or(daemon::DEFAULT_PIDFILE.as_ref()))
+        }
+        Some(Commands::Stop { pidfile }) => daemon::stop(&pidfile),
+    }
+}
+
+/// Starts and initializes the sandbox, then runs until Ctrl+C or SIGTERM
+async fn start(cli: StartArgs) -> Result<()> {
+    let init_scripts = init_scripts(&cli.init_dar, &cli.init_script_name)?;
+    let bootstrap = cli.bootstrap.as_deref().map(bootstrap::read_bootstrap).transpose()?;
 
     info!("Starting sandbox-init");
-    info!("  DAR: {:?}", cli.dar);
-    info!("  Init DAR: {:?}", cli.init_dar);
-    info!("  Init script: {}", cli.init_script_name);
+    info!("  DARs: {:?}", cli.dar);
+    for (init_dar, init_script_name) in &init_scripts {
+        info!("  Init script: {} from {:?}", init_script_name, init_dar);
+    }
+    if let Some(path) = &cli.bootstrap {
+        info!("  Bootstrap: {:?}", path);
+    }
+    info!("  Ledger API port: {}", cli.port);
+
+    let ports = SandboxPorts { ledger_api: cli.port, admin_api: cli.admin_port, json_api: cli.json_api_port };
+    let _guard = start_sandbox(&cli.dar, ports, Duration::from_secs(cli.ready_timeout_secs)).await?;
 
-    let _guard = start_sandbox(&cli.dar)?;
+    let url = format!("http://localhost:{}", cli.port);
+    if let Some(bootstrap) = &bootstrap {
+        let result = bootstrap::run_bootstrap(bootstrap, &url).await?;
+        info!("Bootstrap allocated {} parties and created users {:?}", result.parties.len(), result.users);
+    }
+
+    for (init_dar, init_script_name) in &init_scripts {
+        run_init_script(init_dar, init_script_name, cli.port)?;
+    }
 
-    run_init_script(&cli.init_dar, &cli.init_script_name)?;
+    if let Some(path) = &cli.output {
+        let dars: Vec<PathBuf> = cli.dar.iter().chain(bootstrap.iter().flat_map(|bootstrap| &bootstrap.dars)).cloned().collect();
+        let environment = environment::read_environment(&url, &dars).await?;
+        environment::write_environment(&environment, path)?;
+        info!("Wrote the environment to {:?}", path);
+    }
 
+    let _pidfile = cli.pidfile.map(daemon::Pidfile::write).transpose()?;
     info!("Sandbox initialized successfully. Press Ctrl+C to stop.");
 
-    // Keep running until interrupted
-    tokio::signal::ctrl_c().await?;
+    // Keep running until interrupted, or stopped by `sandbox-init stop`
+    let mut terminate = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())?;
+    tokio::select! {
+        result = tokio::signal::ctrl_c() => result?,
+        _ = terminate.recv() => {}
+    }
     info!("Shutting down...");
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_init_scripts_pair_dars_with_scripts() {
+        let dars = |names: &[&str]| names.iter().map(PathBuf::from).collect::<Vec<_>>();
+        let scripts = |names: &[&str]| names.iter().map(|name| name.to_string()).collect::<Vec<_>>();
+        assert!(init_scripts(&[], &[]).unwrap().is_empty());
+        assert_eq!(
+            init_scripts(&dars(&["test.dar"]), &scripts(&["Setup:parties", "Setup:assets"])).unwrap(),
+            vec![
+                (PathBuf::from("test.dar"), "Setup:parties".to_string()),
+                (PathBuf::from("test.dar"), "Setup:assets".to_string())
+            ]
+        );
+        assert_eq!(
+            init_scripts(&dars(&["a.dar", "b.dar"]), &scripts(&["A:init", "B:init"])).unwrap()[1],
+            (PathBuf::from("b.dar"), "B:init".to_string())
+        );
+        assert!(init_scripts(&dars(&["a.dar", "b.dar"]), &scripts(&["A:init"])).is_err());
+        assert!(init_scripts(&[], &scripts(&["A:init"])).is_err());
+    }
+}
@@ -59,7 +59,7 @@ mod tests {
     use super::*;
     use client::jwt::fake_jwt_for_user;
     use client::party_management::get_parties::get_parties;
-    use client::testutils::start_sandbox;
+    use client::testutils::{allocate_ports, start_sandbox_with_ports};
     use tokio;
     use tracing::info;
     use tracing_subscriber::EnvFilter;
@@ -72,7 +72,8 @@ mod tests {
             .pretty()
             .try_init()
             .ok();
-        let sandbox_port = 6865;
+        let ports = allocate_ports()?;
+        let sandbox_port = ports.ledger_api;
         let url = format!("http://localhost:{}", sandbox_port);
         let crate_root = std::env::var("CARGO_MANIFEST_DIR").unwrap();
         let package_root = std::path::PathBuf::from(&crate_root)
@@ -85,50 +86,52 @@ mod tests {
         info!("Starting DAML sandbox at {}", package_root.display());
         let dar_path = package_root.join(".daml").join("dist").join("daml-asset-0.0.1.dar");
 
-        let _guard = start_sandbox(package_root, dar_path, sandbox_port).await?;
+        let guard = start_sandbox_with_ports(package_root, dar_path, ports).await?;
 
-        // Setup test values
-        let package_id = "#daml-asset".to_string();
-        let alice_user = "alice_user";
-        let alice_token = fake_jwt_for_user(alice_user);
-        let alice_parties =
-            get_parties(url.clone(), Some(&alice_token), Some("Alice".to_string())).await?;
-        let alice_party = alice_parties.get(0).cloned().unwrap();
+        guard.run(async {
+            // Setup test values
+            let package_id = "#daml-asset".to_string();
+            let alice_user = "alice_user";
+            let alice_token = fake_jwt_for_user(alice_user);
+            let alice_parties =
+                get_parties(url.clone(), Some(&alice_token), Some("Alice".to_string())).await?;
+            let alice_party = alice_parties.get(0).cloned().unwrap();
 
-        // Connect to ledger
-        let channel = tonic::transport::Channel::from_shared(url)
-            .unwrap()
-            .connect()
-            .await
-            .unwrap();
-        let mut command_service_client = CommandServiceClient::new(channel);
+            // Connect to ledger
+            let channel = tonic::transport::Channel::from_shared(url)
+                .unwrap()
+                .connect()
+                .await
+                .unwrap();
+            let mut command_service_client = CommandServiceClient::new(channel);
 
-        // Create asset using the generic create_contract function
-        let asset = Asset::new(
-            alice_party.clone(), // issuer
-            alice_party.clone(), // owner
-            "Test asset".to_string(),
-        );
-        let template_id = TemplateId::new(&package_id, "Main", "Asset");
-        let create_result = create_contract(
-            &mut command_service_client,
-            Some(alice_token.as_str()),
-            Some(alice_user),
-            vec![alice_party.clone()],
-            template_id,
-            asset,
-            None, // no disclosed contracts
-        )
-        .await;
+            // Create asset using the generic create_contract function
+            let asset = Asset::new(
+                alice_party.clone(), // issuer
+                alice_party.clone(), // owner
+                "Test asset".to_string(),
+            );
+            let template_id = TemplateId::new(&package_id, "Main", "Asset");
+            let create_result = create_contract(
+                &mut command_service_client,
+                Some(alice_token.as_str()),
+                Some(alice_user),
+                vec![alice_party.clone()],
+                template_id,
+                asset,
+                None, // no disclosed contracts
+            )
+            .await;
 
-        assert!(
-            create_result.is_ok(),
-            "Contract creation failed: {:?}",
-            create_result
-        );
-        let created_contract_id = create_result.unwrap();
-        info!("Created contract with id: {}", created_contract_id);
+            assert!(
+                create_result.is_ok(),
+                "Contract creation failed: {:?}",
+                create_result
+            );
+            let created_contract_id = create_result.unwrap();
+            info!("Created contract with id: {}", created_contract_id);
 
-        Ok(())
+            Ok(())
+        }).await
     }
 }
\ No newline at end of file
@@ -1,10 +1,13 @@
 use anyhow::Result;
 use client::submit_commands::CommandResult;
 use client::submit_commands::submit_commands;
+use crate::submit_command::{SubmitOptions, submit_command_with_options};
+use daml_type_rep::choice::DamlChoice;
+use daml_type_rep::contract_key::DamlContractKey;
 use daml_type_rep::lapi_access::LapiAccess;
-use daml_type_rep::template_id::TemplateId;
+use daml_type_rep::template_id::{DamlTemplate, TemplateId};
 use ledger_api::v2::{
-    Command, Commands, DisclosedContract, ExerciseCommand,
+    Command, Commands, DisclosedContract, ExerciseByKeyCommand, ExerciseCommand,
     command_service_client::CommandServiceClient,
 };
 
@@ -54,6 +57,97 @@ pub async fn exercise_choice<T: LapiAccess>(
     Ok(contract_ids)
 }
 
+/// Exercises a choice described by its `DamlChoice` impl and decodes the choice result.
+/// The template id and choice name come from the choice type.
+pub async fn exercise<C: DamlChoice>(
+    command_service_client: &mut CommandServiceClient<tonic::transport::Channel>,
+    access_token: Option<&str>,
+    options: &SubmitOptions,
+    contract_id: String,
+    choice_argument: C,
+    disclosed_contracts: Option<Vec<DisclosedContract>>,
+) -> Result<C::Result> {
+    let exercise_command = ExerciseCommand {
+        template_id: Some(C::Template::template_id().to_template_id()),
+        contract_id,
+        choice: C::NAME.to_string(),
+        choice_argument: Some(choice_argument.to_lapi_value()),
+    };
+
+    submit_typed_exercise::<C>(
+        command_service_client,
+        access_token,
+        options,
+        ledger_api::v2::command::Command::Exercise(exercise_command),
+        disclosed_contracts,
+    )
+    .await
+}
+
+/// Exercises a choice on the contract with the given key and decodes the choice result.
+/// The key must belong to the template of the choice, which is checked at compile time.
+pub async fn exercise_by_key<C, K>(
+    command_service_client: &mut CommandServiceClient<tonic::transport::Channel>,
+    access_token: Option<&str>,
+    options: &SubmitOptions,
+    contract_key: K,
+    choice_argument: C,
+    disclosed_contracts: Option<Vec<DisclosedContract>>,
+) -> Result<C::Result>
+where
+    C: DamlChoice,
+    K: DamlContractKey<Template = C::Template>,
+{
+    let exercise_by_key_command = ExerciseByKeyCommand {
+        template_id: Some(C::Template::template_id().to_template_id()),
+        contract_key: Some(contract_key.to_key_value()),
+        choice: C::NAME.to_string(),
+        choice_argument: Some(choice_argument.to_lapi_value()),
+    };
+
+    submit_typed_exercise::<C>(
+        command_service_client,
+        access_token,
+        options,
+        ledger_api::v2::command::Command::ExerciseByKey(exercise_by_key_command),
+        disclosed_contracts,
+    )
+    .await
+}
+
+async fn submit_typed_exercise<C: DamlChoice>(
+    command_service_client: &mut CommandServiceClient<tonic::transport::Channel>,
+    access_token: Option<&str>,
+    options: &SubmitOptions,
+    command: ledger_api::v2::command::Command,
+    disclosed_contracts: Option<Vec<DisclosedContract>>,
+) -> Result<C::Result> {
+    let result = submit_command_with_options(
+        command_service_client,
+        access_token,
+        options,
+        Command {
+            command: Some(command),
+        },
+        disclosed_contracts,
+    )
+    .await?;
+    // The root exercise comes first, nested exercises follow in execution order
+    let exercise_result = result
+        .iter()
+        .find_map(|r| {
+            if let CommandResult::ExerciseResult(value) = r {
+                Some(value)
+            } else {
+                None
+            }
+        })
+        .ok_or_else(|| anyhow::anyhow!("No exercise result returned for choice {}", C::NAME))?;
+
+    C::Result::from_lapi_value(exercise_result)
+        .ok_or_else(|| anyhow::anyhow!("Failed to decode the result of choice {}: {:?}", C::NAME, exercise_result))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -62,7 +156,7 @@ mod tests {
     use crate::test_types::give::Give;
     use client::jwt::fake_jwt_for_user;
     use client::party_management::get_parties::get_parties;
-    use client::testutils::start_sandbox;
+    use client::testutils::{allocate_ports, start_sandbox_with_ports};
     use ledger_api::v2::command_service_client::CommandServiceClient;
     use tracing::info;
     use tracing_subscriber::EnvFilter;
@@ -74,7 +168,8 @@ mod tests {
             .pretty()
             .try_init()
             .ok();
-        let sandbox_port = 6865;
+        let ports = allocate_ports()?;
+        let sandbox_port = ports.ledger_api;
         let url = format!("http://localhost:{}", sandbox_port);
         let crate_root = std::env::var("CARGO_MANIFEST_DIR").unwrap();
         let package_root = std::path::PathBuf::from(&crate_root)
@@ -87,71 +182,142 @@ mod tests {
         info!("Starting DAML sandbox at {}", package_root.display());
         let dar_path = package_root.join(".daml").join("dist").join("daml-asset-0.0.1.dar");
 
-        let _guard = start_sandbox(package_root, dar_path, sandbox_port).await?;
-
-        // Setup test values
-        let package_id = "#daml-asset".to_string();
-        let alice_user = "alice_user";
-        let alice_token = fake_jwt_for_user(alice_user);
-        let alice_parties =
-            get_parties(url.clone(), Some(&alice_token), Some("Alice".to_string())).await?;
-        let alice_party = alice_parties.get(0).cloned().unwrap();
-        let bob_parties =
-            get_parties(url.clone(), Some(&alice_token), Some("Bob".to_string())).await?;
-        let bob_party = bob_parties.get(0).cloned().unwrap();
-
-        // Connect to ledger
-        let channel = tonic::transport::Channel::from_shared(url)
-            .unwrap()
-            .connect()
-            .await
-            .unwrap();
-        let mut command_service_client = CommandServiceClient::new(channel);
-
-        // Create asset
-        let asset = Asset::new(
-            alice_party.clone(),
-            alice_party.clone(),
-            "Test asset".to_string(),
-        );
-        let template_id = TemplateId::new(&package_id, "Main", "Asset");
-        let create_result = create_contract(
-            &mut command_service_client,
-            Some(alice_token.as_str()),
-            Some(alice_user),
-            vec![alice_party.clone()],
-            template_id.clone(),
-            asset,
-            None, // no disclosed contracts
-        )
-        .await;
-
-        assert!(
-            create_result.is_ok(),
-            "Asset creation failed: {:?}",
-            create_result
-        );
-        let created_contract_id = create_result.unwrap();
-        info!("Created contract with id: {}", created_contract_id);
-
-        // Exercise Give choice using the generic exercise_choice function
-        let give_result = exercise_choice(
-            &mut command_service_client,
-            Some(alice_token.as_str()),
-            Some(alice_user),
-            vec![alice_party.clone()],
-            vec![], // read_as
-            template_id,
-            created_contract_id,
-            "Give",
-            Give::new(bob_party.clone()),
-            None, // no disclosed contracts
-        )
-        .await;
-
-        assert!(give_result.is_ok(), "Give exercise failed: {:?}", give_result);
-
-        Ok(())
+        let guard = start_sandbox_with_ports(package_root, dar_path, ports).await?;
+
+        guard.run(async {
+            // Setup test values
+            let package_id = "#daml-asset".to_string();
+            let alice_user = "alice_user";
+            let alice_token = fake_jwt_for_user(alice_user);
+            let alice_parties =
+                get_parties(url.clone(), Some(&alice_token), Some("Alice".to_string())).await?;
+            let alice_party = alice_parties.get(0).cloned().unwrap();
+            let bob_parties =
+                get_parties(url.clone(), Some(&alice_token), Some("Bob".to_string())).await?;
+            let bob_party = bob_parties.get(0).cloned().unwrap();
+
+            // Connect to ledger
+            let channel = tonic::transport::Channel::from_shared(url)
+                .unwrap()
+                .connect()
+                .await
+                .unwrap();
+            let mut command_service_client = CommandServiceClient::new(channel);
+
+            // Create asset
+            let asset = Asset::new(
+                alice_party.clone(),
+                alice_party.clone(),
+                "Test asset".to_string(),
+            );
+            let template_id = TemplateId::new(&package_id, "Main", "Asset");
+            let create_result = create_contract(
+                &mut command_service_client,
+                Some(alice_token.as_str()),
+                Some(alice_user),
+                vec![alice_party.clone()],
+                template_id.clone(),
+                asset,
+                None, // no disclosed contracts
+            )
+            .await;
+
+            assert!(
+                create_result.is_ok(),
+                "Asset creation failed: {:?}",
+                create_result
+            );
+            let created_contract_id = create_result.unwrap();
+            info!("Created contract with id: {}", created_contract_id);
+
+            // Exercise Give choice using the generic exercise_choice function
+            let give_result = exercise_choice(
+                &mut command_service_client,
+                Some(alice_token.as_str()),
+                Some(alice_user),
+                vec![alice_party.clone()],
+                vec![], // read_as
+                template_id,
+                created_contract_id,
+                "Give",
+                Give::new(bob_party.clone()),
+                None, // no disclosed contracts
+            )
+            .await;
+
+            assert!(give_result.is_ok(), "Give exercise failed: {:?}", give_result);
+
+            Ok(())
+        }).await
+    }
+
+    #[tokio::test]
+    async fn test_exercise_typed_choice_give() -> Result<()> {
+        tracing_subscriber::fmt()
+            .with_env_filter(EnvFilter::new("debug"))
+            .pretty()
+            .try_init()
+            .ok();
+        let ports = allocate_ports()?;
+        let sandbox_port = ports.ledger_api;
+        let url = format!("http://localhost:{}", sandbox_port);
+        let crate_root = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+        let package_root = std::path::PathBuf::from(&crate_root)
+            .join("..")
+            .join("_daml")
+            .join("daml-asset")
+            .canonicalize()
+            .expect("Failed to canonicalize package_root");
+        let dar_path = package_root.join(".daml").join("dist").join("daml-asset-0.0.1.dar");
+
+        let guard = start_sandbox_with_ports(package_root, dar_path, ports).await?;
+
+        guard.run(async {
+            let alice_user = "alice_user";
+            let alice_token = fake_jwt_for_user(alice_user);
+            let alice_party = get_parties(url.clone(), Some(&alice_token), Some("Alice".to_string()))
+                .await?
+                .first()
+                .cloned()
+                .unwrap();
+            let bob_party = get_parties(url.clone(), Some(&alice_token), Some("Bob".to_string()))
+                .await?
+                .first()
+                .cloned()
+                .unwrap();
+
+            let channel = tonic::transport::Channel::from_shared(url)
+                .unwrap()
+                .connect()
+                .await
+                .unwrap();
+            let mut command_service_client = CommandServiceClient::new(channel);
+
+            let created_contract_id = create_contract(
+                &mut command_service_client,
+                Some(alice_token.as_str()),
+                Some(alice_user),
+                vec![alice_party.clone()],
+                Asset::template_id(),
+                Asset::new(alice_party.clone(), alice_party.clone(), "Test asset".to_string()),
+                None,
+            )
+            .await?;
+
+            let new_contract_id = exercise(
+                &mut command_service_client,
+                Some(alice_token.as_str()),
+                &SubmitOptions::act_as(vec![alice_party.clone()]).with_user_id(alice_user),
+                created_contract_id.clone(),
+                Give::new(bob_party),
+                None,
+            )
+            .await?;
+
+            info!("Give returned contract id: {:?}", new_contract_id);
+            assert_ne!(new_contract_id.contract_id, created_contract_id);
+
+            Ok(())
+        }).await
     }
 }
-
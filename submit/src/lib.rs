@@ -1,3 +1,4 @@
 pub mod create_contract;
 pub mod exercise_choice;
+pub mod submit_command;
 pub mod test_types;
\ No newline at end of file
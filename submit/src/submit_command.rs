@@ -0,0 +1,118 @@
+use anyhow::Result;
+use std::time::Duration;
+use client::submit_commands::CommandResult;
+use client::submit_commands::submit_commands;
+use ledger_api::v2::{
+    Command, Commands, DisclosedContract,
+    command_service_client::CommandServiceClient,
+};
+
+/// The submitting user and parties and the command id of a submission, and how long to wait for
+/// its transaction, built from `SubmitOptions::act_as`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SubmitOptions {
+    pub user_id: Option<String>,
+    pub act_as: Vec<String>,
+    pub read_as: Vec<String>,
+    /// A random `command-<uuid>` if not set
+    pub command_id: Option<String>,
+    /// Waits for the transaction without timeout if not set
+    pub timeout: Option<Duration>,
+}
+
+impl SubmitOptions {
+    /// Submits as the parties, e.g. `SubmitOptions::act_as(vec![alice]).with_user_id("alice")`
+    pub fn act_as(parties: Vec<String>) -> Self {
+        Self { act_as: parties, ..Default::default() }
+    }
+
+    pub fn with_user_id(mut self, user_id: &str) -> Self {
+        self.user_id = Some(user_id.to_string());
+        self
+    }
+
+    pub fn with_read_as(mut self, parties: Vec<String>) -> Self {
+        self.read_as = parties;
+        self
+    }
+
+    pub fn with_command_id(mut self, command_id: &str) -> Self {
+        self.command_id = Some(command_id.to_string());
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+}
+
+/// Submits a single command, e.g. one built by the `exercise`/`into_command` helpers of
+/// `#[derive(Choice)]` types, and waits for the transaction.
+pub async fn submit_command(
+    command_service_client: &mut CommandServiceClient<tonic::transport::Channel>,
+    access_token: Option<&str>,
+    user_id: Option<&str>,
+    act_as: Vec<String>,
+    read_as: Vec<String>,
+    command: Command,
+    disclosed_contracts: Option<Vec<DisclosedContract>>,
+) -> Result<Vec<CommandResult>> {
+    let options = SubmitOptions {
+        user_id: user_id.map(str::to_string),
+        act_as,
+        read_as,
+        ..Default::default()
+    };
+    submit_command_with_options(command_service_client, access_token, &options, command, disclosed_contracts).await
+}
+
+/// Submits a single command with the options and waits for the transaction, failing if it does
+/// not complete within the timeout of the options.
+pub async fn submit_command_with_options(
+    command_service_client: &mut CommandServiceClient<tonic::transport::Channel>,
+    access_token: Option<&str>,
+    options: &SubmitOptions,
+    command: Command,
+    disclosed_contracts: Option<Vec<DisclosedContract>>,
+) -> Result<Vec<CommandResult>> {
+    let command_id = options
+        .command_id
+        .clone()
+        .unwrap_or_else(|| format!("command-{}", uuid::Uuid::new_v4()));
+    let commands = Commands {
+        act_as: options.act_as.clone(),
+        read_as: options.read_as.clone(),
+        user_id: options.user_id.clone().unwrap_or_default(),
+        commands: vec![command],
+        command_id: command_id.clone(),
+        ..Default::default()
+    };
+
+    let submission = submit_commands(command_service_client, access_token, commands, disclosed_contracts);
+    match options.timeout {
+        Some(timeout) => tokio::time::timeout(timeout, submission)
+            .await
+            .map_err(|_| anyhow::anyhow!("No transaction for command {} within {:?}", command_id, timeout))?,
+        None => submission.await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_submit_command_times_out() {
+        // Accepts connections in its backlog but never answers
+        let listener = std::net::TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let channel = tonic::transport::Channel::from_shared(url).unwrap().connect_lazy();
+        let mut command_service_client = CommandServiceClient::new(channel);
+        let options = SubmitOptions::act_as(vec!["Alice".to_string()])
+            .with_command_id("cmd-1")
+            .with_timeout(Duration::from_millis(100));
+        let result = submit_command_with_options(&mut command_service_client, None, &options, Command::default(), None).await;
+        assert_eq!(result.unwrap_err().to_string(), "No transaction for command cmd-1 within 100ms");
+    }
+}
@@ -1,11 +1,12 @@
 use daml_type_rep::lapi_access::ToCreateArguments;
-use derive_lapi_access::ToCreateArguments;
+use derive_lapi_access::{Template, ToCreateArguments};
 use daml_type_rep::built_in_types::{DamlParty, DamlText};
 use ledger_api::v2::Record;
 use daml_type_rep::lapi_access::LapiAccess;
 
 
-#[derive(Debug, serde::Serialize, ToCreateArguments)]
+#[derive(Debug, serde::Serialize, ToCreateArguments, Template)]
+#[template(package = "#daml-asset", module = "Main")]
 pub struct Asset {
     issuer: DamlParty,
     owner: DamlParty,
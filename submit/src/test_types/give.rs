@@ -1,9 +1,11 @@
-use daml_type_rep::built_in_types::DamlParty;
-use derive_lapi_access::LapiAccess;
+use daml_type_rep::built_in_types::{DamlContractId, DamlParty};
+use derive_lapi_access::{Choice, LapiAccess};
+use crate::test_types::asset::Asset;
 use daml_type_rep::lapi_access::LapiAccess;
 use ledger_api::v2::Record;
 
-#[derive(Debug, PartialEq, serde::Serialize, LapiAccess)]
+#[derive(Debug, PartialEq, serde::Serialize, LapiAccess, Choice)]
+#[choice(on = Asset, result = "DamlContractId")]
 pub struct Give {
     new_owner: DamlParty,
 }
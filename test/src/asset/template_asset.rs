@@ -188,7 +188,7 @@ mod tests {
     use client::jwt::fake_jwt_for_user;
     use client::party_management::get_parties::get_parties;
     use client::run_script::run_script;
-    use client::testutils::start_sandbox;
+    use client::testutils::{allocate_ports, start_sandbox_with_ports};
     use tokio;
     use tracing::info;
     use tracing_subscriber::EnvFilter;
@@ -200,7 +200,8 @@ mod tests {
             .pretty()
             .try_init()
             .ok();
-        let sandbox_port = 6865;
+        let ports = allocate_ports()?;
+        let sandbox_port = ports.ledger_api;
         let url = format!("http://localhost:{}", sandbox_port);
         let crate_root = std::env::var("CARGO_MANIFEST_DIR").unwrap();
         let package_root = std::path::PathBuf::from(&crate_root)
@@ -213,51 +214,53 @@ mod tests {
         info!("Starting DAML sandbox at {}", package_root.display());
         let dar_path = package_root.join(".daml").join("dist").join("daml-asset-0.0.1.dar");
 
-        let _guard = start_sandbox(package_root, dar_path, sandbox_port).await?;
-
-        // Setup test values
-        let package_id = "#daml-asset".to_string();
-        let alice_user = "alice_user";
-        let alice_token = fake_jwt_for_user(alice_user);
-        let alice_parties =
-            get_parties(url.clone(), Some(&alice_token), Some("Alice".to_string())).await?;
-        let alice_party = alice_parties.get(0).cloned().unwrap();
-
-        // Connect to ledger
-        let channel = tonic::transport::Channel::from_shared(url)
-            .unwrap()
-            .connect()
-            .await
-            .unwrap();
-        let mut command_service_client = CommandServiceClient::new(channel);
-
-        // Create asset using the generic create_contract function
-        let asset = Asset::new(
-            alice_party.clone(), // issuer
-            alice_party.clone(), // owner
-            "Test asset".to_string(),
-        );
-        let template_id = TemplateId::new(&package_id, "Main", "Asset");
-        let create_result = create_contract(
-            &mut command_service_client,
-            Some(alice_token.as_str()),
-            Some(alice_user),
-            vec![alice_party.clone()],
-            template_id,
-            asset,
-            None, // no disclosed contracts
-        )
-        .await;
-
-        assert!(
-            create_result.is_ok(),
-            "Contract creation failed: {:?}",
-            create_result
-        );
-        let created_contract_id = create_result.unwrap();
-        info!("Created contract with id: {}", created_contract_id);
-
-        Ok(())
+        let guard = start_sandbox_with_ports(package_root, dar_path, ports).await?;
+
+        guard.run(async {
+            // Setup test values
+            let package_id = "#daml-asset".to_string();
+            let alice_user = "alice_user";
+            let alice_token = fake_jwt_for_user(alice_user);
+            let alice_parties =
+                get_parties(url.clone(), Some(&alice_token), Some("Alice".to_string())).await?;
+            let alice_party = alice_parties.get(0).cloned().unwrap();
+
+            // Connect to ledger
+            let channel = tonic::transport::Channel::from_shared(url)
+                .unwrap()
+                .connect()
+                .await
+                .unwrap();
+            let mut command_service_client = CommandServiceClient::new(channel);
+
+            // Create asset using the generic create_contract function
+            let asset = Asset::new(
+                alice_party.clone(), // issuer
+                alice_party.clone(), // owner
+                "Test asset".to_string(),
+            );
+            let template_id = TemplateId::new(&package_id, "Main", "Asset");
+            let create_result = create_contract(
+                &mut command_service_client,
+                Some(alice_token.as_str()),
+                Some(alice_user),
+                vec![alice_party.clone()],
+                template_id,
+                asset,
+                None, // no disclosed contracts
+            )
+            .await;
+
+            assert!(
+                create_result.is_ok(),
+                "Contract creation failed: {:?}",
+                create_result
+            );
+            let created_contract_id = create_result.unwrap();
+            info!("Created contract with id: {}", created_contract_id);
+
+            Ok(())
+        }).await
     }
 
     #[tokio::test]
@@ -266,7 +269,8 @@ mod tests {
             .with_env_filter(EnvFilter::new("debug")) // or "debug", "trace", etc.
             .pretty()
             .init();
-        let sandbox_port = 6865;
+        let ports = allocate_ports()?;
+        let sandbox_port = ports.ledger_api;
         let url = format!("http://localhost:{}", sandbox_port);
         let crate_root = std::env::var("CARGO_MANIFEST_DIR").unwrap();
         let package_root = std::path::PathBuf::from(&crate_root)
@@ -279,94 +283,96 @@ mod tests {
         info!("Starting DAML sandbox at {}", package_root.display());
         let dar_path = package_root.join(".daml").join("dist").join("daml-asset-0.0.1.dar");
 
-        let _guard = start_sandbox(package_root, dar_path.clone(), sandbox_port).await?;
-
-        // Run the setup script from the DAR
-        let result = run_script(
-            "localhost",
-            sandbox_port,
-            &dar_path,
-            "Main:setup",
-        )?;
-        info!("Script result: {}", result);
-
-        // Setup test values
-        let package_id = "#daml-asset".to_string();
-        let alice_user = "alice_user";
-        let alice_token = fake_jwt_for_user(alice_user);
-        let bob_user = "bob_user";
-        let bob_token = fake_jwt_for_user(bob_user);
-        let alice_parties =
-            get_parties(url.clone(), Some(&alice_token), Some("Alice".to_string())).await?;
-        let alice_party = alice_parties.get(0).cloned().unwrap();
-        let bob_parties =
-            get_parties(url.clone(), Some(&alice_token), Some("Bob".to_string())).await?;
-        let bob_party = bob_parties.get(0).cloned().unwrap();
-
-        // Connect to ledger
-        let channel = tonic::transport::Channel::from_shared(url)
-            .unwrap()
-            .connect()
-            .await
-            .unwrap();
-        let mut command_service_client = CommandServiceClient::new(channel);
-
-        // Create asset
-        let asset = Asset::new(
-            alice_party.clone(), // issuer
-            alice_party.clone(), // owner
-            "Test asset".to_string(),
-        );
-        let create_result = create_asset(
-            &mut command_service_client,
-            Some(alice_token.as_str()),
-            Some(alice_user),
-            package_id.clone(),
-            asset,
-        )
-        .await;
-
-        assert!(
-            create_result.is_ok(),
-            "Asset creation failed: {:?}",
-            create_result
-        );
-        let (created_contract_id, _blob) = create_result.unwrap();
-
-        // Give asset
-        let give_result = exercise_give(
-            &mut command_service_client,
-            Some(alice_token.as_str()),
-            Some(alice_user),
-            package_id.clone(),
-            created_contract_id.clone(),
-            alice_party.clone(), // current_owner
-            bob_party.clone(),   // new_owner
-        )
-        .await;
-
-        assert!(give_result.is_ok(), "Give asset failed: {:?}", give_result);
-
-        let (given_contract_id, _blob) = give_result.unwrap();
-
-        // Exercise GetView
-        let get_view_result = exercise_get_view(
-            &mut command_service_client,
-            Some(bob_token.as_str()),
-            Some(bob_user),
-            package_id,
-            given_contract_id,
-            bob_party.clone(), // owner
-        )
-        .await;
-
-        assert!(
-            get_view_result.is_ok(),
-            "GetView exercise failed: {:?}",
-            get_view_result
-        );
-
-        Ok(())
+        let guard = start_sandbox_with_ports(package_root, dar_path.clone(), ports).await?;
+
+        guard.run(async {
+            // Run the setup script from the DAR
+            let result = run_script(
+                "localhost",
+                sandbox_port,
+                &dar_path,
+                "Main:setup",
+            )?;
+            info!("Script result: {}", result);
+
+            // Setup test values
+            let package_id = "#daml-asset".to_string();
+            let alice_user = "alice_user";
+            let alice_token = fake_jwt_for_user(alice_user);
+            let bob_user = "bob_user";
+            let bob_token = fake_jwt_for_user(bob_user);
+            let alice_parties =
+                get_parties(url.clone(), Some(&alice_token), Some("Alice".to_string())).await?;
+            let alice_party = alice_parties.get(0).cloned().unwrap();
+            let bob_parties =
+                get_parties(url.clone(), Some(&alice_token), Some("Bob".to_string())).await?;
+            let bob_party = bob_parties.get(0).cloned().unwrap();
+
+            // Connect to ledger
+            let channel = tonic::transport::Channel::from_shared(url)
+                .unwrap()
+                .connect()
+                .await
+                .unwrap();
+            let mut command_service_client = CommandServiceClient::new(channel);
+
+            // Create asset
+            let asset = Asset::new(
+                alice_party.clone(), // issuer
+                alice_party.clone(), // owner
+                "Test asset".to_string(),
+            );
+            let create_result = create_asset(
+                &mut command_service_client,
+                Some(alice_token.as_str()),
+                Some(alice_user),
+                package_id.clone(),
+                asset,
+            )
+            .await;
+
+            assert!(
+                create_result.is_ok(),
+                "Asset creation failed: {:?}",
+                create_result
+            );
+            let (created_contract_id, _blob) = create_result.unwrap();
+
+            // Give asset
+            let give_result = exercise_give(
+                &mut command_service_client,
+                Some(alice_token.as_str()),
+                Some(alice_user),
+                package_id.clone(),
+                created_contract_id.clone(),
+                alice_party.clone(), // current_owner
+                bob_party.clone(),   // new_owner
+            )
+            .await;
+
+            assert!(give_result.is_ok(), "Give asset failed: {:?}", give_result);
+
+            let (given_contract_id, _blob) = give_result.unwrap();
+
+            // Exercise GetView
+            let get_view_result = exercise_get_view(
+                &mut command_service_client,
+                Some(bob_token.as_str()),
+                Some(bob_user),
+                package_id,
+                given_contract_id,
+                bob_party.clone(), // owner
+            )
+            .await;
+
+            assert!(
+                get_view_result.is_ok(),
+                "GetView exercise failed: {:?}",
+                get_view_result
+            );
+
+            Ok(())
+        }).await
     }
 
     #[tokio::test]
@@ -377,7 +383,8 @@ mod tests {
             .try_init()
             .ok();
 
-        let sandbox_port = 6865;
+        let ports = allocate_ports()?;
+        let sandbox_port = ports.ledger_api;
         let crate_root = std::env::var("CARGO_MANIFEST_DIR").unwrap();
         let package_root = std::path::PathBuf::from(&crate_root)
             .join("..")
@@ -389,18 +396,20 @@ mod tests {
         info!("Starting DAML sandbox at {}", package_root.display());
         let dar_path = package_root.join(".daml").join("dist").join("daml-asset-0.0.1.dar");
 
-        let _guard = start_sandbox(package_root, dar_path.clone(), sandbox_port).await?;
+        let guard = start_sandbox_with_ports(package_root, dar_path.clone(), ports).await?;
 
-        // Run the setup script from the DAR
-        let result = run_script(
-            "localhost",
-            sandbox_port,
-            &dar_path,
-            "Main:setup",
-        )?;
+        guard.run(async {
+            // Run the setup script from the DAR
+            let result = run_script(
+                "localhost",
+                sandbox_port,
+                &dar_path,
+                "Main:setup",
+            )?;
 
-        info!("Script result: {}", result);
+            info!("Script result: {}", result);
 
-        Ok(())
+            Ok(())
+        }).await
     }
 }
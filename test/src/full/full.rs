@@ -52,7 +52,7 @@ pub struct Asset {
     color: Color,
     coordinates: Coordinates,
     mapping: DamlMap<DamlText, DamlInt>,
-    maybeDescription: DamlOptional<DamlText>,
+    maybe_description: DamlOptional<DamlText>,
 }
 
 impl Asset {
@@ -79,7 +79,7 @@ impl Asset {
                     .map(|(k, v)| (DamlText::new(k), DamlInt::new(v)))
                     .collect(),
             ),
-            maybeDescription: DamlOptional::new(maybe_description.map(|desc| DamlText::new(desc))),
+            maybe_description: DamlOptional::new(maybe_description.map(|desc| DamlText::new(desc))),
         }
     }
 }
@@ -259,7 +259,7 @@ mod tests {
     use super::*;
     use client::jwt::fake_jwt_for_user;
     use client::party_management::get_parties::get_parties;
-    use client::testutils::start_sandbox;
+    use client::testutils::{allocate_ports, start_sandbox_with_ports};
     use tokio;
     use tracing::info;
     use tracing_subscriber::EnvFilter;
@@ -269,7 +269,8 @@ mod tests {
         tracing_subscriber::fmt()
             .with_env_filter(EnvFilter::new("debug")) // or "debug", "trace", etc.
             .init();
-        let sandbox_port = 6865;
+        let ports = allocate_ports()?;
+        let sandbox_port = ports.ledger_api;
         let url = format!("http://localhost:{}", sandbox_port);
         let crate_root = std::env::var("CARGO_MANIFEST_DIR").unwrap();
         let package_root = std::path::PathBuf::from(&crate_root)
@@ -282,106 +283,108 @@ mod tests {
         info!("Starting DAML sandbox at {}", package_root.display());
         let dar_path = package_root.join(".daml").join("dist").join("full-0.0.1.dar");
 
-        let _guard = start_sandbox(package_root, dar_path, sandbox_port).await?;
-
-        // Setup test values
-        let package_id = "#full".to_string();
-        // let package_id = "86af146d525686523ff61402624ebb46bdbd8274f6c35391ebfd573667cf4f6c".to_string();
-
-        let alice_user = "alice_user";
-        let alice_token = fake_jwt_for_user(alice_user);
-        let alice_parties =
-            get_parties(url.clone(), Some(&alice_token), Some("Alice".to_string())).await?;
-        let alice_party = alice_parties.get(0).cloned().unwrap();
-
-        let bob_parties =
-            get_parties(url.clone(), Some(&alice_token), Some("Bob".to_string())).await?;
-        let bob_party = bob_parties.get(0).cloned().unwrap();
-        let bob_user = "bob_user";
-        let bob_token = fake_jwt_for_user(bob_user);
-
-        let price = Price::USD {
-            amount: DamlInt::new(100),
-            color: Color::Red,
-        };
-        let color = Color::Red;
-
-        // Connect to ledger
-        let channel = tonic::transport::Channel::from_shared(url)
-            .unwrap()
-            .connect()
-            .await
-            .unwrap();
-        let mut command_service_client = CommandServiceClient::new(channel);
-
-        // Create asset
-        let create_result = create_asset(
-            &mut command_service_client,
-            Some(alice_token.as_str()),
-            Some(alice_user),
-            package_id.clone(),
-            alice_party.clone(), // issuer
-            alice_party.clone(), // owner
-            "Test asset".to_string(),
-            price.clone(),
-            color.clone(),
-            Coordinates {
-                x: DamlInt::new(10),
-                y: DamlInt::new(20),
-                rgb: Rgb {
-                    red: DamlInt::new(255),
-                    green: DamlInt::new(0),
-                    blue: DamlInt::new(0),
+        let guard = start_sandbox_with_ports(package_root, dar_path, ports).await?;
+
+        guard.run(async {
+            // Setup test values
+            let package_id = "#full".to_string();
+            // let package_id = "86af146d525686523ff61402624ebb46bdbd8274f6c35391ebfd573667cf4f6c".to_string();
+
+            let alice_user = "alice_user";
+            let alice_token = fake_jwt_for_user(alice_user);
+            let alice_parties =
+                get_parties(url.clone(), Some(&alice_token), Some("Alice".to_string())).await?;
+            let alice_party = alice_parties.get(0).cloned().unwrap();
+
+            let bob_parties =
+                get_parties(url.clone(), Some(&alice_token), Some("Bob".to_string())).await?;
+            let bob_party = bob_parties.get(0).cloned().unwrap();
+            let bob_user = "bob_user";
+            let bob_token = fake_jwt_for_user(bob_user);
+
+            let price = Price::USD {
+                amount: DamlInt::new(100),
+                color: Color::Red,
+            };
+            let color = Color::Red;
+
+            // Connect to ledger
+            let channel = tonic::transport::Channel::from_shared(url)
+                .unwrap()
+                .connect()
+                .await
+                .unwrap();
+            let mut command_service_client = CommandServiceClient::new(channel);
+
+            // Create asset
+            let create_result = create_asset(
+                &mut command_service_client,
+                Some(alice_token.as_str()),
+                Some(alice_user),
+                package_id.clone(),
+                alice_party.clone(), // issuer
+                alice_party.clone(), // owner
+                "Test asset".to_string(),
+                price.clone(),
+                color.clone(),
+                Coordinates {
+                    x: DamlInt::new(10),
+                    y: DamlInt::new(20),
+                    rgb: Rgb {
+                        red: DamlInt::new(255),
+                        green: DamlInt::new(0),
+                        blue: DamlInt::new(0),
+                    },
                 },
-            },
-            BTreeMap::from([
-                ("a".to_string(), 1),
-                ("b".to_string(), 2),
-                ("c".to_string(), 3),
-            ]),
-            Some("A nice TV".to_string()),
-        )
-        .await;
-
-        assert!(
-            create_result.is_ok(),
-            "Asset creation failed: {:?}",
-            create_result
-        );
-        let created_contract_id = create_result.unwrap();
-
-        // Give asset
-        let give_result = exercise_give(
-            &mut command_service_client,
-            Some(alice_token.as_str()),
-            Some(alice_user),
-            package_id.clone(),
-            created_contract_id.clone(),
-            alice_party.clone(), // current_owner
-            bob_party.clone(),   // new_owner
-        )
-        .await;
-
-        assert!(give_result.is_ok(), "Give asset failed: {:?}", give_result);
-        let given_contract_id = give_result.unwrap();
-
-        // Exercise GetView
-        let get_view_result = exercise_get_view(
-            &mut command_service_client,
-            Some(bob_token.as_str()),
-            Some(bob_user),
-            package_id,
-            given_contract_id,
-            bob_party.clone(), // owner
-        )
-        .await;
-
-        assert!(
-            get_view_result.is_ok(),
-            "GetView exercise failed: {:?}",
-            get_view_result
-        );
+                BTreeMap::from([
+                    ("a".to_string(), 1),
+                    ("b".to_string(), 2),
+                    ("c".to_string(), 3),
+                ]),
+                Some("A nice TV".to_string()),
+            )
+            .await;
+
+            assert!(
+                create_result.is_ok(),
+                "Asset creation failed: {:?}",
+                create_result
+            );
+            let created_contract_id = create_result.unwrap();
+
+            // Give asset
+            let give_result = exercise_give(
+                &mut command_service_client,
+                Some(alice_token.as_str()),
+                Some(alice_user),
+                package_id.clone(),
+                created_contract_id.clone(),
+                alice_party.clone(), // current_owner
+                bob_party.clone(),   // new_owner
+            )
+            .await;
+
+            assert!(give_result.is_ok(), "Give asset failed: {:?}", give_result);
+            let given_contract_id = give_result.unwrap();
+
+            // Exercise GetView
+            let get_view_result = exercise_get_view(
+                &mut command_service_client,
+                Some(bob_token.as_str()),
+                Some(bob_user),
+                package_id,
+                given_contract_id,
+                bob_party.clone(), // owner
+            )
+            .await;
+
+            assert!(
+                get_view_result.is_ok(),
+                "GetView exercise failed: {:?}",
+                get_view_result
+            );
 
-        Ok(())
+            Ok(())
+        }).await
     }
 }
@@ -1,4 +1,3 @@
-use daml_type_rep::template_id::TemplateId;
 use client::submit_commands::CommandResult;
 use client::submit_commands::submit_commands;
 use anyhow::{Result, anyhow};
@@ -6,15 +5,16 @@ use daml_type_rep::built_in_types::{DamlParty, DamlText, DamlInt};
 use derive_lapi_access::ToCreateArguments;
 use ledger_api::v2::Record;
 use ledger_api::v2::{
-    Command, Commands, CreateCommand, ExerciseCommand,
+    Command, Commands, CreateCommand,
     command_service_client::CommandServiceClient,
 };
 use tracing::info;
 use daml_type_rep::lapi_access::LapiAccess;
-use derive_lapi_access::LapiAccess;
+use derive_lapi_access::{Choice, Interface, InterfaceView, LapiAccess, Template};
 use daml_type_rep::lapi_access::ToCreateArguments;
 
-#[derive(serde::Serialize, ToCreateArguments)]
+#[derive(serde::Serialize, ToCreateArguments, Template)]
+#[template(package = "#daml-interface-example-main", module = "Main", entity = "IOU")]
 pub struct IOU {
     issuer: DamlParty,
     owner: DamlParty,
@@ -33,19 +33,22 @@ impl IOU {
     }
 }
 
-#[derive(serde::Serialize, LapiAccess)]
+/// The `Asset` interface, implemented by `IOU`
+#[derive(Interface)]
+#[interface(package = "#daml-interface-example-interfaces", module = "Asset", entity = "Asset")]
+pub struct Asset;
+
+#[derive(serde::Serialize, LapiAccess, Choice)]
+#[choice(on = Asset, name = "GetView", result = "View", nonconsuming)]
 pub struct GetView {}
 
-// TODO implement from_api_value
-#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, LapiAccess, InterfaceView)]
+#[interface(package = "#daml-interface-example-interfaces", module = "Asset", entity = "Asset")]
 pub struct View {
     pub asset_owner: DamlParty,
     pub description: DamlText,
 }
 
-const MAIN_PACKAGE_ID: &str = "35fcd7ce96d0691c435d35f11b0eef259601e7ca3a97d0c142e41bdcac164847";
-const ASSET_PACKAGE_ID: &str = "219bfb9f7a2978b3984883d4db63485b4ef9796b3515007525142b89b01d5498";
-
 pub async fn create_iou(
     command_service_client: &mut CommandServiceClient<tonic::transport::Channel>,
     access_token: Option<&str>,
@@ -55,10 +58,8 @@ pub async fn create_iou(
     value: i64,
     name: String,
 ) -> Result<String> {
-    // TODO the package name #interface-example-main wasn't accepted?
-    let package_id = MAIN_PACKAGE_ID;
     let create_iou_command = CreateCommand {
-        template_id: Some(TemplateId::new(package_id, "Main", "IOU").to_template_id()),
+        template_id: Some(IOU::template_id().to_template_id()),
         create_arguments: Some(
             IOU::new(&issuer, &owner, value, name).to_create_arguments(),
         ),
@@ -92,18 +93,12 @@ pub async fn exercise_getview(
     owner: String,
     user_id: Option<&str>,
     contract_id: String,
-) -> Result<()> {
+) -> Result<View> {
     info!("Called exercise_getview with owner: {}, user_id: {:?}, contract_id: {}", owner, user_id, contract_id);
-    let package_id = ASSET_PACKAGE_ID;
-    info!("Using package_id: {} for Asset interface", package_id);
-    let exercise_getview_command = ExerciseCommand {
-        // To exercise a choice on an interface, specify the interface identifier in the template_id field.
-        // https://docs.digitalasset.com/build/3.3/reference/lapi-proto-docs.html#exercisecommand-message-version-com-daml-ledger-api-v2
-        template_id: Some(TemplateId::new(package_id, "Asset", "Asset").to_template_id()),
-        contract_id: contract_id.clone(),
-        choice: "GetView".into(),
-        choice_argument: Some(GetView {}.to_lapi_value()),
-    };
+    info!("Using {} for Asset interface", Asset::interface_id());
+    // To exercise a choice on an interface, the interface identifier is given in the template_id field.
+    // https://docs.digitalasset.com/build/3.3/reference/lapi-proto-docs.html#exercisecommand-message-version-com-daml-ledger-api-v2
+    let exercise_getview_command = GetView {}.exercise(contract_id.clone());
 
     info!("Constructed ExerciseCommand: contract_id={}, choice=GetView", contract_id);
 
@@ -126,14 +121,15 @@ pub async fn exercise_getview(
         info!("exercise_getview result is empty");
         return Err(anyhow!("exercise_getview result is empty"));
     }
-    if let Some(CommandResult::ExerciseResult(value)) = result.get(0) {
+    let view = if let Some(CommandResult::ExerciseResult(value)) = result.get(0) {
         info!("view_result at {}:{}: {:#?}", file!(), line!(), value);
+        View::from_lapi_value(value).ok_or_else(|| anyhow!("Failed to decode view: {:?}", value))?
     } else {
         info!("No view result found in exercise_getview result");
         return Err(anyhow!("No view result found in exercise_getview result"));
-    }
+    };
     info!("exercise_getview completed successfully");
-    Ok(())
+    Ok(view)
 }
 
 #[cfg(test)]
@@ -141,7 +137,7 @@ mod tests {
     use super::*;
     use client::jwt::fake_jwt_for_user;
     use client::party_management::get_parties::get_parties;
-    use client::testutils::start_sandbox;
+    use client::testutils::{allocate_ports, start_sandbox_with_ports};
     use client::upload_dar::upload_dars;
     use tokio;
 
@@ -153,7 +149,8 @@ mod tests {
             .init();
         tracing::info!("Logger initialized for test_create_iou_and_exercise_getview");
 
-        let sandbox_port = 6865;
+        let ports = allocate_ports()?;
+        let sandbox_port = ports.ledger_api;
         let url = format!("http://localhost:{}", sandbox_port);
         let crate_root = std::env::var("CARGO_MANIFEST_DIR").unwrap();
         info!("Crate root: {}", crate_root);
@@ -169,88 +166,93 @@ mod tests {
         info!("Starting sandbox at {:?}", package_root);
         let dar_path = package_root.join(".daml").join("dist").join("daml-interface-example-test-1.0.0.dar");
 
-        let _guard = start_sandbox(package_root, dar_path, sandbox_port).await?;
+        let guard = start_sandbox_with_ports(package_root, dar_path, ports).await?;
 
-        upload_dars(
-            &std::path::PathBuf::from(format!("http://localhost:{}", sandbox_port)),
-            &vec![
-                std::path::PathBuf::from(&crate_root)
-                    .join("..")
-                    .join("_daml")
-                    .join("daml-interface-example")
-                    .join("interfaces")
-                    .join(".daml")
-                    .join("dist")
-                    .join("daml-interface-example-interfaces-1.0.0.dar"),
-                std::path::PathBuf::from(&crate_root)
-                    .join("..")
-                    .join("_daml")
-                    .join("daml-interface-example")
-                    .join("main")
-                    .join(".daml")
-                    .join("dist")
-                    .join("daml-interface-example-main-1.0.0.dar"),
-            ],
-        ).await?;
+        guard.run(async {
+            upload_dars(
+                &std::path::PathBuf::from(format!("http://localhost:{}", sandbox_port)),
+                &vec![
+                    std::path::PathBuf::from(&crate_root)
+                        .join("..")
+                        .join("_daml")
+                        .join("daml-interface-example")
+                        .join("interfaces")
+                        .join(".daml")
+                        .join("dist")
+                        .join("daml-interface-example-interfaces-1.0.0.dar"),
+                    std::path::PathBuf::from(&crate_root)
+                        .join("..")
+                        .join("_daml")
+                        .join("daml-interface-example")
+                        .join("main")
+                        .join(".daml")
+                        .join("dist")
+                        .join("daml-interface-example-main-1.0.0.dar"),
+                ],
+            ).await?;
 
-        // Setup test values
-        let alice_user = "alice_user";
-        let alice_token = fake_jwt_for_user(alice_user);
-        let alice_parties =
-            get_parties(url.clone(), Some(&alice_token), Some("Alice".to_string())).await?;
-        let bob_parties =
-            get_parties(url.clone(), Some(&alice_token), Some("Bob".to_string())).await?;
-        let issuer = alice_parties
-            .get(0)
-            .cloned()
-            .unwrap_or_else(|| "Alice".to_string());
-        let owner = bob_parties
-            .get(0)
-            .cloned()
-            .unwrap_or_else(|| "Bob".to_string());
-        let value = 42;
-        let name = "Test IOU".to_string();
+            // Setup test values
+            let alice_user = "alice_user";
+            let alice_token = fake_jwt_for_user(alice_user);
+            let alice_parties =
+                get_parties(url.clone(), Some(&alice_token), Some("Alice".to_string())).await?;
+            let bob_parties =
+                get_parties(url.clone(), Some(&alice_token), Some("Bob".to_string())).await?;
+            let issuer = alice_parties
+                .get(0)
+                .cloned()
+                .unwrap_or_else(|| "Alice".to_string());
+            let owner = bob_parties
+                .get(0)
+                .cloned()
+                .unwrap_or_else(|| "Bob".to_string());
+            let value = 42;
+            let name = "Test IOU".to_string();
 
-        // Connect to ledger
-        let channel = tonic::transport::Channel::from_shared(url)
-            .unwrap()
-            .connect()
-            .await
-            .unwrap();
-        let mut command_service_client = CommandServiceClient::new(channel);
+            // Connect to ledger
+            let channel = tonic::transport::Channel::from_shared(url)
+                .unwrap()
+                .connect()
+                .await
+                .unwrap();
+            let mut command_service_client = CommandServiceClient::new(channel);
 
-        // Create IOU
-        let create_result = create_iou(
-            &mut command_service_client,
-            Some(alice_token.as_str()),
-            Some(alice_user),
-            issuer.clone(),
-            owner.clone(),
-            value,
-            name.clone(),
-        )
-        .await;
+            // Create IOU
+            let create_result = create_iou(
+                &mut command_service_client,
+                Some(alice_token.as_str()),
+                Some(alice_user),
+                issuer.clone(),
+                owner.clone(),
+                value,
+                name.clone(),
+            )
+            .await;
 
-        assert!(
-            create_result.is_ok(),
-            "IOU creation failed: {:?}",
-            create_result
-        );
-        let contract_id = create_result.unwrap();
+            assert!(
+                create_result.is_ok(),
+                "IOU creation failed: {:?}",
+                create_result
+            );
+            let contract_id = create_result.unwrap();
 
-        // Exercise GetView
-        let getview_result = exercise_getview(
-            &mut command_service_client,
-            Some(alice_token.as_str()),
-            owner.clone(),
-            Some(alice_user),
-            contract_id,
-        )
-        .await;
+            // Exercise GetView
+            let getview_result = exercise_getview(
+                &mut command_service_client,
+                Some(alice_token.as_str()),
+                owner.clone(),
+                Some(alice_user),
+                contract_id,
+            )
+            .await;
 
-        assert!(getview_result.is_ok(), "GetView exercise failed: {:?}", getview_result);
+            assert!(getview_result.is_ok(), "GetView exercise failed: {:?}", getview_result);
+            let view = getview_result.unwrap();
+            assert_eq!(view.asset_owner, DamlParty::new(&owner));
+            assert_eq!(view.description, DamlText::new(format!("IOU ({})", name)));
 
-        Ok(())
+            Ok(())
+        }).await
     }
 }
 
@@ -188,7 +188,7 @@ mod tests {
     use super::*;
     use client::jwt::fake_jwt_for_user;
     use client::party_management::get_parties::get_parties;
-    use client::testutils::start_sandbox;
+    use client::testutils::{allocate_ports, start_sandbox_with_ports};
     use tokio;
     use tracing::info;
     use tracing_subscriber::EnvFilter;
@@ -198,7 +198,8 @@ mod tests {
         tracing_subscriber::fmt()
             .with_env_filter(EnvFilter::new("debug")) // or "debug", "trace", etc.
             .init();
-        let sandbox_port = 6865;
+        let ports = allocate_ports()?;
+        let sandbox_port = ports.ledger_api;
         let url = format!("http://localhost:{}", sandbox_port);
         let crate_root = std::env::var("CARGO_MANIFEST_DIR").unwrap();
         let package_root = std::path::PathBuf::from(&crate_root)
@@ -211,81 +212,83 @@ mod tests {
         info!("Starting DAML sandbox at {}", package_root.display());
         let dar_path = package_root.join(".daml").join("dist").join("daml-optional-0.0.1.dar");
 
-        let _guard = start_sandbox(package_root, dar_path, sandbox_port).await?;
+        let guard = start_sandbox_with_ports(package_root, dar_path, ports).await?;
 
-        // Setup test values
-        let package_id = "#daml-optional".to_string();
-        let alice_user = "alice_user";
-        let alice_token = fake_jwt_for_user(alice_user);
-        let bob_user = "bob_user";
-        let bob_token = fake_jwt_for_user(bob_user);
-        let alice_parties =
-            get_parties(url.clone(), Some(&alice_token), Some("Alice".to_string())).await?;
-        let alice_party = alice_parties.get(0).cloned().unwrap();
-        let bob_parties =
-            get_parties(url.clone(), Some(&alice_token), Some("Bob".to_string())).await?;
-        let bob_party = bob_parties.get(0).cloned().unwrap();
+        guard.run(async {
+            // Setup test values
+            let package_id = "#daml-optional".to_string();
+            let alice_user = "alice_user";
+            let alice_token = fake_jwt_for_user(alice_user);
+            let bob_user = "bob_user";
+            let bob_token = fake_jwt_for_user(bob_user);
+            let alice_parties =
+                get_parties(url.clone(), Some(&alice_token), Some("Alice".to_string())).await?;
+            let alice_party = alice_parties.get(0).cloned().unwrap();
+            let bob_parties =
+                get_parties(url.clone(), Some(&alice_token), Some("Bob".to_string())).await?;
+            let bob_party = bob_parties.get(0).cloned().unwrap();
 
-        // Connect to ledger
-        let channel = tonic::transport::Channel::from_shared(url)
-            .unwrap()
-            .connect()
-            .await
-            .unwrap();
-        let mut command_service_client = CommandServiceClient::new(channel);
+            // Connect to ledger
+            let channel = tonic::transport::Channel::from_shared(url)
+                .unwrap()
+                .connect()
+                .await
+                .unwrap();
+            let mut command_service_client = CommandServiceClient::new(channel);
 
-        // Create asset
-        let create_result = create_asset(
-            &mut command_service_client,
-            Some(alice_token.as_str()),
-            Some(alice_user),
-            package_id.clone(),
-            alice_party.clone(), // issuer
-            alice_party.clone(), // owner
-            Some("Test asset".to_string()),
-        )
-        .await;
+            // Create asset
+            let create_result = create_asset(
+                &mut command_service_client,
+                Some(alice_token.as_str()),
+                Some(alice_user),
+                package_id.clone(),
+                alice_party.clone(), // issuer
+                alice_party.clone(), // owner
+                Some("Test asset".to_string()),
+            )
+            .await;
 
-        assert!(
-            create_result.is_ok(),
-            "Asset creation failed: {:?}",
-            create_result
-        );
-        let created_contract_id = create_result.unwrap();
+            assert!(
+                create_result.is_ok(),
+                "Asset creation failed: {:?}",
+                create_result
+            );
+            let created_contract_id = create_result.unwrap();
 
-        // Give asset
-        let give_result = exercise_give(
-            &mut command_service_client,
-            Some(alice_token.as_str()),
-            Some(alice_user),
-            package_id.clone(),
-            created_contract_id.clone(),
-            alice_party.clone(), // current_owner
-            bob_party.clone(),   // new_owner
-        )
-        .await;
+            // Give asset
+            let give_result = exercise_give(
+                &mut command_service_client,
+                Some(alice_token.as_str()),
+                Some(alice_user),
+                package_id.clone(),
+                created_contract_id.clone(),
+                alice_party.clone(), // current_owner
+                bob_party.clone(),   // new_owner
+            )
+            .await;
 
-        assert!(give_result.is_ok(), "Give asset failed: {:?}", give_result);
+            assert!(give_result.is_ok(), "Give asset failed: {:?}", give_result);
 
-        let given_contract_id = give_result.unwrap();
+            let given_contract_id = give_result.unwrap();
 
-        // Exercise GetView
-        let get_view_result = exercise_get_view(
-            &mut command_service_client,
-            Some(bob_token.as_str()),
-            Some(bob_user),
-            package_id,
-            given_contract_id,
-            bob_party.clone(), // owner
-        )
-        .await;
+            // Exercise GetView
+            let get_view_result = exercise_get_view(
+                &mut command_service_client,
+                Some(bob_token.as_str()),
+                Some(bob_user),
+                package_id,
+                given_contract_id,
+                bob_party.clone(), // owner
+            )
+            .await;
 
-        assert!(
-            get_view_result.is_ok(),
-            "GetView exercise failed: {:?}",
-            get_view_result
-        );
+            assert!(
+                get_view_result.is_ok(),
+                "GetView exercise failed: {:?}",
+                get_view_result
+            );
 
-        Ok(())
+            Ok(())
+        }).await
     }
 }
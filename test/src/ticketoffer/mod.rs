@@ -1,2 +1,3 @@
+pub mod setup;
 pub mod template_cash;
 pub mod template_ticketoffer;
@@ -0,0 +1,23 @@
+//! The parties and users of the Setup.daml of the ticket offer examples, set up over gRPC with
+//! `client::testutils::setup::Setup`. The tests create their own contracts instead of the ones
+//! of Setup.daml.
+use client::testutils::setup::Setup;
+use client::user_management::hinted_user::HintedUser;
+
+/// Alice, TicketWizard and ScroogeBank, a user acting as each, ScroogeBank's reading as Alice too,
+/// and `all_reader_user` reading as all of them
+pub fn ticketoffer_setup() -> Setup {
+    Setup::new()
+        .party("Alice")
+        .party("TicketWizard")
+        .party("ScroogeBank")
+        .user(HintedUser::new("aliceuser", "Alice"))
+        .user(HintedUser::new("ticketwizarduser", "TicketWizard"))
+        .user(HintedUser::new("scroogebankuser", "ScroogeBank").read_as("Alice"))
+        .user(
+            HintedUser::without_party("all_reader_user")
+                .read_as("Alice")
+                .read_as("TicketWizard")
+                .read_as("ScroogeBank"),
+        )
+}
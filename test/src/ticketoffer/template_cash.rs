@@ -121,14 +121,15 @@ mod tests {
     use super::*;
     use client::jwt::fake_jwt_for_user;
     use client::party_management::get_parties::get_parties;
-    use client::testutils::start_sandbox;
+    use client::testutils::{allocate_ports, start_sandbox_with_ports};
     use tokio;
     use std::path::PathBuf;
 
     #[tokio::test]
     async fn test_create_and_transfer_cash() -> Result<()> {
         tracing_subscriber::fmt::init();
-        let sandbox_port = 6865;
+        let ports = allocate_ports()?;
+        let sandbox_port = ports.ledger_api;
         let url = format!("http://localhost:{}", sandbox_port);
         let crate_root = std::env::var("CARGO_MANIFEST_DIR").unwrap();
         // Use PathBuf for package_root
@@ -140,82 +141,84 @@ mod tests {
             .unwrap();
 
         let dar_path = package_root.join(".daml").join("dist").join("daml-ticketoffer-0.0.1.dar");
-        let _guard = start_sandbox(package_root.clone(), dar_path, sandbox_port).await?;
-
-        // Setup test values
-        let package_id = "#daml-ticketoffer".to_string();
-
-        let alice_user = "aliceuser";
-        let alice_token = fake_jwt_for_user(alice_user);
-        let alice_parties =
-            get_parties(url.clone(), Some(&alice_token), Some("Alice".to_string())).await?;
-
-        let scrooge_bank_user = "scroogebankuser";
-        let scrooge_bank_token = fake_jwt_for_user(scrooge_bank_user);
-        let scrooge_bank_parties =
-            get_parties(url.clone(), Some(&scrooge_bank_token), Some("ScroogeBank".to_string())).await?;
-
-        let ticketwizard_user = "ticketwizarduser";
-        let ticketwizard_token = fake_jwt_for_user(ticketwizard_user);
-        let ticketwizard_parties =
-            get_parties(url.clone(), Some(&ticketwizard_token), Some("TicketWizard".to_string())).await?;
-
-        let issuer = scrooge_bank_parties
-            .get(0)
-            .cloned()
-            .unwrap();
-        let owner = alice_parties
-            .get(0)
-            .cloned()
-            .unwrap();
-        let amount = 10.5_f64;
-        let new_owner = ticketwizard_parties
-            .get(0)
-            .cloned()
-            .unwrap();
-
-        // Connect to ledger
-        let channel = tonic::transport::Channel::from_shared(url)
-            .unwrap()
-            .connect()
-            .await
-            .unwrap();
-        let mut command_service_client = CommandServiceClient::new(channel);
-
-        // Create cash
-        let create_result = create_cash(
-            &mut command_service_client,
-            Some(alice_token.as_str()),
-            Some(alice_user),
-            &package_id,
-            issuer.clone(),
-            owner.clone(),
-            amount,
-        )
-        .await;
-
-        assert!(
-            create_result.is_ok(),
-            "Cash creation failed: {:?}",
-            create_result
-        );
-
-        let contract_id = create_result.unwrap();
-
-        // Transfer cash
-        let transfer_result = exercise_transfer(
-            &mut command_service_client,
-            Some(alice_token.as_str()),
-            Some(alice_user),
-            &package_id,
-            contract_id,
-            new_owner.clone(),
-            owner.clone(),
-        )
-        .await;
-
-        assert!(transfer_result.is_ok(), "Transfer cash failed: {:?}", transfer_result);
-
-        Ok(())
+        let guard = start_sandbox_with_ports(package_root.clone(), dar_path, ports).await?;
+
+        guard.run(async {
+            // Setup test values
+            let package_id = "#daml-ticketoffer".to_string();
+
+            let alice_user = "aliceuser";
+            let alice_token = fake_jwt_for_user(alice_user);
+            let alice_parties =
+                get_parties(url.clone(), Some(&alice_token), Some("Alice".to_string())).await?;
+
+            let scrooge_bank_user = "scroogebankuser";
+            let scrooge_bank_token = fake_jwt_for_user(scrooge_bank_user);
+            let scrooge_bank_parties =
+                get_parties(url.clone(), Some(&scrooge_bank_token), Some("ScroogeBank".to_string())).await?;
+
+            let ticketwizard_user = "ticketwizarduser";
+            let ticketwizard_token = fake_jwt_for_user(ticketwizard_user);
+            let ticketwizard_parties =
+                get_parties(url.clone(), Some(&ticketwizard_token), Some("TicketWizard".to_string())).await?;
+
+            let issuer = scrooge_bank_parties
+                .get(0)
+                .cloned()
+                .unwrap();
+            let owner = alice_parties
+                .get(0)
+                .cloned()
+                .unwrap();
+            let amount = 10.5_f64;
+            let new_owner = ticketwizard_parties
+                .get(0)
+                .cloned()
+                .unwrap();
+
+            // Connect to ledger
+            let channel = tonic::transport::Channel::from_shared(url)
+                .unwrap()
+                .connect()
+                .await
+                .unwrap();
+            let mut command_service_client = CommandServiceClient::new(channel);
+
+            // Create cash
+            let create_result = create_cash(
+                &mut command_service_client,
+                Some(alice_token.as_str()),
+                Some(alice_user),
+                &package_id,
+                issuer.clone(),
+                owner.clone(),
+                amount,
+            )
+            .await;
+
+            assert!(
+                create_result.is_ok(),
+                "Cash creation failed: {:?}",
+                create_result
+            );
+
+            let contract_id = create_result.unwrap();
+
+            // Transfer cash
+            let transfer_result = exercise_transfer(
+                &mut command_service_client,
+                Some(alice_token.as_str()),
+                Some(alice_user),
+                &package_id,
+                contract_id,
+                new_owner.clone(),
+                owner.clone(),
+            )
+            .await;
+
+            assert!(transfer_result.is_ok(), "Transfer cash failed: {:?}", transfer_result);
+
+            Ok(())
+        }).await
     }
 }
\ No newline at end of file
@@ -113,18 +113,16 @@ pub async fn exercise_accept(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use client::jwt::fake_jwt_for_user;
-    use client::party_management::get_parties::get_parties;
-    use client::run_script::run_script;
-    use client::testutils::start_sandbox;
+    use crate::ticketoffer::setup::ticketoffer_setup;
+    use client::testutils::{allocate_ports, start_sandbox_with_ports};
     use tokio;
-    use tracing::info;
     use crate::ticketoffer::template_cash::create_cash;
 
     #[tokio::test]
     async fn test_create_and_accept_ticketoffer() -> Result<()> {
         let _ = tracing_subscriber::fmt().with_test_writer().try_init();
-        let sandbox_port = 6865;
+        let ports = allocate_ports()?;
+        let sandbox_port = ports.ledger_api;
         let url = format!("http://localhost:{}", sandbox_port);
         let crate_root = std::env::var("CARGO_MANIFEST_DIR").unwrap();
         // Use PathBuf for package_root
@@ -136,110 +134,85 @@ mod tests {
             .unwrap();
 
         let dar_path = package_root.join(".daml").join("dist").join("daml-ticketoffer-0.0.1.dar");
-        let _guard = start_sandbox(package_root.clone(), dar_path.clone(), sandbox_port).await?;
-
-        // Run the setup script from the DAR
-        let script_result = run_script(
-            "localhost",
-            sandbox_port,
-            &dar_path,
-            "Main:setup",
-        )?;
-        info!("Script result: {}", script_result);
-
-        // Setup test values
-        let package_id = "#daml-ticketoffer".to_string();
-
-        let alice_user = "aliceuser";
-        let alice_token = fake_jwt_for_user(alice_user);
-        let alice_parties =
-            get_parties(url.clone(), Some(&alice_token), Some("Alice".to_string())).await?;
-
-        let scrooge_bank_user = "scroogebankuser";
-        let scrooge_bank_token = fake_jwt_for_user(scrooge_bank_user);
-        let scrooge_bank_parties =
-            get_parties(url.clone(), Some(&scrooge_bank_token), Some("ScroogeBank".to_string())).await?;
-
-        let ticketwizard_user = "ticketwizarduser";
-        let ticketwizard_token = fake_jwt_for_user(ticketwizard_user);
-        let ticketwizard_parties =
-            get_parties(url.clone(), Some(&ticketwizard_token), Some("TicketWizard".to_string())).await?;
-
-        let issuer = scrooge_bank_parties
-            .get(0)
-            .cloned()
-            .unwrap();
-        let owner = alice_parties
-            .get(0)
-            .cloned()
-            .unwrap();
-        let amount = 10.5_f64;
-        let organizer = ticketwizard_parties
-            .get(0)
-            .cloned()
-            .unwrap();
-        let buyer = alice_parties
-            .get(0)
-            .cloned()
-            .unwrap();
-
-        // Connect to ledger
-        let channel = tonic::transport::Channel::from_shared(url)
-            .unwrap()
-            .connect()
-            .await
-            .unwrap();
-        let mut command_service_client = CommandServiceClient::new(channel);
-
-        // Create ticket offer
-        let create_ticketoffer_result = create_ticketoffer(
-            &mut command_service_client,
-            Some(ticketwizard_token.as_str()),
-            Some(alice_user),
-            &package_id,
-            organizer.clone(),
-            buyer.clone(),
-            amount,
-        )
-        .await;
-
-        assert!(
-            create_ticketoffer_result.is_ok(),
-            "Ticketoffer creation failed: {:?}",
-            create_ticketoffer_result
-        );
-
-        let ticketoffer_contract_id = create_ticketoffer_result.unwrap();
-
-        let create_cash_result = create_cash(
-            &mut command_service_client,
-            Some(alice_token.as_str()),
-            Some(alice_user),
-            &package_id,
-            issuer.clone(),
-            owner.clone(),
-            amount,
-        )
-        .await;
-
-        assert!(create_cash_result.is_ok(), "Cash creation failed: {:?}", create_cash_result);
-
-        let cash_contract_id = create_cash_result.unwrap();
-
-        // Accept ticket offer
-        let accept_result = exercise_accept(
-            &mut command_service_client,
-            Some(alice_token.as_str()),
-            Some(alice_user),
-            &package_id,
-            ticketoffer_contract_id,
-            cash_contract_id,
-            buyer.clone(),
-        )
-        .await;
-
-        assert!(accept_result.is_ok(), "Accept ticket offer failed: {:?}", accept_result);
-
-        Ok(())
+        let guard = start_sandbox_with_ports(package_root.clone(), dar_path, ports).await?;
+
+        guard.run(async {
+            // Allocate the parties and create the users of Setup.daml
+            let setup = ticketoffer_setup().run(&url).await?;
+
+            // Setup test values
+            let package_id = "#daml-ticketoffer".to_string();
+
+            let alice_user = "aliceuser";
+            let alice_token = setup.token(alice_user)?;
+
+            let ticketwizard_user = "ticketwizarduser";
+            let ticketwizard_token = setup.token(ticketwizard_user)?;
+
+            let issuer = setup.party("ScroogeBank")?;
+            let owner = setup.party("Alice")?;
+            let amount = 10.5_f64;
+            let organizer = setup.party("TicketWizard")?;
+            let buyer = setup.party("Alice")?;
+
+            // Connect to ledger
+            let channel = tonic::transport::Channel::from_shared(url)
+                .unwrap()
+                .connect()
+                .await
+                .unwrap();
+            let mut command_service_client = CommandServiceClient::new(channel);
+
+            // Create ticket offer
+            let create_ticketoffer_result = create_ticketoffer(
+                &mut command_service_client,
+                Some(ticketwizard_token.as_str()),
+                Some(alice_user),
+                &package_id,
+                organizer.clone(),
+                buyer.clone(),
+                amount,
+            )
+            .await;
+
+            assert!(
+                create_ticketoffer_result.is_ok(),
+                "Ticketoffer creation failed: {:?}",
+                create_ticketoffer_result
+            );
+
+            let ticketoffer_contract_id = create_ticketoffer_result.unwrap();
+
+            let create_cash_result = create_cash(
+                &mut command_service_client,
+                Some(alice_token.as_str()),
+                Some(alice_user),
+                &package_id,
+                issuer.clone(),
+                owner.clone(),
+                amount,
+            )
+            .await;
+
+            assert!(create_cash_result.is_ok(), "Cash creation failed: {:?}", create_cash_result);
+
+            let cash_contract_id = create_cash_result.unwrap();
+
+            // Accept ticket offer
+            let accept_result = exercise_accept(
+                &mut command_service_client,
+                Some(alice_token.as_str()),
+                Some(alice_user),
+                &package_id,
+                ticketoffer_contract_id,
+                cash_contract_id,
+                buyer.clone(),
+            )
+            .await;
+
+            assert!(accept_result.is_ok(), "Accept ticket offer failed: {:?}", accept_result);
+
+            Ok(())
+        }).await
     }
 }
\ No newline at end of file